@@ -0,0 +1,64 @@
+//! `atree bench`: a quick timing of the searches, to compare rules or versions of the library
+//! without setting up a benchmark.
+
+use std::error::Error;
+use std::io::BufRead;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::rules::{self, Tree};
+
+pub fn run(tree: &Tree, events: &Path, iterations: usize) -> Result<(), Box<dyn Error>> {
+    let mut parsed = Vec::new();
+    for (index, line) in rules::lines(events)?.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match tree.event_from_json(&line) {
+            Ok(event) => parsed.push(event),
+            Err(error) => eprintln!("skipping the event of line {}: {error}", index + 1),
+        }
+    }
+    if parsed.is_empty() {
+        return Err("no event to search".into());
+    }
+
+    let mut timings = Vec::with_capacity(parsed.len() * iterations);
+    let mut matches = 0;
+    let start = Instant::now();
+    for _ in 0..iterations {
+        for event in &parsed {
+            let search = Instant::now();
+            let report = tree.search(event).map_err(|error| error.to_string())?;
+            timings.push(search.elapsed());
+            matches += report.matches().len();
+        }
+    }
+    let total = start.elapsed();
+    if timings.is_empty() {
+        return Err("no search was run".into());
+    }
+
+    timings.sort_unstable();
+    let percentile = |p: usize| timings[(timings.len() - 1) * p / 100];
+    let searches = timings.len();
+    println!(
+        "{} subscriptions, {} events, {searches} searches in {total:?}",
+        tree.stats().subscriptions(),
+        parsed.len()
+    );
+    println!(
+        "mean {:?}, p50 {:?}, p99 {:?}, max {:?}",
+        timings.iter().sum::<Duration>() / searches as u32,
+        percentile(50),
+        percentile(99),
+        timings[searches - 1]
+    );
+    println!(
+        "{:.0} searches/s, {:.2} matches per search",
+        searches as f64 / total.as_secs_f64(),
+        matches as f64 / searches as f64
+    );
+    Ok(())
+}
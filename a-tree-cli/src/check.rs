@@ -0,0 +1,41 @@
+//! `atree check`: report the invalid expressions like a compiler, with their file, line and
+//! column.
+
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use crate::rules::Tree;
+
+pub fn run(tree: &Tree, files: &[PathBuf]) -> Result<ExitCode, Box<dyn Error>> {
+    let (mut checked, mut invalid) = (0, 0);
+    for path in files {
+        let contents =
+            fs::read_to_string(path).map_err(|error| format!("{}: {error}", path.display()))?;
+        for (index, line) in contents.lines().enumerate() {
+            let expression = line.trim();
+            if expression.is_empty() || expression.starts_with('#') {
+                continue;
+            }
+
+            checked += 1;
+            if let Err(error) = tree.validate_expression(expression) {
+                invalid += 1;
+                // The columns count the characters of the line, from 1.
+                let indent = line.len() - line.trim_start().len();
+                let column = error
+                    .span(expression)
+                    .map_or(1, |span| line[..indent + span.start].chars().count() + 1);
+                println!("{}:{}:{column}: {error}", path.display(), index + 1);
+            }
+        }
+    }
+
+    eprintln!("{checked} expressions checked, {invalid} invalid");
+    Ok(if invalid == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
@@ -0,0 +1,162 @@
+//! `atree` command line tool, so that the analysts reproduce the matching decisions of the
+//! production locally: check the expressions, search events against the rules, export the tree,
+//! time the searches and debug the expressions interactively.
+
+mod bench;
+mod check;
+mod repl;
+mod rules;
+mod search;
+
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+#[derive(Parser)]
+#[command(name = "atree", version)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Validate files of expressions, one per line; the empty lines and the lines starting with
+    /// `#` are skipped.
+    Check {
+        /// Rules exported by `ATree::export_json()` whose schema is used
+        #[arg(long, required_unless_present = "schema", conflicts_with = "schema")]
+        rules: Option<PathBuf>,
+        /// JSON file mapping the attribute names to their kind: `boolean`, `integer`, `float`,
+        /// `string`, `integer_list` or `string_list`
+        #[arg(long)]
+        schema: Option<PathBuf>,
+        /// Files of expressions
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Search the events of a JSON lines file, printing `{"line": ..., "matches": [...]}` for
+    /// each event.
+    Search {
+        /// Rules exported by `ATree::export_json()`
+        #[arg(long)]
+        rules: PathBuf,
+        /// JSON lines file of events, or `-` for the standard input
+        #[arg(default_value = "-")]
+        events: PathBuf,
+    },
+    /// Print the rules in another format.
+    Export {
+        /// Rules exported by `ATree::export_json()`
+        #[arg(long)]
+        rules: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::Dot)]
+        format: Format,
+    },
+    /// Time the searches of the events of a JSON lines file.
+    Bench {
+        /// Rules exported by `ATree::export_json()`
+        #[arg(long)]
+        rules: PathBuf,
+        /// JSON lines file of events, or `-` for the standard input
+        #[arg(default_value = "-")]
+        events: PathBuf,
+        /// Number of times each event is searched
+        #[arg(long, default_value_t = 10)]
+        iterations: usize,
+    },
+    /// Type expressions against an event defined interactively, seeing their parse tree and the
+    /// result of each predicate.
+    Repl {
+        /// Rules exported by `ATree::export_json()`, whose schema is used and which `:search` and
+        /// `:explain` use
+        #[arg(long, required_unless_present = "schema", conflicts_with = "schema")]
+        rules: Option<PathBuf>,
+        /// JSON file mapping the attribute names to their kind
+        #[arg(long)]
+        schema: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// Graphviz graph of the nodes of the tree
+    Dot,
+    /// JSON document that `ATree::import_json()` loads
+    Json,
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse().command) {
+        Ok(code) => code,
+        // The output was piped to a command that stopped reading, e.g. `head`.
+        Err(error)
+            if error
+                .downcast_ref::<io::Error>()
+                .is_some_and(|error| error.kind() == io::ErrorKind::BrokenPipe) =>
+        {
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("atree: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<ExitCode, Box<dyn Error>> {
+    match command {
+        Command::Check {
+            rules,
+            schema,
+            files,
+        } => {
+            let tree = match (rules, schema) {
+                (Some(rules), _) => rules::load(&rules)?,
+                (None, Some(schema)) => rules::from_schema(&schema)?,
+                (None, None) => unreachable!("the arguments require rules or a schema"),
+            };
+            check::run(&tree, &files)
+        }
+        Command::Search { rules, events } => {
+            search::run(&rules::load(&rules)?, &events)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Export { rules, format } => {
+            let tree = rules::load(&rules)?;
+            let mut stdout = io::stdout().lock();
+            match format {
+                Format::Dot => tree.write_graphviz(&mut stdout)?,
+                Format::Json => {
+                    tree.write_json(&mut stdout)?;
+                    writeln!(stdout)?;
+                }
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Bench {
+            rules,
+            events,
+            iterations,
+        } => {
+            bench::run(&rules::load(&rules)?, &events, iterations)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Repl { rules, schema } => {
+            let (rules, definitions) = match (rules, schema) {
+                (Some(rules), _) => {
+                    let rules = rules::load(&rules)?;
+                    let definitions = rules.attributes();
+                    (Some(rules), definitions)
+                }
+                (None, Some(schema)) => (None, rules::from_schema(&schema)?.attributes()),
+                (None, None) => unreachable!("the arguments require rules or a schema"),
+            };
+            repl::run(rules, definitions)?;
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! `atree repl`: type expressions against an event defined interactively and see how they are
+//! parsed and evaluated, predicate by predicate.
+
+use std::error::Error;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use a_tree::{
+    ATree, AttributeDefinition, AttributeKind, Event, Explanation, Expression, Literal,
+    PredicateExpression, PredicateOperator,
+};
+use serde_json::{Map, Value};
+
+use crate::rules::{Id, Tree};
+
+const HELP: &str = "\
+Type an expression to see its parse tree and its result for the current event, or:
+  :set NAME VALUE   set an attribute of the event to a JSON value, e.g. :set deals [\"deal-1\"]
+  :unset NAME       make an attribute undefined
+  :clear            make every attribute undefined
+  :event            print the current event
+  :schema           list the attributes and their kind
+  :search           search the current event against the loaded rules
+  :explain ID       explain a loaded rule against the current event
+  :help             print this help
+  :quit             exit";
+
+struct Repl {
+    /// Rules loaded with `--rules`, for `:search` and `:explain`
+    rules: Option<Tree>,
+    definitions: Vec<AttributeDefinition>,
+    event: Map<String, Value>,
+}
+
+pub fn run(
+    rules: Option<Tree>,
+    definitions: Vec<AttributeDefinition>,
+) -> Result<(), Box<dyn Error>> {
+    let mut repl = Repl {
+        rules,
+        definitions,
+        event: Map::new(),
+    };
+    let interactive = io::stdin().is_terminal();
+    if interactive {
+        println!("{HELP}");
+    }
+
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        if interactive {
+            print!("atree> ");
+            io::stdout().flush()?;
+        }
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+        let line = line.trim();
+        if line == ":quit" || line == ":q" {
+            break;
+        }
+        if let Err(error) = repl.eval(line) {
+            println!("error: {error}");
+        }
+    }
+    Ok(())
+}
+
+impl Repl {
+    fn eval(&mut self, line: &str) -> Result<(), Box<dyn Error>> {
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim();
+        match command {
+            "" => {}
+            ":help" => println!("{HELP}"),
+            ":set" => self.set(argument)?,
+            ":unset" => {
+                self.event.remove(argument);
+            }
+            ":clear" => self.event.clear(),
+            ":event" => println!("{}", Value::Object(self.event.clone())),
+            ":schema" => {
+                for definition in &self.definitions {
+                    println!("{} {}", definition.name(), kind(definition.kind()));
+                }
+            }
+            ":search" => self.search()?,
+            ":explain" => self.explain(argument)?,
+            command if command.starts_with(':') => {
+                return Err(format!("unknown command {command}, see :help").into());
+            }
+            _ => self.evaluate(line)?,
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, argument: &str) -> Result<(), Box<dyn Error>> {
+        let (name, value) = argument.split_once(' ').ok_or("expected :set NAME VALUE")?;
+        let value: Value = serde_json::from_str(value.trim())?;
+        let mut event = self.event.clone();
+        event.insert(name.to_owned(), value);
+        // Reject the values that the tree would reject when searching.
+        let tree = ATree::<u64>::new(&self.definitions).map_err(|error| error.to_string())?;
+        build(&tree, &event)?;
+        self.event = event;
+        Ok(())
+    }
+
+    fn evaluate(&self, expression: &str) -> Result<(), Box<dyn Error>> {
+        let mut tree = ATree::<u64>::new(&self.definitions).map_err(|error| error.to_string())?;
+        let parsed = tree
+            .parse_expression(expression)
+            .map_err(|error| error.to_string())?;
+        print_tree(&parsed, 0);
+
+        // The event is built once the strings of the expression are known to the tree.
+        tree.insert(&0, expression)
+            .map_err(|error| error.to_string())?;
+        let event = build(&tree, &self.event)?;
+        let explanation = tree
+            .explain(&0, &event)
+            .ok_or("the expression was not inserted")?;
+        print_explanation(&explanation);
+        Ok(())
+    }
+
+    fn search(&self) -> Result<(), Box<dyn Error>> {
+        let rules = self.rules.as_ref().ok_or("no rules loaded, see --rules")?;
+        let event = build(rules, &self.event)?;
+        let report = rules.search(&event).map_err(|error| error.to_string())?;
+        let mut matches: Vec<_> = report.matches().to_vec();
+        matches.sort();
+        println!("{} matches", matches.len());
+        for id in matches {
+            println!("  {id}: {}", rules.expression(id).unwrap_or_default());
+        }
+        Ok(())
+    }
+
+    fn explain(&self, argument: &str) -> Result<(), Box<dyn Error>> {
+        let rules = self.rules.as_ref().ok_or("no rules loaded, see --rules")?;
+        // A bare word is a string ID.
+        let id = serde_json::from_str(argument).unwrap_or_else(|_| Id::Text(argument.to_owned()));
+        let expression = rules
+            .expression(&id)
+            .ok_or_else(|| format!("no rule {id}"))?;
+        println!("{expression}");
+        let event = build(rules, &self.event)?;
+        let explanation = rules
+            .explain(&id, &event)
+            .ok_or_else(|| format!("no rule {id}"))?;
+        print_explanation(&explanation);
+        Ok(())
+    }
+}
+
+fn build<T>(tree: &ATree<T>, event: &Map<String, Value>) -> Result<Event, Box<dyn Error>>
+where
+    T: Eq + Hash + Clone + Debug,
+{
+    let json = Value::Object(event.clone()).to_string();
+    Ok(tree.event_from_json(&json)?)
+}
+
+fn print_tree(expression: &Expression, depth: usize) {
+    let indent = "  ".repeat(depth);
+    match expression {
+        Expression::And(..) => println!("{indent}and"),
+        Expression::Or(..) => println!("{indent}or"),
+        Expression::Not(..) => println!("{indent}not"),
+        Expression::Predicate(predicate) => println!("{indent}{}", describe(predicate)),
+        Expression::Constant(value) => println!("{indent}{value}"),
+    }
+    for child in expression.children() {
+        print_tree(child, depth + 1);
+    }
+}
+
+fn print_explanation(explanation: &Explanation) {
+    println!("=> {}", outcome(explanation.result()));
+    for predicate in explanation.predicates() {
+        println!(
+            "  {:<9} {}",
+            outcome(predicate.result()),
+            describe(predicate.predicate())
+        );
+    }
+}
+
+fn outcome(result: Option<bool>) -> &'static str {
+    match result {
+        Some(true) => "true",
+        Some(false) => "false",
+        None => "undefined",
+    }
+}
+
+fn describe(predicate: &PredicateExpression) -> String {
+    let attribute = predicate.attribute();
+    match (predicate.operator(), predicate.value()) {
+        (PredicateOperator::Variable, _) => attribute.to_owned(),
+        (PredicateOperator::NegatedVariable, _) => format!("not {attribute}"),
+        (operator, Some(value)) => format!("{attribute} {operator} {}", literal(value)),
+        (operator, None) => format!("{attribute} {operator}"),
+    }
+}
+
+fn literal(value: &Literal) -> String {
+    match value {
+        Literal::Integer(value) => value.to_string(),
+        Literal::Float(value) => value.to_string(),
+        Literal::String(value) => format!("{value:?}"),
+        Literal::IntegerList(values) => format!("{values:?}"),
+        Literal::StringList(values) => format!("{values:?}"),
+        Literal::IntegerRange(low, high) => format!("{low} and {high}"),
+        Literal::FloatRange(low, high) => format!("{low} and {high}"),
+    }
+}
+
+fn kind(kind: &AttributeKind) -> &'static str {
+    match kind {
+        AttributeKind::Boolean => "boolean",
+        AttributeKind::Integer => "integer",
+        AttributeKind::Float => "float",
+        AttributeKind::String => "string",
+        AttributeKind::IntegerList => "integer_list",
+        AttributeKind::StringList => "string_list",
+    }
+}
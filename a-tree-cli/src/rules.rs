@@ -0,0 +1,73 @@
+//! Loading of the rules, the schemas and the events given to the commands.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use a_tree::{ATree, AttributeDefinition};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// ID of a subscription in a rules file, either a number or a string
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Id {
+    Number(u64),
+    Text(String),
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(id) => write!(formatter, "{id}"),
+            Self::Text(id) => write!(formatter, "{id}"),
+        }
+    }
+}
+
+pub type Tree = ATree<Id>;
+
+/// Load the rules exported by `ATree::export_json()`.
+pub fn load(path: &Path) -> Result<Tree, Box<dyn Error>> {
+    let json = fs::read_to_string(path).map_err(|error| in_file(path, error))?;
+    ATree::import_json(&json).map_err(|error| in_file(path, error))
+}
+
+/// Create an empty tree from a JSON object mapping the attribute names to their kind.
+pub fn from_schema(path: &Path) -> Result<Tree, Box<dyn Error>> {
+    let json = fs::read_to_string(path).map_err(|error| in_file(path, error))?;
+    let schema: Map<String, Value> =
+        serde_json::from_str(&json).map_err(|error| in_file(path, error))?;
+    let definitions = schema
+        .iter()
+        .map(|(name, kind)| definition(name, kind))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ATree::new(&definitions).map_err(|error| error.to_string())?)
+}
+
+fn definition(name: &str, kind: &Value) -> Result<AttributeDefinition, String> {
+    match kind.as_str() {
+        Some("boolean") => Ok(AttributeDefinition::boolean(name)),
+        Some("integer") => Ok(AttributeDefinition::integer(name)),
+        Some("float") => Ok(AttributeDefinition::float(name)),
+        Some("string") => Ok(AttributeDefinition::string(name)),
+        Some("integer_list") => Ok(AttributeDefinition::integer_list(name)),
+        Some("string_list") => Ok(AttributeDefinition::string_list(name)),
+        _ => Err(format!("unknown kind {kind} for attribute {name:?}")),
+    }
+}
+
+/// Open a file of JSON lines, or the standard input for `-`.
+pub fn lines(path: &Path) -> Result<Box<dyn BufRead>, Box<dyn Error>> {
+    if path == Path::new("-") {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    let file = fs::File::open(path).map_err(|error| in_file(path, error))?;
+    Ok(Box::new(BufReader::new(file)))
+}
+
+fn in_file(path: &Path, error: impl fmt::Display) -> Box<dyn Error> {
+    format!("{}: {error}", path.display()).into()
+}
@@ -0,0 +1,37 @@
+//! `atree search`: the matches of each event of a JSON lines file, one JSON object per event.
+
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use serde_json::json;
+
+use crate::rules::{self, Tree};
+
+pub fn run(tree: &Tree, events: &Path) -> Result<(), Box<dyn Error>> {
+    let mut stdout = io::stdout().lock();
+    for (index, line) in rules::lines(events)?.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let result = match search(tree, &line) {
+            Ok(matches) => json!({ "line": index + 1, "matches": matches }),
+            Err(error) => json!({ "line": index + 1, "error": error }),
+        };
+        writeln!(stdout, "{result}")?;
+    }
+    Ok(())
+}
+
+fn search(tree: &Tree, event: &str) -> Result<Vec<rules::Id>, String> {
+    let event = tree
+        .event_from_json(event)
+        .map_err(|error| error.to_string())?;
+    let report = tree.search(&event).map_err(|error| error.to_string())?;
+    let mut matches: Vec<_> = report.matches().iter().map(|&id| id.clone()).collect();
+    // The order of the matches is unspecified, so it is fixed for the outputs to be compared.
+    matches.sort();
+    Ok(matches)
+}
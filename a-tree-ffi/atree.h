@@ -6,6 +6,122 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+/**
+ * The call succeeded
+ */
+#define ATREE_OK 0
+
+/**
+ * A required pointer is null or an argument is out of range
+ */
+#define ATREE_ERROR_INVALID_ARGUMENT 1
+
+/**
+ * A string is not valid UTF-8
+ */
+#define ATREE_ERROR_INVALID_UTF8 2
+
+/**
+ * The expression cannot be parsed
+ */
+#define ATREE_ERROR_PARSE 3
+
+/**
+ * An attribute is unknown, defined twice or used with the wrong type
+ */
+#define ATREE_ERROR_EVENT 4
+
+/**
+ * A buffer returned to the caller cannot be allocated
+ */
+#define ATREE_ERROR_OUT_OF_MEMORY 5
+
+/**
+ * The subscription ID is already present and the tree rejects the duplicates
+ */
+#define ATREE_ERROR_DUPLICATE_ID 6
+
+/**
+ * A file cannot be read or written
+ */
+#define ATREE_ERROR_IO 7
+
+/**
+ * The library failed unexpectedly, for instance because a worker thread panicked
+ */
+#define ATREE_ERROR_INTERNAL 8
+
+/**
+ * Version of the ABI described by this header
+ *
+ * It is incremented whenever a function or a type changes in an incompatible way; compare it
+ * with `atree_abi_version()` after loading the library.
+ */
+#define ATREE_ABI_VERSION 1
+
+/**
+ * Kind of a node of a parsed expression
+ */
+typedef enum AtreeAstKind {
+  AstAnd = 0,
+  AstOr = 1,
+  AstNot = 2,
+  AstPredicate = 3,
+  /**
+   * An expression that was folded to `true` or `false`
+   */
+  AstConstant = 4,
+} AtreeAstKind;
+
+/**
+ * Operator of a predicate node
+ */
+typedef enum AtreeAstOperator {
+  /**
+   * The node is not a predicate
+   */
+  OperatorNone = 0,
+  OperatorVariable = 1,
+  OperatorNegatedVariable = 2,
+  OperatorIn = 3,
+  OperatorNotIn = 4,
+  OperatorLessThan = 5,
+  OperatorLessThanEqual = 6,
+  OperatorGreaterThan = 7,
+  OperatorGreaterThanEqual = 8,
+  OperatorEqual = 9,
+  OperatorNotEqual = 10,
+  OperatorOneOf = 11,
+  OperatorNoneOf = 12,
+  OperatorAllOf = 13,
+  OperatorNotAllOf = 14,
+  OperatorIsNull = 15,
+  OperatorIsNotNull = 16,
+  OperatorIsEmpty = 17,
+  OperatorIsNotEmpty = 18,
+  OperatorStartsWith = 19,
+  OperatorNotStartsWith = 20,
+  OperatorBetween = 21,
+  OperatorNotBetween = 22,
+  OperatorContains = 23,
+  OperatorNotContains = 24,
+  /**
+   * The value is a window in seconds before the time of the event
+   */
+  OperatorWithinLast = 25,
+  OperatorNotWithinLast = 26,
+  /**
+   * The minimum amount of values in common is read with `atree_ast_minimum_overlap()`
+   */
+  OperatorIntersectAtLeast = 27,
+  OperatorIntersectFewerThan = 28,
+  /**
+   * The edit distance is read with `atree_ast_edit_distance()`
+   */
+  OperatorFuzzyEqual = 29,
+  OperatorNotFuzzyEqual = 30,
+} AtreeAstOperator;
+
 /**
  * Attribute types supported by the A-Tree
  */
@@ -18,11 +134,307 @@ typedef enum AtreeAttributeType {
   IntegerList = 5,
 } AtreeAttributeType;
 
+/**
+ * Order in which the operands of the `and` operators are evaluated
+ */
+typedef enum AtreeConjunctionOrder {
+  /**
+   * Evaluate the operand with the lowest estimated cost first (the default)
+   */
+  ConjunctionOrderCost = 0,
+  /**
+   * Evaluate the operands in the order in which they are written
+   */
+  ConjunctionOrderLeftToRight = 1,
+} AtreeConjunctionOrder;
+
+/**
+ * What a delete does with the nodes of the subscription
+ */
+typedef enum AtreeDeletePolicy {
+  /**
+   * Remove the nodes right away (the default)
+   */
+  DeletePolicyImmediate = 0,
+  /**
+   * Hide the subscription and keep its nodes until `atree_compact()`
+   */
+  DeletePolicyTombstone = 1,
+} AtreeDeletePolicy;
+
+/**
+ * What an insert does when the subscription ID is already present
+ */
+typedef enum AtreeDuplicateIds {
+  /**
+   * Keep both expressions: the subscription matches when any of them matches (the default)
+   */
+  DuplicateIdsAllow = 0,
+  /**
+   * Delete the previous expression once the new one is parsed
+   */
+  DuplicateIdsReplace = 1,
+  /**
+   * Fail and keep the previous expression
+   */
+  DuplicateIdsReject = 2,
+} AtreeDuplicateIds;
+
+/**
+ * Format of `atree_export()`
+ */
+typedef enum AtreeExportFormat {
+  /**
+   * The Graphviz DOT format of `atree_to_graphviz()`
+   */
+  ExportGraphviz = 0,
+  /**
+   * The JSON document of `atree_export_json()`
+   */
+  ExportJson = 1,
+} AtreeExportFormat;
+
+/**
+ * Type of a tracked handle
+ */
+typedef enum AtreeHandleKind {
+  /**
+   * An `ATreeHandle`
+   */
+  HandleTree = 0,
+  /**
+   * An `ATreeU32Handle`
+   */
+  HandleU32Tree = 1,
+  /**
+   * An `ATreeStrHandle`
+   */
+  HandleStrTree = 2,
+  /**
+   * An event builder
+   */
+  HandleEventBuilder = 3,
+  /**
+   * An `AtreeEventHandle`
+   */
+  HandleEvent = 4,
+} AtreeHandleKind;
+
+/**
+ * Type of the literal value of a node
+ */
+typedef enum AtreeLiteralKind {
+  /**
+   * The node has no value
+   */
+  LiteralNone = 0,
+  /**
+   * The value of a constant node
+   */
+  LiteralBoolean = 1,
+  LiteralInteger = 2,
+  LiteralFloat = 3,
+  LiteralString = 4,
+  LiteralIntegerList = 5,
+  LiteralStringList = 6,
+  /**
+   * The inclusive bounds of a `between`, as a list of two integers
+   */
+  LiteralIntegerRange = 7,
+  /**
+   * The inclusive bounds of a `between`, as a list of two floats
+   */
+  LiteralFloatRange = 8,
+} AtreeLiteralKind;
+
+/**
+ * Severity of a log message
+ */
+typedef enum AtreeLogLevel {
+  /**
+   * An operation failed and its failure could not be reported to the caller
+   */
+  LogError = 0,
+  /**
+   * An operation succeeded but something is likely wrong (slow search, skipped attribute)
+   */
+  LogWarning = 1,
+  /**
+   * An operation failed and its failure was reported to the caller
+   */
+  LogInfo = 2,
+  /**
+   * Detailed information, such as a caller buffer that is too small
+   */
+  LogDebug = 3,
+} AtreeLogLevel;
+
+/**
+ * Kind of error reported in an `AtreeParseError`
+ */
+typedef enum AtreeParseErrorCode {
+  /**
+   * The expression is valid
+   */
+  NoError = 0,
+  /**
+   * A token cannot be recognized
+   */
+  InvalidToken = 1,
+  /**
+   * The expression ends too early
+   */
+  UnexpectedEnd = 2,
+  /**
+   * A token is not expected at this position
+   */
+  UnexpectedToken = 3,
+  /**
+   * A token is found after the end of the expression
+   */
+  ExtraToken = 4,
+  /**
+   * A literal is malformed (e.g. an out of range integer or an unterminated string)
+   */
+  Lexical = 5,
+  /**
+   * The expression refers to an attribute that is not defined
+   */
+  UnknownAttribute = 6,
+  /**
+   * An attribute is used with a value or an operator that does not match its type
+   */
+  TypeMismatch = 7,
+  /**
+   * The arguments passed to the function are invalid (null pointer or invalid UTF-8)
+   */
+  InvalidArgument = 8,
+  /**
+   * Any other error
+   */
+  OtherError = 9,
+  /**
+   * The subscription ID is already present and the tree rejects the duplicates
+   */
+  DuplicateId = 10,
+  /**
+   * A string is not one of the values of the dictionary of its attribute
+   */
+  UnknownValue = 11,
+} AtreeParseErrorCode;
+
+/**
+ * Rounding applied to the floats that have more decimals than the float scale
+ */
+typedef enum AtreeRounding {
+  /**
+   * Round to the nearest value and the midpoints to the nearest even digit (the default)
+   */
+  RoundHalfEven = 0,
+  /**
+   * Round to the nearest value and the midpoints away from zero
+   */
+  RoundHalfAwayFromZero = 1,
+  /**
+   * Truncate the digits
+   */
+  RoundTowardZero = 2,
+  /**
+   * Round away from zero
+   */
+  RoundAwayFromZero = 3,
+  /**
+   * Round toward negative infinity
+   */
+  RoundFloor = 4,
+  /**
+   * Round toward positive infinity
+   */
+  RoundCeiling = 5,
+} AtreeRounding;
+
+/**
+ * What a search does with the event attributes that are not defined
+ */
+typedef enum AtreeUnknownAttributes {
+  /**
+   * Fail (the default)
+   */
+  UnknownAttributesError = 0,
+  /**
+   * Skip the attribute silently
+   */
+  UnknownAttributesIgnore = 1,
+  /**
+   * Skip the attribute and log a warning
+   */
+  UnknownAttributesWarn = 2,
+} AtreeUnknownAttributes;
+
 /**
  * Opaque handle to an ATree instance
  */
 typedef struct ATreeHandle ATreeHandle;
 
+/**
+ * Opaque handle to an A-Tree whose subscription IDs are null-terminated strings
+ */
+typedef struct ATreeStrHandle ATreeStrHandle;
+
+/**
+ * Opaque handle to an A-Tree whose subscription IDs are 32-bit integers
+ */
+typedef struct ATreeU32Handle ATreeU32Handle;
+
+/**
+ * Opaque arena of buffers freed together by `atree_arena_reset()`
+ */
+typedef struct AtreeArena AtreeArena;
+
+/**
+ * Opaque node of a parsed expression; the root is freed with `atree_ast_free()` along with all
+ * its descendants
+ */
+typedef struct AtreeAst AtreeAst;
+
+/**
+ * Opaque pool of event builders for a tree
+ */
+typedef struct AtreeBuilderPool AtreeBuilderPool;
+
+/**
+ * Opaque handle publishing the current tree to the reader threads
+ */
+typedef struct AtreeConcurrentHandle AtreeConcurrentHandle;
+
+/**
+ * Opaque handle to the configuration of an A-Tree
+ */
+typedef struct AtreeConfigHandle AtreeConfigHandle;
+
+/**
+ * Opaque handle to the remaining matches of a search
+ */
+typedef struct AtreeCursor AtreeCursor;
+
+/**
+ * Opaque handle to an event built by `atree_event_from_json()`
+ */
+typedef struct AtreeEventHandle AtreeEventHandle;
+
+/**
+ * Opaque handle to a session of a concurrent handle
+ *
+ * The writes of the session are applied to a copy of the published tree, which the session
+ * searches instead of the published tree until the writes are published. A session is used by
+ * one thread at a time.
+ *
+ * The copy is a full clone of the tree, so it costs as much as the size of the tree: it is made
+ * by the first write, again by the first write or search after another tree was published, and
+ * again by a write made while a tree of the session is pinned.
+ */
+typedef struct AtreeSessionHandle AtreeSessionHandle;
+
 /**
  * Attribute definition for creating an A-Tree
  */
@@ -39,6 +451,70 @@ typedef struct AtreeResult {
   char *error_message;
 } AtreeResult;
 
+/**
+ * Detailed error returned when an expression cannot be parsed
+ *
+ * The position points to the start of the offending token. When the error cannot be located,
+ * `line` and `column` are 0.
+ */
+typedef struct AtreeParseError {
+  enum AtreeParseErrorCode code;
+  /**
+   * 1-based line of the error
+   */
+  uintptr_t line;
+  /**
+   * 1-based column of the error, in characters
+   */
+  uintptr_t column;
+  /**
+   * Byte offset of the error in the expression
+   */
+  uintptr_t offset;
+  /**
+   * Byte length of the offending token (0 at the end of the expression)
+   */
+  uintptr_t length;
+  /**
+   * Human readable message; must be freed with `atree_parse_error_free()`
+   */
+  char *message;
+} AtreeParseError;
+
+/**
+ * Size of an A-Tree
+ */
+typedef struct AtreeStats {
+  /**
+   * Number of subscriptions
+   */
+  uintptr_t subscriptions;
+  /**
+   * Number of nodes, shared or not
+   */
+  uintptr_t nodes;
+  /**
+   * Number of distinct predicates (leaf nodes)
+   */
+  uintptr_t predicates;
+  /**
+   * Number of root nodes
+   */
+  uintptr_t roots;
+  /**
+   * Height of the tallest expression
+   */
+  uintptr_t max_level;
+  /**
+   * Number of distinct strings used by the expressions and the interned values
+   */
+  uintptr_t strings;
+  /**
+   * Estimation of the bytes used by the tree
+   */
+  uintptr_t memory_usage;
+} AtreeStats;
+
 /**
  * Search result containing matching subscription IDs
  */
@@ -47,6 +523,128 @@ typedef struct AtreeSearchResult {
   uintptr_t count;
 } AtreeSearchResult;
 
+/**
+ * Allocate `size` bytes; returns null on failure
+ */
+typedef void *(*AtreeMallocFn)(uintptr_t size, void *ctx);
+
+/**
+ * Free memory returned by the allocation functions
+ */
+typedef void (*AtreeFreeFn)(void *ptr, void *ctx);
+
+/**
+ * Search result containing the matching subscription IDs as a serialized Roaring bitmap
+ *
+ * The bytes are in the portable format of the 64-bit Roaring bitmaps, which the C, C++, Java and
+ * Go Roaring libraries deserialize, e.g. with `roaring64_bitmap_portable_deserialize_safe()` in
+ * CRoaring. They are null on failure; a search without matches still yields the bytes of an empty
+ * bitmap.
+ */
+typedef struct AtreeBitmapResult {
+  uint8_t *bytes;
+  uintptr_t len;
+} AtreeBitmapResult;
+
+/**
+ * Live handle reported by `atree_debug_live_handles()`
+ */
+typedef struct AtreeDebugHandle {
+  /**
+   * Address of the handle
+   */
+  const void *address;
+  /**
+   * Type of the handle
+   */
+  enum AtreeHandleKind kind;
+  /**
+   * Order of creation of the handle, starting at 1
+   */
+  uint64_t generation;
+} AtreeDebugHandle;
+
+/**
+ * Receive a chunk of an export; `chunk` is not null-terminated and only valid during the call
+ */
+typedef void (*AtreeWriteCallback)(const char *chunk, uintptr_t len, void *ctx);
+
+/**
+ * Search results of an `ATreeU32Handle`
+ */
+typedef struct AtreeU32SearchResult {
+  /**
+   * Array of matching subscription IDs
+   */
+  uint32_t *ids;
+  /**
+   * Number of matches
+   */
+  uintptr_t count;
+} AtreeU32SearchResult;
+
+/**
+ * Search results of an `ATreeStrHandle`
+ */
+typedef struct AtreeStrSearchResult {
+  /**
+   * Array of matching subscription IDs, owned by the tree
+   */
+  const char **ids;
+  /**
+   * Number of matches
+   */
+  uintptr_t count;
+} AtreeStrSearchResult;
+
+/**
+ * Receive a log message; `message` is only valid during the call
+ */
+typedef void (*AtreeLogCallback)(enum AtreeLogLevel level, const char *message, void *ctx);
+
+/**
+ * A matching subscription along with its payload
+ */
+typedef struct AtreeMatch {
+  uint64_t id;
+  /**
+   * Payload of the subscription, or null if it has none; owned by the tree
+   */
+  const void *payload;
+  /**
+   * Size of the payload in bytes
+   */
+  uintptr_t payload_len;
+} AtreeMatch;
+
+/**
+ * Search result containing the matching subscriptions along with their payloads
+ */
+typedef struct AtreePayloadSearchResult {
+  struct AtreeMatch *matches;
+  uintptr_t count;
+} AtreePayloadSearchResult;
+
+/**
+ * Receive the status and the result of `atree_search_async()`; the result is empty unless the
+ * status is `ATREE_OK` and must be freed with `atree_search_result_free()`
+ */
+typedef void (*AtreeSearchCallback)(int32_t status, struct AtreeSearchResult result, void *ctx);
+
+/**
+ * Attribute of a tree returned by `atree_attribute_at()`
+ */
+typedef struct AtreeAttributeInfo {
+  /**
+   * Name of the attribute, owned by the tree
+   */
+  const char *name;
+  /**
+   * Type of the attribute
+   */
+  enum AtreeAttributeType attr_type;
+} AtreeAttributeInfo;
+
 /**
  * Create a new A-Tree with the given attribute definitions.
  *
@@ -94,136 +692,434 @@ struct AtreeResult atree_insert(struct ATreeHandle *handle,
                                 const char *expression);
 
 /**
- * Delete a subscription by ID.
+ * Insert a boolean expression and describe where the expression is invalid on failure.
  *
  * # Arguments
  * * `handle` - Valid ATree handle
- * * `subscription_id` - ID of the subscription to delete
+ * * `subscription_id` - Unique ID for this subscription
+ * * `expression` - Null-terminated boolean expression string
+ * * `error` - Optional out-parameter filled when the insertion fails; can be null
+ *
+ * # Returns
+ * Result indicating success or failure
  *
  * # Safety
  * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `expression` must be a valid null-terminated C string
+ * - `error` must be null or point to writable memory for an `AtreeParseError`
+ * - Caller must free result.error_message with `atree_free_error()` if !success
+ * - Caller must free `*error` with `atree_parse_error_free()` if !success and `error` is not null
  */
-void atree_delete(struct ATreeHandle *handle, uint64_t subscription_id);
+struct AtreeResult atree_insert_with_error(struct ATreeHandle *handle,
+                                           uint64_t subscription_id,
+                                           const char *expression,
+                                           struct AtreeParseError *error);
 
 /**
- * Export the tree structure as a Graphviz DOT format string.
+ * Check whether an expression could be inserted in the tree without modifying it.
+ *
+ * # Arguments
+ * * `handle` - Valid ATree handle
+ * * `expression` - Null-terminated boolean expression string
+ * * `error` - Optional out-parameter filled when the expression is invalid; can be null
  *
  * # Returns
- * Null-terminated string containing DOT format, or null on failure
+ * true if the expression is valid, false otherwise
  *
  * # Safety
  * - `handle` must be a valid pointer returned by `atree_new()`
- * - Caller must free the returned string with `atree_free_string()`
+ * - `expression` must be a valid null-terminated C string
+ * - `error` must be null or point to writable memory for an `AtreeParseError`
+ * - Caller must free `*error` with `atree_parse_error_free()` if false is returned and `error`
+ *   is not null
  */
-char *atree_to_graphviz(const struct ATreeHandle *handle);
+bool atree_validate_expression(const struct ATreeHandle *handle,
+                               const char *expression,
+                               struct AtreeParseError *error);
 
 /**
- * Free a string returned by the library.
+ * Free the message of a parse error.
  *
  * # Safety
- * - `string` must be a valid pointer from a function that returns *mut c_char
+ * - `error` must have been filled by `atree_insert_with_error()` or
+ *   `atree_validate_expression()`
+ * - `error.message` must not be used after this call
  */
-void atree_free_string(char *string);
+void atree_parse_error_free(struct AtreeParseError error);
 
 /**
- * Start building an event for searching.
+ * Delete a subscription by ID along with its payload.
+ *
+ * # Arguments
+ * * `handle` - Valid ATree handle
+ * * `subscription_id` - ID of the subscription to delete
  *
  * # Safety
  * - `handle` must be a valid pointer returned by `atree_new()`
- * - Returned pointer must be freed with `atree_event_builder_free()`
  */
-void *atree_event_builder_new(const struct ATreeHandle *handle);
+void atree_delete(struct ATreeHandle *handle, uint64_t subscription_id);
 
 /**
- * Add a boolean attribute to the event.
+ * Remove the nodes of the subscriptions deleted under `DeletePolicyTombstone`, with up to
+ * `atree_parallelism()` threads.
+ *
+ * # Returns
+ * Number of subscriptions whose nodes were removed, or 0 if `handle` is null or a worker thread
+ * panicked
  *
  * # Safety
- * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
- * - `name` must be a valid null-terminated C string
+ * - `handle` must be a valid pointer returned by `atree_new()`
  */
-struct AtreeResult atree_event_builder_with_boolean(void *builder, const char *name, bool value);
+uintptr_t atree_compact(struct ATreeHandle *handle);
 
 /**
- * Add an integer attribute to the event.
+ * Get the number of deleted subscriptions whose nodes are waiting for `atree_compact()`.
+ *
+ * # Returns
+ * Number of tombstoned subscriptions, or 0 if `handle` is null
  *
  * # Safety
- * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
- * - `name` must be a valid null-terminated C string
+ * - `handle` must be a valid pointer returned by `atree_new()`
  */
-struct AtreeResult atree_event_builder_with_integer(void *builder, const char *name, int64_t value);
+uintptr_t atree_tombstone_count(const struct ATreeHandle *handle);
 
 /**
- * Add a string attribute to the event.
+ * Estimate the bytes used by the tree, including the subscription payloads.
+ *
+ * # Returns
+ * Estimation of the bytes used by the tree, or 0 if `handle` is null
  *
  * # Safety
- * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
- * - `name` and `value` must be valid null-terminated C strings
+ * - `handle` must be a valid pointer returned by `atree_new()`
  */
-struct AtreeResult atree_event_builder_with_string(void *builder,
-                                                   const char *name,
-                                                   const char *value);
+uintptr_t atree_memory_usage(const struct ATreeHandle *handle);
 
 /**
- * Add a float attribute to the event.
+ * Get the generation of the subscriptions, incremented by every insertion and deletion.
  *
- * The float is represented as a decimal with a mantissa and scale.
- * For example, 123.45 would be represented as number=12345, scale=2.
+ * A consumer handling the matches of a search asynchronously can compare the generation read
+ * before the search with the current one to tell whether the matches refer to a superseded set
+ * of subscriptions.
+ *
+ * # Returns
+ * Generation of the tree, or 0 if `handle` is null
  *
  * # Safety
- * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
- * - `name` must be a valid null-terminated C string
+ * - `handle` must be a valid pointer returned by `atree_new()`
  */
-struct AtreeResult atree_event_builder_with_float(void *builder,
-                                                  const char *name,
-                                                  int64_t number,
-                                                  uint32_t scale);
+uint64_t atree_generation(const struct ATreeHandle *handle);
 
 /**
- * Add a string list attribute to the event.
+ * Get the number of subscriptions, nodes and strings of the tree along with its memory usage.
+ *
+ * # Returns
+ * true if the stats were written to `out`, false if an argument is null
  *
  * # Safety
- * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
- * - `name` must be a valid null-terminated C string
- * - `values` must point to an array of `count` valid null-terminated C strings
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `out` must point to writable memory for an `AtreeStats`
  */
-struct AtreeResult atree_event_builder_with_string_list(void *builder,
-                                                        const char *name,
-                                                        const char *const *values,
-                                                        uintptr_t count);
+bool atree_stats(const struct ATreeHandle *handle, struct AtreeStats *out);
 
 /**
- * Add an integer list attribute to the event.
+ * Export the tree structure as a Graphviz DOT format string.
+ *
+ * # Returns
+ * Null-terminated string containing DOT format, or null on failure
  *
  * # Safety
- * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
- * - `name` must be a valid null-terminated C string
- * - `values` must point to an array of `count` i64 values
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - Caller must free the returned string with `atree_free_string()`
  */
-struct AtreeResult atree_event_builder_with_integer_list(void *builder,
-                                                         const char *name,
-                                                         const int64_t *values,
-                                                         uintptr_t count);
+char *atree_to_graphviz(const struct ATreeHandle *handle);
 
 /**
- * Add an undefined attribute to the event.
+ * Write the tree structure as a Graphviz DOT format string to a caller buffer.
+ *
+ * # Arguments
+ * * `handle` - Valid ATree handle
+ * * `buffer` - Buffer receiving the null-terminated string; can be null to query the size
+ * * `capacity` - Size of the buffer in bytes
+ * * `required` - Optional out-parameter receiving the size needed, including the terminator
+ *
+ * # Returns
+ * true if the string was written, false if the buffer is too small or on failure
  *
  * # Safety
- * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
- * - `name` must be a valid null-terminated C string
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `buffer` must be null or point to `capacity` writable bytes
+ * - `required` must be null or point to writable memory for a size_t
  */
-struct AtreeResult atree_event_builder_with_undefined(void *builder, const char *name);
+bool atree_to_graphviz_buf(const struct ATreeHandle *handle,
+                           char *buffer,
+                           uintptr_t capacity,
+                           uintptr_t *required);
 
 /**
- * Search the A-Tree for matching expressions.
+ * Export the cumulative counters of the tree as a JSON object.
+ *
+ * The object contains the `searches`, `matches`, `inserts`, `parse_failures`,
+ * `nodes_visited` and `average_nodes_visited` fields.
+ *
+ * # Returns
+ * Null-terminated JSON string, or null on failure
  *
  * # Safety
  * - `handle` must be a valid pointer returned by `atree_new()`
- * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
- * - `builder` will be consumed by this call and must not be used after
- * - Caller must free the returned result with `atree_search_result_free()`
+ * - Caller must free the returned string with `atree_free_string()`
+ */
+char *atree_metrics_json(const struct ATreeHandle *handle);
+
+/**
+ * Write the cumulative counters of the tree as a JSON object to a caller buffer.
+ *
+ * See `atree_metrics_json()` for the format and `atree_to_graphviz_buf()` for the buffer
+ * handling.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `buffer` must be null or point to `capacity` writable bytes
+ * - `required` must be null or point to writable memory for a size_t
+ */
+bool atree_metrics_json_buf(const struct ATreeHandle *handle,
+                            char *buffer,
+                            uintptr_t capacity,
+                            uintptr_t *required);
+
+/**
+ * Export the schema and the subscriptions of the tree as a JSON document.
+ *
+ * The document can be loaded with `atree_import_json()`; see `ATree::export_json()` for its
+ * format.
+ *
+ * # Returns
+ * Null-terminated JSON string, or null on failure
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - Caller must free the returned string with `atree_free_string()`
+ */
+char *atree_export_json(const struct ATreeHandle *handle);
+
+/**
+ * Write the schema and the subscriptions of the tree as a JSON document to a caller buffer.
+ *
+ * See `atree_export_json()` for the format and `atree_to_graphviz_buf()` for the buffer
+ * handling.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `buffer` must be null or point to `capacity` writable bytes
+ * - `required` must be null or point to writable memory for a size_t
+ */
+bool atree_export_json_buf(const struct ATreeHandle *handle,
+                           char *buffer,
+                           uintptr_t capacity,
+                           uintptr_t *required);
+
+/**
+ * Create a new A-Tree from a JSON document produced by `atree_export_json()`.
+ *
+ * # Returns
+ * Pointer to ATreeHandle on success, null on failure
+ *
+ * # Safety
+ * - `json` must be a valid null-terminated C string
+ * - Caller must free the returned handle with `atree_free()`
+ */
+struct ATreeHandle *atree_import_json(const char *json);
+
+/**
+ * Free a string returned by the library.
+ *
+ * # Safety
+ * - `string` must be a valid pointer from a function that returns *mut c_char
+ */
+void atree_free_string(char *string);
+
+/**
+ * Intern a string so that it can be passed to `atree_event_builder_with_interned_string()`
+ * without being hashed for every event.
+ *
+ * The interned ID is written to `out_id`. Interning the same string multiple times returns the
+ * same ID, and it stays valid when new expressions are inserted.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `value` must be a valid null-terminated C string
+ * - `out_id` must point to writable memory for a u64
+ */
+struct AtreeResult atree_intern_string(struct ATreeHandle *handle,
+                                       const char *value,
+                                       uint64_t *out_id);
+
+/**
+ * Look up the interned ID of a string without interning it.
+ *
+ * # Returns
+ * `true` and writes the ID to `out_id` if the string was already interned or is used by an
+ * expression, `false` otherwise
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `value` must be a valid null-terminated C string
+ * - `out_id` must point to writable memory for a u64
+ */
+bool atree_lookup_string(const struct ATreeHandle *handle, const char *value, uint64_t *out_id);
+
+/**
+ * Start building an event for searching.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - Returned pointer must be freed with `atree_event_builder_free()`
+ */
+void *atree_event_builder_new(const struct ATreeHandle *handle);
+
+/**
+ * Add a boolean attribute to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must be a valid null-terminated C string
+ */
+struct AtreeResult atree_event_builder_with_boolean(void *builder, const char *name, bool value);
+
+/**
+ * Add an integer attribute to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must be a valid null-terminated C string
+ */
+struct AtreeResult atree_event_builder_with_integer(void *builder, const char *name, int64_t value);
+
+/**
+ * Add a float attribute to the event from a double.
+ *
+ * The double is rounded to the float scale of the tree (6 decimal places by default) with
+ * its rounding policy (half to even by default). NaN and infinities are rejected.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must be a valid null-terminated C string
+ */
+struct AtreeResult atree_event_builder_with_double(void *builder, const char *name, double value);
+
+/**
+ * Add a string attribute to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` and `value` must be valid null-terminated C strings
+ */
+struct AtreeResult atree_event_builder_with_string(void *builder,
+                                                   const char *name,
+                                                   const char *value);
+
+/**
+ * Add a string attribute to the event from an ID returned by `atree_intern_string()`.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must be a valid null-terminated C string
+ */
+struct AtreeResult atree_event_builder_with_interned_string(void *builder,
+                                                            const char *name,
+                                                            uint64_t id);
+
+/**
+ * Add a float attribute to the event.
+ *
+ * The float is represented as a decimal with a mantissa and scale.
+ * For example, 123.45 would be represented as number=12345, scale=2.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must be a valid null-terminated C string
+ */
+struct AtreeResult atree_event_builder_with_float(void *builder,
+                                                  const char *name,
+                                                  int64_t number,
+                                                  uint32_t scale);
+
+/**
+ * Add a string list attribute to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must be a valid null-terminated C string
+ * - `values` must point to an array of `count` valid null-terminated C strings
+ */
+struct AtreeResult atree_event_builder_with_string_list(void *builder,
+                                                        const char *name,
+                                                        const char *const *values,
+                                                        uintptr_t count);
+
+/**
+ * Add an integer list attribute to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must be a valid null-terminated C string
+ * - `values` must point to an array of `count` i64 values
+ */
+struct AtreeResult atree_event_builder_with_integer_list(void *builder,
+                                                         const char *name,
+                                                         const int64_t *values,
+                                                         uintptr_t count);
+
+/**
+ * Add an undefined attribute to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must be a valid null-terminated C string
+ */
+struct AtreeResult atree_event_builder_with_undefined(void *builder, const char *name);
+
+/**
+ * Search the A-Tree for matching expressions.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `builder` will be consumed by this call and must not be used after
+ * - Caller must free the returned result with `atree_search_result_free()`
  */
 struct AtreeSearchResult atree_search(const struct ATreeHandle *handle, void *builder);
 
+/**
+ * Search the A-Tree for matching expressions without consuming the builder.
+ *
+ * Unlike `atree_search()`, the builder can still be used afterwards, for example by resetting it
+ * with `atree_event_builder_reset()` to build the next event.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - Caller must still free `builder` with `atree_event_builder_free()`
+ * - Caller must free the returned result with `atree_search_result_free()`
+ */
+struct AtreeSearchResult atree_search_borrowed(const struct ATreeHandle *handle,
+                                               const void *builder);
+
+/**
+ * Count the expressions matching an event without copying their IDs.
+ *
+ * The builder is not consumed; it can be reset and reused right after this call.
+ *
+ * # Returns
+ * Number of matching subscriptions; 0 on failure, use `atree_match_count2()` to tell a failure
+ * from an event without matches
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - Caller must still free `builder` with `atree_event_builder_free()`
+ */
+uint64_t atree_match_count(const struct ATreeHandle *handle, const void *builder);
+
 /**
  * Free a search result.
  *
@@ -241,6 +1137,23 @@ void atree_search_result_free(struct AtreeSearchResult result);
  */
 void atree_free_error(char *error);
 
+/**
+ * Reset all the attributes of the event builder to undefined so that it can be reused.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ */
+void atree_event_builder_reset(void *builder);
+
+/**
+ * Set the time of the event, in Unix seconds, that the `within_last()` predicates are relative
+ * to. The time is cleared by `atree_event_builder_reset()`.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ */
+void atree_event_builder_with_now(void *builder, int64_t now);
+
 /**
  * Free an event builder without using it.
  *
@@ -249,4 +1162,2198 @@ void atree_free_error(char *error);
  */
 void atree_event_builder_free(void *builder);
 
+/**
+ * Register the allocator used for every buffer returned to the caller.
+ *
+ * The strings, error messages and search results are then allocated with `malloc_fn` and
+ * released with `free_fn` by the matching `atree_*_free()` functions, instead of with the Rust
+ * allocator. `ctx` is passed unchanged to every call. Passing null for both `malloc_fn` and
+ * `free_fn` restores the default allocator.
+ *
+ * The buffers are allocated with their final size, so no reallocation function is needed.
+ *
+ * # Returns
+ * `ATREE_OK`, or `ATREE_ERROR_INVALID_ARGUMENT` if only one of `malloc_fn` and `free_fn` is
+ * null
+ *
+ * # Safety
+ * - Must be called when no buffer returned by the library is still alive, typically before any
+ *   other call, since a buffer must be freed by the allocator that allocated it
+ * - The functions must be thread-safe if the library is used from multiple threads
+ */
+int32_t atree_set_allocator(AtreeMallocFn malloc_fn, AtreeFreeFn free_fn, void *ctx);
+
+/**
+ * Create an arena for the buffers returned by the `*_in_arena()` functions.
+ *
+ * The arena starts with `capacity` bytes (4 KiB if 0) and grows as needed. An arena is meant to
+ * be used by one thread at a time, typically for the duration of a request.
+ *
+ * # Returns
+ * Pointer to the arena
+ *
+ * # Safety
+ * - Caller must free the returned arena with `atree_arena_free()`
+ */
+struct AtreeArena *atree_arena_new(uintptr_t capacity);
+
+/**
+ * Release every buffer allocated from the arena at once.
+ *
+ * The memory of the arena is kept to serve the next allocations.
+ *
+ * # Safety
+ * - `arena` must be a valid pointer returned by `atree_arena_new()`
+ * - The buffers allocated from the arena must not be used after this call
+ */
+void atree_arena_reset(struct AtreeArena *arena);
+
+/**
+ * Get the number of bytes reserved by the arena, whether they are used or not.
+ *
+ * # Safety
+ * - `arena` must be a valid pointer returned by `atree_arena_new()`
+ */
+uintptr_t atree_arena_capacity(const struct AtreeArena *arena);
+
+/**
+ * Free an arena along with the buffers allocated from it.
+ *
+ * # Safety
+ * - `arena` must be null or a valid pointer returned by `atree_arena_new()`
+ * - `arena` and its buffers must not be used after this call
+ */
+void atree_arena_free(struct AtreeArena *arena);
+
+/**
+ * Search the A-Tree like `atree_search()`, allocating the IDs of the result from an arena.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `builder` will be consumed by this call and must not be used after
+ * - `arena` must be a valid pointer returned by `atree_arena_new()`
+ * - The result is released by `atree_arena_reset()` and must not be passed to
+ *   `atree_search_result_free()`
+ */
+struct AtreeSearchResult atree_search_in_arena(const struct ATreeHandle *handle,
+                                               void *builder,
+                                               struct AtreeArena *arena);
+
+/**
+ * Search the A-Tree like `atree_search_borrowed()`, allocating the IDs of the result from an
+ * arena.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - Caller must still free `builder` with `atree_event_builder_free()`
+ * - `arena` must be a valid pointer returned by `atree_arena_new()`
+ * - The result is released by `atree_arena_reset()` and must not be passed to
+ *   `atree_search_result_free()`
+ */
+struct AtreeSearchResult atree_search_borrowed_in_arena(const struct ATreeHandle *handle,
+                                                        const void *builder,
+                                                        struct AtreeArena *arena);
+
+/**
+ * Export the tree as a JSON document like `atree_export_json()`, allocating the string from an
+ * arena.
+ *
+ * # Returns
+ * Null-terminated JSON string, or null on failure
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `arena` must be a valid pointer returned by `atree_arena_new()`
+ * - The string is released by `atree_arena_reset()` and must not be passed to
+ *   `atree_free_string()`
+ */
+char *atree_export_json_in_arena(const struct ATreeHandle *handle, struct AtreeArena *arena);
+
+/**
+ * Parse an expression with the attributes of the tree without inserting it.
+ *
+ * The strings of the expression are not interned. Use `atree_validate_expression()` to locate
+ * the error of an invalid expression.
+ *
+ * # Returns
+ * Root of the parsed expression, or null if the expression is invalid
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `expression` must be a valid null-terminated C string
+ * - Caller must free the result with `atree_ast_free()`
+ */
+struct AtreeAst *atree_parse(const struct ATreeHandle *handle, const char *expression);
+
+/**
+ * Free an expression returned by `atree_parse()` along with all its nodes and strings.
+ *
+ * # Safety
+ * - `ast` must be null or a pointer returned by `atree_parse()`, not one of its children
+ * - Neither `ast` nor its nodes and strings can be used after this call
+ */
+void atree_ast_free(struct AtreeAst *ast);
+
+/**
+ * Get the kind of a node.
+ *
+ * # Safety
+ * - `node` must be a valid node of an expression returned by `atree_parse()`
+ */
+enum AtreeAstKind atree_ast_kind(const struct AtreeAst *node);
+
+/**
+ * Get the number of operands of a node: 2 for `and` and `or`, 1 for `not` and 0 otherwise.
+ *
+ * # Safety
+ * - `node` must be null or a valid node of an expression returned by `atree_parse()`
+ */
+uintptr_t atree_ast_children_count(const struct AtreeAst *node);
+
+/**
+ * Get an operand of a node, from left to right.
+ *
+ * # Returns
+ * The operand, owned by the expression, or null if `index` is out of bounds
+ *
+ * # Safety
+ * - `node` must be null or a valid node of an expression returned by `atree_parse()`
+ */
+const struct AtreeAst *atree_ast_child(const struct AtreeAst *node, uintptr_t index);
+
+/**
+ * Get the attribute name of a predicate.
+ *
+ * # Returns
+ * The name, owned by the expression, or null if the node is not a predicate
+ *
+ * # Safety
+ * - `node` must be null or a valid node of an expression returned by `atree_parse()`
+ */
+const char *atree_ast_attribute(const struct AtreeAst *node);
+
+/**
+ * Get the operator of a predicate, or `OperatorNone` for the other nodes.
+ *
+ * # Safety
+ * - `node` must be null or a valid node of an expression returned by `atree_parse()`
+ */
+enum AtreeAstOperator atree_ast_operator(const struct AtreeAst *node);
+
+/**
+ * Get the type of the literal value of a node.
+ *
+ * # Safety
+ * - `node` must be null or a valid node of an expression returned by `atree_parse()`
+ */
+enum AtreeLiteralKind atree_ast_literal_kind(const struct AtreeAst *node);
+
+/**
+ * Get the value of a constant node.
+ *
+ * # Returns
+ * true if the node is a constant and its value was written to `out`
+ *
+ * # Safety
+ * - `node` must be null or a valid node of an expression returned by `atree_parse()`
+ * - `out` must point to writable memory
+ */
+bool atree_ast_literal_boolean(const struct AtreeAst *node, bool *out);
+
+/**
+ * Get the integer value of a predicate.
+ *
+ * # Returns
+ * true if the value is an integer and it was written to `out`
+ *
+ * # Safety
+ * - `node` must be null or a valid node of an expression returned by `atree_parse()`
+ * - `out` must point to writable memory
+ */
+bool atree_ast_literal_integer(const struct AtreeAst *node, int64_t *out);
+
+/**
+ * Get the minimum amount of values in common of an `intersect_at_least` predicate.
+ *
+ * # Returns
+ * true if the node has a count and it was written to `out`
+ *
+ * # Safety
+ * - `node` must be null or a valid node of an expression returned by `atree_parse()`
+ * - `out` must point to writable memory
+ */
+bool atree_ast_minimum_overlap(const struct AtreeAst *node, uintptr_t *out);
+
+/**
+ * Get the amount of edits allowed by a `~=` predicate.
+ *
+ * # Returns
+ * true if the node has an edit distance and it was written to `out`
+ *
+ * # Safety
+ * - `node` must be null or a valid node of an expression returned by `atree_parse()`
+ * - `out` must point to writable memory
+ */
+bool atree_ast_edit_distance(const struct AtreeAst *node, uint8_t *out);
+
+/**
+ * Get the float value of a predicate, converted to the nearest double.
+ *
+ * # Returns
+ * true if the value is a float and it was written to `out`
+ *
+ * # Safety
+ * - `node` must be null or a valid node of an expression returned by `atree_parse()`
+ * - `out` must point to writable memory
+ */
+bool atree_ast_literal_float(const struct AtreeAst *node, double *out);
+
+/**
+ * Get the string value of a predicate.
+ *
+ * # Returns
+ * The string, owned by the expression, or null if the value is not a string
+ *
+ * # Safety
+ * - `node` must be null or a valid node of an expression returned by `atree_parse()`
+ */
+const char *atree_ast_literal_string(const struct AtreeAst *node);
+
+/**
+ * Get the number of elements of a list value, sorted and deduplicated as stored by the tree, or
+ * 2 for the bounds of a range.
+ *
+ * # Safety
+ * - `node` must be null or a valid node of an expression returned by `atree_parse()`
+ */
+uintptr_t atree_ast_literal_count(const struct AtreeAst *node);
+
+/**
+ * Get an element of an integer list value or a bound of an integer range.
+ *
+ * # Returns
+ * true if the value is an integer list or range and its element at `index` was written to `out`
+ *
+ * # Safety
+ * - `node` must be null or a valid node of an expression returned by `atree_parse()`
+ * - `out` must point to writable memory
+ */
+bool atree_ast_literal_integer_at(const struct AtreeAst *node, uintptr_t index, int64_t *out);
+
+/**
+ * Get a bound of a float range, converted to the nearest double: 0 for the lower bound and 1 for
+ * the upper one.
+ *
+ * # Returns
+ * true if the value is a float range and its bound at `index` was written to `out`
+ *
+ * # Safety
+ * - `node` must be null or a valid node of an expression returned by `atree_parse()`
+ * - `out` must point to writable memory
+ */
+bool atree_ast_literal_float_at(const struct AtreeAst *node, uintptr_t index, double *out);
+
+/**
+ * Get an element of a string list value.
+ *
+ * # Returns
+ * The string, owned by the expression, or null if the value is not a string list or `index` is
+ * out of bounds
+ *
+ * # Safety
+ * - `node` must be null or a valid node of an expression returned by `atree_parse()`
+ */
+const char *atree_ast_literal_string_at(const struct AtreeAst *node, uintptr_t index);
+
+/**
+ * Search the A-Tree for matching expressions and return their IDs as a serialized Roaring bitmap.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `builder` will be consumed by this call and must not be used after
+ * - Caller must free the returned result with `atree_bitmap_result_free()`
+ */
+struct AtreeBitmapResult atree_search_bitmap(const struct ATreeHandle *handle, void *builder);
+
+/**
+ * Free a bitmap search result.
+ *
+ * # Safety
+ * - `result` must be a valid search result returned by `atree_search_bitmap()`
+ * - `result` must not be used after this call
+ */
+void atree_bitmap_result_free(struct AtreeBitmapResult result);
+
+/**
+ * Create a pool of event builders for the tree, with `size` builders created upfront.
+ *
+ * The builders are spread over as many shards as there are cores so that the threads checking
+ * builders out and in rarely wait for each other. The pool grows when more than `size` builders
+ * are checked out at the same time.
+ *
+ * # Returns
+ * Pointer to the pool, or null if `handle` is null
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`; it must outlive the pool and
+ *   must not be modified while builders are checked out
+ * - Caller must free the returned pool with `atree_builder_pool_free()`
+ */
+struct AtreeBuilderPool *atree_builder_pool_new(const struct ATreeHandle *handle, uintptr_t size);
+
+/**
+ * Check a builder out of the pool, creating one if the pool is empty.
+ *
+ * The builder is empty and is filled with the `atree_event_builder_with_*()` functions. It can
+ * be searched with the functions that do not consume it, such as `atree_search_borrowed()`; a
+ * builder consumed by `atree_search()` must not be checked in.
+ *
+ * # Returns
+ * Pointer to the builder, or null if `pool` is null
+ *
+ * # Safety
+ * - `pool` must be a valid pointer returned by `atree_builder_pool_new()`
+ * - Caller must return the builder with `atree_builder_pool_checkin()`, or consume it
+ */
+void *atree_builder_pool_checkout(const struct AtreeBuilderPool *pool);
+
+/**
+ * Reset a builder and return it to the pool.
+ *
+ * # Safety
+ * - `pool` must be a valid pointer returned by `atree_builder_pool_new()`
+ * - `builder` must be a builder checked out of the same pool; it must not be used after this
+ *   call
+ */
+void atree_builder_pool_checkin(const struct AtreeBuilderPool *pool, void *builder);
+
+/**
+ * Free a pool along with the builders it holds.
+ *
+ * # Safety
+ * - `pool` must be null or a valid pointer returned by `atree_builder_pool_new()`
+ * - Every builder checked out of the pool must have been checked in or consumed
+ * - `pool` must not be used after this call
+ */
+void atree_builder_pool_free(struct AtreeBuilderPool *pool);
+
+/**
+ * Create a concurrent handle publishing `tree`, which it takes ownership of.
+ *
+ * # Returns
+ * Pointer to the concurrent handle, or null if `tree` is null
+ *
+ * # Safety
+ * - `tree` must be a valid pointer returned by `atree_new()`; it must not be used after this call
+ * - Caller must free the returned handle with `atree_concurrent_free()`
+ */
+struct AtreeConcurrentHandle *atree_concurrent_new(struct ATreeHandle *tree);
+
+/**
+ * Pin the current tree of the concurrent handle, to build events and search it.
+ *
+ * The pinned tree stays valid until it is released, even if it is swapped out in the meantime,
+ * so every event built with it must be searched against the same pinned tree. It can be used
+ * with all the functions that take a `const ATreeHandle*`, from any thread, but must not be
+ * modified nor freed with `atree_free()`.
+ *
+ * # Returns
+ * Pointer to the pinned tree, or null if `concurrent` is null
+ *
+ * # Safety
+ * - `concurrent` must be a valid pointer returned by `atree_concurrent_new()`
+ * - Caller must release the returned tree with `atree_concurrent_release()`
+ */
+const struct ATreeHandle *atree_concurrent_acquire(const struct AtreeConcurrentHandle *concurrent);
+
+/**
+ * Release a tree pinned by `atree_concurrent_acquire()`.
+ *
+ * A tree that was swapped out is freed when its last pinned reference is released.
+ *
+ * # Safety
+ * - `tree` must be null or a pointer returned by `atree_concurrent_acquire()`, released once
+ * - Neither `tree` nor the event builders created from it can be used after this call
+ */
+void atree_concurrent_release(const struct ATreeHandle *tree);
+
+/**
+ * Publish `new_tree` to the readers of the concurrent handle, which takes ownership of it.
+ *
+ * The readers that acquire the tree after this call get `new_tree`, while those that pinned the
+ * previous tree keep searching it; the previous tree is freed once the last of them releases
+ * it, or immediately if none has it pinned.
+ *
+ * # Safety
+ * - `concurrent` must be a valid pointer returned by `atree_concurrent_new()`
+ * - `new_tree` must be a valid pointer returned by `atree_new()`; it must not be used after this
+ *   call, unless it is null
+ * - Caller must free `error_message` with `atree_free_error()` on failure
+ */
+struct AtreeResult atree_swap(const struct AtreeConcurrentHandle *concurrent,
+                              struct ATreeHandle *new_tree);
+
+/**
+ * Free a concurrent handle.
+ *
+ * The pinned trees remain valid until they are released.
+ *
+ * # Safety
+ * - `concurrent` must be null or a valid pointer returned by `atree_concurrent_new()`
+ * - `concurrent` must not be used after this call
+ */
+void atree_concurrent_free(struct AtreeConcurrentHandle *concurrent);
+
+/**
+ * Create a configuration with the default options.
+ *
+ * # Returns
+ * Handle that must be freed with `atree_config_free()`
+ */
+struct AtreeConfigHandle *atree_config_new(void);
+
+/**
+ * Free a configuration; the trees created with it are not affected.
+ *
+ * # Safety
+ * - `config` must be a valid pointer returned by `atree_config_new()`
+ * - `config` must not be used after this call
+ */
+void atree_config_free(struct AtreeConfigHandle *config);
+
+/**
+ * Set the number of decimals kept for the float attributes; values above the maximum
+ * supported scale are capped.
+ *
+ * # Returns
+ * `true` on success, `false` if `config` is null
+ *
+ * # Safety
+ * - `config` must be null or a valid pointer returned by `atree_config_new()`
+ */
+bool atree_config_set_float_scale(struct AtreeConfigHandle *config, uint32_t scale);
+
+/**
+ * Set how the floats with more decimals than the float scale are rounded.
+ *
+ * # Returns
+ * `true` on success, `false` if `config` is null
+ *
+ * # Safety
+ * - `config` must be null or a valid pointer returned by `atree_config_new()`
+ */
+bool atree_config_set_rounding(struct AtreeConfigHandle *config, enum AtreeRounding rounding);
+
+/**
+ * Set what a search does with the event attributes that are not defined.
+ *
+ * # Returns
+ * `true` on success, `false` if `config` is null
+ *
+ * # Safety
+ * - `config` must be null or a valid pointer returned by `atree_config_new()`
+ */
+bool atree_config_set_unknown_attributes(struct AtreeConfigHandle *config,
+                                         enum AtreeUnknownAttributes policy);
+
+/**
+ * Set what an insert does when the subscription ID is already present.
+ *
+ * # Returns
+ * `true` on success, `false` if `config` is null
+ *
+ * # Safety
+ * - `config` must be null or a valid pointer returned by `atree_config_new()`
+ */
+bool atree_config_set_duplicate_ids(struct AtreeConfigHandle *config,
+                                    enum AtreeDuplicateIds policy);
+
+/**
+ * Set what a delete does with the nodes of the subscription; with `DeletePolicyTombstone` the
+ * deleted subscriptions stop matching right away but their nodes are only removed by
+ * `atree_compact()`.
+ *
+ * # Returns
+ * `true` on success, `false` if `config` is null
+ *
+ * # Safety
+ * - `config` must be null or a valid pointer returned by `atree_config_new()`
+ */
+bool atree_config_set_deletes(struct AtreeConfigHandle *config, enum AtreeDeletePolicy policy);
+
+/**
+ * Set the order in which the operands of the `and` operators are evaluated; the matches are the
+ * same in both orders, only the predicates evaluated before the short-circuits differ.
+ *
+ * # Returns
+ * `true` on success, `false` if `config` is null
+ *
+ * # Safety
+ * - `config` must be null or a valid pointer returned by `atree_config_new()`
+ */
+bool atree_config_set_conjunction_order(struct AtreeConfigHandle *config,
+                                        enum AtreeConjunctionOrder order);
+
+/**
+ * Set the event values that are converted into the type of their attribute instead of being
+ * rejected: the integers set to a float attribute and the strings holding a number set to an
+ * integer or a float attribute. None of them are converted by default; every conversion is
+ * logged at the debug level.
+ *
+ * # Returns
+ * `true` on success, `false` if `config` is null
+ *
+ * # Safety
+ * - `config` must be null or a valid pointer returned by `atree_config_new()`
+ */
+bool atree_config_set_coercions(struct AtreeConfigHandle *config,
+                                bool integers_to_floats,
+                                bool strings_to_numbers);
+
+/**
+ * Keep the parse result of up to `capacity` distinct expressions; 0, the default, disables the
+ * cache.
+ *
+ * # Returns
+ * `true` on success, `false` if `config` is null
+ *
+ * # Safety
+ * - `config` must be null or a valid pointer returned by `atree_config_new()`
+ */
+bool atree_config_set_parse_cache(struct AtreeConfigHandle *config, uintptr_t capacity);
+
+/**
+ * Create a new A-Tree with the given attribute definitions and configuration.
+ *
+ * The configuration is copied: it can be freed or reused right after this call.
+ *
+ * # Returns
+ * Pointer to ATreeHandle on success, null on failure
+ *
+ * # Safety
+ * - `defs` must point to valid memory containing `count` AtreeAttributeDef structs
+ * - Each `name` field must be a valid null-terminated C string
+ * - `config` must be a valid pointer returned by `atree_config_new()`
+ * - Caller must free the returned handle with `atree_free()`
+ */
+struct ATreeHandle *atree_new_with_config(const struct AtreeAttributeDef *defs,
+                                          uintptr_t count,
+                                          const struct AtreeConfigHandle *config);
+
+/**
+ * Search the A-Tree and iterate over the matches one at a time or in batches.
+ *
+ * The builder is not consumed; it can be reset and reused right after this call. The matches
+ * are owned by the cursor, so the tree can be modified while iterating.
+ *
+ * # Returns
+ * Pointer to a cursor on success, null on failure
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - Caller must free the returned cursor with `atree_cursor_free()`
+ */
+struct AtreeCursor *atree_search_cursor(const struct ATreeHandle *handle, const void *builder);
+
+/**
+ * Get the next match.
+ *
+ * # Returns
+ * `true` if `out_id` received a match, `false` once all the matches were returned or if an
+ * argument is null
+ *
+ * # Safety
+ * - `cursor` must be a valid pointer returned by `atree_search_cursor()`
+ * - `out_id` must point to writable memory for a `uint64_t`
+ */
+bool atree_cursor_next(struct AtreeCursor *cursor, uint64_t *out_id);
+
+/**
+ * Get up to `capacity` of the next matches.
+ *
+ * # Returns
+ * Number of matches written to `out_ids`; 0 once all the matches were returned or if an argument
+ * is null
+ *
+ * # Safety
+ * - `cursor` must be a valid pointer returned by `atree_search_cursor()`
+ * - `out_ids` must point to writable memory for `capacity` `uint64_t`
+ */
+uintptr_t atree_cursor_next_batch(struct AtreeCursor *cursor,
+                                  uint64_t *out_ids,
+                                  uintptr_t capacity);
+
+/**
+ * Get the number of matches that were not returned yet.
+ *
+ * # Safety
+ * - `cursor` must be null or a valid pointer returned by `atree_search_cursor()`
+ */
+uintptr_t atree_cursor_remaining(const struct AtreeCursor *cursor);
+
+/**
+ * Free a cursor, whether all its matches were returned or not.
+ *
+ * # Safety
+ * - `cursor` must be a valid pointer returned by `atree_search_cursor()`
+ * - `cursor` must not be used after this call
+ */
+void atree_cursor_free(struct AtreeCursor *cursor);
+
+/**
+ * Enable the tracking of the handles.
+ *
+ * It must be called before any handle is created: the handles created beforehand are reported
+ * as unknown. The tracking cannot be disabled afterwards.
+ *
+ * Once enabled, the functions given a freed handle, a handle of the wrong type or an unknown
+ * pointer report the problem to the log callback at the error level and fail as if the handle
+ * was null. The memory of the freed handles is not released, so this mode is meant for the
+ * debug builds of the host.
+ */
+void atree_debug_enable(void);
+
+/**
+ * List the live handles in their order of creation, to find the leaks of the host.
+ *
+ * # Returns
+ * Number of live handles, which can be larger than `capacity`; always 0 if the tracking is not
+ * enabled with `atree_debug_enable()`
+ *
+ * # Safety
+ * - `out` must be null or point to writable memory for `capacity` AtreeDebugHandle structs
+ */
+uintptr_t atree_debug_live_handles(struct AtreeDebugHandle *out, uintptr_t capacity);
+
+/**
+ * Build an event from a flat JSON object whose keys are the attribute names.
+ *
+ * Booleans, strings and arrays are mapped onto the attributes of the same type, numbers onto the
+ * integer and float attributes and `null` leaves the attribute undefined, as does a missing key;
+ * see `ATree::event_from_json()`. The reason of a failure is reported to the log callback at the
+ * error level.
+ *
+ * Unlike an event builder, the event can be searched any number of times.
+ *
+ * # Returns
+ * Pointer to the event on success, null on failure
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `json` must be a valid null-terminated C string
+ * - Caller must free the returned event with `atree_event_free()`
+ */
+struct AtreeEventHandle *atree_event_from_json(const struct ATreeHandle *handle, const char *json);
+
+/**
+ * Search the A-Tree for the expressions matching an event built by `atree_event_from_json()`.
+ *
+ * The event is not consumed.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `event` must be a valid pointer returned by `atree_event_from_json()` for the same tree
+ * - Caller must free the returned result with `atree_search_result_free()`
+ */
+struct AtreeSearchResult atree_search_event(const struct ATreeHandle *handle,
+                                            const struct AtreeEventHandle *event);
+
+/**
+ * Free an event built by `atree_event_from_json()`.
+ *
+ * # Safety
+ * - `event` must be null or a valid pointer returned by `atree_event_from_json()`
+ * - `event` must not be used after this call
+ */
+void atree_event_free(struct AtreeEventHandle *event);
+
+/**
+ * Explain why a subscription matches an event or not, predicate by predicate.
+ *
+ * The builder is not consumed. The returned JSON object has the following fields:
+ * - `id`: the subscription ID
+ * - `matches`: whether the subscription matches the event
+ * - `result`: `true`, `false` or `null` when the outcome depends on an undefined attribute
+ * - `predicates`: the predicates of the expression from left to right, each with its
+ *   `attribute`, `operator` (e.g. `equal`, `one_of`), `value` (`null` for the operators without
+ *   one), `count` (the minimum overlap of an `intersect_at_least`, `null` otherwise),
+ *   `edit_distance` (the edits allowed by a `~=`, `null` otherwise) and `result` (`null` when
+ *   the attribute is undefined)
+ *
+ * # Returns
+ * Null-terminated JSON string, or null if the subscription does not exist or on failure
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - Caller must still free `builder` with `atree_event_builder_free()`
+ * - Caller must free the returned string with `atree_free_string()`
+ */
+char *atree_explain(const struct ATreeHandle *handle,
+                    const void *builder,
+                    uint64_t subscription_id);
+
+/**
+ * Check an expression for mistakes that still parse, without inserting it.
+ *
+ * The returned JSON object has the following fields:
+ * - `valid`: whether the expression parses
+ * - `error`: the parse error, or `null` if the expression is valid
+ * - `warnings`: the problems found in a valid expression, each with a `code` and a `message`
+ *
+ * The codes of the warnings are:
+ * - `always_true` / `always_false`: the expression matches every event or none
+ * - `contradiction`: an `and` requires a predicate and its negation, or two different values
+ *   for the same attribute
+ * - `tautology`: an `or` accepts a predicate and its negation
+ * - `duplicate`: the same operand is repeated in an `and` or an `or`
+ * - `double_negation`: a `not` of a `not`
+ * - `single_value_list`: an `in` or `not in` with a single value, better written with `=` or
+ *   `<>`
+ *
+ * # Returns
+ * Null-terminated JSON string, or null on failure
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `expression` must be a valid null-terminated C string
+ * - Caller must free the returned string with `atree_free_string()`
+ */
+char *atree_lint_expression(const struct ATreeHandle *handle, const char *expression);
+
+/**
+ * Export the tree and pass the output to `write` in chunks of about 64 KiB.
+ *
+ * Unlike `atree_to_graphviz()` and `atree_export_json()`, the document is never held in memory
+ * as a whole. `write` is called on the calling thread, in order, before this function returns.
+ *
+ * # Returns
+ * A successful result if the whole document was written; the chunks already passed to `write`
+ * are not taken back on failure
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `ctx` is passed to `write` as is
+ * - Caller must free `error_message` with `atree_free_error()` on failure
+ */
+struct AtreeResult atree_export(const struct ATreeHandle *handle,
+                                enum AtreeExportFormat format,
+                                AtreeWriteCallback write,
+                                void *ctx);
+
+/**
+ * Save a binary snapshot of the tree to a file, replacing it atomically.
+ *
+ * The snapshot is written to a temporary file next to `path`, flushed to the disk and then
+ * renamed over `path`, so that a crash leaves either the previous file or the new one. The
+ * payloads attached with `atree_insert_with_payload()` are not part of the snapshot.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `path` must be a valid null-terminated C string
+ * - Caller must free `error_message` with `atree_free_error()` on failure
+ */
+struct AtreeResult atree_save_file(const struct ATreeHandle *handle, const char *path);
+
+/**
+ * Return the fingerprint of the snapshot saved by `atree_save_file()`.
+ *
+ * Two trees with the same configuration, schema and subscriptions have the same snapshot and
+ * fingerprint whatever the order of their insertions, so it can be used to address the
+ * snapshots distributed to a fleet. Returns 0 if `handle` is invalid.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ */
+uint64_t atree_fingerprint(const struct ATreeHandle *handle);
+
+/**
+ * Create a new A-Tree from a snapshot saved by `atree_save_file()`.
+ *
+ * The reason of a failure is reported to the log callback at the error level.
+ *
+ * # Returns
+ * Pointer to ATreeHandle on success, null on failure
+ *
+ * # Safety
+ * - `path` must be a valid null-terminated C string
+ * - Caller must free the returned handle with `atree_free()`
+ */
+struct ATreeHandle *atree_load_file(const char *path);
+
+/**
+ * Search the A-Tree for the matching expressions among the candidate subscriptions.
+ *
+ * The subscriptions that are not candidates are left out of the result, e.g. those without a
+ * remaining budget; the unknown candidates are ignored.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `builder` will be consumed by this call and must not be used after
+ * - `candidates` must point to `count` subscription IDs; it can be null if `count` is 0
+ * - Caller must free the returned result with `atree_search_result_free()`
+ */
+struct AtreeSearchResult atree_search_among(const struct ATreeHandle *handle,
+                                            void *builder,
+                                            const uint64_t *candidates,
+                                            uintptr_t count);
+
+/**
+ * Search the A-Tree for at most `max` matching expressions.
+ *
+ * The search stops as soon as `max` matches are found, so which of the matching subscriptions
+ * are returned is unspecified.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `builder` will be consumed by this call and must not be used after
+ * - Caller must free the returned result with `atree_search_result_free()`
+ */
+struct AtreeSearchResult atree_search_limited(const struct ATreeHandle *handle,
+                                              void *builder,
+                                              uintptr_t max);
+
+/**
+ * Create a new A-Tree whose subscription IDs are 32-bit integers.
+ *
+ * # Returns
+ * Pointer to ATreeU32Handle on success, null on failure
+ *
+ * # Safety
+ * - `defs` must point to valid memory containing `count` AtreeAttributeDef structs
+ * - Each `name` field must be a valid null-terminated C string
+ * - Caller must free the returned handle with `atree_u32_free()`
+ */
+struct ATreeU32Handle *atree_new_u32_ids(const struct AtreeAttributeDef *defs, uintptr_t count);
+
+/**
+ * Free an A-Tree handle created by `atree_new_u32_ids()`.
+ *
+ * # Safety
+ * - `handle` must be null or a valid pointer returned by `atree_new_u32_ids()`
+ * - `handle` must not be used after this call
+ */
+void atree_u32_free(struct ATreeU32Handle *handle);
+
+/**
+ * Insert a boolean expression associated with a 32-bit subscription ID.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new_u32_ids()`
+ * - `expression` must be a valid null-terminated C string
+ * - Caller must free result.error_message with `atree_free_error()` if !success
+ */
+struct AtreeResult atree_u32_insert(struct ATreeU32Handle *handle,
+                                    uint32_t subscription_id,
+                                    const char *expression);
+
+/**
+ * Delete a subscription by its 32-bit ID.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new_u32_ids()`
+ */
+void atree_u32_delete(struct ATreeU32Handle *handle, uint32_t subscription_id);
+
+/**
+ * Create a new event builder for a tree created by `atree_new_u32_ids()`.
+ *
+ * The builder is filled with the `atree_event_builder_with_*()` functions.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new_u32_ids()`
+ * - Caller must pass the builder to `atree_u32_search()` or free it with
+ *   `atree_event_builder_free()`
+ */
+void *atree_u32_event_builder_new(const struct ATreeU32Handle *handle);
+
+/**
+ * Search a tree created by `atree_new_u32_ids()` for matching expressions.
+ *
+ * # Returns
+ * Search result containing the matching IDs; empty on failure
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new_u32_ids()`
+ * - `builder` must be a valid pointer returned by `atree_u32_event_builder_new()`
+ * - `builder` will be consumed by this call and must not be used after
+ * - Caller must free the returned result with `atree_u32_search_result_free()`
+ */
+struct AtreeU32SearchResult atree_u32_search(const struct ATreeU32Handle *handle, void *builder);
+
+/**
+ * Free a search result returned by `atree_u32_search()`.
+ *
+ * # Safety
+ * - `result` must be a valid search result returned by `atree_u32_search()`
+ * - `result` must not be used after this call
+ */
+void atree_u32_search_result_free(struct AtreeU32SearchResult result);
+
+/**
+ * Create a new A-Tree whose subscription IDs are null-terminated strings (UUIDs, keys, ...).
+ *
+ * # Returns
+ * Pointer to ATreeStrHandle on success, null on failure
+ *
+ * # Safety
+ * - `defs` must point to valid memory containing `count` AtreeAttributeDef structs
+ * - Each `name` field must be a valid null-terminated C string
+ * - Caller must free the returned handle with `atree_str_free()`
+ */
+struct ATreeStrHandle *atree_new_str_ids(const struct AtreeAttributeDef *defs, uintptr_t count);
+
+/**
+ * Free an A-Tree handle created by `atree_new_str_ids()`.
+ *
+ * # Safety
+ * - `handle` must be null or a valid pointer returned by `atree_new_str_ids()`
+ * - `handle` must not be used after this call
+ */
+void atree_str_free(struct ATreeStrHandle *handle);
+
+/**
+ * Insert a boolean expression associated with a string subscription ID, which is copied.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new_str_ids()`
+ * - `subscription_id` and `expression` must be valid null-terminated C strings
+ * - Caller must free result.error_message with `atree_free_error()` if !success
+ */
+struct AtreeResult atree_str_insert(struct ATreeStrHandle *handle,
+                                    const char *subscription_id,
+                                    const char *expression);
+
+/**
+ * Delete a subscription by its string ID.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new_str_ids()`
+ * - `subscription_id` must be a valid null-terminated C string
+ */
+void atree_str_delete(struct ATreeStrHandle *handle, const char *subscription_id);
+
+/**
+ * Create a new event builder for a tree created by `atree_new_str_ids()`.
+ *
+ * The builder is filled with the `atree_event_builder_with_*()` functions.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new_str_ids()`
+ * - Caller must pass the builder to `atree_str_search()` or free it with
+ *   `atree_event_builder_free()`
+ */
+void *atree_str_event_builder_new(const struct ATreeStrHandle *handle);
+
+/**
+ * Search a tree created by `atree_new_str_ids()` for matching expressions.
+ *
+ * The IDs are not copied: they point to the strings owned by the tree, which remain valid until
+ * their subscription is deleted or the tree is freed.
+ *
+ * # Returns
+ * Search result containing the matching IDs; empty on failure
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new_str_ids()`
+ * - `builder` must be a valid pointer returned by `atree_str_event_builder_new()`
+ * - `builder` will be consumed by this call and must not be used after
+ * - Caller must free the returned result with `atree_str_search_result_free()`
+ */
+struct AtreeStrSearchResult atree_str_search(const struct ATreeStrHandle *handle, void *builder);
+
+/**
+ * Free a search result returned by `atree_str_search()`; the IDs remain owned by the tree.
+ *
+ * # Safety
+ * - `result` must be a valid search result returned by `atree_str_search()`
+ * - `result` must not be used after this call
+ */
+void atree_str_search_result_free(struct AtreeStrSearchResult result);
+
+/**
+ * Register the callback receiving the warnings of the library.
+ *
+ * Without a callback, the warnings are dropped. Passing null removes the callback. `ctx` is
+ * passed unchanged to every call.
+ *
+ * # Safety
+ * - `callback` must be thread-safe if the library is used from multiple threads
+ * - `ctx` must stay valid until the callback is replaced or removed
+ */
+void atree_set_log_callback(AtreeLogCallback callback, void *ctx);
+
+/**
+ * Log a warning for every search that takes longer than `micros` microseconds.
+ *
+ * 0, the default, disables the warning.
+ */
+void atree_set_slow_search_threshold(uint64_t micros);
+
+/**
+ * Set the maximum number of threads used by `atree_search_batch()`, `atree_insert_many()`,
+ * `atree_compact()` and `atree_search_async()`.
+ *
+ * The default, 1, runs the batch operations on the calling thread only. 0 uses as many threads
+ * as there are available CPUs. Above 1, the batches are split in as many chunks, which run on
+ * worker threads owned by the library while the calling thread waits for them; the workers are
+ * started on first use, up to this number, and are kept for the next calls. Lowering the number
+ * leaves the workers that were already started idle.
+ */
+void atree_set_parallelism(uint32_t threads);
+
+/**
+ * Get the maximum number of threads used by the batch operations.
+ *
+ * # Returns
+ * The value set with `atree_set_parallelism()`, with 0 replaced by the number of available CPUs
+ */
+uint32_t atree_parallelism(void);
+
+/**
+ * Search the A-Tree for each of the events, spreading them over the threads allowed by
+ * `atree_set_parallelism()`.
+ *
+ * The builders are not consumed. On success, `out_results[i]` receives the matches of
+ * `builders[i]`; on failure, nothing is written. A worker thread that panics fails the call
+ * with `ATREE_ERROR_INTERNAL`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `builders` must point to `count` valid pointers returned by `atree_event_builder_new()`
+ * - `out_results` must point to writable memory for `count` AtreeSearchResult structs
+ * - Caller must free every result with `atree_search_result_free()`
+ */
+struct AtreeResult atree_search_batch(const struct ATreeHandle *handle,
+                                      const void *const *builders,
+                                      uintptr_t count,
+                                      struct AtreeSearchResult *out_results);
+
+/**
+ * Insert many expressions, parsing them on the threads allowed by `atree_set_parallelism()`.
+ *
+ * The expressions are inserted in order once they are all parsed; an invalid expression does
+ * not prevent the next ones from being inserted. If a worker thread panics, every expression
+ * receives an error, although the tree may keep the ones inserted before the panic.
+ *
+ * # Arguments
+ * * `ids` - Array of `count` subscription IDs
+ * * `expressions` - Array of `count` null-terminated expressions
+ * * `out_errors` - Optional array of `count` pointers; `out_errors[i]` receives null if the
+ *   expression was inserted or an error message to free with `atree_free_error()` otherwise
+ *
+ * # Returns
+ * Number of inserted expressions
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `ids` and `expressions` must point to `count` elements
+ * - Each expression must be a valid null-terminated C string
+ * - `out_errors` must be null or point to writable memory for `count` pointers
+ */
+uintptr_t atree_insert_many(struct ATreeHandle *handle,
+                            const uint64_t *ids,
+                            const char *const *expressions,
+                            uintptr_t count,
+                            char **out_errors);
+
+/**
+ * Insert a boolean expression and attach a copy of `data` to the subscription.
+ *
+ * The payload replaces the one previously attached to the subscription ID, if any; an empty
+ * payload only removes it. It is only attached if the expression is inserted, and it is removed
+ * by `atree_delete()`.
+ *
+ * # Arguments
+ * * `data` - Payload to copy; can be null if `len` is 0
+ * * `len` - Size of the payload in bytes
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `expression` must be a valid null-terminated C string
+ * - `data` must point to `len` readable bytes
+ * - Caller must free `error_message` with `atree_free_error()` on failure
+ */
+struct AtreeResult atree_insert_with_payload(struct ATreeHandle *handle,
+                                             uint64_t subscription_id,
+                                             const char *expression,
+                                             const void *data,
+                                             uintptr_t len);
+
+/**
+ * Get the payload attached to a subscription.
+ *
+ * # Returns
+ * Pointer to the payload, owned by the tree, or null if the subscription has no payload
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `out_len` must be null or point to writable memory; it receives the size of the payload
+ * - The payload must not be used after the subscription is deleted, its payload replaced or
+ *   the tree freed
+ */
+const void *atree_payload(const struct ATreeHandle *handle,
+                          uint64_t subscription_id,
+                          uintptr_t *out_len);
+
+/**
+ * Search the A-Tree and return the payload of each match.
+ *
+ * The builder is not consumed. The payloads are owned by the tree: the result only holds
+ * pointers to them.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - Caller must free the returned result with `atree_payload_search_result_free()`
+ * - The payloads must not be used after their subscription is deleted, their payload replaced
+ *   or the tree freed
+ */
+struct AtreePayloadSearchResult atree_search_with_payloads(const struct ATreeHandle *handle,
+                                                           const void *builder);
+
+/**
+ * Free the matches returned by `atree_search_with_payloads()`; the payloads stay owned by the
+ * tree.
+ *
+ * # Safety
+ * - `result` must have been returned by `atree_search_with_payloads()`
+ * - `result.matches` must not be used after this call
+ */
+void atree_payload_search_result_free(struct AtreePayloadSearchResult result);
+
+/**
+ * Search the A-Tree on a worker thread and pass the matches to `done`.
+ *
+ * The event is built before returning, so the builder can be reset and reused right away. The
+ * search then runs on one of the worker threads owned by the library, which are started as
+ * needed up to the value of `atree_set_parallelism()`. `done` is called on the worker thread
+ * with `ATREE_OK` and the matches, or with the status of the failure and an empty result, the
+ * reason of the failure going to the log callback.
+ *
+ * # Returns
+ * A successful result if the search was queued; `done` is not called otherwise
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`; it must not be freed nor
+ *   modified until `done` is called
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `done` must be thread-safe and must free the result with `atree_search_result_free()`
+ * - `ctx` must stay valid until `done` is called
+ */
+struct AtreeResult atree_search_async(const struct ATreeHandle *handle,
+                                      const void *builder,
+                                      AtreeSearchCallback done,
+                                      void *ctx);
+
+/**
+ * Get the number of attributes of the tree.
+ *
+ * # Returns
+ * Number of attributes, or 0 if `handle` is null
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ */
+uintptr_t atree_attribute_count(const struct ATreeHandle *handle);
+
+/**
+ * Get the attribute at `index`, in the order of the definitions given to `atree_new()`.
+ *
+ * The name remains valid until the tree is freed.
+ *
+ * # Returns
+ * true if `out` was filled, false if `index` is out of bounds or on invalid arguments
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `out` must point to writable memory for an AtreeAttributeInfo
+ */
+bool atree_attribute_at(const struct ATreeHandle *handle,
+                        uintptr_t index,
+                        struct AtreeAttributeInfo *out);
+
+/**
+ * Create a session writing to the trees published by `concurrent`.
+ *
+ * # Returns
+ * Pointer to the session, or null if `concurrent` is null
+ *
+ * # Safety
+ * - `concurrent` must be a valid pointer returned by `atree_concurrent_new()`; it must not be
+ *   freed before the session
+ * - Caller must free the returned session with `atree_session_free()`
+ */
+struct AtreeSessionHandle *atree_session_new(const struct AtreeConcurrentHandle *concurrent);
+
+/**
+ * Insert a boolean expression in the tree of the session.
+ *
+ * The first write of a session copies the published tree, which costs as much as the size of
+ * the tree. The expression is searched by the trees acquired from the session right away, and
+ * by the other readers once it is published with `atree_session_publish()`.
+ *
+ * The call fails without writing when another tree was published and the pending writes of the
+ * session cannot be replayed on it; see `atree_session_publish()`.
+ *
+ * # Safety
+ * - `session` must be a valid pointer returned by `atree_session_new()`
+ * - `expression` must be a valid null-terminated C string
+ * - Caller must free `error_message` with `atree_free_error()` on failure
+ */
+struct AtreeResult atree_session_insert(struct AtreeSessionHandle *session,
+                                        uint64_t subscription_id,
+                                        const char *expression);
+
+/**
+ * Delete a subscription by ID, along with its payload, from the tree of the session.
+ *
+ * The call fails without writing when another tree was published and the pending writes of the
+ * session cannot be replayed on it; see `atree_session_publish()`.
+ *
+ * # Safety
+ * - `session` must be a valid pointer returned by `atree_session_new()`
+ * - Caller must free `error_message` with `atree_free_error()` on failure
+ */
+struct AtreeResult atree_session_delete(struct AtreeSessionHandle *session,
+                                        uint64_t subscription_id);
+
+/**
+ * Pin the tree searched by the session: the published tree with the writes of the session that
+ * were not published yet.
+ *
+ * The writes made while a tree is pinned are not visible in it; the next write of the session
+ * copies the whole tree again, so the pinned tree should be released before writing.
+ *
+ * # Returns
+ * Pointer to the pinned tree, or null if `session` is null or if another tree was published
+ * and the pending writes of the session cannot be replayed on it
+ *
+ * # Safety
+ * - `session` must be a valid pointer returned by `atree_session_new()`
+ * - Caller must release the returned tree with `atree_concurrent_release()`
+ */
+const struct ATreeHandle *atree_session_acquire(struct AtreeSessionHandle *session);
+
+/**
+ * Publish the tree of the session, with its writes, to the readers of the concurrent handle.
+ *
+ * When another tree was published since the last write, the writes are replayed on it before
+ * it is replaced, so that no write is lost. If one of them is rejected by that tree, for instance
+ * because another writer inserted the same ID into a tree rejecting the duplicates, nothing is
+ * published and the call fails with the status of the rejected write; the writes are kept until
+ * the session is reset.
+ *
+ * # Safety
+ * - `session` must be a valid pointer returned by `atree_session_new()`
+ * - Caller must free `error_message` with `atree_free_error()` on failure
+ */
+struct AtreeResult atree_session_publish(struct AtreeSessionHandle *session);
+
+/**
+ * Drop the writes of the session that were not published, for instance once they are part of
+ * a tree published by another writer; the session searches the published tree again.
+ *
+ * # Safety
+ * - `session` must be a valid pointer returned by `atree_session_new()`
+ */
+void atree_session_reset(struct AtreeSessionHandle *session);
+
+/**
+ * Get the number of writes of the session that were not published.
+ *
+ * # Returns
+ * Number of pending writes, or 0 if `session` is null
+ *
+ * # Safety
+ * - `session` must be a valid pointer returned by `atree_session_new()`
+ */
+uintptr_t atree_session_pending(const struct AtreeSessionHandle *session);
+
+/**
+ * Free a session, dropping the writes that were not published.
+ *
+ * The trees pinned with `atree_session_acquire()` remain valid until they are released.
+ *
+ * # Safety
+ * - `session` must be null or a valid pointer returned by `atree_session_new()`
+ * - `session` must not be used after this call
+ */
+void atree_session_free(struct AtreeSessionHandle *session);
+
+/**
+ * Write a snapshot of the tree to a new sealed shared-memory file and return its descriptor.
+ *
+ * The file is created with `memfd_create()` and sealed against any modification once written,
+ * so the workers can trust its content. The descriptor is inherited by the forked processes and
+ * can be sent to the other ones over a Unix socket; it is closed on `exec()`. The payloads
+ * attached with `atree_insert_with_payload()` are not part of the snapshot. Only supported on
+ * Linux; the failures to create or seal the file are reported with `ATREE_ERROR_IO`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `out_fd` must be a valid pointer to an `int`
+ * - Caller must close the descriptor once the workers opened it
+ * - Caller must free `error_message` with `atree_free_error()` on failure
+ */
+struct AtreeResult atree_share(const struct ATreeHandle *handle, int *out_fd);
+
+/**
+ * Create a new A-Tree from the shared-memory file written by `atree_share()`.
+ *
+ * The file is mapped read-only while the tree is decoded, then unmapped and left open. The tree
+ * is a private copy of the snapshot, as large as the tree of the process that shared it. A file
+ * that is not sealed against the writes and the shrinking is rejected, since another process
+ * could change it while it is decoded. The reason of a failure is reported to the log callback
+ * at the error level.
+ *
+ * # Returns
+ * Pointer to ATreeHandle on success, null on failure
+ *
+ * # Safety
+ * - `fd` must be a descriptor returned by `atree_share()`, or a copy of it
+ * - Caller must free the returned handle with `atree_free()`
+ */
+struct ATreeHandle *atree_open_shared(int fd);
+
+/**
+ * Insert a boolean expression of `len` bytes; see `atree_insert()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `expression` must point to `len` readable bytes; it can be null if `len` is 0
+ * - Caller must free result.error_message with `atree_free_error()` if !success
+ */
+struct AtreeResult atree_insert_n(struct ATreeHandle *handle,
+                                  uint64_t subscription_id,
+                                  const char *expression,
+                                  uintptr_t len);
+
+/**
+ * Insert a boolean expression of `len` bytes and describe where it is invalid on failure; see
+ * `atree_insert_with_error()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `expression` must point to `len` readable bytes; it can be null if `len` is 0
+ * - `error` must be null or point to writable memory for an `AtreeParseError`
+ * - Caller must free result.error_message with `atree_free_error()` if !success
+ * - Caller must free `*error` with `atree_parse_error_free()` if !success and `error` is not null
+ */
+struct AtreeResult atree_insert_with_error_n(struct ATreeHandle *handle,
+                                             uint64_t subscription_id,
+                                             const char *expression,
+                                             uintptr_t len,
+                                             struct AtreeParseError *error);
+
+/**
+ * Check whether an expression of `len` bytes could be inserted; see
+ * `atree_validate_expression()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `expression` must point to `len` readable bytes; it can be null if `len` is 0
+ * - `error` must be null or point to writable memory for an `AtreeParseError`
+ * - Caller must free `*error` with `atree_parse_error_free()` if false is returned and `error`
+ *   is not null
+ */
+bool atree_validate_expression_n(const struct ATreeHandle *handle,
+                                 const char *expression,
+                                 uintptr_t len,
+                                 struct AtreeParseError *error);
+
+/**
+ * Insert a boolean expression of `len` bytes and attach a copy of `data` to the subscription;
+ * see `atree_insert_with_payload()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `expression` must point to `len` readable bytes; it can be null if `len` is 0
+ * - `data` must point to `data_len` readable bytes
+ * - Caller must free `error_message` with `atree_free_error()` on failure
+ */
+struct AtreeResult atree_insert_with_payload_n(struct ATreeHandle *handle,
+                                               uint64_t subscription_id,
+                                               const char *expression,
+                                               uintptr_t len,
+                                               const void *data,
+                                               uintptr_t data_len);
+
+/**
+ * Insert a boolean expression of `len` bytes tagged with each of the specified tags;
+ * `tags[i]` is made of `tag_lengths[i]` bytes. See `atree_insert_tagged()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `expression` must point to `len` readable bytes; it can be null if `len` is 0
+ * - `tags` and `tag_lengths` must point to arrays of `count` elements
+ * - `tags[i]` must point to `tag_lengths[i]` readable bytes
+ * - Caller must free `error_message` with `atree_free_error()` on failure
+ */
+struct AtreeResult atree_insert_tagged_n(struct ATreeHandle *handle,
+                                         uint64_t subscription_id,
+                                         const char *expression,
+                                         uintptr_t len,
+                                         const char *const *tags,
+                                         const uintptr_t *tag_lengths,
+                                         uintptr_t count);
+
+/**
+ * Delete every subscription tagged with a tag of `len` bytes; see `atree_delete_by_tag()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `tag` must point to `len` readable bytes; it can be null if `len` is 0
+ */
+uintptr_t atree_delete_by_tag_n(struct ATreeHandle *handle, const char *tag, uintptr_t len);
+
+/**
+ * Get the IDs of the subscriptions tagged with a tag of `len` bytes; see
+ * `atree_ids_with_tag()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `tag` must point to `len` readable bytes; it can be null if `len` is 0
+ * - Caller must free the returned result with `atree_search_result_free()`
+ */
+struct AtreeSearchResult atree_ids_with_tag_n(const struct ATreeHandle *handle,
+                                              const char *tag,
+                                              uintptr_t len);
+
+/**
+ * Parse an expression of `len` bytes without inserting it; see `atree_parse()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `expression` must point to `len` readable bytes; it can be null if `len` is 0
+ * - Caller must free the returned expression with `atree_ast_free()`
+ */
+struct AtreeAst *atree_parse_n(const struct ATreeHandle *handle,
+                               const char *expression,
+                               uintptr_t len);
+
+/**
+ * Check an expression of `len` bytes for mistakes; see `atree_lint_expression()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `expression` must point to `len` readable bytes; it can be null if `len` is 0
+ * - Caller must free the returned string with `atree_free_string()`
+ */
+char *atree_lint_expression_n(const struct ATreeHandle *handle,
+                              const char *expression,
+                              uintptr_t len);
+
+/**
+ * Intern a string of `len` bytes; see `atree_intern_string()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `value` must point to `len` readable bytes; it can be null if `len` is 0
+ * - `out_id` must point to writable memory for a u64
+ */
+struct AtreeResult atree_intern_string_n(struct ATreeHandle *handle,
+                                         const char *value,
+                                         uintptr_t len,
+                                         uint64_t *out_id);
+
+/**
+ * Look up the interned ID of a string of `len` bytes without interning it; see
+ * `atree_lookup_string()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `value` must point to `len` readable bytes; it can be null if `len` is 0
+ * - `out_id` must point to writable memory for a u64
+ */
+bool atree_lookup_string_n(const struct ATreeHandle *handle,
+                           const char *value,
+                           uintptr_t len,
+                           uint64_t *out_id);
+
+/**
+ * Build an event from a JSON object of `len` bytes; see `atree_event_from_json()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `json` must point to `len` readable bytes
+ * - Caller must free the returned event with `atree_event_free()`
+ */
+struct AtreeEventHandle *atree_event_from_json_n(const struct ATreeHandle *handle,
+                                                 const char *json,
+                                                 uintptr_t len);
+
+/**
+ * Create a new A-Tree from a JSON document of `len` bytes; see `atree_import_json()`.
+ *
+ * # Safety
+ * - `json` must point to `len` readable bytes
+ * - Caller must free the returned handle with `atree_free()`
+ */
+struct ATreeHandle *atree_import_json_n(const char *json, uintptr_t len);
+
+/**
+ * Add a boolean attribute with a name of `name_len` bytes to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must point to `name_len` readable bytes
+ */
+struct AtreeResult atree_event_builder_with_boolean_n(void *builder,
+                                                      const char *name,
+                                                      uintptr_t name_len,
+                                                      bool value);
+
+/**
+ * Add an integer attribute with a name of `name_len` bytes to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must point to `name_len` readable bytes
+ */
+struct AtreeResult atree_event_builder_with_integer_n(void *builder,
+                                                      const char *name,
+                                                      uintptr_t name_len,
+                                                      int64_t value);
+
+/**
+ * Add a float attribute with a name of `name_len` bytes to the event from a double; see
+ * `atree_event_builder_with_double()`.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must point to `name_len` readable bytes
+ */
+struct AtreeResult atree_event_builder_with_double_n(void *builder,
+                                                     const char *name,
+                                                     uintptr_t name_len,
+                                                     double value);
+
+/**
+ * Add a float attribute with a name of `name_len` bytes to the event; see
+ * `atree_event_builder_with_float()`.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must point to `name_len` readable bytes
+ */
+struct AtreeResult atree_event_builder_with_float_n(void *builder,
+                                                    const char *name,
+                                                    uintptr_t name_len,
+                                                    int64_t number,
+                                                    uint32_t scale);
+
+/**
+ * Add a string attribute to the event, with a name of `name_len` bytes and a value of
+ * `value_len` bytes.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must point to `name_len` readable bytes
+ * - `value` must point to `value_len` readable bytes; it can be null if `value_len` is 0
+ */
+struct AtreeResult atree_event_builder_with_string_n(void *builder,
+                                                     const char *name,
+                                                     uintptr_t name_len,
+                                                     const char *value,
+                                                     uintptr_t value_len);
+
+/**
+ * Add a string attribute with a name of `name_len` bytes to the event from an ID returned by
+ * `atree_intern_string()`.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must point to `name_len` readable bytes
+ */
+struct AtreeResult atree_event_builder_with_interned_string_n(void *builder,
+                                                              const char *name,
+                                                              uintptr_t name_len,
+                                                              uint64_t id);
+
+/**
+ * Add a string list attribute with a name of `name_len` bytes to the event; `values[i]` is
+ * made of `lengths[i]` bytes.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must point to `name_len` readable bytes
+ * - `values` and `lengths` must point to arrays of `count` elements
+ * - `values[i]` must point to `lengths[i]` readable bytes
+ */
+struct AtreeResult atree_event_builder_with_string_list_n(void *builder,
+                                                          const char *name,
+                                                          uintptr_t name_len,
+                                                          const char *const *values,
+                                                          const uintptr_t *lengths,
+                                                          uintptr_t count);
+
+/**
+ * Add an integer list attribute with a name of `name_len` bytes to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must point to `name_len` readable bytes
+ * - `values` must point to an array of `count` i64 values
+ */
+struct AtreeResult atree_event_builder_with_integer_list_n(void *builder,
+                                                           const char *name,
+                                                           uintptr_t name_len,
+                                                           const int64_t *values,
+                                                           uintptr_t count);
+
+/**
+ * Add an undefined attribute with a name of `name_len` bytes to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must point to `name_len` readable bytes
+ */
+struct AtreeResult atree_event_builder_with_undefined_n(void *builder,
+                                                        const char *name,
+                                                        uintptr_t name_len);
+
+/**
+ * Describe a status code.
+ *
+ * # Returns
+ * Static null-terminated string that must not be freed
+ */
+const char *atree_status_message(int32_t status);
+
+/**
+ * Create a new A-Tree with the given attribute definitions.
+ *
+ * # Safety
+ * - `defs` must point to valid memory containing `count` AtreeAttributeDef structs
+ * - Each `name` field must be a valid null-terminated C string
+ * - `out_handle` must point to writable memory for a pointer
+ * - Caller must free the handle written to `out_handle` with `atree_free()`
+ */
+int32_t atree_new2(const struct AtreeAttributeDef *defs,
+                   uintptr_t count,
+                   struct ATreeHandle **out_handle);
+
+/**
+ * Insert a boolean expression associated with a subscription ID.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+ * - `expression` must be a valid null-terminated C string
+ */
+int32_t atree_insert2(struct ATreeHandle *handle, uint64_t subscription_id, const char *expression);
+
+/**
+ * Intern a string; see `atree_intern_string()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+ * - `value` must be a valid null-terminated C string
+ * - `out_id` must point to writable memory for a u64
+ */
+int32_t atree_intern_string2(struct ATreeHandle *handle, const char *value, uint64_t *out_id);
+
+/**
+ * Start building an event for searching.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+ * - `out_builder` must point to writable memory for a pointer
+ * - The builder written to `out_builder` must be freed with `atree_event_builder_free()` or
+ *   consumed by `atree_search2()`
+ */
+int32_t atree_event_builder_new2(const struct ATreeHandle *handle, void **out_builder);
+
+/**
+ * Add a boolean attribute to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+ * - `name` must be a valid null-terminated C string
+ */
+int32_t atree_event_builder_with_boolean2(void *builder, const char *name, bool value);
+
+/**
+ * Add an integer attribute to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+ * - `name` must be a valid null-terminated C string
+ */
+int32_t atree_event_builder_with_integer2(void *builder, const char *name, int64_t value);
+
+/**
+ * Add a float attribute to the event from a double; see `atree_event_builder_with_double()`.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+ * - `name` must be a valid null-terminated C string
+ */
+int32_t atree_event_builder_with_double2(void *builder, const char *name, double value);
+
+/**
+ * Add a float attribute to the event from a mantissa and a scale.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+ * - `name` must be a valid null-terminated C string
+ */
+int32_t atree_event_builder_with_float2(void *builder,
+                                        const char *name,
+                                        int64_t number,
+                                        uint32_t scale);
+
+/**
+ * Add a string attribute to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+ * - `name` and `value` must be valid null-terminated C strings
+ */
+int32_t atree_event_builder_with_string2(void *builder, const char *name, const char *value);
+
+/**
+ * Add a string attribute to the event from an ID returned by `atree_intern_string2()`.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+ * - `name` must be a valid null-terminated C string
+ */
+int32_t atree_event_builder_with_interned_string2(void *builder, const char *name, uint64_t id);
+
+/**
+ * Add a string list attribute to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+ * - `name` must be a valid null-terminated C string
+ * - `values` must point to an array of `count` valid null-terminated C strings
+ */
+int32_t atree_event_builder_with_string_list2(void *builder,
+                                              const char *name,
+                                              const char *const *values,
+                                              uintptr_t count);
+
+/**
+ * Add an integer list attribute to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+ * - `name` must be a valid null-terminated C string
+ * - `values` must point to an array of `count` i64 values
+ */
+int32_t atree_event_builder_with_integer_list2(void *builder,
+                                               const char *name,
+                                               const int64_t *values,
+                                               uintptr_t count);
+
+/**
+ * Add an undefined attribute to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+ * - `name` must be a valid null-terminated C string
+ */
+int32_t atree_event_builder_with_undefined2(void *builder, const char *name);
+
+/**
+ * Search the A-Tree for matching expressions.
+ *
+ * The IDs of the matching subscriptions are written to `out_ids` and their number to
+ * `out_count`; `out_ids` is null when nothing matches.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+ * - `builder` will be consumed by this call and must not be used after, even on failure
+ * - `out_ids` and `out_count` must point to writable memory
+ * - Caller must free the IDs with `atree_ids_free()`
+ */
+int32_t atree_search2(const struct ATreeHandle *handle,
+                      void *builder,
+                      uint64_t **out_ids,
+                      uintptr_t *out_count);
+
+/**
+ * Search the A-Tree for matching expressions without consuming the builder.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+ * - `out_ids` and `out_count` must point to writable memory
+ * - Caller must free the IDs with `atree_ids_free()`
+ */
+int32_t atree_search_borrowed2(const struct ATreeHandle *handle,
+                               const void *builder,
+                               uint64_t **out_ids,
+                               uintptr_t *out_count);
+
+/**
+ * Count the expressions matching an event without copying their IDs.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+ * - `out_count` must point to writable memory
+ */
+int32_t atree_match_count2(const struct ATreeHandle *handle,
+                           const void *builder,
+                           uint64_t *out_count);
+
+/**
+ * Search the A-Tree for the expressions matching a prepared event; see `atree_search_event()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+ * - `event` must be a valid pointer returned by `atree_event_from_json()` for the same tree
+ * - `out_ids` and `out_count` must point to writable memory
+ * - Caller must free the IDs with `atree_ids_free()`
+ */
+int32_t atree_search_event2(const struct ATreeHandle *handle,
+                            const struct AtreeEventHandle *event,
+                            uint64_t **out_ids,
+                            uintptr_t *out_count);
+
+/**
+ * Search the A-Tree for the matching expressions among candidate subscriptions; see
+ * `atree_search_among()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+ * - `builder` will be consumed by this call and must not be used after, even on failure
+ * - `candidates` must point to `count` subscription IDs; it can be null if `count` is 0
+ * - `out_ids` and `out_count` must point to writable memory
+ * - Caller must free the IDs with `atree_ids_free()`
+ */
+int32_t atree_search_among2(const struct ATreeHandle *handle,
+                            void *builder,
+                            const uint64_t *candidates,
+                            uintptr_t count,
+                            uint64_t **out_ids,
+                            uintptr_t *out_count);
+
+/**
+ * Search the A-Tree for at most `max` matching expressions; see `atree_search_limited()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+ * - `builder` will be consumed by this call and must not be used after, even on failure
+ * - `out_ids` and `out_count` must point to writable memory
+ * - Caller must free the IDs with `atree_ids_free()`
+ */
+int32_t atree_search_limited2(const struct ATreeHandle *handle,
+                              void *builder,
+                              uintptr_t max,
+                              uint64_t **out_ids,
+                              uintptr_t *out_count);
+
+/**
+ * Insert a boolean expression along with the tags of the subscription; see
+ * `atree_insert_tagged()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+ * - `expression` must be a valid null-terminated C string
+ * - `tags` must point to `count` valid null-terminated C strings; it can be null if `count` is 0
+ */
+int32_t atree_insert_tagged2(struct ATreeHandle *handle,
+                             uint64_t subscription_id,
+                             const char *expression,
+                             const char *const *tags,
+                             uintptr_t count);
+
+/**
+ * Insert a boolean expression and attach a copy of `data` to the subscription; see
+ * `atree_insert_with_payload()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+ * - `expression` must be a valid null-terminated C string
+ * - `data` must point to `len` readable bytes; it can be null if `len` is 0
+ */
+int32_t atree_insert_with_payload2(struct ATreeHandle *handle,
+                                   uint64_t subscription_id,
+                                   const char *expression,
+                                   const void *data,
+                                   uintptr_t len);
+
+/**
+ * Get the IDs of the subscriptions tagged with `tag`, in no particular order.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+ * - `tag` must be a valid null-terminated C string
+ * - `out_ids` and `out_count` must point to writable memory
+ * - Caller must free the IDs with `atree_ids_free()`
+ */
+int32_t atree_ids_with_tag2(const struct ATreeHandle *handle,
+                            const char *tag,
+                            uint64_t **out_ids,
+                            uintptr_t *out_count);
+
+/**
+ * Stream an export of the tree to a callback; see `atree_export()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+ * - `ctx` is passed to `write` as is
+ */
+int32_t atree_export2(const struct ATreeHandle *handle,
+                      enum AtreeExportFormat format,
+                      AtreeWriteCallback write,
+                      void *ctx);
+
+/**
+ * Save a binary snapshot of the tree to a file, replacing it atomically; see
+ * `atree_save_file()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+ * - `path` must be a valid null-terminated C string
+ */
+int32_t atree_save_file2(const struct ATreeHandle *handle, const char *path);
+
+/**
+ * Publish a new tree to the readers of a concurrent handle; see `atree_swap()`.
+ *
+ * # Safety
+ * - `concurrent` must be a valid pointer returned by `atree_concurrent_new()`
+ * - `new_tree` must be a valid pointer returned by `atree_new()` or `atree_new2()`; it must not
+ *   be used after this call, unless it is null
+ */
+int32_t atree_swap2(const struct AtreeConcurrentHandle *concurrent, struct ATreeHandle *new_tree);
+
+/**
+ * Free the IDs returned by the `*2()` search functions.
+ *
+ * # Safety
+ * - `ids` and `count` must have been written by one of the `*2()` search functions
+ * - `ids` must not be used after this call
+ */
+void atree_ids_free(uint64_t *ids, uintptr_t count);
+
+/**
+ * Insert a boolean expression tagged with each of the specified tags.
+ *
+ * # Arguments
+ * * `tags` - Array of `count` null-terminated tags; can be null if `count` is 0
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `expression` and each tag must be valid null-terminated C strings
+ * - `tags` must point to `count` pointers
+ * - Caller must free `error_message` with `atree_free_error()` on failure
+ */
+struct AtreeResult atree_insert_tagged(struct ATreeHandle *handle,
+                                       uint64_t subscription_id,
+                                       const char *expression,
+                                       const char *const *tags,
+                                       uintptr_t count);
+
+/**
+ * Delete every subscription tagged with `tag`, along with their payloads.
+ *
+ * # Returns
+ * Number of deleted subscriptions
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `tag` must be a valid null-terminated C string
+ */
+uintptr_t atree_delete_by_tag(struct ATreeHandle *handle, const char *tag);
+
+/**
+ * Get the IDs of the subscriptions tagged with `tag`, in no particular order.
+ *
+ * # Returns
+ * Search result containing the IDs; empty if an argument is invalid
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `tag` must be a valid null-terminated C string
+ * - Caller must free the returned result with `atree_search_result_free()`
+ */
+struct AtreeSearchResult atree_ids_with_tag(const struct ATreeHandle *handle, const char *tag);
+
+/**
+ * Get the version of the library, such as `"0.1.0"`.
+ *
+ * # Returns
+ * Static null-terminated string that must not be freed
+ */
+const char *atree_version_string(void);
+
+/**
+ * Get the major version of the library.
+ */
+uint32_t atree_version_major(void);
+
+/**
+ * Get the minor version of the library.
+ */
+uint32_t atree_version_minor(void);
+
+/**
+ * Get the patch version of the library.
+ */
+uint32_t atree_version_patch(void);
+
+/**
+ * Get the version of the ABI implemented by the library.
+ *
+ * A host is compatible with the library when it matches the `ATREE_ABI_VERSION` of the header
+ * it was compiled against.
+ */
+uint32_t atree_abi_version(void);
+
+/**
+ * Insert a boolean expression given as a UTF-16 string; see `atree_insert()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `expression` must be a valid null-terminated UTF-16 string
+ * - Caller must free result.error_message with `atree_free_error()` if !success
+ */
+struct AtreeResult atree_insert_w(struct ATreeHandle *handle,
+                                  uint64_t subscription_id,
+                                  const uint16_t *expression);
+
+/**
+ * Intern a UTF-16 string; see `atree_intern_string()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `value` must be a valid null-terminated UTF-16 string
+ * - `out_id` must point to writable memory for a u64
+ */
+struct AtreeResult atree_intern_string_w(struct ATreeHandle *handle,
+                                         const uint16_t *value,
+                                         uint64_t *out_id);
+
+/**
+ * Look up the interned ID of a UTF-16 string without interning it; see
+ * `atree_lookup_string()`.
+ *
+ * # Safety
+ * - `handle` must be a valid pointer returned by `atree_new()`
+ * - `value` must be a valid null-terminated UTF-16 string
+ * - `out_id` must point to writable memory for a u64
+ */
+bool atree_lookup_string_w(const struct ATreeHandle *handle,
+                           const uint16_t *value,
+                           uint64_t *out_id);
+
+/**
+ * Add a boolean attribute named with a UTF-16 string to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must be a valid null-terminated UTF-16 string
+ */
+struct AtreeResult atree_event_builder_with_boolean_w(void *builder,
+                                                      const uint16_t *name,
+                                                      bool value);
+
+/**
+ * Add an integer attribute named with a UTF-16 string to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must be a valid null-terminated UTF-16 string
+ */
+struct AtreeResult atree_event_builder_with_integer_w(void *builder,
+                                                      const uint16_t *name,
+                                                      int64_t value);
+
+/**
+ * Add a float attribute named with a UTF-16 string to the event from a double; see
+ * `atree_event_builder_with_double()`.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must be a valid null-terminated UTF-16 string
+ */
+struct AtreeResult atree_event_builder_with_double_w(void *builder,
+                                                     const uint16_t *name,
+                                                     double value);
+
+/**
+ * Add a float attribute named with a UTF-16 string to the event; see
+ * `atree_event_builder_with_float()`.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must be a valid null-terminated UTF-16 string
+ */
+struct AtreeResult atree_event_builder_with_float_w(void *builder,
+                                                    const uint16_t *name,
+                                                    int64_t number,
+                                                    uint32_t scale);
+
+/**
+ * Add a string attribute to the event, with its name and its value as UTF-16 strings.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` and `value` must be valid null-terminated UTF-16 strings
+ */
+struct AtreeResult atree_event_builder_with_string_w(void *builder,
+                                                     const uint16_t *name,
+                                                     const uint16_t *value);
+
+/**
+ * Add a string attribute named with a UTF-16 string to the event from an ID returned by
+ * `atree_intern_string()`.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must be a valid null-terminated UTF-16 string
+ */
+struct AtreeResult atree_event_builder_with_interned_string_w(void *builder,
+                                                              const uint16_t *name,
+                                                              uint64_t id);
+
+/**
+ * Add a string list attribute to the event, with its name and its values as UTF-16 strings.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must be a valid null-terminated UTF-16 string
+ * - `values` must point to an array of `count` valid null-terminated UTF-16 strings
+ */
+struct AtreeResult atree_event_builder_with_string_list_w(void *builder,
+                                                          const uint16_t *name,
+                                                          const uint16_t *const *values,
+                                                          uintptr_t count);
+
+/**
+ * Add an integer list attribute named with a UTF-16 string to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must be a valid null-terminated UTF-16 string
+ * - `values` must point to an array of `count` i64 values
+ */
+struct AtreeResult atree_event_builder_with_integer_list_w(void *builder,
+                                                           const uint16_t *name,
+                                                           const int64_t *values,
+                                                           uintptr_t count);
+
+/**
+ * Add an undefined attribute named with a UTF-16 string to the event.
+ *
+ * # Safety
+ * - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+ * - `name` must be a valid null-terminated UTF-16 string
+ */
+struct AtreeResult atree_event_builder_with_undefined_w(void *builder, const uint16_t *name);
+
 #endif  /* ATREE_H */
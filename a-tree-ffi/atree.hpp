@@ -12,6 +12,7 @@
 
 #include <algorithm>
 #include <cstdint>
+#include <functional>
 #include <memory>
 #include <optional>
 #include <stdexcept>
@@ -155,6 +156,43 @@ public:
     explicit operator bool() const { return is_ok(); }
 };
 
+/// @brief Location and description of an invalid expression
+struct ParseError {
+    AtreeParseErrorCode code;
+    /// 1-based line of the offending token (0 if it cannot be located)
+    size_t line;
+    /// 1-based column of the offending token, in characters (0 if it cannot be located)
+    size_t column;
+    /// Byte offset of the offending token
+    size_t offset;
+    /// Byte length of the offending token
+    size_t length;
+    std::string message;
+
+    /// @brief Take ownership of the error filled by the C API
+    static ParseError from_c(AtreeParseError& error) {
+        ParseError result{error.code, error.line, error.column, error.offset, error.length,
+                          error.message ? error.message : ""};
+        atree_parse_error_free(error);
+        return result;
+    }
+};
+
+/// @brief Check that the loaded library implements the ABI of this header
+inline bool is_abi_compatible() {
+    return atree_abi_version() == ATREE_ABI_VERSION;
+}
+
+/// @brief Set the maximum number of threads used by the batch operations (0 uses every CPU)
+inline void set_parallelism(uint32_t threads) {
+    atree_set_parallelism(threads);
+}
+
+/// @brief Get the version of the loaded library, such as "0.1.0"
+inline std::string_view version() {
+    return atree_version_string();
+}
+
 // ============================================================================
 // Attribute Types
 // ============================================================================
@@ -205,6 +243,99 @@ struct AttributeDefinition {
     }
 };
 
+/// @brief Options of a tree, applied when it is created
+class Config {
+private:
+    std::optional<uint32_t> float_scale_;
+    std::optional<AtreeRounding> rounding_;
+    std::optional<AtreeUnknownAttributes> unknown_attributes_;
+    std::optional<AtreeDuplicateIds> duplicate_ids_;
+    std::optional<AtreeDeletePolicy> deletes_;
+    std::optional<AtreeConjunctionOrder> conjunction_order_;
+    std::optional<std::pair<bool, bool>> coercions_;
+    std::optional<size_t> parse_cache_;
+
+public:
+    /// @brief Set the number of decimals kept for the float attributes
+    Config& with_float_scale(uint32_t scale) {
+        float_scale_ = scale;
+        return *this;
+    }
+
+    /// @brief Set how the floats with more decimals than the float scale are rounded
+    Config& with_rounding(AtreeRounding rounding) {
+        rounding_ = rounding;
+        return *this;
+    }
+
+    /// @brief Set what a search does with the event attributes that are not defined
+    Config& with_unknown_attributes(AtreeUnknownAttributes policy) {
+        unknown_attributes_ = policy;
+        return *this;
+    }
+
+    /// @brief Set what an insert does when the subscription ID is already present
+    Config& with_duplicate_ids(AtreeDuplicateIds policy) {
+        duplicate_ids_ = policy;
+        return *this;
+    }
+
+    /// @brief Set what a delete does with the nodes of the subscription
+    Config& with_deletes(AtreeDeletePolicy policy) {
+        deletes_ = policy;
+        return *this;
+    }
+
+    /// @brief Set the order in which the operands of the `and` operators are evaluated
+    Config& with_conjunction_order(AtreeConjunctionOrder order) {
+        conjunction_order_ = order;
+        return *this;
+    }
+
+    /// @brief Convert the integers set to a float attribute and the strings holding a number set to
+    /// a numeric attribute instead of rejecting them
+    Config& with_coercions(bool integers_to_floats, bool strings_to_numbers) {
+        coercions_ = std::make_pair(integers_to_floats, strings_to_numbers);
+        return *this;
+    }
+
+    /// @brief Keep the parse result of up to `capacity` distinct expressions
+    Config& with_parse_cache(size_t capacity) {
+        parse_cache_ = capacity;
+        return *this;
+    }
+
+    /// @brief Create the matching C configuration; it must be freed with atree_config_free()
+    AtreeConfigHandle* to_c() const {
+        AtreeConfigHandle* config = atree_config_new();
+        if (float_scale_) {
+            atree_config_set_float_scale(config, *float_scale_);
+        }
+        if (rounding_) {
+            atree_config_set_rounding(config, *rounding_);
+        }
+        if (unknown_attributes_) {
+            atree_config_set_unknown_attributes(config, *unknown_attributes_);
+        }
+        if (duplicate_ids_) {
+            atree_config_set_duplicate_ids(config, *duplicate_ids_);
+        }
+        if (deletes_) {
+            atree_config_set_deletes(config, *deletes_);
+        }
+        if (conjunction_order_) {
+            atree_config_set_conjunction_order(config, *conjunction_order_);
+        }
+        if (coercions_) {
+            atree_config_set_coercions(config, coercions_->first, coercions_->second);
+        }
+        if (parse_cache_) {
+            atree_config_set_parse_cache(config, *parse_cache_);
+        }
+        return config;
+    }
+};
+
 // ============================================================================
 // Forward Declarations
 // ============================================================================
@@ -212,6 +343,16 @@ struct AttributeDefinition {
 class Tree;
 class TreeBuilder;
 class EventBuilder;
+class Event;
+class SearchResult;
+class Arena;
+class PinnedTree;
+class U32Tree;
+class StrTree;
+class ConcurrentTree;
+class Session;
+class BuilderPool;
+class PooledBuilder;
 
 // ============================================================================
 // EventBuilder - Fluent API for building events
@@ -224,8 +365,12 @@ private:
     bool consumed_;
 
     friend class Tree;
+    friend class U32Tree;
+    friend class StrTree;
+    friend class BuilderPool;
+    friend class PooledBuilder;
 
-    // Private constructor - only Tree can create builders
+    // Private constructor - only the trees can create builders
     explicit EventBuilder(void* builder) : builder_(builder), consumed_(false) {
         if (!builder_) {
             throw Error("Failed to create event builder");
@@ -267,8 +412,8 @@ public:
     /// @brief Add a boolean attribute
     EventBuilder& with_boolean(std::string_view name, bool value) {
         check_not_consumed();
-        AtreeResult result = atree_event_builder_with_boolean(
-            builder_, std::string(name).c_str(), value);
+        AtreeResult result = atree_event_builder_with_boolean_n(
+            builder_, name.data(), name.size(), value);
         handle_result(result);
         return *this;
     }
@@ -276,8 +421,8 @@ public:
     /// @brief Add an integer attribute
     EventBuilder& with_integer(std::string_view name, int64_t value) {
         check_not_consumed();
-        AtreeResult result = atree_event_builder_with_integer(
-            builder_, std::string(name).c_str(), value);
+        AtreeResult result = atree_event_builder_with_integer_n(
+            builder_, name.data(), name.size(), value);
         handle_result(result);
         return *this;
     }
@@ -285,8 +430,17 @@ public:
     /// @brief Add a string attribute
     EventBuilder& with_string(std::string_view name, std::string_view value) {
         check_not_consumed();
-        AtreeResult result = atree_event_builder_with_string(
-            builder_, std::string(name).c_str(), std::string(value).c_str());
+        AtreeResult result = atree_event_builder_with_string_n(
+            builder_, name.data(), name.size(), value.data(), value.size());
+        handle_result(result);
+        return *this;
+    }
+
+    /// @brief Add a string attribute from an ID returned by Tree::intern()
+    EventBuilder& with_interned_string(std::string_view name, uint64_t id) {
+        check_not_consumed();
+        AtreeResult result = atree_event_builder_with_interned_string_n(
+            builder_, name.data(), name.size(), id);
         handle_result(result);
         return *this;
     }
@@ -297,19 +451,21 @@ public:
     /// @param scale Number of decimal places (e.g., 123.45 = number:12345, scale:2)
     EventBuilder& with_float(std::string_view name, int64_t number, uint32_t scale) {
         check_not_consumed();
-        AtreeResult result = atree_event_builder_with_float(
-            builder_, std::string(name).c_str(), number, scale);
+        AtreeResult result = atree_event_builder_with_float_n(
+            builder_, name.data(), name.size(), number, scale);
         handle_result(result);
         return *this;
     }
 
     /// @brief Add a float attribute from a double
     /// @param name Attribute name
-    /// @param value Double value (converted to decimal with 6 decimal places)
+    /// @param value Double value (rounded to the float scale of the tree, 6 decimal places by default)
     EventBuilder& with_float(std::string_view name, double value) {
-        // Convert double to decimal with 6 decimal places
-        int64_t number = static_cast<int64_t>(value * 1000000);
-        return with_float(name, number, 6);
+        check_not_consumed();
+        AtreeResult result = atree_event_builder_with_double_n(
+            builder_, name.data(), name.size(), value);
+        handle_result(result);
+        return *this;
     }
 
     /// @brief Add a string list attribute
@@ -317,12 +473,15 @@ public:
                                    const std::vector<std::string>& values) {
         check_not_consumed();
         std::vector<const char*> c_strs;
+        std::vector<size_t> lengths;
         c_strs.reserve(values.size());
+        lengths.reserve(values.size());
         for (const auto& s : values) {
-            c_strs.push_back(s.c_str());
+            c_strs.push_back(s.data());
+            lengths.push_back(s.size());
         }
-        AtreeResult result = atree_event_builder_with_string_list(
-            builder_, std::string(name).c_str(), c_strs.data(), c_strs.size());
+        AtreeResult result = atree_event_builder_with_string_list_n(
+            builder_, name.data(), name.size(), c_strs.data(), lengths.data(), c_strs.size());
         handle_result(result);
         return *this;
     }
@@ -331,8 +490,8 @@ public:
     EventBuilder& with_integer_list(std::string_view name,
                                     const std::vector<int64_t>& values) {
         check_not_consumed();
-        AtreeResult result = atree_event_builder_with_integer_list(
-            builder_, std::string(name).c_str(), values.data(), values.size());
+        AtreeResult result = atree_event_builder_with_integer_list_n(
+            builder_, name.data(), name.size(), values.data(), values.size());
         handle_result(result);
         return *this;
     }
@@ -340,13 +499,73 @@ public:
     /// @brief Add an undefined/null attribute
     EventBuilder& with_undefined(std::string_view name) {
         check_not_consumed();
-        AtreeResult result = atree_event_builder_with_undefined(
-            builder_, std::string(name).c_str());
+        AtreeResult result = atree_event_builder_with_undefined_n(
+            builder_, name.data(), name.size());
+        handle_result(result);
+        return *this;
+    }
+
+    /// @brief Add a boolean attribute named with a UTF-16 string
+    EventBuilder& with_boolean(std::u16string_view name, bool value) {
+        check_not_consumed();
+        AtreeResult result = atree_event_builder_with_boolean_w(
+            builder_, wide(std::u16string(name)), value);
+        handle_result(result);
+        return *this;
+    }
+
+    /// @brief Add an integer attribute named with a UTF-16 string
+    EventBuilder& with_integer(std::u16string_view name, int64_t value) {
+        check_not_consumed();
+        AtreeResult result = atree_event_builder_with_integer_w(
+            builder_, wide(std::u16string(name)), value);
+        handle_result(result);
+        return *this;
+    }
+
+    /// @brief Add a string attribute with its name and its value as UTF-16 strings
+    EventBuilder& with_string(std::u16string_view name, std::u16string_view value) {
+        check_not_consumed();
+        AtreeResult result = atree_event_builder_with_string_w(
+            builder_, wide(std::u16string(name)), wide(std::u16string(value)));
+        handle_result(result);
+        return *this;
+    }
+
+    /// @brief Add a string list attribute with its name and its values as UTF-16 strings
+    EventBuilder& with_string_list(std::u16string_view name,
+                                   const std::vector<std::u16string>& values) {
+        check_not_consumed();
+        std::vector<const uint16_t*> c_strs;
+        c_strs.reserve(values.size());
+        for (const auto& s : values) {
+            c_strs.push_back(wide(s));
+        }
+        AtreeResult result = atree_event_builder_with_string_list_w(
+            builder_, wide(std::u16string(name)), c_strs.data(), c_strs.size());
         handle_result(result);
         return *this;
     }
 
+    /// @brief Set the time of the event in Unix seconds, for the within_last() predicates
+    EventBuilder& with_now(int64_t now) {
+        check_not_consumed();
+        atree_event_builder_with_now(builder_, now);
+        return *this;
+    }
+
+    /// @brief Reset all the attributes to undefined so that the builder can be reused
+    EventBuilder& reset() {
+        check_not_consumed();
+        atree_event_builder_reset(builder_);
+        return *this;
+    }
+
 private:
+    static const uint16_t* wide(const std::u16string& value) {
+        return reinterpret_cast<const uint16_t*>(value.c_str());
+    }
+
     void check_not_consumed() const {
         if (consumed_) {
             throw Error("EventBuilder has already been consumed by search()");
@@ -369,236 +588,1631 @@ private:
 };
 
 // ============================================================================
-// TreeBuilder - Fluent API for building Trees
+// Cursor - Incremental iteration over the matches of a search
 // ============================================================================
 
-/// @brief Fluent builder for creating A-Trees
-class TreeBuilder {
+/// @brief Remaining matches of a search, returned one at a time or in batches
+class Cursor {
 private:
-    std::vector<AttributeDefinition> definitions_;
+    AtreeCursor* cursor_;
 
-public:
-    TreeBuilder() = default;
+    friend class Tree;
 
-    /// @brief Add a boolean attribute
-    TreeBuilder& with_boolean(std::string name) {
-        definitions_.push_back(AttributeDefinition::boolean(std::move(name)));
-        return *this;
+    // Private constructor - only Tree can create cursors
+    explicit Cursor(AtreeCursor* cursor) : cursor_(cursor) {
+        if (!cursor_) {
+            throw Error("Search failed");
+        }
     }
 
-    /// @brief Add an integer attribute
-    TreeBuilder& with_integer(std::string name) {
-        definitions_.push_back(AttributeDefinition::integer(std::move(name)));
-        return *this;
+public:
+    /// @brief Destructor - frees the remaining matches
+    ~Cursor() {
+        if (cursor_) {
+            atree_cursor_free(cursor_);
+        }
     }
 
-    /// @brief Add a float attribute
-    TreeBuilder& with_float(std::string name) {
-        definitions_.push_back(AttributeDefinition::float_attr(std::move(name)));
-        return *this;
-    }
+    // Disable copying
+    Cursor(const Cursor&) = delete;
+    Cursor& operator=(const Cursor&) = delete;
 
-    /// @brief Add a string attribute
-    TreeBuilder& with_string(std::string name) {
-        definitions_.push_back(AttributeDefinition::string(std::move(name)));
-        return *this;
+    // Enable moving
+    Cursor(Cursor&& other) noexcept : cursor_(other.cursor_) {
+        other.cursor_ = nullptr;
     }
 
-    /// @brief Add a string list attribute
-    TreeBuilder& with_string_list(std::string name) {
-        definitions_.push_back(AttributeDefinition::string_list(std::move(name)));
+    Cursor& operator=(Cursor&& other) noexcept {
+        if (this != &other) {
+            if (cursor_) {
+                atree_cursor_free(cursor_);
+            }
+            cursor_ = other.cursor_;
+            other.cursor_ = nullptr;
+        }
         return *this;
     }
 
-    /// @brief Add an integer list attribute
-    TreeBuilder& with_integer_list(std::string name) {
-        definitions_.push_back(AttributeDefinition::integer_list(std::move(name)));
-        return *this;
+    /// @brief Get the next match, or std::nullopt once all the matches were returned
+    std::optional<uint64_t> next() {
+        uint64_t id = 0;
+        if (cursor_ && atree_cursor_next(cursor_, &id)) {
+            return id;
+        }
+        return std::nullopt;
     }
 
-    /// @brief Build the tree (throws on error)
-    Tree build() &&;
+    /// @brief Replace the content of `batch` with up to `capacity` of the next matches
+    /// @return false once all the matches were returned
+    bool next_batch(std::vector<uint64_t>& batch, size_t capacity) {
+        batch.resize(capacity);
+        size_t count = cursor_ ? atree_cursor_next_batch(cursor_, batch.data(), capacity) : 0;
+        batch.resize(count);
+        return count > 0;
+    }
 
-    /// @brief Build the tree (returns Result)
-    Result<Tree> try_build() &&;
+    /// @brief Get the number of matches that were not returned yet
+    size_t remaining() const {
+        return atree_cursor_remaining(cursor_);
+    }
 };
 
 // ============================================================================
-// Tree - Main A-Tree container
+// Event - An event built from a JSON object, reusable across searches
 // ============================================================================
 
-/// @brief A-Tree container for boolean expression matching
-class Tree {
+/// @brief Event built by Tree::event_from_json(), searched with Tree::search(const Event&)
+class Event {
 private:
-    ATreeHandle* handle_;
-
-public:
-    /// @brief Create a new A-Tree with the given attribute definitions
-    /// @param definitions Vector of attribute definitions
-    /// @throws Error if creation fails
-    explicit Tree(const std::vector<AttributeDefinition>& definitions) {
-        std::vector<AtreeAttributeDef> c_defs;
-        c_defs.reserve(definitions.size());
+    AtreeEventHandle* event_;
 
-        for (const auto& def : definitions) {
-            c_defs.push_back({
-                def.name.c_str(),
-                static_cast<AtreeAttributeType>(def.type)
-            });
-        }
+    friend class Tree;
 
-        handle_ = atree_new(c_defs.data(), c_defs.size());
-        if (!handle_) {
-            throw Error("Failed to create A-Tree");
-        }
-    }
+    // Private constructor - only Tree can create events
+    explicit Event(AtreeEventHandle* event) : event_(event) {}
 
-    /// @brief Destructor - frees the tree
-    ~Tree() {
-        if (handle_) {
-            atree_free(handle_);
+public:
+    /// @brief Destructor - frees the event
+    ~Event() {
+        if (event_) {
+            atree_event_free(event_);
         }
     }
 
     // Disable copying
-    Tree(const Tree&) = delete;
-    Tree& operator=(const Tree&) = delete;
+    Event(const Event&) = delete;
+    Event& operator=(const Event&) = delete;
 
     // Enable moving
-    Tree(Tree&& other) noexcept : handle_(other.handle_) {
-        other.handle_ = nullptr;
+    Event(Event&& other) noexcept : event_(other.event_) {
+        other.event_ = nullptr;
     }
 
-    Tree& operator=(Tree&& other) noexcept {
+    Event& operator=(Event&& other) noexcept {
         if (this != &other) {
-            if (handle_) {
-                atree_free(handle_);
+            if (event_) {
+                atree_event_free(event_);
             }
-            handle_ = other.handle_;
-            other.handle_ = nullptr;
+            event_ = other.event_;
+            other.event_ = nullptr;
         }
         return *this;
     }
+};
 
-    /// @brief Create a TreeBuilder for fluent tree construction
-    static TreeBuilder builder() {
-        return TreeBuilder();
-    }
+// ============================================================================
+// SearchResult - Owner of the IDs returned by the C search functions
+// ============================================================================
 
-    /// @brief Insert a boolean expression (throws on error)
-    /// @param subscription_id Unique identifier for this subscription
-    /// @param expression Boolean expression string
-    /// @throws Error if insertion fails
-    void insert(uint64_t subscription_id, std::string_view expression) {
-        AtreeResult result = atree_insert(
-            handle_, subscription_id, std::string(expression).c_str());
+/// @brief Matching subscription IDs returned by a C search function, freed when destroyed
+///
+/// Wraps the AtreeSearchResult of the C API, for instance when mixing C and C++ code:
+/// `atree::SearchResult matches(atree_search(handle, builder));`
+class SearchResult {
+private:
+    AtreeSearchResult result_;
 
-        if (!result.success) {
-            std::string error_msg = result.error_message;
-            atree_free_error(result.error_message);
-            throw Error(error_msg);
-        }
+public:
+    /// @brief Take ownership of a result returned by the C API
+    explicit SearchResult(AtreeSearchResult result) : result_(result) {}
+
+    /// @brief Destructor - frees the IDs
+    ~SearchResult() {
+        atree_search_result_free(result_);
     }
 
-    /// @brief Insert a boolean expression (returns Result)
-    /// @param subscription_id Unique identifier for this subscription
-    /// @param expression Boolean expression string
-    /// @return Result indicating success or failure
-    Result<void> try_insert(uint64_t subscription_id, std::string_view expression) {
-        AtreeResult result = atree_insert(
-            handle_, subscription_id, std::string(expression).c_str());
+    // Disable copying
+    SearchResult(const SearchResult&) = delete;
+    SearchResult& operator=(const SearchResult&) = delete;
 
-        if (result.success) {
-            return Result<void>::ok();
-        } else {
-            std::string error_msg = result.error_message;
-            atree_free_error(result.error_message);
-            return Result<void>::err(std::move(error_msg));
-        }
+    // Enable moving
+    SearchResult(SearchResult&& other) noexcept : result_(other.result_) {
+        other.result_ = AtreeSearchResult{nullptr, 0};
     }
 
-    /// @brief Delete a subscription by ID
-    /// @param subscription_id ID of the subscription to remove
-    void delete_subscription(uint64_t subscription_id) {
-        atree_delete(handle_, subscription_id);
+    SearchResult& operator=(SearchResult&& other) noexcept {
+        if (this != &other) {
+            atree_search_result_free(result_);
+            result_ = other.result_;
+            other.result_ = AtreeSearchResult{nullptr, 0};
+        }
+        return *this;
     }
 
-    /// @brief Create a new event builder
-    /// @return EventBuilder for constructing an event
-    EventBuilder make_event() const {
-        void* builder = atree_event_builder_new(handle_);
-        return EventBuilder(builder);
+    const uint64_t* begin() const { return result_.ids; }
+    const uint64_t* end() const { return result_.ids + result_.count; }
+    size_t size() const { return result_.count; }
+    bool empty() const { return result_.count == 0; }
+
+    /// @brief Copy the IDs to a vector
+    std::vector<uint64_t> to_vector() const {
+        return std::vector<uint64_t>(begin(), end());
     }
+};
 
-    /// @brief Search for expressions (throws on error)
-    /// @param builder EventBuilder containing the event data (consumed by this call)
-    /// @return Vector of matching subscription IDs
-    std::vector<uint64_t> search(EventBuilder& builder) const {
-        AtreeSearchResult result = atree_search(handle_, builder.release());
+// ============================================================================
+// Arena - Buffers of a request released at once
+// ============================================================================
 
-        std::vector<uint64_t> matches;
-        if (result.ids != nullptr && result.count > 0) {
-            matches.assign(result.ids, result.ids + result.count);
-            atree_search_result_free(result);
-        }
+/// @brief Arena holding the results of the searches of a request until reset() is called
+class Arena {
+private:
+    AtreeArena* arena_;
 
-        return matches;
-    }
+    friend class Tree;
 
-    /// @brief Search for expressions (rvalue overload, throws on error)
-    /// @param builder EventBuilder containing the event data (consumed by this call)
-    /// @return Vector of matching subscription IDs
-    std::vector<uint64_t> search(EventBuilder&& builder) const {
-        return search(builder);
+public:
+    /// @brief Create an arena starting with `capacity` bytes (4 KiB if 0)
+    explicit Arena(size_t capacity = 0) : arena_(atree_arena_new(capacity)) {}
+
+    /// @brief Destructor - frees the arena and the results allocated from it
+    ~Arena() {
+        if (arena_) {
+            atree_arena_free(arena_);
+        }
     }
 
-    /// @brief Search for expressions (returns Result)
-    /// @param builder EventBuilder containing the event data (consumed by this call)
-    /// @return Result containing vector of matching subscription IDs
-    Result<std::vector<uint64_t>> try_search(EventBuilder& builder) const {
-        AtreeSearchResult result = atree_search(handle_, builder.release());
+    // Disable copying
+    Arena(const Arena&) = delete;
+    Arena& operator=(const Arena&) = delete;
 
-        std::vector<uint64_t> matches;
-        if (result.ids != nullptr && result.count > 0) {
-            matches.assign(result.ids, result.ids + result.count);
-            atree_search_result_free(result);
+    // Enable moving
+    Arena(Arena&& other) noexcept : arena_(other.arena_) {
+        other.arena_ = nullptr;
+    }
+
+    Arena& operator=(Arena&& other) noexcept {
+        if (this != &other) {
+            if (arena_) {
+                atree_arena_free(arena_);
+            }
+            arena_ = other.arena_;
+            other.arena_ = nullptr;
         }
+        return *this;
+    }
 
-        return Result<std::vector<uint64_t>>::ok(std::move(matches));
+    /// @brief Release every result allocated from the arena, keeping its memory for the next ones
+    void reset() {
+        atree_arena_reset(arena_);
     }
 
-    /// @brief Search for expressions (rvalue overload, returns Result)
+    /// @brief Get the number of bytes reserved by the arena
+    size_t capacity() const {
+        return atree_arena_capacity(arena_);
+    }
+};
+
+/// @brief Matching subscription IDs owned by an Arena, valid until it is reset or destroyed
+struct ArenaIds {
+    const uint64_t* ids;
+    size_t count;
+
+    const uint64_t* begin() const { return ids; }
+    const uint64_t* end() const { return ids + count; }
+    size_t size() const { return count; }
+    bool empty() const { return count == 0; }
+};
+
+// ============================================================================
+// Match - A matching subscription along with its payload
+// ============================================================================
+
+/// @brief A matching subscription returned by Tree::search_with_payloads()
+struct Match {
+    uint64_t id;
+    /// @brief Payload attached to the subscription, owned by the tree; empty if it has none
+    std::string_view payload;
+};
+
+// ============================================================================
+// Ast - Structure of a parsed expression
+// ============================================================================
+
+/// @brief Non-owning view of a node of an Ast; valid as long as the Ast
+class AstNode {
+private:
+    const AtreeAst* node_;
+
+public:
+    explicit AstNode(const AtreeAst* node) : node_(node) {}
+
+    /// @brief Kind of the node
+    AtreeAstKind kind() const {
+        return atree_ast_kind(node_);
+    }
+
+    /// @brief Operands of an `and`, an `or` or a `not`, from left to right
+    std::vector<AstNode> children() const {
+        std::vector<AstNode> children;
+        size_t count = atree_ast_children_count(node_);
+        children.reserve(count);
+        for (size_t i = 0; i < count; ++i) {
+            children.emplace_back(atree_ast_child(node_, i));
+        }
+        return children;
+    }
+
+    /// @brief Attribute name of a predicate, or std::nullopt for the other nodes
+    std::optional<std::string_view> attribute() const {
+        const char* attribute = atree_ast_attribute(node_);
+        if (!attribute) {
+            return std::nullopt;
+        }
+        return std::string_view(attribute);
+    }
+
+    /// @brief Operator of a predicate, or OperatorNone for the other nodes
+    AtreeAstOperator op() const {
+        return atree_ast_operator(node_);
+    }
+
+    /// @brief Type of the literal value of the node
+    AtreeLiteralKind literal_kind() const {
+        return atree_ast_literal_kind(node_);
+    }
+
+    /// @brief Value of a constant node
+    std::optional<bool> boolean() const {
+        bool value = false;
+        if (!atree_ast_literal_boolean(node_, &value)) {
+            return std::nullopt;
+        }
+        return value;
+    }
+
+    /// @brief Integer value of a predicate
+    std::optional<int64_t> integer() const {
+        int64_t value = 0;
+        if (!atree_ast_literal_integer(node_, &value)) {
+            return std::nullopt;
+        }
+        return value;
+    }
+
+    /// @brief Float value of a predicate
+    std::optional<double> number() const {
+        double value = 0.0;
+        if (!atree_ast_literal_float(node_, &value)) {
+            return std::nullopt;
+        }
+        return value;
+    }
+
+    /// @brief String value of a predicate
+    std::optional<std::string_view> string() const {
+        const char* value = atree_ast_literal_string(node_);
+        if (!value) {
+            return std::nullopt;
+        }
+        return std::string_view(value);
+    }
+
+    /// @brief Elements of an integer list value; empty for the other values
+    std::vector<int64_t> integers() const {
+        std::vector<int64_t> values;
+        int64_t value = 0;
+        for (size_t i = 0; atree_ast_literal_integer_at(node_, i, &value); ++i) {
+            values.push_back(value);
+        }
+        return values;
+    }
+
+    /// @brief Elements of a string list value; empty for the other values
+    std::vector<std::string_view> strings() const {
+        std::vector<std::string_view> values;
+        for (size_t i = 0; const char* value = atree_ast_literal_string_at(node_, i); ++i) {
+            values.emplace_back(value);
+        }
+        return values;
+    }
+
+    /// @brief Minimum amount of values in common of an intersect_at_least predicate
+    std::optional<size_t> minimum_overlap() const {
+        size_t value = 0;
+        if (!atree_ast_minimum_overlap(node_, &value)) {
+            return std::nullopt;
+        }
+        return value;
+    }
+
+    /// @brief Amount of edits allowed by a fuzzy (~=) predicate
+    std::optional<uint8_t> edit_distance() const {
+        uint8_t value = 0;
+        if (!atree_ast_edit_distance(node_, &value)) {
+            return std::nullopt;
+        }
+        return value;
+    }
+};
+
+/// @brief Expression parsed by Tree::parse() (RAII)
+class Ast {
+private:
+    AtreeAst* ast_;
+
+    friend class Tree;
+
+    explicit Ast(AtreeAst* ast) : ast_(ast) {}
+
+public:
+    /// @brief Destructor - frees the nodes and their strings
+    ~Ast() {
+        if (ast_) {
+            atree_ast_free(ast_);
+        }
+    }
+
+    // Disable copying
+    Ast(const Ast&) = delete;
+    Ast& operator=(const Ast&) = delete;
+
+    // Enable moving
+    Ast(Ast&& other) noexcept : ast_(other.ast_) {
+        other.ast_ = nullptr;
+    }
+
+    Ast& operator=(Ast&& other) noexcept {
+        if (this != &other) {
+            if (ast_) {
+                atree_ast_free(ast_);
+            }
+            ast_ = other.ast_;
+            other.ast_ = nullptr;
+        }
+        return *this;
+    }
+
+    /// @brief Root of the expression
+    AstNode root() const {
+        return AstNode(ast_);
+    }
+};
+
+// ============================================================================
+// TreeBuilder - Fluent API for building Trees
+// ============================================================================
+
+/// @brief Fluent builder for creating A-Trees
+class TreeBuilder {
+private:
+    std::vector<AttributeDefinition> definitions_;
+    Config config_;
+
+public:
+    TreeBuilder() = default;
+
+    /// @brief Add a boolean attribute
+    TreeBuilder& with_boolean(std::string name) {
+        definitions_.push_back(AttributeDefinition::boolean(std::move(name)));
+        return *this;
+    }
+
+    /// @brief Add an integer attribute
+    TreeBuilder& with_integer(std::string name) {
+        definitions_.push_back(AttributeDefinition::integer(std::move(name)));
+        return *this;
+    }
+
+    /// @brief Add a float attribute
+    TreeBuilder& with_float(std::string name) {
+        definitions_.push_back(AttributeDefinition::float_attr(std::move(name)));
+        return *this;
+    }
+
+    /// @brief Add a string attribute
+    TreeBuilder& with_string(std::string name) {
+        definitions_.push_back(AttributeDefinition::string(std::move(name)));
+        return *this;
+    }
+
+    /// @brief Add a string list attribute
+    TreeBuilder& with_string_list(std::string name) {
+        definitions_.push_back(AttributeDefinition::string_list(std::move(name)));
+        return *this;
+    }
+
+    /// @brief Add an integer list attribute
+    TreeBuilder& with_integer_list(std::string name) {
+        definitions_.push_back(AttributeDefinition::integer_list(std::move(name)));
+        return *this;
+    }
+
+    /// @brief Set the options of the tree
+    TreeBuilder& with_config(Config config) {
+        config_ = std::move(config);
+        return *this;
+    }
+
+    /// @brief Build the tree (throws on error)
+    Tree build() &&;
+
+    /// @brief Build the tree (returns Result)
+    Result<Tree> try_build() &&;
+};
+
+// ============================================================================
+// Tree - Main A-Tree container
+// ============================================================================
+
+/// @brief A-Tree container for boolean expression matching
+class Tree {
+private:
+    friend class PinnedTree;
+    friend class ConcurrentTree;
+    friend class BuilderPool;
+
+    ATreeHandle* handle_;
+
+    // Private constructor - takes ownership of an existing handle
+    explicit Tree(ATreeHandle* handle) : handle_(handle) {}
+
+    // Completion callback of search_async() - takes ownership of the std::function
+    static void complete_search(int32_t status, AtreeSearchResult result, void* ctx) {
+        std::unique_ptr<std::function<void(int32_t, std::vector<uint64_t>)>> done(
+            static_cast<std::function<void(int32_t, std::vector<uint64_t>)>*>(ctx));
+        (*done)(status, SearchResult(result).to_vector());
+    }
+
+    // Write callback of export_to() - forwards a chunk to the std::function
+    static void write_chunk(const char* chunk, size_t len, void* ctx) {
+        (*static_cast<const std::function<void(std::string_view)>*>(ctx))(
+            std::string_view(chunk, len));
+    }
+
+public:
+    /// @brief Create a new A-Tree with the given attribute definitions
+    /// @param definitions Vector of attribute definitions
+    /// @throws Error if creation fails
+    explicit Tree(const std::vector<AttributeDefinition>& definitions)
+        : Tree(definitions, Config()) {}
+
+    /// @brief Create a new A-Tree with the given attribute definitions and options
+    /// @param definitions Vector of attribute definitions
+    /// @param config Options of the tree
+    /// @throws Error if creation fails
+    Tree(const std::vector<AttributeDefinition>& definitions, const Config& config) {
+        std::vector<AtreeAttributeDef> c_defs;
+        c_defs.reserve(definitions.size());
+
+        for (const auto& def : definitions) {
+            c_defs.push_back({
+                def.name.c_str(),
+                static_cast<AtreeAttributeType>(def.type)
+            });
+        }
+
+        AtreeConfigHandle* c_config = config.to_c();
+        handle_ = atree_new_with_config(c_defs.data(), c_defs.size(), c_config);
+        atree_config_free(c_config);
+        if (!handle_) {
+            throw Error("Failed to create A-Tree");
+        }
+    }
+
+    /// @brief Create a new A-Tree from a JSON document produced by export_json()
+    /// @param json JSON document containing the schema and the subscriptions
+    /// @throws Error if the document is invalid
+    static Tree import_json(std::string_view json) {
+        ATreeHandle* handle = atree_import_json_n(json.data(), json.size());
+        if (!handle) {
+            throw Error("Failed to import the A-Tree from JSON");
+        }
+        return Tree(handle);
+    }
+
+    /// @brief Create a new A-Tree from a snapshot saved by save_file()
+    /// @param path Path of the snapshot
+    /// @throws Error if the file cannot be read or is not a valid snapshot
+    static Tree load_file(const std::string& path) {
+        ATreeHandle* handle = atree_load_file(path.c_str());
+        if (!handle) {
+            throw Error("Failed to load the A-Tree from " + path);
+        }
+        return Tree(handle);
+    }
+
+    /// @brief Decode a private copy of the tree from the shared-memory file written by share()
+    /// @param fd Descriptor returned by share(), inherited or received by the worker
+    /// @throws Error if the file cannot be mapped or is not a valid snapshot
+    static Tree open_shared(int fd) {
+        ATreeHandle* handle = atree_open_shared(fd);
+        if (!handle) {
+            throw Error("Failed to open the shared A-Tree snapshot");
+        }
+        return Tree(handle);
+    }
+
+    /// @brief Destructor - frees the tree
+    ~Tree() {
+        if (handle_) {
+            atree_free(handle_);
+        }
+    }
+
+    // Disable copying
+    Tree(const Tree&) = delete;
+    Tree& operator=(const Tree&) = delete;
+
+    // Enable moving
+    Tree(Tree&& other) noexcept : handle_(other.handle_) {
+        other.handle_ = nullptr;
+    }
+
+    Tree& operator=(Tree&& other) noexcept {
+        if (this != &other) {
+            if (handle_) {
+                atree_free(handle_);
+            }
+            handle_ = other.handle_;
+            other.handle_ = nullptr;
+        }
+        return *this;
+    }
+
+    /// @brief Create a TreeBuilder for fluent tree construction
+    static TreeBuilder builder() {
+        return TreeBuilder();
+    }
+
+    /// @brief Insert a boolean expression (throws on error)
+    /// @param subscription_id Unique identifier for this subscription
+    /// @param expression Boolean expression string
+    /// @throws Error if insertion fails
+    void insert(uint64_t subscription_id, std::string_view expression) {
+        AtreeResult result = atree_insert_n(
+            handle_, subscription_id, expression.data(), expression.size());
+
+        if (!result.success) {
+            std::string error_msg = result.error_message;
+            atree_free_error(result.error_message);
+            throw Error(error_msg);
+        }
+    }
+
+    /// @brief Insert a boolean expression given as a UTF-16 string (throws on error)
+    /// @param subscription_id Unique identifier for this subscription
+    /// @param expression Boolean expression string
+    /// @throws Error if insertion fails
+    void insert(uint64_t subscription_id, std::u16string_view expression) {
+        std::u16string c_expression(expression);
+        AtreeResult result = atree_insert_w(
+            handle_, subscription_id, reinterpret_cast<const uint16_t*>(c_expression.c_str()));
+
+        if (!result.success) {
+            std::string error_msg = result.error_message;
+            atree_free_error(result.error_message);
+            throw Error(error_msg);
+        }
+    }
+
+    /// @brief Insert a boolean expression (returns Result)
+    /// @param subscription_id Unique identifier for this subscription
+    /// @param expression Boolean expression string
+    /// @return Result indicating success or failure
+    Result<void> try_insert(uint64_t subscription_id, std::string_view expression) {
+        AtreeResult result = atree_insert_n(
+            handle_, subscription_id, expression.data(), expression.size());
+
+        if (result.success) {
+            return Result<void>::ok();
+        } else {
+            std::string error_msg = result.error_message;
+            atree_free_error(result.error_message);
+            return Result<void>::err(std::move(error_msg));
+        }
+    }
+
+    /// @brief Insert a boolean expression and attach a copy of `payload` to it (throws on error)
+    /// @param subscription_id Unique identifier for this subscription
+    /// @param expression Boolean expression string
+    /// @param payload Bytes returned along with the matches; empty to remove the previous payload
+    /// @throws Error if insertion fails
+    void insert_with_payload(uint64_t subscription_id, std::string_view expression,
+                             std::string_view payload) {
+        AtreeResult result = atree_insert_with_payload_n(
+            handle_, subscription_id, expression.data(), expression.size(), payload.data(),
+            payload.size());
+
+        if (!result.success) {
+            std::string error_msg = result.error_message;
+            atree_free_error(result.error_message);
+            throw Error(error_msg);
+        }
+    }
+
+    /// @brief Get the payload attached to a subscription
+    /// @return View of the payload owned by the tree, or std::nullopt if it has none
+    std::optional<std::string_view> payload(uint64_t subscription_id) const {
+        size_t len = 0;
+        const void* data = atree_payload(handle_, subscription_id, &len);
+        if (!data) {
+            return std::nullopt;
+        }
+        return std::string_view(static_cast<const char*>(data), len);
+    }
+
+    /// @brief Parse an expression with the attributes of the tree without inserting it
+    /// @param expression Boolean expression string
+    /// @return Structure of the expression
+    /// @throws Error if the expression is invalid
+    Ast parse(std::string_view expression) const {
+        AtreeAst* ast = atree_parse_n(handle_, expression.data(), expression.size());
+        if (!ast) {
+            AtreeParseError error{};
+            atree_validate_expression_n(handle_, expression.data(), expression.size(), &error);
+            std::string error_msg = error.message ? error.message : "Failed to parse the expression";
+            atree_parse_error_free(error);
+            throw Error(error_msg);
+        }
+        return Ast(ast);
+    }
+
+    /// @brief Explain why a subscription matches an event or not, predicate by predicate
+    /// @param builder EventBuilder containing the event data (can be reused afterwards)
+    /// @param subscription_id Subscription to explain
+    /// @return JSON document described by atree_explain(), or nullopt if there is no such
+    ///         subscription
+    std::optional<std::string> explain(const EventBuilder& builder,
+                                       uint64_t subscription_id) const {
+        builder.check_not_consumed();
+        char* json = atree_explain(handle_, builder.builder_, subscription_id);
+        if (!json) {
+            return std::nullopt;
+        }
+
+        std::string result(json);
+        atree_free_string(json);
+        return result;
+    }
+
+    /// @brief Check an expression for mistakes that still parse, without inserting it
+    /// @param expression Boolean expression string
+    /// @return JSON document described by atree_lint_expression()
+    /// @throws Error if the expression cannot be checked
+    std::string lint_expression(std::string_view expression) const {
+        char* json = atree_lint_expression_n(handle_, expression.data(), expression.size());
+        if (!json) {
+            throw Error("Failed to lint the expression");
+        }
+
+        std::string result(json);
+        atree_free_string(json);
+        return result;
+    }
+
+    /// @brief Insert a boolean expression tagged with each of the tags (throws on error)
+    /// @param subscription_id Unique identifier for this subscription
+    /// @param expression Boolean expression string
+    /// @param tags Tags to pass to delete_by_tag() and ids_with_tag()
+    /// @throws Error if insertion fails
+    void insert_tagged(uint64_t subscription_id, std::string_view expression,
+                       const std::vector<std::string>& tags) {
+        std::vector<const char*> c_tags;
+        std::vector<size_t> lengths;
+        c_tags.reserve(tags.size());
+        lengths.reserve(tags.size());
+        for (const auto& tag : tags) {
+            c_tags.push_back(tag.data());
+            lengths.push_back(tag.size());
+        }
+
+        AtreeResult result = atree_insert_tagged_n(
+            handle_, subscription_id, expression.data(), expression.size(), c_tags.data(),
+            lengths.data(), c_tags.size());
+
+        if (!result.success) {
+            std::string error_msg = result.error_message;
+            atree_free_error(result.error_message);
+            throw Error(error_msg);
+        }
+    }
+
+    /// @brief Insert many expressions, parsing them on the threads allowed by set_parallelism()
+    /// @param subscriptions Pairs of subscription ID and boolean expression
+    /// @return The error message of each subscription, or std::nullopt if it was inserted
+    std::vector<std::optional<std::string>> insert_many(
+        const std::vector<std::pair<uint64_t, std::string>>& subscriptions) {
+        std::vector<uint64_t> ids;
+        std::vector<const char*> expressions;
+        ids.reserve(subscriptions.size());
+        expressions.reserve(subscriptions.size());
+        for (const auto& [id, expression] : subscriptions) {
+            ids.push_back(id);
+            expressions.push_back(expression.c_str());
+        }
+
+        std::vector<char*> c_errors(subscriptions.size(), nullptr);
+        atree_insert_many(handle_, ids.data(), expressions.data(), subscriptions.size(),
+                          c_errors.data());
+
+        std::vector<std::optional<std::string>> errors;
+        errors.reserve(c_errors.size());
+        for (char* error : c_errors) {
+            if (error) {
+                errors.emplace_back(error);
+                atree_free_error(error);
+            } else {
+                errors.emplace_back(std::nullopt);
+            }
+        }
+        return errors;
+    }
+
+    /// @brief Check whether an expression could be inserted without modifying the tree
+    /// @param expression Boolean expression string
+    /// @return The location of the error, or std::nullopt if the expression is valid
+    std::optional<ParseError> validate(std::string_view expression) const {
+        AtreeParseError error{};
+        if (atree_validate_expression_n(handle_, expression.data(), expression.size(), &error)) {
+            return std::nullopt;
+        }
+        return ParseError::from_c(error);
+    }
+
+    /// @brief Delete a subscription by ID
+    /// @param subscription_id ID of the subscription to remove
+    void delete_subscription(uint64_t subscription_id) {
+        atree_delete(handle_, subscription_id);
+    }
+
+    /// @brief Remove the nodes of the subscriptions deleted under DeletePolicyTombstone
+    /// @return Number of subscriptions whose nodes were removed
+    size_t compact() {
+        return atree_compact(handle_);
+    }
+
+    /// @brief Get the number of deleted subscriptions waiting for compact()
+    size_t tombstone_count() const {
+        return atree_tombstone_count(handle_);
+    }
+
+    /// @brief Delete every subscription tagged with `tag`
+    /// @return Number of deleted subscriptions
+    size_t delete_by_tag(std::string_view tag) {
+        return atree_delete_by_tag_n(handle_, tag.data(), tag.size());
+    }
+
+    /// @brief Get the IDs of the subscriptions tagged with `tag`, in no particular order
+    std::vector<uint64_t> ids_with_tag(std::string_view tag) const {
+        return SearchResult(atree_ids_with_tag_n(handle_, tag.data(), tag.size())).to_vector();
+    }
+
+    /// @brief Intern a string to pass it to EventBuilder::with_interned_string() (throws on error)
+    /// @param value String to intern
+    /// @return Interned ID, stable for the lifetime of the tree
+    uint64_t intern(std::string_view value) {
+        uint64_t id = 0;
+        AtreeResult result = atree_intern_string_n(handle_, value.data(), value.size(), &id);
+
+        if (!result.success) {
+            std::string error_msg = result.error_message;
+            atree_free_error(result.error_message);
+            throw Error(error_msg);
+        }
+        return id;
+    }
+
+    /// @brief Look up the interned ID of a string without interning it
+    /// @param value String to look up
+    /// @return Interned ID, or std::nullopt if the string is unknown to the tree
+    std::optional<uint64_t> lookup(std::string_view value) const {
+        uint64_t id = 0;
+        if (atree_lookup_string_n(handle_, value.data(), value.size(), &id)) {
+            return id;
+        }
+        return std::nullopt;
+    }
+
+    /// @brief Estimate the bytes used by the tree
+    size_t memory_usage() const {
+        return atree_memory_usage(handle_);
+    }
+
+    /// @brief Get the generation of the subscriptions, incremented by every insertion and deletion
+    uint64_t generation() const {
+        return atree_generation(handle_);
+    }
+
+    /// @brief Get the number of subscriptions, nodes and strings along with the memory usage
+    AtreeStats stats() const {
+        AtreeStats stats{};
+        atree_stats(handle_, &stats);
+        return stats;
+    }
+
+    /// @brief Get the attributes of the tree in the order they were defined
+    std::vector<AttributeDefinition> attributes() const {
+        std::vector<AttributeDefinition> attributes;
+        size_t count = atree_attribute_count(handle_);
+        attributes.reserve(count);
+        for (size_t index = 0; index < count; ++index) {
+            AtreeAttributeInfo info{};
+            if (atree_attribute_at(handle_, index, &info)) {
+                attributes.push_back({info.name, static_cast<AttributeType>(info.attr_type)});
+            }
+        }
+        return attributes;
+    }
+
+    /// @brief Create a new event builder
+    /// @return EventBuilder for constructing an event
+    EventBuilder make_event() const {
+        void* builder = atree_event_builder_new(handle_);
+        return EventBuilder(builder);
+    }
+
+    /// @brief Search for expressions (throws on error)
+    /// @param builder EventBuilder containing the event data (consumed by this call)
+    /// @return Vector of matching subscription IDs
+    std::vector<uint64_t> search(EventBuilder& builder) const {
+        return SearchResult(atree_search(handle_, builder.release())).to_vector();
+    }
+
+    /// @brief Build an event from a flat JSON object whose keys are the attribute names
+    /// @param json JSON object; null values and missing keys leave the attributes undefined
+    /// @return Event that can be searched any number of times
+    /// @throws Error if the JSON does not match the schema (the reason is logged)
+    Event event_from_json(std::string_view json) const {
+        AtreeEventHandle* event = atree_event_from_json_n(handle_, json.data(), json.size());
+        if (!event) {
+            throw Error("Failed to build the event from JSON");
+        }
+        return Event(event);
+    }
+
+    /// @brief Search for expressions matching an event built by event_from_json()
+    /// @param event Event of this tree (not consumed)
+    /// @return Vector of matching subscription IDs
+    std::vector<uint64_t> search(const Event& event) const {
+        if (!event.event_) {
+            throw Error("Event has been moved");
+        }
+        return SearchResult(atree_search_event(handle_, event.event_)).to_vector();
+    }
+
+    /// @brief Search for expressions among the candidate subscriptions
+    /// @param builder EventBuilder containing the event data (consumed by this call)
+    /// @param candidates Subscriptions that can be returned, e.g. those with a remaining budget
+    /// @return Vector of the matching candidates
+    std::vector<uint64_t> search_among(EventBuilder& builder,
+                                       const std::vector<uint64_t>& candidates) const {
+        SearchResult matches(atree_search_among(
+            handle_, builder.release(), candidates.data(), candidates.size()));
+        return matches.to_vector();
+    }
+
+    /// @brief Search for at most max expressions, stopping as soon as they are found
+    /// @param builder EventBuilder containing the event data (consumed by this call)
+    /// @param max Maximum number of matches
+    /// @return Vector of at most max matching subscription IDs, chosen arbitrarily
+    std::vector<uint64_t> search_limited(EventBuilder& builder, size_t max) const {
+        return SearchResult(atree_search_limited(handle_, builder.release(), max)).to_vector();
+    }
+
+    /// @brief Search for expressions, returning the IDs as a serialized 64-bit Roaring bitmap
+    /// @param builder EventBuilder containing the event data (consumed by this call)
+    /// @return Bytes in the portable format, e.g. for roaring::Roaring64Map::readSafe()
+    /// @throws Error if the search fails
+    std::vector<uint8_t> search_bitmap(EventBuilder& builder) const {
+        AtreeBitmapResult result = atree_search_bitmap(handle_, builder.release());
+        if (!result.bytes) {
+            throw Error("Failed to search the tree");
+        }
+        std::vector<uint8_t> bytes(result.bytes, result.bytes + result.len);
+        atree_bitmap_result_free(result);
+        return bytes;
+    }
+
+    /// @brief Search for expressions without consuming the builder
+    /// @param builder EventBuilder containing the event data (can be reset and reused afterwards)
+    /// @return Vector of matching subscription IDs
+    std::vector<uint64_t> search_borrowed(const EventBuilder& builder) const {
+        builder.check_not_consumed();
+        return SearchResult(atree_search_borrowed(handle_, builder.builder_)).to_vector();
+    }
+
+    /// @brief Search for expressions without consuming the builder, keeping the IDs in an arena
+    /// @param builder EventBuilder containing the event data (can be reset and reused afterwards)
+    /// @param arena Arena owning the returned IDs until it is reset
+    ArenaIds search_borrowed(const EventBuilder& builder, Arena& arena) const {
+        builder.check_not_consumed();
+        AtreeSearchResult result =
+            atree_search_borrowed_in_arena(handle_, builder.builder_, arena.arena_);
+        return ArenaIds{result.ids, result.count};
+    }
+
+    /// @brief Search for many events on the threads allowed by set_parallelism()
+    /// @param builders EventBuilders containing the events (can be reset and reused afterwards)
+    /// @return The matching subscription IDs of each event, in the same order
+    /// @throws Error if a search fails
+    std::vector<std::vector<uint64_t>> search_batch(
+        const std::vector<const EventBuilder*>& builders) const {
+        std::vector<const void*> c_builders;
+        c_builders.reserve(builders.size());
+        for (const EventBuilder* builder : builders) {
+            builder->check_not_consumed();
+            c_builders.push_back(builder->builder_);
+        }
+
+        std::vector<AtreeSearchResult> c_results(builders.size());
+        AtreeResult result =
+            atree_search_batch(handle_, c_builders.data(), c_builders.size(), c_results.data());
+        if (!result.success) {
+            std::string error_msg = result.error_message;
+            atree_free_error(result.error_message);
+            throw Error(error_msg);
+        }
+
+        std::vector<std::vector<uint64_t>> matches;
+        matches.reserve(c_results.size());
+        for (AtreeSearchResult& c_result : c_results) {
+            matches.push_back(SearchResult(c_result).to_vector());
+        }
+        return matches;
+    }
+
+    /// @brief Search for expressions and return the payload of each match
+    /// @param builder EventBuilder containing the event data (can be reset and reused afterwards)
+    /// @return Matching subscriptions; their payloads are valid until they are deleted or
+    ///         replaced
+    std::vector<Match> search_with_payloads(const EventBuilder& builder) const {
+        builder.check_not_consumed();
+        AtreePayloadSearchResult result = atree_search_with_payloads(handle_, builder.builder_);
+
+        std::vector<Match> matches;
+        matches.reserve(result.count);
+        for (size_t i = 0; i < result.count; ++i) {
+            const AtreeMatch& match = result.matches[i];
+            std::string_view payload;
+            if (match.payload) {
+                payload = std::string_view(static_cast<const char*>(match.payload),
+                                           match.payload_len);
+            }
+            matches.push_back({match.id, payload});
+        }
+        atree_payload_search_result_free(result);
+        return matches;
+    }
+
+    /// @brief Search for expressions on a worker thread of the library
+    /// @param builder EventBuilder containing the event data (can be reset and reused right away)
+    /// @param done Called on the worker thread with ATREE_OK and the matching subscription IDs,
+    ///             or with the status of the failure and no IDs; the tree must not be destroyed
+    ///             nor modified until it is called
+    /// @throws Error if the search cannot be queued
+    void search_async(const EventBuilder& builder,
+                      std::function<void(int32_t, std::vector<uint64_t>)> done) const {
+        builder.check_not_consumed();
+        auto callback = std::make_unique<std::function<void(int32_t, std::vector<uint64_t>)>>(
+            std::move(done));
+        AtreeResult result = atree_search_async(
+            handle_, builder.builder_, &Tree::complete_search, callback.get());
+        if (!result.success) {
+            std::string error_msg = result.error_message;
+            atree_free_error(result.error_message);
+            throw Error(error_msg);
+        }
+        callback.release();
+    }
+
+    /// @brief Count the expressions matching an event without copying their IDs
+    /// @param builder EventBuilder containing the event data (can be reset and reused afterwards)
+    /// @return Number of matching subscriptions
+    /// @throws Error if the search fails
+    uint64_t match_count(const EventBuilder& builder) const {
+        builder.check_not_consumed();
+        uint64_t count = 0;
+        int32_t status = atree_match_count2(handle_, builder.builder_, &count);
+        if (status != ATREE_OK) {
+            throw Error(atree_status_message(status));
+        }
+        return count;
+    }
+
+    /// @brief Search for expressions and iterate over the matches incrementally
+    /// @param builder EventBuilder containing the event data (can be reset and reused afterwards)
+    /// @return Cursor over the matching subscription IDs
+    /// @throws Error if the search fails
+    Cursor search_cursor(const EventBuilder& builder) const {
+        builder.check_not_consumed();
+        return Cursor(atree_search_cursor(handle_, builder.builder_));
+    }
+
+    /// @brief Search for expressions (rvalue overload, throws on error)
+    /// @param builder EventBuilder containing the event data (consumed by this call)
+    /// @return Vector of matching subscription IDs
+    std::vector<uint64_t> search(EventBuilder&& builder) const {
+        return search(builder);
+    }
+
+    /// @brief Search for expressions (returns Result)
+    /// @param builder EventBuilder containing the event data (consumed by this call)
+    /// @return Result containing vector of matching subscription IDs
+    Result<std::vector<uint64_t>> try_search(EventBuilder& builder) const {
+        SearchResult matches(atree_search(handle_, builder.release()));
+        return Result<std::vector<uint64_t>>::ok(matches.to_vector());
+    }
+
+    /// @brief Search for expressions (rvalue overload, returns Result)
+    /// @param builder EventBuilder containing the event data (consumed by this call)
+    /// @return Result containing vector of matching subscription IDs
+    Result<std::vector<uint64_t>> try_search(EventBuilder&& builder) const {
+        return try_search(builder);
+    }
+
+    /// @brief Export the tree structure as Graphviz DOT format (throws on error)
+    /// @return DOT format string
+    /// @throws Error if export fails
+    std::string to_graphviz() const {
+        char* dot = atree_to_graphviz(handle_);
+        if (!dot) {
+            throw Error("Failed to generate Graphviz output");
+        }
+
+        std::string result(dot);
+        atree_free_string(dot);
+        return result;
+    }
+
+    /// @brief Export the tree structure as Graphviz DOT format (returns Result)
+    /// @return Result containing DOT format string
+    Result<std::string> try_to_graphviz() const {
+        char* dot = atree_to_graphviz(handle_);
+        if (!dot) {
+            return Result<std::string>::err("Failed to generate Graphviz output");
+        }
+
+        std::string result(dot);
+        atree_free_string(dot);
+        return Result<std::string>::ok(std::move(result));
+    }
+
+    /// @brief Export the schema and the subscriptions as a JSON document (throws on error)
+    /// @return JSON string that can be loaded with Tree::import_json()
+    /// @throws Error if export fails
+    std::string export_json() const {
+        char* json = atree_export_json(handle_);
+        if (!json) {
+            throw Error("Failed to export the A-Tree as JSON");
+        }
+
+        std::string result(json);
+        atree_free_string(json);
+        return result;
+    }
+
+    /// @brief Save a snapshot of the tree to a file, replacing it atomically (payloads excluded)
+    /// @param path Path of the snapshot; a temporary file is created in the same directory
+    /// @throws Error if the snapshot cannot be written
+    void save_file(const std::string& path) const {
+        AtreeResult result = atree_save_file(handle_, path.c_str());
+        if (!result.success) {
+            std::string error_msg = result.error_message;
+            atree_free_error(result.error_message);
+            throw Error(error_msg);
+        }
+    }
+
+    /// @brief Fingerprint of the snapshot saved by save_file(), independent of the insertion order
+    uint64_t fingerprint() const {
+        return atree_fingerprint(handle_);
+    }
+
+    /// @brief Write a snapshot to a sealed shared-memory file for the worker processes (Linux)
+    /// @return The descriptor of the file, to be closed by the caller
+    /// @throws Error if the file cannot be created
+    int share() const {
+        int fd = -1;
+        AtreeResult result = atree_share(handle_, &fd);
+        if (!result.success) {
+            std::string error_msg = result.error_message;
+            atree_free_error(result.error_message);
+            throw Error(error_msg);
+        }
+        return fd;
+    }
+
+    /// @brief Stream an export of the tree in chunks instead of copying the whole document
+    /// @param format ExportGraphviz or ExportJson
+    /// @param write Called with each chunk, in order, before this function returns; must not
+    ///              throw
+    /// @throws Error if export fails
+    void export_to(AtreeExportFormat format,
+                   const std::function<void(std::string_view)>& write) const {
+        AtreeResult result = atree_export(
+            handle_, format, &Tree::write_chunk,
+            const_cast<std::function<void(std::string_view)>*>(&write));
+        if (!result.success) {
+            std::string error_msg = result.error_message;
+            atree_free_error(result.error_message);
+            throw Error(error_msg);
+        }
+    }
+
+    /// @brief Export the cumulative counters of the tree as a JSON object (throws on error)
+    /// @return JSON string
+    /// @throws Error if export fails
+    std::string metrics_json() const {
+        char* json = atree_metrics_json(handle_);
+        if (!json) {
+            throw Error("Failed to export the metrics");
+        }
+
+        std::string result(json);
+        atree_free_string(json);
+        return result;
+    }
+};
+
+// ============================================================================
+// Concurrent Trees
+// ============================================================================
+
+/// @brief Tree pinned by ConcurrentTree::acquire(), valid until destroyed even if swapped out
+///
+/// Events must be built and searched with the same pinned tree.
+class PinnedTree {
+private:
+    friend class ConcurrentTree;
+    friend class Session;
+
+    Tree tree_;
+
+    explicit PinnedTree(const ATreeHandle* handle)
+        : tree_(const_cast<ATreeHandle*>(handle)) {}
+
+public:
+    ~PinnedTree() {
+        if (tree_.handle_) {
+            atree_concurrent_release(tree_.handle_);
+            tree_.handle_ = nullptr;
+        }
+    }
+
+    PinnedTree(const PinnedTree&) = delete;
+    PinnedTree& operator=(const PinnedTree&) = delete;
+
+    PinnedTree(PinnedTree&& other) noexcept = default;
+
+    PinnedTree& operator=(PinnedTree&& other) noexcept {
+        if (this != &other) {
+            if (tree_.handle_) {
+                atree_concurrent_release(tree_.handle_);
+            }
+            tree_.handle_ = other.tree_.handle_;
+            other.tree_.handle_ = nullptr;
+        }
+        return *this;
+    }
+
+    const Tree& operator*() const { return tree_; }
+    const Tree* operator->() const { return &tree_; }
+};
+
+/// @brief Tree shared by reader threads that can be replaced without stopping the searches
+class ConcurrentTree {
+private:
+    friend class Session;
+
+    AtreeConcurrentHandle* handle_;
+
+public:
+    /// @brief Publish a tree to the readers
+    /// @param tree Tree to publish, moved into the concurrent tree
+    explicit ConcurrentTree(Tree&& tree) : handle_(atree_concurrent_new(tree.handle_)) {
+        if (!handle_) {
+            throw Error("Failed to create the concurrent A-Tree");
+        }
+        tree.handle_ = nullptr;
+    }
+
+    /// @brief Destructor - the pinned trees remain valid until they are destroyed
+    ~ConcurrentTree() {
+        if (handle_) {
+            atree_concurrent_free(handle_);
+        }
+    }
+
+    ConcurrentTree(const ConcurrentTree&) = delete;
+    ConcurrentTree& operator=(const ConcurrentTree&) = delete;
+
+    ConcurrentTree(ConcurrentTree&& other) noexcept : handle_(other.handle_) {
+        other.handle_ = nullptr;
+    }
+
+    ConcurrentTree& operator=(ConcurrentTree&& other) noexcept {
+        if (this != &other) {
+            if (handle_) {
+                atree_concurrent_free(handle_);
+            }
+            handle_ = other.handle_;
+            other.handle_ = nullptr;
+        }
+        return *this;
+    }
+
+    /// @brief Pin the current tree to search it; safe to call from any thread
+    PinnedTree acquire() const {
+        return PinnedTree(atree_concurrent_acquire(handle_));
+    }
+
+    /// @brief Publish a new tree; the previous one is freed once the last reader releases it
+    /// @param tree Tree to publish, moved into the concurrent tree
+    void swap(Tree&& tree) {
+        AtreeResult result = atree_swap(handle_, tree.handle_);
+        if (!result.success) {
+            std::string error_msg = result.error_message;
+            atree_free_error(result.error_message);
+            throw Error(error_msg);
+        }
+        tree.handle_ = nullptr;
+    }
+};
+
+/// @brief Writer of a ConcurrentTree that searches its own writes before they are published
+///
+/// The session must be destroyed before its concurrent tree and used by one thread at a time.
+class Session {
+private:
+    AtreeSessionHandle* handle_;
+
+public:
+    /// @brief Create a session writing to the trees published by `concurrent`
+    explicit Session(const ConcurrentTree& concurrent)
+        : handle_(atree_session_new(concurrent.handle_)) {
+        if (!handle_) {
+            throw Error("Failed to create the A-Tree session");
+        }
+    }
+
+    /// @brief Destructor - drops the writes that were not published
+    ~Session() {
+        if (handle_) {
+            atree_session_free(handle_);
+        }
+    }
+
+    Session(const Session&) = delete;
+    Session& operator=(const Session&) = delete;
+
+    Session(Session&& other) noexcept : handle_(other.handle_) {
+        other.handle_ = nullptr;
+    }
+
+    Session& operator=(Session&& other) noexcept {
+        if (this != &other) {
+            if (handle_) {
+                atree_session_free(handle_);
+            }
+            handle_ = other.handle_;
+            other.handle_ = nullptr;
+        }
+        return *this;
+    }
+
+    /// @brief Insert an expression, searched by the session right away
+    /// @param subscription_id Unique ID for this subscription
+    /// @param expression Boolean expression string
+    void insert(uint64_t subscription_id, const std::string& expression) {
+        AtreeResult result = atree_session_insert(handle_, subscription_id, expression.c_str());
+        if (!result.success) {
+            std::string error_msg = result.error_message;
+            atree_free_error(result.error_message);
+            throw Error(error_msg);
+        }
+    }
+
+    /// @brief Delete a subscription by ID, searched by the session right away
+    void delete_subscription(uint64_t subscription_id) {
+        AtreeResult result = atree_session_delete(handle_, subscription_id);
+        if (!result.success) {
+            std::string error_msg = result.error_message;
+            atree_free_error(result.error_message);
+            throw Error(error_msg);
+        }
+    }
+
+    /// @brief Pin the published tree along with the writes of the session
+    /// @throws Error if the writes cannot be replayed on a tree published by another writer
+    PinnedTree acquire() {
+        const ATreeHandle* handle = atree_session_acquire(handle_);
+        if (!handle) {
+            throw Error("Failed to replay the writes of the session on the published tree");
+        }
+        return PinnedTree(handle);
+    }
+
+    /// @brief Publish the writes of the session to the readers of the concurrent tree
+    /// @throws Error if a write is rejected by a tree published by another writer; the writes
+    /// are kept until reset() is called
+    void publish() {
+        AtreeResult result = atree_session_publish(handle_);
+        if (!result.success) {
+            std::string error_msg = result.error_message;
+            atree_free_error(result.error_message);
+            throw Error(error_msg);
+        }
+    }
+
+    /// @brief Drop the writes that were not published
+    void reset() {
+        atree_session_reset(handle_);
+    }
+
+    /// @brief Get the number of writes that were not published
+    size_t pending() const {
+        return atree_session_pending(handle_);
+    }
+};
+
+// ============================================================================
+// Builder Pools
+// ============================================================================
+
+/// @brief Event builder checked out of a BuilderPool, checked back in when destroyed
+///
+/// Search it with the functions taking a const EventBuilder&, such as Tree::search_borrowed();
+/// a builder consumed by Tree::search() is not returned to the pool.
+class PooledBuilder {
+private:
+    friend class BuilderPool;
+
+    AtreeBuilderPool* pool_;
+    EventBuilder builder_;
+
+    PooledBuilder(AtreeBuilderPool* pool, void* builder) : pool_(pool), builder_(builder) {}
+
+public:
+    /// @brief Destructor - resets the builder and returns it to the pool
+    ~PooledBuilder() {
+        if (builder_.builder_ && !builder_.consumed_) {
+            atree_builder_pool_checkin(pool_, builder_.release());
+        }
+    }
+
+    PooledBuilder(const PooledBuilder&) = delete;
+    PooledBuilder& operator=(const PooledBuilder&) = delete;
+
+    PooledBuilder(PooledBuilder&& other) noexcept = default;
+    PooledBuilder& operator=(PooledBuilder&&) = delete;
+
+    EventBuilder& operator*() { return builder_; }
+    EventBuilder* operator->() { return &builder_; }
+};
+
+/// @brief Event builders reused across the requests of a multithreaded server
+///
+/// The pool must be destroyed before its tree, and after every builder was checked back in.
+/// The tree must not be modified while builders are checked out.
+class BuilderPool {
+private:
+    AtreeBuilderPool* pool_;
+
+public:
+    /// @brief Create a pool with `size` builders created upfront
+    BuilderPool(const Tree& tree, size_t size)
+        : pool_(atree_builder_pool_new(tree.handle_, size)) {
+        if (!pool_) {
+            throw Error("Failed to create the builder pool");
+        }
+    }
+
+    /// @brief Destructor - frees the pooled builders
+    ~BuilderPool() {
+        if (pool_) {
+            atree_builder_pool_free(pool_);
+        }
+    }
+
+    BuilderPool(const BuilderPool&) = delete;
+    BuilderPool& operator=(const BuilderPool&) = delete;
+
+    BuilderPool(BuilderPool&& other) noexcept : pool_(other.pool_) {
+        other.pool_ = nullptr;
+    }
+
+    BuilderPool& operator=(BuilderPool&& other) noexcept {
+        if (this != &other) {
+            if (pool_) {
+                atree_builder_pool_free(pool_);
+            }
+            pool_ = other.pool_;
+            other.pool_ = nullptr;
+        }
+        return *this;
+    }
+
+    /// @brief Check an empty builder out of the pool; safe to call from any thread
+    PooledBuilder checkout() const {
+        return PooledBuilder(pool_, atree_builder_pool_checkout(pool_));
+    }
+};
+
+// ============================================================================
+// Alternative ID Types
+// ============================================================================
+
+namespace detail {
+
+inline std::vector<AtreeAttributeDef> c_definitions(
+    const std::vector<AttributeDefinition>& definitions) {
+    std::vector<AtreeAttributeDef> c_defs;
+    c_defs.reserve(definitions.size());
+    for (const auto& def : definitions) {
+        c_defs.push_back({def.name.c_str(), static_cast<AtreeAttributeType>(def.type)});
+    }
+    return c_defs;
+}
+
+inline void check(AtreeResult result) {
+    if (!result.success) {
+        std::string error_msg = result.error_message;
+        atree_free_error(result.error_message);
+        throw Error(error_msg);
+    }
+}
+
+} // namespace detail
+
+/// @brief A-Tree whose subscription IDs are 32-bit integers
+class U32Tree {
+private:
+    ATreeU32Handle* handle_;
+
+public:
+    /// @brief Create a new A-Tree with the given attribute definitions
+    /// @throws Error if creation fails
+    explicit U32Tree(const std::vector<AttributeDefinition>& definitions) {
+        auto c_defs = detail::c_definitions(definitions);
+        handle_ = atree_new_u32_ids(c_defs.data(), c_defs.size());
+        if (!handle_) {
+            throw Error("Failed to create A-Tree");
+        }
+    }
+
+    ~U32Tree() {
+        if (handle_) {
+            atree_u32_free(handle_);
+        }
+    }
+
+    U32Tree(const U32Tree&) = delete;
+    U32Tree& operator=(const U32Tree&) = delete;
+
+    U32Tree(U32Tree&& other) noexcept : handle_(other.handle_) {
+        other.handle_ = nullptr;
+    }
+
+    U32Tree& operator=(U32Tree&& other) noexcept {
+        if (this != &other) {
+            if (handle_) {
+                atree_u32_free(handle_);
+            }
+            handle_ = other.handle_;
+            other.handle_ = nullptr;
+        }
+        return *this;
+    }
+
+    /// @brief Insert a boolean expression (throws on error)
+    /// @throws Error if insertion fails
+    void insert(uint32_t subscription_id, const std::string& expression) {
+        detail::check(atree_u32_insert(handle_, subscription_id, expression.c_str()));
+    }
+
+    /// @brief Delete a subscription by ID
+    void delete_subscription(uint32_t subscription_id) {
+        atree_u32_delete(handle_, subscription_id);
+    }
+
+    /// @brief Create a new event builder
+    EventBuilder make_event() const {
+        return EventBuilder(atree_u32_event_builder_new(handle_));
+    }
+
+    /// @brief Search for expressions
     /// @param builder EventBuilder containing the event data (consumed by this call)
-    /// @return Result containing vector of matching subscription IDs
-    Result<std::vector<uint64_t>> try_search(EventBuilder&& builder) const {
-        return try_search(builder);
+    std::vector<uint32_t> search(EventBuilder& builder) const {
+        AtreeU32SearchResult result = atree_u32_search(handle_, builder.release());
+
+        std::vector<uint32_t> matches;
+        if (result.ids != nullptr && result.count > 0) {
+            matches.assign(result.ids, result.ids + result.count);
+            atree_u32_search_result_free(result);
+        }
+        return matches;
     }
+};
 
-    /// @brief Export the tree structure as Graphviz DOT format (throws on error)
-    /// @return DOT format string
-    /// @throws Error if export fails
-    std::string to_graphviz() const {
-        char* dot = atree_to_graphviz(handle_);
-        if (!dot) {
-            throw Error("Failed to generate Graphviz output");
+/// @brief A-Tree whose subscription IDs are strings (UUIDs, keys, ...)
+class StrTree {
+private:
+    ATreeStrHandle* handle_;
+
+public:
+    /// @brief Create a new A-Tree with the given attribute definitions
+    /// @throws Error if creation fails
+    explicit StrTree(const std::vector<AttributeDefinition>& definitions) {
+        auto c_defs = detail::c_definitions(definitions);
+        handle_ = atree_new_str_ids(c_defs.data(), c_defs.size());
+        if (!handle_) {
+            throw Error("Failed to create A-Tree");
         }
+    }
 
-        std::string result(dot);
-        atree_free_string(dot);
-        return result;
+    ~StrTree() {
+        if (handle_) {
+            atree_str_free(handle_);
+        }
     }
 
-    /// @brief Export the tree structure as Graphviz DOT format (returns Result)
-    /// @return Result containing DOT format string
-    Result<std::string> try_to_graphviz() const {
-        char* dot = atree_to_graphviz(handle_);
-        if (!dot) {
-            return Result<std::string>::err("Failed to generate Graphviz output");
+    StrTree(const StrTree&) = delete;
+    StrTree& operator=(const StrTree&) = delete;
+
+    StrTree(StrTree&& other) noexcept : handle_(other.handle_) {
+        other.handle_ = nullptr;
+    }
+
+    StrTree& operator=(StrTree&& other) noexcept {
+        if (this != &other) {
+            if (handle_) {
+                atree_str_free(handle_);
+            }
+            handle_ = other.handle_;
+            other.handle_ = nullptr;
         }
+        return *this;
+    }
 
-        std::string result(dot);
-        atree_free_string(dot);
-        return Result<std::string>::ok(std::move(result));
+    /// @brief Insert a boolean expression (throws on error)
+    /// @throws Error if insertion fails
+    void insert(const std::string& subscription_id, const std::string& expression) {
+        detail::check(atree_str_insert(handle_, subscription_id.c_str(), expression.c_str()));
+    }
+
+    /// @brief Delete a subscription by ID
+    void delete_subscription(const std::string& subscription_id) {
+        atree_str_delete(handle_, subscription_id.c_str());
+    }
+
+    /// @brief Create a new event builder
+    EventBuilder make_event() const {
+        return EventBuilder(atree_str_event_builder_new(handle_));
+    }
+
+    /// @brief Search for expressions
+    /// @param builder EventBuilder containing the event data (consumed by this call)
+    /// @return Views of the IDs owned by the tree, valid until their subscription is deleted
+    std::vector<std::string_view> search(EventBuilder& builder) const {
+        AtreeStrSearchResult result = atree_str_search(handle_, builder.release());
+
+        std::vector<std::string_view> matches;
+        if (result.ids != nullptr && result.count > 0) {
+            matches.assign(result.ids, result.ids + result.count);
+            atree_str_search_result_free(result);
+        }
+        return matches;
     }
 };
 
@@ -607,12 +2221,12 @@ public:
 // ============================================================================
 
 inline Tree TreeBuilder::build() && {
-    return Tree(std::move(definitions_));
+    return Tree(definitions_, config_);
 }
 
 inline Result<Tree> TreeBuilder::try_build() && {
     try {
-        return Result<Tree>::ok(Tree(std::move(definitions_)));
+        return Result<Tree>::ok(Tree(definitions_, config_));
     } catch (const Error& e) {
         return Result<Tree>::err(e.what());
     }
@@ -1,16 +1,624 @@
 use std::env;
+use std::fs;
 use std::path::PathBuf;
 
+/// Prefix added to the exported functions, e.g. `myco_` for `myco_atree_new()`
+const SYMBOL_PREFIX: &str = "ATREE_SYMBOL_PREFIX";
+
 fn main() {
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed={SYMBOL_PREFIX}");
+
     let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
-    let output_file = PathBuf::from(&crate_dir).join("atree.h");
+    if env::var_os("CARGO_FEATURE_PYTHON").is_some() {
+        write_python_declarations(&crate_dir);
+    }
+    if env::var_os("CARGO_FEATURE_DOTNET").is_some() {
+        write_dotnet_interop(&crate_dir);
+    }
+    if env::var_os("CARGO_FEATURE_GO").is_some() {
+        write_go_constants(&crate_dir);
+    }
+    if env::var_os("CARGO_FEATURE_SWIFT").is_some() {
+        write_module_map(&crate_dir);
+    }
+    write_c_header(&crate_dir);
+}
 
-    cbindgen::Builder::new()
+/// Generate `atree.h`, with the aliases of the prefixed symbols when a prefix is set.
+fn write_c_header(crate_dir: &str) {
+    let output_file = PathBuf::from(crate_dir).join("atree.h");
+
+    let bindings = cbindgen::Builder::new()
         .with_crate(crate_dir)
         .with_language(cbindgen::Language::C)
         .with_documentation(true)
         .with_include_guard("ATREE_H")
         .generate()
-        .expect("Unable to generate C bindings")
-        .write_to_file(output_file);
+        .expect("Unable to generate C bindings");
+
+    let symbols_file = PathBuf::from(crate_dir).join("atree.syms");
+    let prefix = env::var(SYMBOL_PREFIX).unwrap_or_default();
+    if prefix.is_empty() {
+        bindings.write_to_file(output_file);
+        let _ = fs::remove_file(symbols_file);
+        return;
+    }
+    assert!(
+        is_identifier(&prefix),
+        "{SYMBOL_PREFIX} must be a valid C identifier, got {prefix:?}"
+    );
+
+    let mut header = vec![];
+    bindings.write(&mut header);
+    let header = String::from_utf8(header).expect("The C bindings are not valid UTF-8");
+    let functions = exported_functions(&header);
+
+    // Map the names used by the callers (and by atree.hpp) to the prefixed symbols.
+    let aliases: String = functions
+        .iter()
+        .map(|function| format!("#define {function} {prefix}{function}\n"))
+        .collect();
+    let (includes, declarations) = header
+        .split_once("\n\n/**")
+        .expect("The C bindings have no declarations");
+    let header = format!(
+        "{includes}\n\n/* Symbols exported with the {prefix:?} prefix */\n{aliases}\n/**{declarations}"
+    );
+    fs::write(&output_file, header).expect("Unable to write the C bindings");
+
+    // Renaming of the symbols of the static library with `objcopy --redefine-syms`
+    let renames: String = functions
+        .iter()
+        .map(|function| format!("{function} {prefix}{function}\n"))
+        .collect();
+    fs::write(symbols_file, renames).expect("Unable to write the symbol renames");
+}
+
+/// Generate the declarations of `atree.h` for the Python bindings: `atree.pxd` for Cython and
+/// `atree.cdef` for `cffi.FFI.cdef()`, which accepts no preprocessor directive but the integer
+/// constants.
+fn write_python_declarations(crate_dir: &str) {
+    let mut config = cbindgen::Config {
+        language: cbindgen::Language::Cython,
+        ..Default::default()
+    };
+    config.cython.header = Some("\"atree.h\"".to_owned());
+    cbindgen::Builder::new()
+        .with_crate(crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("Unable to generate Cython bindings")
+        .write_to_file(PathBuf::from(crate_dir).join("atree.pxd"));
+
+    let mut declarations = vec![];
+    cbindgen::Builder::new()
+        .with_crate(crate_dir)
+        .with_language(cbindgen::Language::C)
+        .with_no_includes()
+        .with_documentation(false)
+        .generate()
+        .expect("Unable to generate cffi bindings")
+        .write(&mut declarations);
+    let declarations = String::from_utf8(declarations).expect("The C bindings are not valid UTF-8");
+    let cdef: String = declarations
+        .lines()
+        .filter(|line| !line.starts_with('#') || is_integer_constant(line))
+        .map(|line| format!("{line}\n"))
+        .collect();
+    fs::write(PathBuf::from(crate_dir).join("atree.cdef"), cdef)
+        .expect("Unable to write the cffi bindings");
+}
+
+/// Generate `go/atree/constants.go` with the status codes of the C API, their sentinel errors
+/// and the ABI version, so that the Go package follows the header.
+fn write_go_constants(crate_dir: &str) {
+    let mut header = vec![];
+    cbindgen::Builder::new()
+        .with_crate(crate_dir)
+        .with_language(cbindgen::Language::C)
+        .with_no_includes()
+        .with_documentation(true)
+        .generate()
+        .expect("Unable to generate Go bindings")
+        .write(&mut header);
+    let header = String::from_utf8(header).expect("The C bindings are not valid UTF-8");
+
+    // Integer constants along with the first line of their documentation
+    let mut constants = vec![];
+    let mut documentation = None;
+    for line in header.lines() {
+        if line == "/**" {
+            documentation = None;
+        } else if let Some(text) = line.strip_prefix(" * ") {
+            documentation.get_or_insert(text);
+        } else if is_integer_constant(line) {
+            let tokens: Vec<_> = line.split_whitespace().collect();
+            constants.push((tokens[1], tokens[2], documentation.unwrap_or_default()));
+        }
+    }
+
+    let mut statuses = String::new();
+    let mut errors = String::new();
+    let mut abi_version = "0";
+    for (name, value, documentation) in constants {
+        let Some(status) = name
+            .strip_prefix("ATREE_ERROR_")
+            .or(name.strip_prefix("ATREE_").filter(|status| *status == "OK"))
+        else {
+            if name == "ATREE_ABI_VERSION" {
+                abi_version = value;
+            }
+            continue;
+        };
+        let status = go_name(status);
+        let meaning = lowercase_first(documentation.trim_end_matches('.'));
+        // Separated by blank lines so that gofmt aligns nothing
+        statuses.push_str(&format!(
+            "\n\t// Status{status} means {meaning}.\n\tStatus{status} Status = {value}\n"
+        ));
+        if name != "ATREE_OK" {
+            errors.push_str(&format!(
+                "\n\t// Err{status} matches the errors with Status{status}.\n\tErr{status} = newError(Status{status})\n"
+            ));
+        }
+    }
+    let statuses = statuses.trim_start_matches('\n');
+    let errors = errors.trim_start_matches('\n');
+
+    let code = format!(
+        "// Code generated by build.rs with the go feature. DO NOT EDIT.\n\n\
+         package atree\n\n\
+         // ABIVersion is the version of the ABI of the C API the package was generated for.\n\
+         const ABIVersion = {abi_version}\n\n\
+         // Status is a status code returned by the C API.\n\
+         type Status int32\n\n\
+         // Status codes of the C API\n\
+         const (\n{statuses})\n\n\
+         // Sentinel errors of the status codes, to match with errors.Is()\n\
+         var (\n{errors})\n"
+    );
+    fs::write(PathBuf::from(crate_dir).join("go/atree/constants.go"), code)
+        .expect("Unable to write the Go constants");
+}
+
+/// Convert a name like `INVALID_UTF8` to `InvalidUTF8`, keeping the initialisms in uppercase.
+fn go_name(name: &str) -> String {
+    name.split('_')
+        .map(|word| match word {
+            "ID" | "OK" | "UTF8" => word.to_owned(),
+            _ => {
+                let word = word.to_ascii_lowercase();
+                word[..1].to_ascii_uppercase() + &word[1..]
+            }
+        })
+        .collect()
+}
+
+fn lowercase_first(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map_or(String::new(), |first| {
+        first.to_lowercase().chain(chars).collect()
+    })
+}
+
+/// Generate the module map of the C API for the Swift package, which imports it as `CATree`.
+///
+/// The Swift importer ignores the aliases of `ATREE_SYMBOL_PREFIX`, so the module always links
+/// the library with the unprefixed symbols.
+fn write_module_map(crate_dir: &str) {
+    let directory = PathBuf::from(crate_dir).join("swift/Sources/CATree");
+    fs::create_dir_all(&directory).expect("Unable to create the Swift module directory");
+    let module_map =
+        "module CATree {\n    header \"../../../atree.h\"\n    link \"a_tree_ffi\"\n    export *\n}\n";
+    fs::write(directory.join("module.modulemap"), module_map)
+        .expect("Unable to write the Swift module map");
+}
+
+/// Generate `ATree.Interop.cs`, the P/Invoke declarations of the C API for .NET.
+///
+/// The functions taking or returning structs by value are left out, as their marshaling differs
+/// between the platforms; their `*2()` variants return a status code instead.
+fn write_dotnet_interop(crate_dir: &str) {
+    let mut declarations = vec![];
+    cbindgen::Builder::new()
+        .with_crate(crate_dir)
+        .with_language(cbindgen::Language::C)
+        .with_no_includes()
+        .with_documentation(false)
+        .generate()
+        .expect("Unable to generate .NET bindings")
+        .write(&mut declarations);
+    let declarations = String::from_utf8(declarations).expect("The C bindings are not valid UTF-8");
+
+    let mut interop = Interop::default();
+    for line in declarations
+        .lines()
+        .filter(|line| is_integer_constant(line))
+    {
+        let tokens: Vec<_> = line.split_whitespace().collect();
+        interop
+            .constants
+            .push((tokens[1].to_owned(), tokens[2].to_owned()));
+    }
+    let code: String = declarations
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join(" ");
+    for statement in statements(&code) {
+        interop.add(&statement);
+    }
+    fs::write(
+        PathBuf::from(crate_dir).join("ATree.Interop.cs"),
+        interop.render(),
+    )
+    .expect("Unable to write the .NET bindings");
+}
+
+/// Split C declarations at the semicolons outside of the braces, collapsing the whitespace.
+fn statements(code: &str) -> Vec<String> {
+    let mut statements = vec![];
+    let (mut depth, mut start) = (0, 0);
+    for (index, char) in code.char_indices() {
+        match char {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ';' if depth == 0 => {
+                let statement = code[start..index].split_whitespace().collect::<Vec<_>>();
+                if !statement.is_empty() {
+                    statements.push(statement.join(" "));
+                }
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    statements
+}
+
+/// C type of a declaration, e.g. `const char *const *` is `char` at depth 2 with a const pointee
+struct CType {
+    base: String,
+    depth: usize,
+    const_pointee: bool,
+}
+
+/// Split a declaration like `const char *name` into its type and its name.
+fn declaration(text: &str) -> (CType, String) {
+    let text = text.trim();
+    let split = text
+        .rfind(|char: char| !(char.is_ascii_alphanumeric() || char == '_'))
+        .map_or(0, |index| index + 1);
+    let (ty, name) = text.split_at(split);
+    let pointee: Vec<_> = ty
+        .split('*')
+        .next()
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect();
+    let ty = CType {
+        base: pointee
+            .iter()
+            .rev()
+            .find(|token| !matches!(**token, "const" | "struct" | "enum"))
+            .map_or(String::new(), |base| (*base).to_owned()),
+        depth: ty.matches('*').count(),
+        const_pointee: pointee.contains(&"const"),
+    };
+    (ty, name.to_owned())
+}
+
+fn primitive(base: &str) -> Option<&'static str> {
+    Some(match base {
+        "bool" => "bool",
+        "char" | "uint8_t" => "byte",
+        "int8_t" => "sbyte",
+        "int16_t" => "short",
+        "uint16_t" => "ushort",
+        "int32_t" | "int" => "int",
+        "uint32_t" => "uint",
+        "int64_t" => "long",
+        "uint64_t" => "ulong",
+        "intptr_t" => "IntPtr",
+        "uintptr_t" => "UIntPtr",
+        "float" => "float",
+        "double" => "double",
+        _ => return None,
+    })
+}
+
+/// Escape the C names that are C# keywords.
+fn identifier(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "base", "bool", "byte", "char", "checked", "class", "const", "default", "delegate",
+        "double", "event", "fixed", "float", "in", "int", "internal", "is", "lock", "long",
+        "object", "operator", "out", "params", "ref", "short", "string", "uint", "ulong",
+    ];
+    if KEYWORDS.contains(&name) {
+        format!("@{name}")
+    } else {
+        name.to_owned()
+    }
+}
+
+const MARSHAL_BOOL: &str = "[MarshalAs(UnmanagedType.U1)] ";
+
+/// P/Invoke declarations collected from the C declarations
+#[derive(Default)]
+struct Interop {
+    constants: Vec<(String, String)>,
+    enums: Vec<String>,
+    structs: Vec<String>,
+    delegates: Vec<String>,
+    functions: Vec<String>,
+    skipped: Vec<String>,
+    enum_names: Vec<String>,
+    struct_names: Vec<String>,
+    delegate_names: Vec<String>,
+}
+
+impl Interop {
+    fn add(&mut self, statement: &str) {
+        if let Some(rest) = statement.strip_prefix("typedef enum ") {
+            let (name, body) = rest.split_once(" {").expect("Unexpected enum declaration");
+            let body = body.rsplit_once('}').map_or(body, |(body, _)| body);
+            let variants: Vec<_> = body
+                .split(',')
+                .map(str::trim)
+                .filter(|variant| !variant.is_empty())
+                .map(|variant| match variant.split_once(" = ") {
+                    Some((name, value)) => format!("        {} = {value},", identifier(name)),
+                    None => format!("        {},", identifier(variant)),
+                })
+                .collect();
+            self.enums.push(format!(
+                "    public enum {name}\n    {{\n{}\n    }}\n",
+                variants.join("\n")
+            ));
+            self.enum_names.push(name.to_owned());
+        } else if let Some(rest) = statement.strip_prefix("typedef struct ") {
+            // Opaque handles are passed as IntPtr.
+            let Some((name, body)) = rest.split_once(" {") else {
+                return;
+            };
+            let body = body.rsplit_once('}').map_or(body, |(body, _)| body);
+            let fields: Vec<_> = body
+                .split(';')
+                .filter(|field| !field.trim().is_empty())
+                .map(|field| {
+                    let (ty, field) = declaration(field);
+                    let (attribute, ty) = self.field_type(&ty);
+                    format!("        {attribute}public {ty} {};", identifier(&field))
+                })
+                .collect();
+            self.structs.push(format!(
+                "    [StructLayout(LayoutKind.Sequential)]\n    public struct {name}\n    {{\n{}\n    }}\n",
+                fields.join("\n")
+            ));
+            self.struct_names.push(name.to_owned());
+        } else if let Some(rest) = statement.strip_prefix("typedef ") {
+            // Function pointers, e.g. `void (*AtreeLogCallback)(enum AtreeLogLevel level, ...)`
+            let (returns, rest) = rest.split_once("(*").expect("Unexpected typedef");
+            let (name, parameters) = rest.split_once(")(").expect("Unexpected typedef");
+            let parameters = parameters.strip_suffix(')').unwrap_or(parameters);
+            let signature = self.signature(returns, parameters, true);
+            if let Some((_, returns, parameters)) = signature {
+                self.delegates.push(format!(
+                    "    [UnmanagedFunctionPointer(CallingConvention.Cdecl)]\n    public delegate {returns} {name}({parameters});\n"
+                ));
+                self.delegate_names.push(name.to_owned());
+            }
+        } else {
+            let (head, parameters) = statement.split_once('(').expect("Unexpected declaration");
+            let parameters = parameters.strip_suffix(')').unwrap_or(parameters);
+            let (_, name) = declaration(head);
+            let returns = head[..head.len() - name.len()].to_owned();
+            match self.signature(&returns, parameters, false) {
+                Some((attribute, returns, parameters)) => self.functions.push(format!(
+                    "        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]\n{attribute}        public static extern {returns} {name}({parameters});\n"
+                )),
+                None => self.skipped.push(name),
+            }
+        }
+    }
+
+    /// Map a C signature to its C# return attribute, return type and parameters, or `None` if a
+    /// struct is passed by value.
+    fn signature(
+        &self,
+        returns: &str,
+        parameters: &str,
+        callback: bool,
+    ) -> Option<(String, String, String)> {
+        let (returns, _) = declaration(&format!("{returns} _"));
+        let (attribute, returns) = match (returns.depth, returns.base.as_str()) {
+            (0, "void") => (String::new(), "void".to_owned()),
+            (0, "bool") if !callback => (
+                "        [return: MarshalAs(UnmanagedType.U1)]\n".to_owned(),
+                "bool".to_owned(),
+            ),
+            (0, base) => (String::new(), self.value_type(base)?),
+            _ => (String::new(), "IntPtr".to_owned()),
+        };
+
+        let parameters: Vec<_> = parameters
+            .split(',')
+            .map(str::trim)
+            .filter(|parameter| !parameter.is_empty() && *parameter != "void")
+            .map(declaration)
+            .collect();
+        let mut mapped = Vec::with_capacity(parameters.len());
+        for (index, (ty, name)) in parameters.iter().enumerate() {
+            let next = parameters.get(index + 1).map(|(_, next)| next.as_str());
+            let ty = if callback {
+                self.callback_parameter(ty)?
+            } else {
+                self.parameter(ty, name, next)?
+            };
+            mapped.push(format!("{ty} {}", identifier(name)));
+        }
+        Some((attribute, returns, mapped.join(", ")))
+    }
+
+    /// C# type of a value passed by value, which cannot be a struct
+    fn value_type(&self, base: &str) -> Option<String> {
+        if let Some(primitive) = primitive(base) {
+            return Some(primitive.to_owned());
+        }
+        let known = self.enum_names.iter().chain(&self.delegate_names);
+        known
+            .into_iter()
+            .any(|name| name == base)
+            .then(|| base.to_owned())
+    }
+
+    /// C# attribute and type of a struct field
+    fn field_type(&self, ty: &CType) -> (&'static str, String) {
+        match (ty.depth, ty.base.as_str()) {
+            (0, "bool") => (MARSHAL_BOOL, "bool".to_owned()),
+            (0, base) if self.struct_names.iter().any(|name| name == base) => ("", base.to_owned()),
+            (0, base) => (
+                "",
+                self.value_type(base).unwrap_or_else(|| "IntPtr".to_owned()),
+            ),
+            _ => ("", "IntPtr".to_owned()),
+        }
+    }
+
+    /// The callbacks receive every pointer as IntPtr, as their strings may not be
+    /// null-terminated.
+    fn callback_parameter(&self, ty: &CType) -> Option<String> {
+        match (ty.depth, ty.base.as_str()) {
+            (0, "bool") => Some(format!("{MARSHAL_BOOL}bool")),
+            (0, base) => self.value_type(base),
+            _ => Some("IntPtr".to_owned()),
+        }
+    }
+
+    fn parameter(&self, ty: &CType, name: &str, next: Option<&str>) -> Option<String> {
+        let is_struct = self.struct_names.contains(&ty.base);
+        let element = || {
+            if is_struct || self.enum_names.contains(&ty.base) {
+                Some(ty.base.clone())
+            } else {
+                primitive(&ty.base).map(str::to_owned)
+            }
+        };
+        let is_out = name == "out" || name == "required" || name.starts_with("out_");
+        let has_length = next.is_some_and(|next| next == "len" || next.ends_with("_len"));
+        Some(match (ty.depth, ty.base.as_str()) {
+            (0, "bool") => format!("{MARSHAL_BOOL}bool"),
+            (0, base) => self.value_type(base)?,
+            // Strings of a given length in bytes, e.g. `Encoding.UTF8.GetBytes()`
+            (1, "char") if ty.const_pointee && has_length => "byte[]".to_owned(),
+            (1, "char") if ty.const_pointee => "[MarshalAs(UnmanagedType.LPUTF8Str)] string".to_owned(),
+            (1, "uint16_t") if ty.const_pointee => "[MarshalAs(UnmanagedType.LPWStr)] string".to_owned(),
+            (1, "void") => "IntPtr".to_owned(),
+            // Buffers filled by the call, followed by their capacity
+            (1, _) if !ty.const_pointee && next == Some("capacity") => match element() {
+                Some(element) => format!("[Out] {element}[]"),
+                None => "IntPtr".to_owned(),
+            },
+            (1, "bool") if is_out => format!("{MARSHAL_BOOL}out bool"),
+            (1, _) if is_out => match element() {
+                Some(element) => format!("out {element}"),
+                None => "IntPtr".to_owned(),
+            },
+            (1, _) if ty.const_pointee => match element() {
+                Some(element) => format!("{element}[]"),
+                None => "IntPtr".to_owned(),
+            },
+            (2, "char") if ty.const_pointee => {
+                "[MarshalAs(UnmanagedType.LPArray, ArraySubType = UnmanagedType.LPUTF8Str)] string[]"
+                    .to_owned()
+            }
+            (2, "uint16_t") if ty.const_pointee => {
+                "[MarshalAs(UnmanagedType.LPArray, ArraySubType = UnmanagedType.LPWStr)] string[]"
+                    .to_owned()
+            }
+            // Arrays of strings filled by the call, e.g. the errors of `atree_insert_many()`
+            (2, "char") => "[Out] IntPtr[]".to_owned(),
+            (2, _) if is_out => "out IntPtr".to_owned(),
+            (2, _) => "IntPtr[]".to_owned(),
+            _ => "IntPtr".to_owned(),
+        })
+    }
+
+    fn render(&self) -> String {
+        let constants: String = self
+            .constants
+            .iter()
+            .map(|(name, value)| {
+                let ty = if value.parse::<i32>().is_ok() {
+                    "int"
+                } else {
+                    "long"
+                };
+                format!("        public const {ty} {name} = {value};\n")
+            })
+            .collect();
+        let mut skipped = String::from("//");
+        for name in &self.skipped {
+            if skipped.rsplit('\n').next().unwrap_or_default().len() + name.len() > 96 {
+                skipped.push_str("\n//");
+            }
+            skipped.push_str(&format!(" {name},"));
+        }
+        skipped.pop();
+        format!(
+            "// P/Invoke declarations of the a-tree C API, generated by build.rs with the dotnet\n\
+             // feature. Do not edit.\n\
+             //\n\
+             // Not declared, as they take or return structs by value:\n\
+             {skipped}\n\
+             \n\
+             using System;\n\
+             using System.Runtime.InteropServices;\n\
+             \n\
+             namespace ATree.Interop\n\
+             {{\n\
+             {}\n\
+             {}\n\
+             {}\n    public static partial class NativeMethods\n    {{\n        public const string Library = \"a_tree_ffi\";\n\n{constants}\n{}    }}\n}}\n",
+            self.enums.join("\n"),
+            self.structs.join("\n"),
+            self.delegates.join("\n"),
+            self.functions.join("\n"),
+        )
+    }
+}
+
+fn is_integer_constant(line: &str) -> bool {
+    match line
+        .strip_prefix("#define ")
+        .map(|rest| rest.split_whitespace().collect::<Vec<_>>())
+    {
+        Some(tokens) => tokens.len() == 2 && tokens[1].parse::<i64>().is_ok(),
+        None => false,
+    }
+}
+
+fn is_identifier(value: &str) -> bool {
+    let mut chars = value.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|next| next.is_ascii_alphanumeric() || next == '_')
+}
+
+/// Collect the names of the functions declared in the generated header.
+fn exported_functions(header: &str) -> Vec<&str> {
+    let mut functions: Vec<_> = header
+        .lines()
+        .filter(|line| !line.starts_with(['/', ' ', '#']))
+        .filter_map(|line| {
+            let name = line.split('(').next()?.rsplit([' ', '*']).next()?;
+            (name.starts_with("atree_") && line.contains('(')).then_some(name)
+        })
+        .collect();
+    functions.sort_unstable();
+    functions.dedup();
+    functions
 }
@@ -0,0 +1,266 @@
+// Package atree wraps the C API of the a-tree library, which indexes the boolean expressions of
+// subscriptions and searches the ones matching an event.
+//
+// Build the library and generate constants.go first, from the a-tree-ffi directory:
+//
+//	cargo build --release --features go
+package atree
+
+/*
+#cgo CFLAGS: -I${SRCDIR}/../..
+#cgo LDFLAGS: -L${SRCDIR}/../../target/release -la_tree_ffi
+#include <stdlib.h>
+#include "atree.h"
+*/
+import "C"
+
+import (
+	"errors"
+	"fmt"
+	"runtime"
+	"sync"
+	"unsafe"
+)
+
+// Kind is the type of an attribute of the events.
+type Kind int
+
+// Kinds of attributes
+const (
+	Boolean     Kind = C.Boolean
+	Integer     Kind = C.Integer
+	Float       Kind = C.Float
+	String      Kind = C.String
+	StringList  Kind = C.StringList
+	IntegerList Kind = C.IntegerList
+)
+
+// Attribute defines an attribute of the events.
+type Attribute struct {
+	Name string
+	Kind Kind
+}
+
+// Event maps the names of the attributes to their values: bool, int, int64, float64, string,
+// []int64, []string or nil for an undefined value. The attributes it does not set are undefined.
+type Event map[string]any
+
+// ErrClosed is returned by the methods of a closed tree.
+var ErrClosed = errors.New("atree: the tree is closed")
+
+// Error is a failure reported with a status code by the C API.
+type Error struct {
+	Status  Status
+	Message string
+}
+
+func (e *Error) Error() string {
+	return "atree: " + e.Message
+}
+
+// Is reports whether target is the sentinel error of the same status, e.g. ErrParse.
+func (e *Error) Is(target error) bool {
+	other, ok := target.(*Error)
+	return ok && other.Status == e.Status
+}
+
+// ParseError describes where an expression is invalid; it matches ErrParse.
+type ParseError struct {
+	Message string
+
+	// Line and Column of the invalid token, starting at 1
+	Line, Column int
+
+	// Offset and Length of the invalid token in bytes
+	Offset, Length int
+}
+
+func (e *ParseError) Error() string {
+	return fmt.Sprintf("atree: %s at line %d, column %d", e.Message, e.Line, e.Column)
+}
+
+// Is reports whether target is ErrParse.
+func (e *ParseError) Is(target error) bool {
+	return target == ErrParse
+}
+
+func newError(status Status) *Error {
+	message := C.GoString(C.atree_status_message(C.int32_t(status)))
+	return &Error{Status: status, Message: message}
+}
+
+func check(status C.int32_t) error {
+	if status == C.ATREE_OK {
+		return nil
+	}
+	return newError(Status(status))
+}
+
+// Tree indexes the boolean expressions of the subscriptions.
+//
+// The searches can run on many goroutines at once, while the insertions and the deletions are
+// exclusive. The tree is freed by Close, or by a finalizer once it is unreachable.
+type Tree struct {
+	mu     sync.RWMutex
+	handle *C.ATreeHandle
+}
+
+// New creates an empty tree for events with the given attributes.
+func New(attributes ...Attribute) (*Tree, error) {
+	definitions := make([]C.AtreeAttributeDef, len(attributes))
+	for i, attribute := range attributes {
+		name := C.CString(attribute.Name)
+		defer C.free(unsafe.Pointer(name))
+		definitions[i] = C.AtreeAttributeDef{
+			name:      name,
+			attr_type: C.enum_AtreeAttributeType(attribute.Kind),
+		}
+	}
+
+	var first *C.AtreeAttributeDef
+	if len(definitions) > 0 {
+		first = &definitions[0]
+	}
+	var handle *C.ATreeHandle
+	if err := check(C.atree_new2(first, C.uintptr_t(len(definitions)), &handle)); err != nil {
+		return nil, err
+	}
+
+	tree := &Tree{handle: handle}
+	runtime.SetFinalizer(tree, (*Tree).Close)
+	return tree, nil
+}
+
+// Close frees the tree; the other methods return ErrClosed afterwards.
+func (t *Tree) Close() error {
+	t.mu.Lock()
+	defer t.mu.Unlock()
+	if t.handle != nil {
+		C.atree_free(t.handle)
+		t.handle = nil
+		runtime.SetFinalizer(t, nil)
+	}
+	return nil
+}
+
+// Insert adds the expression of a subscription, e.g. `price > 100 and country in ["CA"]`.
+//
+// An invalid expression returns a *ParseError, and an ID already present in a tree that rejects
+// the duplicates returns ErrDuplicateID.
+func (t *Tree) Insert(id uint64, expression string) error {
+	cexpression := C.CString(expression)
+	defer C.free(unsafe.Pointer(cexpression))
+
+	t.mu.Lock()
+	defer t.mu.Unlock()
+	if t.handle == nil {
+		return ErrClosed
+	}
+
+	var details C.AtreeParseError
+	result := C.atree_insert_with_error(t.handle, C.uint64_t(id), cexpression, &details)
+	if result.success {
+		return nil
+	}
+	defer C.atree_free_error(result.error_message)
+	defer C.atree_parse_error_free(details)
+
+	switch details.code {
+	case C.DuplicateId:
+		return ErrDuplicateID
+	case C.NoError:
+		return &Error{Status: StatusInvalidArgument, Message: C.GoString(result.error_message)}
+	default:
+		return &ParseError{
+			Message: C.GoString(details.message),
+			Line:    int(details.line),
+			Column:  int(details.column),
+			Offset:  int(details.offset),
+			Length:  int(details.length),
+		}
+	}
+}
+
+// Delete removes a subscription; nothing happens if it is not in the tree.
+func (t *Tree) Delete(id uint64) error {
+	t.mu.Lock()
+	defer t.mu.Unlock()
+	if t.handle == nil {
+		return ErrClosed
+	}
+
+	C.atree_delete(t.handle, C.uint64_t(id))
+	return nil
+}
+
+// Search returns the IDs of the subscriptions matching an event, in no particular order.
+func (t *Tree) Search(event Event) ([]uint64, error) {
+	t.mu.RLock()
+	defer t.mu.RUnlock()
+	if t.handle == nil {
+		return nil, ErrClosed
+	}
+
+	var builder unsafe.Pointer
+	if err := check(C.atree_event_builder_new2(t.handle, &builder)); err != nil {
+		return nil, err
+	}
+	defer C.atree_event_builder_free(builder)
+	for name, value := range event {
+		if err := add(builder, name, value); err != nil {
+			return nil, err
+		}
+	}
+
+	var ids *C.uint64_t
+	var count C.uintptr_t
+	if err := check(C.atree_search_borrowed2(t.handle, builder, &ids, &count)); err != nil {
+		return nil, err
+	}
+	defer C.atree_ids_free(ids, count)
+
+	matches := make([]uint64, count)
+	copy(matches, unsafe.Slice((*uint64)(unsafe.Pointer(ids)), count))
+	return matches, nil
+}
+
+func add(builder unsafe.Pointer, name string, value any) error {
+	cname := C.CString(name)
+	defer C.free(unsafe.Pointer(cname))
+
+	switch value := value.(type) {
+	case nil:
+		return check(C.atree_event_builder_with_undefined2(builder, cname))
+	case bool:
+		return check(C.atree_event_builder_with_boolean2(builder, cname, C.bool(value)))
+	case int:
+		return check(C.atree_event_builder_with_integer2(builder, cname, C.int64_t(value)))
+	case int64:
+		return check(C.atree_event_builder_with_integer2(builder, cname, C.int64_t(value)))
+	case float64:
+		return check(C.atree_event_builder_with_double2(builder, cname, C.double(value)))
+	case string:
+		cvalue := C.CString(value)
+		defer C.free(unsafe.Pointer(cvalue))
+		return check(C.atree_event_builder_with_string2(builder, cname, cvalue))
+	case []int64:
+		var first *C.int64_t
+		if len(value) > 0 {
+			first = (*C.int64_t)(unsafe.Pointer(&value[0]))
+		}
+		return check(C.atree_event_builder_with_integer_list2(builder, cname, first, C.uintptr_t(len(value))))
+	case []string:
+		cvalues := make([]*C.char, len(value))
+		for i, item := range value {
+			cvalues[i] = C.CString(item)
+			defer C.free(unsafe.Pointer(cvalues[i]))
+		}
+		var first **C.char
+		if len(cvalues) > 0 {
+			first = &cvalues[0]
+		}
+		return check(C.atree_event_builder_with_string_list2(builder, cname, first, C.uintptr_t(len(cvalues))))
+	default:
+		return fmt.Errorf("atree: unsupported value %T for attribute %q", value, name)
+	}
+}
@@ -0,0 +1,3 @@
+module github.com/mrayva/a-tree/a-tree-ffi/go
+
+go 1.21
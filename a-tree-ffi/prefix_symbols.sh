@@ -0,0 +1,27 @@
+#!/usr/bin/env bash
+# Build a static library whose exported functions start with a custom prefix, so that two
+# plugins embedding different versions of the library can be loaded in the same process.
+#
+# Usage: ./prefix_symbols.sh <prefix>
+#   e.g. ./prefix_symbols.sh myco_ exports myco_atree_new(), myco_atree_search(), ...
+set -euo pipefail
+
+if [ $# -ne 1 ]; then
+    echo "Usage: $0 <prefix>" >&2
+    exit 1
+fi
+prefix="$1"
+
+echo "[1/2] Building FFI library with the ${prefix} prefix..."
+ATREE_SYMBOL_PREFIX="${prefix}" cargo build --release
+
+echo "[2/2] Renaming the symbols of the static library..."
+objcopy --redefine-syms=atree.syms \
+    target/release/liba_tree_ffi.a \
+    "target/release/lib${prefix}a_tree_ffi.a"
+
+echo ""
+echo "Build complete!"
+echo "  Header:  atree.h (maps the atree_* names to the ${prefix}atree_* symbols)"
+echo "  Library: target/release/lib${prefix}a_tree_ffi.a"
+echo "Run 'cargo build' without ATREE_SYMBOL_PREFIX to restore the default header."
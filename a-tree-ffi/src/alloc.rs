@@ -0,0 +1,148 @@
+//! Allocation of the buffers handed over to the caller (strings, error messages and search
+//! results), either with the Rust allocator or with the one registered by the host.
+
+use std::ffi::CString;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::sync::{PoisonError, RwLock};
+
+use crate::status::{ATREE_ERROR_INVALID_ARGUMENT, ATREE_OK};
+
+/// Allocate `size` bytes; returns null on failure
+pub type AtreeMallocFn = Option<unsafe extern "C" fn(size: usize, ctx: *mut c_void) -> *mut c_void>;
+/// Free memory returned by the allocation functions
+pub type AtreeFreeFn = Option<unsafe extern "C" fn(ptr: *mut c_void, ctx: *mut c_void)>;
+
+#[derive(Clone, Copy)]
+struct Allocator {
+    malloc: unsafe extern "C" fn(usize, *mut c_void) -> *mut c_void,
+    free: unsafe extern "C" fn(*mut c_void, *mut c_void),
+    ctx: *mut c_void,
+}
+
+// The context is only passed back to the host's functions, which must be thread-safe themselves.
+unsafe impl Send for Allocator {}
+unsafe impl Sync for Allocator {}
+
+static ALLOCATOR: RwLock<Option<Allocator>> = RwLock::new(None);
+
+fn allocator() -> Option<Allocator> {
+    *ALLOCATOR.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Register the allocator used for every buffer returned to the caller.
+///
+/// The strings, error messages and search results are then allocated with `malloc_fn` and
+/// released with `free_fn` by the matching `atree_*_free()` functions, instead of with the Rust
+/// allocator. `ctx` is passed unchanged to every call. Passing null for both `malloc_fn` and
+/// `free_fn` restores the default allocator.
+///
+/// The buffers are allocated with their final size, so no reallocation function is needed.
+///
+/// # Returns
+/// `ATREE_OK`, or `ATREE_ERROR_INVALID_ARGUMENT` if only one of `malloc_fn` and `free_fn` is
+/// null
+///
+/// # Safety
+/// - Must be called when no buffer returned by the library is still alive, typically before any
+///   other call, since a buffer must be freed by the allocator that allocated it
+/// - The functions must be thread-safe if the library is used from multiple threads
+#[no_mangle]
+pub unsafe extern "C" fn atree_set_allocator(
+    malloc_fn: AtreeMallocFn,
+    free_fn: AtreeFreeFn,
+    ctx: *mut c_void,
+) -> i32 {
+    let allocator = match (malloc_fn, free_fn) {
+        (Some(malloc), Some(free)) => Some(Allocator { malloc, free, ctx }),
+        (None, None) => None,
+        _ => return ATREE_ERROR_INVALID_ARGUMENT,
+    };
+    *ALLOCATOR.write().unwrap_or_else(PoisonError::into_inner) = allocator;
+    ATREE_OK
+}
+
+/// Copy a string to a new null-terminated buffer.
+///
+/// Returns null if the memory cannot be allocated or if the string contains a NUL byte.
+pub(crate) fn allocate_string(value: &str) -> *mut c_char {
+    if value.as_bytes().contains(&0) {
+        return ptr::null_mut();
+    }
+
+    match allocator() {
+        Some(allocator) => unsafe {
+            let buffer = (allocator.malloc)(value.len() + 1, allocator.ctx).cast::<c_char>();
+            if !buffer.is_null() {
+                ptr::copy_nonoverlapping(value.as_ptr().cast::<c_char>(), buffer, value.len());
+                *buffer.add(value.len()) = 0;
+            }
+            buffer
+        },
+        None => {
+            let mut bytes = Vec::new();
+            if bytes.try_reserve_exact(value.len() + 1).is_err() {
+                return ptr::null_mut();
+            }
+            bytes.extend_from_slice(value.as_bytes());
+            CString::new(bytes).map_or(ptr::null_mut(), CString::into_raw)
+        }
+    }
+}
+
+/// Free a string returned by `allocate_string()`.
+pub(crate) unsafe fn free_string(string: *mut c_char) {
+    if string.is_null() {
+        return;
+    }
+
+    match allocator() {
+        Some(allocator) => (allocator.free)(string.cast(), allocator.ctx),
+        None => drop(CString::from_raw(string)),
+    }
+}
+
+/// Move the IDs to a new buffer.
+///
+/// Returns null if there are no IDs or if the memory cannot be allocated.
+pub(crate) fn allocate_ids(ids: Vec<u64>) -> *mut u64 {
+    allocate_slice(ids)
+}
+
+/// Free IDs returned by `allocate_ids()`.
+pub(crate) unsafe fn free_ids(ids: *mut u64, count: usize) {
+    free_slice(ids, count);
+}
+
+/// Move the values to a new buffer.
+///
+/// Returns null if there are no values or if the memory cannot be allocated.
+pub(crate) fn allocate_slice<V: Copy>(values: Vec<V>) -> *mut V {
+    if values.is_empty() {
+        return ptr::null_mut();
+    }
+
+    match allocator() {
+        Some(allocator) => unsafe {
+            let size = values.len() * size_of::<V>();
+            let buffer = (allocator.malloc)(size, allocator.ctx).cast::<V>();
+            if !buffer.is_null() {
+                ptr::copy_nonoverlapping(values.as_ptr(), buffer, values.len());
+            }
+            buffer
+        },
+        None => Box::into_raw(values.into_boxed_slice()).cast::<V>(),
+    }
+}
+
+/// Free values returned by `allocate_slice()`.
+pub(crate) unsafe fn free_slice<V>(values: *mut V, count: usize) {
+    if values.is_null() || count == 0 {
+        return;
+    }
+
+    match allocator() {
+        Some(allocator) => (allocator.free)(values.cast(), allocator.ctx),
+        None => drop(Box::from_raw(ptr::slice_from_raw_parts_mut(values, count))),
+    }
+}
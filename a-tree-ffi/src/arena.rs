@@ -0,0 +1,228 @@
+//! Arenas holding the buffers returned by the searches and exports of a request, so that a C
+//! server frees them all at once at the end of the request instead of one by one.
+
+use std::mem::MaybeUninit;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+
+use crate::debug::is_invalid;
+use crate::{search, search_borrowed, ATreeHandle, AtreeSearchResult};
+
+/// Size of the first chunk of an arena created with a capacity of 0
+const DEFAULT_CAPACITY: usize = 4096;
+
+/// The chunks are made of words so that every buffer can be aligned on 8 bytes.
+type Word = MaybeUninit<u64>;
+
+/// Opaque arena of buffers freed together by `atree_arena_reset()`
+pub struct AtreeArena {
+    chunks: Vec<Box<[Word]>>,
+    /// Index of the chunk being filled
+    current: usize,
+    /// Number of words used in the current chunk
+    used: usize,
+}
+
+impl AtreeArena {
+    fn new(capacity: usize) -> Self {
+        let words = capacity.max(DEFAULT_CAPACITY).div_ceil(size_of::<Word>());
+        Self {
+            chunks: vec![Self::chunk(words)],
+            current: 0,
+            used: 0,
+        }
+    }
+
+    fn chunk(words: usize) -> Box<[Word]> {
+        vec![MaybeUninit::uninit(); words].into_boxed_slice()
+    }
+
+    /// Reserve `size` bytes aligned on 8 bytes, moving to the next chunk when the current one is
+    /// full and adding a chunk twice as large as the last one when all are full.
+    fn allocate(&mut self, size: usize) -> *mut u8 {
+        let words = size.div_ceil(size_of::<Word>());
+        while self.chunks[self.current].len() - self.used < words {
+            self.current += 1;
+            self.used = 0;
+            if self.current == self.chunks.len() {
+                let last = self.chunks[self.current - 1].len();
+                self.chunks.push(Self::chunk(words.max(last * 2)));
+            }
+        }
+        let buffer = self.chunks[self.current][self.used..].as_mut_ptr();
+        self.used += words;
+        buffer.cast()
+    }
+
+    /// Copy the values to the arena; returns null if there are no values.
+    fn allocate_slice<V: Copy>(&mut self, values: &[V]) -> *mut V {
+        debug_assert!(align_of::<V>() <= align_of::<Word>());
+        if values.is_empty() {
+            return ptr::null_mut();
+        }
+
+        let buffer = self.allocate(size_of_val(values)).cast::<V>();
+        unsafe { ptr::copy_nonoverlapping(values.as_ptr(), buffer, values.len()) };
+        buffer
+    }
+
+    /// Copy a string to a null-terminated buffer of the arena; returns null if the string
+    /// contains a NUL byte.
+    fn allocate_string(&mut self, value: &str) -> *mut c_char {
+        if value.as_bytes().contains(&0) {
+            return ptr::null_mut();
+        }
+
+        let buffer = self.allocate(value.len() + 1).cast::<c_char>();
+        unsafe {
+            ptr::copy_nonoverlapping(value.as_ptr().cast::<c_char>(), buffer, value.len());
+            *buffer.add(value.len()) = 0;
+        }
+        buffer
+    }
+
+    fn capacity(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.len()).sum::<usize>() * size_of::<Word>()
+    }
+
+    fn search_result(&mut self, ids: Vec<u64>) -> AtreeSearchResult {
+        AtreeSearchResult {
+            ids: self.allocate_slice(&ids),
+            count: ids.len(),
+        }
+    }
+}
+
+/// Create an arena for the buffers returned by the `*_in_arena()` functions.
+///
+/// The arena starts with `capacity` bytes (4 KiB if 0) and grows as needed. An arena is meant to
+/// be used by one thread at a time, typically for the duration of a request.
+///
+/// # Returns
+/// Pointer to the arena
+///
+/// # Safety
+/// - Caller must free the returned arena with `atree_arena_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_arena_new(capacity: usize) -> *mut AtreeArena {
+    Box::into_raw(Box::new(AtreeArena::new(capacity)))
+}
+
+/// Release every buffer allocated from the arena at once.
+///
+/// The memory of the arena is kept to serve the next allocations.
+///
+/// # Safety
+/// - `arena` must be a valid pointer returned by `atree_arena_new()`
+/// - The buffers allocated from the arena must not be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_arena_reset(arena: *mut AtreeArena) {
+    if arena.is_null() {
+        return;
+    }
+
+    let arena = &mut *arena;
+    arena.current = 0;
+    arena.used = 0;
+}
+
+/// Get the number of bytes reserved by the arena, whether they are used or not.
+///
+/// # Safety
+/// - `arena` must be a valid pointer returned by `atree_arena_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_arena_capacity(arena: *const AtreeArena) -> usize {
+    if arena.is_null() {
+        return 0;
+    }
+
+    (*arena).capacity()
+}
+
+/// Free an arena along with the buffers allocated from it.
+///
+/// # Safety
+/// - `arena` must be null or a valid pointer returned by `atree_arena_new()`
+/// - `arena` and its buffers must not be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_arena_free(arena: *mut AtreeArena) {
+    if !arena.is_null() {
+        drop(Box::from_raw(arena));
+    }
+}
+
+/// Search the A-Tree like `atree_search()`, allocating the IDs of the result from an arena.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `builder` will be consumed by this call and must not be used after
+/// - `arena` must be a valid pointer returned by `atree_arena_new()`
+/// - The result is released by `atree_arena_reset()` and must not be passed to
+///   `atree_search_result_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_search_in_arena(
+    handle: *const ATreeHandle,
+    builder: *mut c_void,
+    arena: *mut AtreeArena,
+) -> AtreeSearchResult {
+    if arena.is_null() {
+        return AtreeSearchResult::empty();
+    }
+
+    search(handle, builder)
+        .map(|ids| (*arena).search_result(ids))
+        .unwrap_or(AtreeSearchResult::empty())
+}
+
+/// Search the A-Tree like `atree_search_borrowed()`, allocating the IDs of the result from an
+/// arena.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - Caller must still free `builder` with `atree_event_builder_free()`
+/// - `arena` must be a valid pointer returned by `atree_arena_new()`
+/// - The result is released by `atree_arena_reset()` and must not be passed to
+///   `atree_search_result_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_search_borrowed_in_arena(
+    handle: *const ATreeHandle,
+    builder: *const c_void,
+    arena: *mut AtreeArena,
+) -> AtreeSearchResult {
+    if arena.is_null() {
+        return AtreeSearchResult::empty();
+    }
+
+    search_borrowed(handle, builder)
+        .map(|ids| (*arena).search_result(ids))
+        .unwrap_or(AtreeSearchResult::empty())
+}
+
+/// Export the tree as a JSON document like `atree_export_json()`, allocating the string from an
+/// arena.
+///
+/// # Returns
+/// Null-terminated JSON string, or null on failure
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `arena` must be a valid pointer returned by `atree_arena_new()`
+/// - The string is released by `atree_arena_reset()` and must not be passed to
+///   `atree_free_string()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_export_json_in_arena(
+    handle: *const ATreeHandle,
+    arena: *mut AtreeArena,
+) -> *mut c_char {
+    if is_invalid(handle) || arena.is_null() {
+        return ptr::null_mut();
+    }
+
+    let handle_ref = &*handle;
+    let Ok(json) = handle_ref.tree.export_json() else {
+        return ptr::null_mut();
+    };
+    (*arena).allocate_string(&json)
+}
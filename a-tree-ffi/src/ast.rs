@@ -0,0 +1,523 @@
+//! Read-only access to the structure of an expression, parsed with the same grammar as the
+//! inserted expressions, for the hosts that analyze their rules.
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::ptr;
+
+use a_tree::{Expression, Literal, PredicateOperator};
+
+use crate::debug::is_invalid;
+use crate::{to_str, ATreeHandle};
+
+/// Kind of a node of a parsed expression
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AtreeAstKind {
+    AstAnd = 0,
+    AstOr = 1,
+    AstNot = 2,
+    AstPredicate = 3,
+    /// An expression that was folded to `true` or `false`
+    AstConstant = 4,
+}
+
+/// Operator of a predicate node
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AtreeAstOperator {
+    /// The node is not a predicate
+    OperatorNone = 0,
+    OperatorVariable = 1,
+    OperatorNegatedVariable = 2,
+    OperatorIn = 3,
+    OperatorNotIn = 4,
+    OperatorLessThan = 5,
+    OperatorLessThanEqual = 6,
+    OperatorGreaterThan = 7,
+    OperatorGreaterThanEqual = 8,
+    OperatorEqual = 9,
+    OperatorNotEqual = 10,
+    OperatorOneOf = 11,
+    OperatorNoneOf = 12,
+    OperatorAllOf = 13,
+    OperatorNotAllOf = 14,
+    OperatorIsNull = 15,
+    OperatorIsNotNull = 16,
+    OperatorIsEmpty = 17,
+    OperatorIsNotEmpty = 18,
+    OperatorStartsWith = 19,
+    OperatorNotStartsWith = 20,
+    OperatorBetween = 21,
+    OperatorNotBetween = 22,
+    OperatorContains = 23,
+    OperatorNotContains = 24,
+    /// The value is a window in seconds before the time of the event
+    OperatorWithinLast = 25,
+    OperatorNotWithinLast = 26,
+    /// The minimum amount of values in common is read with `atree_ast_minimum_overlap()`
+    OperatorIntersectAtLeast = 27,
+    OperatorIntersectFewerThan = 28,
+    /// The edit distance is read with `atree_ast_edit_distance()`
+    OperatorFuzzyEqual = 29,
+    OperatorNotFuzzyEqual = 30,
+}
+
+impl From<PredicateOperator> for AtreeAstOperator {
+    fn from(operator: PredicateOperator) -> Self {
+        match operator {
+            PredicateOperator::Variable => Self::OperatorVariable,
+            PredicateOperator::NegatedVariable => Self::OperatorNegatedVariable,
+            PredicateOperator::In => Self::OperatorIn,
+            PredicateOperator::NotIn => Self::OperatorNotIn,
+            PredicateOperator::LessThan => Self::OperatorLessThan,
+            PredicateOperator::LessThanEqual => Self::OperatorLessThanEqual,
+            PredicateOperator::GreaterThan => Self::OperatorGreaterThan,
+            PredicateOperator::GreaterThanEqual => Self::OperatorGreaterThanEqual,
+            PredicateOperator::Equal => Self::OperatorEqual,
+            PredicateOperator::NotEqual => Self::OperatorNotEqual,
+            PredicateOperator::OneOf => Self::OperatorOneOf,
+            PredicateOperator::NoneOf => Self::OperatorNoneOf,
+            PredicateOperator::AllOf => Self::OperatorAllOf,
+            PredicateOperator::NotAllOf => Self::OperatorNotAllOf,
+            PredicateOperator::IsNull => Self::OperatorIsNull,
+            PredicateOperator::IsNotNull => Self::OperatorIsNotNull,
+            PredicateOperator::IsEmpty => Self::OperatorIsEmpty,
+            PredicateOperator::IsNotEmpty => Self::OperatorIsNotEmpty,
+            PredicateOperator::StartsWith => Self::OperatorStartsWith,
+            PredicateOperator::NotStartsWith => Self::OperatorNotStartsWith,
+            PredicateOperator::Between => Self::OperatorBetween,
+            PredicateOperator::NotBetween => Self::OperatorNotBetween,
+            PredicateOperator::Contains => Self::OperatorContains,
+            PredicateOperator::NotContains => Self::OperatorNotContains,
+            PredicateOperator::WithinLast => Self::OperatorWithinLast,
+            PredicateOperator::NotWithinLast => Self::OperatorNotWithinLast,
+            PredicateOperator::IntersectAtLeast => Self::OperatorIntersectAtLeast,
+            PredicateOperator::IntersectFewerThan => Self::OperatorIntersectFewerThan,
+            PredicateOperator::FuzzyEqual => Self::OperatorFuzzyEqual,
+            PredicateOperator::NotFuzzyEqual => Self::OperatorNotFuzzyEqual,
+        }
+    }
+}
+
+/// Type of the literal value of a node
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AtreeLiteralKind {
+    /// The node has no value
+    LiteralNone = 0,
+    /// The value of a constant node
+    LiteralBoolean = 1,
+    LiteralInteger = 2,
+    LiteralFloat = 3,
+    LiteralString = 4,
+    LiteralIntegerList = 5,
+    LiteralStringList = 6,
+    /// The inclusive bounds of a `between`, as a list of two integers
+    LiteralIntegerRange = 7,
+    /// The inclusive bounds of a `between`, as a list of two floats
+    LiteralFloatRange = 8,
+}
+
+enum LiteralValue {
+    None,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(CString),
+    IntegerList(Vec<i64>),
+    StringList(Vec<CString>),
+    IntegerRange([i64; 2]),
+    FloatRange([f64; 2]),
+}
+
+/// Opaque node of a parsed expression; the root is freed with `atree_ast_free()` along with all
+/// its descendants
+pub struct AtreeAst {
+    kind: AtreeAstKind,
+    children: Vec<AtreeAst>,
+    attribute: Option<CString>,
+    operator: AtreeAstOperator,
+    value: LiteralValue,
+    count: Option<usize>,
+    edit_distance: Option<u8>,
+}
+
+impl AtreeAst {
+    /// Convert an expression, failing if one of its strings contains a null character.
+    fn new(expression: Expression) -> Option<Self> {
+        let node = |kind, children| Self {
+            kind,
+            children,
+            attribute: None,
+            operator: AtreeAstOperator::OperatorNone,
+            value: LiteralValue::None,
+            count: None,
+            edit_distance: None,
+        };
+        let convert = |expression: Box<Expression>| Self::new(*expression);
+        Some(match expression {
+            Expression::And(left, right) => {
+                node(AtreeAstKind::AstAnd, vec![convert(left)?, convert(right)?])
+            }
+            Expression::Or(left, right) => {
+                node(AtreeAstKind::AstOr, vec![convert(left)?, convert(right)?])
+            }
+            Expression::Not(operand) => node(AtreeAstKind::AstNot, vec![convert(operand)?]),
+            Expression::Constant(value) => Self {
+                value: LiteralValue::Boolean(value),
+                ..node(AtreeAstKind::AstConstant, vec![])
+            },
+            Expression::Predicate(predicate) => Self {
+                attribute: Some(CString::new(predicate.attribute()).ok()?),
+                operator: predicate.operator().into(),
+                value: match predicate.value() {
+                    None => LiteralValue::None,
+                    Some(Literal::Integer(value)) => LiteralValue::Integer(*value),
+                    Some(Literal::Float(value)) => LiteralValue::Float(*value),
+                    Some(Literal::String(value)) => {
+                        LiteralValue::String(CString::new(value.as_str()).ok()?)
+                    }
+                    Some(Literal::IntegerList(values)) => LiteralValue::IntegerList(values.clone()),
+                    Some(Literal::StringList(values)) => LiteralValue::StringList(
+                        values
+                            .iter()
+                            .map(|value| CString::new(value.as_str()).ok())
+                            .collect::<Option<_>>()?,
+                    ),
+                    Some(Literal::IntegerRange(low, high)) => {
+                        LiteralValue::IntegerRange([*low, *high])
+                    }
+                    Some(Literal::FloatRange(low, high)) => LiteralValue::FloatRange([*low, *high]),
+                },
+                count: predicate.count(),
+                edit_distance: predicate.edit_distance(),
+                ..node(AtreeAstKind::AstPredicate, vec![])
+            },
+        })
+    }
+}
+
+/// Parse an expression with the attributes of the tree without inserting it.
+///
+/// The strings of the expression are not interned. Use `atree_validate_expression()` to locate
+/// the error of an invalid expression.
+///
+/// # Returns
+/// Root of the parsed expression, or null if the expression is invalid
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `expression` must be a valid null-terminated C string
+/// - Caller must free the result with `atree_ast_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_parse(
+    handle: *const ATreeHandle,
+    expression: *const c_char,
+) -> *mut AtreeAst {
+    if is_invalid(handle) || expression.is_null() {
+        return ptr::null_mut();
+    }
+    let Ok(expression) = to_str(expression, "Invalid UTF-8 in expression") else {
+        return ptr::null_mut();
+    };
+
+    parse(&*handle, expression)
+}
+
+pub(crate) fn parse(handle: &ATreeHandle, expression: &str) -> *mut AtreeAst {
+    handle
+        .tree
+        .parse_expression(expression)
+        .ok()
+        .and_then(AtreeAst::new)
+        .map_or(ptr::null_mut(), |ast| Box::into_raw(Box::new(ast)))
+}
+
+/// Free an expression returned by `atree_parse()` along with all its nodes and strings.
+///
+/// # Safety
+/// - `ast` must be null or a pointer returned by `atree_parse()`, not one of its children
+/// - Neither `ast` nor its nodes and strings can be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_ast_free(ast: *mut AtreeAst) {
+    if !ast.is_null() {
+        drop(Box::from_raw(ast));
+    }
+}
+
+/// Get the kind of a node.
+///
+/// # Safety
+/// - `node` must be a valid node of an expression returned by `atree_parse()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_ast_kind(node: *const AtreeAst) -> AtreeAstKind {
+    (*node).kind
+}
+
+/// Get the number of operands of a node: 2 for `and` and `or`, 1 for `not` and 0 otherwise.
+///
+/// # Safety
+/// - `node` must be null or a valid node of an expression returned by `atree_parse()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_ast_children_count(node: *const AtreeAst) -> usize {
+    node.as_ref().map_or(0, |node| node.children.len())
+}
+
+/// Get an operand of a node, from left to right.
+///
+/// # Returns
+/// The operand, owned by the expression, or null if `index` is out of bounds
+///
+/// # Safety
+/// - `node` must be null or a valid node of an expression returned by `atree_parse()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_ast_child(node: *const AtreeAst, index: usize) -> *const AtreeAst {
+    node.as_ref()
+        .and_then(|node| node.children.get(index))
+        .map_or(ptr::null(), |child| child as *const AtreeAst)
+}
+
+/// Get the attribute name of a predicate.
+///
+/// # Returns
+/// The name, owned by the expression, or null if the node is not a predicate
+///
+/// # Safety
+/// - `node` must be null or a valid node of an expression returned by `atree_parse()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_ast_attribute(node: *const AtreeAst) -> *const c_char {
+    node.as_ref()
+        .and_then(|node| node.attribute.as_ref())
+        .map_or(ptr::null(), |attribute| attribute.as_ptr())
+}
+
+/// Get the operator of a predicate, or `OperatorNone` for the other nodes.
+///
+/// # Safety
+/// - `node` must be null or a valid node of an expression returned by `atree_parse()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_ast_operator(node: *const AtreeAst) -> AtreeAstOperator {
+    node.as_ref()
+        .map_or(AtreeAstOperator::OperatorNone, |node| node.operator)
+}
+
+/// Get the type of the literal value of a node.
+///
+/// # Safety
+/// - `node` must be null or a valid node of an expression returned by `atree_parse()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_ast_literal_kind(node: *const AtreeAst) -> AtreeLiteralKind {
+    match node.as_ref().map(|node| &node.value) {
+        None | Some(LiteralValue::None) => AtreeLiteralKind::LiteralNone,
+        Some(LiteralValue::Boolean(_)) => AtreeLiteralKind::LiteralBoolean,
+        Some(LiteralValue::Integer(_)) => AtreeLiteralKind::LiteralInteger,
+        Some(LiteralValue::Float(_)) => AtreeLiteralKind::LiteralFloat,
+        Some(LiteralValue::String(_)) => AtreeLiteralKind::LiteralString,
+        Some(LiteralValue::IntegerList(_)) => AtreeLiteralKind::LiteralIntegerList,
+        Some(LiteralValue::StringList(_)) => AtreeLiteralKind::LiteralStringList,
+        Some(LiteralValue::IntegerRange(_)) => AtreeLiteralKind::LiteralIntegerRange,
+        Some(LiteralValue::FloatRange(_)) => AtreeLiteralKind::LiteralFloatRange,
+    }
+}
+
+/// Get the value of a constant node.
+///
+/// # Returns
+/// true if the node is a constant and its value was written to `out`
+///
+/// # Safety
+/// - `node` must be null or a valid node of an expression returned by `atree_parse()`
+/// - `out` must point to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn atree_ast_literal_boolean(node: *const AtreeAst, out: *mut bool) -> bool {
+    match node.as_ref().map(|node| &node.value) {
+        Some(LiteralValue::Boolean(value)) if !out.is_null() => {
+            *out = *value;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Get the integer value of a predicate.
+///
+/// # Returns
+/// true if the value is an integer and it was written to `out`
+///
+/// # Safety
+/// - `node` must be null or a valid node of an expression returned by `atree_parse()`
+/// - `out` must point to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn atree_ast_literal_integer(node: *const AtreeAst, out: *mut i64) -> bool {
+    match node.as_ref().map(|node| &node.value) {
+        Some(LiteralValue::Integer(value)) if !out.is_null() => {
+            *out = *value;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Get the minimum amount of values in common of an `intersect_at_least` predicate.
+///
+/// # Returns
+/// true if the node has a count and it was written to `out`
+///
+/// # Safety
+/// - `node` must be null or a valid node of an expression returned by `atree_parse()`
+/// - `out` must point to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn atree_ast_minimum_overlap(node: *const AtreeAst, out: *mut usize) -> bool {
+    match node.as_ref().and_then(|node| node.count) {
+        Some(count) if !out.is_null() => {
+            *out = count;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Get the amount of edits allowed by a `~=` predicate.
+///
+/// # Returns
+/// true if the node has an edit distance and it was written to `out`
+///
+/// # Safety
+/// - `node` must be null or a valid node of an expression returned by `atree_parse()`
+/// - `out` must point to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn atree_ast_edit_distance(node: *const AtreeAst, out: *mut u8) -> bool {
+    match node.as_ref().and_then(|node| node.edit_distance) {
+        Some(distance) if !out.is_null() => {
+            *out = distance;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Get the float value of a predicate, converted to the nearest double.
+///
+/// # Returns
+/// true if the value is a float and it was written to `out`
+///
+/// # Safety
+/// - `node` must be null or a valid node of an expression returned by `atree_parse()`
+/// - `out` must point to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn atree_ast_literal_float(node: *const AtreeAst, out: *mut f64) -> bool {
+    match node.as_ref().map(|node| &node.value) {
+        Some(LiteralValue::Float(value)) if !out.is_null() => {
+            *out = *value;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Get the string value of a predicate.
+///
+/// # Returns
+/// The string, owned by the expression, or null if the value is not a string
+///
+/// # Safety
+/// - `node` must be null or a valid node of an expression returned by `atree_parse()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_ast_literal_string(node: *const AtreeAst) -> *const c_char {
+    match node.as_ref().map(|node| &node.value) {
+        Some(LiteralValue::String(value)) => value.as_ptr(),
+        _ => ptr::null(),
+    }
+}
+
+/// Get the number of elements of a list value, sorted and deduplicated as stored by the tree, or
+/// 2 for the bounds of a range.
+///
+/// # Safety
+/// - `node` must be null or a valid node of an expression returned by `atree_parse()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_ast_literal_count(node: *const AtreeAst) -> usize {
+    match node.as_ref().map(|node| &node.value) {
+        Some(LiteralValue::IntegerList(values)) => values.len(),
+        Some(LiteralValue::StringList(values)) => values.len(),
+        Some(LiteralValue::IntegerRange(_) | LiteralValue::FloatRange(_)) => 2,
+        _ => 0,
+    }
+}
+
+/// Get an element of an integer list value or a bound of an integer range.
+///
+/// # Returns
+/// true if the value is an integer list or range and its element at `index` was written to `out`
+///
+/// # Safety
+/// - `node` must be null or a valid node of an expression returned by `atree_parse()`
+/// - `out` must point to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn atree_ast_literal_integer_at(
+    node: *const AtreeAst,
+    index: usize,
+    out: *mut i64,
+) -> bool {
+    let values = match node.as_ref().map(|node| &node.value) {
+        Some(LiteralValue::IntegerList(values)) => values.as_slice(),
+        Some(LiteralValue::IntegerRange(bounds)) => bounds.as_slice(),
+        _ => return false,
+    };
+    match values.get(index) {
+        Some(value) if !out.is_null() => {
+            *out = *value;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Get a bound of a float range, converted to the nearest double: 0 for the lower bound and 1 for
+/// the upper one.
+///
+/// # Returns
+/// true if the value is a float range and its bound at `index` was written to `out`
+///
+/// # Safety
+/// - `node` must be null or a valid node of an expression returned by `atree_parse()`
+/// - `out` must point to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn atree_ast_literal_float_at(
+    node: *const AtreeAst,
+    index: usize,
+    out: *mut f64,
+) -> bool {
+    match node.as_ref().map(|node| &node.value) {
+        Some(LiteralValue::FloatRange(bounds)) if !out.is_null() => match bounds.get(index) {
+            Some(value) => {
+                *out = *value;
+                true
+            }
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+/// Get an element of a string list value.
+///
+/// # Returns
+/// The string, owned by the expression, or null if the value is not a string list or `index` is
+/// out of bounds
+///
+/// # Safety
+/// - `node` must be null or a valid node of an expression returned by `atree_parse()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_ast_literal_string_at(
+    node: *const AtreeAst,
+    index: usize,
+) -> *const c_char {
+    match node.as_ref().map(|node| &node.value) {
+        Some(LiteralValue::StringList(values)) => values
+            .get(index)
+            .map_or(ptr::null(), |value| value.as_ptr()),
+        _ => ptr::null(),
+    }
+}
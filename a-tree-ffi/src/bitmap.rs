@@ -0,0 +1,81 @@
+//! Search results as serialized Roaring bitmaps, so that the hosts intersect the matches with
+//! their own sets of subscriptions, e.g. the ones that still have a budget, without copying the
+//! IDs one by one.
+
+use std::os::raw::c_void;
+use std::ptr;
+
+use a_tree::roaring::RoaringTreemap;
+
+use crate::{alloc, search, ATreeHandle, Failure, ATREE_ERROR_OUT_OF_MEMORY};
+
+/// Search result containing the matching subscription IDs as a serialized Roaring bitmap
+///
+/// The bytes are in the portable format of the 64-bit Roaring bitmaps, which the C, C++, Java and
+/// Go Roaring libraries deserialize, e.g. with `roaring64_bitmap_portable_deserialize_safe()` in
+/// CRoaring. They are null on failure; a search without matches still yields the bytes of an empty
+/// bitmap.
+#[repr(C)]
+pub struct AtreeBitmapResult {
+    pub bytes: *mut u8,
+    pub len: usize,
+}
+
+/// Search the A-Tree for matching expressions and return their IDs as a serialized Roaring bitmap.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `builder` will be consumed by this call and must not be used after
+/// - Caller must free the returned result with `atree_bitmap_result_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_search_bitmap(
+    handle: *const ATreeHandle,
+    builder: *mut c_void,
+) -> AtreeBitmapResult {
+    search(handle, builder)
+        .and_then(AtreeBitmapResult::new)
+        .unwrap_or(AtreeBitmapResult::empty())
+}
+
+/// Free a bitmap search result.
+///
+/// # Safety
+/// - `result` must be a valid search result returned by `atree_search_bitmap()`
+/// - `result` must not be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_bitmap_result_free(result: AtreeBitmapResult) {
+    alloc::free_slice(result.bytes, result.len);
+}
+
+impl AtreeBitmapResult {
+    const fn empty() -> Self {
+        Self {
+            bytes: ptr::null_mut(),
+            len: 0,
+        }
+    }
+
+    fn new(mut matches: Vec<u64>) -> Result<Self, Failure> {
+        // The IDs are appended to the last container of the bitmap when they are sorted.
+        matches.sort_unstable();
+        let bitmap: RoaringTreemap = matches.into_iter().collect();
+        let mut serialized = Vec::with_capacity(bitmap.serialized_size());
+        bitmap.serialize_into(&mut serialized).map_err(|_| {
+            Failure::new(
+                ATREE_ERROR_OUT_OF_MEMORY,
+                "Failed to serialize the search results",
+            )
+        })?;
+
+        let len = serialized.len();
+        let bytes = alloc::allocate_slice(serialized);
+        if bytes.is_null() {
+            return Err(Failure::new(
+                ATREE_ERROR_OUT_OF_MEMORY,
+                "Failed to allocate the search results",
+            ));
+        }
+        Ok(Self { bytes, len })
+    }
+}
@@ -0,0 +1,153 @@
+//! Event builders kept for reuse across requests, so that the threads of a server do not
+//! allocate a builder for each event nor share a single one behind a lock.
+
+use std::os::raw::c_void;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+use a_tree::EventBuilder;
+
+use crate::debug::{self, is_invalid, is_invalid_builder};
+use crate::ATreeHandle;
+
+static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// The shard used first by the current thread, assigned in turn to the threads so that they
+    /// rarely contend on the same lock.
+    static HOME_SHARD: usize = NEXT_SHARD.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A builder waiting in a pool; it is only used by the thread that checks it out.
+struct Pooled(*mut c_void);
+
+unsafe impl Send for Pooled {}
+
+/// Opaque pool of event builders for a tree
+pub struct AtreeBuilderPool {
+    handle: *const ATreeHandle,
+    shards: Box<[Mutex<Vec<Pooled>>]>,
+}
+
+impl AtreeBuilderPool {
+    fn home(&self) -> usize {
+        HOME_SHARD.with(|shard| *shard) % self.shards.len()
+    }
+
+    fn shard(&self, index: usize) -> MutexGuard<'_, Vec<Pooled>> {
+        self.shards[index]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Take a builder from the shard of the thread, then from the others.
+    fn take(&self) -> Option<*mut c_void> {
+        let home = self.home();
+        (0..self.shards.len())
+            .map(|offset| (home + offset) % self.shards.len())
+            .find_map(|index| self.shard(index).pop())
+            .map(|pooled| pooled.0)
+    }
+
+    unsafe fn make_builder(&self) -> *mut c_void {
+        debug::into_raw((*self.handle).tree.make_event()) as *mut c_void
+    }
+}
+
+/// Create a pool of event builders for the tree, with `size` builders created upfront.
+///
+/// The builders are spread over as many shards as there are cores so that the threads checking
+/// builders out and in rarely wait for each other. The pool grows when more than `size` builders
+/// are checked out at the same time.
+///
+/// # Returns
+/// Pointer to the pool, or null if `handle` is null
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`; it must outlive the pool and
+///   must not be modified while builders are checked out
+/// - Caller must free the returned pool with `atree_builder_pool_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_builder_pool_new(
+    handle: *const ATreeHandle,
+    size: usize,
+) -> *mut AtreeBuilderPool {
+    if is_invalid(handle) {
+        return ptr::null_mut();
+    }
+
+    let shards = thread::available_parallelism().map_or(1, |count| count.get());
+    let pool = AtreeBuilderPool {
+        handle,
+        shards: (0..shards).map(|_| Mutex::new(Vec::new())).collect(),
+    };
+    for index in 0..size {
+        let builder = pool.make_builder();
+        pool.shard(index % shards).push(Pooled(builder));
+    }
+    Box::into_raw(Box::new(pool))
+}
+
+/// Check a builder out of the pool, creating one if the pool is empty.
+///
+/// The builder is empty and is filled with the `atree_event_builder_with_*()` functions. It can
+/// be searched with the functions that do not consume it, such as `atree_search_borrowed()`; a
+/// builder consumed by `atree_search()` must not be checked in.
+///
+/// # Returns
+/// Pointer to the builder, or null if `pool` is null
+///
+/// # Safety
+/// - `pool` must be a valid pointer returned by `atree_builder_pool_new()`
+/// - Caller must return the builder with `atree_builder_pool_checkin()`, or consume it
+#[no_mangle]
+pub unsafe extern "C" fn atree_builder_pool_checkout(pool: *const AtreeBuilderPool) -> *mut c_void {
+    if pool.is_null() {
+        return ptr::null_mut();
+    }
+
+    let pool = &*pool;
+    pool.take().unwrap_or_else(|| pool.make_builder())
+}
+
+/// Reset a builder and return it to the pool.
+///
+/// # Safety
+/// - `pool` must be a valid pointer returned by `atree_builder_pool_new()`
+/// - `builder` must be a builder checked out of the same pool; it must not be used after this
+///   call
+#[no_mangle]
+pub unsafe extern "C" fn atree_builder_pool_checkin(
+    pool: *const AtreeBuilderPool,
+    builder: *mut c_void,
+) {
+    if pool.is_null() || is_invalid_builder(builder) {
+        return;
+    }
+
+    let pool = &*pool;
+    (*(builder as *mut EventBuilder)).reset();
+    pool.shard(pool.home()).push(Pooled(builder));
+}
+
+/// Free a pool along with the builders it holds.
+///
+/// # Safety
+/// - `pool` must be null or a valid pointer returned by `atree_builder_pool_new()`
+/// - Every builder checked out of the pool must have been checked in or consumed
+/// - `pool` must not be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_builder_pool_free(pool: *mut AtreeBuilderPool) {
+    if pool.is_null() {
+        return;
+    }
+
+    let pool = Box::from_raw(pool);
+    for shard in pool.shards.into_vec() {
+        for builder in shard.into_inner().unwrap_or_else(PoisonError::into_inner) {
+            debug::free(builder.0 as *mut EventBuilder);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! A handle shared by reader threads whose tree can be replaced by a freshly built one without
+//! stopping the searches.
+
+use std::sync::{Arc, PoisonError, RwLock};
+
+use crate::debug::{self, is_invalid};
+use crate::{ATreeHandle, AtreeResult, Failure};
+
+/// Opaque handle publishing the current tree to the reader threads
+pub struct AtreeConcurrentHandle {
+    current: RwLock<Arc<ATreeHandle>>,
+}
+
+impl AtreeConcurrentHandle {
+    pub(crate) fn current(&self) -> Arc<ATreeHandle> {
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn replace(&self, tree: Arc<ATreeHandle>) -> Arc<ATreeHandle> {
+        let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+        std::mem::replace(&mut current, tree)
+    }
+
+    /// Publish `tree` unless the current tree is no longer `expected`, returning the tree that
+    /// was replaced or `tree` back.
+    pub(crate) fn replace_if(
+        &self,
+        expected: &Arc<ATreeHandle>,
+        tree: Arc<ATreeHandle>,
+    ) -> Result<Arc<ATreeHandle>, Arc<ATreeHandle>> {
+        let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+        if Arc::ptr_eq(&current, expected) {
+            Ok(std::mem::replace(&mut current, tree))
+        } else {
+            Err(tree)
+        }
+    }
+}
+
+/// Create a concurrent handle publishing `tree`, which it takes ownership of.
+///
+/// # Returns
+/// Pointer to the concurrent handle, or null if `tree` is null
+///
+/// # Safety
+/// - `tree` must be a valid pointer returned by `atree_new()`; it must not be used after this call
+/// - Caller must free the returned handle with `atree_concurrent_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_concurrent_new(
+    tree: *mut ATreeHandle,
+) -> *mut AtreeConcurrentHandle {
+    if is_invalid(tree) {
+        return std::ptr::null_mut();
+    }
+
+    let current = Arc::new(debug::take(tree));
+    debug::track(Arc::as_ptr(&current));
+    Box::into_raw(Box::new(AtreeConcurrentHandle {
+        current: RwLock::new(current),
+    }))
+}
+
+/// Pin the current tree of the concurrent handle, to build events and search it.
+///
+/// The pinned tree stays valid until it is released, even if it is swapped out in the meantime,
+/// so every event built with it must be searched against the same pinned tree. It can be used
+/// with all the functions that take a `const ATreeHandle*`, from any thread, but must not be
+/// modified nor freed with `atree_free()`.
+///
+/// # Returns
+/// Pointer to the pinned tree, or null if `concurrent` is null
+///
+/// # Safety
+/// - `concurrent` must be a valid pointer returned by `atree_concurrent_new()`
+/// - Caller must release the returned tree with `atree_concurrent_release()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_concurrent_acquire(
+    concurrent: *const AtreeConcurrentHandle,
+) -> *const ATreeHandle {
+    if concurrent.is_null() {
+        return std::ptr::null();
+    }
+
+    Arc::into_raw((*concurrent).current())
+}
+
+/// Release a tree pinned by `atree_concurrent_acquire()`.
+///
+/// A tree that was swapped out is freed when its last pinned reference is released.
+///
+/// # Safety
+/// - `tree` must be null or a pointer returned by `atree_concurrent_acquire()`, released once
+/// - Neither `tree` nor the event builders created from it can be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_concurrent_release(tree: *const ATreeHandle) {
+    if !is_invalid(tree) {
+        debug::release_shared(Arc::from_raw(tree));
+    }
+}
+
+/// Publish `new_tree` to the readers of the concurrent handle, which takes ownership of it.
+///
+/// The readers that acquire the tree after this call get `new_tree`, while those that pinned the
+/// previous tree keep searching it; the previous tree is freed once the last of them releases
+/// it, or immediately if none has it pinned.
+///
+/// # Safety
+/// - `concurrent` must be a valid pointer returned by `atree_concurrent_new()`
+/// - `new_tree` must be a valid pointer returned by `atree_new()`; it must not be used after this
+///   call, unless it is null
+/// - Caller must free `error_message` with `atree_free_error()` on failure
+#[no_mangle]
+pub unsafe extern "C" fn atree_swap(
+    concurrent: *const AtreeConcurrentHandle,
+    new_tree: *mut ATreeHandle,
+) -> AtreeResult {
+    swap(concurrent, new_tree).into()
+}
+
+pub(crate) unsafe fn swap(
+    concurrent: *const AtreeConcurrentHandle,
+    new_tree: *mut ATreeHandle,
+) -> Result<(), Failure> {
+    if concurrent.is_null() || is_invalid(new_tree) {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let new_tree = Arc::new(debug::take(new_tree));
+    debug::track(Arc::as_ptr(&new_tree));
+    let previous = (*concurrent).replace(new_tree);
+    // Dropped outside of the lock so that the readers do not wait for the previous tree to be freed
+    debug::release_shared(previous);
+    Ok(())
+}
+
+/// Free a concurrent handle.
+///
+/// The pinned trees remain valid until they are released.
+///
+/// # Safety
+/// - `concurrent` must be null or a valid pointer returned by `atree_concurrent_new()`
+/// - `concurrent` must not be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_concurrent_free(concurrent: *mut AtreeConcurrentHandle) {
+    if !concurrent.is_null() {
+        let concurrent = Box::from_raw(concurrent);
+        let current = concurrent
+            .current
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        debug::release_shared(current);
+    }
+}
@@ -0,0 +1,336 @@
+//! Configuration of the trees created with `atree_new_with_config()`, mirroring the options of
+//! the Rust `ATreeConfig` builder.
+
+use std::ptr;
+
+use a_tree::{
+    ATree, ATreeConfig, Coercions, ConjunctionOrder, DeletePolicy, DuplicateIdPolicy,
+    RoundingPolicy, UnknownAttributePolicy,
+};
+
+use crate::debug;
+use crate::{ATreeHandle, AtreeAttributeDef, Failure};
+
+/// Opaque handle to the configuration of an A-Tree
+pub struct AtreeConfigHandle {
+    config: ATreeConfig,
+}
+
+/// Rounding applied to the floats that have more decimals than the float scale
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AtreeRounding {
+    /// Round to the nearest value and the midpoints to the nearest even digit (the default)
+    RoundHalfEven = 0,
+    /// Round to the nearest value and the midpoints away from zero
+    RoundHalfAwayFromZero = 1,
+    /// Truncate the digits
+    RoundTowardZero = 2,
+    /// Round away from zero
+    RoundAwayFromZero = 3,
+    /// Round toward negative infinity
+    RoundFloor = 4,
+    /// Round toward positive infinity
+    RoundCeiling = 5,
+}
+
+/// What a search does with the event attributes that are not defined
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AtreeUnknownAttributes {
+    /// Fail (the default)
+    UnknownAttributesError = 0,
+    /// Skip the attribute silently
+    UnknownAttributesIgnore = 1,
+    /// Skip the attribute and log a warning
+    UnknownAttributesWarn = 2,
+}
+
+/// What an insert does when the subscription ID is already present
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AtreeDuplicateIds {
+    /// Keep both expressions: the subscription matches when any of them matches (the default)
+    DuplicateIdsAllow = 0,
+    /// Delete the previous expression once the new one is parsed
+    DuplicateIdsReplace = 1,
+    /// Fail and keep the previous expression
+    DuplicateIdsReject = 2,
+}
+
+/// What a delete does with the nodes of the subscription
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AtreeDeletePolicy {
+    /// Remove the nodes right away (the default)
+    DeletePolicyImmediate = 0,
+    /// Hide the subscription and keep its nodes until `atree_compact()`
+    DeletePolicyTombstone = 1,
+}
+
+/// Order in which the operands of the `and` operators are evaluated
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AtreeConjunctionOrder {
+    /// Evaluate the operand with the lowest estimated cost first (the default)
+    ConjunctionOrderCost = 0,
+    /// Evaluate the operands in the order in which they are written
+    ConjunctionOrderLeftToRight = 1,
+}
+
+impl From<AtreeRounding> for RoundingPolicy {
+    fn from(rounding: AtreeRounding) -> Self {
+        match rounding {
+            AtreeRounding::RoundHalfEven => Self::HalfEven,
+            AtreeRounding::RoundHalfAwayFromZero => Self::HalfAwayFromZero,
+            AtreeRounding::RoundTowardZero => Self::TowardZero,
+            AtreeRounding::RoundAwayFromZero => Self::AwayFromZero,
+            AtreeRounding::RoundFloor => Self::Floor,
+            AtreeRounding::RoundCeiling => Self::Ceiling,
+        }
+    }
+}
+
+impl From<AtreeUnknownAttributes> for UnknownAttributePolicy {
+    fn from(policy: AtreeUnknownAttributes) -> Self {
+        match policy {
+            AtreeUnknownAttributes::UnknownAttributesError => Self::Error,
+            AtreeUnknownAttributes::UnknownAttributesIgnore => Self::Ignore,
+            AtreeUnknownAttributes::UnknownAttributesWarn => Self::Warn,
+        }
+    }
+}
+
+impl From<AtreeDuplicateIds> for DuplicateIdPolicy {
+    fn from(policy: AtreeDuplicateIds) -> Self {
+        match policy {
+            AtreeDuplicateIds::DuplicateIdsAllow => Self::Allow,
+            AtreeDuplicateIds::DuplicateIdsReplace => Self::Replace,
+            AtreeDuplicateIds::DuplicateIdsReject => Self::Reject,
+        }
+    }
+}
+
+impl From<AtreeDeletePolicy> for DeletePolicy {
+    fn from(policy: AtreeDeletePolicy) -> Self {
+        match policy {
+            AtreeDeletePolicy::DeletePolicyImmediate => Self::Immediate,
+            AtreeDeletePolicy::DeletePolicyTombstone => Self::Tombstone,
+        }
+    }
+}
+
+impl From<AtreeConjunctionOrder> for ConjunctionOrder {
+    fn from(order: AtreeConjunctionOrder) -> Self {
+        match order {
+            AtreeConjunctionOrder::ConjunctionOrderCost => Self::Cost,
+            AtreeConjunctionOrder::ConjunctionOrderLeftToRight => Self::LeftToRight,
+        }
+    }
+}
+
+/// Create a configuration with the default options.
+///
+/// # Returns
+/// Handle that must be freed with `atree_config_free()`
+#[no_mangle]
+pub extern "C" fn atree_config_new() -> *mut AtreeConfigHandle {
+    Box::into_raw(Box::new(AtreeConfigHandle {
+        config: ATreeConfig::new(),
+    }))
+}
+
+/// Free a configuration; the trees created with it are not affected.
+///
+/// # Safety
+/// - `config` must be a valid pointer returned by `atree_config_new()`
+/// - `config` must not be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_config_free(config: *mut AtreeConfigHandle) {
+    if !config.is_null() {
+        drop(Box::from_raw(config));
+    }
+}
+
+unsafe fn update(
+    config: *mut AtreeConfigHandle,
+    update: impl FnOnce(ATreeConfig) -> ATreeConfig,
+) -> bool {
+    let Some(handle) = config.as_mut() else {
+        return false;
+    };
+    handle.config = update(handle.config);
+    true
+}
+
+/// Set the number of decimals kept for the float attributes; values above the maximum
+/// supported scale are capped.
+///
+/// # Returns
+/// `true` on success, `false` if `config` is null
+///
+/// # Safety
+/// - `config` must be null or a valid pointer returned by `atree_config_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_config_set_float_scale(
+    config: *mut AtreeConfigHandle,
+    scale: u32,
+) -> bool {
+    update(config, |config| config.with_float_scale(scale))
+}
+
+/// Set how the floats with more decimals than the float scale are rounded.
+///
+/// # Returns
+/// `true` on success, `false` if `config` is null
+///
+/// # Safety
+/// - `config` must be null or a valid pointer returned by `atree_config_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_config_set_rounding(
+    config: *mut AtreeConfigHandle,
+    rounding: AtreeRounding,
+) -> bool {
+    update(config, |config| config.with_rounding(rounding.into()))
+}
+
+/// Set what a search does with the event attributes that are not defined.
+///
+/// # Returns
+/// `true` on success, `false` if `config` is null
+///
+/// # Safety
+/// - `config` must be null or a valid pointer returned by `atree_config_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_config_set_unknown_attributes(
+    config: *mut AtreeConfigHandle,
+    policy: AtreeUnknownAttributes,
+) -> bool {
+    update(config, |config| {
+        config.with_unknown_attributes(policy.into())
+    })
+}
+
+/// Set what an insert does when the subscription ID is already present.
+///
+/// # Returns
+/// `true` on success, `false` if `config` is null
+///
+/// # Safety
+/// - `config` must be null or a valid pointer returned by `atree_config_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_config_set_duplicate_ids(
+    config: *mut AtreeConfigHandle,
+    policy: AtreeDuplicateIds,
+) -> bool {
+    update(config, |config| config.with_duplicate_ids(policy.into()))
+}
+
+/// Set what a delete does with the nodes of the subscription; with `DeletePolicyTombstone` the
+/// deleted subscriptions stop matching right away but their nodes are only removed by
+/// `atree_compact()`.
+///
+/// # Returns
+/// `true` on success, `false` if `config` is null
+///
+/// # Safety
+/// - `config` must be null or a valid pointer returned by `atree_config_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_config_set_deletes(
+    config: *mut AtreeConfigHandle,
+    policy: AtreeDeletePolicy,
+) -> bool {
+    update(config, |config| config.with_deletes(policy.into()))
+}
+
+/// Set the order in which the operands of the `and` operators are evaluated; the matches are the
+/// same in both orders, only the predicates evaluated before the short-circuits differ.
+///
+/// # Returns
+/// `true` on success, `false` if `config` is null
+///
+/// # Safety
+/// - `config` must be null or a valid pointer returned by `atree_config_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_config_set_conjunction_order(
+    config: *mut AtreeConfigHandle,
+    order: AtreeConjunctionOrder,
+) -> bool {
+    update(config, |config| config.with_conjunction_order(order.into()))
+}
+
+/// Set the event values that are converted into the type of their attribute instead of being
+/// rejected: the integers set to a float attribute and the strings holding a number set to an
+/// integer or a float attribute. None of them are converted by default; every conversion is
+/// logged at the debug level.
+///
+/// # Returns
+/// `true` on success, `false` if `config` is null
+///
+/// # Safety
+/// - `config` must be null or a valid pointer returned by `atree_config_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_config_set_coercions(
+    config: *mut AtreeConfigHandle,
+    integers_to_floats: bool,
+    strings_to_numbers: bool,
+) -> bool {
+    let mut coercions = Coercions::NONE;
+    if integers_to_floats {
+        coercions = coercions.with_integers_to_floats();
+    }
+    if strings_to_numbers {
+        coercions = coercions.with_strings_to_numbers();
+    }
+    update(config, |config| config.with_coercions(coercions))
+}
+
+/// Keep the parse result of up to `capacity` distinct expressions; 0, the default, disables the
+/// cache.
+///
+/// # Returns
+/// `true` on success, `false` if `config` is null
+///
+/// # Safety
+/// - `config` must be null or a valid pointer returned by `atree_config_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_config_set_parse_cache(
+    config: *mut AtreeConfigHandle,
+    capacity: usize,
+) -> bool {
+    update(config, |config| config.with_parse_cache(capacity))
+}
+
+/// Create a new A-Tree with the given attribute definitions and configuration.
+///
+/// The configuration is copied: it can be freed or reused right after this call.
+///
+/// # Returns
+/// Pointer to ATreeHandle on success, null on failure
+///
+/// # Safety
+/// - `defs` must point to valid memory containing `count` AtreeAttributeDef structs
+/// - Each `name` field must be a valid null-terminated C string
+/// - `config` must be a valid pointer returned by `atree_config_new()`
+/// - Caller must free the returned handle with `atree_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_new_with_config(
+    defs: *const AtreeAttributeDef,
+    count: usize,
+    config: *const AtreeConfigHandle,
+) -> *mut ATreeHandle {
+    new_with_config(defs, count, config).map_or(ptr::null_mut(), debug::into_raw)
+}
+
+unsafe fn new_with_config(
+    defs: *const AtreeAttributeDef,
+    count: usize,
+    config: *const AtreeConfigHandle,
+) -> Result<ATreeHandle, Failure> {
+    let Some(config) = config.as_ref() else {
+        return Err(Failure::invalid_arguments());
+    };
+    let definitions = crate::attribute_definitions(defs, count)?;
+    let tree = ATree::<u64>::with_config(&definitions, config.config)?;
+    Ok(ATreeHandle::new(tree))
+}
@@ -0,0 +1,111 @@
+//! Incremental iteration over the matches of a search, for the hosts that do not want to copy
+//! every match to a single array.
+
+use std::os::raw::c_void;
+use std::ptr;
+use std::slice;
+use std::vec;
+
+use crate::{search_borrowed, ATreeHandle};
+
+/// Opaque handle to the remaining matches of a search
+pub struct AtreeCursor {
+    ids: vec::IntoIter<u64>,
+}
+
+/// Search the A-Tree and iterate over the matches one at a time or in batches.
+///
+/// The builder is not consumed; it can be reset and reused right after this call. The matches
+/// are owned by the cursor, so the tree can be modified while iterating.
+///
+/// # Returns
+/// Pointer to a cursor on success, null on failure
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - Caller must free the returned cursor with `atree_cursor_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_search_cursor(
+    handle: *const ATreeHandle,
+    builder: *const c_void,
+) -> *mut AtreeCursor {
+    search_borrowed(handle, builder).map_or(ptr::null_mut(), |ids| {
+        Box::into_raw(Box::new(AtreeCursor {
+            ids: ids.into_iter(),
+        }))
+    })
+}
+
+/// Get the next match.
+///
+/// # Returns
+/// `true` if `out_id` received a match, `false` once all the matches were returned or if an
+/// argument is null
+///
+/// # Safety
+/// - `cursor` must be a valid pointer returned by `atree_search_cursor()`
+/// - `out_id` must point to writable memory for a `uint64_t`
+#[no_mangle]
+pub unsafe extern "C" fn atree_cursor_next(cursor: *mut AtreeCursor, out_id: *mut u64) -> bool {
+    if cursor.is_null() || out_id.is_null() {
+        return false;
+    }
+
+    match (*cursor).ids.next() {
+        Some(id) => {
+            *out_id = id;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Get up to `capacity` of the next matches.
+///
+/// # Returns
+/// Number of matches written to `out_ids`; 0 once all the matches were returned or if an argument
+/// is null
+///
+/// # Safety
+/// - `cursor` must be a valid pointer returned by `atree_search_cursor()`
+/// - `out_ids` must point to writable memory for `capacity` `uint64_t`
+#[no_mangle]
+pub unsafe extern "C" fn atree_cursor_next_batch(
+    cursor: *mut AtreeCursor,
+    out_ids: *mut u64,
+    capacity: usize,
+) -> usize {
+    if cursor.is_null() || out_ids.is_null() {
+        return 0;
+    }
+
+    let out_ids = slice::from_raw_parts_mut(out_ids, capacity);
+    let mut count = 0;
+    for (slot, id) in out_ids.iter_mut().zip(&mut (*cursor).ids) {
+        *slot = id;
+        count += 1;
+    }
+    count
+}
+
+/// Get the number of matches that were not returned yet.
+///
+/// # Safety
+/// - `cursor` must be null or a valid pointer returned by `atree_search_cursor()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_cursor_remaining(cursor: *const AtreeCursor) -> usize {
+    cursor.as_ref().map_or(0, |cursor| cursor.ids.len())
+}
+
+/// Free a cursor, whether all its matches were returned or not.
+///
+/// # Safety
+/// - `cursor` must be a valid pointer returned by `atree_search_cursor()`
+/// - `cursor` must not be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_cursor_free(cursor: *mut AtreeCursor) {
+    if !cursor.is_null() {
+        drop(Box::from_raw(cursor));
+    }
+}
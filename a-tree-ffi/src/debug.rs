@@ -0,0 +1,283 @@
+//! Opt-in tracking of the handles given to the host, to turn the use of a freed handle or of a
+//! handle of the wrong type into an error instead of undefined behavior.
+//!
+//! Once enabled, every handle is recorded in a registry along with its type and a generation
+//! number, and the memory of the freed handles is never released so that their addresses are
+//! not reused while the host may still hold them.
+
+use std::collections::HashMap;
+use std::mem::MaybeUninit;
+use std::os::raw::c_void;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use a_tree::EventBuilder;
+
+use crate::{log, ATreeHandle, ATreeStrHandle, ATreeU32Handle, AtreeEventHandle, AtreeLogLevel};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    handles: None,
+    generation: 0,
+});
+
+/// Type of a tracked handle
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AtreeHandleKind {
+    /// An `ATreeHandle`
+    HandleTree = 0,
+    /// An `ATreeU32Handle`
+    HandleU32Tree = 1,
+    /// An `ATreeStrHandle`
+    HandleStrTree = 2,
+    /// An event builder
+    HandleEventBuilder = 3,
+    /// An `AtreeEventHandle`
+    HandleEvent = 4,
+}
+
+impl AtreeHandleKind {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::HandleTree => "ATreeHandle",
+            Self::HandleU32Tree => "ATreeU32Handle",
+            Self::HandleStrTree => "ATreeStrHandle",
+            Self::HandleEventBuilder => "event builder",
+            Self::HandleEvent => "AtreeEventHandle",
+        }
+    }
+}
+
+/// Live handle reported by `atree_debug_live_handles()`
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct AtreeDebugHandle {
+    /// Address of the handle
+    pub address: *const c_void,
+    /// Type of the handle
+    pub kind: AtreeHandleKind,
+    /// Order of creation of the handle, starting at 1
+    pub generation: u64,
+}
+
+#[derive(Copy, Clone)]
+struct Entry {
+    kind: AtreeHandleKind,
+    generation: u64,
+    live: bool,
+}
+
+struct Registry {
+    handles: Option<HashMap<usize, Entry>>,
+    generation: u64,
+}
+
+impl Registry {
+    fn lock() -> MutexGuard<'static, Self> {
+        REGISTRY.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn insert(&mut self, address: usize, kind: AtreeHandleKind) {
+        self.generation += 1;
+        let entry = Entry {
+            kind,
+            generation: self.generation,
+            live: true,
+        };
+        self.handles
+            .get_or_insert_with(HashMap::new)
+            .insert(address, entry);
+    }
+
+    fn retire(&mut self, address: usize, kind: AtreeHandleKind) {
+        let handles = self.handles.get_or_insert_with(HashMap::new);
+        handles
+            .entry(address)
+            .and_modify(|entry| entry.live = false)
+            .or_insert(Entry {
+                kind,
+                generation: 0,
+                live: false,
+            });
+    }
+}
+
+/// A type of handle given to the host
+pub(crate) trait Tracked {
+    const KIND: AtreeHandleKind;
+}
+
+impl Tracked for ATreeHandle {
+    const KIND: AtreeHandleKind = AtreeHandleKind::HandleTree;
+}
+
+impl Tracked for ATreeU32Handle {
+    const KIND: AtreeHandleKind = AtreeHandleKind::HandleU32Tree;
+}
+
+impl Tracked for ATreeStrHandle {
+    const KIND: AtreeHandleKind = AtreeHandleKind::HandleStrTree;
+}
+
+impl Tracked for EventBuilder<'_> {
+    const KIND: AtreeHandleKind = AtreeHandleKind::HandleEventBuilder;
+}
+
+impl Tracked for AtreeEventHandle {
+    const KIND: AtreeHandleKind = AtreeHandleKind::HandleEvent;
+}
+
+fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Tell whether a handle is null or, when the tracking is enabled, not a live handle of its type.
+pub(crate) fn is_invalid<T: Tracked>(handle: *const T) -> bool {
+    if handle.is_null() {
+        return true;
+    }
+    if !enabled() {
+        return false;
+    }
+
+    let expected = T::KIND;
+    let entry = Registry::lock()
+        .handles
+        .as_ref()
+        .and_then(|handles| handles.get(&(handle as usize)).copied());
+    let problem = match entry {
+        Some(entry) if entry.live && entry.kind == expected => return false,
+        Some(entry) if entry.kind != expected => format!(
+            "{} expected but got {} {} (generation {})",
+            expected.name(),
+            if entry.live { "a live" } else { "a freed" },
+            entry.kind.name(),
+            entry.generation
+        ),
+        Some(entry) => format!(
+            "use of a freed {} (generation {})",
+            expected.name(),
+            entry.generation
+        ),
+        None => format!("unknown {}", expected.name()),
+    };
+    log::log(AtreeLogLevel::LogError, || {
+        format!("{problem} at {handle:p}")
+    });
+    true
+}
+
+/// Tell whether an event builder is null or, when the tracking is enabled, not a live builder.
+pub(crate) fn is_invalid_builder(builder: *const c_void) -> bool {
+    is_invalid(builder as *const EventBuilder)
+}
+
+/// Move a handle to the heap and record it.
+pub(crate) fn into_raw<T: Tracked>(value: T) -> *mut T {
+    let handle = Box::into_raw(Box::new(value));
+    track(handle);
+    handle
+}
+
+/// Record a handle allocated elsewhere.
+pub(crate) fn track<T: Tracked>(handle: *const T) {
+    if enabled() {
+        Registry::lock().insert(handle as usize, T::KIND);
+    }
+}
+
+/// Move a handle returned by `into_raw()` out of the heap.
+///
+/// When the tracking is enabled, the memory of the handle is kept so that its address is not
+/// reused.
+///
+/// # Safety
+/// `handle` must be a live handle returned by `into_raw()`.
+pub(crate) unsafe fn take<T: Tracked>(handle: *mut T) -> T {
+    let value = ptr::read(handle);
+    if enabled() {
+        Registry::lock().retire(handle as usize, T::KIND);
+    } else {
+        drop(Box::from_raw(handle.cast::<MaybeUninit<T>>()));
+    }
+    value
+}
+
+/// Free a handle returned by `into_raw()`.
+///
+/// # Safety
+/// `handle` must be a live handle returned by `into_raw()`.
+pub(crate) unsafe fn free<T: Tracked>(handle: *mut T) {
+    drop(take(handle));
+}
+
+/// Drop a reference to a handle recorded with `track()`, retiring it with its last reference.
+pub(crate) fn release_shared<T: Tracked>(handle: Arc<T>) {
+    if !enabled() {
+        drop(handle);
+        return;
+    }
+
+    let address = Arc::as_ptr(&handle) as usize;
+    // The registry is locked until the handle is retired so that a handle allocated at the same
+    // address is recorded after it
+    let mut registry = Registry::lock();
+    let last = Arc::into_inner(handle);
+    if last.is_some() {
+        registry.retire(address, T::KIND);
+    }
+    drop(registry);
+    drop(last);
+}
+
+/// Enable the tracking of the handles.
+///
+/// It must be called before any handle is created: the handles created beforehand are reported
+/// as unknown. The tracking cannot be disabled afterwards.
+///
+/// Once enabled, the functions given a freed handle, a handle of the wrong type or an unknown
+/// pointer report the problem to the log callback at the error level and fail as if the handle
+/// was null. The memory of the freed handles is not released, so this mode is meant for the
+/// debug builds of the host.
+#[no_mangle]
+pub extern "C" fn atree_debug_enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// List the live handles in their order of creation, to find the leaks of the host.
+///
+/// # Returns
+/// Number of live handles, which can be larger than `capacity`; always 0 if the tracking is not
+/// enabled with `atree_debug_enable()`
+///
+/// # Safety
+/// - `out` must be null or point to writable memory for `capacity` AtreeDebugHandle structs
+#[no_mangle]
+pub unsafe extern "C" fn atree_debug_live_handles(
+    out: *mut AtreeDebugHandle,
+    capacity: usize,
+) -> usize {
+    let registry = Registry::lock();
+    let Some(handles) = registry.handles.as_ref() else {
+        return 0;
+    };
+
+    let mut live: Vec<_> = handles
+        .iter()
+        .filter(|(_, entry)| entry.live)
+        .map(|(&address, entry)| AtreeDebugHandle {
+            address: address as *const c_void,
+            kind: entry.kind,
+            generation: entry.generation,
+        })
+        .collect();
+    live.sort_unstable_by_key(|handle| handle.generation);
+    if !out.is_null() {
+        for (index, handle) in live.iter().take(capacity).enumerate() {
+            *out.add(index) = *handle;
+        }
+    }
+    live.len()
+}
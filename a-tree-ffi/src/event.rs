@@ -0,0 +1,91 @@
+//! Events built in a single call from a flat JSON object, instead of one call per attribute.
+
+use std::os::raw::c_char;
+use std::ptr;
+
+use a_tree::Event;
+
+use crate::debug::{self, is_invalid};
+use crate::{log, search_event, to_str, ATreeHandle, AtreeLogLevel, AtreeSearchResult};
+
+/// Opaque handle to an event built by `atree_event_from_json()`
+pub struct AtreeEventHandle {
+    pub(crate) event: Event,
+}
+
+/// Build an event from a flat JSON object whose keys are the attribute names.
+///
+/// Booleans, strings and arrays are mapped onto the attributes of the same type, numbers onto the
+/// integer and float attributes and `null` leaves the attribute undefined, as does a missing key;
+/// see `ATree::event_from_json()`. The reason of a failure is reported to the log callback at the
+/// error level.
+///
+/// Unlike an event builder, the event can be searched any number of times.
+///
+/// # Returns
+/// Pointer to the event on success, null on failure
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `json` must be a valid null-terminated C string
+/// - Caller must free the returned event with `atree_event_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_from_json(
+    handle: *const ATreeHandle,
+    json: *const c_char,
+) -> *mut AtreeEventHandle {
+    if is_invalid(handle) || json.is_null() {
+        return ptr::null_mut();
+    }
+    let Ok(json) = to_str(json, "Invalid UTF-8 in JSON") else {
+        return ptr::null_mut();
+    };
+
+    event_from_json(&*handle, json)
+}
+
+pub(crate) fn event_from_json(handle: &ATreeHandle, json: &str) -> *mut AtreeEventHandle {
+    match handle.tree.event_from_json(json) {
+        Ok(event) => debug::into_raw(AtreeEventHandle { event }),
+        Err(error) => {
+            log::log(AtreeLogLevel::LogError, || {
+                format!("failed to build the event from JSON: {error}")
+            });
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Search the A-Tree for the expressions matching an event built by `atree_event_from_json()`.
+///
+/// The event is not consumed.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `event` must be a valid pointer returned by `atree_event_from_json()` for the same tree
+/// - Caller must free the returned result with `atree_search_result_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_search_event(
+    handle: *const ATreeHandle,
+    event: *const AtreeEventHandle,
+) -> AtreeSearchResult {
+    if is_invalid(handle) || is_invalid(event) {
+        return AtreeSearchResult::empty();
+    }
+
+    search_event(&*handle, &(*event).event)
+        .and_then(AtreeSearchResult::new)
+        .unwrap_or(AtreeSearchResult::empty())
+}
+
+/// Free an event built by `atree_event_from_json()`.
+///
+/// # Safety
+/// - `event` must be null or a valid pointer returned by `atree_event_from_json()`
+/// - `event` must not be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_free(event: *mut AtreeEventHandle) {
+    if !is_invalid(event) {
+        debug::free(event);
+    }
+}
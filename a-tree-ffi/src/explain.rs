@@ -0,0 +1,366 @@
+//! Explanations of the search results and warnings about the expressions as JSON documents, for
+//! the support tools that debug the targeting without linking the Rust crate.
+
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+
+use a_tree::{EventBuilder, Expression, Literal, PredicateExpression, PredicateOperator};
+use serde_json::{json, Value};
+
+use crate::debug::{is_invalid, is_invalid_builder};
+use crate::{alloc, log, to_str, ATreeHandle, AtreeLogLevel};
+
+fn operator_name(operator: PredicateOperator) -> &'static str {
+    match operator {
+        PredicateOperator::Variable => "variable",
+        PredicateOperator::NegatedVariable => "negated_variable",
+        PredicateOperator::In => "in",
+        PredicateOperator::NotIn => "not_in",
+        PredicateOperator::LessThan => "less_than",
+        PredicateOperator::LessThanEqual => "less_than_equal",
+        PredicateOperator::GreaterThan => "greater_than",
+        PredicateOperator::GreaterThanEqual => "greater_than_equal",
+        PredicateOperator::Equal => "equal",
+        PredicateOperator::NotEqual => "not_equal",
+        PredicateOperator::OneOf => "one_of",
+        PredicateOperator::NoneOf => "none_of",
+        PredicateOperator::AllOf => "all_of",
+        PredicateOperator::NotAllOf => "not_all_of",
+        PredicateOperator::IsNull => "is_null",
+        PredicateOperator::IsNotNull => "is_not_null",
+        PredicateOperator::IsEmpty => "is_empty",
+        PredicateOperator::IsNotEmpty => "is_not_empty",
+        PredicateOperator::StartsWith => "starts_with",
+        PredicateOperator::NotStartsWith => "not_starts_with",
+        PredicateOperator::Between => "between",
+        PredicateOperator::NotBetween => "not_between",
+        PredicateOperator::Contains => "contains",
+        PredicateOperator::NotContains => "not_contains",
+        PredicateOperator::WithinLast => "within_last",
+        PredicateOperator::NotWithinLast => "not_within_last",
+        PredicateOperator::IntersectAtLeast => "intersect_at_least",
+        PredicateOperator::IntersectFewerThan => "intersect_fewer_than",
+        PredicateOperator::FuzzyEqual => "fuzzy_equal",
+        PredicateOperator::NotFuzzyEqual => "not_fuzzy_equal",
+    }
+}
+
+fn literal_json(literal: Option<&Literal>) -> Value {
+    match literal {
+        None => Value::Null,
+        Some(Literal::Integer(value)) => json!(value),
+        Some(Literal::Float(value)) => json!(value),
+        Some(Literal::String(value)) => json!(value),
+        Some(Literal::IntegerList(values)) => json!(values),
+        Some(Literal::StringList(values)) => json!(values),
+        Some(Literal::IntegerRange(low, high)) => json!([low, high]),
+        Some(Literal::FloatRange(low, high)) => json!([low, high]),
+    }
+}
+
+/// Explain why a subscription matches an event or not, predicate by predicate.
+///
+/// The builder is not consumed. The returned JSON object has the following fields:
+/// - `id`: the subscription ID
+/// - `matches`: whether the subscription matches the event
+/// - `result`: `true`, `false` or `null` when the outcome depends on an undefined attribute
+/// - `predicates`: the predicates of the expression from left to right, each with its
+///   `attribute`, `operator` (e.g. `equal`, `one_of`), `value` (`null` for the operators without
+///   one), `count` (the minimum overlap of an `intersect_at_least`, `null` otherwise),
+///   `edit_distance` (the edits allowed by a `~=`, `null` otherwise) and `result` (`null` when
+///   the attribute is undefined)
+///
+/// # Returns
+/// Null-terminated JSON string, or null if the subscription does not exist or on failure
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - Caller must still free `builder` with `atree_event_builder_free()`
+/// - Caller must free the returned string with `atree_free_string()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_explain(
+    handle: *const ATreeHandle,
+    builder: *const c_void,
+    subscription_id: u64,
+) -> *mut c_char {
+    if is_invalid(handle) || is_invalid_builder(builder) {
+        return ptr::null_mut();
+    }
+
+    let handle_ref = &*handle;
+    let builder_ref = &*(builder as *const EventBuilder);
+    let event = match builder_ref.to_event() {
+        Ok(event) => event,
+        Err(error) => {
+            log::log(AtreeLogLevel::LogError, || {
+                format!("failed to build the event to explain: {error}")
+            });
+            return ptr::null_mut();
+        }
+    };
+    let Some(explanation) = handle_ref.tree.explain(&subscription_id, &event) else {
+        return ptr::null_mut();
+    };
+
+    let predicates: Vec<_> = explanation
+        .predicates()
+        .iter()
+        .map(|outcome| {
+            let predicate = outcome.predicate();
+            json!({
+                "attribute": predicate.attribute(),
+                "operator": operator_name(predicate.operator()),
+                "value": literal_json(predicate.value()),
+                "count": predicate.count(),
+                "edit_distance": predicate.edit_distance(),
+                "result": outcome.result(),
+            })
+        })
+        .collect();
+    let document = json!({
+        "id": subscription_id,
+        "matches": explanation.matches(),
+        "result": explanation.result(),
+        "predicates": predicates,
+    });
+    alloc::allocate_string(&document.to_string())
+}
+
+/// Check an expression for mistakes that still parse, without inserting it.
+///
+/// The returned JSON object has the following fields:
+/// - `valid`: whether the expression parses
+/// - `error`: the parse error, or `null` if the expression is valid
+/// - `warnings`: the problems found in a valid expression, each with a `code` and a `message`
+///
+/// The codes of the warnings are:
+/// - `always_true` / `always_false`: the expression matches every event or none
+/// - `contradiction`: an `and` requires a predicate and its negation, or two different values
+///   for the same attribute
+/// - `tautology`: an `or` accepts a predicate and its negation
+/// - `duplicate`: the same operand is repeated in an `and` or an `or`
+/// - `double_negation`: a `not` of a `not`
+/// - `single_value_list`: an `in` or `not in` with a single value, better written with `=` or
+///   `<>`
+///
+/// # Returns
+/// Null-terminated JSON string, or null on failure
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `expression` must be a valid null-terminated C string
+/// - Caller must free the returned string with `atree_free_string()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_lint_expression(
+    handle: *const ATreeHandle,
+    expression: *const c_char,
+) -> *mut c_char {
+    if is_invalid(handle) || expression.is_null() {
+        return ptr::null_mut();
+    }
+    let Ok(expression) = to_str(expression, "Invalid UTF-8 in expression") else {
+        return ptr::null_mut();
+    };
+
+    lint_expression(&*handle, expression)
+}
+
+pub(crate) fn lint_expression(handle: &ATreeHandle, expression: &str) -> *mut c_char {
+    let document = match handle.tree.parse_expression(expression) {
+        Ok(parsed) => {
+            let mut warnings = vec![];
+            lint(&parsed, &mut warnings);
+            let warnings: Vec<_> = warnings
+                .into_iter()
+                .map(|(code, message)| json!({ "code": code, "message": message }))
+                .collect();
+            json!({ "valid": true, "error": null, "warnings": warnings })
+        }
+        Err(error) => json!({ "valid": false, "error": error.to_string(), "warnings": [] }),
+    };
+    alloc::allocate_string(&document.to_string())
+}
+
+type Warning = (&'static str, String);
+
+fn lint(expression: &Expression, warnings: &mut Vec<Warning>) {
+    match expression {
+        Expression::Constant(true) => warnings.push((
+            "always_true",
+            "the expression matches every event".to_owned(),
+        )),
+        Expression::Constant(false) => {
+            warnings.push(("always_false", "the expression never matches".to_owned()))
+        }
+        _ => lint_node(expression, warnings),
+    }
+}
+
+fn lint_node(expression: &Expression, warnings: &mut Vec<Warning>) {
+    match expression {
+        Expression::And(..) | Expression::Or(..) => {
+            let is_and = matches!(expression, Expression::And(..));
+            let mut operands = vec![];
+            flatten(expression, is_and, &mut operands);
+            lint_operands(&operands, is_and, warnings);
+            for operand in operands {
+                lint_node(operand, warnings);
+            }
+        }
+        Expression::Not(operand) => {
+            if let Expression::Not(inner) = operand.as_ref() {
+                warnings.push((
+                    "double_negation",
+                    format!(
+                        "`not not {}` can be written `{}`",
+                        describe(inner),
+                        describe(inner)
+                    ),
+                ));
+            }
+            lint_node(operand, warnings);
+        }
+        Expression::Predicate(predicate) => {
+            let operator = match predicate.operator() {
+                PredicateOperator::In => "=",
+                PredicateOperator::NotIn => "<>",
+                _ => return,
+            };
+            let single = match predicate.value() {
+                Some(Literal::IntegerList(values)) if values.len() == 1 => values[0].to_string(),
+                Some(Literal::StringList(values)) if values.len() == 1 => {
+                    format!("{:?}", values[0])
+                }
+                _ => return,
+            };
+            warnings.push((
+                "single_value_list",
+                format!(
+                    "`{}` can be written `{} {operator} {single}`",
+                    describe_predicate(predicate),
+                    predicate.attribute()
+                ),
+            ));
+        }
+        Expression::Constant(_) => {}
+    }
+}
+
+/// Collect the operands of a chain of `and` (or `or`) operators.
+fn flatten<'a>(expression: &'a Expression, is_and: bool, operands: &mut Vec<&'a Expression>) {
+    match expression {
+        Expression::And(left, right) if is_and => {
+            flatten(left, is_and, operands);
+            flatten(right, is_and, operands);
+        }
+        Expression::Or(left, right) if !is_and => {
+            flatten(left, is_and, operands);
+            flatten(right, is_and, operands);
+        }
+        _ => operands.push(expression),
+    }
+}
+
+fn lint_operands(operands: &[&Expression], is_and: bool, warnings: &mut Vec<Warning>) {
+    let operator = if is_and { "and" } else { "or" };
+    for (index, left) in operands.iter().enumerate() {
+        for right in &operands[index + 1..] {
+            if left == right {
+                warnings.push((
+                    "duplicate",
+                    format!("`{}` is repeated in the same `{operator}`", describe(left)),
+                ));
+            } else if are_complements(left, right) {
+                let (code, outcome) = if is_and {
+                    ("contradiction", "never matches")
+                } else {
+                    ("tautology", "always matches")
+                };
+                warnings.push((
+                    code,
+                    format!(
+                        "`{} {operator} {}` {outcome}",
+                        describe(left),
+                        describe(right)
+                    ),
+                ));
+            } else if is_and && are_conflicting_equalities(left, right) {
+                warnings.push((
+                    "contradiction",
+                    format!("`{} and {}` never matches", describe(left), describe(right)),
+                ));
+            }
+        }
+    }
+}
+
+fn are_complements(left: &Expression, right: &Expression) -> bool {
+    match (left, right) {
+        (Expression::Not(operand), other) | (other, Expression::Not(operand)) => {
+            operand.as_ref() == other
+        }
+        (Expression::Predicate(left), Expression::Predicate(right)) => {
+            left.attribute() == right.attribute()
+                && matches!(
+                    (left.operator(), right.operator()),
+                    (
+                        PredicateOperator::Variable,
+                        PredicateOperator::NegatedVariable
+                    ) | (
+                        PredicateOperator::NegatedVariable,
+                        PredicateOperator::Variable
+                    )
+                )
+        }
+        _ => false,
+    }
+}
+
+fn are_conflicting_equalities(left: &Expression, right: &Expression) -> bool {
+    match (left, right) {
+        (Expression::Predicate(left), Expression::Predicate(right)) => {
+            left.attribute() == right.attribute()
+                && left.operator() == PredicateOperator::Equal
+                && right.operator() == PredicateOperator::Equal
+                && left.value() != right.value()
+        }
+        _ => false,
+    }
+}
+
+/// Write an expression back in the syntax of the expressions, for the messages.
+fn describe(expression: &Expression) -> String {
+    let group = |expression: &Expression| match expression {
+        Expression::And(..) | Expression::Or(..) => format!("({})", describe(expression)),
+        _ => describe(expression),
+    };
+    match expression {
+        Expression::And(left, right) => format!("{} and {}", group(left), group(right)),
+        Expression::Or(left, right) => format!("{} or {}", group(left), group(right)),
+        Expression::Not(operand) => format!("not {}", group(operand)),
+        Expression::Predicate(predicate) => describe_predicate(predicate),
+        Expression::Constant(value) => value.to_string(),
+    }
+}
+
+fn describe_predicate(predicate: &PredicateExpression) -> String {
+    let attribute = predicate.attribute();
+    let operator = predicate.operator();
+    let value = match predicate.value() {
+        None => None,
+        Some(Literal::Integer(value)) => Some(value.to_string()),
+        Some(Literal::Float(value)) => Some(value.to_string()),
+        Some(Literal::String(value)) => Some(format!("{value:?}")),
+        Some(Literal::IntegerList(values)) => Some(format!("{values:?}")),
+        Some(Literal::StringList(values)) => Some(format!("{values:?}")),
+        Some(Literal::IntegerRange(low, high)) => Some(format!("{low} and {high}")),
+        Some(Literal::FloatRange(low, high)) => Some(format!("{low} and {high}")),
+    };
+    match (operator, value) {
+        (PredicateOperator::Variable, _) => attribute.to_owned(),
+        (PredicateOperator::NegatedVariable, _) => format!("not {attribute}"),
+        (_, Some(value)) => format!("{attribute} {operator} {value}"),
+        (_, None) => format!("{attribute} {operator}"),
+    }
+}
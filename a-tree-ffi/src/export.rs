@@ -0,0 +1,94 @@
+//! Exports streamed to the host in chunks, so that exporting a large tree does not need a copy
+//! of the whole document.
+
+use std::io::{self, BufWriter, Write};
+use std::os::raw::{c_char, c_void};
+
+use crate::debug::is_invalid;
+use crate::{ATreeHandle, AtreeResult, Failure, ATREE_ERROR_INVALID_ARGUMENT};
+
+/// Size of the buffer flushed to the write callback
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Format of `atree_export()`
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AtreeExportFormat {
+    /// The Graphviz DOT format of `atree_to_graphviz()`
+    ExportGraphviz = 0,
+    /// The JSON document of `atree_export_json()`
+    ExportJson = 1,
+}
+
+/// Receive a chunk of an export; `chunk` is not null-terminated and only valid during the call
+pub type AtreeWriteCallback =
+    Option<unsafe extern "C" fn(chunk: *const c_char, len: usize, ctx: *mut c_void)>;
+
+struct CallbackWriter {
+    write: unsafe extern "C" fn(*const c_char, usize, *mut c_void),
+    ctx: *mut c_void,
+}
+
+impl Write for CallbackWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !buf.is_empty() {
+            unsafe { (self.write)(buf.as_ptr().cast(), buf.len(), self.ctx) };
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Export the tree and pass the output to `write` in chunks of about 64 KiB.
+///
+/// Unlike `atree_to_graphviz()` and `atree_export_json()`, the document is never held in memory
+/// as a whole. `write` is called on the calling thread, in order, before this function returns.
+///
+/// # Returns
+/// A successful result if the whole document was written; the chunks already passed to `write`
+/// are not taken back on failure
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `ctx` is passed to `write` as is
+/// - Caller must free `error_message` with `atree_free_error()` on failure
+#[no_mangle]
+pub unsafe extern "C" fn atree_export(
+    handle: *const ATreeHandle,
+    format: AtreeExportFormat,
+    write: AtreeWriteCallback,
+    ctx: *mut c_void,
+) -> AtreeResult {
+    export(handle, format, write, ctx).into()
+}
+
+pub(crate) unsafe fn export(
+    handle: *const ATreeHandle,
+    format: AtreeExportFormat,
+    write: AtreeWriteCallback,
+    ctx: *mut c_void,
+) -> Result<(), Failure> {
+    let Some(write) = write else {
+        return Err(Failure::invalid_arguments());
+    };
+    if is_invalid(handle) {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let handle_ref = &*handle;
+    let mut writer = BufWriter::with_capacity(CHUNK_SIZE, CallbackWriter { write, ctx });
+    let written = match format {
+        AtreeExportFormat::ExportGraphviz => handle_ref.tree.write_graphviz(&mut writer).is_ok(),
+        AtreeExportFormat::ExportJson => handle_ref.tree.write_json(&mut writer).is_ok(),
+    };
+    if !written || writer.flush().is_err() {
+        return Err(Failure::new(
+            ATREE_ERROR_INVALID_ARGUMENT,
+            "Failed to export the tree",
+        ));
+    }
+    Ok(())
+}
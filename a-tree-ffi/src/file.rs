@@ -0,0 +1,169 @@
+//! Snapshots saved to and loaded from files, for the hosts that checkpoint a tree to disk.
+
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::os::raw::c_char;
+use std::path::Path;
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use a_tree::ATree;
+
+use crate::debug::{self, is_invalid};
+#[cfg(not(unix))]
+use crate::to_str;
+use crate::{log, ATreeHandle, AtreeLogLevel, AtreeResult, Failure, ATREE_ERROR_IO};
+
+/// Borrow a path; it must be valid UTF-8 on the platforms where the paths are not bytes.
+unsafe fn to_path<'a>(path: *const c_char) -> Result<&'a Path, Failure> {
+    #[cfg(unix)]
+    {
+        use std::ffi::{CStr, OsStr};
+        use std::os::unix::ffi::OsStrExt;
+
+        Ok(Path::new(OsStr::from_bytes(
+            CStr::from_ptr(path).to_bytes(),
+        )))
+    }
+    #[cfg(not(unix))]
+    {
+        to_str(path, "Invalid UTF-8 in path").map(Path::new)
+    }
+}
+
+/// Save a binary snapshot of the tree to a file, replacing it atomically.
+///
+/// The snapshot is written to a temporary file next to `path`, flushed to the disk and then
+/// renamed over `path`, so that a crash leaves either the previous file or the new one. The
+/// payloads attached with `atree_insert_with_payload()` are not part of the snapshot.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `path` must be a valid null-terminated C string
+/// - Caller must free `error_message` with `atree_free_error()` on failure
+#[no_mangle]
+pub unsafe extern "C" fn atree_save_file(
+    handle: *const ATreeHandle,
+    path: *const c_char,
+) -> AtreeResult {
+    save_file(handle, path).into()
+}
+
+pub(crate) unsafe fn save_file(
+    handle: *const ATreeHandle,
+    path: *const c_char,
+) -> Result<(), Failure> {
+    if is_invalid(handle) || path.is_null() {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let path = to_path(path)?;
+    let handle_ref = &*handle;
+    write_atomically(path, &handle_ref.tree.to_snapshot()).map_err(|error| Failure {
+        status: ATREE_ERROR_IO,
+        message: Cow::Owned(format!(
+            "failed to save the snapshot to {}: {error}",
+            path.display()
+        )),
+    })
+}
+
+/// Return the fingerprint of the snapshot saved by `atree_save_file()`.
+///
+/// Two trees with the same configuration, schema and subscriptions have the same snapshot and
+/// fingerprint whatever the order of their insertions, so it can be used to address the
+/// snapshots distributed to a fleet. Returns 0 if `handle` is invalid.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_fingerprint(handle: *const ATreeHandle) -> u64 {
+    if is_invalid(handle) {
+        return 0;
+    }
+    (*handle).tree.fingerprint()
+}
+
+/// Number of the next temporary file, so that the threads saving to the same path do not write to
+/// the same temporary file
+static TEMPORARY_FILES: AtomicU64 = AtomicU64::new(0);
+
+fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the path has no file name"))?;
+    let mut temporary_name = OsString::from(".");
+    temporary_name.push(file_name);
+    temporary_name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        TEMPORARY_FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temporary = path.with_file_name(temporary_name);
+
+    let written = write_and_sync(&temporary, bytes).and_then(|()| fs::rename(&temporary, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temporary);
+        return written;
+    }
+    sync_directory(path)
+}
+
+fn write_and_sync(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(bytes)?;
+    file.sync_all()
+}
+
+/// Persist the rename by flushing the directory holding the file.
+#[cfg(unix)]
+fn sync_directory(path: &Path) -> io::Result<()> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(directory)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_directory(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Create a new A-Tree from a snapshot saved by `atree_save_file()`.
+///
+/// The reason of a failure is reported to the log callback at the error level.
+///
+/// # Returns
+/// Pointer to ATreeHandle on success, null on failure
+///
+/// # Safety
+/// - `path` must be a valid null-terminated C string
+/// - Caller must free the returned handle with `atree_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_load_file(path: *const c_char) -> *mut ATreeHandle {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+    let Ok(path) = to_path(path) else {
+        return ptr::null_mut();
+    };
+
+    let loaded = fs::read(path)
+        .map_err(|error| error.to_string())
+        .and_then(|bytes| ATree::<u64>::from_snapshot(&bytes).map_err(|error| error.to_string()));
+    match loaded {
+        Ok(tree) => debug::into_raw(ATreeHandle::new(tree)),
+        Err(error) => {
+            log::log(AtreeLogLevel::LogError, || {
+                format!(
+                    "failed to load the snapshot from {}: {error}",
+                    path.display()
+                )
+            });
+            ptr::null_mut()
+        }
+    }
+}
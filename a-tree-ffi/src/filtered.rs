@@ -0,0 +1,94 @@
+//! Searches restricted to a set of candidate subscriptions or capped to a number of matches, so
+//! that the hosts can apply their budget filters and result limits inside the search.
+
+use std::collections::HashSet;
+use std::os::raw::c_void;
+use std::slice;
+
+use a_tree::EventBuilder;
+
+use crate::debug::{self, is_invalid, is_invalid_builder};
+use crate::{timed_search, ATreeHandle, AtreeSearchResult, Failure};
+
+/// Search the A-Tree for the matching expressions among the candidate subscriptions.
+///
+/// The subscriptions that are not candidates are left out of the result, e.g. those without a
+/// remaining budget; the unknown candidates are ignored.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `builder` will be consumed by this call and must not be used after
+/// - `candidates` must point to `count` subscription IDs; it can be null if `count` is 0
+/// - Caller must free the returned result with `atree_search_result_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_search_among(
+    handle: *const ATreeHandle,
+    builder: *mut c_void,
+    candidates: *const u64,
+    count: usize,
+) -> AtreeSearchResult {
+    search_among(handle, builder, candidates, count)
+        .and_then(AtreeSearchResult::new)
+        .unwrap_or(AtreeSearchResult::empty())
+}
+
+pub(crate) unsafe fn search_among(
+    handle: *const ATreeHandle,
+    builder: *mut c_void,
+    candidates: *const u64,
+    count: usize,
+) -> Result<Vec<u64>, Failure> {
+    if is_invalid(handle) || is_invalid_builder(builder) || (candidates.is_null() && count > 0) {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let candidates: HashSet<u64> = if count == 0 {
+        HashSet::new()
+    } else {
+        slice::from_raw_parts(candidates, count)
+            .iter()
+            .copied()
+            .collect()
+    };
+    let event = debug::take(builder as *mut EventBuilder).build()?;
+    let tree = &(*handle).tree;
+    let matches = timed_search(|| tree.search_among(&event, &candidates))?;
+    Ok(matches.into_iter().copied().collect())
+}
+
+/// Search the A-Tree for at most `max` matching expressions.
+///
+/// The search stops as soon as `max` matches are found, so which of the matching subscriptions
+/// are returned is unspecified.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `builder` will be consumed by this call and must not be used after
+/// - Caller must free the returned result with `atree_search_result_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_search_limited(
+    handle: *const ATreeHandle,
+    builder: *mut c_void,
+    max: usize,
+) -> AtreeSearchResult {
+    search_limited(handle, builder, max)
+        .and_then(AtreeSearchResult::new)
+        .unwrap_or(AtreeSearchResult::empty())
+}
+
+pub(crate) unsafe fn search_limited(
+    handle: *const ATreeHandle,
+    builder: *mut c_void,
+    max: usize,
+) -> Result<Vec<u64>, Failure> {
+    if is_invalid(handle) || is_invalid_builder(builder) {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let event = debug::take(builder as *mut EventBuilder).build()?;
+    let tree = &(*handle).tree;
+    let matches = timed_search(|| tree.search_limited(&event, max))?;
+    Ok(matches.into_iter().copied().collect())
+}
@@ -0,0 +1,339 @@
+//! Handles for the trees keyed on 32-bit IDs or on strings, for the hosts whose subscription IDs
+//! are not 64-bit integers.
+
+use std::ffi::{CStr, CString};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::sync::Arc;
+
+use a_tree::{ATree, EventBuilder};
+
+use crate::debug::{self, is_invalid, is_invalid_builder};
+use crate::{
+    alloc, attribute_definitions, log::LogObserver, log_insert_failure, search_tree, to_str,
+    AtreeAttributeDef, AtreeResult, Failure, ATREE_ERROR_OUT_OF_MEMORY,
+};
+
+/// Opaque handle to an A-Tree whose subscription IDs are 32-bit integers
+pub struct ATreeU32Handle {
+    tree: ATree<u32>,
+}
+
+/// Opaque handle to an A-Tree whose subscription IDs are null-terminated strings
+pub struct ATreeStrHandle {
+    tree: ATree<CString>,
+}
+
+/// Search results of an `ATreeU32Handle`
+#[repr(C)]
+pub struct AtreeU32SearchResult {
+    /// Array of matching subscription IDs
+    pub ids: *mut u32,
+    /// Number of matches
+    pub count: usize,
+}
+
+/// Search results of an `ATreeStrHandle`
+#[repr(C)]
+pub struct AtreeStrSearchResult {
+    /// Array of matching subscription IDs, owned by the tree
+    pub ids: *mut *const c_char,
+    /// Number of matches
+    pub count: usize,
+}
+
+unsafe fn new_tree<T>(defs: *const AtreeAttributeDef, count: usize) -> Result<ATree<T>, Failure>
+where
+    T: Eq + Hash + Clone + Debug,
+{
+    let definitions = attribute_definitions(defs, count)?;
+    let mut tree = ATree::new(&definitions)?;
+    tree.register_observer(Arc::new(LogObserver));
+    Ok(tree)
+}
+
+unsafe fn insert<T>(
+    tree: &mut ATree<T>,
+    subscription_id: T,
+    expression: *const c_char,
+) -> Result<(), Failure>
+where
+    T: Eq + Hash + Clone + Debug,
+{
+    if expression.is_null() {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let expr_str = to_str(expression, "Invalid UTF-8 in expression")?;
+    tree.insert(&subscription_id, expr_str)
+        .inspect_err(|error| log_insert_failure(format!("{subscription_id:?}"), error))?;
+    Ok(())
+}
+
+unsafe fn search<T>(tree: &ATree<T>, builder: *mut c_void) -> Result<Vec<&T>, Failure>
+where
+    T: Eq + Hash + Clone + Debug,
+{
+    if is_invalid_builder(builder) {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let builder_owned = debug::take(builder as *mut EventBuilder);
+    let event = builder_owned.build()?;
+    search_tree(tree, &event)
+}
+
+fn allocate<V: Copy>(matches: Vec<V>) -> Result<(*mut V, usize), Failure> {
+    let count = matches.len();
+    let ids = alloc::allocate_slice(matches);
+    if ids.is_null() && count > 0 {
+        return Err(Failure::new(
+            ATREE_ERROR_OUT_OF_MEMORY,
+            "Failed to allocate the search results",
+        ));
+    }
+    Ok((ids, count))
+}
+
+/// Create a new A-Tree whose subscription IDs are 32-bit integers.
+///
+/// # Returns
+/// Pointer to ATreeU32Handle on success, null on failure
+///
+/// # Safety
+/// - `defs` must point to valid memory containing `count` AtreeAttributeDef structs
+/// - Each `name` field must be a valid null-terminated C string
+/// - Caller must free the returned handle with `atree_u32_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_new_u32_ids(
+    defs: *const AtreeAttributeDef,
+    count: usize,
+) -> *mut ATreeU32Handle {
+    new_tree(defs, count).map_or(ptr::null_mut(), |tree| {
+        debug::into_raw(ATreeU32Handle { tree })
+    })
+}
+
+/// Free an A-Tree handle created by `atree_new_u32_ids()`.
+///
+/// # Safety
+/// - `handle` must be null or a valid pointer returned by `atree_new_u32_ids()`
+/// - `handle` must not be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_u32_free(handle: *mut ATreeU32Handle) {
+    if !is_invalid(handle) {
+        debug::free(handle);
+    }
+}
+
+/// Insert a boolean expression associated with a 32-bit subscription ID.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new_u32_ids()`
+/// - `expression` must be a valid null-terminated C string
+/// - Caller must free result.error_message with `atree_free_error()` if !success
+#[no_mangle]
+pub unsafe extern "C" fn atree_u32_insert(
+    handle: *mut ATreeU32Handle,
+    subscription_id: u32,
+    expression: *const c_char,
+) -> AtreeResult {
+    if is_invalid(handle) {
+        return Err(Failure::invalid_arguments()).into();
+    }
+
+    insert(&mut (*handle).tree, subscription_id, expression).into()
+}
+
+/// Delete a subscription by its 32-bit ID.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new_u32_ids()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_u32_delete(handle: *mut ATreeU32Handle, subscription_id: u32) {
+    if !is_invalid(handle) {
+        (*handle).tree.delete(&subscription_id);
+    }
+}
+
+/// Create a new event builder for a tree created by `atree_new_u32_ids()`.
+///
+/// The builder is filled with the `atree_event_builder_with_*()` functions.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new_u32_ids()`
+/// - Caller must pass the builder to `atree_u32_search()` or free it with
+///   `atree_event_builder_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_u32_event_builder_new(handle: *const ATreeU32Handle) -> *mut c_void {
+    if is_invalid(handle) {
+        return ptr::null_mut();
+    }
+
+    debug::into_raw((*handle).tree.make_event()) as *mut c_void
+}
+
+/// Search a tree created by `atree_new_u32_ids()` for matching expressions.
+///
+/// # Returns
+/// Search result containing the matching IDs; empty on failure
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new_u32_ids()`
+/// - `builder` must be a valid pointer returned by `atree_u32_event_builder_new()`
+/// - `builder` will be consumed by this call and must not be used after
+/// - Caller must free the returned result with `atree_u32_search_result_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_u32_search(
+    handle: *const ATreeU32Handle,
+    builder: *mut c_void,
+) -> AtreeU32SearchResult {
+    let empty = AtreeU32SearchResult {
+        ids: ptr::null_mut(),
+        count: 0,
+    };
+    if is_invalid(handle) {
+        return empty;
+    }
+
+    search(&(*handle).tree, builder)
+        .and_then(|matches| allocate(matches.into_iter().copied().collect()))
+        .map_or(empty, |(ids, count)| AtreeU32SearchResult { ids, count })
+}
+
+/// Free a search result returned by `atree_u32_search()`.
+///
+/// # Safety
+/// - `result` must be a valid search result returned by `atree_u32_search()`
+/// - `result` must not be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_u32_search_result_free(result: AtreeU32SearchResult) {
+    alloc::free_slice(result.ids, result.count);
+}
+
+/// Create a new A-Tree whose subscription IDs are null-terminated strings (UUIDs, keys, ...).
+///
+/// # Returns
+/// Pointer to ATreeStrHandle on success, null on failure
+///
+/// # Safety
+/// - `defs` must point to valid memory containing `count` AtreeAttributeDef structs
+/// - Each `name` field must be a valid null-terminated C string
+/// - Caller must free the returned handle with `atree_str_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_new_str_ids(
+    defs: *const AtreeAttributeDef,
+    count: usize,
+) -> *mut ATreeStrHandle {
+    new_tree(defs, count).map_or(ptr::null_mut(), |tree| {
+        debug::into_raw(ATreeStrHandle { tree })
+    })
+}
+
+/// Free an A-Tree handle created by `atree_new_str_ids()`.
+///
+/// # Safety
+/// - `handle` must be null or a valid pointer returned by `atree_new_str_ids()`
+/// - `handle` must not be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_str_free(handle: *mut ATreeStrHandle) {
+    if !is_invalid(handle) {
+        debug::free(handle);
+    }
+}
+
+/// Insert a boolean expression associated with a string subscription ID, which is copied.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new_str_ids()`
+/// - `subscription_id` and `expression` must be valid null-terminated C strings
+/// - Caller must free result.error_message with `atree_free_error()` if !success
+#[no_mangle]
+pub unsafe extern "C" fn atree_str_insert(
+    handle: *mut ATreeStrHandle,
+    subscription_id: *const c_char,
+    expression: *const c_char,
+) -> AtreeResult {
+    if is_invalid(handle) || subscription_id.is_null() {
+        return Err(Failure::invalid_arguments()).into();
+    }
+
+    let subscription_id = CStr::from_ptr(subscription_id).to_owned();
+    insert(&mut (*handle).tree, subscription_id, expression).into()
+}
+
+/// Delete a subscription by its string ID.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new_str_ids()`
+/// - `subscription_id` must be a valid null-terminated C string
+#[no_mangle]
+pub unsafe extern "C" fn atree_str_delete(
+    handle: *mut ATreeStrHandle,
+    subscription_id: *const c_char,
+) {
+    if !is_invalid(handle) && !subscription_id.is_null() {
+        let subscription_id = CStr::from_ptr(subscription_id).to_owned();
+        (*handle).tree.delete(&subscription_id);
+    }
+}
+
+/// Create a new event builder for a tree created by `atree_new_str_ids()`.
+///
+/// The builder is filled with the `atree_event_builder_with_*()` functions.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new_str_ids()`
+/// - Caller must pass the builder to `atree_str_search()` or free it with
+///   `atree_event_builder_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_str_event_builder_new(handle: *const ATreeStrHandle) -> *mut c_void {
+    if is_invalid(handle) {
+        return ptr::null_mut();
+    }
+
+    debug::into_raw((*handle).tree.make_event()) as *mut c_void
+}
+
+/// Search a tree created by `atree_new_str_ids()` for matching expressions.
+///
+/// The IDs are not copied: they point to the strings owned by the tree, which remain valid until
+/// their subscription is deleted or the tree is freed.
+///
+/// # Returns
+/// Search result containing the matching IDs; empty on failure
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new_str_ids()`
+/// - `builder` must be a valid pointer returned by `atree_str_event_builder_new()`
+/// - `builder` will be consumed by this call and must not be used after
+/// - Caller must free the returned result with `atree_str_search_result_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_str_search(
+    handle: *const ATreeStrHandle,
+    builder: *mut c_void,
+) -> AtreeStrSearchResult {
+    let empty = AtreeStrSearchResult {
+        ids: ptr::null_mut(),
+        count: 0,
+    };
+    if is_invalid(handle) {
+        return empty;
+    }
+
+    search(&(*handle).tree, builder)
+        .and_then(|matches| allocate(matches.into_iter().map(|id| id.as_ptr()).collect()))
+        .map_or(empty, |(ids, count)| AtreeStrSearchResult { ids, count })
+}
+
+/// Free a search result returned by `atree_str_search()`; the IDs remain owned by the tree.
+///
+/// # Safety
+/// - `result` must be a valid search result returned by `atree_str_search()`
+/// - `result` must not be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_str_search_result_free(result: AtreeStrSearchResult) {
+    alloc::free_slice(result.ids, result.count);
+}
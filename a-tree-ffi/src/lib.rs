@@ -2,16 +2,95 @@
 //!
 //! This crate provides a C-compatible API for using the a-tree library from C/C++ code.
 
-use std::ffi::{CStr, CString};
+use std::borrow::Cow;
+use std::ffi::CStr;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
 use std::os::raw::{c_char, c_void};
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr;
 use std::slice;
+use std::sync::Arc;
+use std::time::Instant;
 
-use a_tree::{ATree, AttributeDefinition};
+use a_tree::{ATree, ATreeError, AttributeDefinition, EventError, InternedString, ParserError};
+use debug::{is_invalid, is_invalid_builder};
+use lalrpop_util::ParseError;
+
+mod alloc;
+mod arena;
+mod ast;
+mod bitmap;
+mod builders;
+mod concurrent;
+mod config;
+mod cursor;
+mod debug;
+mod event;
+mod explain;
+mod export;
+mod file;
+mod filtered;
+mod ids;
+mod log;
+mod parallel;
+mod payload;
+mod pool;
+mod schema;
+mod session;
+mod shared;
+mod sized;
+mod status;
+mod tags;
+mod version;
+mod wide;
+
+pub use alloc::*;
+pub use arena::*;
+pub use ast::*;
+pub use bitmap::*;
+pub use builders::*;
+pub use concurrent::*;
+pub use config::*;
+pub use cursor::*;
+pub use debug::*;
+pub use event::*;
+pub use explain::*;
+pub use export::*;
+pub use file::*;
+pub use filtered::*;
+pub use ids::*;
+pub use log::*;
+pub use parallel::*;
+pub use payload::*;
+pub use pool::*;
+pub use schema::*;
+pub use session::*;
+pub use shared::*;
+pub use sized::*;
+pub use status::*;
+pub use tags::*;
+pub use version::*;
+pub use wide::*;
 
 /// Opaque handle to an ATree instance
+#[derive(Clone)]
 pub struct ATreeHandle {
     tree: ATree<u64>,
+    payloads: Payloads,
+    schema: Schema,
+}
+
+impl ATreeHandle {
+    fn new(mut tree: ATree<u64>) -> Self {
+        tree.register_observer(Arc::new(LogObserver));
+        tree.set_executor(Arc::new(pool::WorkerPool));
+        Self {
+            schema: Schema::new(&tree),
+            tree,
+            payloads: Payloads::new(),
+        }
+    }
 }
 
 /// Attribute types supported by the A-Tree
@@ -40,6 +119,26 @@ pub struct AtreeResult {
     pub error_message: *mut c_char,
 }
 
+/// Size of an A-Tree
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct AtreeStats {
+    /// Number of subscriptions
+    pub subscriptions: usize,
+    /// Number of nodes, shared or not
+    pub nodes: usize,
+    /// Number of distinct predicates (leaf nodes)
+    pub predicates: usize,
+    /// Number of root nodes
+    pub roots: usize,
+    /// Height of the tallest expression
+    pub max_level: usize,
+    /// Number of distinct strings used by the expressions and the interned values
+    pub strings: usize,
+    /// Estimation of the bytes used by the tree
+    pub memory_usage: usize,
+}
+
 /// Search result containing matching subscription IDs
 #[repr(C)]
 pub struct AtreeSearchResult {
@@ -47,6 +146,255 @@ pub struct AtreeSearchResult {
     pub count: usize,
 }
 
+/// Kind of error reported in an `AtreeParseError`
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AtreeParseErrorCode {
+    /// The expression is valid
+    NoError = 0,
+    /// A token cannot be recognized
+    InvalidToken = 1,
+    /// The expression ends too early
+    UnexpectedEnd = 2,
+    /// A token is not expected at this position
+    UnexpectedToken = 3,
+    /// A token is found after the end of the expression
+    ExtraToken = 4,
+    /// A literal is malformed (e.g. an out of range integer or an unterminated string)
+    Lexical = 5,
+    /// The expression refers to an attribute that is not defined
+    UnknownAttribute = 6,
+    /// An attribute is used with a value or an operator that does not match its type
+    TypeMismatch = 7,
+    /// The arguments passed to the function are invalid (null pointer or invalid UTF-8)
+    InvalidArgument = 8,
+    /// Any other error
+    OtherError = 9,
+    /// The subscription ID is already present and the tree rejects the duplicates
+    DuplicateId = 10,
+    /// A string is not one of the values of the dictionary of its attribute
+    UnknownValue = 11,
+}
+
+/// Detailed error returned when an expression cannot be parsed
+///
+/// The position points to the start of the offending token. When the error cannot be located,
+/// `line` and `column` are 0.
+#[repr(C)]
+pub struct AtreeParseError {
+    pub code: AtreeParseErrorCode,
+    /// 1-based line of the error
+    pub line: usize,
+    /// 1-based column of the error, in characters
+    pub column: usize,
+    /// Byte offset of the error in the expression
+    pub offset: usize,
+    /// Byte length of the offending token (0 at the end of the expression)
+    pub length: usize,
+    /// Human readable message; must be freed with `atree_parse_error_free()`
+    pub message: *mut c_char,
+}
+
+impl AtreeParseError {
+    fn new(code: AtreeParseErrorCode, message: &str) -> Self {
+        Self {
+            code,
+            line: 0,
+            column: 0,
+            offset: 0,
+            length: 0,
+            message: to_c_message(message),
+        }
+    }
+
+    fn from_error(expression: &str, error: &ATreeError) -> Self {
+        let code = match error {
+            ATreeError::ParseError(ParseError::InvalidToken { .. }) => {
+                AtreeParseErrorCode::InvalidToken
+            }
+            ATreeError::ParseError(ParseError::UnrecognizedEof { .. }) => {
+                AtreeParseErrorCode::UnexpectedEnd
+            }
+            ATreeError::ParseError(ParseError::UnrecognizedToken { .. }) => {
+                AtreeParseErrorCode::UnexpectedToken
+            }
+            ATreeError::ParseError(ParseError::ExtraToken { .. }) => {
+                AtreeParseErrorCode::ExtraToken
+            }
+            ATreeError::ParseError(ParseError::User {
+                error: ParserError::Lexical(_),
+            }) => AtreeParseErrorCode::Lexical,
+            ATreeError::ParseError(ParseError::User {
+                error: ParserError::Event(error),
+            })
+            | ATreeError::Event(error) => match error {
+                EventError::NonExistingAttribute(_) => AtreeParseErrorCode::UnknownAttribute,
+                EventError::WrongType { .. } | EventError::MismatchingTypes { .. } => {
+                    AtreeParseErrorCode::TypeMismatch
+                }
+                EventError::UnknownValue(_) => AtreeParseErrorCode::UnknownValue,
+                _ => AtreeParseErrorCode::OtherError,
+            },
+            ATreeError::DuplicateId(_) => AtreeParseErrorCode::DuplicateId,
+            ATreeError::ParameterCount { .. } | ATreeError::InvalidParameter(_) => {
+                AtreeParseErrorCode::OtherError
+            }
+        };
+        let mut result = Self::new(code, &error.to_string());
+        if let Some(span) = error.span(expression) {
+            let before = &expression[..span.start];
+            result.offset = span.start;
+            result.length = span.len();
+            result.line = before.matches('\n').count() + 1;
+            result.column = before
+                .rsplit_once('\n')
+                .map_or(before, |(_, line)| line)
+                .chars()
+                .count()
+                + 1;
+        }
+        result
+    }
+}
+
+/// Write the error to the out-parameter, if any.
+unsafe fn report_parse_error(error: *mut AtreeParseError, value: AtreeParseError) {
+    if error.is_null() {
+        atree_parse_error_free(value);
+    } else {
+        *error = value;
+    }
+}
+
+/// Message returned when the actual error message cannot be allocated or contains a NUL byte
+///
+/// It is never freed: the free functions ignore it.
+static FALLBACK_MESSAGE: &CStr = c"Failed to build the error message";
+
+/// Convert a message to a C string, falling back to a static message on failure.
+fn to_c_message(message: &str) -> *mut c_char {
+    let string = alloc::allocate_string(message);
+    if string.is_null() {
+        log::log(AtreeLogLevel::LogError, || {
+            format!("failed to build the error message: {message}")
+        });
+        FALLBACK_MESSAGE.as_ptr().cast_mut()
+    } else {
+        string
+    }
+}
+
+/// Free a C string allocated by `alloc::allocate_string()` or `to_c_message()`.
+unsafe fn free_c_string(string: *mut c_char) {
+    if !ptr::eq(string, FALLBACK_MESSAGE.as_ptr()) {
+        alloc::free_string(string);
+    }
+}
+
+/// Copy a string along with its NUL terminator to a caller buffer.
+///
+/// The size required to hold the string, including the NUL terminator, is written to `required`
+/// when it is not null, even if the buffer is too small. Returns whether the string was written.
+unsafe fn write_to_buffer(
+    value: &str,
+    buffer: *mut c_char,
+    capacity: usize,
+    required: *mut usize,
+) -> bool {
+    let size = value.len() + 1;
+    if !required.is_null() {
+        *required = size;
+    }
+    if buffer.is_null() || capacity < size || value.as_bytes().contains(&0) {
+        if !buffer.is_null() {
+            log::log(AtreeLogLevel::LogDebug, || {
+                format!("the buffer of {capacity} bytes is too small, {size} bytes are required")
+            });
+        }
+        return false;
+    }
+
+    ptr::copy_nonoverlapping(value.as_ptr().cast::<c_char>(), buffer, value.len());
+    *buffer.add(value.len()) = 0;
+    true
+}
+
+/// Reason of a failed call, reported either as an `AtreeResult` or as a status code
+struct Failure {
+    status: i32,
+    message: Cow<'static, str>,
+}
+
+impl Failure {
+    const fn new(status: i32, message: &'static str) -> Self {
+        Self {
+            status,
+            message: Cow::Borrowed(message),
+        }
+    }
+
+    const fn invalid_arguments() -> Self {
+        Self::new(ATREE_ERROR_INVALID_ARGUMENT, "Invalid arguments")
+    }
+}
+
+impl From<EventError> for Failure {
+    fn from(error: EventError) -> Self {
+        Self {
+            status: ATREE_ERROR_EVENT,
+            message: Cow::Owned(error.to_string()),
+        }
+    }
+}
+
+impl From<ATreeError<'_>> for Failure {
+    fn from(error: ATreeError<'_>) -> Self {
+        let status = match error {
+            ATreeError::Event(_)
+            | ATreeError::ParseError(ParseError::User {
+                error: ParserError::Event(_),
+            }) => ATREE_ERROR_EVENT,
+            ATreeError::ParseError(_)
+            | ATreeError::ParameterCount { .. }
+            | ATreeError::InvalidParameter(_) => ATREE_ERROR_PARSE,
+            ATreeError::DuplicateId(_) => ATREE_ERROR_DUPLICATE_ID,
+        };
+        Self {
+            status,
+            message: Cow::Owned(error.to_string()),
+        }
+    }
+}
+
+impl From<Result<(), Failure>> for AtreeResult {
+    fn from(result: Result<(), Failure>) -> Self {
+        match result {
+            Ok(()) => Self::ok(),
+            Err(failure) => Self::err(&failure.message),
+        }
+    }
+}
+
+/// Borrow a C string as UTF-8, failing with `message` if it is not.
+unsafe fn to_str<'a>(value: *const c_char, message: &'static str) -> Result<&'a str, Failure> {
+    CStr::from_ptr(value)
+        .to_str()
+        .map_err(|_| Failure::new(ATREE_ERROR_INVALID_UTF8, message))
+}
+
+/// Borrow the event builder and the name of the attribute to set.
+unsafe fn event_builder<'a>(
+    builder: *mut c_void,
+    name: *const c_char,
+) -> Result<(&'a mut a_tree::EventBuilder<'a>, &'a str), Failure> {
+    if is_invalid_builder(builder) || name.is_null() {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let name_str = to_str(name, "Invalid UTF-8 in name")?;
+    Ok((&mut *(builder as *mut a_tree::EventBuilder), name_str))
+}
+
 impl AtreeResult {
     fn ok() -> Self {
         Self {
@@ -56,10 +404,9 @@ impl AtreeResult {
     }
 
     fn err(msg: &str) -> Self {
-        let c_msg = CString::new(msg).unwrap_or_else(|_| CString::new("Invalid error message").unwrap());
         Self {
             success: false,
-            error_message: c_msg.into_raw(),
+            error_message: to_c_message(msg),
         }
     }
 }
@@ -79,8 +426,21 @@ impl AtreeResult {
 /// - Caller must free the returned handle with `atree_free()`
 #[no_mangle]
 pub unsafe extern "C" fn atree_new(defs: *const AtreeAttributeDef, count: usize) -> *mut ATreeHandle {
+    new(defs, count).map_or(ptr::null_mut(), debug::into_raw)
+}
+
+unsafe fn new(defs: *const AtreeAttributeDef, count: usize) -> Result<ATreeHandle, Failure> {
+    let definitions = attribute_definitions(defs, count)?;
+    let tree = ATree::<u64>::new(&definitions)?;
+    Ok(ATreeHandle::new(tree))
+}
+
+unsafe fn attribute_definitions(
+    defs: *const AtreeAttributeDef,
+    count: usize,
+) -> Result<Vec<AttributeDefinition>, Failure> {
     if defs.is_null() || count == 0 {
-        return ptr::null_mut();
+        return Err(Failure::invalid_arguments());
     }
 
     let defs_slice = slice::from_raw_parts(defs, count);
@@ -88,13 +448,10 @@ pub unsafe extern "C" fn atree_new(defs: *const AtreeAttributeDef, count: usize)
 
     for def in defs_slice {
         if def.name.is_null() {
-            return ptr::null_mut();
+            return Err(Failure::invalid_arguments());
         }
 
-        let name = match CStr::from_ptr(def.name).to_str() {
-            Ok(s) => s,
-            Err(_) => return ptr::null_mut(),
-        };
+        let name = to_str(def.name, "Invalid UTF-8 in name")?;
 
         let attr_def = match def.attr_type {
             AtreeAttributeType::Boolean => AttributeDefinition::boolean(name),
@@ -108,112 +465,595 @@ pub unsafe extern "C" fn atree_new(defs: *const AtreeAttributeDef, count: usize)
         attr_defs.push(attr_def);
     }
 
-    match ATree::<u64>::new(&attr_defs) {
-        Ok(tree) => Box::into_raw(Box::new(ATreeHandle { tree })),
-        Err(_) => ptr::null_mut(),
-    }
+    Ok(attr_defs)
 }
 
 /// Free an A-Tree handle.
 ///
 /// # Safety
 /// - `handle` must be a valid pointer returned by `atree_new()`
-/// - `handle` must not be used after this call
+/// - `handle` must not be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_free(handle: *mut ATreeHandle) {
+    if !is_invalid(handle) {
+        debug::free(handle);
+    }
+}
+
+/// Insert a boolean expression associated with a subscription ID.
+///
+/// # Arguments
+/// * `handle` - Valid ATree handle
+/// * `subscription_id` - Unique ID for this subscription
+/// * `expression` - Null-terminated boolean expression string
+///
+/// # Returns
+/// Result indicating success or failure
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `expression` must be a valid null-terminated C string
+/// - Caller must free result.error_message with `atree_free_error()` if !success
+#[no_mangle]
+pub unsafe extern "C" fn atree_insert(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: *const c_char,
+) -> AtreeResult {
+    insert(handle, subscription_id, expression).into()
+}
+
+unsafe fn insert(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: *const c_char,
+) -> Result<(), Failure> {
+    if is_invalid(handle) || expression.is_null() {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let expr_str = to_str(expression, "Invalid UTF-8 in expression")?;
+    let handle_ref = &mut *handle;
+    handle_ref
+        .tree
+        .insert(&subscription_id, expr_str)
+        .inspect_err(|error| log_insert_failure(subscription_id, error))?;
+    Ok(())
+}
+
+fn log_insert_failure(subscription_id: impl Display, error: &ATreeError) {
+    log::log(AtreeLogLevel::LogInfo, || {
+        format!("failed to insert the subscription {subscription_id}: {error}")
+    });
+}
+
+/// Insert a boolean expression and describe where the expression is invalid on failure.
+///
+/// # Arguments
+/// * `handle` - Valid ATree handle
+/// * `subscription_id` - Unique ID for this subscription
+/// * `expression` - Null-terminated boolean expression string
+/// * `error` - Optional out-parameter filled when the insertion fails; can be null
+///
+/// # Returns
+/// Result indicating success or failure
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `expression` must be a valid null-terminated C string
+/// - `error` must be null or point to writable memory for an `AtreeParseError`
+/// - Caller must free result.error_message with `atree_free_error()` if !success
+/// - Caller must free `*error` with `atree_parse_error_free()` if !success and `error` is not null
+#[no_mangle]
+pub unsafe extern "C" fn atree_insert_with_error(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: *const c_char,
+    error: *mut AtreeParseError,
+) -> AtreeResult {
+    let expression = (!expression.is_null()).then(|| CStr::from_ptr(expression).to_bytes());
+    insert_with_error(handle, subscription_id, expression, error)
+}
+
+unsafe fn insert_with_error(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: Option<&[u8]>,
+    error: *mut AtreeParseError,
+) -> AtreeResult {
+    let Some(expression) = expression.filter(|_| !is_invalid(handle)) else {
+        let message = "Invalid arguments";
+        report_parse_error(
+            error,
+            AtreeParseError::new(AtreeParseErrorCode::InvalidArgument, message),
+        );
+        return AtreeResult::err(message);
+    };
+
+    let expr_str = match std::str::from_utf8(expression) {
+        Ok(s) => s,
+        Err(_) => {
+            let message = "Invalid UTF-8 in expression";
+            report_parse_error(
+                error,
+                AtreeParseError::new(AtreeParseErrorCode::InvalidArgument, message),
+            );
+            return AtreeResult::err(message);
+        }
+    };
+
+    let handle_ref = &mut *handle;
+    match handle_ref.tree.insert(&subscription_id, expr_str) {
+        Ok(_) => AtreeResult::ok(),
+        Err(e) => {
+            log_insert_failure(subscription_id, &e);
+            report_parse_error(error, AtreeParseError::from_error(expr_str, &e));
+            AtreeResult::err(&e.to_string())
+        }
+    }
+}
+
+/// Check whether an expression could be inserted in the tree without modifying it.
+///
+/// # Arguments
+/// * `handle` - Valid ATree handle
+/// * `expression` - Null-terminated boolean expression string
+/// * `error` - Optional out-parameter filled when the expression is invalid; can be null
+///
+/// # Returns
+/// true if the expression is valid, false otherwise
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `expression` must be a valid null-terminated C string
+/// - `error` must be null or point to writable memory for an `AtreeParseError`
+/// - Caller must free `*error` with `atree_parse_error_free()` if false is returned and `error`
+///   is not null
+#[no_mangle]
+pub unsafe extern "C" fn atree_validate_expression(
+    handle: *const ATreeHandle,
+    expression: *const c_char,
+    error: *mut AtreeParseError,
+) -> bool {
+    let expression = (!expression.is_null()).then(|| CStr::from_ptr(expression).to_bytes());
+    validate_expression(handle, expression, error)
+}
+
+unsafe fn validate_expression(
+    handle: *const ATreeHandle,
+    expression: Option<&[u8]>,
+    error: *mut AtreeParseError,
+) -> bool {
+    let Some(expression) = expression.filter(|_| !is_invalid(handle)) else {
+        report_parse_error(
+            error,
+            AtreeParseError::new(AtreeParseErrorCode::InvalidArgument, "Invalid arguments"),
+        );
+        return false;
+    };
+
+    let expr_str = match std::str::from_utf8(expression) {
+        Ok(s) => s,
+        Err(_) => {
+            report_parse_error(
+                error,
+                AtreeParseError::new(
+                    AtreeParseErrorCode::InvalidArgument,
+                    "Invalid UTF-8 in expression",
+                ),
+            );
+            return false;
+        }
+    };
+
+    let handle_ref = &*handle;
+    match handle_ref.tree.validate_expression(expr_str) {
+        Ok(()) => true,
+        Err(e) => {
+            report_parse_error(error, AtreeParseError::from_error(expr_str, &e));
+            false
+        }
+    }
+}
+
+/// Free the message of a parse error.
+///
+/// # Safety
+/// - `error` must have been filled by `atree_insert_with_error()` or
+///   `atree_validate_expression()`
+/// - `error.message` must not be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_parse_error_free(error: AtreeParseError) {
+    free_c_string(error.message);
+}
+
+/// Delete a subscription by ID along with its payload.
+///
+/// # Arguments
+/// * `handle` - Valid ATree handle
+/// * `subscription_id` - ID of the subscription to delete
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_delete(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+) {
+    if is_invalid(handle) {
+        return;
+    }
+
+    let handle_ref = &mut *handle;
+    handle_ref.tree.delete(&subscription_id);
+    handle_ref.payloads.remove(&subscription_id);
+}
+
+/// Remove the nodes of the subscriptions deleted under `DeletePolicyTombstone`, with up to
+/// `atree_parallelism()` threads.
+///
+/// # Returns
+/// Number of subscriptions whose nodes were removed, or 0 if `handle` is null or a worker thread
+/// panicked
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_compact(handle: *mut ATreeHandle) -> usize {
+    if is_invalid(handle) {
+        return 0;
+    }
+
+    let handle_ref = &mut *handle;
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        handle_ref.tree.compact(parallel::parallelism())
+    }))
+    .unwrap_or_else(|_| {
+        log::log(AtreeLogLevel::LogError, || {
+            "a worker thread panicked while compacting the tree".to_owned()
+        });
+        0
+    })
+}
+
+/// Get the number of deleted subscriptions whose nodes are waiting for `atree_compact()`.
+///
+/// # Returns
+/// Number of tombstoned subscriptions, or 0 if `handle` is null
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_tombstone_count(handle: *const ATreeHandle) -> usize {
+    if is_invalid(handle) {
+        return 0;
+    }
+
+    (*handle).tree.tombstone_count()
+}
+
+/// Estimate the bytes used by the tree, including the subscription payloads.
+///
+/// # Returns
+/// Estimation of the bytes used by the tree, or 0 if `handle` is null
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_memory_usage(handle: *const ATreeHandle) -> usize {
+    if is_invalid(handle) {
+        return 0;
+    }
+
+    let handle_ref = &*handle;
+    handle_ref.tree.memory_usage() + payload::heap_bytes(&handle_ref.payloads)
+}
+
+/// Get the generation of the subscriptions, incremented by every insertion and deletion.
+///
+/// A consumer handling the matches of a search asynchronously can compare the generation read
+/// before the search with the current one to tell whether the matches refer to a superseded set
+/// of subscriptions.
+///
+/// # Returns
+/// Generation of the tree, or 0 if `handle` is null
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_generation(handle: *const ATreeHandle) -> u64 {
+    if is_invalid(handle) {
+        return 0;
+    }
+
+    (*handle).tree.generation()
+}
+
+/// Get the number of subscriptions, nodes and strings of the tree along with its memory usage.
+///
+/// # Returns
+/// true if the stats were written to `out`, false if an argument is null
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `out` must point to writable memory for an `AtreeStats`
+#[no_mangle]
+pub unsafe extern "C" fn atree_stats(handle: *const ATreeHandle, out: *mut AtreeStats) -> bool {
+    if is_invalid(handle) || out.is_null() {
+        return false;
+    }
+
+    let handle_ref = &*handle;
+    let stats = handle_ref.tree.stats();
+    *out = AtreeStats {
+        subscriptions: stats.subscriptions(),
+        nodes: stats.nodes(),
+        predicates: stats.predicates(),
+        roots: stats.roots(),
+        max_level: stats.max_level(),
+        strings: stats.strings(),
+        memory_usage: stats.memory_usage() + payload::heap_bytes(&handle_ref.payloads),
+    };
+    true
+}
+
+/// Export the tree structure as a Graphviz DOT format string.
+///
+/// # Returns
+/// Null-terminated string containing DOT format, or null on failure
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - Caller must free the returned string with `atree_free_string()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_to_graphviz(handle: *const ATreeHandle) -> *mut c_char {
+    if is_invalid(handle) {
+        return ptr::null_mut();
+    }
+
+    let handle_ref = &*handle;
+    let dot = handle_ref.tree.to_graphviz();
+    alloc::allocate_string(&dot)
+}
+
+/// Write the tree structure as a Graphviz DOT format string to a caller buffer.
+///
+/// # Arguments
+/// * `handle` - Valid ATree handle
+/// * `buffer` - Buffer receiving the null-terminated string; can be null to query the size
+/// * `capacity` - Size of the buffer in bytes
+/// * `required` - Optional out-parameter receiving the size needed, including the terminator
+///
+/// # Returns
+/// true if the string was written, false if the buffer is too small or on failure
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `buffer` must be null or point to `capacity` writable bytes
+/// - `required` must be null or point to writable memory for a size_t
+#[no_mangle]
+pub unsafe extern "C" fn atree_to_graphviz_buf(
+    handle: *const ATreeHandle,
+    buffer: *mut c_char,
+    capacity: usize,
+    required: *mut usize,
+) -> bool {
+    if is_invalid(handle) {
+        return false;
+    }
+
+    let handle_ref = &*handle;
+    write_to_buffer(&handle_ref.tree.to_graphviz(), buffer, capacity, required)
+}
+
+/// Export the cumulative counters of the tree as a JSON object.
+///
+/// The object contains the `searches`, `matches`, `inserts`, `parse_failures`,
+/// `nodes_visited` and `average_nodes_visited` fields.
+///
+/// # Returns
+/// Null-terminated JSON string, or null on failure
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - Caller must free the returned string with `atree_free_string()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_metrics_json(handle: *const ATreeHandle) -> *mut c_char {
+    if is_invalid(handle) {
+        return ptr::null_mut();
+    }
+
+    let handle_ref = &*handle;
+    let json = metrics_json(&handle_ref.tree);
+    alloc::allocate_string(&json)
+}
+
+/// Write the cumulative counters of the tree as a JSON object to a caller buffer.
+///
+/// See `atree_metrics_json()` for the format and `atree_to_graphviz_buf()` for the buffer
+/// handling.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `buffer` must be null or point to `capacity` writable bytes
+/// - `required` must be null or point to writable memory for a size_t
+#[no_mangle]
+pub unsafe extern "C" fn atree_metrics_json_buf(
+    handle: *const ATreeHandle,
+    buffer: *mut c_char,
+    capacity: usize,
+    required: *mut usize,
+) -> bool {
+    if is_invalid(handle) {
+        return false;
+    }
+
+    let handle_ref = &*handle;
+    write_to_buffer(&metrics_json(&handle_ref.tree), buffer, capacity, required)
+}
+
+fn metrics_json(tree: &ATree<u64>) -> String {
+    let metrics = tree.metrics();
+    format!(
+        "{{\"searches\":{},\"matches\":{},\"inserts\":{},\"parse_failures\":{},\"nodes_visited\":{},\"average_nodes_visited\":{}}}",
+        metrics.searches(),
+        metrics.matches(),
+        metrics.inserts(),
+        metrics.parse_failures(),
+        metrics.nodes_visited(),
+        metrics.average_nodes_visited()
+    )
+}
+
+/// Export the schema and the subscriptions of the tree as a JSON document.
+///
+/// The document can be loaded with `atree_import_json()`; see `ATree::export_json()` for its
+/// format.
+///
+/// # Returns
+/// Null-terminated JSON string, or null on failure
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - Caller must free the returned string with `atree_free_string()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_export_json(handle: *const ATreeHandle) -> *mut c_char {
+    if is_invalid(handle) {
+        return ptr::null_mut();
+    }
+
+    let handle_ref = &*handle;
+    let Ok(json) = handle_ref.tree.export_json() else {
+        return ptr::null_mut();
+    };
+    alloc::allocate_string(&json)
+}
+
+/// Write the schema and the subscriptions of the tree as a JSON document to a caller buffer.
+///
+/// See `atree_export_json()` for the format and `atree_to_graphviz_buf()` for the buffer
+/// handling.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `buffer` must be null or point to `capacity` writable bytes
+/// - `required` must be null or point to writable memory for a size_t
 #[no_mangle]
-pub unsafe extern "C" fn atree_free(handle: *mut ATreeHandle) {
-    if !handle.is_null() {
-        drop(Box::from_raw(handle));
+pub unsafe extern "C" fn atree_export_json_buf(
+    handle: *const ATreeHandle,
+    buffer: *mut c_char,
+    capacity: usize,
+    required: *mut usize,
+) -> bool {
+    if is_invalid(handle) {
+        return false;
     }
+
+    let handle_ref = &*handle;
+    let Ok(json) = handle_ref.tree.export_json() else {
+        return false;
+    };
+    write_to_buffer(&json, buffer, capacity, required)
 }
 
-/// Insert a boolean expression associated with a subscription ID.
-///
-/// # Arguments
-/// * `handle` - Valid ATree handle
-/// * `subscription_id` - Unique ID for this subscription
-/// * `expression` - Null-terminated boolean expression string
+/// Create a new A-Tree from a JSON document produced by `atree_export_json()`.
 ///
 /// # Returns
-/// Result indicating success or failure
+/// Pointer to ATreeHandle on success, null on failure
 ///
 /// # Safety
-/// - `handle` must be a valid pointer returned by `atree_new()`
-/// - `expression` must be a valid null-terminated C string
-/// - Caller must free result.error_message with `atree_free_error()` if !success
+/// - `json` must be a valid null-terminated C string
+/// - Caller must free the returned handle with `atree_free()`
 #[no_mangle]
-pub unsafe extern "C" fn atree_insert(
-    handle: *mut ATreeHandle,
-    subscription_id: u64,
-    expression: *const c_char,
-) -> AtreeResult {
-    if handle.is_null() || expression.is_null() {
-        return AtreeResult::err("Invalid arguments");
+pub unsafe extern "C" fn atree_import_json(json: *const c_char) -> *mut ATreeHandle {
+    if json.is_null() {
+        return ptr::null_mut();
     }
 
-    let expr_str = match CStr::from_ptr(expression).to_str() {
-        Ok(s) => s,
-        Err(_) => return AtreeResult::err("Invalid UTF-8 in expression"),
+    let Ok(json_str) = CStr::from_ptr(json).to_str() else {
+        return ptr::null_mut();
     };
 
-    let handle_ref = &mut *handle;
-    match handle_ref.tree.insert(&subscription_id, expr_str) {
-        Ok(_) => AtreeResult::ok(),
-        Err(e) => AtreeResult::err(&format!("{:?}", e)),
+    match ATree::<u64>::import_json(json_str) {
+        Ok(tree) => debug::into_raw(ATreeHandle::new(tree)),
+        Err(_) => ptr::null_mut(),
     }
 }
 
-/// Delete a subscription by ID.
+/// Free a string returned by the library.
 ///
-/// # Arguments
-/// * `handle` - Valid ATree handle
-/// * `subscription_id` - ID of the subscription to delete
+/// # Safety
+/// - `string` must be a valid pointer from a function that returns *mut c_char
+#[no_mangle]
+pub unsafe extern "C" fn atree_free_string(string: *mut c_char) {
+    free_c_string(string);
+}
+
+/// Intern a string so that it can be passed to `atree_event_builder_with_interned_string()`
+/// without being hashed for every event.
+///
+/// The interned ID is written to `out_id`. Interning the same string multiple times returns the
+/// same ID, and it stays valid when new expressions are inserted.
 ///
 /// # Safety
 /// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `value` must be a valid null-terminated C string
+/// - `out_id` must point to writable memory for a u64
 #[no_mangle]
-pub unsafe extern "C" fn atree_delete(
+pub unsafe extern "C" fn atree_intern_string(
     handle: *mut ATreeHandle,
-    subscription_id: u64,
-) {
-    if handle.is_null() {
-        return;
+    value: *const c_char,
+    out_id: *mut u64,
+) -> AtreeResult {
+    intern_string(handle, value, out_id).into()
+}
+
+unsafe fn intern_string(
+    handle: *mut ATreeHandle,
+    value: *const c_char,
+    out_id: *mut u64,
+) -> Result<(), Failure> {
+    if is_invalid(handle) || value.is_null() || out_id.is_null() {
+        return Err(Failure::invalid_arguments());
     }
 
+    let value_str = to_str(value, "Invalid UTF-8 in value")?;
     let handle_ref = &mut *handle;
-    handle_ref.tree.delete(&subscription_id);
+    *out_id = handle_ref.tree.intern(value_str).to_raw();
+    Ok(())
 }
 
-/// Export the tree structure as a Graphviz DOT format string.
+/// Look up the interned ID of a string without interning it.
 ///
 /// # Returns
-/// Null-terminated string containing DOT format, or null on failure
+/// `true` and writes the ID to `out_id` if the string was already interned or is used by an
+/// expression, `false` otherwise
 ///
 /// # Safety
 /// - `handle` must be a valid pointer returned by `atree_new()`
-/// - Caller must free the returned string with `atree_free_string()`
+/// - `value` must be a valid null-terminated C string
+/// - `out_id` must point to writable memory for a u64
 #[no_mangle]
-pub unsafe extern "C" fn atree_to_graphviz(handle: *const ATreeHandle) -> *mut c_char {
-    if handle.is_null() {
-        return ptr::null_mut();
+pub unsafe extern "C" fn atree_lookup_string(
+    handle: *const ATreeHandle,
+    value: *const c_char,
+    out_id: *mut u64,
+) -> bool {
+    if is_invalid(handle) || value.is_null() || out_id.is_null() {
+        return false;
     }
 
-    let handle_ref = &*handle;
-    let dot = handle_ref.tree.to_graphviz();
-
-    match CString::new(dot) {
-        Ok(c_str) => c_str.into_raw(),
-        Err(_) => ptr::null_mut(),
-    }
-}
+    let Ok(value_str) = CStr::from_ptr(value).to_str() else {
+        return false;
+    };
 
-/// Free a string returned by the library.
-///
-/// # Safety
-/// - `string` must be a valid pointer from a function that returns *mut c_char
-#[no_mangle]
-pub unsafe extern "C" fn atree_free_string(string: *mut c_char) {
-    if !string.is_null() {
-        drop(CString::from_raw(string));
+    let handle_ref = &*handle;
+    match handle_ref.tree.interned(value_str) {
+        Some(interned) => {
+            *out_id = interned.to_raw();
+            true
+        }
+        None => false,
     }
 }
 
@@ -224,13 +1064,13 @@ pub unsafe extern "C" fn atree_free_string(string: *mut c_char) {
 /// - Returned pointer must be freed with `atree_event_builder_free()`
 #[no_mangle]
 pub unsafe extern "C" fn atree_event_builder_new(handle: *const ATreeHandle) -> *mut c_void {
-    if handle.is_null() {
+    if is_invalid(handle) {
         return ptr::null_mut();
     }
 
     let handle_ref = &*handle;
     let builder = handle_ref.tree.make_event();
-    Box::into_raw(Box::new(builder)) as *mut c_void
+    debug::into_raw(builder) as *mut c_void
 }
 
 /// Add a boolean attribute to the event.
@@ -244,20 +1084,17 @@ pub unsafe extern "C" fn atree_event_builder_with_boolean(
     name: *const c_char,
     value: bool,
 ) -> AtreeResult {
-    if builder.is_null() || name.is_null() {
-        return AtreeResult::err("Invalid arguments");
-    }
-
-    let name_str = match CStr::from_ptr(name).to_str() {
-        Ok(s) => s,
-        Err(_) => return AtreeResult::err("Invalid UTF-8 in name"),
-    };
+    with_boolean(builder, name, value).into()
+}
 
-    let builder_ref = &mut *(builder as *mut a_tree::EventBuilder);
-    match builder_ref.with_boolean(name_str, value) {
-        Ok(_) => AtreeResult::ok(),
-        Err(e) => AtreeResult::err(&format!("{:?}", e)),
-    }
+unsafe fn with_boolean(
+    builder: *mut c_void,
+    name: *const c_char,
+    value: bool,
+) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name)?;
+    builder.with_boolean(name, value)?;
+    Ok(())
 }
 
 /// Add an integer attribute to the event.
@@ -271,20 +1108,44 @@ pub unsafe extern "C" fn atree_event_builder_with_integer(
     name: *const c_char,
     value: i64,
 ) -> AtreeResult {
-    if builder.is_null() || name.is_null() {
-        return AtreeResult::err("Invalid arguments");
-    }
+    with_integer(builder, name, value).into()
+}
 
-    let name_str = match CStr::from_ptr(name).to_str() {
-        Ok(s) => s,
-        Err(_) => return AtreeResult::err("Invalid UTF-8 in name"),
-    };
+unsafe fn with_integer(
+    builder: *mut c_void,
+    name: *const c_char,
+    value: i64,
+) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name)?;
+    builder.with_integer(name, value)?;
+    Ok(())
+}
 
-    let builder_ref = &mut *(builder as *mut a_tree::EventBuilder);
-    match builder_ref.with_integer(name_str, value) {
-        Ok(_) => AtreeResult::ok(),
-        Err(e) => AtreeResult::err(&format!("{:?}", e)),
-    }
+/// Add a float attribute to the event from a double.
+///
+/// The double is rounded to the float scale of the tree (6 decimal places by default) with
+/// its rounding policy (half to even by default). NaN and infinities are rejected.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must be a valid null-terminated C string
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_double(
+    builder: *mut c_void,
+    name: *const c_char,
+    value: f64,
+) -> AtreeResult {
+    with_double(builder, name, value).into()
+}
+
+unsafe fn with_double(
+    builder: *mut c_void,
+    name: *const c_char,
+    value: f64,
+) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name)?;
+    builder.with_f64(name, value)?;
+    Ok(())
 }
 
 /// Add a string attribute to the event.
@@ -298,25 +1159,46 @@ pub unsafe extern "C" fn atree_event_builder_with_string(
     name: *const c_char,
     value: *const c_char,
 ) -> AtreeResult {
-    if builder.is_null() || name.is_null() || value.is_null() {
-        return AtreeResult::err("Invalid arguments");
+    with_string(builder, name, value).into()
+}
+
+unsafe fn with_string(
+    builder: *mut c_void,
+    name: *const c_char,
+    value: *const c_char,
+) -> Result<(), Failure> {
+    if value.is_null() {
+        return Err(Failure::invalid_arguments());
     }
 
-    let name_str = match CStr::from_ptr(name).to_str() {
-        Ok(s) => s,
-        Err(_) => return AtreeResult::err("Invalid UTF-8 in name"),
-    };
+    let (builder, name) = event_builder(builder, name)?;
+    let value_str = to_str(value, "Invalid UTF-8 in value")?;
+    builder.with_string(name, value_str)?;
+    Ok(())
+}
 
-    let value_str = match CStr::from_ptr(value).to_str() {
-        Ok(s) => s,
-        Err(_) => return AtreeResult::err("Invalid UTF-8 in value"),
-    };
+/// Add a string attribute to the event from an ID returned by `atree_intern_string()`.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must be a valid null-terminated C string
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_interned_string(
+    builder: *mut c_void,
+    name: *const c_char,
+    id: u64,
+) -> AtreeResult {
+    with_interned_string(builder, name, id).into()
+}
 
-    let builder_ref = &mut *(builder as *mut a_tree::EventBuilder);
-    match builder_ref.with_string(name_str, value_str) {
-        Ok(_) => AtreeResult::ok(),
-        Err(e) => AtreeResult::err(&format!("{:?}", e)),
-    }
+unsafe fn with_interned_string(
+    builder: *mut c_void,
+    name: *const c_char,
+    id: u64,
+) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name)?;
+    builder.with_interned_string(name, InternedString::from_raw(id))?;
+    Ok(())
 }
 
 /// Add a float attribute to the event.
@@ -334,20 +1216,18 @@ pub unsafe extern "C" fn atree_event_builder_with_float(
     number: i64,
     scale: u32,
 ) -> AtreeResult {
-    if builder.is_null() || name.is_null() {
-        return AtreeResult::err("Invalid arguments");
-    }
-
-    let name_str = match CStr::from_ptr(name).to_str() {
-        Ok(s) => s,
-        Err(_) => return AtreeResult::err("Invalid UTF-8 in name"),
-    };
+    with_float(builder, name, number, scale).into()
+}
 
-    let builder_ref = &mut *(builder as *mut a_tree::EventBuilder);
-    match builder_ref.with_float(name_str, number, scale) {
-        Ok(_) => AtreeResult::ok(),
-        Err(e) => AtreeResult::err(&format!("{:?}", e)),
-    }
+unsafe fn with_float(
+    builder: *mut c_void,
+    name: *const c_char,
+    number: i64,
+    scale: u32,
+) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name)?;
+    builder.with_float(name, number, scale)?;
+    Ok(())
 }
 
 /// Add a string list attribute to the event.
@@ -363,34 +1243,35 @@ pub unsafe extern "C" fn atree_event_builder_with_string_list(
     values: *const *const c_char,
     count: usize,
 ) -> AtreeResult {
-    if builder.is_null() || name.is_null() || values.is_null() {
-        return AtreeResult::err("Invalid arguments");
-    }
+    with_string_list(builder, name, values, count).into()
+}
 
-    let name_str = match CStr::from_ptr(name).to_str() {
-        Ok(s) => s,
-        Err(_) => return AtreeResult::err("Invalid UTF-8 in name"),
-    };
+unsafe fn with_string_list(
+    builder: *mut c_void,
+    name: *const c_char,
+    values: *const *const c_char,
+    count: usize,
+) -> Result<(), Failure> {
+    if values.is_null() {
+        return Err(Failure::invalid_arguments());
+    }
 
+    let (builder, name) = event_builder(builder, name)?;
     let values_slice = slice::from_raw_parts(values, count);
     let mut string_vec = Vec::with_capacity(count);
 
     for &value_ptr in values_slice {
         if value_ptr.is_null() {
-            return AtreeResult::err("Null pointer in string list");
+            return Err(Failure::new(
+                ATREE_ERROR_INVALID_ARGUMENT,
+                "Null pointer in string list",
+            ));
         }
-        let value_str = match CStr::from_ptr(value_ptr).to_str() {
-            Ok(s) => s,
-            Err(_) => return AtreeResult::err("Invalid UTF-8 in string list"),
-        };
-        string_vec.push(value_str);
+        string_vec.push(to_str(value_ptr, "Invalid UTF-8 in string list")?);
     }
 
-    let builder_ref = &mut *(builder as *mut a_tree::EventBuilder);
-    match builder_ref.with_string_list(name_str, &string_vec) {
-        Ok(_) => AtreeResult::ok(),
-        Err(e) => AtreeResult::err(&format!("{:?}", e)),
-    }
+    builder.with_string_list(name, &string_vec)?;
+    Ok(())
 }
 
 /// Add an integer list attribute to the event.
@@ -406,22 +1287,23 @@ pub unsafe extern "C" fn atree_event_builder_with_integer_list(
     values: *const i64,
     count: usize,
 ) -> AtreeResult {
-    if builder.is_null() || name.is_null() || values.is_null() {
-        return AtreeResult::err("Invalid arguments");
-    }
+    with_integer_list(builder, name, values, count).into()
+}
 
-    let name_str = match CStr::from_ptr(name).to_str() {
-        Ok(s) => s,
-        Err(_) => return AtreeResult::err("Invalid UTF-8 in name"),
-    };
+unsafe fn with_integer_list(
+    builder: *mut c_void,
+    name: *const c_char,
+    values: *const i64,
+    count: usize,
+) -> Result<(), Failure> {
+    if values.is_null() {
+        return Err(Failure::invalid_arguments());
+    }
 
+    let (builder, name) = event_builder(builder, name)?;
     let values_slice = slice::from_raw_parts(values, count);
-
-    let builder_ref = &mut *(builder as *mut a_tree::EventBuilder);
-    match builder_ref.with_integer_list(name_str, values_slice) {
-        Ok(_) => AtreeResult::ok(),
-        Err(e) => AtreeResult::err(&format!("{:?}", e)),
-    }
+    builder.with_integer_list(name, values_slice)?;
+    Ok(())
 }
 
 /// Add an undefined attribute to the event.
@@ -434,20 +1316,13 @@ pub unsafe extern "C" fn atree_event_builder_with_undefined(
     builder: *mut c_void,
     name: *const c_char,
 ) -> AtreeResult {
-    if builder.is_null() || name.is_null() {
-        return AtreeResult::err("Invalid arguments");
-    }
-
-    let name_str = match CStr::from_ptr(name).to_str() {
-        Ok(s) => s,
-        Err(_) => return AtreeResult::err("Invalid UTF-8 in name"),
-    };
+    with_undefined(builder, name).into()
+}
 
-    let builder_ref = &mut *(builder as *mut a_tree::EventBuilder);
-    match builder_ref.with_undefined(name_str) {
-        Ok(_) => AtreeResult::ok(),
-        Err(e) => AtreeResult::err(&format!("{:?}", e)),
-    }
+unsafe fn with_undefined(builder: *mut c_void, name: *const c_char) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name)?;
+    builder.with_undefined(name)?;
+    Ok(())
 }
 
 /// Search the A-Tree for matching expressions.
@@ -462,48 +1337,150 @@ pub unsafe extern "C" fn atree_search(
     handle: *const ATreeHandle,
     builder: *mut c_void,
 ) -> AtreeSearchResult {
-    if handle.is_null() || builder.is_null() {
-        return AtreeSearchResult {
-            ids: ptr::null_mut(),
-            count: 0,
-        };
+    search(handle, builder)
+        .and_then(AtreeSearchResult::new)
+        .unwrap_or(AtreeSearchResult::empty())
+}
+
+unsafe fn search(handle: *const ATreeHandle, builder: *mut c_void) -> Result<Vec<u64>, Failure> {
+    if is_invalid(handle) || is_invalid_builder(builder) {
+        return Err(Failure::invalid_arguments());
     }
 
     let handle_ref = &*handle;
-    let builder_owned = Box::from_raw(builder as *mut a_tree::EventBuilder);
+    let builder_owned = debug::take(builder as *mut a_tree::EventBuilder);
+    let event = builder_owned.build()?;
+    search_event(handle_ref, &event)
+}
 
-    let event = match builder_owned.build() {
-        Ok(e) => e,
-        Err(_) => {
-            return AtreeSearchResult {
-                ids: ptr::null_mut(),
-                count: 0,
-            }
-        }
-    };
+/// Search the A-Tree for matching expressions without consuming the builder.
+///
+/// Unlike `atree_search()`, the builder can still be used afterwards, for example by resetting it
+/// with `atree_event_builder_reset()` to build the next event.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - Caller must still free `builder` with `atree_event_builder_free()`
+/// - Caller must free the returned result with `atree_search_result_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_search_borrowed(
+    handle: *const ATreeHandle,
+    builder: *const c_void,
+) -> AtreeSearchResult {
+    search_borrowed(handle, builder)
+        .and_then(AtreeSearchResult::new)
+        .unwrap_or(AtreeSearchResult::empty())
+}
 
-    let report = match handle_ref.tree.search(&event) {
-        Ok(r) => r,
-        Err(_) => {
-            return AtreeSearchResult {
-                ids: ptr::null_mut(),
-                count: 0,
-            }
-        }
-    };
+unsafe fn search_borrowed(
+    handle: *const ATreeHandle,
+    builder: *const c_void,
+) -> Result<Vec<u64>, Failure> {
+    if is_invalid(handle) || is_invalid_builder(builder) {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let handle_ref = &*handle;
+    let builder_ref = &*(builder as *const a_tree::EventBuilder);
+    let event = builder_ref.to_event()?;
+    search_event(handle_ref, &event)
+}
+
+/// Count the expressions matching an event without copying their IDs.
+///
+/// The builder is not consumed; it can be reset and reused right after this call.
+///
+/// # Returns
+/// Number of matching subscriptions; 0 on failure, use `atree_match_count2()` to tell a failure
+/// from an event without matches
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - Caller must still free `builder` with `atree_event_builder_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_match_count(
+    handle: *const ATreeHandle,
+    builder: *const c_void,
+) -> u64 {
+    match_count(handle, builder).unwrap_or(0)
+}
+
+unsafe fn match_count(handle: *const ATreeHandle, builder: *const c_void) -> Result<u64, Failure> {
+    if is_invalid(handle) || is_invalid_builder(builder) {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let handle_ref = &*handle;
+    let builder_ref = &*(builder as *const a_tree::EventBuilder);
+    let event = builder_ref.to_event()?;
+    let count = handle_ref.tree.match_count(&event).inspect_err(|error| {
+        log::log(AtreeLogLevel::LogError, || {
+            format!("failed to search the tree: {error}")
+        });
+    })?;
+    Ok(count as u64)
+}
 
-    let matches: Vec<u64> = report.matches().iter().map(|&&id| id).collect();
-    let count = matches.len();
+fn search_event(handle_ref: &ATreeHandle, event: &a_tree::Event) -> Result<Vec<u64>, Failure> {
+    let matches = search_tree(&handle_ref.tree, event)?;
+    Ok(matches.into_iter().copied().collect())
+}
+
+fn search_tree<'a, T>(tree: &'a ATree<T>, event: &a_tree::Event) -> Result<Vec<&'a T>, Failure>
+where
+    T: Eq + Hash + Clone + Debug,
+{
+    timed_search(|| tree.search(event))
+}
+
+/// Run a search, logging its failure and reporting it if it is slow.
+fn timed_search<'a, T>(
+    search: impl FnOnce() -> Result<a_tree::Report<'a, T>, ATreeError<'a>>,
+) -> Result<Vec<&'a T>, Failure>
+where
+    T: Clone,
+{
+    let start = Instant::now();
+    let report = search().inspect_err(|error| {
+        log::log(AtreeLogLevel::LogError, || {
+            format!("failed to search the tree: {error}")
+        });
+    })?;
+    let elapsed = start.elapsed();
+    if log::slow_search_threshold().is_some_and(|threshold| elapsed > threshold) {
+        log::log(AtreeLogLevel::LogWarning, || {
+            format!(
+                "slow search: {} microseconds for {} matches",
+                elapsed.as_micros(),
+                report.matches().len()
+            )
+        });
+    }
+    Ok(report.matches().to_vec())
+}
 
-    if count == 0 {
-        AtreeSearchResult {
+impl AtreeSearchResult {
+    const fn empty() -> Self {
+        Self {
             ids: ptr::null_mut(),
             count: 0,
         }
-    } else {
-        let boxed = matches.into_boxed_slice();
-        let ptr = Box::into_raw(boxed) as *mut u64;
-        AtreeSearchResult { ids: ptr, count }
+    }
+}
+
+impl AtreeSearchResult {
+    fn new(matches: Vec<u64>) -> Result<Self, Failure> {
+        let count = matches.len();
+        let ids = alloc::allocate_ids(matches);
+        if ids.is_null() && count > 0 {
+            return Err(Failure::new(
+                ATREE_ERROR_OUT_OF_MEMORY,
+                "Failed to allocate the search results",
+            ));
+        }
+        Ok(Self { ids, count })
     }
 }
 
@@ -514,12 +1491,7 @@ pub unsafe extern "C" fn atree_search(
 /// - `result` must not be used after this call
 #[no_mangle]
 pub unsafe extern "C" fn atree_search_result_free(result: AtreeSearchResult) {
-    if !result.ids.is_null() && result.count > 0 {
-        drop(Box::from_raw(slice::from_raw_parts_mut(
-            result.ids,
-            result.count,
-        )));
-    }
+    alloc::free_ids(result.ids, result.count);
 }
 
 /// Free an error message string.
@@ -528,9 +1500,36 @@ pub unsafe extern "C" fn atree_search_result_free(result: AtreeSearchResult) {
 /// - `error` must be a valid pointer from AtreeResult.error_message
 #[no_mangle]
 pub unsafe extern "C" fn atree_free_error(error: *mut c_char) {
-    if !error.is_null() {
-        drop(CString::from_raw(error));
+    free_c_string(error);
+}
+
+/// Reset all the attributes of the event builder to undefined so that it can be reused.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_reset(builder: *mut c_void) {
+    if is_invalid_builder(builder) {
+        return;
+    }
+
+    let builder_ref = &mut *(builder as *mut a_tree::EventBuilder);
+    builder_ref.reset();
+}
+
+/// Set the time of the event, in Unix seconds, that the `within_last()` predicates are relative
+/// to. The time is cleared by `atree_event_builder_reset()`.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_now(builder: *mut c_void, now: i64) {
+    if is_invalid_builder(builder) {
+        return;
     }
+
+    let builder_ref = &mut *(builder as *mut a_tree::EventBuilder);
+    builder_ref.with_now(now);
 }
 
 /// Free an event builder without using it.
@@ -539,7 +1538,7 @@ pub unsafe extern "C" fn atree_free_error(error: *mut c_char) {
 /// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
 #[no_mangle]
 pub unsafe extern "C" fn atree_event_builder_free(builder: *mut c_void) {
-    if !builder.is_null() {
-        drop(Box::from_raw(builder as *mut a_tree::EventBuilder));
+    if !is_invalid_builder(builder) {
+        debug::free(builder as *mut a_tree::EventBuilder);
     }
 }
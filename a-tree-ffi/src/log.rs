@@ -0,0 +1,101 @@
+//! Routing of the warnings raised by the library to the host's logging system.
+
+use std::ffi::CString;
+use std::os::raw::{c_char, c_void};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{PoisonError, RwLock};
+use std::time::Duration;
+
+use a_tree::{AttributeKind, TreeObserver};
+
+/// Severity of a log message
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AtreeLogLevel {
+    /// An operation failed and its failure could not be reported to the caller
+    LogError = 0,
+    /// An operation succeeded but something is likely wrong (slow search, skipped attribute)
+    LogWarning = 1,
+    /// An operation failed and its failure was reported to the caller
+    LogInfo = 2,
+    /// Detailed information, such as a caller buffer that is too small
+    LogDebug = 3,
+}
+
+/// Receive a log message; `message` is only valid during the call
+pub type AtreeLogCallback =
+    Option<unsafe extern "C" fn(level: AtreeLogLevel, message: *const c_char, ctx: *mut c_void)>;
+
+#[derive(Clone, Copy)]
+struct Logger {
+    callback: unsafe extern "C" fn(AtreeLogLevel, *const c_char, *mut c_void),
+    ctx: *mut c_void,
+}
+
+// The context is only passed back to the host's callback, which must be thread-safe itself.
+unsafe impl Send for Logger {}
+unsafe impl Sync for Logger {}
+
+static LOGGER: RwLock<Option<Logger>> = RwLock::new(None);
+static SLOW_SEARCH_MICROS: AtomicU64 = AtomicU64::new(0);
+
+/// Register the callback receiving the warnings of the library.
+///
+/// Without a callback, the warnings are dropped. Passing null removes the callback. `ctx` is
+/// passed unchanged to every call.
+///
+/// # Safety
+/// - `callback` must be thread-safe if the library is used from multiple threads
+/// - `ctx` must stay valid until the callback is replaced or removed
+#[no_mangle]
+pub unsafe extern "C" fn atree_set_log_callback(callback: AtreeLogCallback, ctx: *mut c_void) {
+    let logger = callback.map(|callback| Logger { callback, ctx });
+    *LOGGER.write().unwrap_or_else(PoisonError::into_inner) = logger;
+}
+
+/// Log a warning for every search that takes longer than `micros` microseconds.
+///
+/// 0, the default, disables the warning.
+#[no_mangle]
+pub extern "C" fn atree_set_slow_search_threshold(micros: u64) {
+    SLOW_SEARCH_MICROS.store(micros, Ordering::Relaxed);
+}
+
+/// The duration after which a search is reported as slow, if any.
+pub(crate) fn slow_search_threshold() -> Option<Duration> {
+    match SLOW_SEARCH_MICROS.load(Ordering::Relaxed) {
+        0 => None,
+        micros => Some(Duration::from_micros(micros)),
+    }
+}
+
+/// Send a message to the host's callback; the message is only built when a callback is set.
+pub(crate) fn log(level: AtreeLogLevel, message: impl FnOnce() -> String) {
+    let Some(logger) = *LOGGER.read().unwrap_or_else(PoisonError::into_inner) else {
+        return;
+    };
+
+    let mut message = message().into_bytes();
+    message.retain(|&byte| byte != 0);
+    let Ok(message) = CString::new(message) else {
+        return;
+    };
+    unsafe { (logger.callback)(level, message.as_ptr(), logger.ctx) };
+}
+
+/// Forward the warnings raised by a tree to the log callback
+pub(crate) struct LogObserver;
+
+impl<T> TreeObserver<T> for LogObserver {
+    fn on_unknown_attribute(&self, name: &str) {
+        log(AtreeLogLevel::LogWarning, || {
+            format!("skipping the unknown event attribute '{name}'")
+        });
+    }
+
+    fn on_coercion(&self, name: &str, from: &AttributeKind, to: &AttributeKind) {
+        log(AtreeLogLevel::LogDebug, || {
+            format!("coercing the event attribute '{name}' from {from:?} to {to:?}")
+        });
+    }
+}
@@ -0,0 +1,224 @@
+//! Batch operations that can run on multiple threads, within the limit set by the host.
+
+use std::num::NonZeroUsize;
+use std::os::raw::{c_char, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::debug::{is_invalid, is_invalid_builder};
+use crate::log::{self, AtreeLogLevel};
+use crate::pool;
+use crate::{
+    atree_search_result_free, log_insert_failure, search_event, to_c_message, to_str, ATreeHandle,
+    AtreeResult, AtreeSearchResult, Failure, ATREE_ERROR_INTERNAL,
+};
+
+static PARALLELISM: AtomicUsize = AtomicUsize::new(1);
+
+/// Set the maximum number of threads used by `atree_search_batch()`, `atree_insert_many()`,
+/// `atree_compact()` and `atree_search_async()`.
+///
+/// The default, 1, runs the batch operations on the calling thread only. 0 uses as many threads
+/// as there are available CPUs. Above 1, the batches are split in as many chunks, which run on
+/// worker threads owned by the library while the calling thread waits for them; the workers are
+/// started on first use, up to this number, and are kept for the next calls. Lowering the number
+/// leaves the workers that were already started idle.
+#[no_mangle]
+pub extern "C" fn atree_set_parallelism(threads: u32) {
+    PARALLELISM.store(threads as usize, Ordering::Relaxed);
+}
+
+/// Get the maximum number of threads used by the batch operations.
+///
+/// # Returns
+/// The value set with `atree_set_parallelism()`, with 0 replaced by the number of available CPUs
+#[no_mangle]
+pub extern "C" fn atree_parallelism() -> u32 {
+    parallelism().get().try_into().unwrap_or(u32::MAX)
+}
+
+pub(crate) fn parallelism() -> NonZeroUsize {
+    match NonZeroUsize::new(PARALLELISM.load(Ordering::Relaxed)) {
+        Some(threads) => threads,
+        None => thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+    }
+}
+
+/// Search the A-Tree for each of the events, spreading them over the threads allowed by
+/// `atree_set_parallelism()`.
+///
+/// The builders are not consumed. On success, `out_results[i]` receives the matches of
+/// `builders[i]`; on failure, nothing is written. A worker thread that panics fails the call
+/// with `ATREE_ERROR_INTERNAL`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `builders` must point to `count` valid pointers returned by `atree_event_builder_new()`
+/// - `out_results` must point to writable memory for `count` AtreeSearchResult structs
+/// - Caller must free every result with `atree_search_result_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_search_batch(
+    handle: *const ATreeHandle,
+    builders: *const *const c_void,
+    count: usize,
+    out_results: *mut AtreeSearchResult,
+) -> AtreeResult {
+    search_batch(handle, builders, count, out_results).into()
+}
+
+unsafe fn search_batch(
+    handle: *const ATreeHandle,
+    builders: *const *const c_void,
+    count: usize,
+    out_results: *mut AtreeSearchResult,
+) -> Result<(), Failure> {
+    if is_invalid(handle) || ((builders.is_null() || out_results.is_null()) && count > 0) {
+        return Err(Failure::invalid_arguments());
+    }
+    if count == 0 {
+        return Ok(());
+    }
+
+    let handle_ref = &*handle;
+    let events = slice::from_raw_parts(builders, count)
+        .iter()
+        .map(|&builder| {
+            if is_invalid_builder(builder) {
+                return Err(Failure::invalid_arguments());
+            }
+            let builder_ref = &*(builder as *const a_tree::EventBuilder);
+            Ok(builder_ref.to_event()?)
+        })
+        .collect::<Result<Vec<_>, Failure>>()?;
+
+    let chunk_size = count.div_ceil(parallelism().get());
+    let mut chunks: Vec<Option<Vec<_>>> = events.chunks(chunk_size).map(|_| None).collect();
+    let jobs = events
+        .chunks(chunk_size)
+        .zip(&mut chunks)
+        .map(|(chunk, matches)| {
+            Box::new(move || {
+                *matches = Some(
+                    chunk
+                        .iter()
+                        .map(|event| search_event(handle_ref, event))
+                        .collect(),
+                );
+            }) as a_tree::Job<'_>
+        })
+        .collect();
+    pool::run_all(jobs)?;
+    let matches = chunks
+        .into_iter()
+        .flatten()
+        .flatten()
+        .collect::<Result<Vec<_>, Failure>>()?;
+
+    let mut results = Vec::with_capacity(count);
+    for ids in matches {
+        match AtreeSearchResult::new(ids) {
+            Ok(result) => results.push(result),
+            Err(failure) => {
+                for result in results {
+                    atree_search_result_free(result);
+                }
+                return Err(failure);
+            }
+        }
+    }
+    ptr::copy_nonoverlapping(results.as_ptr(), out_results, count);
+    Ok(())
+}
+
+/// Insert many expressions, parsing them on the threads allowed by `atree_set_parallelism()`.
+///
+/// The expressions are inserted in order once they are all parsed; an invalid expression does
+/// not prevent the next ones from being inserted. If a worker thread panics, every expression
+/// receives an error, although the tree may keep the ones inserted before the panic.
+///
+/// # Arguments
+/// * `ids` - Array of `count` subscription IDs
+/// * `expressions` - Array of `count` null-terminated expressions
+/// * `out_errors` - Optional array of `count` pointers; `out_errors[i]` receives null if the
+///   expression was inserted or an error message to free with `atree_free_error()` otherwise
+///
+/// # Returns
+/// Number of inserted expressions
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `ids` and `expressions` must point to `count` elements
+/// - Each expression must be a valid null-terminated C string
+/// - `out_errors` must be null or point to writable memory for `count` pointers
+#[no_mangle]
+pub unsafe extern "C" fn atree_insert_many(
+    handle: *mut ATreeHandle,
+    ids: *const u64,
+    expressions: *const *const c_char,
+    count: usize,
+    out_errors: *mut *mut c_char,
+) -> usize {
+    if is_invalid(handle) || ids.is_null() || expressions.is_null() || count == 0 {
+        return 0;
+    }
+
+    let handle_ref = &mut *handle;
+    let ids = slice::from_raw_parts(ids, count);
+    let expressions = slice::from_raw_parts(expressions, count);
+    let mut errors: Vec<Option<Failure>> = (0..count).map(|_| None).collect();
+    let mut subscriptions = Vec::with_capacity(count);
+    let mut indices = Vec::with_capacity(count);
+    for (index, (&id, &expression)) in ids.iter().zip(expressions).enumerate() {
+        if expression.is_null() {
+            errors[index] = Some(Failure::invalid_arguments());
+            continue;
+        }
+        match to_str(expression, "Invalid UTF-8 in expression") {
+            Ok(expression) => {
+                subscriptions.push((id, expression));
+                indices.push(index);
+            }
+            Err(failure) => errors[index] = Some(failure),
+        }
+    }
+
+    let results = panic::catch_unwind(AssertUnwindSafe(|| {
+        handle_ref.tree.insert_many(&subscriptions, parallelism())
+    }));
+    match results {
+        Ok(results) => {
+            for (index, result) in indices.into_iter().zip(results) {
+                errors[index] = result
+                    .inspect_err(|error| log_insert_failure(ids[index], error))
+                    .err()
+                    .map(Failure::from);
+            }
+        }
+        Err(_) => {
+            log::log(AtreeLogLevel::LogError, || {
+                "a worker thread panicked while inserting many expressions".to_owned()
+            });
+            for index in indices {
+                errors[index] = Some(Failure::new(
+                    ATREE_ERROR_INTERNAL,
+                    "A worker thread panicked",
+                ));
+            }
+        }
+    }
+
+    let mut inserted = 0;
+    for (index, error) in errors.into_iter().enumerate() {
+        if error.is_none() {
+            inserted += 1;
+        }
+        if !out_errors.is_null() {
+            *out_errors.add(index) =
+                error.map_or(ptr::null_mut(), |failure| to_c_message(&failure.message));
+        }
+    }
+    inserted
+}
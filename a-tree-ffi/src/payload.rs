@@ -0,0 +1,182 @@
+//! Opaque data attached to the subscriptions and returned along with their matches, so that the
+//! hosts do not have to look the matches up in their own tables.
+
+use std::collections::HashMap;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::slice;
+
+use crate::{
+    alloc, insert, search_borrowed, ATreeHandle, AtreeResult, Failure, ATREE_ERROR_OUT_OF_MEMORY,
+};
+
+/// The payloads of the subscriptions of a tree, by subscription ID
+pub(crate) type Payloads = HashMap<u64, Box<[u8]>>;
+
+/// The heap bytes used by the payloads.
+pub(crate) fn heap_bytes(payloads: &Payloads) -> usize {
+    payloads.capacity() * (size_of::<(u64, Box<[u8]>)>() + 1)
+        + payloads
+            .values()
+            .map(|payload| payload.len())
+            .sum::<usize>()
+}
+
+/// A matching subscription along with its payload
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct AtreeMatch {
+    pub id: u64,
+    /// Payload of the subscription, or null if it has none; owned by the tree
+    pub payload: *const c_void,
+    /// Size of the payload in bytes
+    pub payload_len: usize,
+}
+
+/// Search result containing the matching subscriptions along with their payloads
+#[repr(C)]
+pub struct AtreePayloadSearchResult {
+    pub matches: *mut AtreeMatch,
+    pub count: usize,
+}
+
+/// Insert a boolean expression and attach a copy of `data` to the subscription.
+///
+/// The payload replaces the one previously attached to the subscription ID, if any; an empty
+/// payload only removes it. It is only attached if the expression is inserted, and it is removed
+/// by `atree_delete()`.
+///
+/// # Arguments
+/// * `data` - Payload to copy; can be null if `len` is 0
+/// * `len` - Size of the payload in bytes
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `expression` must be a valid null-terminated C string
+/// - `data` must point to `len` readable bytes
+/// - Caller must free `error_message` with `atree_free_error()` on failure
+#[no_mangle]
+pub unsafe extern "C" fn atree_insert_with_payload(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: *const c_char,
+    data: *const c_void,
+    len: usize,
+) -> AtreeResult {
+    insert_with_payload(handle, subscription_id, data, len, || {
+        insert(handle, subscription_id, expression)
+    })
+    .into()
+}
+
+/// Copy the payload, insert the expression with `insert` and then attach the payload.
+pub(crate) unsafe fn insert_with_payload(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    data: *const c_void,
+    len: usize,
+    insert: impl FnOnce() -> Result<(), Failure>,
+) -> Result<(), Failure> {
+    if data.is_null() && len > 0 {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let mut payload = Vec::new();
+    if payload.try_reserve_exact(len).is_err() {
+        return Err(Failure::new(ATREE_ERROR_OUT_OF_MEMORY, "Out of memory"));
+    }
+    if len > 0 {
+        payload.extend_from_slice(slice::from_raw_parts(data.cast::<u8>(), len));
+    }
+    insert()?;
+
+    let handle_ref = &mut *handle;
+    if payload.is_empty() {
+        handle_ref.payloads.remove(&subscription_id);
+    } else {
+        handle_ref
+            .payloads
+            .insert(subscription_id, payload.into_boxed_slice());
+    }
+    Ok(())
+}
+
+/// Get the payload attached to a subscription.
+///
+/// # Returns
+/// Pointer to the payload, owned by the tree, or null if the subscription has no payload
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `out_len` must be null or point to writable memory; it receives the size of the payload
+/// - The payload must not be used after the subscription is deleted, its payload replaced or
+///   the tree freed
+#[no_mangle]
+pub unsafe extern "C" fn atree_payload(
+    handle: *const ATreeHandle,
+    subscription_id: u64,
+    out_len: *mut usize,
+) -> *const c_void {
+    let payload = handle
+        .as_ref()
+        .and_then(|handle_ref| handle_ref.payloads.get(&subscription_id));
+    if !out_len.is_null() {
+        *out_len = payload.map_or(0, |payload| payload.len());
+    }
+    payload.map_or(ptr::null(), |payload| payload.as_ptr().cast())
+}
+
+/// Search the A-Tree and return the payload of each match.
+///
+/// The builder is not consumed. The payloads are owned by the tree: the result only holds
+/// pointers to them.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - Caller must free the returned result with `atree_payload_search_result_free()`
+/// - The payloads must not be used after their subscription is deleted, their payload replaced
+///   or the tree freed
+#[no_mangle]
+pub unsafe extern "C" fn atree_search_with_payloads(
+    handle: *const ATreeHandle,
+    builder: *const c_void,
+) -> AtreePayloadSearchResult {
+    let empty = AtreePayloadSearchResult {
+        matches: ptr::null_mut(),
+        count: 0,
+    };
+    let Ok(ids) = search_borrowed(handle, builder) else {
+        return empty;
+    };
+
+    let payloads = &(*handle).payloads;
+    let matches: Vec<_> = ids
+        .into_iter()
+        .map(|id| {
+            let payload = payloads.get(&id);
+            AtreeMatch {
+                id,
+                payload: payload.map_or(ptr::null(), |payload| payload.as_ptr().cast()),
+                payload_len: payload.map_or(0, |payload| payload.len()),
+            }
+        })
+        .collect();
+    let count = matches.len();
+    let matches = alloc::allocate_slice(matches);
+    if matches.is_null() {
+        return empty;
+    }
+    AtreePayloadSearchResult { matches, count }
+}
+
+/// Free the matches returned by `atree_search_with_payloads()`; the payloads stay owned by the
+/// tree.
+///
+/// # Safety
+/// - `result` must have been returned by `atree_search_with_payloads()`
+/// - `result.matches` must not be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_payload_search_result_free(result: AtreePayloadSearchResult) {
+    alloc::free_slice(result.matches, result.count);
+}
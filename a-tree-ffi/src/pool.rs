@@ -0,0 +1,270 @@
+//! Worker threads owned by the library, running the searches of `atree_search_async()` after the
+//! call returned and the chunks of the batch operations.
+
+use std::cell::Cell;
+use std::mem;
+use std::os::raw::c_void;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, OnceLock, PoisonError};
+use std::thread;
+
+use a_tree::{Event, Executor};
+
+use crate::debug::{is_invalid, is_invalid_builder};
+use crate::log::{self, AtreeLogLevel};
+use crate::parallel::parallelism;
+use crate::{
+    search_event, ATreeHandle, AtreeResult, AtreeSearchResult, Failure, ATREE_ERROR_INTERNAL,
+    ATREE_OK,
+};
+
+/// Receive the status and the result of `atree_search_async()`; the result is empty unless the
+/// status is `ATREE_OK` and must be freed with `atree_search_result_free()`
+pub type AtreeSearchCallback =
+    Option<unsafe extern "C" fn(status: i32, result: AtreeSearchResult, ctx: *mut c_void)>;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct Pool {
+    sender: Mutex<Sender<Job>>,
+    receiver: Arc<Mutex<Receiver<Job>>>,
+    workers: AtomicUsize,
+}
+
+static POOL: OnceLock<Pool> = OnceLock::new();
+
+thread_local! {
+    static IS_WORKER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Queue a job, starting workers until there are as many as allowed by `atree_set_parallelism()`.
+///
+/// The job is given back if no worker can run it.
+fn queue(job: Job) -> Result<(), Job> {
+    let pool = POOL.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        Pool {
+            sender: Mutex::new(sender),
+            receiver: Arc::new(Mutex::new(receiver)),
+            workers: AtomicUsize::new(0),
+        }
+    });
+    let sender = pool.sender.lock().unwrap_or_else(PoisonError::into_inner);
+    while pool.workers.load(Ordering::Relaxed) < parallelism().get() {
+        let index = pool.workers.load(Ordering::Relaxed);
+        let receiver = Arc::clone(&pool.receiver);
+        let spawned = thread::Builder::new()
+            .name(format!("a-tree-worker-{index}"))
+            .spawn(move || work(&receiver));
+        if let Err(error) = spawned {
+            log::log(AtreeLogLevel::LogError, || {
+                format!("failed to start a worker thread: {error}")
+            });
+            break;
+        }
+        pool.workers.store(index + 1, Ordering::Relaxed);
+    }
+    if pool.workers.load(Ordering::Relaxed) == 0 {
+        return Err(job);
+    }
+    sender.send(job).map_err(|error| error.0)
+}
+
+/// Queue a job, starting the workers on the first call.
+fn submit(job: Job) -> Result<(), Failure> {
+    queue(job).map_err(|_| {
+        Failure::new(
+            crate::ATREE_ERROR_OUT_OF_MEMORY,
+            "No worker thread could be started",
+        )
+    })
+}
+
+fn work(receiver: &Mutex<Receiver<Job>>) {
+    IS_WORKER.set(true);
+    loop {
+        let job = receiver
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .recv();
+        match job {
+            // A panic is reported by the job itself; the worker keeps serving the next ones
+            Ok(job) => drop(panic::catch_unwind(AssertUnwindSafe(job))),
+            Err(_) => return,
+        }
+    }
+}
+
+/// The jobs of a batch that are not done yet, and whether one of them panicked
+#[derive(Default)]
+struct Batch {
+    remaining: Mutex<usize>,
+    done: Condvar,
+    panicked: AtomicBool,
+}
+
+/// Counts a job of a batch as done once it ran, panicked or was dropped without running.
+struct Completion(Arc<Batch>);
+
+impl Drop for Completion {
+    fn drop(&mut self) {
+        let mut remaining = self
+            .0
+            .remaining
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *remaining -= 1;
+        if *remaining == 0 {
+            self.0.done.notify_all();
+        }
+    }
+}
+
+/// Run the jobs of a batch operation on the workers and wait until they are all done.
+///
+/// The jobs run on the calling thread when it is itself a worker, so that a batch started from
+/// the callback of `atree_search_async()` cannot wait for the worker it occupies.
+pub(crate) fn run_all(jobs: Vec<a_tree::Job<'_>>) -> Result<(), Failure> {
+    let batch = Arc::new(Batch {
+        remaining: Mutex::new(jobs.len()),
+        ..Batch::default()
+    });
+    let inline = jobs.len() <= 1 || IS_WORKER.get();
+    for job in jobs {
+        let completion = Completion(Arc::clone(&batch));
+        let job = move || {
+            if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                completion.0.panicked.store(true, Ordering::Relaxed);
+            }
+            drop(completion);
+        };
+        if inline {
+            job();
+            continue;
+        }
+        let job: a_tree::Job<'_> = Box::new(job);
+        // SAFETY: the borrows of the job outlive it because this function does not return before
+        // every job was counted as done by its `Completion`, which happens when the job ran or
+        // was dropped
+        let job = unsafe { mem::transmute::<a_tree::Job<'_>, Job>(job) };
+        if let Err(job) = queue(job) {
+            job();
+        }
+    }
+
+    let mut remaining = batch
+        .remaining
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    while *remaining > 0 {
+        remaining = batch
+            .done
+            .wait(remaining)
+            .unwrap_or_else(PoisonError::into_inner);
+    }
+    if batch.panicked.load(Ordering::Relaxed) {
+        log::log(AtreeLogLevel::LogError, || {
+            "a worker thread panicked while running a batch operation".to_owned()
+        });
+        return Err(Failure::new(
+            ATREE_ERROR_INTERNAL,
+            "A worker thread panicked",
+        ));
+    }
+    Ok(())
+}
+
+/// The [`Executor`] of the trees of the C API, running the chunks of their batch operations on
+/// the workers of the library
+pub(crate) struct WorkerPool;
+
+impl Executor for WorkerPool {
+    fn execute(&self, jobs: Vec<a_tree::Job<'_>>) {
+        // A job that panicked leaves its chunk without a result, which makes the batch operation
+        // panic in turn on the calling thread, where the C API catches it
+        let _ = run_all(jobs);
+    }
+}
+
+/// The pointers handed over to a worker; the host guarantees that they stay valid until the
+/// callback is called.
+struct AsyncSearch {
+    handle: *const ATreeHandle,
+    event: Event,
+    done: unsafe extern "C" fn(i32, AtreeSearchResult, *mut c_void),
+    ctx: *mut c_void,
+}
+
+unsafe impl Send for AsyncSearch {}
+
+impl AsyncSearch {
+    unsafe fn run(self) {
+        let search = panic::catch_unwind(AssertUnwindSafe(|| {
+            search_event(&*self.handle, &self.event).and_then(AtreeSearchResult::new)
+        }));
+        let (status, result) = match search
+            .unwrap_or_else(|_| Err(Failure::new(ATREE_ERROR_INTERNAL, "The search panicked")))
+        {
+            Ok(result) => (ATREE_OK, result),
+            Err(failure) => {
+                log::log(AtreeLogLevel::LogInfo, || {
+                    format!("failed to search asynchronously: {}", failure.message)
+                });
+                (failure.status, AtreeSearchResult::empty())
+            }
+        };
+        (self.done)(status, result, self.ctx);
+    }
+}
+
+/// Search the A-Tree on a worker thread and pass the matches to `done`.
+///
+/// The event is built before returning, so the builder can be reset and reused right away. The
+/// search then runs on one of the worker threads owned by the library, which are started as
+/// needed up to the value of `atree_set_parallelism()`. `done` is called on the worker thread
+/// with `ATREE_OK` and the matches, or with the status of the failure and an empty result, the
+/// reason of the failure going to the log callback.
+///
+/// # Returns
+/// A successful result if the search was queued; `done` is not called otherwise
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`; it must not be freed nor
+///   modified until `done` is called
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `done` must be thread-safe and must free the result with `atree_search_result_free()`
+/// - `ctx` must stay valid until `done` is called
+#[no_mangle]
+pub unsafe extern "C" fn atree_search_async(
+    handle: *const ATreeHandle,
+    builder: *const c_void,
+    done: AtreeSearchCallback,
+    ctx: *mut c_void,
+) -> AtreeResult {
+    search_async(handle, builder, done, ctx).into()
+}
+
+unsafe fn search_async(
+    handle: *const ATreeHandle,
+    builder: *const c_void,
+    done: AtreeSearchCallback,
+    ctx: *mut c_void,
+) -> Result<(), Failure> {
+    let Some(done) = done else {
+        return Err(Failure::invalid_arguments());
+    };
+    if is_invalid(handle) || is_invalid_builder(builder) {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let builder_ref = &*(builder as *const a_tree::EventBuilder);
+    let search = AsyncSearch {
+        handle,
+        event: builder_ref.to_event()?,
+        done,
+        ctx,
+    };
+    submit(Box::new(move || unsafe { search.run() }))
+}
@@ -0,0 +1,97 @@
+//! Introspection of the attributes of a tree, for the generic bindings that discover the schema
+//! at runtime instead of duplicating it in their configuration.
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+use a_tree::{ATree, AttributeKind};
+
+use crate::debug::is_invalid;
+use crate::{ATreeHandle, AtreeAttributeType};
+
+/// Attribute of a tree returned by `atree_attribute_at()`
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct AtreeAttributeInfo {
+    /// Name of the attribute, owned by the tree
+    pub name: *const c_char,
+    /// Type of the attribute
+    pub attr_type: AtreeAttributeType,
+}
+
+/// The attributes of a tree with their names as C strings, since the schema never changes.
+#[derive(Clone)]
+pub(crate) struct Schema {
+    attributes: Vec<(CString, AtreeAttributeType)>,
+}
+
+impl Schema {
+    pub(crate) fn new(tree: &ATree<u64>) -> Self {
+        let attributes = tree
+            .attributes()
+            .iter()
+            .map(|definition| {
+                let attr_type = match definition.kind() {
+                    AttributeKind::Boolean => AtreeAttributeType::Boolean,
+                    AttributeKind::Integer => AtreeAttributeType::Integer,
+                    AttributeKind::Float => AtreeAttributeType::Float,
+                    AttributeKind::String => AtreeAttributeType::String,
+                    AttributeKind::IntegerList => AtreeAttributeType::IntegerList,
+                    AttributeKind::StringList => AtreeAttributeType::StringList,
+                };
+                // The names of the trees loaded from JSON can contain a null character
+                let name = CString::new(definition.name()).unwrap_or_default();
+                (name, attr_type)
+            })
+            .collect();
+        Self { attributes }
+    }
+}
+
+/// Get the number of attributes of the tree.
+///
+/// # Returns
+/// Number of attributes, or 0 if `handle` is null
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_attribute_count(handle: *const ATreeHandle) -> usize {
+    if is_invalid(handle) {
+        return 0;
+    }
+
+    let handle_ref = &*handle;
+    handle_ref.schema.attributes.len()
+}
+
+/// Get the attribute at `index`, in the order of the definitions given to `atree_new()`.
+///
+/// The name remains valid until the tree is freed.
+///
+/// # Returns
+/// true if `out` was filled, false if `index` is out of bounds or on invalid arguments
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `out` must point to writable memory for an AtreeAttributeInfo
+#[no_mangle]
+pub unsafe extern "C" fn atree_attribute_at(
+    handle: *const ATreeHandle,
+    index: usize,
+    out: *mut AtreeAttributeInfo,
+) -> bool {
+    if is_invalid(handle) || out.is_null() {
+        return false;
+    }
+
+    let handle_ref = &*handle;
+    let Some((name, attr_type)) = handle_ref.schema.attributes.get(index) else {
+        return false;
+    };
+    *out = AtreeAttributeInfo {
+        name: name.as_ptr(),
+        attr_type: *attr_type,
+    };
+    true
+}
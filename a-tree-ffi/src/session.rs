@@ -0,0 +1,432 @@
+//! Sessions writing to a concurrent handle that search their own writes before they are
+//! published to the other readers.
+
+use std::borrow::Cow;
+use std::os::raw::c_char;
+use std::ptr;
+use std::sync::Arc;
+
+use crate::debug;
+use crate::log::{self, AtreeLogLevel};
+use crate::{log_insert_failure, to_str, ATreeHandle, AtreeConcurrentHandle, AtreeResult, Failure};
+
+/// Opaque handle to a session of a concurrent handle
+///
+/// The writes of the session are applied to a copy of the published tree, which the session
+/// searches instead of the published tree until the writes are published. A session is used by
+/// one thread at a time.
+///
+/// The copy is a full clone of the tree, so it costs as much as the size of the tree: it is made
+/// by the first write, again by the first write or search after another tree was published, and
+/// again by a write made while a tree of the session is pinned.
+pub struct AtreeSessionHandle {
+    concurrent: *const AtreeConcurrentHandle,
+    fork: Option<Fork>,
+    writes: Vec<Write>,
+}
+
+/// A copy of a published tree along with the writes of the session
+struct Fork {
+    base: Arc<ATreeHandle>,
+    tree: Arc<ATreeHandle>,
+}
+
+/// A write of a session, replayed on every tree published before the session publishes it
+enum Write {
+    Insert {
+        subscription_id: u64,
+        expression: String,
+    },
+    Delete(u64),
+}
+
+impl AtreeSessionHandle {
+    /// The fork of the current published tree, whose copy is made again with the writes of the
+    /// session when another tree was published since the last one.
+    ///
+    /// The writes are kept when they cannot be replayed on the published tree.
+    unsafe fn fork(&mut self) -> Result<&mut Fork, Failure> {
+        let current = (*self.concurrent).current();
+        let fork = match self.fork.take() {
+            Some(fork) if Arc::ptr_eq(&fork.base, &current) => {
+                debug::release_shared(current);
+                fork
+            }
+            previous => {
+                if let Some(previous) = previous {
+                    previous.release();
+                }
+                Fork::new(current, &self.writes)?
+            }
+        };
+        Ok(self.fork.insert(fork))
+    }
+
+    /// Apply a write to the fork and keep it to be replayed.
+    unsafe fn write(&mut self, write: Write) -> Result<(), Failure> {
+        write.apply(self.fork()?.tree_mut())?;
+        self.writes.push(write);
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.writes.clear();
+        if let Some(fork) = self.fork.take() {
+            fork.release();
+        }
+    }
+}
+
+impl Fork {
+    /// Copy `base` and replay the writes on the copy, failing with the first write that is
+    /// rejected, such as an ID already inserted by another writer into a tree rejecting the
+    /// duplicates.
+    fn new(base: Arc<ATreeHandle>, writes: &[Write]) -> Result<Self, Failure> {
+        let mut tree = ATreeHandle::clone(&base);
+        for write in writes {
+            if let Err(failure) = write.apply(&mut tree) {
+                debug::release_shared(base);
+                return Err(Failure {
+                    status: failure.status,
+                    message: Cow::Owned(format!(
+                        "A write of the session cannot be replayed on the published tree: {}",
+                        failure.message
+                    )),
+                });
+            }
+        }
+        let tree = Arc::new(tree);
+        debug::track(Arc::as_ptr(&tree));
+        Ok(Self { base, tree })
+    }
+
+    /// The tree of the fork, copied first if a search of the session still has it pinned.
+    fn tree_mut(&mut self) -> &mut ATreeHandle {
+        let pinned = Arc::as_ptr(&self.tree);
+        let tree = Arc::make_mut(&mut self.tree);
+        if !ptr::eq(pinned, tree) {
+            debug::track(&*tree);
+        }
+        tree
+    }
+
+    fn release(self) {
+        debug::release_shared(self.base);
+        debug::release_shared(self.tree);
+    }
+}
+
+impl Write {
+    fn apply(&self, handle: &mut ATreeHandle) -> Result<(), Failure> {
+        match self {
+            Self::Insert {
+                subscription_id,
+                expression,
+            } => {
+                handle
+                    .tree
+                    .insert(subscription_id, expression)
+                    .inspect_err(|error| log_insert_failure(subscription_id, error))?;
+            }
+            Self::Delete(subscription_id) => {
+                handle.tree.delete(subscription_id);
+                handle.payloads.remove(subscription_id);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Create a session writing to the trees published by `concurrent`.
+///
+/// # Returns
+/// Pointer to the session, or null if `concurrent` is null
+///
+/// # Safety
+/// - `concurrent` must be a valid pointer returned by `atree_concurrent_new()`; it must not be
+///   freed before the session
+/// - Caller must free the returned session with `atree_session_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_session_new(
+    concurrent: *const AtreeConcurrentHandle,
+) -> *mut AtreeSessionHandle {
+    if concurrent.is_null() {
+        return ptr::null_mut();
+    }
+
+    Box::into_raw(Box::new(AtreeSessionHandle {
+        concurrent,
+        fork: None,
+        writes: Vec::new(),
+    }))
+}
+
+/// Insert a boolean expression in the tree of the session.
+///
+/// The first write of a session copies the published tree, which costs as much as the size of
+/// the tree. The expression is searched by the trees acquired from the session right away, and
+/// by the other readers once it is published with `atree_session_publish()`.
+///
+/// The call fails without writing when another tree was published and the pending writes of the
+/// session cannot be replayed on it; see `atree_session_publish()`.
+///
+/// # Safety
+/// - `session` must be a valid pointer returned by `atree_session_new()`
+/// - `expression` must be a valid null-terminated C string
+/// - Caller must free `error_message` with `atree_free_error()` on failure
+#[no_mangle]
+pub unsafe extern "C" fn atree_session_insert(
+    session: *mut AtreeSessionHandle,
+    subscription_id: u64,
+    expression: *const c_char,
+) -> AtreeResult {
+    insert(session, subscription_id, expression).into()
+}
+
+unsafe fn insert(
+    session: *mut AtreeSessionHandle,
+    subscription_id: u64,
+    expression: *const c_char,
+) -> Result<(), Failure> {
+    if session.is_null() || expression.is_null() {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let expression = to_str(expression, "Invalid UTF-8 in expression")?;
+    (*session).write(Write::Insert {
+        subscription_id,
+        expression: expression.to_owned(),
+    })
+}
+
+/// Delete a subscription by ID, along with its payload, from the tree of the session.
+///
+/// The call fails without writing when another tree was published and the pending writes of the
+/// session cannot be replayed on it; see `atree_session_publish()`.
+///
+/// # Safety
+/// - `session` must be a valid pointer returned by `atree_session_new()`
+/// - Caller must free `error_message` with `atree_free_error()` on failure
+#[no_mangle]
+pub unsafe extern "C" fn atree_session_delete(
+    session: *mut AtreeSessionHandle,
+    subscription_id: u64,
+) -> AtreeResult {
+    delete(session, subscription_id).into()
+}
+
+unsafe fn delete(session: *mut AtreeSessionHandle, subscription_id: u64) -> Result<(), Failure> {
+    if session.is_null() {
+        return Err(Failure::invalid_arguments());
+    }
+
+    (*session).write(Write::Delete(subscription_id))
+}
+
+/// Pin the tree searched by the session: the published tree with the writes of the session that
+/// were not published yet.
+///
+/// The writes made while a tree is pinned are not visible in it; the next write of the session
+/// copies the whole tree again, so the pinned tree should be released before writing.
+///
+/// # Returns
+/// Pointer to the pinned tree, or null if `session` is null or if another tree was published
+/// and the pending writes of the session cannot be replayed on it
+///
+/// # Safety
+/// - `session` must be a valid pointer returned by `atree_session_new()`
+/// - Caller must release the returned tree with `atree_concurrent_release()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_session_acquire(
+    session: *mut AtreeSessionHandle,
+) -> *const ATreeHandle {
+    if session.is_null() {
+        return ptr::null();
+    }
+
+    let session = &mut *session;
+    let tree = if session.writes.is_empty() {
+        (*session.concurrent).current()
+    } else {
+        match session.fork() {
+            Ok(fork) => Arc::clone(&fork.tree),
+            Err(failure) => {
+                log::log(AtreeLogLevel::LogError, || failure.message.into_owned());
+                return ptr::null();
+            }
+        }
+    };
+    Arc::into_raw(tree)
+}
+
+/// Publish the tree of the session, with its writes, to the readers of the concurrent handle.
+///
+/// When another tree was published since the last write, the writes are replayed on it before
+/// it is replaced, so that no write is lost. If one of them is rejected by that tree, for instance
+/// because another writer inserted the same ID into a tree rejecting the duplicates, nothing is
+/// published and the call fails with the status of the rejected write; the writes are kept until
+/// the session is reset.
+///
+/// # Safety
+/// - `session` must be a valid pointer returned by `atree_session_new()`
+/// - Caller must free `error_message` with `atree_free_error()` on failure
+#[no_mangle]
+pub unsafe extern "C" fn atree_session_publish(session: *mut AtreeSessionHandle) -> AtreeResult {
+    publish(session).into()
+}
+
+unsafe fn publish(session: *mut AtreeSessionHandle) -> Result<(), Failure> {
+    if session.is_null() {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let session = &mut *session;
+    let concurrent = &*session.concurrent;
+    while !session.writes.is_empty() {
+        let fork = session.fork()?;
+        match concurrent.replace_if(&fork.base, Arc::clone(&fork.tree)) {
+            Ok(previous) => {
+                debug::release_shared(previous);
+                session.reset();
+            }
+            // Another tree was published in the meantime
+            Err(tree) => debug::release_shared(tree),
+        }
+    }
+    Ok(())
+}
+
+/// Drop the writes of the session that were not published, for instance once they are part of
+/// a tree published by another writer; the session searches the published tree again.
+///
+/// # Safety
+/// - `session` must be a valid pointer returned by `atree_session_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_session_reset(session: *mut AtreeSessionHandle) {
+    if !session.is_null() {
+        (*session).reset();
+    }
+}
+
+/// Get the number of writes of the session that were not published.
+///
+/// # Returns
+/// Number of pending writes, or 0 if `session` is null
+///
+/// # Safety
+/// - `session` must be a valid pointer returned by `atree_session_new()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_session_pending(session: *const AtreeSessionHandle) -> usize {
+    if session.is_null() {
+        return 0;
+    }
+
+    (*session).writes.len()
+}
+
+/// Free a session, dropping the writes that were not published.
+///
+/// The trees pinned with `atree_session_acquire()` remain valid until they are released.
+///
+/// # Safety
+/// - `session` must be null or a valid pointer returned by `atree_session_new()`
+/// - `session` must not be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_session_free(session: *mut AtreeSessionHandle) {
+    if !session.is_null() {
+        Box::from_raw(session).reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        atree_concurrent_acquire, atree_concurrent_free, atree_concurrent_new,
+        atree_concurrent_release, atree_swap, ATREE_ERROR_DUPLICATE_ID,
+    };
+    use a_tree::{ATree, ATreeConfig, AttributeDefinition, DuplicateIdPolicy};
+
+    fn tree(expressions: &[(u64, &str)]) -> *mut ATreeHandle {
+        let definitions = [AttributeDefinition::integer("age")];
+        let config = ATreeConfig::default().with_duplicate_ids(DuplicateIdPolicy::Reject);
+        let mut tree = ATree::with_config(&definitions, config).unwrap();
+        for (id, expression) in expressions {
+            tree.insert(id, expression).unwrap();
+        }
+        debug::into_raw(ATreeHandle::new(tree))
+    }
+
+    unsafe fn matches(tree: *const ATreeHandle, age: i64) -> Vec<u64> {
+        let tree = &(*tree).tree;
+        let mut builder = tree.make_event();
+        builder.with_integer("age", age).unwrap();
+        let event = builder.build().unwrap();
+        let mut matches: Vec<_> = tree
+            .search(&event)
+            .unwrap()
+            .matches()
+            .iter()
+            .map(|id| **id)
+            .collect();
+        matches.sort_unstable();
+        matches
+    }
+
+    #[test]
+    fn search_the_writes_of_the_session_before_they_are_published() {
+        unsafe {
+            let concurrent = atree_concurrent_new(tree(&[(1, "age > 18")]));
+            let session = atree_session_new(concurrent);
+
+            assert!(atree_session_insert(session, 2, c"age > 10".as_ptr()).success);
+            assert!(atree_session_delete(session, 1).success);
+
+            let own = atree_session_acquire(session);
+            let published = atree_concurrent_acquire(concurrent);
+            assert_eq!(vec![2], matches(own, 20));
+            assert_eq!(vec![1], matches(published, 20));
+            atree_concurrent_release(own);
+            atree_concurrent_release(published);
+
+            assert!(atree_session_publish(session).success);
+            assert_eq!(0, atree_session_pending(session));
+            let published = atree_concurrent_acquire(concurrent);
+            assert_eq!(vec![2], matches(published, 20));
+            atree_concurrent_release(published);
+
+            atree_session_free(session);
+            atree_concurrent_free(concurrent);
+        }
+    }
+
+    #[test]
+    fn keep_the_writes_that_cannot_be_replayed_on_the_tree_published_by_another_writer() {
+        unsafe {
+            let concurrent = atree_concurrent_new(tree(&[]));
+            let session = atree_session_new(concurrent);
+            assert!(atree_session_insert(session, 1, c"age > 18".as_ptr()).success);
+
+            assert!(atree_swap(concurrent, tree(&[(1, "age < 10")])).success);
+            let failure = publish(session).unwrap_err();
+
+            assert_eq!(ATREE_ERROR_DUPLICATE_ID, failure.status);
+            assert_eq!(1, atree_session_pending(session));
+            assert!(atree_session_acquire(session).is_null());
+            let published = atree_concurrent_acquire(concurrent);
+            assert_eq!(vec![1], matches(published, 5));
+            assert!(matches(published, 20).is_empty());
+            atree_concurrent_release(published);
+
+            atree_session_reset(session);
+            assert!(atree_session_insert(session, 2, c"age > 18".as_ptr()).success);
+            assert!(atree_session_publish(session).success);
+            let published = atree_concurrent_acquire(concurrent);
+            assert_eq!(vec![2], matches(published, 20));
+            atree_concurrent_release(published);
+
+            atree_session_free(session);
+            atree_concurrent_free(concurrent);
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Snapshots handed to other processes through a sealed shared-memory file, for the pre-fork
+//! servers whose workers load the same tree.
+//!
+//! The segment holds a single copy of the snapshot for all the processes; each worker maps it
+//! read-only and decodes its own tree from the mapping without reading a file. This is not a
+//! read-only tree shared by the workers: the decoded tree is made of pointers so it cannot be
+//! searched in place, every worker that opens the segment holds a private copy of the tree, as
+//! large as the tree itself, and the segment only saves the reads of a file. A server that must
+//! not hold a copy per worker has to build the tree before forking them instead, since the
+//! searches do not write to the tree and its pages stay shared copy-on-write.
+
+use std::borrow::Cow;
+use std::os::raw::c_int;
+use std::ptr;
+
+use a_tree::ATree;
+
+use crate::debug::{self, is_invalid};
+use crate::{log, ATreeHandle, AtreeLogLevel, AtreeResult, Failure, ATREE_ERROR_IO};
+
+/// Write a snapshot of the tree to a new sealed shared-memory file and return its descriptor.
+///
+/// The file is created with `memfd_create()` and sealed against any modification once written,
+/// so the workers can trust its content. The descriptor is inherited by the forked processes and
+/// can be sent to the other ones over a Unix socket; it is closed on `exec()`. The payloads
+/// attached with `atree_insert_with_payload()` are not part of the snapshot. Only supported on
+/// Linux; the failures to create or seal the file are reported with `ATREE_ERROR_IO`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `out_fd` must be a valid pointer to an `int`
+/// - Caller must close the descriptor once the workers opened it
+/// - Caller must free `error_message` with `atree_free_error()` on failure
+#[no_mangle]
+pub unsafe extern "C" fn atree_share(
+    handle: *const ATreeHandle,
+    out_fd: *mut c_int,
+) -> AtreeResult {
+    share(handle, out_fd).into()
+}
+
+unsafe fn share(handle: *const ATreeHandle, out_fd: *mut c_int) -> Result<(), Failure> {
+    if is_invalid(handle) || out_fd.is_null() {
+        return Err(Failure::invalid_arguments());
+    }
+    let snapshot = (*handle).tree.to_snapshot();
+    let fd = segment::create(&snapshot).map_err(|error| Failure {
+        status: ATREE_ERROR_IO,
+        message: Cow::Owned(format!("failed to share the snapshot: {error}")),
+    })?;
+    *out_fd = fd;
+    Ok(())
+}
+
+/// Create a new A-Tree from the shared-memory file written by `atree_share()`.
+///
+/// The file is mapped read-only while the tree is decoded, then unmapped and left open. The tree
+/// is a private copy of the snapshot, as large as the tree of the process that shared it. A file
+/// that is not sealed against the writes and the shrinking is rejected, since another process
+/// could change it while it is decoded. The reason of a failure is reported to the log callback
+/// at the error level.
+///
+/// # Returns
+/// Pointer to ATreeHandle on success, null on failure
+///
+/// # Safety
+/// - `fd` must be a descriptor returned by `atree_share()`, or a copy of it
+/// - Caller must free the returned handle with `atree_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_open_shared(fd: c_int) -> *mut ATreeHandle {
+    let loaded = segment::read(fd, |bytes| {
+        ATree::<u64>::from_snapshot(bytes).map_err(|error| error.to_string())
+    });
+    match loaded {
+        Ok(tree) => debug::into_raw(ATreeHandle::new(tree)),
+        Err(error) => {
+            log::log(AtreeLogLevel::LogError, || {
+                format!("failed to open the shared snapshot {fd}: {error}")
+            });
+            ptr::null_mut()
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod segment {
+    use std::fs::File;
+    use std::io::{self, Write};
+    use std::os::fd::{FromRawFd, IntoRawFd};
+    use std::os::raw::c_int;
+    use std::{ptr, slice};
+
+    pub(super) fn create(bytes: &[u8]) -> io::Result<c_int> {
+        // SAFETY: the name is a null-terminated string and the flags are valid.
+        let fd = unsafe {
+            libc::memfd_create(
+                c"atree-snapshot".as_ptr(),
+                libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the descriptor was just created and is owned by the file until it is sealed.
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        file.write_all(bytes)?;
+        let seals =
+            libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL;
+        // SAFETY: the descriptor is valid and was created with sealing allowed.
+        if unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, seals) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(file.into_raw_fd())
+    }
+
+    pub(super) fn read<T>(
+        fd: c_int,
+        decode: impl FnOnce(&[u8]) -> Result<T, String>,
+    ) -> Result<T, String> {
+        // SAFETY: an invalid descriptor or one that does not support the seals is reported by the
+        // call.
+        let seals = unsafe { libc::fcntl(fd, libc::F_GET_SEALS) };
+        if seals < 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        let required = libc::F_SEAL_WRITE | libc::F_SEAL_SHRINK;
+        if seals & required != required {
+            return Err("the file is not sealed against the writes and the shrinking".to_owned());
+        }
+        // SAFETY: `stat` is plain data that `fstat()` fills.
+        let mut stat = unsafe { std::mem::zeroed::<libc::stat>() };
+        // SAFETY: `stat` is a valid pointer; an invalid descriptor is reported by the call.
+        if unsafe { libc::fstat(fd, &mut stat) } < 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        let size = usize::try_from(stat.st_size).map_err(|error| error.to_string())?;
+        if size == 0 {
+            return decode(&[]);
+        }
+        // SAFETY: the mapping is read-only and unmapped before returning.
+        let mapping = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                size,
+                libc::PROT_READ,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            )
+        };
+        if mapping == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        // SAFETY: the mapping covers `size` bytes that are sealed against writes.
+        let decoded = decode(unsafe { slice::from_raw_parts(mapping.cast::<u8>(), size) });
+        // SAFETY: the mapping was returned by `mmap()` with the same size.
+        unsafe { libc::munmap(mapping, size) };
+        decoded
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod segment {
+    use std::io;
+    use std::os::raw::c_int;
+
+    const UNSUPPORTED: &str = "the shared snapshots are only supported on Linux";
+
+    pub(super) fn create(_bytes: &[u8]) -> io::Result<c_int> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, UNSUPPORTED))
+    }
+
+    pub(super) fn read<T>(
+        _fd: c_int,
+        _decode: impl FnOnce(&[u8]) -> Result<T, String>,
+    ) -> Result<T, String> {
+        Err(UNSUPPORTED.to_owned())
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::atree_free;
+    use a_tree::AttributeDefinition;
+    use std::fs::File;
+    use std::io::Write;
+    use std::os::fd::{AsRawFd, FromRawFd};
+
+    #[test]
+    fn can_open_a_shared_snapshot() {
+        let definitions = [AttributeDefinition::integer("age")];
+        let mut tree = ATree::new(&definitions).unwrap();
+        tree.insert(&1, "age > 18").unwrap();
+        let handle = debug::into_raw(ATreeHandle::new(tree));
+        let mut fd = -1;
+
+        unsafe {
+            assert!(atree_share(handle, &mut fd).success);
+            let file = File::from_raw_fd(fd);
+            let opened = atree_open_shared(file.as_raw_fd());
+
+            assert!(!opened.is_null());
+            assert_eq!(Some("age > 18"), (*opened).tree.expression(&1));
+            atree_free(opened);
+            atree_free(handle);
+        }
+    }
+
+    #[test]
+    fn reject_a_file_that_is_not_sealed() {
+        let definitions = [AttributeDefinition::integer("age")];
+        let tree = ATree::<u64>::new(&definitions).unwrap();
+        unsafe {
+            let fd = libc::memfd_create(c"atree-test".as_ptr(), libc::MFD_CLOEXEC);
+            assert!(fd >= 0);
+            let mut file = File::from_raw_fd(fd);
+            file.write_all(&tree.to_snapshot()).unwrap();
+
+            assert!(atree_open_shared(file.as_raw_fd()).is_null());
+        }
+    }
+}
@@ -0,0 +1,671 @@
+//! Entry points taking UTF-8 strings as a pointer and a length instead of null-terminated
+//! strings, so that the hosts using length-prefixed strings do not copy them to add a
+//! terminator.
+
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::slice;
+
+use a_tree::{ATree, EventBuilder, InternedString, Metadata};
+
+use crate::debug::{self, is_invalid, is_invalid_builder};
+use crate::{
+    ast, event, explain, insert_with_error, log_insert_failure, payload, tags, validate_expression,
+    ATreeHandle, AtreeAst, AtreeEventHandle, AtreeParseError, AtreeResult, AtreeSearchResult,
+    Failure, ATREE_ERROR_INVALID_ARGUMENT, ATREE_ERROR_INVALID_UTF8,
+};
+
+/// Borrow `len` bytes; a null pointer is only accepted for an empty string.
+unsafe fn to_bytes<'a>(value: *const c_char, len: usize) -> Option<&'a [u8]> {
+    match (value.is_null(), len) {
+        (true, 0) => Some(&[]),
+        (true, _) => None,
+        (false, _) => Some(slice::from_raw_parts(value.cast(), len)),
+    }
+}
+
+/// Borrow `len` bytes as a string.
+unsafe fn to_str<'a>(
+    value: *const c_char,
+    len: usize,
+    message: &'static str,
+) -> Result<&'a str, Failure> {
+    let bytes = to_bytes(value, len).ok_or_else(Failure::invalid_arguments)?;
+    std::str::from_utf8(bytes).map_err(|_| Failure::new(ATREE_ERROR_INVALID_UTF8, message))
+}
+
+/// Borrow the event builder and the name of the attribute to set.
+unsafe fn event_builder<'a>(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+) -> Result<(&'a mut EventBuilder<'a>, &'a str), Failure> {
+    if is_invalid_builder(builder) {
+        return Err(Failure::invalid_arguments());
+    }
+    let name = to_str(name, name_len, "Invalid UTF-8 in name")?;
+    Ok((&mut *(builder as *mut EventBuilder), name))
+}
+
+/// Insert a boolean expression of `len` bytes; see `atree_insert()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `expression` must point to `len` readable bytes; it can be null if `len` is 0
+/// - Caller must free result.error_message with `atree_free_error()` if !success
+#[no_mangle]
+pub unsafe extern "C" fn atree_insert_n(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: *const c_char,
+    len: usize,
+) -> AtreeResult {
+    insert(handle, subscription_id, expression, len).into()
+}
+
+unsafe fn insert(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: *const c_char,
+    len: usize,
+) -> Result<(), Failure> {
+    if is_invalid(handle) {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let expr_str = to_str(expression, len, "Invalid UTF-8 in expression")?;
+    let handle_ref = &mut *handle;
+    handle_ref
+        .tree
+        .insert(&subscription_id, expr_str)
+        .inspect_err(|error| log_insert_failure(subscription_id, error))?;
+    Ok(())
+}
+
+/// Insert a boolean expression of `len` bytes and describe where it is invalid on failure; see
+/// `atree_insert_with_error()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `expression` must point to `len` readable bytes; it can be null if `len` is 0
+/// - `error` must be null or point to writable memory for an `AtreeParseError`
+/// - Caller must free result.error_message with `atree_free_error()` if !success
+/// - Caller must free `*error` with `atree_parse_error_free()` if !success and `error` is not null
+#[no_mangle]
+pub unsafe extern "C" fn atree_insert_with_error_n(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: *const c_char,
+    len: usize,
+    error: *mut AtreeParseError,
+) -> AtreeResult {
+    insert_with_error(handle, subscription_id, to_bytes(expression, len), error)
+}
+
+/// Check whether an expression of `len` bytes could be inserted; see
+/// `atree_validate_expression()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `expression` must point to `len` readable bytes; it can be null if `len` is 0
+/// - `error` must be null or point to writable memory for an `AtreeParseError`
+/// - Caller must free `*error` with `atree_parse_error_free()` if false is returned and `error`
+///   is not null
+#[no_mangle]
+pub unsafe extern "C" fn atree_validate_expression_n(
+    handle: *const ATreeHandle,
+    expression: *const c_char,
+    len: usize,
+    error: *mut AtreeParseError,
+) -> bool {
+    validate_expression(handle, to_bytes(expression, len), error)
+}
+
+/// Insert a boolean expression of `len` bytes and attach a copy of `data` to the subscription;
+/// see `atree_insert_with_payload()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `expression` must point to `len` readable bytes; it can be null if `len` is 0
+/// - `data` must point to `data_len` readable bytes
+/// - Caller must free `error_message` with `atree_free_error()` on failure
+#[no_mangle]
+pub unsafe extern "C" fn atree_insert_with_payload_n(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: *const c_char,
+    len: usize,
+    data: *const c_void,
+    data_len: usize,
+) -> AtreeResult {
+    payload::insert_with_payload(handle, subscription_id, data, data_len, || {
+        insert(handle, subscription_id, expression, len)
+    })
+    .into()
+}
+
+/// Insert a boolean expression of `len` bytes tagged with each of the specified tags;
+/// `tags[i]` is made of `tag_lengths[i]` bytes. See `atree_insert_tagged()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `expression` must point to `len` readable bytes; it can be null if `len` is 0
+/// - `tags` and `tag_lengths` must point to arrays of `count` elements
+/// - `tags[i]` must point to `tag_lengths[i]` readable bytes
+/// - Caller must free `error_message` with `atree_free_error()` on failure
+#[no_mangle]
+pub unsafe extern "C" fn atree_insert_tagged_n(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: *const c_char,
+    len: usize,
+    tags: *const *const c_char,
+    tag_lengths: *const usize,
+    count: usize,
+) -> AtreeResult {
+    insert_tagged(
+        handle,
+        subscription_id,
+        expression,
+        len,
+        tags,
+        tag_lengths,
+        count,
+    )
+    .into()
+}
+
+unsafe fn insert_tagged(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: *const c_char,
+    len: usize,
+    tags: *const *const c_char,
+    tag_lengths: *const usize,
+    count: usize,
+) -> Result<(), Failure> {
+    if is_invalid(handle) || ((tags.is_null() || tag_lengths.is_null()) && count > 0) {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let mut metadata = Metadata::new();
+    if count > 0 {
+        let tags = slice::from_raw_parts(tags, count);
+        let tag_lengths = slice::from_raw_parts(tag_lengths, count);
+        for (&tag, &tag_len) in tags.iter().zip(tag_lengths) {
+            metadata = metadata.with_tag(to_str(tag, tag_len, "Invalid UTF-8 in tag")?);
+        }
+    }
+    let expr_str = to_str(expression, len, "Invalid UTF-8 in expression")?;
+    tags::insert_with_metadata(&mut *handle, subscription_id, expr_str, metadata)
+}
+
+/// Delete every subscription tagged with a tag of `len` bytes; see `atree_delete_by_tag()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `tag` must point to `len` readable bytes; it can be null if `len` is 0
+#[no_mangle]
+pub unsafe extern "C" fn atree_delete_by_tag_n(
+    handle: *mut ATreeHandle,
+    tag: *const c_char,
+    len: usize,
+) -> usize {
+    if is_invalid(handle) {
+        return 0;
+    }
+    let Ok(tag) = to_str(tag, len, "Invalid UTF-8 in tag") else {
+        return 0;
+    };
+
+    tags::delete_by_tag(&mut *handle, tag)
+}
+
+/// Get the IDs of the subscriptions tagged with a tag of `len` bytes; see
+/// `atree_ids_with_tag()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `tag` must point to `len` readable bytes; it can be null if `len` is 0
+/// - Caller must free the returned result with `atree_search_result_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_ids_with_tag_n(
+    handle: *const ATreeHandle,
+    tag: *const c_char,
+    len: usize,
+) -> AtreeSearchResult {
+    if is_invalid(handle) {
+        return AtreeSearchResult::empty();
+    }
+    let Ok(tag) = to_str(tag, len, "Invalid UTF-8 in tag") else {
+        return AtreeSearchResult::empty();
+    };
+
+    tags::ids_with_tag(&*handle, tag)
+}
+
+/// Parse an expression of `len` bytes without inserting it; see `atree_parse()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `expression` must point to `len` readable bytes; it can be null if `len` is 0
+/// - Caller must free the returned expression with `atree_ast_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_parse_n(
+    handle: *const ATreeHandle,
+    expression: *const c_char,
+    len: usize,
+) -> *mut AtreeAst {
+    if is_invalid(handle) {
+        return ptr::null_mut();
+    }
+    let Ok(expression) = to_str(expression, len, "Invalid UTF-8 in expression") else {
+        return ptr::null_mut();
+    };
+
+    ast::parse(&*handle, expression)
+}
+
+/// Check an expression of `len` bytes for mistakes; see `atree_lint_expression()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `expression` must point to `len` readable bytes; it can be null if `len` is 0
+/// - Caller must free the returned string with `atree_free_string()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_lint_expression_n(
+    handle: *const ATreeHandle,
+    expression: *const c_char,
+    len: usize,
+) -> *mut c_char {
+    if is_invalid(handle) {
+        return ptr::null_mut();
+    }
+    let Ok(expression) = to_str(expression, len, "Invalid UTF-8 in expression") else {
+        return ptr::null_mut();
+    };
+
+    explain::lint_expression(&*handle, expression)
+}
+
+/// Intern a string of `len` bytes; see `atree_intern_string()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `value` must point to `len` readable bytes; it can be null if `len` is 0
+/// - `out_id` must point to writable memory for a u64
+#[no_mangle]
+pub unsafe extern "C" fn atree_intern_string_n(
+    handle: *mut ATreeHandle,
+    value: *const c_char,
+    len: usize,
+    out_id: *mut u64,
+) -> AtreeResult {
+    intern_string(handle, value, len, out_id).into()
+}
+
+unsafe fn intern_string(
+    handle: *mut ATreeHandle,
+    value: *const c_char,
+    len: usize,
+    out_id: *mut u64,
+) -> Result<(), Failure> {
+    if is_invalid(handle) || out_id.is_null() {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let value_str = to_str(value, len, "Invalid UTF-8 in value")?;
+    let handle_ref = &mut *handle;
+    *out_id = handle_ref.tree.intern(value_str).to_raw();
+    Ok(())
+}
+
+/// Look up the interned ID of a string of `len` bytes without interning it; see
+/// `atree_lookup_string()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `value` must point to `len` readable bytes; it can be null if `len` is 0
+/// - `out_id` must point to writable memory for a u64
+#[no_mangle]
+pub unsafe extern "C" fn atree_lookup_string_n(
+    handle: *const ATreeHandle,
+    value: *const c_char,
+    len: usize,
+    out_id: *mut u64,
+) -> bool {
+    if is_invalid(handle) || out_id.is_null() {
+        return false;
+    }
+    let Ok(value_str) = to_str(value, len, "Invalid UTF-8 in value") else {
+        return false;
+    };
+
+    let handle_ref = &*handle;
+    match handle_ref.tree.interned(value_str) {
+        Some(interned) => {
+            *out_id = interned.to_raw();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Build an event from a JSON object of `len` bytes; see `atree_event_from_json()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `json` must point to `len` readable bytes
+/// - Caller must free the returned event with `atree_event_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_from_json_n(
+    handle: *const ATreeHandle,
+    json: *const c_char,
+    len: usize,
+) -> *mut AtreeEventHandle {
+    if is_invalid(handle) {
+        return ptr::null_mut();
+    }
+    let Ok(json) = to_str(json, len, "Invalid UTF-8 in JSON") else {
+        return ptr::null_mut();
+    };
+
+    event::event_from_json(&*handle, json)
+}
+
+/// Create a new A-Tree from a JSON document of `len` bytes; see `atree_import_json()`.
+///
+/// # Safety
+/// - `json` must point to `len` readable bytes
+/// - Caller must free the returned handle with `atree_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_import_json_n(json: *const c_char, len: usize) -> *mut ATreeHandle {
+    let Ok(json_str) = to_str(json, len, "Invalid UTF-8 in JSON") else {
+        return ptr::null_mut();
+    };
+
+    match ATree::<u64>::import_json(json_str) {
+        Ok(tree) => debug::into_raw(ATreeHandle::new(tree)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Add a boolean attribute with a name of `name_len` bytes to the event.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must point to `name_len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_boolean_n(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+    value: bool,
+) -> AtreeResult {
+    with_boolean(builder, name, name_len, value).into()
+}
+
+unsafe fn with_boolean(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+    value: bool,
+) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name, name_len)?;
+    builder.with_boolean(name, value)?;
+    Ok(())
+}
+
+/// Add an integer attribute with a name of `name_len` bytes to the event.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must point to `name_len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_integer_n(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+    value: i64,
+) -> AtreeResult {
+    with_integer(builder, name, name_len, value).into()
+}
+
+unsafe fn with_integer(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+    value: i64,
+) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name, name_len)?;
+    builder.with_integer(name, value)?;
+    Ok(())
+}
+
+/// Add a float attribute with a name of `name_len` bytes to the event from a double; see
+/// `atree_event_builder_with_double()`.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must point to `name_len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_double_n(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+    value: f64,
+) -> AtreeResult {
+    with_double(builder, name, name_len, value).into()
+}
+
+unsafe fn with_double(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+    value: f64,
+) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name, name_len)?;
+    builder.with_f64(name, value)?;
+    Ok(())
+}
+
+/// Add a float attribute with a name of `name_len` bytes to the event; see
+/// `atree_event_builder_with_float()`.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must point to `name_len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_float_n(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+    number: i64,
+    scale: u32,
+) -> AtreeResult {
+    with_float(builder, name, name_len, number, scale).into()
+}
+
+unsafe fn with_float(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+    number: i64,
+    scale: u32,
+) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name, name_len)?;
+    builder.with_float(name, number, scale)?;
+    Ok(())
+}
+
+/// Add a string attribute to the event, with a name of `name_len` bytes and a value of
+/// `value_len` bytes.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must point to `name_len` readable bytes
+/// - `value` must point to `value_len` readable bytes; it can be null if `value_len` is 0
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_string_n(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+    value: *const c_char,
+    value_len: usize,
+) -> AtreeResult {
+    with_string(builder, name, name_len, value, value_len).into()
+}
+
+unsafe fn with_string(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+    value: *const c_char,
+    value_len: usize,
+) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name, name_len)?;
+    let value_str = to_str(value, value_len, "Invalid UTF-8 in value")?;
+    builder.with_string(name, value_str)?;
+    Ok(())
+}
+
+/// Add a string attribute with a name of `name_len` bytes to the event from an ID returned by
+/// `atree_intern_string()`.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must point to `name_len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_interned_string_n(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+    id: u64,
+) -> AtreeResult {
+    with_interned_string(builder, name, name_len, id).into()
+}
+
+unsafe fn with_interned_string(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+    id: u64,
+) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name, name_len)?;
+    builder.with_interned_string(name, InternedString::from_raw(id))?;
+    Ok(())
+}
+
+/// Add a string list attribute with a name of `name_len` bytes to the event; `values[i]` is
+/// made of `lengths[i]` bytes.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must point to `name_len` readable bytes
+/// - `values` and `lengths` must point to arrays of `count` elements
+/// - `values[i]` must point to `lengths[i]` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_string_list_n(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+    values: *const *const c_char,
+    lengths: *const usize,
+    count: usize,
+) -> AtreeResult {
+    with_string_list(builder, name, name_len, values, lengths, count).into()
+}
+
+unsafe fn with_string_list(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+    values: *const *const c_char,
+    lengths: *const usize,
+    count: usize,
+) -> Result<(), Failure> {
+    if (values.is_null() || lengths.is_null()) && count > 0 {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let (builder, name) = event_builder(builder, name, name_len)?;
+    let mut strings = Vec::with_capacity(count);
+    if count > 0 {
+        let values = slice::from_raw_parts(values, count);
+        let lengths = slice::from_raw_parts(lengths, count);
+        for (&value, &len) in values.iter().zip(lengths) {
+            if value.is_null() && len > 0 {
+                return Err(Failure::new(
+                    ATREE_ERROR_INVALID_ARGUMENT,
+                    "Null pointer in string list",
+                ));
+            }
+            strings.push(to_str(value, len, "Invalid UTF-8 in string list")?);
+        }
+    }
+
+    builder.with_string_list(name, &strings)?;
+    Ok(())
+}
+
+/// Add an integer list attribute with a name of `name_len` bytes to the event.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must point to `name_len` readable bytes
+/// - `values` must point to an array of `count` i64 values
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_integer_list_n(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+    values: *const i64,
+    count: usize,
+) -> AtreeResult {
+    with_integer_list(builder, name, name_len, values, count).into()
+}
+
+unsafe fn with_integer_list(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+    values: *const i64,
+    count: usize,
+) -> Result<(), Failure> {
+    if values.is_null() && count > 0 {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let (builder, name) = event_builder(builder, name, name_len)?;
+    let values: &[i64] = if count > 0 {
+        slice::from_raw_parts(values, count)
+    } else {
+        &[]
+    };
+    builder.with_integer_list(name, values)?;
+    Ok(())
+}
+
+/// Add an undefined attribute with a name of `name_len` bytes to the event.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must point to `name_len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_undefined_n(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+) -> AtreeResult {
+    with_undefined(builder, name, name_len).into()
+}
+
+unsafe fn with_undefined(
+    builder: *mut c_void,
+    name: *const c_char,
+    name_len: usize,
+) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name, name_len)?;
+    builder.with_undefined(name)?;
+    Ok(())
+}
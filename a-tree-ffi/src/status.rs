@@ -0,0 +1,551 @@
+//! Variants of the C API that return an `int32_t` status code and write their results to
+//! out-parameters.
+//!
+//! They are easier to bind from languages that cannot receive structs by value (C#, Go, LuaJIT
+//! FFI, ...). Every function returns `ATREE_OK` on success and one of the `ATREE_ERROR_*` codes
+//! otherwise; the out-parameters are left untouched on failure.
+
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_void};
+
+use crate::debug::{self, is_invalid};
+use crate::{concurrent, export, file, filtered, payload, tags, to_str};
+use crate::{
+    ATreeHandle, AtreeAttributeDef, AtreeConcurrentHandle, AtreeEventHandle, AtreeExportFormat,
+    AtreeWriteCallback, Failure,
+};
+
+/// The call succeeded
+pub const ATREE_OK: i32 = 0;
+/// A required pointer is null or an argument is out of range
+pub const ATREE_ERROR_INVALID_ARGUMENT: i32 = 1;
+/// A string is not valid UTF-8
+pub const ATREE_ERROR_INVALID_UTF8: i32 = 2;
+/// The expression cannot be parsed
+pub const ATREE_ERROR_PARSE: i32 = 3;
+/// An attribute is unknown, defined twice or used with the wrong type
+pub const ATREE_ERROR_EVENT: i32 = 4;
+/// A buffer returned to the caller cannot be allocated
+pub const ATREE_ERROR_OUT_OF_MEMORY: i32 = 5;
+/// The subscription ID is already present and the tree rejects the duplicates
+pub const ATREE_ERROR_DUPLICATE_ID: i32 = 6;
+/// A file cannot be read or written
+pub const ATREE_ERROR_IO: i32 = 7;
+/// The library failed unexpectedly, for instance because a worker thread panicked
+pub const ATREE_ERROR_INTERNAL: i32 = 8;
+
+/// Describe a status code.
+///
+/// # Returns
+/// Static null-terminated string that must not be freed
+#[no_mangle]
+pub extern "C" fn atree_status_message(status: i32) -> *const c_char {
+    let message: &'static CStr = match status {
+        ATREE_OK => c"Success",
+        ATREE_ERROR_INVALID_ARGUMENT => c"Invalid arguments",
+        ATREE_ERROR_INVALID_UTF8 => c"Invalid UTF-8",
+        ATREE_ERROR_PARSE => c"Invalid expression",
+        ATREE_ERROR_EVENT => c"Invalid attribute",
+        ATREE_ERROR_OUT_OF_MEMORY => c"Out of memory",
+        ATREE_ERROR_DUPLICATE_ID => c"Duplicate subscription ID",
+        ATREE_ERROR_IO => c"I/O error",
+        ATREE_ERROR_INTERNAL => c"Internal error",
+        _ => c"Unknown status",
+    };
+    message.as_ptr()
+}
+
+fn status(result: Result<(), Failure>) -> i32 {
+    match result {
+        Ok(()) => ATREE_OK,
+        Err(failure) => failure.status,
+    }
+}
+
+/// Create a new A-Tree with the given attribute definitions.
+///
+/// # Safety
+/// - `defs` must point to valid memory containing `count` AtreeAttributeDef structs
+/// - Each `name` field must be a valid null-terminated C string
+/// - `out_handle` must point to writable memory for a pointer
+/// - Caller must free the handle written to `out_handle` with `atree_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_new2(
+    defs: *const AtreeAttributeDef,
+    count: usize,
+    out_handle: *mut *mut ATreeHandle,
+) -> i32 {
+    if out_handle.is_null() {
+        return ATREE_ERROR_INVALID_ARGUMENT;
+    }
+
+    status(crate::new(defs, count).map(|handle| {
+        *out_handle = debug::into_raw(handle);
+    }))
+}
+
+/// Insert a boolean expression associated with a subscription ID.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+/// - `expression` must be a valid null-terminated C string
+#[no_mangle]
+pub unsafe extern "C" fn atree_insert2(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: *const c_char,
+) -> i32 {
+    status(crate::insert(handle, subscription_id, expression))
+}
+
+/// Intern a string; see `atree_intern_string()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+/// - `value` must be a valid null-terminated C string
+/// - `out_id` must point to writable memory for a u64
+#[no_mangle]
+pub unsafe extern "C" fn atree_intern_string2(
+    handle: *mut ATreeHandle,
+    value: *const c_char,
+    out_id: *mut u64,
+) -> i32 {
+    status(crate::intern_string(handle, value, out_id))
+}
+
+/// Start building an event for searching.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+/// - `out_builder` must point to writable memory for a pointer
+/// - The builder written to `out_builder` must be freed with `atree_event_builder_free()` or
+///   consumed by `atree_search2()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_new2(
+    handle: *const ATreeHandle,
+    out_builder: *mut *mut c_void,
+) -> i32 {
+    if is_invalid(handle) || out_builder.is_null() {
+        return ATREE_ERROR_INVALID_ARGUMENT;
+    }
+
+    *out_builder = crate::atree_event_builder_new(handle);
+    ATREE_OK
+}
+
+/// Add a boolean attribute to the event.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+/// - `name` must be a valid null-terminated C string
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_boolean2(
+    builder: *mut c_void,
+    name: *const c_char,
+    value: bool,
+) -> i32 {
+    status(crate::with_boolean(builder, name, value))
+}
+
+/// Add an integer attribute to the event.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+/// - `name` must be a valid null-terminated C string
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_integer2(
+    builder: *mut c_void,
+    name: *const c_char,
+    value: i64,
+) -> i32 {
+    status(crate::with_integer(builder, name, value))
+}
+
+/// Add a float attribute to the event from a double; see `atree_event_builder_with_double()`.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+/// - `name` must be a valid null-terminated C string
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_double2(
+    builder: *mut c_void,
+    name: *const c_char,
+    value: f64,
+) -> i32 {
+    status(crate::with_double(builder, name, value))
+}
+
+/// Add a float attribute to the event from a mantissa and a scale.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+/// - `name` must be a valid null-terminated C string
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_float2(
+    builder: *mut c_void,
+    name: *const c_char,
+    number: i64,
+    scale: u32,
+) -> i32 {
+    status(crate::with_float(builder, name, number, scale))
+}
+
+/// Add a string attribute to the event.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+/// - `name` and `value` must be valid null-terminated C strings
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_string2(
+    builder: *mut c_void,
+    name: *const c_char,
+    value: *const c_char,
+) -> i32 {
+    status(crate::with_string(builder, name, value))
+}
+
+/// Add a string attribute to the event from an ID returned by `atree_intern_string2()`.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+/// - `name` must be a valid null-terminated C string
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_interned_string2(
+    builder: *mut c_void,
+    name: *const c_char,
+    id: u64,
+) -> i32 {
+    status(crate::with_interned_string(builder, name, id))
+}
+
+/// Add a string list attribute to the event.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+/// - `name` must be a valid null-terminated C string
+/// - `values` must point to an array of `count` valid null-terminated C strings
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_string_list2(
+    builder: *mut c_void,
+    name: *const c_char,
+    values: *const *const c_char,
+    count: usize,
+) -> i32 {
+    status(crate::with_string_list(builder, name, values, count))
+}
+
+/// Add an integer list attribute to the event.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+/// - `name` must be a valid null-terminated C string
+/// - `values` must point to an array of `count` i64 values
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_integer_list2(
+    builder: *mut c_void,
+    name: *const c_char,
+    values: *const i64,
+    count: usize,
+) -> i32 {
+    status(crate::with_integer_list(builder, name, values, count))
+}
+
+/// Add an undefined attribute to the event.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+/// - `name` must be a valid null-terminated C string
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_undefined2(
+    builder: *mut c_void,
+    name: *const c_char,
+) -> i32 {
+    status(crate::with_undefined(builder, name))
+}
+
+/// Write the matches to the out-parameters.
+unsafe fn write_ids(
+    result: Result<Vec<u64>, Failure>,
+    out_ids: *mut *mut u64,
+    out_count: *mut usize,
+) -> i32 {
+    status(
+        result
+            .and_then(crate::AtreeSearchResult::new)
+            .map(|matches| {
+                *out_ids = matches.ids;
+                *out_count = matches.count;
+            }),
+    )
+}
+
+/// Search the A-Tree for matching expressions.
+///
+/// The IDs of the matching subscriptions are written to `out_ids` and their number to
+/// `out_count`; `out_ids` is null when nothing matches.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+/// - `builder` will be consumed by this call and must not be used after, even on failure
+/// - `out_ids` and `out_count` must point to writable memory
+/// - Caller must free the IDs with `atree_ids_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_search2(
+    handle: *const ATreeHandle,
+    builder: *mut c_void,
+    out_ids: *mut *mut u64,
+    out_count: *mut usize,
+) -> i32 {
+    if out_ids.is_null() || out_count.is_null() {
+        crate::atree_event_builder_free(builder);
+        return ATREE_ERROR_INVALID_ARGUMENT;
+    }
+
+    write_ids(crate::search(handle, builder), out_ids, out_count)
+}
+
+/// Search the A-Tree for matching expressions without consuming the builder.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+/// - `out_ids` and `out_count` must point to writable memory
+/// - Caller must free the IDs with `atree_ids_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_search_borrowed2(
+    handle: *const ATreeHandle,
+    builder: *const c_void,
+    out_ids: *mut *mut u64,
+    out_count: *mut usize,
+) -> i32 {
+    if out_ids.is_null() || out_count.is_null() {
+        return ATREE_ERROR_INVALID_ARGUMENT;
+    }
+
+    write_ids(crate::search_borrowed(handle, builder), out_ids, out_count)
+}
+
+/// Count the expressions matching an event without copying their IDs.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+/// - `out_count` must point to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn atree_match_count2(
+    handle: *const ATreeHandle,
+    builder: *const c_void,
+    out_count: *mut u64,
+) -> i32 {
+    if out_count.is_null() {
+        return ATREE_ERROR_INVALID_ARGUMENT;
+    }
+
+    status(crate::match_count(handle, builder).map(|count| {
+        *out_count = count;
+    }))
+}
+
+/// Search the A-Tree for the expressions matching a prepared event; see `atree_search_event()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+/// - `event` must be a valid pointer returned by `atree_event_from_json()` for the same tree
+/// - `out_ids` and `out_count` must point to writable memory
+/// - Caller must free the IDs with `atree_ids_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_search_event2(
+    handle: *const ATreeHandle,
+    event: *const AtreeEventHandle,
+    out_ids: *mut *mut u64,
+    out_count: *mut usize,
+) -> i32 {
+    if is_invalid(handle) || is_invalid(event) || out_ids.is_null() || out_count.is_null() {
+        return ATREE_ERROR_INVALID_ARGUMENT;
+    }
+
+    write_ids(
+        crate::search_event(&*handle, &(*event).event),
+        out_ids,
+        out_count,
+    )
+}
+
+/// Search the A-Tree for the matching expressions among candidate subscriptions; see
+/// `atree_search_among()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+/// - `builder` will be consumed by this call and must not be used after, even on failure
+/// - `candidates` must point to `count` subscription IDs; it can be null if `count` is 0
+/// - `out_ids` and `out_count` must point to writable memory
+/// - Caller must free the IDs with `atree_ids_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_search_among2(
+    handle: *const ATreeHandle,
+    builder: *mut c_void,
+    candidates: *const u64,
+    count: usize,
+    out_ids: *mut *mut u64,
+    out_count: *mut usize,
+) -> i32 {
+    if out_ids.is_null() || out_count.is_null() {
+        crate::atree_event_builder_free(builder);
+        return ATREE_ERROR_INVALID_ARGUMENT;
+    }
+
+    let result = filtered::search_among(handle, builder, candidates, count);
+    write_ids(result, out_ids, out_count)
+}
+
+/// Search the A-Tree for at most `max` matching expressions; see `atree_search_limited()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new2()`
+/// - `builder` will be consumed by this call and must not be used after, even on failure
+/// - `out_ids` and `out_count` must point to writable memory
+/// - Caller must free the IDs with `atree_ids_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_search_limited2(
+    handle: *const ATreeHandle,
+    builder: *mut c_void,
+    max: usize,
+    out_ids: *mut *mut u64,
+    out_count: *mut usize,
+) -> i32 {
+    if out_ids.is_null() || out_count.is_null() {
+        crate::atree_event_builder_free(builder);
+        return ATREE_ERROR_INVALID_ARGUMENT;
+    }
+
+    write_ids(
+        filtered::search_limited(handle, builder, max),
+        out_ids,
+        out_count,
+    )
+}
+
+/// Insert a boolean expression along with the tags of the subscription; see
+/// `atree_insert_tagged()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+/// - `expression` must be a valid null-terminated C string
+/// - `tags` must point to `count` valid null-terminated C strings; it can be null if `count` is 0
+#[no_mangle]
+pub unsafe extern "C" fn atree_insert_tagged2(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: *const c_char,
+    tags: *const *const c_char,
+    count: usize,
+) -> i32 {
+    status(tags::insert_tagged(
+        handle,
+        subscription_id,
+        expression,
+        tags,
+        count,
+    ))
+}
+
+/// Insert a boolean expression and attach a copy of `data` to the subscription; see
+/// `atree_insert_with_payload()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+/// - `expression` must be a valid null-terminated C string
+/// - `data` must point to `len` readable bytes; it can be null if `len` is 0
+#[no_mangle]
+pub unsafe extern "C" fn atree_insert_with_payload2(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: *const c_char,
+    data: *const c_void,
+    len: usize,
+) -> i32 {
+    status(payload::insert_with_payload(
+        handle,
+        subscription_id,
+        data,
+        len,
+        || crate::insert(handle, subscription_id, expression),
+    ))
+}
+
+/// Get the IDs of the subscriptions tagged with `tag`, in no particular order.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+/// - `tag` must be a valid null-terminated C string
+/// - `out_ids` and `out_count` must point to writable memory
+/// - Caller must free the IDs with `atree_ids_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_ids_with_tag2(
+    handle: *const ATreeHandle,
+    tag: *const c_char,
+    out_ids: *mut *mut u64,
+    out_count: *mut usize,
+) -> i32 {
+    if is_invalid(handle) || tag.is_null() || out_ids.is_null() || out_count.is_null() {
+        return ATREE_ERROR_INVALID_ARGUMENT;
+    }
+
+    let handle_ref = &*handle;
+    let ids = to_str(tag, "Invalid UTF-8 in tag")
+        .map(|tag| handle_ref.tree.ids_with_tag(tag).copied().collect());
+    write_ids(ids, out_ids, out_count)
+}
+
+/// Stream an export of the tree to a callback; see `atree_export()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+/// - `ctx` is passed to `write` as is
+#[no_mangle]
+pub unsafe extern "C" fn atree_export2(
+    handle: *const ATreeHandle,
+    format: AtreeExportFormat,
+    write: AtreeWriteCallback,
+    ctx: *mut c_void,
+) -> i32 {
+    status(export::export(handle, format, write, ctx))
+}
+
+/// Save a binary snapshot of the tree to a file, replacing it atomically; see
+/// `atree_save_file()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()` or `atree_new2()`
+/// - `path` must be a valid null-terminated C string
+#[no_mangle]
+pub unsafe extern "C" fn atree_save_file2(handle: *const ATreeHandle, path: *const c_char) -> i32 {
+    status(file::save_file(handle, path))
+}
+
+/// Publish a new tree to the readers of a concurrent handle; see `atree_swap()`.
+///
+/// # Safety
+/// - `concurrent` must be a valid pointer returned by `atree_concurrent_new()`
+/// - `new_tree` must be a valid pointer returned by `atree_new()` or `atree_new2()`; it must not
+///   be used after this call, unless it is null
+#[no_mangle]
+pub unsafe extern "C" fn atree_swap2(
+    concurrent: *const AtreeConcurrentHandle,
+    new_tree: *mut ATreeHandle,
+) -> i32 {
+    status(concurrent::swap(concurrent, new_tree))
+}
+
+/// Free the IDs returned by the `*2()` search functions.
+///
+/// # Safety
+/// - `ids` and `count` must have been written by one of the `*2()` search functions
+/// - `ids` must not be used after this call
+#[no_mangle]
+pub unsafe extern "C" fn atree_ids_free(ids: *mut u64, count: usize) {
+    crate::alloc::free_ids(ids, count);
+}
@@ -0,0 +1,128 @@
+//! Tags attached to the subscriptions, to insert, list and delete a group of subscriptions (a
+//! campaign, a tenant, ...) at once.
+
+use std::os::raw::c_char;
+use std::slice;
+
+use a_tree::Metadata;
+
+use crate::debug::is_invalid;
+use crate::{log_insert_failure, to_str, ATreeHandle, AtreeResult, AtreeSearchResult, Failure};
+
+/// Insert a boolean expression tagged with each of the specified tags.
+///
+/// # Arguments
+/// * `tags` - Array of `count` null-terminated tags; can be null if `count` is 0
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `expression` and each tag must be valid null-terminated C strings
+/// - `tags` must point to `count` pointers
+/// - Caller must free `error_message` with `atree_free_error()` on failure
+#[no_mangle]
+pub unsafe extern "C" fn atree_insert_tagged(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: *const c_char,
+    tags: *const *const c_char,
+    count: usize,
+) -> AtreeResult {
+    insert_tagged(handle, subscription_id, expression, tags, count).into()
+}
+
+pub(crate) unsafe fn insert_tagged(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: *const c_char,
+    tags: *const *const c_char,
+    count: usize,
+) -> Result<(), Failure> {
+    if is_invalid(handle) || expression.is_null() || (tags.is_null() && count > 0) {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let mut metadata = Metadata::new();
+    if count > 0 {
+        for &tag in slice::from_raw_parts(tags, count) {
+            if tag.is_null() {
+                return Err(Failure::invalid_arguments());
+            }
+            metadata = metadata.with_tag(to_str(tag, "Invalid UTF-8 in tag")?);
+        }
+    }
+    let expr_str = to_str(expression, "Invalid UTF-8 in expression")?;
+    insert_with_metadata(&mut *handle, subscription_id, expr_str, metadata)
+}
+
+pub(crate) fn insert_with_metadata(
+    handle: &mut ATreeHandle,
+    subscription_id: u64,
+    expression: &str,
+    metadata: Metadata,
+) -> Result<(), Failure> {
+    handle
+        .tree
+        .insert_with_metadata(&subscription_id, expression, metadata)
+        .inspect_err(|error| log_insert_failure(subscription_id, error))?;
+    Ok(())
+}
+
+/// Delete every subscription tagged with `tag`, along with their payloads.
+///
+/// # Returns
+/// Number of deleted subscriptions
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `tag` must be a valid null-terminated C string
+#[no_mangle]
+pub unsafe extern "C" fn atree_delete_by_tag(
+    handle: *mut ATreeHandle,
+    tag: *const c_char,
+) -> usize {
+    if is_invalid(handle) || tag.is_null() {
+        return 0;
+    }
+    let Ok(tag) = to_str(tag, "Invalid UTF-8 in tag") else {
+        return 0;
+    };
+
+    delete_by_tag(&mut *handle, tag)
+}
+
+pub(crate) fn delete_by_tag(handle: &mut ATreeHandle, tag: &str) -> usize {
+    let deleted = handle.tree.delete_by_tag(tag);
+    for id in &deleted {
+        handle.payloads.remove(id);
+    }
+    deleted.len()
+}
+
+/// Get the IDs of the subscriptions tagged with `tag`, in no particular order.
+///
+/// # Returns
+/// Search result containing the IDs; empty if an argument is invalid
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `tag` must be a valid null-terminated C string
+/// - Caller must free the returned result with `atree_search_result_free()`
+#[no_mangle]
+pub unsafe extern "C" fn atree_ids_with_tag(
+    handle: *const ATreeHandle,
+    tag: *const c_char,
+) -> AtreeSearchResult {
+    if is_invalid(handle) || tag.is_null() {
+        return AtreeSearchResult::empty();
+    }
+    let Ok(tag) = to_str(tag, "Invalid UTF-8 in tag") else {
+        return AtreeSearchResult::empty();
+    };
+
+    ids_with_tag(&*handle, tag)
+}
+
+pub(crate) fn ids_with_tag(handle: &ATreeHandle, tag: &str) -> AtreeSearchResult {
+    let ids = handle.tree.ids_with_tag(tag).copied().collect();
+    AtreeSearchResult::new(ids).unwrap_or(AtreeSearchResult::empty())
+}
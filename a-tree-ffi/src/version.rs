@@ -0,0 +1,66 @@
+//! Version of the library, to check at runtime that the loaded shared object matches the header.
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+/// Version of the ABI described by this header
+///
+/// It is incremented whenever a function or a type changes in an incompatible way; compare it
+/// with `atree_abi_version()` after loading the library.
+pub const ATREE_ABI_VERSION: u32 = 1;
+
+const VERSION: &CStr =
+    match CStr::from_bytes_with_nul(concat!(env!("CARGO_PKG_VERSION"), "\0").as_bytes()) {
+        Ok(version) => version,
+        Err(_) => panic!("the package version contains a NUL byte"),
+    };
+
+const fn parse_component(component: &str) -> u32 {
+    let bytes = component.as_bytes();
+    let mut value = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        value = value * 10 + (bytes[i] - b'0') as u32;
+        i += 1;
+    }
+    value
+}
+
+/// Get the version of the library, such as `"0.1.0"`.
+///
+/// # Returns
+/// Static null-terminated string that must not be freed
+#[no_mangle]
+pub extern "C" fn atree_version_string() -> *const c_char {
+    VERSION.as_ptr()
+}
+
+/// Get the major version of the library.
+#[no_mangle]
+pub extern "C" fn atree_version_major() -> u32 {
+    const MAJOR: u32 = parse_component(env!("CARGO_PKG_VERSION_MAJOR"));
+    MAJOR
+}
+
+/// Get the minor version of the library.
+#[no_mangle]
+pub extern "C" fn atree_version_minor() -> u32 {
+    const MINOR: u32 = parse_component(env!("CARGO_PKG_VERSION_MINOR"));
+    MINOR
+}
+
+/// Get the patch version of the library.
+#[no_mangle]
+pub extern "C" fn atree_version_patch() -> u32 {
+    const PATCH: u32 = parse_component(env!("CARGO_PKG_VERSION_PATCH"));
+    PATCH
+}
+
+/// Get the version of the ABI implemented by the library.
+///
+/// A host is compatible with the library when it matches the `ATREE_ABI_VERSION` of the header
+/// it was compiled against.
+#[no_mangle]
+pub extern "C" fn atree_abi_version() -> u32 {
+    ATREE_ABI_VERSION
+}
@@ -0,0 +1,361 @@
+//! Entry points taking null-terminated UTF-16 strings, for the hosts that store their strings as
+//! UTF-16 (e.g. `wchar_t` on Windows). The strings are converted once, to UTF-8, by the library.
+
+use std::os::raw::c_void;
+use std::slice;
+
+use a_tree::{EventBuilder, InternedString};
+
+use crate::debug::{is_invalid, is_invalid_builder};
+use crate::{
+    log_insert_failure, ATreeHandle, AtreeResult, Failure, ATREE_ERROR_INVALID_ARGUMENT,
+    ATREE_ERROR_INVALID_UTF8,
+};
+
+/// Convert a null-terminated UTF-16 string.
+unsafe fn to_string(value: *const u16, message: &'static str) -> Result<String, Failure> {
+    if value.is_null() {
+        return Err(Failure::invalid_arguments());
+    }
+    let mut len = 0;
+    while *value.add(len) != 0 {
+        len += 1;
+    }
+    String::from_utf16(slice::from_raw_parts(value, len))
+        .map_err(|_| Failure::new(ATREE_ERROR_INVALID_UTF8, message))
+}
+
+/// Borrow the event builder and convert the name of the attribute to set.
+unsafe fn event_builder<'a>(
+    builder: *mut c_void,
+    name: *const u16,
+) -> Result<(&'a mut EventBuilder<'a>, String), Failure> {
+    if is_invalid_builder(builder) {
+        return Err(Failure::invalid_arguments());
+    }
+    let name = to_string(name, "Invalid UTF-16 in name")?;
+    Ok((&mut *(builder as *mut EventBuilder), name))
+}
+
+/// Insert a boolean expression given as a UTF-16 string; see `atree_insert()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `expression` must be a valid null-terminated UTF-16 string
+/// - Caller must free result.error_message with `atree_free_error()` if !success
+#[no_mangle]
+pub unsafe extern "C" fn atree_insert_w(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: *const u16,
+) -> AtreeResult {
+    insert(handle, subscription_id, expression).into()
+}
+
+unsafe fn insert(
+    handle: *mut ATreeHandle,
+    subscription_id: u64,
+    expression: *const u16,
+) -> Result<(), Failure> {
+    if is_invalid(handle) {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let expression = to_string(expression, "Invalid UTF-16 in expression")?;
+    let handle_ref = &mut *handle;
+    handle_ref
+        .tree
+        .insert(&subscription_id, &expression)
+        .inspect_err(|error| log_insert_failure(subscription_id, error))?;
+    Ok(())
+}
+
+/// Intern a UTF-16 string; see `atree_intern_string()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `value` must be a valid null-terminated UTF-16 string
+/// - `out_id` must point to writable memory for a u64
+#[no_mangle]
+pub unsafe extern "C" fn atree_intern_string_w(
+    handle: *mut ATreeHandle,
+    value: *const u16,
+    out_id: *mut u64,
+) -> AtreeResult {
+    intern_string(handle, value, out_id).into()
+}
+
+unsafe fn intern_string(
+    handle: *mut ATreeHandle,
+    value: *const u16,
+    out_id: *mut u64,
+) -> Result<(), Failure> {
+    if is_invalid(handle) || out_id.is_null() {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let value = to_string(value, "Invalid UTF-16 in value")?;
+    let handle_ref = &mut *handle;
+    *out_id = handle_ref.tree.intern(&value).to_raw();
+    Ok(())
+}
+
+/// Look up the interned ID of a UTF-16 string without interning it; see
+/// `atree_lookup_string()`.
+///
+/// # Safety
+/// - `handle` must be a valid pointer returned by `atree_new()`
+/// - `value` must be a valid null-terminated UTF-16 string
+/// - `out_id` must point to writable memory for a u64
+#[no_mangle]
+pub unsafe extern "C" fn atree_lookup_string_w(
+    handle: *const ATreeHandle,
+    value: *const u16,
+    out_id: *mut u64,
+) -> bool {
+    if is_invalid(handle) || out_id.is_null() {
+        return false;
+    }
+    let Ok(value) = to_string(value, "Invalid UTF-16 in value") else {
+        return false;
+    };
+
+    let handle_ref = &*handle;
+    match handle_ref.tree.interned(&value) {
+        Some(interned) => {
+            *out_id = interned.to_raw();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Add a boolean attribute named with a UTF-16 string to the event.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must be a valid null-terminated UTF-16 string
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_boolean_w(
+    builder: *mut c_void,
+    name: *const u16,
+    value: bool,
+) -> AtreeResult {
+    with_boolean(builder, name, value).into()
+}
+
+unsafe fn with_boolean(builder: *mut c_void, name: *const u16, value: bool) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name)?;
+    builder.with_boolean(&name, value)?;
+    Ok(())
+}
+
+/// Add an integer attribute named with a UTF-16 string to the event.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must be a valid null-terminated UTF-16 string
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_integer_w(
+    builder: *mut c_void,
+    name: *const u16,
+    value: i64,
+) -> AtreeResult {
+    with_integer(builder, name, value).into()
+}
+
+unsafe fn with_integer(builder: *mut c_void, name: *const u16, value: i64) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name)?;
+    builder.with_integer(&name, value)?;
+    Ok(())
+}
+
+/// Add a float attribute named with a UTF-16 string to the event from a double; see
+/// `atree_event_builder_with_double()`.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must be a valid null-terminated UTF-16 string
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_double_w(
+    builder: *mut c_void,
+    name: *const u16,
+    value: f64,
+) -> AtreeResult {
+    with_double(builder, name, value).into()
+}
+
+unsafe fn with_double(builder: *mut c_void, name: *const u16, value: f64) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name)?;
+    builder.with_f64(&name, value)?;
+    Ok(())
+}
+
+/// Add a float attribute named with a UTF-16 string to the event; see
+/// `atree_event_builder_with_float()`.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must be a valid null-terminated UTF-16 string
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_float_w(
+    builder: *mut c_void,
+    name: *const u16,
+    number: i64,
+    scale: u32,
+) -> AtreeResult {
+    with_float(builder, name, number, scale).into()
+}
+
+unsafe fn with_float(
+    builder: *mut c_void,
+    name: *const u16,
+    number: i64,
+    scale: u32,
+) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name)?;
+    builder.with_float(&name, number, scale)?;
+    Ok(())
+}
+
+/// Add a string attribute to the event, with its name and its value as UTF-16 strings.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` and `value` must be valid null-terminated UTF-16 strings
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_string_w(
+    builder: *mut c_void,
+    name: *const u16,
+    value: *const u16,
+) -> AtreeResult {
+    with_string(builder, name, value).into()
+}
+
+unsafe fn with_string(
+    builder: *mut c_void,
+    name: *const u16,
+    value: *const u16,
+) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name)?;
+    let value = to_string(value, "Invalid UTF-16 in value")?;
+    builder.with_string(&name, &value)?;
+    Ok(())
+}
+
+/// Add a string attribute named with a UTF-16 string to the event from an ID returned by
+/// `atree_intern_string()`.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must be a valid null-terminated UTF-16 string
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_interned_string_w(
+    builder: *mut c_void,
+    name: *const u16,
+    id: u64,
+) -> AtreeResult {
+    with_interned_string(builder, name, id).into()
+}
+
+unsafe fn with_interned_string(
+    builder: *mut c_void,
+    name: *const u16,
+    id: u64,
+) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name)?;
+    builder.with_interned_string(&name, InternedString::from_raw(id))?;
+    Ok(())
+}
+
+/// Add a string list attribute to the event, with its name and its values as UTF-16 strings.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must be a valid null-terminated UTF-16 string
+/// - `values` must point to an array of `count` valid null-terminated UTF-16 strings
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_string_list_w(
+    builder: *mut c_void,
+    name: *const u16,
+    values: *const *const u16,
+    count: usize,
+) -> AtreeResult {
+    with_string_list(builder, name, values, count).into()
+}
+
+unsafe fn with_string_list(
+    builder: *mut c_void,
+    name: *const u16,
+    values: *const *const u16,
+    count: usize,
+) -> Result<(), Failure> {
+    if values.is_null() {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let (builder, name) = event_builder(builder, name)?;
+    let mut strings = Vec::with_capacity(count);
+    for &value in slice::from_raw_parts(values, count) {
+        if value.is_null() {
+            return Err(Failure::new(
+                ATREE_ERROR_INVALID_ARGUMENT,
+                "Null pointer in string list",
+            ));
+        }
+        strings.push(to_string(value, "Invalid UTF-16 in string list")?);
+    }
+    let strings: Vec<_> = strings.iter().map(String::as_str).collect();
+    builder.with_string_list(&name, &strings)?;
+    Ok(())
+}
+
+/// Add an integer list attribute named with a UTF-16 string to the event.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must be a valid null-terminated UTF-16 string
+/// - `values` must point to an array of `count` i64 values
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_integer_list_w(
+    builder: *mut c_void,
+    name: *const u16,
+    values: *const i64,
+    count: usize,
+) -> AtreeResult {
+    with_integer_list(builder, name, values, count).into()
+}
+
+unsafe fn with_integer_list(
+    builder: *mut c_void,
+    name: *const u16,
+    values: *const i64,
+    count: usize,
+) -> Result<(), Failure> {
+    if values.is_null() {
+        return Err(Failure::invalid_arguments());
+    }
+
+    let (builder, name) = event_builder(builder, name)?;
+    builder.with_integer_list(&name, slice::from_raw_parts(values, count))?;
+    Ok(())
+}
+
+/// Add an undefined attribute named with a UTF-16 string to the event.
+///
+/// # Safety
+/// - `builder` must be a valid pointer returned by `atree_event_builder_new()`
+/// - `name` must be a valid null-terminated UTF-16 string
+#[no_mangle]
+pub unsafe extern "C" fn atree_event_builder_with_undefined_w(
+    builder: *mut c_void,
+    name: *const u16,
+) -> AtreeResult {
+    with_undefined(builder, name).into()
+}
+
+unsafe fn with_undefined(builder: *mut c_void, name: *const u16) -> Result<(), Failure> {
+    let (builder, name) = event_builder(builder, name)?;
+    builder.with_undefined(&name)?;
+    Ok(())
+}
@@ -0,0 +1,23 @@
+// swift-tools-version:5.7
+//
+// Swift overlay of the a-tree C API. Build the library and the module map first:
+//
+//     cargo build --release --features swift
+//
+// and pass the directory of the library to the linker, e.g.
+// `swift build -Xlinker -L../target/release`.
+
+import PackageDescription
+
+let package = Package(
+    name: "ATree",
+    platforms: [.macOS(.v11), .iOS(.v14)],
+    products: [
+        .library(name: "ATree", targets: ["ATree"]),
+    ],
+    targets: [
+        // module.modulemap is generated by build.rs with the swift feature
+        .systemLibrary(name: "CATree", path: "Sources/CATree"),
+        .target(name: "ATree", dependencies: ["CATree"]),
+    ]
+)
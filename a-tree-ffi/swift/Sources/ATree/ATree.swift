@@ -0,0 +1,154 @@
+import CATree
+import Foundation
+
+/// Error reported by the library, e.g. an expression that cannot be parsed
+public struct ATreeError: Error, CustomStringConvertible {
+    public let message: String
+
+    public var description: String { message }
+}
+
+/// Throw the error message of a failed call, freeing it.
+private func check(_ result: AtreeResult) throws {
+    guard !result.success else { return }
+    defer { atree_free_error(result.error_message) }
+    throw ATreeError(message: result.error_message.map { String(cString: $0) } ?? "Unknown error")
+}
+
+/// Throw the description of a status code other than `ATREE_OK`.
+private func check(status: Int32) throws {
+    guard status != ATREE_OK else { return }
+    throw ATreeError(message: String(cString: atree_status_message(status)))
+}
+
+/// Type of an attribute of the events
+public enum AttributeKind: Int {
+    // Same order as AtreeAttributeType
+    case boolean, integer, float, string, stringList, integerList
+
+    var native: AtreeAttributeType { AtreeAttributeType(rawValue: numericCast(rawValue)) }
+}
+
+/// Value of an attribute of an event
+public enum Value: Equatable {
+    case boolean(Bool)
+    case integer(Int64)
+    case float(Double)
+    case string(String)
+    case integerList([Int64])
+    case stringList([String])
+    case undefined
+}
+
+extension Value: ExpressibleByBooleanLiteral, ExpressibleByIntegerLiteral,
+    ExpressibleByFloatLiteral, ExpressibleByStringLiteral
+{
+    public init(booleanLiteral value: Bool) { self = .boolean(value) }
+    public init(integerLiteral value: Int64) { self = .integer(value) }
+    public init(floatLiteral value: Double) { self = .float(value) }
+    public init(stringLiteral value: String) { self = .string(value) }
+}
+
+/// Event searched in a tree; the attributes it does not set are undefined
+public struct Event: Equatable {
+    public var values: [String: Value]
+
+    public init(_ values: [String: Value] = [:]) {
+        self.values = values
+    }
+
+    public subscript(name: String) -> Value? {
+        get { values[name] }
+        set { values[name] = newValue }
+    }
+}
+
+/// A-Tree indexing the boolean expressions of the subscriptions
+///
+/// The searches can run on many threads at once, but not along with an insertion or a deletion.
+public final class Tree {
+    private let handle: OpaquePointer
+
+    /// Create an empty tree for events with the given attributes.
+    public init(attributes: [(name: String, kind: AttributeKind)]) throws {
+        let names = attributes.map { strdup($0.name) }
+        defer { names.forEach { free($0) } }
+        let definitions = zip(names, attributes).map { name, attribute in
+            AtreeAttributeDef(name: UnsafePointer(name), attr_type: attribute.kind.native)
+        }
+
+        var handle: OpaquePointer?
+        try check(status: atree_new2(definitions, UInt(definitions.count), &handle))
+        self.handle = handle!
+    }
+
+    /// Create a tree from a JSON document exported by `exportJSON()`.
+    public init(json: String) throws {
+        guard let handle = atree_import_json(json) else {
+            throw ATreeError(message: "Invalid JSON document")
+        }
+        self.handle = handle
+    }
+
+    deinit {
+        atree_free(handle)
+    }
+
+    /// Insert the expression of a subscription, e.g. `price > 100 and country in ["CA"]`.
+    public func insert(id: UInt64, expression: String) throws {
+        try check(atree_insert(handle, id, expression))
+    }
+
+    /// Delete a subscription; nothing happens if it is not in the tree.
+    public func delete(id: UInt64) {
+        atree_delete(handle, id)
+    }
+
+    /// Search the IDs of the subscriptions matching an event.
+    public func search(_ event: Event) throws -> [UInt64] {
+        var builder: UnsafeMutableRawPointer?
+        try check(status: atree_event_builder_new2(handle, &builder))
+        defer { atree_event_builder_free(builder) }
+        for (name, value) in event.values {
+            try add(value, named: name, to: builder)
+        }
+
+        var ids: UnsafeMutablePointer<UInt64>?
+        var count: UInt = 0
+        try check(status: atree_search_borrowed2(handle, builder, &ids, &count))
+        defer { atree_ids_free(ids, count) }
+        return Array(UnsafeBufferPointer(start: ids, count: Int(count)))
+    }
+
+    /// Export the schema and the subscriptions of the tree as a JSON document.
+    public func exportJSON() throws -> String {
+        guard let json = atree_export_json(handle) else {
+            throw ATreeError(message: "The tree cannot be exported")
+        }
+        defer { atree_free_string(json) }
+        return String(cString: json)
+    }
+
+    private func add(_ value: Value, named name: String, to builder: UnsafeMutableRawPointer?)
+        throws
+    {
+        switch value {
+        case .boolean(let value):
+            try check(atree_event_builder_with_boolean(builder, name, value))
+        case .integer(let value):
+            try check(atree_event_builder_with_integer(builder, name, value))
+        case .float(let value):
+            try check(atree_event_builder_with_double(builder, name, value))
+        case .string(let value):
+            try check(atree_event_builder_with_string(builder, name, value))
+        case .integerList(let values):
+            try check(atree_event_builder_with_integer_list(builder, name, values, UInt(values.count)))
+        case .stringList(let values):
+            let copies = values.map { UnsafePointer(strdup($0)) }
+            defer { copies.forEach { free(UnsafeMutablePointer(mutating: $0)) } }
+            try check(atree_event_builder_with_string_list(builder, name, copies, UInt(copies.count)))
+        case .undefined:
+            try check(atree_event_builder_with_undefined(builder, name))
+        }
+    }
+}
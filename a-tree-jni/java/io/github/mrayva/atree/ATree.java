@@ -0,0 +1,117 @@
+package io.github.mrayva.atree;
+
+import java.nio.ByteBuffer;
+import java.util.Map;
+
+/**
+ * A-Tree indexing the boolean expressions of the subscriptions.
+ *
+ * <p>The searches can run on many threads at once; the insertions and the deletions wait for the
+ * searches in progress. The tree must be closed to free its native memory.
+ */
+public final class ATree implements AutoCloseable {
+    static {
+        System.loadLibrary("a_tree_jni");
+    }
+
+    /** Kind of an attribute; the order matches the native code. */
+    public enum Kind {
+        BOOLEAN,
+        INTEGER,
+        FLOAT,
+        STRING,
+        INTEGER_LIST,
+        STRING_LIST,
+    }
+
+    private long handle;
+
+    /**
+     * Create an empty tree for events with the given attributes.
+     *
+     * @param attributes Kind of each attribute, by name
+     * @throws ATreeException if an attribute is defined twice
+     */
+    public ATree(Map<String, Kind> attributes) {
+        String[] names = new String[attributes.size()];
+        int[] kinds = new int[attributes.size()];
+        int index = 0;
+        for (Map.Entry<String, Kind> attribute : attributes.entrySet()) {
+            names[index] = attribute.getKey();
+            kinds[index] = attribute.getValue().ordinal();
+            index++;
+        }
+        handle = nativeNew(names, kinds);
+    }
+
+    /**
+     * Insert the expression of a subscription, e.g. {@code exchange_id = 1 and private}.
+     *
+     * @throws ATreeParseException if the expression does not parse or does not fit the attributes
+     */
+    public void insert(long id, String expression) {
+        nativeInsert(handle(), id, expression);
+    }
+
+    /** Delete a subscription; nothing happens if it is not in the tree. */
+    public void delete(long id) {
+        nativeDelete(handle(), id);
+    }
+
+    /**
+     * Search the subscriptions matching an event.
+     *
+     * @param event JSON object mapping the attribute names to their values; the missing
+     *     attributes are undefined
+     * @return IDs of the matching subscriptions
+     * @throws ATreeException if the event has an unknown attribute or a value of the wrong type
+     */
+    public long[] search(String event) {
+        return nativeSearch(handle(), event);
+    }
+
+    /**
+     * Search the subscriptions matching many events, read without copy from a direct buffer.
+     *
+     * @param events Direct buffer holding one UTF-8 JSON object per line between its position and
+     *     its limit; the blank lines are skipped
+     * @return IDs of the matching subscriptions of each event, in the same order
+     * @throws IllegalArgumentException if the buffer is not direct
+     * @throws ATreeException if an event has an unknown attribute or a value of the wrong type
+     */
+    public long[][] searchBatch(ByteBuffer events) {
+        if (!events.isDirect()) {
+            throw new IllegalArgumentException("the events must be in a direct buffer");
+        }
+        return nativeSearchBatch(handle(), events, events.position(), events.remaining());
+    }
+
+    /** Free the native memory of the tree; it must not be used by another thread. */
+    @Override
+    public void close() {
+        if (handle != 0) {
+            nativeFree(handle);
+            handle = 0;
+        }
+    }
+
+    private long handle() {
+        if (handle == 0) {
+            throw new IllegalStateException("the tree is closed");
+        }
+        return handle;
+    }
+
+    private static native long nativeNew(String[] names, int[] kinds);
+
+    private static native void nativeFree(long handle);
+
+    private static native void nativeInsert(long handle, long id, String expression);
+
+    private static native void nativeDelete(long handle, long id);
+
+    private static native long[] nativeSearch(long handle, String event);
+
+    private static native long[][] nativeSearchBatch(
+            long handle, ByteBuffer events, int offset, int length);
+}
@@ -0,0 +1,8 @@
+package io.github.mrayva.atree;
+
+/** Failure of an A-Tree operation, such as an event with an unknown attribute. */
+public class ATreeException extends RuntimeException {
+    public ATreeException(String message) {
+        super(message);
+    }
+}
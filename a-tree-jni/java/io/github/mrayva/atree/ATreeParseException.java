@@ -0,0 +1,23 @@
+package io.github.mrayva.atree;
+
+/** Expression that cannot be inserted because it does not parse or does not fit the attributes. */
+public class ATreeParseException extends ATreeException {
+    private final int start;
+    private final int end;
+
+    public ATreeParseException(String message, int start, int end) {
+        super(message);
+        this.start = start;
+        this.end = end;
+    }
+
+    /** Index of the first character of the offending part of the expression, or -1 if unknown. */
+    public int getStart() {
+        return start;
+    }
+
+    /** Index after the last character of the offending part of the expression, or -1 if unknown. */
+    public int getEnd() {
+        return end;
+    }
+}
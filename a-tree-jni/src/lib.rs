@@ -0,0 +1,272 @@
+//! JNI bindings for the [a-tree](https://crates.io/crates/a-tree) library, used by the
+//! `io.github.mrayva.atree.ATree` Java class.
+//!
+//! The Java object holds a pointer to the tree as a `long`. The tree sits behind a lock so that
+//! many threads can search it at once, while the insertions and the deletions are exclusive.
+
+use std::ptr;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use a_tree::{ATree, ATreeError, AttributeDefinition};
+use jni::objects::{
+    JByteBuffer, JClass, JIntArray, JObject, JObjectArray, JString, JThrowable, JValue,
+};
+use jni::sys::{jint, jlong, jlongArray, jobjectArray};
+use jni::JNIEnv;
+
+const EXCEPTION: &str = "io/github/mrayva/atree/ATreeException";
+const PARSE_EXCEPTION: &str = "io/github/mrayva/atree/ATreeParseException";
+
+type Tree = RwLock<ATree<i64>>;
+
+/// Reason of a failure, thrown as a Java exception before returning to the JVM
+enum Failure {
+    /// A JNI call failed; a Java exception may already be pending
+    Jni(jni::errors::Error),
+    /// An expression cannot be parsed; the span is in UTF-16 code units, like a Java string
+    Parse {
+        message: String,
+        start: jint,
+        end: jint,
+    },
+    Other(String),
+}
+
+impl From<jni::errors::Error> for Failure {
+    fn from(error: jni::errors::Error) -> Self {
+        Self::Jni(error)
+    }
+}
+
+impl Failure {
+    fn other(error: impl ToString) -> Self {
+        Self::Other(error.to_string())
+    }
+
+    fn insertion(error: ATreeError<'_>, expression: &str) -> Self {
+        if !matches!(error, ATreeError::ParseError(_)) {
+            return Self::other(error);
+        }
+
+        let position = |offset: usize| expression[..offset].encode_utf16().count() as jint;
+        let (start, end) = error
+            .span(expression)
+            .map_or((-1, -1), |span| (position(span.start), position(span.end)));
+        Self::Parse {
+            message: error.to_string(),
+            start,
+            end,
+        }
+    }
+
+    fn throw(self, env: &mut JNIEnv) {
+        let result = match self {
+            Self::Jni(jni::errors::Error::JavaException) => Ok(()),
+            Self::Jni(error) => env.throw_new(EXCEPTION, error.to_string()),
+            Self::Other(message) => env.throw_new(EXCEPTION, message),
+            Self::Parse {
+                message,
+                start,
+                end,
+            } => throw_parse_exception(env, &message, start, end),
+        };
+        // Nothing else can be reported if even the exception cannot be thrown.
+        let _ = result;
+    }
+}
+
+fn throw_parse_exception(
+    env: &mut JNIEnv,
+    message: &str,
+    start: jint,
+    end: jint,
+) -> jni::errors::Result<()> {
+    let message = env.new_string(message)?;
+    let exception = env.new_object(
+        PARSE_EXCEPTION,
+        "(Ljava/lang/String;II)V",
+        &[
+            JValue::Object(&message),
+            JValue::Int(start),
+            JValue::Int(end),
+        ],
+    )?;
+    env.throw(JThrowable::from(exception))
+}
+
+/// Run the body of a native method, throwing its failure and returning `default` instead.
+fn run<'local, T>(
+    env: &mut JNIEnv<'local>,
+    default: T,
+    body: impl FnOnce(&mut JNIEnv<'local>) -> Result<T, Failure>,
+) -> T {
+    body(env).unwrap_or_else(|failure| {
+        failure.throw(env);
+        default
+    })
+}
+
+/// # Safety
+/// `handle` must be a pointer returned by `nativeNew()` that was not freed.
+unsafe fn tree<'a>(handle: jlong) -> &'a Tree {
+    &*(handle as *const Tree)
+}
+
+fn read(tree: &Tree) -> Result<RwLockReadGuard<'_, ATree<i64>>, Failure> {
+    tree.read().map_err(Failure::other)
+}
+
+fn write(tree: &Tree) -> Result<RwLockWriteGuard<'_, ATree<i64>>, Failure> {
+    tree.write().map_err(Failure::other)
+}
+
+fn definition(name: &str, kind: jint) -> Result<AttributeDefinition, Failure> {
+    // Ordinals of the ATree.Kind enum
+    match kind {
+        0 => Ok(AttributeDefinition::boolean(name)),
+        1 => Ok(AttributeDefinition::integer(name)),
+        2 => Ok(AttributeDefinition::float(name)),
+        3 => Ok(AttributeDefinition::string(name)),
+        4 => Ok(AttributeDefinition::integer_list(name)),
+        5 => Ok(AttributeDefinition::string_list(name)),
+        kind => Err(Failure::Other(format!(
+            "unknown kind {kind} for attribute {name:?}"
+        ))),
+    }
+}
+
+fn search(tree: &ATree<i64>, json: &str) -> Result<Vec<jlong>, Failure> {
+    let event = tree.event_from_json(json).map_err(Failure::other)?;
+    let report = tree.search(&event).map_err(Failure::other)?;
+    Ok(report.matches().iter().map(|&&id| id).collect())
+}
+
+fn long_array<'local>(env: &mut JNIEnv<'local>, ids: &[jlong]) -> Result<JObject<'local>, Failure> {
+    let array = env.new_long_array(ids.len() as jint)?;
+    env.set_long_array_region(&array, 0, ids)?;
+    Ok(array.into())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_github_mrayva_atree_ATree_nativeNew<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    names: JObjectArray<'local>,
+    kinds: JIntArray<'local>,
+) -> jlong {
+    run(&mut env, 0, |env| {
+        let count = env.get_array_length(&names)?;
+        let mut ordinals = vec![0; count as usize];
+        env.get_int_array_region(&kinds, 0, &mut ordinals)?;
+
+        let mut definitions = Vec::with_capacity(ordinals.len());
+        for (index, kind) in ordinals.into_iter().enumerate() {
+            let name = JString::from(env.get_object_array_element(&names, index as jint)?);
+            let name: String = env.get_string(&name)?.into();
+            definitions.push(definition(&name, kind)?);
+        }
+
+        let tree = ATree::<i64>::new(&definitions).map_err(Failure::other)?;
+        Ok(Box::into_raw(Box::new(RwLock::new(tree))) as jlong)
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_github_mrayva_atree_ATree_nativeFree<'local>(
+    _env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+) {
+    if handle != 0 {
+        drop(unsafe { Box::from_raw(handle as *mut Tree) });
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_github_mrayva_atree_ATree_nativeInsert<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+    id: jlong,
+    expression: JString<'local>,
+) {
+    run(&mut env, (), |env| {
+        let expression: String = env.get_string(&expression)?.into();
+        let mut tree = write(unsafe { tree(handle) })?;
+        tree.insert(&id, &expression)
+            .map_err(|error| Failure::insertion(error, &expression))
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_github_mrayva_atree_ATree_nativeDelete<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+    id: jlong,
+) {
+    run(&mut env, (), |_| {
+        write(unsafe { tree(handle) })?.delete(&id);
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_github_mrayva_atree_ATree_nativeSearch<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+    event: JString<'local>,
+) -> jlongArray {
+    run(&mut env, ptr::null_mut(), |env| {
+        let event: String = env.get_string(&event)?.into();
+        let tree = read(unsafe { tree(handle) })?;
+        let ids = search(&tree, &event)?;
+        Ok(long_array(env, &ids)?.into_raw())
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_io_github_mrayva_atree_ATree_nativeSearchBatch<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+    events: JByteBuffer<'local>,
+    offset: jint,
+    length: jint,
+) -> jobjectArray {
+    run(&mut env, ptr::null_mut(), |env| {
+        let address = env.get_direct_buffer_address(&events)?;
+        let capacity = env.get_direct_buffer_capacity(&events)?;
+        let range = usize::try_from(offset)
+            .ok()
+            .zip(usize::try_from(length).ok())
+            .filter(|&(offset, length)| {
+                offset
+                    .checked_add(length)
+                    .is_some_and(|end| end <= capacity)
+            });
+        let Some((offset, length)) = range else {
+            return Err(Failure::Other(format!(
+                "{length} bytes from {offset} are out of the buffer of {capacity} bytes"
+            )));
+        };
+        // The buffer is direct, so its memory does not move while the JVM runs this method.
+        let bytes = unsafe { std::slice::from_raw_parts(address.add(offset), length) };
+        let lines = std::str::from_utf8(bytes).map_err(Failure::other)?;
+
+        let tree = read(unsafe { tree(handle) })?;
+        let events: Vec<_> = lines
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        let results = env.new_object_array(events.len() as jint, "[J", JObject::null())?;
+        for (index, event) in events.into_iter().enumerate() {
+            let ids = search(&tree, event)?;
+            let array = long_array(env, &ids)?;
+            env.set_object_array_element(&results, index as jint, &array)?;
+            env.delete_local_ref(array)?;
+        }
+        Ok(results.into_raw())
+    })
+}
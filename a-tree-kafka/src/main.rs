@@ -0,0 +1,231 @@
+//! Kafka consumer matching a topic of JSON events against a hot-reloaded
+//! [a-tree](https://crates.io/crates/a-tree) and producing the IDs of the matching subscriptions
+//! of each event.
+//!
+//! The events are searched in batches. The next batch is only consumed once the results of the
+//! previous one are delivered and its offsets committed, so that a slow output topic slows down
+//! the consumption instead of filling the memory, and no event is lost on a restart.
+
+mod rules;
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use a_tree::Event;
+use clap::Parser;
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::message::{BorrowedMessage, Message, OwnedMessage};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::ClientConfig;
+use serde_json::{json, Map, Value};
+use tokio::time::{self, Instant};
+
+use crate::rules::{Rules, Tree};
+
+/// Pause after a consumption error, so that the errors repeated while the brokers are down do
+/// not flood the logs
+const ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Consume JSON events from a topic and produce `{"event_id": ..., "matched_ids": [...]}`
+/// records to another.
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// Kafka brokers, e.g. `localhost:9092`
+    #[arg(long)]
+    brokers: String,
+    /// Consumer group sharing the partitions of the events topic
+    #[arg(long, default_value = "a-tree-kafka")]
+    group: String,
+    /// Topic of the JSON events
+    #[arg(long)]
+    input: String,
+    /// Topic of the matches
+    #[arg(long)]
+    output: String,
+    /// Rules exported by `ATree::export_json()`, reloaded when the file changes
+    #[arg(long)]
+    rules: PathBuf,
+    /// Seconds between the checks of the rules file
+    #[arg(long, default_value_t = 10)]
+    reload_interval: u64,
+    /// Attribute of the events holding their ID, removed before the search; the key of the
+    /// messages is used otherwise
+    #[arg(long)]
+    id_field: Option<String>,
+    /// Maximum number of events searched at once
+    #[arg(long, default_value_t = 500)]
+    batch_size: usize,
+    /// Milliseconds to wait for a batch to fill before searching it
+    #[arg(long, default_value_t = 100)]
+    batch_timeout: u64,
+    /// Do not produce a record for the events without any match
+    #[arg(long)]
+    skip_unmatched: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let (rules, modified) = Rules::load(&args.rules)?;
+    tokio::spawn(rules.clone().watch(
+        args.rules.clone(),
+        modified,
+        Duration::from_secs(args.reload_interval),
+    ));
+
+    let consumer: StreamConsumer = ClientConfig::new()
+        .set("bootstrap.servers", &args.brokers)
+        .set("group.id", &args.group)
+        .set("enable.auto.commit", "false")
+        .create()?;
+    consumer.subscribe(&[&args.input])?;
+    let producer: FutureProducer = ClientConfig::new()
+        .set("bootstrap.servers", &args.brokers)
+        .create()?;
+
+    eprintln!("a-tree-kafka matching {} into {}", args.input, args.output);
+    tokio::select! {
+        result = run(&args, &rules, &consumer, &producer) => result,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    }
+}
+
+async fn run(
+    args: &Args,
+    rules: &Rules,
+    consumer: &StreamConsumer,
+    producer: &FutureProducer,
+) -> Result<(), Box<dyn Error>> {
+    let batch_timeout = Duration::from_millis(args.batch_timeout);
+    loop {
+        let batch = next_batch(consumer, args.batch_size.max(1), batch_timeout).await;
+        let records = search(&rules.current(), args, &batch);
+
+        let mut deliveries = Vec::with_capacity(records.len());
+        for (key, payload) in &records {
+            let mut record = FutureRecord::to(&args.output).payload(payload);
+            if let Some(key) = key {
+                record = record.key(key);
+            }
+            loop {
+                match producer.send_result(record) {
+                    Ok(delivery) => break deliveries.push(delivery),
+                    Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), rejected)) => {
+                        record = rejected;
+                        time::sleep(Duration::from_millis(10)).await;
+                    }
+                    Err((error, _)) => return Err(error.into()),
+                }
+            }
+        }
+        for delivery in deliveries {
+            if let Err((error, _)) = delivery.await? {
+                return Err(error.into());
+            }
+        }
+        consumer.commit_consumer_state(CommitMode::Async)?;
+    }
+}
+
+/// Wait for an event, then for the following ones until the batch is full or the timeout
+/// expires.
+///
+/// The consumption errors are reported and skipped since the client recovers from them, e.g. by
+/// reconnecting to the brokers.
+async fn next_batch(
+    consumer: &StreamConsumer,
+    size: usize,
+    timeout: Duration,
+) -> Vec<OwnedMessage> {
+    let mut batch = Vec::with_capacity(size);
+    while batch.is_empty() {
+        if !receive(consumer.recv().await, &mut batch) {
+            time::sleep(ERROR_BACKOFF).await;
+        }
+    }
+    let deadline = Instant::now() + timeout;
+    while batch.len() < size {
+        let Ok(message) = time::timeout_at(deadline, consumer.recv()).await else {
+            break;
+        };
+        if !receive(message, &mut batch) {
+            break;
+        }
+    }
+    batch
+}
+
+/// Add a message to the batch, returning whether it was received.
+fn receive(
+    message: Result<BorrowedMessage<'_>, KafkaError>,
+    batch: &mut Vec<OwnedMessage>,
+) -> bool {
+    match message {
+        Ok(message) => {
+            batch.push(message.detach());
+            true
+        }
+        Err(error) => {
+            eprintln!("cannot consume: {error}");
+            false
+        }
+    }
+}
+
+/// Search the events of a batch, returning the key and the payload of the records to produce;
+/// the invalid events are reported and skipped.
+fn search(tree: &Tree, args: &Args, batch: &[OwnedMessage]) -> Vec<(Option<Vec<u8>>, String)> {
+    let mut ids = Vec::with_capacity(batch.len());
+    let mut events = Vec::with_capacity(batch.len());
+    for message in batch {
+        match event(tree, args.id_field.as_deref(), message) {
+            Ok((id, event)) => {
+                ids.push((message.key().map(<[u8]>::to_vec), id));
+                events.push(event);
+            }
+            Err(error) => eprintln!(
+                "skipping the event at offset {} of partition {}: {error}",
+                message.offset(),
+                message.partition()
+            ),
+        }
+    }
+
+    let reports = match tree.search_batch(&events) {
+        Ok(reports) => reports,
+        Err(error) => {
+            eprintln!("skipping a batch of {} events: {error}", events.len());
+            return vec![];
+        }
+    };
+    ids.into_iter()
+        .zip(reports)
+        .filter(|(_, report)| !(args.skip_unmatched && report.matches().is_empty()))
+        .map(|((key, event_id), report)| {
+            let payload = json!({ "event_id": event_id, "matched_ids": report.matches() });
+            (key, payload.to_string())
+        })
+        .collect()
+}
+
+/// Parse the ID and the attributes of an event; its ID is the `id_field` attribute, or else the
+/// key of the message, or else its partition and offset.
+fn event(
+    tree: &Tree,
+    id_field: Option<&str>,
+    message: &OwnedMessage,
+) -> Result<(Value, Event), Box<dyn Error>> {
+    let payload = message.payload().ok_or("the message has no payload")?;
+    let mut attributes: Map<String, Value> = serde_json::from_slice(payload)?;
+    let id = id_field.and_then(|field| attributes.remove(field));
+    let id = match (id, message.key()) {
+        (Some(id), _) => id,
+        (None, Some(key)) => Value::String(String::from_utf8_lossy(key).into_owned()),
+        (None, None) => Value::String(format!("{}:{}", message.partition(), message.offset())),
+    };
+    let event = tree.event_from_json(&Value::Object(attributes).to_string())?;
+    Ok((id, event))
+}
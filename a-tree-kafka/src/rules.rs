@@ -0,0 +1,89 @@
+//! Rules loaded from a JSON export of a tree and reloaded when the file changes, without pausing
+//! the searches.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use a_tree::ATree;
+
+pub type Tree = ATree<u64>;
+
+/// Current tree, replaced as a whole by the reloads
+#[derive(Clone)]
+pub struct Rules {
+    current: Arc<RwLock<Arc<Tree>>>,
+}
+
+impl Rules {
+    /// Load the rules exported by `ATree::export_json()` to a file.
+    pub fn load(path: &Path) -> Result<(Self, SystemTime), Box<dyn Error>> {
+        let (tree, modified) = read(path)?;
+        let rules = Self {
+            current: Arc::new(RwLock::new(Arc::new(tree))),
+        };
+        Ok((rules, modified))
+    }
+
+    /// The tree to search; a reload does not affect the trees already returned.
+    pub fn current(&self) -> Arc<Tree> {
+        let current = self
+            .current
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        Arc::clone(&current)
+    }
+
+    fn replace(&self, tree: Tree) {
+        let mut current = self
+            .current
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *current = Arc::new(tree);
+    }
+
+    /// Check the modification time of the file at each interval and reload the rules when it
+    /// changes; invalid rules are reported and the previous tree is kept.
+    pub async fn watch(self, path: PathBuf, mut modified: SystemTime, interval: Duration) {
+        let mut ticks = tokio::time::interval(interval);
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            match fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+                Ok(time) if time == modified => continue,
+                Ok(_) => {}
+                Err(error) => {
+                    eprintln!("cannot check {}: {error}", path.display());
+                    continue;
+                }
+            }
+
+            match read(&path) {
+                Ok((tree, time)) => {
+                    eprintln!(
+                        "reloaded {} subscriptions from {}",
+                        tree.stats().subscriptions(),
+                        path.display()
+                    );
+                    self.replace(tree);
+                    modified = time;
+                }
+                Err(error) => {
+                    eprintln!("keeping the previous rules, {}: {error}", path.display());
+                    // Retry only once the file changes again.
+                    if let Ok(time) = fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+                        modified = time;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<(Tree, SystemTime), Box<dyn Error>> {
+    let modified = fs::metadata(path)?.modified()?;
+    let tree = ATree::import_json(&fs::read_to_string(path)?)?;
+    Ok((tree, modified))
+}
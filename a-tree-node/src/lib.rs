@@ -0,0 +1,136 @@
+//! Node.js bindings for the [a-tree](https://crates.io/crates/a-tree) library.
+//!
+//! The events are plain JavaScript objects mapping the attribute names to their values, and the
+//! asynchronous searches run on the libuv thread pool so that they do not block the event loop.
+
+use std::fmt::Display;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use a_tree::{ATree, AttributeDefinition};
+use napi::bindgen_prelude::{AsyncTask, Error, Result};
+use napi::{Env, Task};
+use napi_derive::napi;
+use serde_json::Value;
+
+/// Definition of an attribute of the events
+#[napi(object)]
+pub struct Attribute {
+    pub name: String,
+    /// One of `boolean`, `integer`, `float`, `string`, `integer_list` and `string_list`
+    pub kind: String,
+}
+
+impl TryFrom<&Attribute> for AttributeDefinition {
+    type Error = Error;
+
+    fn try_from(attribute: &Attribute) -> Result<Self> {
+        let name = attribute.name.as_str();
+        match attribute.kind.as_str() {
+            "boolean" => Ok(Self::boolean(name)),
+            "integer" => Ok(Self::integer(name)),
+            "float" => Ok(Self::float(name)),
+            "string" => Ok(Self::string(name)),
+            "integer_list" => Ok(Self::integer_list(name)),
+            "string_list" => Ok(Self::string_list(name)),
+            kind => Err(Error::from_reason(format!(
+                "unknown kind {kind:?} for attribute {name:?}"
+            ))),
+        }
+    }
+}
+
+fn to_error(error: impl Display) -> Error {
+    Error::from_reason(error.to_string())
+}
+
+/// A-Tree indexing the boolean expressions of the subscriptions
+///
+/// The subscription IDs are JavaScript numbers, which must be integers in the safe range.
+#[napi]
+pub struct Tree {
+    inner: Arc<RwLock<ATree<i64>>>,
+}
+
+#[napi]
+impl Tree {
+    /// Create an empty tree for events with the given attributes.
+    #[napi(constructor)]
+    pub fn new(attributes: Vec<Attribute>) -> Result<Self> {
+        let definitions = attributes
+            .iter()
+            .map(AttributeDefinition::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        let tree = ATree::new(&definitions).map_err(to_error)?;
+        Ok(Self {
+            inner: Arc::new(RwLock::new(tree)),
+        })
+    }
+
+    /// Insert the expression of a subscription, e.g. `exchange_id = 1 and deal_ids one of ["deal-1"]`.
+    #[napi]
+    pub fn insert(&self, id: i64, expression: String) -> Result<()> {
+        let mut tree = self.write()?;
+        tree.insert(&id, &expression).map_err(to_error)
+    }
+
+    /// Delete a subscription; nothing happens if it is not in the tree.
+    #[napi]
+    pub fn delete(&self, id: i64) -> Result<()> {
+        self.write()?.delete(&id);
+        Ok(())
+    }
+
+    /// Search the subscriptions matching an event on the libuv thread pool.
+    ///
+    /// The attributes missing from the event are undefined; the promise is rejected if the event
+    /// has an unknown attribute or a value of the wrong type.
+    #[napi(ts_return_type = "Promise<Array<number>>")]
+    pub fn search(&self, event: Value) -> AsyncTask<Search> {
+        AsyncTask::new(Search {
+            tree: Arc::clone(&self.inner),
+            event,
+        })
+    }
+
+    /// Search the subscriptions matching an event on the calling thread.
+    #[napi]
+    pub fn search_sync(&self, event: Value) -> Result<Vec<i64>> {
+        let tree = self.read()?;
+        search(&tree, &event)
+    }
+
+    fn read(&self) -> Result<RwLockReadGuard<'_, ATree<i64>>> {
+        self.inner.read().map_err(to_error)
+    }
+
+    fn write(&self) -> Result<RwLockWriteGuard<'_, ATree<i64>>> {
+        self.inner.write().map_err(to_error)
+    }
+}
+
+/// Search running on the libuv thread pool, see [`Tree::search()`]
+pub struct Search {
+    tree: Arc<RwLock<ATree<i64>>>,
+    event: Value,
+}
+
+#[napi]
+impl Task for Search {
+    type Output = Vec<i64>;
+    type JsValue = Vec<i64>;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let tree = self.tree.read().map_err(to_error)?;
+        search(&tree, &self.event)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+fn search(tree: &ATree<i64>, event: &Value) -> Result<Vec<i64>> {
+    let event = tree.event_from_json(&event.to_string()).map_err(to_error)?;
+    let report = tree.search(&event).map_err(to_error)?;
+    Ok(report.matches().iter().map(|&&id| id).collect())
+}
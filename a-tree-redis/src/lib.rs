@@ -0,0 +1,227 @@
+//! Redis module exposing the [a-tree](https://crates.io/crates/a-tree) library as `ATREE.*`
+//! commands on trees stored in the keyspace, so that the Redis-centric stacks match the events
+//! next to their data.
+//!
+//! The trees are persisted in the RDB files as snapshots, the AOF rewrites store them with
+//! `ATREE.RESTORE`, and the write commands are replicated verbatim.
+
+use std::fmt::Display;
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr;
+
+use a_tree::{ATree, AttributeDefinition};
+use redis_module::native_types::RedisType;
+use redis_module::{
+    raw, redis_module, Context, NextArg, RedisError, RedisResult, RedisString, RedisValue,
+};
+
+/// Name of the data type in the RDB files, which must be 9 characters long
+const TYPE_NAME: &str = "a-tree-rs";
+/// Version of the RDB encoding; the snapshots carry their own format version.
+const ENCODING_VERSION: i32 = 0;
+
+type Tree = ATree<i64>;
+
+static ATREE_TYPE: RedisType = RedisType::new(
+    TYPE_NAME,
+    ENCODING_VERSION,
+    raw::RedisModuleTypeMethods {
+        version: raw::REDISMODULE_TYPE_METHOD_VERSION as u64,
+        rdb_load: Some(rdb_load),
+        rdb_save: Some(rdb_save),
+        aof_rewrite: Some(aof_rewrite),
+        free: Some(free),
+        mem_usage: Some(mem_usage),
+        // SAFETY: the other callbacks, whose set depends on the version of the module API, are
+        // optional function pointers and integers for which zero means none.
+        ..unsafe { std::mem::zeroed() }
+    },
+);
+
+unsafe extern "C" fn rdb_load(rdb: *mut raw::RedisModuleIO, _encver: c_int) -> *mut c_void {
+    let Ok(buffer) = raw::load_string_buffer(rdb) else {
+        return ptr::null_mut();
+    };
+    match Tree::from_snapshot(buffer.as_ref()) {
+        Ok(tree) => Box::into_raw(Box::new(tree)).cast(),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn rdb_save(rdb: *mut raw::RedisModuleIO, value: *mut c_void) {
+    let tree = &*value.cast::<Tree>();
+    raw::save_slice(rdb, &tree.to_snapshot());
+}
+
+unsafe extern "C" fn aof_rewrite(
+    aof: *mut raw::RedisModuleIO,
+    key: *mut raw::RedisModuleString,
+    value: *mut c_void,
+) {
+    let tree = &*value.cast::<Tree>();
+    let snapshot = tree.to_snapshot();
+    if let Some(emit) = raw::RedisModule_EmitAOF {
+        emit(
+            aof,
+            c"ATREE.RESTORE".as_ptr(),
+            c"sb".as_ptr(),
+            key,
+            snapshot.as_ptr().cast::<c_char>(),
+            snapshot.len(),
+        );
+    }
+}
+
+unsafe extern "C" fn free(value: *mut c_void) {
+    drop(Box::from_raw(value.cast::<Tree>()));
+}
+
+unsafe extern "C" fn mem_usage(value: *const c_void) -> usize {
+    (*value.cast::<Tree>()).memory_usage()
+}
+
+fn error(error: impl Display) -> RedisError {
+    RedisError::String(format!("ERR {error}"))
+}
+
+fn definition(name: &str, kind: &str) -> Result<AttributeDefinition, RedisError> {
+    match kind.to_ascii_lowercase().as_str() {
+        "boolean" => Ok(AttributeDefinition::boolean(name)),
+        "integer" => Ok(AttributeDefinition::integer(name)),
+        "float" => Ok(AttributeDefinition::float(name)),
+        "string" => Ok(AttributeDefinition::string(name)),
+        "integer_list" => Ok(AttributeDefinition::integer_list(name)),
+        "string_list" => Ok(AttributeDefinition::string_list(name)),
+        _ => Err(RedisError::String(format!(
+            "ERR unknown kind {kind:?} for attribute {name:?}"
+        ))),
+    }
+}
+
+/// `ATREE.CREATE key name kind [name kind ...]`: store an empty tree for events with the given
+/// attributes; fails if the key exists.
+fn create(ctx: &Context, args: Vec<RedisString>) -> RedisResult {
+    if args.len() < 4 || args.len() % 2 != 0 {
+        return Err(RedisError::WrongArity);
+    }
+
+    let mut args = args.into_iter().skip(1);
+    let key = args.next_arg()?;
+    let mut definitions = Vec::with_capacity(args.len() / 2);
+    while let (Some(name), Some(kind)) = (args.next(), args.next()) {
+        definitions.push(definition(name.try_as_str()?, kind.try_as_str()?)?);
+    }
+    let tree = Tree::new(&definitions).map_err(error)?;
+
+    let key = ctx.open_key_writable(&key);
+    if !key.is_empty() {
+        return Err(RedisError::Str("ERR key already exists"));
+    }
+    key.set_value(&ATREE_TYPE, tree)?;
+    ctx.replicate_verbatim();
+    Ok(RedisValue::SimpleStringStatic("OK"))
+}
+
+/// `ATREE.RESTORE key snapshot`: store the tree of a snapshot, such as the ones written by the
+/// AOF rewrites; fails if the key exists.
+fn restore(ctx: &Context, args: Vec<RedisString>) -> RedisResult {
+    if args.len() != 3 {
+        return Err(RedisError::WrongArity);
+    }
+
+    let mut args = args.into_iter().skip(1);
+    let key = args.next_arg()?;
+    let snapshot = args.next_arg()?;
+    let tree = Tree::from_snapshot(snapshot.as_slice()).map_err(error)?;
+
+    let key = ctx.open_key_writable(&key);
+    if !key.is_empty() {
+        return Err(RedisError::Str("ERR key already exists"));
+    }
+    key.set_value(&ATREE_TYPE, tree)?;
+    ctx.replicate_verbatim();
+    Ok(RedisValue::SimpleStringStatic("OK"))
+}
+
+/// `ATREE.INSERT key id expression`: insert the expression of a subscription, replacing the
+/// previous expression of the ID only if the new one is valid.
+fn insert(ctx: &Context, args: Vec<RedisString>) -> RedisResult {
+    if args.len() != 4 {
+        return Err(RedisError::WrongArity);
+    }
+
+    let mut args = args.into_iter().skip(1);
+    let key = args.next_arg()?;
+    let id = args.next_i64()?;
+    let expression = args.next_string()?;
+
+    let key = ctx.open_key_writable(&key);
+    let tree = key
+        .get_value::<Tree>(&ATREE_TYPE)?
+        .ok_or(RedisError::Str("ERR no such tree"))?;
+    tree.validate_expression(&expression).map_err(error)?;
+    tree.delete(&id);
+    tree.insert(&id, &expression).map_err(error)?;
+    ctx.replicate_verbatim();
+    Ok(RedisValue::SimpleStringStatic("OK"))
+}
+
+/// `ATREE.DELETE key id`: delete a subscription; returns 1 if it was in the tree, 0 otherwise.
+fn delete(ctx: &Context, args: Vec<RedisString>) -> RedisResult {
+    if args.len() != 3 {
+        return Err(RedisError::WrongArity);
+    }
+
+    let mut args = args.into_iter().skip(1);
+    let key = args.next_arg()?;
+    let id = args.next_i64()?;
+
+    let key = ctx.open_key_writable(&key);
+    let tree = key
+        .get_value::<Tree>(&ATREE_TYPE)?
+        .ok_or(RedisError::Str("ERR no such tree"))?;
+    let deleted = tree.expression(&id).is_some();
+    tree.delete(&id);
+    ctx.replicate_verbatim();
+    Ok(RedisValue::Integer(deleted.into()))
+}
+
+/// `ATREE.SEARCH key event`: the IDs of the subscriptions matching an event given as a JSON
+/// object; the missing attributes are undefined.
+fn search(ctx: &Context, args: Vec<RedisString>) -> RedisResult {
+    if args.len() != 3 {
+        return Err(RedisError::WrongArity);
+    }
+
+    let mut args = args.into_iter().skip(1);
+    let key = args.next_arg()?;
+    let event = args.next_arg()?;
+
+    let key = ctx.open_key(&key);
+    let tree = key
+        .get_value::<Tree>(&ATREE_TYPE)?
+        .ok_or(RedisError::Str("ERR no such tree"))?;
+    let event = tree.event_from_json(event.try_as_str()?).map_err(error)?;
+    let report = tree.search(&event).map_err(error)?;
+    Ok(RedisValue::Array(
+        report
+            .matches()
+            .iter()
+            .map(|&&id| RedisValue::Integer(id))
+            .collect(),
+    ))
+}
+
+redis_module! {
+    name: "atree",
+    version: 1,
+    allocator: (redis_module::alloc::RedisAlloc, redis_module::alloc::RedisAlloc),
+    data_types: [ATREE_TYPE],
+    commands: [
+        ["atree.create", create, "write deny-oom", 1, 1, 1],
+        ["atree.restore", restore, "write deny-oom", 1, 1, 1],
+        ["atree.insert", insert, "write deny-oom", 1, 1, 1],
+        ["atree.delete", delete, "write", 1, 1, 1],
+        ["atree.search", search, "readonly", 1, 1, 1],
+    ],
+}
@@ -0,0 +1,8 @@
+fn main() {
+    // Use the vendored protoc unless the build environment provides one.
+    if std::env::var_os("PROTOC").is_none() {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("No vendored protoc");
+        std::env::set_var("PROTOC", protoc);
+    }
+    tonic_prost_build::compile_protos("proto/atree.proto").expect("Unable to compile the protos");
+}
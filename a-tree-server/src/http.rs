@@ -0,0 +1,258 @@
+//! HTTP/JSON API for the internal tooling and the integration tests, served along with gRPC on
+//! the address given by `--http`.
+
+use std::fmt::Write;
+use std::num::NonZeroUsize;
+use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+use std::thread;
+
+use a_tree::{ATree, ATreeError};
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+use crate::service::Tree;
+
+/// Serve the API until the process stops, reporting the failure of the server.
+pub async fn serve(listener: TcpListener, tree: Tree) {
+    if let Err(error) = axum::serve(listener, router(tree)).await {
+        eprintln!("the HTTP server failed: {error}");
+    }
+}
+
+fn router(tree: Tree) -> Router {
+    Router::new()
+        .route("/rules", post(insert_many))
+        .route("/rules/{id}", get(expression).put(replace).delete(delete))
+        .route("/search", post(search))
+        .route("/search/batch", post(search_batch))
+        .route("/metrics", get(metrics))
+        .with_state(tree)
+}
+
+/// Failure returned as `{"error": "..."}`
+struct Failure(StatusCode, String);
+
+impl Failure {
+    fn bad_request(error: impl ToString) -> Self {
+        Self(StatusCode::BAD_REQUEST, error.to_string())
+    }
+
+    fn insertion(error: ATreeError<'_>) -> Self {
+        match error {
+            ATreeError::DuplicateId(_) => Self(StatusCode::CONFLICT, error.to_string()),
+            error => Self::bad_request(error),
+        }
+    }
+}
+
+impl IntoResponse for Failure {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+fn read(tree: &Tree) -> Result<RwLockReadGuard<'_, ATree<u64>>, Failure> {
+    tree.read().map_err(|_| {
+        Failure(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "the tree is poisoned".to_owned(),
+        )
+    })
+}
+
+fn write(tree: &Tree) -> Result<RwLockWriteGuard<'_, ATree<u64>>, Failure> {
+    tree.write().map_err(|_| {
+        Failure(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "the tree is poisoned".to_owned(),
+        )
+    })
+}
+
+#[derive(Deserialize)]
+struct Rule {
+    expression: String,
+}
+
+#[derive(Deserialize)]
+struct NewRule {
+    id: u64,
+    expression: String,
+}
+
+#[derive(Serialize)]
+struct RejectedRule {
+    id: u64,
+    error: String,
+}
+
+#[derive(Serialize)]
+struct Matches {
+    ids: Vec<u64>,
+}
+
+/// `GET /rules/{id}`: the expression of a subscription, as it was inserted
+async fn expression(State(tree): State<Tree>, Path(id): Path<u64>) -> Result<Json<Value>, Failure> {
+    let tree = read(&tree)?;
+    let expression = tree.expression(&id).ok_or_else(|| {
+        Failure(
+            StatusCode::NOT_FOUND,
+            format!("subscription {id} is not in the tree"),
+        )
+    })?;
+    Ok(Json(json!({ "id": id, "expression": expression })))
+}
+
+/// `PUT /rules/{id}`: insert the expression of a subscription, replacing the previous one only
+/// if the new one is valid
+async fn replace(
+    State(tree): State<Tree>,
+    Path(id): Path<u64>,
+    Json(rule): Json<Rule>,
+) -> Result<StatusCode, Failure> {
+    let mut tree = write(&tree)?;
+    tree.validate_expression(&rule.expression)
+        .map_err(Failure::insertion)?;
+    tree.delete(&id);
+    tree.insert(&id, &rule.expression)
+        .map_err(Failure::insertion)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `DELETE /rules/{id}`: delete a subscription; nothing happens if it is not in the tree
+async fn delete(State(tree): State<Tree>, Path(id): Path<u64>) -> Result<StatusCode, Failure> {
+    write(&tree)?.delete(&id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `POST /rules`: insert many `{"id": ..., "expression": ...}` rules, parsed on every CPU, and
+/// report the rejected ones
+async fn insert_many(
+    State(tree): State<Tree>,
+    Json(rules): Json<Vec<NewRule>>,
+) -> Result<Json<Value>, Failure> {
+    let subscriptions: Vec<_> = rules
+        .iter()
+        .map(|rule| (rule.id, rule.expression.as_str()))
+        .collect();
+    let threads = thread::available_parallelism().unwrap_or(NonZeroUsize::MIN);
+    let results = write(&tree)?.insert_many(&subscriptions, threads);
+
+    let rejected: Vec<_> = rules
+        .iter()
+        .zip(results)
+        .filter_map(|(rule, result)| {
+            result.err().map(|error| RejectedRule {
+                id: rule.id,
+                error: error.to_string(),
+            })
+        })
+        .collect();
+    Ok(Json(json!({
+        "inserted": rules.len() - rejected.len(),
+        "rejected": rejected,
+    })))
+}
+
+fn matches(tree: &ATree<u64>, event: &str) -> Result<Vec<u64>, Failure> {
+    let event = tree.event_from_json(event).map_err(Failure::bad_request)?;
+    let report = tree.search(&event).map_err(Failure::bad_request)?;
+    Ok(report.matches().iter().map(|&&id| id).collect())
+}
+
+/// `POST /search`: the IDs of the subscriptions matching an event given as a JSON object
+async fn search(State(tree): State<Tree>, event: String) -> Result<Json<Matches>, Failure> {
+    let tree = read(&tree)?;
+    let ids = matches(&tree, &event)?;
+    Ok(Json(Matches { ids }))
+}
+
+/// `POST /search/batch`: the IDs matching each event of a JSON array, in the same order
+async fn search_batch(
+    State(tree): State<Tree>,
+    Json(events): Json<Vec<Value>>,
+) -> Result<Json<Vec<Matches>>, Failure> {
+    let tree = read(&tree)?;
+    let results = events
+        .iter()
+        .enumerate()
+        .map(|(index, event)| {
+            matches(&tree, &event.to_string())
+                .map(|ids| Matches { ids })
+                .map_err(|Failure(status, error)| {
+                    Failure(status, format!("event {index}: {error}"))
+                })
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Json(results))
+}
+
+/// `GET /metrics`: the statistics and the counters of the tree in the Prometheus text format
+async fn metrics(State(tree): State<Tree>) -> Result<impl IntoResponse, Failure> {
+    let tree = read(&tree)?;
+    let stats = tree.stats();
+    let metrics = tree.metrics();
+    let gauges = [
+        (
+            "subscriptions",
+            "Number of subscriptions",
+            stats.subscriptions() as u64,
+        ),
+        (
+            "nodes",
+            "Number of nodes, shared or not",
+            stats.nodes() as u64,
+        ),
+        (
+            "predicates",
+            "Number of distinct predicates",
+            stats.predicates() as u64,
+        ),
+        (
+            "memory_bytes",
+            "Estimated memory used by the tree",
+            stats.memory_usage() as u64,
+        ),
+    ];
+    let counters = [
+        ("searches_total", "Number of searches", metrics.searches()),
+        (
+            "matches_total",
+            "Number of matches returned by the searches",
+            metrics.matches(),
+        ),
+        (
+            "inserts_total",
+            "Number of inserted expressions",
+            metrics.inserts(),
+        ),
+        (
+            "parse_failures_total",
+            "Number of rejected expressions",
+            metrics.parse_failures(),
+        ),
+        (
+            "nodes_visited_total",
+            "Number of nodes visited by the searches",
+            metrics.nodes_visited(),
+        ),
+    ];
+
+    let mut body = String::new();
+    let kinds = gauges.iter().map(|gauge| (gauge, "gauge"));
+    for ((name, help, value), kind) in
+        kinds.chain(counters.iter().map(|counter| (counter, "counter")))
+    {
+        let _ = writeln!(
+            body,
+            "# HELP a_tree_{name} {help}\n# TYPE a_tree_{name} {kind}\na_tree_{name} {value}"
+        );
+    }
+    Ok(([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body))
+}
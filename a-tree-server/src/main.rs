@@ -0,0 +1,92 @@
+//! gRPC server exposing an [a-tree](https://crates.io/crates/a-tree), so that the services
+//! written in languages that cannot embed the library match the events with its exact semantics.
+
+#[cfg(feature = "http")]
+mod http;
+mod service;
+
+use std::error::Error;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use a_tree::{ATree, AttributeDefinition};
+use clap::Parser;
+use serde_json::{Map, Value};
+use tonic::transport::Server;
+
+use crate::service::proto::matcher_server::MatcherServer;
+use crate::service::MatcherService;
+
+/// Serve the Insert, Delete, Search, SearchBatch and Snapshot RPCs of `atree.v1.Matcher`.
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:50051")]
+    listen: SocketAddr,
+    /// JSON file mapping the attribute names to their kind: `boolean`, `integer`, `float`,
+    /// `string`, `integer_list` or `string_list`
+    #[arg(
+        long,
+        required_unless_present = "snapshot",
+        conflicts_with = "snapshot"
+    )]
+    schema: Option<PathBuf>,
+    /// Snapshot returned by the Snapshot RPC, to restart with the same subscriptions
+    #[arg(long)]
+    snapshot: Option<PathBuf>,
+    /// Address to serve the HTTP/JSON API on, along with gRPC
+    #[cfg(feature = "http")]
+    #[arg(long)]
+    http: Option<SocketAddr>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let tree = match (args.schema, args.snapshot) {
+        (_, Some(snapshot)) => ATree::from_snapshot(&fs::read(snapshot)?)?,
+        (Some(schema), None) => new_tree(&fs::read_to_string(schema)?)?,
+        (None, None) => unreachable!("the arguments require a schema or a snapshot"),
+    };
+    let tree = Arc::new(RwLock::new(tree));
+
+    #[cfg(feature = "http")]
+    if let Some(address) = args.http {
+        let listener = tokio::net::TcpListener::bind(address).await?;
+        eprintln!("a-tree-server serving HTTP on {address}");
+        tokio::spawn(http::serve(listener, Arc::clone(&tree)));
+    }
+
+    eprintln!("a-tree-server listening on {}", args.listen);
+    Server::builder()
+        .add_service(MatcherServer::new(MatcherService::new(tree)))
+        .serve_with_shutdown(args.listen, async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+    Ok(())
+}
+
+fn new_tree(schema: &str) -> Result<ATree<u64>, Box<dyn Error>> {
+    let schema: Map<String, Value> = serde_json::from_str(schema)?;
+    let definitions = schema
+        .iter()
+        .map(|(name, kind)| definition(name, kind))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ATree::new(&definitions).map_err(|error| error.to_string())?)
+}
+
+fn definition(name: &str, kind: &Value) -> Result<AttributeDefinition, String> {
+    match kind.as_str() {
+        Some("boolean") => Ok(AttributeDefinition::boolean(name)),
+        Some("integer") => Ok(AttributeDefinition::integer(name)),
+        Some("float") => Ok(AttributeDefinition::float(name)),
+        Some("string") => Ok(AttributeDefinition::string(name)),
+        Some("integer_list") => Ok(AttributeDefinition::integer_list(name)),
+        Some("string_list") => Ok(AttributeDefinition::string_list(name)),
+        _ => Err(format!("unknown kind {kind} for attribute {name:?}")),
+    }
+}
@@ -0,0 +1,136 @@
+//! Implementation of the `atree.v1.Matcher` gRPC service.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use a_tree::{ATree, ATreeError, Event};
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status, Streaming};
+
+use self::proto::value::Kind;
+use self::proto::{
+    DeleteRequest, DeleteResponse, InsertRequest, InsertResponse, SearchRequest, SearchResponse,
+    SnapshotRequest, SnapshotResponse, Value,
+};
+
+pub mod proto {
+    tonic::include_proto!("atree.v1");
+}
+
+/// Tree shared by the gRPC service and the HTTP API
+pub type Tree = Arc<RwLock<ATree<u64>>>;
+
+/// Matcher service sharing a tree between the requests
+///
+/// The searches run at once, while the insertions and the deletions are exclusive.
+pub struct MatcherService {
+    tree: Tree,
+}
+
+impl MatcherService {
+    pub fn new(tree: Tree) -> Self {
+        Self { tree }
+    }
+}
+
+fn read(tree: &Tree) -> Result<RwLockReadGuard<'_, ATree<u64>>, Status> {
+    tree.read()
+        .map_err(|_| Status::internal("the tree is poisoned"))
+}
+
+fn write(tree: &Tree) -> Result<RwLockWriteGuard<'_, ATree<u64>>, Status> {
+    tree.write()
+        .map_err(|_| Status::internal("the tree is poisoned"))
+}
+
+fn invalid_argument(error: impl ToString) -> Status {
+    Status::invalid_argument(error.to_string())
+}
+
+fn insertion_error(error: ATreeError<'_>) -> Status {
+    match error {
+        ATreeError::DuplicateId(_) => Status::already_exists(error.to_string()),
+        error => invalid_argument(error),
+    }
+}
+
+fn event(tree: &ATree<u64>, attributes: &HashMap<String, Value>) -> Result<Event, Status> {
+    let mut builder = tree.make_event();
+    for (name, value) in attributes {
+        match &value.kind {
+            None => builder.with_undefined(name),
+            Some(Kind::Boolean(value)) => builder.with_boolean(name, *value),
+            Some(Kind::Integer(value)) => builder.with_integer(name, *value),
+            Some(Kind::Float(value)) => builder.with_f64(name, *value),
+            Some(Kind::String(value)) => builder.with_string(name, value),
+            Some(Kind::IntegerList(list)) => builder.with_integer_list(name, &list.values),
+            Some(Kind::StringList(list)) => {
+                let values: Vec<_> = list.values.iter().map(String::as_str).collect();
+                builder.with_string_list(name, &values)
+            }
+        }
+        .map_err(invalid_argument)?;
+    }
+    builder.build().map_err(invalid_argument)
+}
+
+fn search(tree: &Tree, request: SearchRequest) -> Result<SearchResponse, Status> {
+    let tree = read(tree)?;
+    let event = event(&tree, &request.attributes)?;
+    let report = tree.search(&event).map_err(invalid_argument)?;
+    Ok(SearchResponse {
+        ids: report.matches().iter().map(|&&id| id).collect(),
+    })
+}
+
+#[tonic::async_trait]
+impl proto::matcher_server::Matcher for MatcherService {
+    type SearchBatchStream =
+        Pin<Box<dyn Stream<Item = Result<SearchResponse, Status>> + Send + 'static>>;
+
+    async fn insert(
+        &self,
+        request: Request<InsertRequest>,
+    ) -> Result<Response<InsertResponse>, Status> {
+        let request = request.into_inner();
+        write(&self.tree)?
+            .insert(&request.id, &request.expression)
+            .map_err(insertion_error)?;
+        Ok(Response::new(InsertResponse {}))
+    }
+
+    async fn delete(
+        &self,
+        request: Request<DeleteRequest>,
+    ) -> Result<Response<DeleteResponse>, Status> {
+        write(&self.tree)?.delete(&request.into_inner().id);
+        Ok(Response::new(DeleteResponse {}))
+    }
+
+    async fn search(
+        &self,
+        request: Request<SearchRequest>,
+    ) -> Result<Response<SearchResponse>, Status> {
+        search(&self.tree, request.into_inner()).map(Response::new)
+    }
+
+    async fn search_batch(
+        &self,
+        request: Request<Streaming<SearchRequest>>,
+    ) -> Result<Response<Self::SearchBatchStream>, Status> {
+        let tree = Arc::clone(&self.tree);
+        let responses = request
+            .into_inner()
+            .map(move |request| search(&tree, request?));
+        Ok(Response::new(Box::pin(responses)))
+    }
+
+    async fn snapshot(
+        &self,
+        _request: Request<SnapshotRequest>,
+    ) -> Result<Response<SnapshotResponse>, Status> {
+        let snapshot = read(&self.tree)?.to_snapshot();
+        Ok(Response::new(SnapshotResponse { snapshot }))
+    }
+}
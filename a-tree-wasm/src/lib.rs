@@ -0,0 +1,83 @@
+//! WebAssembly bindings for the [a-tree](https://crates.io/crates/a-tree) library, so that the
+//! browsers and the edge workers evaluate the same expressions as the servers.
+//!
+//! The events are passed as JSON objects, e.g. with `JSON.stringify()`, and the matching
+//! subscription IDs are returned as an `Uint32Array`.
+
+use a_tree::{ATree, AttributeDefinition};
+use serde_json::{Map, Value};
+use wasm_bindgen::prelude::*;
+
+/// A-Tree indexing the boolean expressions of the subscriptions
+#[wasm_bindgen]
+pub struct Tree {
+    inner: ATree<u32>,
+}
+
+#[wasm_bindgen]
+impl Tree {
+    /// Create an empty tree from a JSON object mapping the attribute names to their kind, one of
+    /// `boolean`, `integer`, `float`, `string`, `integer_list` and `string_list`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(schema: &str) -> Result<Tree, JsError> {
+        let schema: Map<String, Value> = serde_json::from_str(schema)?;
+        let definitions = schema
+            .iter()
+            .map(|(name, kind)| definition(name, kind))
+            .collect::<Result<Vec<_>, _>>()?;
+        let inner = ATree::new(&definitions).map_err(|error| JsError::new(&error.to_string()))?;
+        Ok(Self { inner })
+    }
+
+    /// Create a tree from a JSON document exported by `exportJson()` or by `ATree::export_json()`
+    /// on a server.
+    #[wasm_bindgen(js_name = importJson)]
+    pub fn import_json(json: &str) -> Result<Tree, JsError> {
+        Ok(Self {
+            inner: ATree::import_json(json)?,
+        })
+    }
+
+    /// Export the schema and the subscriptions of the tree as a JSON document.
+    #[wasm_bindgen(js_name = exportJson)]
+    pub fn export_json(&self) -> Result<String, JsError> {
+        Ok(self.inner.export_json()?)
+    }
+
+    /// Insert the expression of a subscription, e.g. `exchange_id = 1 and private`.
+    pub fn insert(&mut self, id: u32, expression: &str) -> Result<(), JsError> {
+        self.inner
+            .insert(&id, expression)
+            .map_err(|error| JsError::new(&error.to_string()))
+    }
+
+    /// Delete a subscription; nothing happens if it is not in the tree.
+    pub fn delete(&mut self, id: u32) {
+        self.inner.delete(&id);
+    }
+
+    /// Search the subscriptions matching an event given as a JSON object; the missing attributes
+    /// are undefined.
+    pub fn search(&self, event: &str) -> Result<Vec<u32>, JsError> {
+        let event = self.inner.event_from_json(event)?;
+        let report = self
+            .inner
+            .search(&event)
+            .map_err(|error| JsError::new(&error.to_string()))?;
+        Ok(report.matches().iter().map(|&&id| id).collect())
+    }
+}
+
+fn definition(name: &str, kind: &Value) -> Result<AttributeDefinition, JsError> {
+    match kind.as_str() {
+        Some("boolean") => Ok(AttributeDefinition::boolean(name)),
+        Some("integer") => Ok(AttributeDefinition::integer(name)),
+        Some("float") => Ok(AttributeDefinition::float(name)),
+        Some("string") => Ok(AttributeDefinition::string(name)),
+        Some("integer_list") => Ok(AttributeDefinition::integer_list(name)),
+        Some("string_list") => Ok(AttributeDefinition::string_list(name)),
+        _ => Err(JsError::new(&format!(
+            "unknown kind {kind} for attribute {name:?}"
+        ))),
+    }
+}
@@ -1,8 +1,11 @@
+mod workload;
+
 use a_tree::{ATree, AttributeDefinition};
-use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
 use itertools::Itertools;
 use serde::Deserialize;
 use std::collections::HashMap;
+use workload::WorkloadConfig;
 
 const AN_EXPRESSION: &str = r#"exchange_id = 1 and deal_ids one of ["deal-1", "deal-2"] and segment_ids one of [1, 2, 3] and country = 'CA' and city in ['QC'] or country = 'US' and city in ['AZ']"#;
 const ID: u64 = 1;
@@ -153,5 +156,219 @@ pub fn search_with_files(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, insert_expression, search, search_with_files);
+pub fn insert_generated(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert_generated");
+    for subscriptions in [1_000, 10_000] {
+        let workload = WorkloadConfig::new()
+            .with_subscriptions(subscriptions)
+            .generate();
+        group.throughput(Throughput::Elements(subscriptions as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(subscriptions),
+            &workload,
+            |b, workload| {
+                b.iter_batched(
+                    || ATree::new(&workload.definitions).unwrap(),
+                    |mut atree| {
+                        for (id, expression) in &workload.expressions {
+                            atree.insert(id, expression).unwrap();
+                        }
+                        atree
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+pub fn search_generated(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search_generated");
+    for subscriptions in [1_000, 10_000, 100_000] {
+        let workload = WorkloadConfig::new()
+            .with_subscriptions(subscriptions)
+            .generate();
+        let atree = workload.tree();
+        let events = workload.events(&atree);
+        group.throughput(Throughput::Elements(events.len() as u64));
+        group.bench_function(BenchmarkId::from_parameter(subscriptions), |b| {
+            b.iter(|| {
+                for event in &events {
+                    let _ = std::hint::black_box(atree.search(event));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+pub fn search_by_sharing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search_by_sharing");
+    for sharing in [0.0, 0.5, 0.9] {
+        let workload = WorkloadConfig::new()
+            .with_subscriptions(10_000)
+            .with_sharing(sharing)
+            .generate();
+        let atree = workload.tree();
+        let events = workload.events(&atree);
+        group.throughput(Throughput::Elements(events.len() as u64));
+        group.bench_function(BenchmarkId::from_parameter(sharing), |b| {
+            b.iter(|| {
+                for event in &events {
+                    let _ = std::hint::black_box(atree.search(event));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+pub fn search_by_list_size(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search_by_list_size");
+    for list_size in [1, 10, 100] {
+        let workload = WorkloadConfig::new()
+            .with_subscriptions(10_000)
+            .with_list_size(list_size)
+            .with_domain(1_000)
+            .generate();
+        let atree = workload.tree();
+        let events = workload.events(&atree);
+        group.throughput(Throughput::Elements(events.len() as u64));
+        group.bench_function(BenchmarkId::from_parameter(list_size), |b| {
+            b.iter(|| {
+                for event in &events {
+                    let _ = std::hint::black_box(atree.search(event));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+pub fn search_against_baseline(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search_against_baseline");
+    let workload = WorkloadConfig::new().with_subscriptions(10_000).generate();
+    let atree = workload.tree();
+    let events = workload.events(&atree);
+    group.throughput(Throughput::Elements(events.len() as u64));
+    group.bench_function("tree", |b| {
+        b.iter(|| {
+            for event in &events {
+                let _ = std::hint::black_box(atree.search(event));
+            }
+        })
+    });
+    group.bench_function("baseline", |b| {
+        b.iter(|| {
+            for event in &events {
+                let _ = std::hint::black_box(atree.baseline_search(event));
+            }
+        })
+    });
+    group.finish();
+}
+
+pub fn search_by_thresholds(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search_by_thresholds");
+    for thresholds in [100, 1_000, 10_000] {
+        let definitions = [AttributeDefinition::float("bidfloor")];
+        let mut atree = ATree::new(&definitions).unwrap();
+        for threshold in 0..thresholds {
+            let expression = format!("bidfloor > {}.{:02}", threshold / 100, threshold % 100);
+            atree.insert(&threshold, &expression).unwrap();
+        }
+        let events: Vec<_> = (0..100)
+            .map(|i| {
+                let mut builder = atree.make_event();
+                builder
+                    .with_f64("bidfloor", f64::from(i) * f64::from(thresholds) / 10_000.0)
+                    .unwrap();
+                builder.build().unwrap()
+            })
+            .collect();
+        group.throughput(Throughput::Elements(events.len() as u64));
+        group.bench_function(BenchmarkId::from_parameter(thresholds), |b| {
+            b.iter(|| {
+                for event in &events {
+                    let _ = std::hint::black_box(atree.search(event));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+pub fn search_by_intervals(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search_by_intervals");
+    for intervals in [100, 1_000, 10_000] {
+        let definitions = [AttributeDefinition::integer("age")];
+        let mut atree = ATree::new(&definitions).unwrap();
+        for interval in 0..intervals {
+            let low = interval % 80;
+            let expression = format!("age between {low} and {}", low + interval % 15);
+            atree.insert(&interval, &expression).unwrap();
+        }
+        let events: Vec<_> = (0..100)
+            .map(|age| {
+                let mut builder = atree.make_event();
+                builder.with_integer("age", age).unwrap();
+                builder.build().unwrap()
+            })
+            .collect();
+        group.throughput(Throughput::Elements(events.len() as u64));
+        group.bench_function(BenchmarkId::from_parameter(intervals), |b| {
+            b.iter(|| {
+                for event in &events {
+                    let _ = std::hint::black_box(atree.search(event));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+pub fn search_by_substrings(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search_by_substrings");
+    for patterns in [100, 1_000, 10_000] {
+        let definitions = [AttributeDefinition::string("url")];
+        let mut atree = ATree::new(&definitions).unwrap();
+        for pattern in 0..patterns {
+            let expression = format!(r#"url contains "/section-{pattern}/""#);
+            atree.insert(&pattern, &expression).unwrap();
+        }
+        let events: Vec<_> = (0..100)
+            .map(|i| {
+                let mut builder = atree.make_event();
+                let url = format!("https://www.example.com/section-{}/articles/{i}", i * 37);
+                builder.with_string("url", &url).unwrap();
+                builder.build().unwrap()
+            })
+            .collect();
+        group.throughput(Throughput::Elements(events.len() as u64));
+        group.bench_function(BenchmarkId::from_parameter(patterns), |b| {
+            b.iter(|| {
+                for event in &events {
+                    let _ = std::hint::black_box(atree.search(event));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    insert_expression,
+    search,
+    search_with_files,
+    insert_generated,
+    search_generated,
+    search_by_sharing,
+    search_by_list_size,
+    search_against_baseline,
+    search_by_thresholds,
+    search_by_intervals,
+    search_by_substrings
+);
 criterion_main!(benches);
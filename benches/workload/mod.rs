@@ -0,0 +1,324 @@
+//! Deterministic generators of synthetic rule sets and event streams.
+//!
+//! The generators use their own pseudo-random number generator so that a seed yields the same
+//! workload from one release to the next, whatever the versions of the dependencies.
+
+// Every knob is not used by the benchmarks, but they are there for the contributors validating
+// an optimization on their own workload.
+#![allow(dead_code)]
+
+use a_tree::{ATree, AttributeDefinition, AttributeKind, Event};
+use std::fmt::Debug;
+use std::hash::Hash;
+
+const KINDS: [AttributeKind; 6] = [
+    AttributeKind::Boolean,
+    AttributeKind::Integer,
+    AttributeKind::Float,
+    AttributeKind::String,
+    AttributeKind::IntegerList,
+    AttributeKind::StringList,
+];
+
+/// Shape of a generated workload
+#[derive(Clone, Debug)]
+pub struct WorkloadConfig {
+    seed: u64,
+    attributes: usize,
+    subscriptions: usize,
+    predicates: usize,
+    list_size: usize,
+    domain: usize,
+    sharing: f64,
+    events: usize,
+    density: f64,
+}
+
+impl Default for WorkloadConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0x5eed,
+            attributes: 20,
+            subscriptions: 1_000,
+            predicates: 4,
+            list_size: 5,
+            domain: 100,
+            sharing: 0.5,
+            events: 100,
+            density: 0.8,
+        }
+    }
+}
+
+impl WorkloadConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seed of the pseudo-random number generator.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Number of attributes, whose kinds alternate between boolean, integer, float, string,
+    /// integer list and string list.
+    pub fn with_attributes(mut self, attributes: usize) -> Self {
+        self.attributes = attributes.max(1);
+        self
+    }
+
+    /// Number of expressions.
+    pub fn with_subscriptions(mut self, subscriptions: usize) -> Self {
+        self.subscriptions = subscriptions;
+        self
+    }
+
+    /// Maximum number of predicates of an expression; each expression has between 1 and this
+    /// number of predicates.
+    pub fn with_predicates(mut self, predicates: usize) -> Self {
+        self.predicates = predicates.max(1);
+        self
+    }
+
+    /// Number of values of the lists, in the expressions and in the events.
+    pub fn with_list_size(mut self, list_size: usize) -> Self {
+        self.list_size = list_size.max(1);
+        self
+    }
+
+    /// Number of distinct values of each attribute; the smaller, the more matches.
+    pub fn with_domain(mut self, domain: usize) -> Self {
+        self.domain = domain.max(1);
+        self
+    }
+
+    /// Ratio of the predicates drawn from a small pool common to every expression, from 0 (every
+    /// predicate is random) to 1 (the expressions only share predicates).
+    pub fn with_sharing(mut self, sharing: f64) -> Self {
+        self.sharing = sharing.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Number of events.
+    pub fn with_events(mut self, events: usize) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Ratio of the attributes defined in each event, the others being undefined.
+    pub fn with_density(mut self, density: f64) -> Self {
+        self.density = density.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Generate the attribute definitions, the expressions and the events.
+    pub fn generate(&self) -> Workload {
+        let mut random = SplitMix64(self.seed);
+        let definitions: Vec<_> = (0..self.attributes)
+            .map(|index| {
+                let name = format!("attribute_{index}");
+                match KINDS[index % KINDS.len()] {
+                    AttributeKind::Boolean => AttributeDefinition::boolean(&name),
+                    AttributeKind::Integer => AttributeDefinition::integer(&name),
+                    AttributeKind::Float => AttributeDefinition::float(&name),
+                    AttributeKind::String => AttributeDefinition::string(&name),
+                    AttributeKind::IntegerList => AttributeDefinition::integer_list(&name),
+                    AttributeKind::StringList => AttributeDefinition::string_list(&name),
+                }
+            })
+            .collect();
+
+        let pool: Vec<_> = (0..self.attributes * 2)
+            .map(|_| self.predicate(&mut random, &definitions))
+            .collect();
+        let expressions = (0..self.subscriptions as u64)
+            .map(|id| {
+                let count = 1 + random.below(self.predicates);
+                let predicates: Vec<_> = (0..count)
+                    .map(|_| {
+                        if random.chance(self.sharing) {
+                            pool[random.below(pool.len())].clone()
+                        } else {
+                            self.predicate(&mut random, &definitions)
+                        }
+                    })
+                    .collect();
+                (id, predicates.join(" and "))
+            })
+            .collect();
+
+        let mut events = Vec::with_capacity(self.events);
+        for _ in 0..self.events {
+            let mut event = vec![];
+            for definition in &definitions {
+                if random.chance(self.density) {
+                    let value = self.value(&mut random, definition.kind());
+                    event.push((definition.name().to_owned(), value));
+                }
+            }
+            events.push(event);
+        }
+
+        Workload {
+            definitions,
+            expressions,
+            events,
+        }
+    }
+
+    fn predicate(&self, random: &mut SplitMix64, definitions: &[AttributeDefinition]) -> String {
+        let definition = &definitions[random.below(definitions.len())];
+        let name = definition.name();
+        match definition.kind() {
+            AttributeKind::Boolean if random.chance(0.5) => name.to_owned(),
+            AttributeKind::Boolean => format!("not {name}"),
+            AttributeKind::Integer => match random.below(4) {
+                0 => format!("{name} = {}", random.below(self.domain)),
+                1 => format!("{name} < {}", random.below(self.domain)),
+                2 => format!("{name} > {}", random.below(self.domain)),
+                _ => format!("{name} in [{}]", self.integers(random).join(", ")),
+            },
+            AttributeKind::Float if random.chance(0.5) => {
+                format!("{name} < {:.2}", self.float(random))
+            }
+            AttributeKind::Float => format!("{name} >= {:.2}", self.float(random)),
+            AttributeKind::String if random.chance(0.5) => {
+                format!("{name} = {}", self.string(random))
+            }
+            AttributeKind::String => format!("{name} in [{}]", self.strings(random).join(", ")),
+            AttributeKind::IntegerList => {
+                let operator = list_operator(random);
+                format!("{name} {operator} [{}]", self.integers(random).join(", "))
+            }
+            AttributeKind::StringList => {
+                let operator = list_operator(random);
+                format!("{name} {operator} [{}]", self.strings(random).join(", "))
+            }
+        }
+    }
+
+    fn value(&self, random: &mut SplitMix64, kind: &AttributeKind) -> Value {
+        match kind {
+            AttributeKind::Boolean => Value::Boolean(random.chance(0.5)),
+            AttributeKind::Integer => Value::Integer(random.below(self.domain) as i64),
+            AttributeKind::Float => Value::Float(self.float(random)),
+            AttributeKind::String => Value::String(format!("value-{}", random.below(self.domain))),
+            AttributeKind::IntegerList => Value::IntegerList(
+                (0..self.list_size)
+                    .map(|_| random.below(self.domain) as i64)
+                    .collect(),
+            ),
+            AttributeKind::StringList => Value::StringList(
+                (0..self.list_size)
+                    .map(|_| format!("value-{}", random.below(self.domain)))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn integers(&self, random: &mut SplitMix64) -> Vec<String> {
+        (0..self.list_size)
+            .map(|_| random.below(self.domain).to_string())
+            .collect()
+    }
+
+    /// A float with two decimals, in the range of the integers.
+    fn float(&self, random: &mut SplitMix64) -> f64 {
+        random.below(self.domain * 100) as f64 / 100.0
+    }
+
+    fn string(&self, random: &mut SplitMix64) -> String {
+        format!("\"value-{}\"", random.below(self.domain))
+    }
+
+    fn strings(&self, random: &mut SplitMix64) -> Vec<String> {
+        (0..self.list_size).map(|_| self.string(random)).collect()
+    }
+}
+
+fn list_operator(random: &mut SplitMix64) -> &'static str {
+    ["one of", "none of", "all of"][random.below(3)]
+}
+
+/// Value of an attribute of a generated event
+#[derive(Clone, Debug)]
+pub enum Value {
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    IntegerList(Vec<i64>),
+    StringList(Vec<String>),
+}
+
+/// Generated rule set and event stream; see [`WorkloadConfig::generate()`]
+#[derive(Clone, Debug)]
+pub struct Workload {
+    pub definitions: Vec<AttributeDefinition>,
+    pub expressions: Vec<(u64, String)>,
+    pub events: Vec<Vec<(String, Value)>>,
+}
+
+impl Workload {
+    /// Create a tree holding every expression of the workload.
+    pub fn tree(&self) -> ATree<u64> {
+        let mut atree = ATree::new(&self.definitions).unwrap();
+        for (id, expression) in &self.expressions {
+            atree.insert(id, expression).unwrap();
+        }
+        atree
+    }
+
+    /// Build the events of the workload for a tree; the tree must hold the expressions for the
+    /// strings of the events to be interned.
+    pub fn events<T>(&self, atree: &ATree<T>) -> Vec<Event>
+    where
+        T: Eq + Hash + Clone + Debug,
+    {
+        self.events
+            .iter()
+            .map(|attributes| {
+                let mut builder = atree.make_event();
+                for (name, value) in attributes {
+                    match value {
+                        Value::Boolean(value) => builder.with_boolean(name, *value),
+                        Value::Integer(value) => builder.with_integer(name, *value),
+                        Value::Float(value) => builder.with_f64(name, *value),
+                        Value::String(value) => builder.with_string(name, value),
+                        Value::IntegerList(values) => builder.with_integer_list(name, values),
+                        Value::StringList(values) => {
+                            let values: Vec<_> = values.iter().map(String::as_str).collect();
+                            builder.with_string_list(name, &values)
+                        }
+                    }
+                    .unwrap();
+                }
+                builder.build().unwrap()
+            })
+            .collect()
+    }
+}
+
+/// SplitMix64 pseudo-random number generator, whose sequence is fixed for a seed
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut value = self.0;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        value ^ (value >> 31)
+    }
+
+    /// A number in `0..bound`.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    /// `true` with the given probability.
+    fn chance(&mut self, probability: f64) -> bool {
+        ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+}
@@ -0,0 +1,46 @@
+//! Search generated events against generated expressions on a generated schema, checking the
+//! matches against a straightforward interpreter of each expression.
+//!
+//! The input seeds the random number generator of the strategies of `a_tree::testutil`, so that
+//! every input is a valid schema, expressions and event. The bytes are not passed through to the
+//! strategies, whose rejection sampling never ends once the bytes are exhausted.
+
+#![no_main]
+
+use a_tree::{testutil, ATree};
+use libfuzzer_sys::fuzz_target;
+use proptest::{
+    collection,
+    strategy::{Just, Strategy, ValueTree},
+    test_runner::{Config, RngAlgorithm, TestRng, TestRunner},
+};
+
+fuzz_target!(|seed: [u8; 32]| {
+    let rng = TestRng::from_seed(RngAlgorithm::ChaCha, &seed);
+    let mut runner = TestRunner::new_with_rng(Config::default(), rng);
+    let strategy = testutil::schema().prop_flat_map(|definitions| {
+        (
+            Just(definitions.clone()),
+            collection::vec(testutil::expression_tree(&definitions), 1..20),
+            testutil::event(&definitions),
+        )
+    });
+    let Ok(tree) = strategy.new_tree(&mut runner) else {
+        return;
+    };
+    let (definitions, expressions, event) = tree.current();
+
+    let mut atree = ATree::new(&definitions).unwrap();
+    for (id, expression) in (0u64..).zip(&expressions) {
+        atree.insert(&id, &expression.to_string()).unwrap();
+    }
+    let report = atree.search(&event.build(&atree).unwrap()).unwrap();
+
+    for (id, expression) in (0u64..).zip(&expressions) {
+        assert_eq!(
+            expression.evaluate(&event) == Some(true),
+            report.matches().contains(&&id),
+            "{expression} on {event:?}"
+        );
+    }
+});
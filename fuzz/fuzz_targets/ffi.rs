@@ -0,0 +1,191 @@
+//! Drive the C API with arbitrary sequences of calls and arbitrary bytes for the strings, as the
+//! hosts pass them without validating them first.
+
+#![no_main]
+
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::ptr;
+
+use a_tree_ffi::*;
+use a_tree_fuzz::{Attribute, Value, ATTRIBUTES};
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+enum Operation {
+    Insert {
+        id: u8,
+        expression: Vec<u8>,
+    },
+    InsertWithError {
+        id: u8,
+        expression: Vec<u8>,
+    },
+    Validate {
+        expression: Vec<u8>,
+    },
+    Parse {
+        expression: Vec<u8>,
+    },
+    Delete {
+        id: u8,
+    },
+    Search {
+        attributes: Vec<Attribute>,
+        borrowed: bool,
+    },
+    SearchJson {
+        json: Vec<u8>,
+    },
+    ExportImport,
+}
+
+fuzz_target!(|operations: Vec<Operation>| unsafe {
+    let names: Vec<_> = ATTRIBUTES
+        .iter()
+        .map(|name| c_string(name.as_bytes()))
+        .collect();
+    let types = [
+        AtreeAttributeType::Boolean,
+        AtreeAttributeType::Integer,
+        AtreeAttributeType::Float,
+        AtreeAttributeType::String,
+        AtreeAttributeType::IntegerList,
+        AtreeAttributeType::StringList,
+    ];
+    let definitions: Vec<_> = names
+        .iter()
+        .zip(types)
+        .map(|(name, attr_type)| AtreeAttributeDef {
+            name: name.as_ptr(),
+            attr_type,
+        })
+        .collect();
+    let handle = atree_new(definitions.as_ptr(), definitions.len());
+    assert!(!handle.is_null());
+
+    for operation in operations {
+        match operation {
+            Operation::Insert { id, expression } => {
+                let expression = c_string(&expression);
+                free_result(atree_insert(handle, id.into(), expression.as_ptr()));
+            }
+            Operation::InsertWithError { id, expression } => {
+                let expression = c_string(&expression);
+                let mut error = parse_error();
+                let result =
+                    atree_insert_with_error(handle, id.into(), expression.as_ptr(), &mut error);
+                if !result.success {
+                    atree_parse_error_free(error);
+                }
+                free_result(result);
+            }
+            Operation::Validate { expression } => {
+                let expression = c_string(&expression);
+                let mut error = parse_error();
+                if !atree_validate_expression(handle, expression.as_ptr(), &mut error) {
+                    assert!(error.offset + error.length <= expression.as_bytes().len());
+                    atree_parse_error_free(error);
+                }
+            }
+            Operation::Parse { expression } => {
+                let expression = c_string(&expression);
+                atree_ast_free(atree_parse(handle, expression.as_ptr()));
+            }
+            Operation::Delete { id } => atree_delete(handle, id.into()),
+            Operation::Search {
+                attributes,
+                borrowed,
+            } => {
+                let builder = atree_event_builder_new(handle);
+                for attribute in &attributes {
+                    set(builder, attribute);
+                }
+                if borrowed {
+                    atree_search_result_free(atree_search_borrowed(handle, builder));
+                    atree_event_builder_free(builder);
+                } else {
+                    atree_search_result_free(atree_search(handle, builder));
+                }
+            }
+            Operation::SearchJson { json } => {
+                let json = c_string(&json);
+                let event = atree_event_from_json(handle, json.as_ptr());
+                if !event.is_null() {
+                    atree_search_result_free(atree_search_event(handle, event));
+                }
+                atree_event_free(event);
+            }
+            Operation::ExportImport => {
+                let json = atree_export_json(handle);
+                assert!(!json.is_null());
+                let imported = atree_import_json(json);
+                assert!(
+                    !imported.is_null(),
+                    "{:?}",
+                    CStr::from_ptr(json).to_string_lossy()
+                );
+                atree_free(imported);
+                atree_free_string(json);
+            }
+        }
+    }
+
+    atree_free(handle);
+});
+
+/// The strings stop at their first NUL byte, like they would in C.
+fn c_string(bytes: &[u8]) -> CString {
+    let end = bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(bytes.len());
+    CString::new(&bytes[..end]).unwrap()
+}
+
+fn parse_error() -> AtreeParseError {
+    AtreeParseError {
+        code: AtreeParseErrorCode::NoError,
+        line: 0,
+        column: 0,
+        offset: 0,
+        length: 0,
+        message: ptr::null_mut(),
+    }
+}
+
+unsafe fn free_result(result: AtreeResult) {
+    if !result.success {
+        atree_free_error(result.error_message);
+    }
+}
+
+unsafe fn set(builder: *mut c_void, attribute: &Attribute) {
+    let name = c_string(attribute.name().as_bytes());
+    let name = name.as_ptr();
+    let result = match &attribute.value {
+        Value::Boolean(value) => atree_event_builder_with_boolean(builder, name, *value),
+        Value::Integer(value) => atree_event_builder_with_integer(builder, name, *value),
+        Value::Float { number, scale } => {
+            atree_event_builder_with_float(builder, name, *number, *scale)
+        }
+        Value::Double(value) => atree_event_builder_with_double(builder, name, *value),
+        Value::String(value) => {
+            let value = c_string(value.as_bytes());
+            atree_event_builder_with_string(builder, name, value.as_ptr())
+        }
+        Value::IntegerList(values) => {
+            atree_event_builder_with_integer_list(builder, name, values.as_ptr(), values.len())
+        }
+        Value::StringList(values) => {
+            let values: Vec<_> = values
+                .iter()
+                .map(|value| c_string(value.as_bytes()))
+                .collect();
+            let pointers: Vec<*const c_char> = values.iter().map(|value| value.as_ptr()).collect();
+            atree_event_builder_with_string_list(builder, name, pointers.as_ptr(), pointers.len())
+        }
+        Value::Undefined => atree_event_builder_with_undefined(builder, name),
+    };
+    free_result(result);
+}
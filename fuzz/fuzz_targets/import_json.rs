@@ -0,0 +1,21 @@
+//! Import arbitrary JSON documents as rules exported by `ATree::export_json()`.
+
+#![no_main]
+
+use a_tree::ATree;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|json: &str| {
+    let Ok(atree) = ATree::<u64>::import_json(json) else {
+        return;
+    };
+
+    let exported = atree.export_json().unwrap();
+    let imported = ATree::<u64>::import_json(&exported).unwrap();
+    assert_eq!(
+        atree.stats().subscriptions(),
+        imported.stats().subscriptions()
+    );
+    let event = atree.make_event().build().unwrap();
+    atree.search(&event).unwrap();
+});
@@ -0,0 +1,30 @@
+//! Parse arbitrary expressions, as submitted by the self-serve users.
+
+#![no_main]
+
+use a_tree::ATree;
+use a_tree_fuzz::definitions;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|expression: &str| {
+    let mut atree = ATree::<u64>::new(&definitions()).unwrap();
+
+    let validation = atree.validate_expression(expression);
+    if let Err(error) = &validation {
+        // The span is reported to the users, so it must slice the expression.
+        if let Some(span) = error.span(expression) {
+            assert!(expression.get(span).is_some(), "{error}");
+        }
+    }
+    assert_eq!(
+        validation.is_ok(),
+        atree.parse_expression(expression).is_ok()
+    );
+
+    if atree.insert(&0, expression).is_ok() {
+        assert!(validation.is_ok());
+        let event = atree.make_event().build().unwrap();
+        atree.search(&event).unwrap();
+        atree.delete(&0);
+    }
+});
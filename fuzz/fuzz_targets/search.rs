@@ -0,0 +1,41 @@
+//! Search arbitrary events against arbitrary expressions, checking the matches against the
+//! evaluation of each expression on its own.
+
+#![no_main]
+
+use a_tree::ATree;
+use a_tree_fuzz::{definitions, event, Attribute};
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    expressions: Vec<String>,
+    attributes: Vec<Attribute>,
+}
+
+fuzz_target!(|input: Input| {
+    let mut atree = ATree::new(&definitions()).unwrap();
+    let mut ids = vec![];
+    for (id, expression) in (0u64..).zip(&input.expressions) {
+        if atree.insert(&id, expression).is_ok() {
+            ids.push(id);
+        }
+    }
+    let Ok(event) = event(&atree, &input.attributes) else {
+        return;
+    };
+
+    let report = atree.search(&event).unwrap();
+    let matches = report.matches();
+    for id in &ids {
+        let explanation = atree.explain(id, &event).unwrap();
+        assert_eq!(
+            explanation.result() == Some(true),
+            matches.contains(&id),
+            "{:?}",
+            atree.expression(id)
+        );
+    }
+    assert!(matches.iter().all(|id| ids.contains(id)));
+});
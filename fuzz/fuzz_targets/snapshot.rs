@@ -0,0 +1,45 @@
+//! Load corrupted snapshots, as read back from a truncated or tampered file.
+//!
+//! The snapshots are written from arbitrary rules and then altered, since random bytes rarely get
+//! past the header.
+
+#![no_main]
+
+use a_tree::ATree;
+use a_tree_fuzz::definitions;
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    expressions: Vec<String>,
+    /// Bytes to overwrite, by offset modulo the length of the snapshot
+    edits: Vec<(u16, u8)>,
+    truncate: Option<u16>,
+}
+
+fuzz_target!(|input: Input| {
+    let mut atree = ATree::new(&definitions()).unwrap();
+    for (id, expression) in (0u64..).zip(&input.expressions) {
+        let _ = atree.insert(&id, expression);
+    }
+    let mut snapshot = atree.to_snapshot();
+    for (offset, byte) in input.edits {
+        let offset = usize::from(offset) % snapshot.len();
+        snapshot[offset] = byte;
+    }
+    if let Some(length) = input.truncate {
+        snapshot.truncate(usize::from(length));
+    }
+
+    let Ok(atree) = ATree::<u64>::from_snapshot(&snapshot) else {
+        return;
+    };
+    let reloaded = ATree::<u64>::from_snapshot(&atree.to_snapshot()).unwrap();
+    assert_eq!(
+        atree.stats().subscriptions(),
+        reloaded.stats().subscriptions()
+    );
+    let event = atree.make_event().build().unwrap();
+    atree.search(&event).unwrap();
+});
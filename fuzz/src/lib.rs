@@ -0,0 +1,79 @@
+//! Schema and structured inputs shared by the fuzz targets.
+
+use a_tree::{ATree, AttributeDefinition, Event, EventError};
+use arbitrary::Arbitrary;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// Attributes of the fuzzed trees, one of each kind so that every operator can be valid
+pub const ATTRIBUTES: [&str; 6] = [
+    "private",
+    "exchange_id",
+    "bidfloor",
+    "country",
+    "segment_ids",
+    "deal_ids",
+];
+
+pub fn definitions() -> Vec<AttributeDefinition> {
+    vec![
+        AttributeDefinition::boolean(ATTRIBUTES[0]),
+        AttributeDefinition::integer(ATTRIBUTES[1]),
+        AttributeDefinition::float(ATTRIBUTES[2]),
+        AttributeDefinition::string(ATTRIBUTES[3]),
+        AttributeDefinition::integer_list(ATTRIBUTES[4]),
+        AttributeDefinition::string_list(ATTRIBUTES[5]),
+    ]
+}
+
+/// Value set on an attribute of an event, whatever its kind, so that the mismatches are fuzzed
+/// too
+#[derive(Arbitrary, Debug)]
+pub enum Value {
+    Boolean(bool),
+    Integer(i64),
+    Float { number: i64, scale: u32 },
+    Double(f64),
+    String(String),
+    IntegerList(Vec<i64>),
+    StringList(Vec<String>),
+    Undefined,
+}
+
+/// Attribute of an event, picked among [`ATTRIBUTES`]
+#[derive(Arbitrary, Debug)]
+pub struct Attribute {
+    index: u8,
+    pub value: Value,
+}
+
+impl Attribute {
+    pub fn name(&self) -> &'static str {
+        ATTRIBUTES[usize::from(self.index) % ATTRIBUTES.len()]
+    }
+}
+
+/// Build an event, skipping the values rejected by the builder.
+pub fn event<T>(atree: &ATree<T>, attributes: &[Attribute]) -> Result<Event, EventError>
+where
+    T: Eq + Hash + Clone + Debug,
+{
+    let mut builder = atree.make_event();
+    for attribute in attributes {
+        let name = attribute.name();
+        let _ = match &attribute.value {
+            Value::Boolean(value) => builder.with_boolean(name, *value),
+            Value::Integer(value) => builder.with_integer(name, *value),
+            Value::Float { number, scale } => builder.with_float(name, *number, *scale),
+            Value::Double(value) => builder.with_f64(name, *value),
+            Value::String(value) => builder.with_string(name, value),
+            Value::IntegerList(values) => builder.with_integer_list(name, values),
+            Value::StringList(values) => {
+                let values: Vec<_> = values.iter().map(String::as_str).collect();
+                builder.with_string_list(name, &values)
+            }
+            Value::Undefined => builder.with_undefined(name),
+        };
+    }
+    builder.build()
+}
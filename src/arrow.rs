@@ -0,0 +1,429 @@
+use crate::events::{AttributeKind, Event, EventBuilder, EventError};
+use arrow_array::{
+    cast::AsArray,
+    types::{
+        Decimal128Type, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type,
+        UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+    },
+    Array, ArrayRef, ArrowPrimitiveType, OffsetSizeTrait, RecordBatch,
+};
+use arrow_schema::DataType;
+use rust_decimal::Decimal;
+use std::{fmt::Display, ops::Range};
+use thiserror::Error;
+
+/// The errors that can happen while building [`Event`]s from an Arrow [`RecordBatch`]
+#[derive(Error, PartialEq, Debug)]
+pub enum RecordBatchError {
+    #[error("{name:?}: cannot map a column of type {data_type} onto {expected:?}")]
+    UnsupportedType {
+        name: String,
+        expected: AttributeKind,
+        data_type: DataType,
+    },
+    #[error("{name:?}: cannot coerce {found} at row {row} into {expected:?}")]
+    Coercion {
+        name: String,
+        expected: AttributeKind,
+        row: usize,
+        found: String,
+    },
+    #[error("failed with {0:?}")]
+    Event(EventError),
+}
+
+type Getter<'b, V> = Box<dyn Fn(usize) -> Result<V, String> + 'b>;
+type Ranges<'b> = Box<dyn Fn(usize) -> Range<usize> + 'b>;
+type Setter<'b> = Box<dyn Fn(&mut EventBuilder<'_>, usize) -> Result<(), RecordBatchError> + 'b>;
+
+pub(crate) fn events_from_record_batch<'atree>(
+    make_event: impl Fn() -> EventBuilder<'atree>,
+    batch: &RecordBatch,
+) -> Result<Vec<Event>, RecordBatchError> {
+    let schema = batch.schema();
+    let mut template = make_event();
+    let mut setters = Vec::with_capacity(batch.num_columns());
+    for (field, column) in schema.fields().iter().zip(batch.columns()) {
+        let name = field.name().as_str();
+        let Ok(expected) = template.kind_of(name) else {
+            template
+                .unknown_attribute(name)
+                .map_err(RecordBatchError::Event)?;
+            continue;
+        };
+        setters.push(setter(name, expected, column)?);
+    }
+
+    (0..batch.num_rows())
+        .map(|row| {
+            let mut builder = make_event();
+            for set in &setters {
+                set(&mut builder, row)?;
+            }
+            builder.build().map_err(RecordBatchError::Event)
+        })
+        .collect()
+}
+
+fn setter<'b>(
+    name: &'b str,
+    expected: AttributeKind,
+    column: &'b ArrayRef,
+) -> Result<Setter<'b>, RecordBatchError> {
+    let unsupported = |data_type: &DataType| RecordBatchError::UnsupportedType {
+        name: name.to_owned(),
+        expected: expected.clone(),
+        data_type: data_type.clone(),
+    };
+    let array = column.as_ref();
+    let set: Setter<'b> = match expected {
+        AttributeKind::Boolean => {
+            let values = array
+                .as_boolean_opt()
+                .ok_or_else(|| unsupported(array.data_type()))?;
+            Box::new(move |builder, row| {
+                builder
+                    .with_boolean(name, values.value(row))
+                    .map_err(RecordBatchError::Event)
+            })
+        }
+        AttributeKind::Integer => {
+            let values = integers(array).ok_or_else(|| unsupported(array.data_type()))?;
+            Box::new(move |builder, row| {
+                let value = values(row)
+                    .map_err(|found| coercion(name, AttributeKind::Integer, row, found))?;
+                builder
+                    .with_integer(name, value)
+                    .map_err(RecordBatchError::Event)
+            })
+        }
+        AttributeKind::Float => {
+            let values = floats(array).ok_or_else(|| unsupported(array.data_type()))?;
+            Box::new(move |builder, row| {
+                let value = values(row)
+                    .map_err(|found| coercion(name, AttributeKind::Float, row, found))?;
+                builder
+                    .with_decimal(name, value)
+                    .map_err(RecordBatchError::Event)
+            })
+        }
+        AttributeKind::String => {
+            let values = strings(array).ok_or_else(|| unsupported(array.data_type()))?;
+            Box::new(move |builder, row| {
+                let value = values(row)
+                    .map_err(|found| coercion(name, AttributeKind::String, row, found))?;
+                builder
+                    .with_string(name, value)
+                    .map_err(RecordBatchError::Event)
+            })
+        }
+        AttributeKind::IntegerList => {
+            let (ranges, child) = lists(array).ok_or_else(|| unsupported(array.data_type()))?;
+            let values = integers(child).ok_or_else(|| unsupported(array.data_type()))?;
+            Box::new(move |builder, row| {
+                let values = elements(child, ranges(row), &values)
+                    .map_err(|found| coercion(name, AttributeKind::IntegerList, row, found))?;
+                builder
+                    .with_integer_list(name, &values)
+                    .map_err(RecordBatchError::Event)
+            })
+        }
+        AttributeKind::StringList => {
+            let (ranges, child) = lists(array).ok_or_else(|| unsupported(array.data_type()))?;
+            let values = strings(child).ok_or_else(|| unsupported(array.data_type()))?;
+            Box::new(move |builder, row| {
+                let values = elements(child, ranges(row), &values)
+                    .map_err(|found| coercion(name, AttributeKind::StringList, row, found))?;
+                builder
+                    .with_string_list(name, &values)
+                    .map_err(RecordBatchError::Event)
+            })
+        }
+    };
+
+    Ok(Box::new(move |builder, row| {
+        if array.is_null(row) {
+            builder
+                .with_undefined(name)
+                .map_err(RecordBatchError::Event)
+        } else {
+            set(builder, row)
+        }
+    }))
+}
+
+fn coercion(name: &str, expected: AttributeKind, row: usize, found: String) -> RecordBatchError {
+    RecordBatchError::Coercion {
+        name: name.to_owned(),
+        expected,
+        row,
+        found,
+    }
+}
+
+fn elements<V>(
+    child: &dyn Array,
+    range: Range<usize>,
+    values: &Getter<'_, V>,
+) -> Result<Vec<V>, String> {
+    range
+        .enumerate()
+        .map(|(index, element)| {
+            if child.is_null(element) {
+                Err(format!("null at index {index}"))
+            } else {
+                values(element).map_err(|found| format!("{found} at index {index}"))
+            }
+        })
+        .collect()
+}
+
+fn integers(array: &dyn Array) -> Option<Getter<'_, i64>> {
+    match array.data_type() {
+        DataType::Int8 => primitive::<Int8Type, _>(array, convert),
+        DataType::Int16 => primitive::<Int16Type, _>(array, convert),
+        DataType::Int32 => primitive::<Int32Type, _>(array, convert),
+        DataType::Int64 => primitive::<Int64Type, _>(array, convert),
+        DataType::UInt8 => primitive::<UInt8Type, _>(array, convert),
+        DataType::UInt16 => primitive::<UInt16Type, _>(array, convert),
+        DataType::UInt32 => primitive::<UInt32Type, _>(array, convert),
+        DataType::UInt64 => primitive::<UInt64Type, _>(array, convert),
+        _ => None,
+    }
+}
+
+fn floats(array: &dyn Array) -> Option<Getter<'_, Decimal>> {
+    match array.data_type() {
+        DataType::Float32 => {
+            primitive::<Float32Type, _>(array, |value| Decimal::try_from(value).ok())
+        }
+        DataType::Float64 => {
+            primitive::<Float64Type, _>(array, |value| Decimal::try_from(value).ok())
+        }
+        DataType::Decimal128(_, scale) => {
+            let scale = u32::try_from(*scale).ok()?;
+            primitive::<Decimal128Type, _>(array, move |value| {
+                Decimal::try_from_i128_with_scale(value, scale).ok()
+            })
+        }
+        _ => {
+            let values = integers(array)?;
+            Some(Box::new(move |index| values(index).map(Decimal::from)))
+        }
+    }
+}
+
+fn strings(array: &dyn Array) -> Option<Getter<'_, &str>> {
+    match array.data_type() {
+        DataType::Utf8 => {
+            let values = array.as_string_opt::<i32>()?;
+            Some(Box::new(move |index| Ok(values.value(index))))
+        }
+        DataType::LargeUtf8 => {
+            let values = array.as_string_opt::<i64>()?;
+            Some(Box::new(move |index| Ok(values.value(index))))
+        }
+        DataType::Utf8View => {
+            let values = array.as_string_view_opt()?;
+            Some(Box::new(move |index| Ok(values.value(index))))
+        }
+        _ => None,
+    }
+}
+
+fn lists(array: &dyn Array) -> Option<(Ranges<'_>, &dyn Array)> {
+    match array.data_type() {
+        DataType::List(_) => offsets::<i32>(array),
+        DataType::LargeList(_) => offsets::<i64>(array),
+        _ => None,
+    }
+}
+
+fn offsets<O: OffsetSizeTrait>(array: &dyn Array) -> Option<(Ranges<'_>, &dyn Array)> {
+    let list = array.as_list_opt::<O>()?;
+    let offsets = list.value_offsets();
+    Some((
+        Box::new(move |row| offsets[row].as_usize()..offsets[row + 1].as_usize()),
+        list.values().as_ref(),
+    ))
+}
+
+fn primitive<'b, T, V>(
+    array: &'b dyn Array,
+    convert: impl Fn(T::Native) -> Option<V> + 'b,
+) -> Option<Getter<'b, V>>
+where
+    T: ArrowPrimitiveType,
+    T::Native: Display,
+{
+    let values = array.as_primitive_opt::<T>()?;
+    Some(Box::new(move |index| {
+        let value = values.value(index);
+        convert(value).ok_or_else(|| format!("{} {value}", array.data_type()))
+    }))
+}
+
+#[inline]
+fn convert<N: TryInto<i64>>(value: N) -> Option<i64> {
+    value.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{events::AttributeDefinition, ATree};
+    use arrow_array::{
+        builder::{ListBuilder, StringBuilder},
+        BooleanArray, Float64Array, Int32Array, ListArray, StringArray, UInt64Array,
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn can_build_one_event_per_row() {
+        let atree = build_atree();
+        let batch = RecordBatch::try_from_iter([
+            (
+                "private",
+                Arc::new(BooleanArray::from(vec![Some(true), None, Some(false)])) as ArrayRef,
+            ),
+            (
+                "exchange_id",
+                Arc::new(Int32Array::from(vec![1, 2, 3])) as ArrayRef,
+            ),
+            (
+                "bidfloor",
+                Arc::new(Float64Array::from(vec![1.5, 2.0, 0.25])) as ArrayRef,
+            ),
+            (
+                "country",
+                Arc::new(StringArray::from(vec![Some("CA"), Some("US"), None])) as ArrayRef,
+            ),
+            (
+                "segment_ids",
+                Arc::new(ListArray::from_iter_primitive::<Int64Type, _, _>([
+                    Some(vec![Some(1), Some(2)]),
+                    None,
+                    Some(vec![]),
+                ])) as ArrayRef,
+            ),
+            ("deals", Arc::new(string_lists()) as ArrayRef),
+        ])
+        .unwrap();
+
+        let events = atree.events_from_record_batch(&batch).unwrap();
+
+        assert_eq!(3, events.len());
+    }
+
+    #[test]
+    fn can_search_the_events_of_a_record_batch() {
+        let mut atree = build_atree();
+        atree
+            .insert(&1u64, r#"private and deals one of ["deal-1"]"#)
+            .unwrap();
+        atree.insert(&2u64, "exchange_id = 2").unwrap();
+        let batch = RecordBatch::try_from_iter([
+            (
+                "private",
+                Arc::new(BooleanArray::from(vec![true, true, true])) as ArrayRef,
+            ),
+            (
+                "exchange_id",
+                Arc::new(Int32Array::from(vec![1, 2, 3])) as ArrayRef,
+            ),
+            ("deals", Arc::new(string_lists()) as ArrayRef),
+        ])
+        .unwrap();
+        let events = atree.events_from_record_batch(&batch).unwrap();
+
+        let reports = atree.search_batch(&events).unwrap();
+
+        let matches: Vec<_> = reports.iter().map(|report| report.matches()).collect();
+        assert_eq!(vec![&[&1u64] as &[_], &[&2u64], &[]], matches);
+    }
+
+    #[test]
+    fn return_an_error_on_columns_without_a_matching_attribute() {
+        let atree = build_atree();
+        let batch = RecordBatch::try_from_iter([(
+            "unknown",
+            Arc::new(Int32Array::from(vec![1])) as ArrayRef,
+        )])
+        .unwrap();
+
+        let result = atree.events_from_record_batch(&batch);
+
+        assert_eq!(
+            Err(RecordBatchError::Event(EventError::NonExistingAttribute(
+                "unknown".to_string()
+            ))),
+            result.map(|_| ())
+        );
+    }
+
+    #[test]
+    fn return_an_error_on_columns_with_an_unsupported_type() {
+        let atree = build_atree();
+        let batch = RecordBatch::try_from_iter([(
+            "private",
+            Arc::new(Int32Array::from(vec![1])) as ArrayRef,
+        )])
+        .unwrap();
+
+        let result = atree.events_from_record_batch(&batch);
+
+        assert_eq!(
+            Err(RecordBatchError::UnsupportedType {
+                name: "private".to_string(),
+                expected: AttributeKind::Boolean,
+                data_type: DataType::Int32
+            }),
+            result.map(|_| ())
+        );
+    }
+
+    #[test]
+    fn return_an_error_with_the_row_of_the_value_that_cannot_be_coerced() {
+        let atree = build_atree();
+        let batch = RecordBatch::try_from_iter([(
+            "exchange_id",
+            Arc::new(UInt64Array::from(vec![1, u64::MAX])) as ArrayRef,
+        )])
+        .unwrap();
+
+        let result = atree.events_from_record_batch(&batch);
+
+        assert_eq!(
+            Err(RecordBatchError::Coercion {
+                name: "exchange_id".to_string(),
+                expected: AttributeKind::Integer,
+                row: 1,
+                found: format!("UInt64 {}", u64::MAX)
+            }),
+            result.map(|_| ())
+        );
+    }
+
+    fn string_lists() -> ListArray {
+        let mut builder = ListBuilder::new(StringBuilder::new());
+        builder.values().append_value("deal-1");
+        builder.append(true);
+        builder.values().append_value("deal-2");
+        builder.values().append_value("deal-3");
+        builder.append(true);
+        builder.append(false);
+        builder.finish()
+    }
+
+    fn build_atree() -> ATree<u64> {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::float("bidfloor"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::integer_list("segment_ids"),
+            AttributeDefinition::string_list("deals"),
+        ];
+        ATree::new(&definitions).unwrap()
+    }
+}
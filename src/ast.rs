@@ -1,4 +1,4 @@
-use crate::predicates::Predicate;
+use crate::{config::ConjunctionOrder, predicates::Predicate, template::Placeholder};
 use std::cmp::{max, min};
 use std::hash::{DefaultHasher, Hash, Hasher};
 
@@ -10,6 +10,8 @@ pub enum Node {
     Or(TreeNode, TreeNode),
     Not(TreeNode),
     Value(Predicate),
+    Constant(bool),
+    Placeholder(Placeholder),
 }
 
 #[derive(PartialEq, Clone, Debug)]
@@ -26,25 +28,36 @@ pub enum Operator {
 }
 
 impl OptimizedNode {
+    /// Return the ID of the expression, which is the same for the expressions that only differ by
+    /// the order of their operands unless the operands of the `and` are evaluated in the written
+    /// order.
     #[inline]
-    pub fn id(&self) -> u64 {
+    pub fn id(&self, order: ConjunctionOrder) -> u64 {
         match self {
             Self::And(left, right) => {
                 let mut hasher = DefaultHasher::new();
                 Operator::And.hash(&mut hasher);
 
-                let left_id = left.id();
-                let right_id = right.id();
-                min(left_id, right_id).hash(&mut hasher);
-                max(left_id, right_id).hash(&mut hasher);
+                let left_id = left.id(order);
+                let right_id = right.id(order);
+                match order {
+                    ConjunctionOrder::Cost => {
+                        min(left_id, right_id).hash(&mut hasher);
+                        max(left_id, right_id).hash(&mut hasher);
+                    }
+                    ConjunctionOrder::LeftToRight => {
+                        left_id.hash(&mut hasher);
+                        right_id.hash(&mut hasher);
+                    }
+                }
                 hasher.finish()
             }
             Self::Or(left, right) => {
                 let mut hasher = DefaultHasher::new();
                 Operator::Or.hash(&mut hasher);
 
-                let left_id = left.id();
-                let right_id = right.id();
+                let left_id = left.id(order);
+                let right_id = right.id(order);
                 min(left_id, right_id).hash(&mut hasher);
                 max(left_id, right_id).hash(&mut hasher);
                 hasher.finish()
@@ -67,6 +80,32 @@ impl OptimizedNode {
 }
 
 impl Node {
+    /// Combine the expressions with an `and`, folding the `true` and `false` literals.
+    pub fn and(left: Self, right: Self) -> Self {
+        match (left, right) {
+            (Self::Constant(false), _) | (_, Self::Constant(false)) => Self::Constant(false),
+            (Self::Constant(true), other) | (other, Self::Constant(true)) => other,
+            (left, right) => Self::And(Box::new(left), Box::new(right)),
+        }
+    }
+
+    /// Combine the expressions with an `or`, folding the `true` and `false` literals.
+    pub fn or(left: Self, right: Self) -> Self {
+        match (left, right) {
+            (Self::Constant(true), _) | (_, Self::Constant(true)) => Self::Constant(true),
+            (Self::Constant(false), other) | (other, Self::Constant(false)) => other,
+            (left, right) => Self::Or(Box::new(left), Box::new(right)),
+        }
+    }
+
+    /// Negate the expression, folding the `true` and `false` literals.
+    pub fn not(expression: Self) -> Self {
+        match expression {
+            Self::Constant(value) => Self::Constant(!value),
+            expression => Self::Not(Box::new(expression)),
+        }
+    }
+
     #[inline]
     pub fn optimize(self) -> OptimizedNode {
         self.zero_suppression_filter(false)
@@ -94,6 +133,12 @@ impl Node {
                 Box::new(right.zero_suppression_filter(false)),
             ),
             (Self::Value(predicate), _) => OptimizedNode::Value(predicate),
+            (Self::Constant(_), _) => {
+                unreachable!("the constants are folded while parsing the expression")
+            }
+            (Self::Placeholder(_), _) => {
+                unreachable!("the placeholders are bound before inserting the expression")
+            }
         }
     }
 }
@@ -111,6 +156,34 @@ mod tests {
         },
     };
 
+    #[test]
+    fn fold_the_constants() {
+        let attributes = define_attributes();
+        let a_predicate = Predicate::new(&attributes, "private", PredicateKind::Variable).unwrap();
+
+        assert_eq!(
+            Node::Constant(false),
+            Node::and(Node::Constant(false), value!(a_predicate.clone()))
+        );
+        assert_eq!(
+            value!(a_predicate.clone()),
+            Node::and(value!(a_predicate.clone()), Node::Constant(true))
+        );
+        assert_eq!(
+            Node::Constant(true),
+            Node::or(value!(a_predicate.clone()), Node::Constant(true))
+        );
+        assert_eq!(
+            value!(a_predicate.clone()),
+            Node::or(Node::Constant(false), value!(a_predicate.clone()))
+        );
+        assert_eq!(Node::Constant(false), Node::not(Node::Constant(true)));
+        assert_eq!(
+            not!(value!(a_predicate.clone())),
+            Node::not(value!(a_predicate))
+        );
+    }
+
     #[test]
     fn can_optimize_a_negated_or_expression() {
         let attributes = define_attributes();
@@ -305,7 +378,10 @@ mod tests {
             optimized_node::value!(another_predicate)
         );
 
-        assert_ne!(expression.id(), another_expression.id());
+        assert_ne!(
+            expression.id(ConjunctionOrder::Cost),
+            another_expression.id(ConjunctionOrder::Cost)
+        );
     }
 
     #[test]
@@ -323,7 +399,10 @@ mod tests {
             optimized_node::value!(another_predicate)
         );
 
-        assert_eq!(expression.id(), another_expression.id());
+        assert_eq!(
+            expression.id(ConjunctionOrder::Cost),
+            another_expression.id(ConjunctionOrder::Cost)
+        );
     }
 
     #[test]
@@ -341,7 +420,35 @@ mod tests {
             optimized_node::value!(a_predicate)
         );
 
-        assert_eq!(expression.id(), another_expression.id());
+        assert_eq!(
+            expression.id(ConjunctionOrder::Cost),
+            another_expression.id(ConjunctionOrder::Cost)
+        );
+    }
+
+    #[test]
+    fn return_different_ids_on_and_expressions_in_another_order_when_evaluated_left_to_right() {
+        let attributes = define_attributes();
+        let a_predicate = Predicate::new(&attributes, "private", PredicateKind::Variable).unwrap();
+        let another_predicate =
+            Predicate::new(&attributes, "test", PredicateKind::Variable).unwrap();
+        let expression = optimized_node::and!(
+            optimized_node::value!(a_predicate.clone()),
+            optimized_node::value!(another_predicate.clone())
+        );
+        let another_expression = optimized_node::and!(
+            optimized_node::value!(another_predicate),
+            optimized_node::value!(a_predicate)
+        );
+
+        assert_eq!(
+            expression.id(ConjunctionOrder::Cost),
+            another_expression.id(ConjunctionOrder::Cost)
+        );
+        assert_ne!(
+            expression.id(ConjunctionOrder::LeftToRight),
+            another_expression.id(ConjunctionOrder::LeftToRight)
+        );
     }
 
     fn define_attributes() -> AttributeTable {
@@ -1,14 +1,55 @@
+#[cfg(feature = "arrow")]
+use crate::arrow::{self, RecordBatchError};
+#[cfg(feature = "metrics")]
+use crate::metrics::{Counters, SearchMetrics, TreeMetrics};
+#[cfg(feature = "openrtb")]
+use crate::openrtb::OpenRtbMapping;
+#[cfg(feature = "profiling")]
+use crate::profiling::{self, EvaluationCounter, EvaluationTimer, PredicateTiming};
 use crate::{
     ast::*,
+    audit::{self, AuditReport},
+    baseline::BaselineReport,
+    cache::{ParseCache, ParseCacheStats},
+    cluster::{self, ClusterReport},
+    compilation::CompilationCache,
+    config::{ATreeConfig, ConjunctionOrder, DeletePolicy, DuplicateIdPolicy},
     error::ATreeError,
     evaluation::EvaluationResult,
-    events::{AttributeDefinition, AttributeTable, Event, EventBuilder},
+    events::{AttributeDefinition, AttributeId, AttributeTable, Event, EventBuilder},
+    executor::{Executor, TreeExecutor},
+    explain::{self, Explanation},
+    expression::{Expression, Literal, PredicateExpression},
+    loader::{self, FileFormat, LoadReport, Progress},
+    metadata::{Metadata, Subscription},
+    metrics::Recorder,
+    observer::{Observers, TreeObserver},
     parser,
-    predicates::Predicate,
-    strings::StringTable,
+    predicates::{Predicate, PredicateKind},
+    range::PredicateIndex,
+    snapshot::{self, SnapshotError, SnapshotId},
+    stats::{self, TreeStats},
+    strings::{FrozenStringTable, InternedString, LocalStringTable, StringTable},
+    template::Template,
+};
+#[cfg(feature = "json")]
+use crate::{
+    interchange,
+    json::{self, JsonError},
 };
 use slab::Slab;
-use std::{collections::HashMap, fmt::Debug, hash::Hash};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Debug,
+    fs::File,
+    hash::Hash,
+    io::{self, BufReader},
+    num::NonZeroUsize,
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+    time::Instant,
+};
 
 type NodeId = usize;
 type ExpressionId = u64;
@@ -25,9 +66,28 @@ pub struct ATree<T> {
     attributes: AttributeTable,
     roots: Vec<NodeId>,
     max_level: usize,
-    predicates: Vec<NodeId>,
+    predicates: PredicateIndex,
     expression_to_node: HashMap<ExpressionId, NodeId>,
     nodes_by_ids: HashMap<T, NodeId>,
+    always_matching: HashSet<T>,
+    subscriptions: HashMap<T, Subscription>,
+    /// The deleted subscriptions whose nodes are left in the tree until the next compaction; see
+    /// [`DeletePolicy::Tombstone`]
+    tombstones: HashSet<T>,
+    /// Whether an ID was attached to several expressions, in which case the matches are
+    /// deduplicated
+    shared_ids: bool,
+    parse_cache: ParseCache,
+    compilation_cache: CompilationCache,
+    /// The amount of insertions and deletions since the creation of the tree
+    generation: u64,
+    config: ATreeConfig,
+    #[cfg(feature = "metrics")]
+    counters: Counters,
+    #[cfg(feature = "profiling")]
+    predicate_timing: bool,
+    observers: Observers<T>,
+    executor: TreeExecutor,
 }
 
 impl<T: Eq + Hash + Clone + Debug> ATree<T> {
@@ -63,18 +123,44 @@ impl<T: Eq + Hash + Clone + Debug> ATree<T> {
     /// let result = ATree::<u64>::new(&definitions);
     /// assert!(result.is_err());
     /// ```
+    #[inline]
     pub fn new(definitions: &'_ [AttributeDefinition]) -> Result<Self, ATreeError<'_>> {
-        let attributes = AttributeTable::new(definitions).map_err(ATreeError::Event)?;
-        let strings = StringTable::new();
+        Self::with_config(definitions, ATreeConfig::default())
+    }
+
+    /// Create a new [`ATree`] like [`ATree::new()`] but with the specified [`ATreeConfig`].
+    pub fn with_config(
+        definitions: &'_ [AttributeDefinition],
+        config: ATreeConfig,
+    ) -> Result<Self, ATreeError<'_>> {
+        let mut attributes = AttributeTable::new(definitions).map_err(ATreeError::Event)?;
+        let mut strings = StringTable::new();
+        attributes.intern_dictionaries(&mut strings);
+        let predicates = PredicateIndex::with_capacity(Self::DEFAULT_PREDICATES)
+            .with_high_cardinality(attributes.high_cardinality());
         Ok(Self {
             attributes,
             strings,
             max_level: 1,
             roots: Vec::with_capacity(Self::DEFAULT_ROOTS),
-            predicates: Vec::with_capacity(Self::DEFAULT_PREDICATES),
+            predicates,
             nodes: Slab::with_capacity(Self::DEFAULT_NODES),
             expression_to_node: HashMap::new(),
             nodes_by_ids: HashMap::new(),
+            always_matching: HashSet::new(),
+            subscriptions: HashMap::new(),
+            tombstones: HashSet::new(),
+            shared_ids: false,
+            parse_cache: ParseCache::new(config.parse_cache_capacity()),
+            compilation_cache: CompilationCache::default(),
+            generation: 0,
+            config,
+            #[cfg(feature = "metrics")]
+            counters: Counters::default(),
+            #[cfg(feature = "profiling")]
+            predicate_timing: false,
+            observers: Observers::new(),
+            executor: TreeExecutor::default(),
         })
     }
 
@@ -99,15 +185,334 @@ impl<T: Eq + Hash + Clone + Debug> ATree<T> {
         subscription_id: &T,
         expression: &'a str,
     ) -> Result<(), ATreeError<'a>> {
-        let ast = parser::parse(expression, &self.attributes, &mut self.strings)
+        self.insert_with_metadata(subscription_id, expression, Metadata::default())
+    }
+
+    /// Insert an arbitrary boolean expression inside the [`ATree`] along with its [`Metadata`].
+    ///
+    /// The metadata can be retrieved afterwards with [`ATree::metadata()`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "insert",
+            level = "debug",
+            skip_all,
+            fields(subscription_id = ?subscription_id, nodes = tracing::field::Empty)
+        )
+    )]
+    pub fn insert_with_metadata<'a>(
+        &'a mut self,
+        subscription_id: &T,
+        expression: &'a str,
+        metadata: Metadata,
+    ) -> Result<(), ATreeError<'a>> {
+        let ast = self.parse(expression)?;
+        self.insert_ast(subscription_id, expression, ast, metadata)?;
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("nodes", self.nodes.len());
+        Ok(())
+    }
+
+    /// Parse an arbitrary boolean expression whose values are left as `?` placeholders, to insert
+    /// it many times with different values through [`ATree::insert_template()`].
+    ///
+    /// The template is only parsed once; the subscriptions bound to it share the nodes of the
+    /// [`ATree`] like any other identical sub-expressions.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition, Literal};
+    ///
+    /// let definitions = [
+    ///     AttributeDefinition::integer("price"),
+    ///     AttributeDefinition::string("country"),
+    /// ];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// let template = atree.template("price > ? and country in ?").unwrap();
+    ///
+    /// let parameters = [
+    ///     Literal::Integer(10),
+    ///     Literal::StringList(vec!["CA".to_owned(), "US".to_owned()]),
+    /// ];
+    /// atree.insert_template(&1u64, &template, &parameters).unwrap();
+    ///
+    /// assert_eq!(
+    ///     Some(r#"price > 10 and country in ["CA", "US"]"#),
+    ///     atree.expression(&1u64)
+    /// );
+    /// ```
+    pub fn template<'a>(&mut self, expression: &'a str) -> Result<Template, ATreeError<'a>> {
+        parser::parse_template(expression, &self.attributes, &mut self.strings)
+            .and_then(|ast| Template::new(expression, ast))
+            .map_err(|error| {
+                self.record_parse_failure();
+                ATreeError::ParseError(error)
+            })
+    }
+
+    /// Insert the expression of a [`Template`] with its placeholders bound to the `parameters`,
+    /// in order; see [`ATree::template()`].
+    ///
+    /// The bound expression is kept as the expression of the subscription.
+    pub fn insert_template(
+        &mut self,
+        subscription_id: &T,
+        template: &Template,
+        parameters: &[Literal],
+    ) -> Result<(), ATreeError<'static>> {
+        self.insert_template_with_metadata(
+            subscription_id,
+            template,
+            parameters,
+            Metadata::default(),
+        )
+    }
+
+    /// Insert the expression of a [`Template`] along with its [`Metadata`]; see
+    /// [`ATree::insert_template()`].
+    pub fn insert_template_with_metadata(
+        &mut self,
+        subscription_id: &T,
+        template: &Template,
+        parameters: &[Literal],
+        metadata: Metadata,
+    ) -> Result<(), ATreeError<'static>> {
+        let (expression, ast) = template.bind(parameters, &self.attributes, &mut self.strings)?;
+        self.insert_ast(subscription_id, &expression, ast, metadata)
+    }
+
+    /// Insert many arbitrary boolean expressions, parsing them on up to `threads` threads.
+    ///
+    /// The expressions are parsed in parallel and then inserted one after the other in the order
+    /// of `subscriptions`. The result of each insertion is returned at the same index: an invalid
+    /// expression does not prevent the next ones from being inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::num::NonZeroUsize;
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [
+    ///     AttributeDefinition::boolean("private"),
+    ///     AttributeDefinition::integer("exchange_id"),
+    /// ];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// let subscriptions = [(1u64, "private"), (2, "exchange_id ="), (3, "exchange_id = 5")];
+    ///
+    /// let results = atree.insert_many(&subscriptions, NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert!(results[0].is_ok());
+    /// assert!(results[1].is_err());
+    /// assert!(results[2].is_ok());
+    /// ```
+    pub fn insert_many<'a>(
+        &mut self,
+        subscriptions: &[(T, &'a str)],
+        threads: NonZeroUsize,
+    ) -> Vec<Result<(), ATreeError<'a>>> {
+        self.insert_many_with_progress(subscriptions, threads, |_| {})
+    }
+
+    /// Insert many arbitrary boolean expressions like [`ATree::insert_many()`], reporting the
+    /// [`Progress`] of the insertion.
+    ///
+    /// The expressions are processed in batches: the expressions of a batch are parsed and
+    /// type-checked in parallel, then merged into the tree on the calling thread. `on_progress`
+    /// is called on the calling thread after each of these phases.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::num::NonZeroUsize;
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::integer("exchange_id")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// let subscriptions = [(1u64, "exchange_id = 1"), (2, "exchange_id = 2")];
+    ///
+    /// let mut merged = 0;
+    /// atree.insert_many_with_progress(&subscriptions, NonZeroUsize::MIN, |progress| {
+    ///     assert_eq!(Some(2), progress.total());
+    ///     merged = progress.merged();
+    /// });
+    ///
+    /// assert_eq!(2, merged);
+    /// ```
+    pub fn insert_many_with_progress<'a>(
+        &mut self,
+        subscriptions: &[(T, &'a str)],
+        threads: NonZeroUsize,
+        mut on_progress: impl FnMut(Progress),
+    ) -> Vec<Result<(), ATreeError<'a>>> {
+        let mut progress = Progress::new(Some(subscriptions.len()));
+        let mut results = Vec::with_capacity(subscriptions.len());
+        for batch in subscriptions.chunks(loader::BATCH_SIZE) {
+            let expressions: Vec<_> = batch.iter().map(|(_, expression)| *expression).collect();
+            let parsed = loader::parse_all(self, &expressions, threads);
+            progress.record_parsed(batch.len());
+            on_progress(progress);
+
+            results.extend(batch.iter().zip(parsed).map(
+                |((subscription_id, expression), parsed)| {
+                    let ast = match parsed {
+                        Ok(Some(ast)) => ast,
+                        Ok(None) => self.parse(expression)?,
+                        Err(error) => {
+                            self.record_parse_failure();
+                            return Err(ATreeError::ParseError(error));
+                        }
+                    };
+                    self.insert_ast(subscription_id, expression, ast, Metadata::default())
+                },
+            ));
+            progress.record_merged(batch.len());
+            on_progress(progress);
+        }
+        results
+    }
+
+    /// Parse an expression with the strings of this [`ATree`], going through the parse cache.
+    fn parse<'a>(&mut self, expression: &'a str) -> Result<Node, ATreeError<'a>> {
+        if let Some(ast) = self.parse_cache.get(expression) {
+            return Ok(ast);
+        }
+        if let Some(ast) = self.compiled(expression) {
+            self.parse_cache.insert(expression, &ast);
+            return Ok(ast);
+        }
+        match parser::parse(expression, &self.attributes, &mut self.strings) {
+            Ok(ast) => {
+                self.parse_cache.insert(expression, &ast);
+                Ok(ast)
+            }
+            Err(error) => {
+                self.record_parse_failure();
+                Err(ATreeError::ParseError(error))
+            }
+        }
+    }
+
+    /// Decode the AST of the expression from the compilation cache, if it is there.
+    pub(crate) fn compiled(&mut self, expression: &str) -> Option<Node> {
+        if self.compilation_cache.is_empty() {
+            return None;
+        }
+        self.compilation_cache
+            .get(expression, &self.attributes, &mut self.strings)
+    }
+
+    /// Check that an arbitrary boolean expression could be inserted without modifying the
+    /// [`ATree`].
+    ///
+    /// The returned error can be located in the expression with [`ATreeError::span()`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::boolean("private")];
+    /// let atree = ATree::<u64>::new(&definitions).unwrap();
+    /// assert!(atree.validate_expression("not private").is_ok());
+    /// assert!(atree.validate_expression("private = 1").is_err());
+    /// ```
+    pub fn validate_expression<'a>(&self, expression: &'a str) -> Result<(), ATreeError<'a>> {
+        let mut strings = FrozenStringTable::new(&self.strings);
+        parser::parse(expression, &self.attributes, &mut strings)
+            .map(|_| ())
+            .map_err(ATreeError::ParseError)
+    }
+
+    /// Parse an arbitrary boolean expression without inserting it, to analyze its structure.
+    ///
+    /// The expression goes through the same grammar and checks as [`ATree::insert()`]; its
+    /// strings are not interned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition, Expression, Literal, PredicateOperator};
+    ///
+    /// let definitions = [AttributeDefinition::integer("exchange_id")];
+    /// let atree = ATree::<u64>::new(&definitions).unwrap();
+    /// let Expression::Predicate(predicate) = atree.parse_expression("exchange_id = 1").unwrap()
+    /// else {
+    ///     unreachable!();
+    /// };
+    /// assert_eq!("exchange_id", predicate.attribute());
+    /// assert_eq!(PredicateOperator::Equal, predicate.operator());
+    /// assert_eq!(Some(&Literal::Integer(1)), predicate.value());
+    /// ```
+    pub fn parse_expression<'a>(&self, expression: &'a str) -> Result<Expression, ATreeError<'a>> {
+        let mut strings = LocalStringTable::default();
+        let ast = parser::parse(expression, &self.attributes, &mut strings)
             .map_err(ATreeError::ParseError)?;
-        let ast = ast.optimize();
-        self.insert_root(subscription_id, ast);
+        let names = self
+            .attributes
+            .names()
+            .map(|(name, id)| (id, name))
+            .collect();
+        Ok(Expression::new(ast, &names, &strings))
+    }
+
+    /// Insert an expression that was already parsed with the strings of this [`ATree`].
+    pub(crate) fn insert_ast<'a>(
+        &mut self,
+        subscription_id: &T,
+        expression: &str,
+        ast: Node,
+        metadata: Metadata,
+    ) -> Result<(), ATreeError<'a>> {
+        // The nodes of a deleted subscription would still match for it once its ID is reused.
+        if self.tombstones.remove(subscription_id) {
+            if let Some(node_id) = self.nodes_by_ids.get(subscription_id) {
+                self.delete_node(subscription_id, *node_id);
+            }
+        }
+        let mut shared = Vec::new();
+        if self.subscriptions.contains_key(subscription_id) {
+            match self.config.duplicate_ids() {
+                DuplicateIdPolicy::Allow => {
+                    self.shared_ids = true;
+                    if let Some(previous) = self.subscriptions.remove(subscription_id) {
+                        shared = previous.shared;
+                        shared.push(previous.expression);
+                    }
+                }
+                // A tombstone would hide the new expression as well as the previous one.
+                DuplicateIdPolicy::Replace => self.remove_now(subscription_id),
+                DuplicateIdPolicy::Reject => {
+                    return Err(ATreeError::DuplicateId(format!("{subscription_id:?}")));
+                }
+            }
+        }
+        match ast {
+            // The expressions that were folded to a literal do not need any node.
+            Node::Constant(true) => {
+                self.always_matching.insert(subscription_id.clone());
+            }
+            Node::Constant(false) => {}
+            ast => self.insert_root(subscription_id, ast.optimize()),
+        }
+        self.subscriptions.insert(
+            subscription_id.clone(),
+            Subscription {
+                expression: expression.into(),
+                metadata,
+                shared,
+            },
+        );
+        self.generation += 1;
+        #[cfg(feature = "metrics")]
+        self.counters.record_insert();
+        self.observers.on_insert(subscription_id, expression);
         Ok(())
     }
 
     fn insert_root(&mut self, subscription_id: &T, root: OptimizedNode) {
-        let expression_id = root.id();
+        let expression_id = root.id(self.config.conjunction_order());
         if let Some(node_id) = self.expression_to_node.get(&expression_id) {
             add_subscription_id(
                 subscription_id,
@@ -116,6 +521,9 @@ impl<T: Eq + Hash + Clone + Debug> ATree<T> {
                 &mut self.nodes_by_ids,
             );
             increment_use_count(*node_id, &mut self.nodes);
+            // A predicate that is only the non-access child of `and` nodes is not evaluated
+            // unless it has subscriptions of its own.
+            add_predicate(*node_id, &self.nodes, &mut self.predicates);
             return;
         }
 
@@ -130,11 +538,7 @@ impl<T: Eq + Hash + Clone + Debug> ATree<T> {
                 let rnode = ATreeNode::RNode(RNode {
                     level: 1 + std::cmp::max(left_entry.node.level(), right_entry.node.level()),
                     operator: if is_and { Operator::And } else { Operator::Or },
-                    children: if left_entry.cost > right_entry.cost {
-                        vec![right_id, left_id]
-                    } else {
-                        vec![left_id, right_id]
-                    },
+                    children: self.children(left_id, right_id, is_and),
                 });
                 let node_id = insert_node(
                     &mut self.expression_to_node,
@@ -149,6 +553,7 @@ impl<T: Eq + Hash + Clone + Debug> ATree<T> {
                         left_id,
                         right_id,
                         node_id,
+                        self.config.conjunction_order(),
                         &mut self.nodes,
                         &mut self.predicates,
                     );
@@ -161,6 +566,7 @@ impl<T: Eq + Hash + Clone + Debug> ATree<T> {
                 node_id
             }
             OptimizedNode::Value(value) => {
+                self.keep_texts(&value);
                 let lnode = ATreeNode::lnode(&value);
                 let node_id = insert_node(
                     &mut self.expression_to_node,
@@ -170,7 +576,7 @@ impl<T: Eq + Hash + Clone + Debug> ATree<T> {
                     Some(subscription_id.clone()),
                     cost,
                 );
-                self.predicates.push(node_id);
+                self.predicates.insert(node_id, &value);
                 node_id
             }
         };
@@ -180,7 +586,7 @@ impl<T: Eq + Hash + Clone + Debug> ATree<T> {
     }
 
     fn insert_node(&mut self, node: OptimizedNode) -> NodeId {
-        let expression_id = node.id();
+        let expression_id = node.id(self.config.conjunction_order());
         if let Some(node_id) = self.expression_to_node.get(&expression_id) {
             change_rnode_to_inode(*node_id, &mut self.nodes);
             increment_use_count(*node_id, &mut self.nodes);
@@ -199,11 +605,7 @@ impl<T: Eq + Hash + Clone + Debug> ATree<T> {
                     parents: vec![],
                     level: 1 + std::cmp::max(left_entry.node.level(), right_entry.node.level()),
                     operator: if is_and { Operator::And } else { Operator::Or },
-                    children: if left_entry.cost > right_entry.cost {
-                        vec![right_id, left_id]
-                    } else {
-                        vec![left_id, right_id]
-                    },
+                    children: self.children(left_id, right_id, is_and),
                 };
                 let inode = ATreeNode::INode(inode);
                 let node_id = insert_node(
@@ -219,6 +621,7 @@ impl<T: Eq + Hash + Clone + Debug> ATree<T> {
                         left_id,
                         right_id,
                         node_id,
+                        self.config.conjunction_order(),
                         &mut self.nodes,
                         &mut self.predicates,
                     );
@@ -231,6 +634,7 @@ impl<T: Eq + Hash + Clone + Debug> ATree<T> {
                 node_id
             }
             OptimizedNode::Value(node) => {
+                self.keep_texts(&node);
                 let lnode = ATreeNode::lnode(&node);
                 insert_node(
                     &mut self.expression_to_node,
@@ -244,1046 +648,4167 @@ impl<T: Eq + Hash + Clone + Debug> ATree<T> {
         }
     }
 
-    /// Create a new [`EventBuilder`] to be able to generate an [`Event`] that will be usable for
-    /// finding the matching arbitrary boolean expressions inside the [`ATree`] via the
-    /// [`ATree::search()`] function.
-    #[inline]
-    pub fn make_event(&'_ self) -> EventBuilder<'_> {
-        EventBuilder::new(&self.attributes, &self.strings)
+    /// Have the events keep the text of the attributes matched against a pattern.
+    fn keep_texts(&mut self, predicate: &Predicate) {
+        if matches!(
+            predicate.kind(),
+            PredicateKind::Pattern(_, _) | PredicateKind::Fuzzy(_, _)
+        ) {
+            self.attributes.keep_text(predicate.attribute());
+        }
     }
 
-    /// Search the [`ATree`] for arbitrary boolean expressions that match the [`Event`].
-    pub fn search(&'_ self, event: &Event) -> Result<Report<'_, T>, ATreeError<'_>> {
-        let mut results = EvaluationResult::new(self.nodes.len());
-        let mut matches = Vec::with_capacity(50);
-
-        // Since the predicates will already be evaluated and their parents will be put into the
-        // queues, then there is no need to keep a queue for them.
-        let mut queues = vec![Vec::with_capacity(50); self.max_level - 1];
-        process_predicates(
-            &self.predicates,
-            &self.nodes,
-            event,
-            &mut matches,
-            &mut results,
-            &mut queues,
-        );
+    /// Order the children of an operator in which they are evaluated.
+    fn children(&self, left_id: NodeId, right_id: NodeId, is_and: bool) -> Vec<NodeId> {
+        let in_written_order = is_and
+            && matches!(
+                self.config.conjunction_order(),
+                ConjunctionOrder::LeftToRight
+            );
+        if !in_written_order && self.nodes[left_id].cost > self.nodes[right_id].cost {
+            vec![right_id, left_id]
+        } else {
+            vec![left_id, right_id]
+        }
+    }
 
-        for level in 0..queues.len() {
-            while let Some((node_id, node)) = queues[level].pop() {
-                if results.is_evaluated(node_id) {
-                    continue;
-                }
+    /// Export the schema and the subscriptions of the [`ATree`] as a JSON document that can be
+    /// loaded with [`ATree::import_json()`].
+    ///
+    /// The document has the following format where the subscriptions are in no particular order,
+    /// the `id` is the serialized subscription ID while the `expiry` and the `created_at` are Unix
+    /// timestamps in seconds or `null` (see [`Metadata`]):
+    ///
+    /// ```json
+    /// {
+    ///     "version": 1,
+    ///     "schema": [
+    ///         {"name": "private", "type": "boolean"},
+    ///         {"name": "deals", "type": "string_list"}
+    ///     ],
+    ///     "subscriptions": [
+    ///         {
+    ///             "id": 1,
+    ///             "expression": "private and deals one of [\"deal-1\"]",
+    ///             "tags": ["campaign-1"],
+    ///             "expiry": 1767225600,
+    ///             "owner": "ads",
+    ///             "author": "bob",
+    ///             "created_at": 1764547200
+    ///         }
+    ///     ]
+    /// }
+    /// ```
+    ///
+    /// The attribute types are `boolean`, `integer`, `float`, `string`, `integer_list` and
+    /// `string_list`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::boolean("private")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "private").unwrap();
+    ///
+    /// let json = atree.export_json().unwrap();
+    /// let copy = ATree::<u64>::import_json(&json).unwrap();
+    /// assert_eq!(Some("private"), copy.expression(&1));
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn export_json(&self) -> Result<String, JsonError>
+    where
+        T: serde::Serialize,
+    {
+        let mut json = Vec::new();
+        self.write_json(&mut json)?;
+        Ok(String::from_utf8(json).expect("serde_json only writes UTF-8"))
+    }
 
-                let result = evaluate_node(
-                    node_id,
-                    event,
-                    node,
-                    &self.nodes,
-                    &mut results,
-                    &mut matches,
-                );
-                add_matches(result, node, &mut matches);
+    /// Write the document of [`ATree::export_json()`] to a writer, one subscription at a time,
+    /// without holding the whole document in memory.
+    #[cfg(feature = "json")]
+    pub fn write_json(&self, writer: &mut impl io::Write) -> Result<(), JsonError>
+    where
+        T: serde::Serialize,
+    {
+        interchange::write(
+            &self.attributes.definitions(),
+            self.subscriptions.iter(),
+            writer,
+        )
+    }
 
-                if node.is_root() {
-                    continue;
-                }
+    /// Create an [`ATree`] from a JSON document produced by [`ATree::export_json()`].
+    ///
+    /// The `tags`, `expiry`, `owner`, `author` and `created_at` of the subscriptions can be
+    /// omitted. The import stops at the first subscription that cannot be inserted and reports
+    /// its ID.
+    #[cfg(feature = "json")]
+    pub fn import_json(json: &str) -> Result<Self, JsonError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        interchange::import(json)
+    }
 
-                for parent_id in node.parents() {
-                    let entry = &self.nodes[*parent_id];
-                    let is_evaluated = results.is_evaluated(*parent_id);
-                    if !is_evaluated
-                        && matches!(entry.operator(), Operator::And)
-                        && !result.unwrap_or(true)
-                    {
-                        results.set_result(*parent_id, Some(false));
-                        continue;
-                    }
+    /// Serialize the configuration, the schema and the subscriptions of the [`ATree`] to a
+    /// compact binary snapshot that can be loaded with [`ATree::from_snapshot()`].
+    ///
+    /// The snapshot starts with a header holding the format version and the version of the crate
+    /// that wrote it so that the snapshots written by a previous version of the crate can be
+    /// migrated when they are loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::boolean("private")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "private").unwrap();
+    ///
+    /// let snapshot = atree.to_snapshot();
+    /// let copy = ATree::<u64>::from_snapshot(&snapshot).unwrap();
+    /// assert_eq!(Some("private"), copy.expression(&1));
+    /// ```
+    pub fn to_snapshot(&self) -> Vec<u8>
+    where
+        T: SnapshotId,
+    {
+        snapshot::write(
+            &self.config,
+            &self.attributes.definitions(),
+            self.subscriptions.iter(),
+        )
+    }
 
-                    if !is_evaluated {
-                        queues[entry.level() - 2].push((*parent_id, entry));
-                    }
-                }
-            }
-        }
+    /// Return a fingerprint of the snapshot produced by [`ATree::to_snapshot()`].
+    ///
+    /// The snapshots, and thus the fingerprints, of two trees with the same configuration,
+    /// schema and subscriptions are identical whatever the order in which the subscriptions were
+    /// inserted, so the fingerprint can be used to address the snapshots distributed to a fleet.
+    /// It is stable across platforms and crate versions as long as the snapshot format does not
+    /// change.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::integer("exchange_id")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "exchange_id = 1").unwrap();
+    /// atree.insert(&2u64, "exchange_id = 2").unwrap();
+    /// let mut other = ATree::new(&definitions).unwrap();
+    /// other.insert(&2u64, "exchange_id = 2").unwrap();
+    /// other.insert(&1u64, "exchange_id = 1").unwrap();
+    ///
+    /// assert_eq!(atree.fingerprint(), other.fingerprint());
+    /// assert_eq!(atree.to_snapshot(), other.to_snapshot());
+    /// let snapshot = atree.to_snapshot();
+    /// assert_eq!(Ok(atree.fingerprint()), ATree::<u64>::snapshot_fingerprint(&snapshot));
+    /// ```
+    pub fn fingerprint(&self) -> u64
+    where
+        T: SnapshotId,
+    {
+        snapshot::fingerprint(
+            &self.config,
+            &self.attributes.definitions(),
+            self.subscriptions.iter(),
+        )
+    }
 
-        Ok(Report::new(matches))
+    /// Return the fingerprint of a snapshot produced by [`ATree::to_snapshot()`] without
+    /// loading it; see [`ATree::fingerprint()`].
+    ///
+    /// The snapshots written with a previous format are migrated first, so their fingerprint is
+    /// the one of the tree they load.
+    pub fn snapshot_fingerprint(bytes: &[u8]) -> Result<u64, SnapshotError>
+    where
+        T: SnapshotId,
+    {
+        snapshot::fingerprint_of::<T>(bytes)
     }
 
-    #[inline]
-    /// Delete the specified expression
-    pub fn delete(&mut self, subscription_id: &T) {
-        if let Some(node_id) = self.nodes_by_ids.get(subscription_id) {
-            self.delete_node(subscription_id, *node_id);
-        }
+    /// Create an [`ATree`] from a snapshot produced by [`ATree::to_snapshot()`].
+    ///
+    /// The snapshots written with a former format version are migrated to the current one; the
+    /// snapshots written with a newer format version are rejected with
+    /// [`SnapshotError::UnsupportedVersion`].
+    pub fn from_snapshot(bytes: &[u8]) -> Result<Self, SnapshotError>
+    where
+        T: SnapshotId,
+    {
+        snapshot::read(bytes)
     }
 
-    #[inline]
-    fn delete_node(&mut self, subscription_id: &T, node_id: NodeId) {
-        let children = decrement_use_count(
-            subscription_id,
-            node_id,
-            &mut self.nodes,
-            &mut self.expression_to_node,
-            &mut self.roots,
-            &mut self.predicates,
-            &mut self.nodes_by_ids,
-            &mut self.max_level,
-        );
+    /// Insert the subscriptions of a rules file, skipping the invalid lines.
+    ///
+    /// The file is read in batches whose expressions are parsed in parallel; the subscriptions
+    /// are then inserted in the order of the file. The lines that cannot be decoded or parsed are
+    /// reported in the [`LoadReport`] while the other lines are still inserted, and only the I/O
+    /// errors abort the load. See [`FileFormat`] for the supported formats.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition, FileFormat};
+    ///
+    /// let path = std::env::temp_dir().join("a-tree-load-from-path-example.csv");
+    /// std::fs::write(&path, "id,expression\n1,private\n2,exchange_id = 5\n3,private =\n").unwrap();
+    ///
+    /// let definitions = [
+    ///     AttributeDefinition::boolean("private"),
+    ///     AttributeDefinition::integer("exchange_id"),
+    /// ];
+    /// let mut atree = ATree::<u64>::new(&definitions).unwrap();
+    /// let report = atree.load_from_path(&path, FileFormat::Csv).unwrap();
+    /// # std::fs::remove_file(&path).unwrap();
+    ///
+    /// assert_eq!(2, report.loaded());
+    /// assert_eq!(1, report.errors().len());
+    /// ```
+    pub fn load_from_path(
+        &mut self,
+        path: impl AsRef<Path>,
+        format: FileFormat,
+    ) -> io::Result<LoadReport>
+    where
+        T: FromStr,
+    {
+        self.load_from_path_with_progress(path, format, |_| {})
+    }
 
-        if let Some(children) = children {
-            for child in children {
-                self.delete_node(subscription_id, child);
-            }
-        }
+    /// Insert the subscriptions of a rules file like [`ATree::load_from_path()`], reporting the
+    /// [`Progress`] of the load after the parsing and the merge of each batch of lines.
+    ///
+    /// The total amount of subscriptions is not known while the file is read.
+    pub fn load_from_path_with_progress(
+        &mut self,
+        path: impl AsRef<Path>,
+        format: FileFormat,
+        on_progress: impl FnMut(Progress),
+    ) -> io::Result<LoadReport>
+    where
+        T: FromStr,
+    {
+        let file = File::open(path)?;
+        loader::load_with_progress(self, BufReader::new(file), format, on_progress)
     }
 
-    /// Export the [`ATree`] to the Graphviz format.
-    pub fn to_graphviz(&self) -> String {
-        const DEFAULT_CAPACITY: usize = 100_000;
-        let mut builder = String::with_capacity(DEFAULT_CAPACITY);
-        builder.push_str("digraph {\n");
-        builder.push_str("rankdir = TB;\n");
-        builder.push_str(r#"node [shape = "record"];"#);
-        builder.push('\n');
-        let mut relations = Vec::with_capacity(DEFAULT_CAPACITY);
-        let mut levels = vec![vec![]; self.max_level];
-        for (id, entry) in &self.nodes {
-            match &entry.node {
-                ATreeNode::LNode(LNode {
-                    parents, predicate, ..
-                }) => {
-                    let node = format!(
-                        r#"node_{id} [label = "{{{id} | level: {} | {predicate} | subscriptions: {:?} | l-node}}", style = "rounded"];"#,
-                        entry.level(),
-                        entry.subscription_ids
-                    );
-                    levels[entry.level() - 1].push((id, node));
-
-                    for parent_id in parents {
-                        relations.push(format!("node_{id} -> node_{parent_id};"));
-                    }
-                }
-                ATreeNode::INode(INode {
-                    children,
-                    parents,
-                    operator,
-                    ..
-                }) => {
-                    let node = format!(
-                        r#"node_{id} [label = "{{{id} | level: {} | {operator:#?} | subscriptions: {:?} | i-node}}"];"#,
-                        entry.level(),
-                        entry.subscription_ids
-                    );
-                    levels[entry.level() - 1].push((id, node));
-
-                    for parent_id in parents {
-                        relations.push(format!("node_{id} -> node_{parent_id};"));
-                    }
-
-                    for child_id in children {
-                        relations.push(format!("node_{id} -> node_{child_id};"));
-                    }
-                }
-                ATreeNode::RNode(RNode {
-                    children, operator, ..
-                }) => {
-                    let node = format!(
-                        r#"node_{id} [label = "{{{id} | level: {} | {operator:#?} | subscriptions: {:?} | r-node}}"];"#,
-                        entry.level(),
-                        entry.subscription_ids
-                    );
-                    levels[entry.level() - 1].push((id, node));
-
-                    for child_id in children {
-                        relations.push(format!("node_{id} -> node_{child_id};"));
-                    }
-                }
-            }
-        }
+    /// Write the compiled form of the expressions of the subscriptions to a file, returning the
+    /// amount of expressions written.
+    ///
+    /// Only the expressions that have lists or patterns are compiled ahead since their strings
+    /// have to be interned and their lists sorted. The expressions that were not read from a
+    /// previous cache are parsed again to be compiled. The entries are keyed by the hash of their
+    /// expression so the file stays valid as long as the attributes used by its expressions keep
+    /// their type.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let path = std::env::temp_dir().join("a-tree-compilation-cache-example.bin");
+    /// let definitions = [AttributeDefinition::integer("exchange_id")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "exchange_id in [1, 2, 3]").unwrap();
+    /// atree.insert(&2u64, "exchange_id = 5").unwrap();
+    /// assert_eq!(1, atree.save_compilation_cache(&path).unwrap());
+    ///
+    /// let mut restarted = ATree::new(&definitions).unwrap();
+    /// assert_eq!(1, restarted.load_compilation_cache(&path).unwrap());
+    /// # std::fs::remove_file(&path).unwrap();
+    /// restarted.insert(&1u64, "exchange_id in [1, 2, 3]").unwrap();
+    /// ```
+    pub fn save_compilation_cache(&self, path: impl AsRef<Path>) -> io::Result<usize> {
+        let mut buffer = Vec::new();
+        let expressions = self
+            .subscriptions
+            .values()
+            .map(|subscription| subscription.expression.as_ref());
+        let written = self
+            .compilation_cache
+            .write(expressions, &self.attributes, &mut buffer);
+        std::fs::write(path, buffer)?;
+        Ok(written)
+    }
 
-        builder.push_str("\n// nodes\n");
-        for entries in levels.into_iter().rev() {
-            for (_, node) in entries.iter() {
-                builder.push_str(node);
-                builder.push('\n');
-            }
+    /// Read a file written by [`ATree::save_compilation_cache()`] so that the expressions found
+    /// in it are decoded instead of being parsed when they are inserted, returning the amount of
+    /// expressions read.
+    ///
+    /// A file that was written with another version of its format is rejected with
+    /// [`io::ErrorKind::InvalidData`]; its expressions are then simply parsed again. An expression
+    /// whose attributes changed since the file was written is parsed again as well.
+    pub fn load_compilation_cache(&mut self, path: impl AsRef<Path>) -> io::Result<usize> {
+        let bytes = std::fs::read(path)?;
+        self.compilation_cache.read(&bytes)
+    }
 
-            builder.push_str("{rank = same; ");
-            for (id, _) in entries {
-                builder.push_str(&format!("node_{id}; "));
-            }
-            builder.push_str("};\n");
-        }
+    /// Return the tables needed to parse the expressions without modifying the [`ATree`].
+    #[inline]
+    pub(crate) const fn parsing_tables(&self) -> (&AttributeTable, &StringTable) {
+        (&self.attributes, &self.strings)
+    }
 
-        builder.push_str("\n// edges\n");
-        for relation in relations {
-            builder.push_str(&relation);
-            builder.push('\n');
-        }
+    #[inline]
+    pub(crate) fn record_parse_failure(&self) {
+        #[cfg(feature = "metrics")]
+        self.counters.record_parse_failure();
+    }
 
-        builder.push('}');
-        builder
+    /// Return the statistics of the parse cache enabled with [`ATreeConfig::with_parse_cache()`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, ATreeConfig, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::integer("exchange_id")];
+    /// let config = ATreeConfig::new().with_parse_cache(1_000);
+    /// let mut atree = ATree::with_config(&definitions, config).unwrap();
+    /// for id in 0..10u64 {
+    ///     atree.insert(&id, "exchange_id = 5").unwrap();
+    /// }
+    ///
+    /// let stats = atree.parse_cache_stats();
+    /// assert_eq!(9, stats.hits());
+    /// assert_eq!(1, stats.misses());
+    /// ```
+    #[inline]
+    pub fn parse_cache_stats(&self) -> ParseCacheStats {
+        self.parse_cache.stats()
     }
-}
 
-#[inline]
-#[allow(clippy::too_many_arguments)]
-fn decrement_use_count<T: Eq + Hash>(
-    subscription_id: &T,
-    node_id: NodeId,
-    nodes: &mut Slab<Entry<T>>,
-    expression_to_node: &mut HashMap<ExpressionId, NodeId>,
-    roots: &mut Vec<NodeId>,
-    predicates: &mut Vec<NodeId>,
-    nodes_by_ids: &mut HashMap<T, NodeId>,
-    max_level: &mut usize,
-) -> Option<Vec<NodeId>> {
-    let node = &mut nodes[node_id];
-    node.use_count -= 1;
-    let mut children = None;
-    node.subscription_ids.retain(|x| *x != *subscription_id);
-    nodes_by_ids.remove(subscription_id);
-    if node.use_count == 0 {
-        if !node.is_leaf() {
-            children = Some(node.children().to_vec());
+    /// Return the amount of subscriptions, nodes and strings of the [`ATree`] along with an
+    /// estimation of its memory usage.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [
+    ///     AttributeDefinition::boolean("private"),
+    ///     AttributeDefinition::integer("exchange_id"),
+    /// ];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "private and exchange_id = 1").unwrap();
+    /// atree.insert(&2u64, "private").unwrap();
+    ///
+    /// let stats = atree.stats();
+    /// assert_eq!(2, stats.subscriptions());
+    /// assert_eq!(2, stats.predicates());
+    /// assert_eq!(3, stats.nodes());
+    /// ```
+    pub fn stats(&self) -> TreeStats {
+        TreeStats {
+            subscriptions: self.subscriptions.len(),
+            nodes: self.nodes.len(),
+            predicates: self
+                .nodes
+                .iter()
+                .filter(|(_, entry)| entry.is_leaf())
+                .count(),
+            roots: self.roots.len(),
+            max_level: self.max_level,
+            strings: self.strings.len(),
+            memory_usage: self.memory_usage(),
         }
-        let expression_id = node.id;
-        roots.retain(|x| *x != node_id);
-        predicates.retain(|x| *x != node_id);
-        *max_level = get_max_level(roots, nodes);
-        expression_to_node.remove(&expression_id);
-        nodes.remove(node_id);
     }
 
-    children
-}
-
-#[inline]
-fn insert_node<T>(
-    expression_to_node: &mut HashMap<ExpressionId, NodeId>,
-    nodes: &mut Slab<Entry<T>>,
-    expression_id: &ExpressionId,
-    node: ATreeNode,
-    subscription_id: Option<T>,
-    cost: u64,
-) -> NodeId {
-    let entry = Entry::new(*expression_id, node, subscription_id, cost);
-    let node_id = nodes.insert(entry);
-    if expression_to_node.insert(*expression_id, node_id).is_some() {
-        unreachable!("{expression_id} is already present; this is a bug");
+    /// Return an estimation of the bytes used by the [`ATree`].
+    ///
+    /// The estimation includes the reserved but unused capacity of the internal collections; the
+    /// subscription IDs are only counted for their inline size and the ASTs of the parse cache
+    /// are not counted.
+    pub fn memory_usage(&self) -> usize {
+        let nodes = self.nodes.capacity() * size_of::<Entry<T>>()
+            + self
+                .nodes
+                .iter()
+                .map(|(_, entry)| entry.heap_bytes())
+                .sum::<usize>();
+        let subscriptions = stats::map_bytes(&self.subscriptions)
+            + self
+                .subscriptions
+                .values()
+                .map(Subscription::heap_bytes)
+                .sum::<usize>();
+        let indexes = stats::vec_bytes(&self.roots)
+            + self.predicates.heap_bytes()
+            + stats::map_bytes(&self.expression_to_node)
+            + stats::map_bytes(&self.nodes_by_ids)
+            + stats::set_bytes(&self.always_matching)
+            + stats::set_bytes(&self.tombstones);
+        size_of::<Self>()
+            + nodes
+            + subscriptions
+            + indexes
+            + self.strings.heap_bytes()
+            + self.parse_cache.heap_bytes()
+            + self.compilation_cache.heap_bytes()
     }
-    node_id
-}
-
-#[inline]
-fn add_parent<T>(entry: &mut Entry<T>, node_id: NodeId) {
-    entry.node.add_parent(node_id);
-}
 
-#[inline]
-fn add_subscription_id<T: Eq + Hash + Clone>(
-    subscription_id: &T,
-    node_id: NodeId,
-    nodes: &mut Slab<Entry<T>>,
-    nodes_by_ids: &mut HashMap<T, NodeId>,
-) {
-    nodes[node_id]
-        .subscription_ids
-        .push(subscription_id.clone());
-    nodes_by_ids.insert(subscription_id.clone(), node_id);
-}
+    /// Return the arbitrary boolean expression of the specified subscription as it was inserted.
+    pub fn expression(&self, subscription_id: &T) -> Option<&str> {
+        self.subscriptions
+            .get(subscription_id)
+            .map(|subscription| subscription.expression.as_ref())
+    }
 
-#[inline]
-fn increment_use_count<T>(node_id: NodeId, nodes: &mut Slab<Entry<T>>) {
-    nodes[node_id].use_count += 1;
-}
+    /// Return the [`Metadata`] of the specified subscription.
+    pub fn metadata(&self, subscription_id: &T) -> Option<&Metadata> {
+        self.subscriptions
+            .get(subscription_id)
+            .map(|subscription| &subscription.metadata)
+    }
 
-#[inline]
-fn get_max_level<T>(roots: &[NodeId], nodes: &Slab<Entry<T>>) -> usize {
-    roots
-        .iter()
-        .map(|root_id| nodes[*root_id].level())
-        .max()
-        .unwrap_or(1)
-}
+    /// Return the IDs of the subscriptions tagged with the specified tag, in no particular order.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition, Metadata};
+    ///
+    /// let definitions = [AttributeDefinition::boolean("private")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// let metadata = Metadata::new().with_tag("campaign-1");
+    /// atree.insert_with_metadata(&1u64, "private", metadata).unwrap();
+    /// atree.insert(&2u64, "not private").unwrap();
+    ///
+    /// let ids: Vec<_> = atree.ids_with_tag("campaign-1").collect();
+    /// assert_eq!(vec![&1u64], ids);
+    /// ```
+    pub fn ids_with_tag<'b>(&'b self, tag: &'b str) -> impl Iterator<Item = &'b T> + 'b {
+        self.subscriptions
+            .iter()
+            .filter(move |(_, subscription)| subscription.metadata.has_tag(tag))
+            .map(|(id, _)| id)
+    }
 
-#[inline]
-fn change_rnode_to_inode<T>(node_id: NodeId, nodes: &mut Slab<Entry<T>>) {
-    let entry = &mut nodes[node_id];
-    if let ATreeNode::RNode(RNode {
-        children,
-        level,
-        operator,
-    }) = &entry.node
-    {
-        let inode = ATreeNode::INode(INode {
-            parents: vec![],
-            children: children.to_vec(),
-            level: *level,
-            operator: operator.clone(),
-        });
-        entry.node = inode;
+    /// Delete the subscriptions tagged with the specified tag.
+    ///
+    /// Returns the IDs of the deleted subscriptions, in no particular order.
+    pub fn delete_by_tag(&mut self, tag: &str) -> Vec<T> {
+        let ids: Vec<_> = self.ids_with_tag(tag).cloned().collect();
+        for id in &ids {
+            self.delete(id);
+        }
+        ids
     }
-}
 
-#[inline]
-fn choose_access_child<T>(
-    left_id: NodeId,
-    right_id: NodeId,
-    parent_id: NodeId,
-    nodes: &mut Slab<Entry<T>>,
-    predicates: &mut Vec<NodeId>,
-) {
-    let left_entry = &nodes[left_id];
-    let right_entry = &nodes[right_id];
-    let accessor_id = if left_entry.cost < right_entry.cost {
-        left_id
-    } else {
-        right_id
-    };
-    add_parent(&mut nodes[accessor_id], parent_id);
-    add_predicate(accessor_id, nodes, predicates);
-}
+    /// Summarize the subscriptions by owner with the attributes they use; see [`Metadata`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition, Metadata};
+    ///
+    /// let definitions = [AttributeDefinition::boolean("private")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// let metadata = Metadata::new().with_owner("ads").with_author("bob");
+    /// atree.insert_with_metadata(&1u64, "private", metadata).unwrap();
+    ///
+    /// let report = atree.audit_report();
+    /// let ads = report.owner(Some("ads")).unwrap();
+    /// assert_eq!(1, ads.subscriptions());
+    /// assert_eq!([("private".to_owned(), 1)], ads.attribute_usage());
+    /// ```
+    pub fn audit_report(&self) -> AuditReport {
+        let names = self
+            .attributes
+            .names()
+            .map(|(name, id)| (id, name))
+            .collect();
+        audit::report(
+            self.subscriptions.iter().map(|(id, subscription)| {
+                (&subscription.metadata, self.subscription_attributes(id))
+            }),
+            &names,
+        )
+    }
 
-#[inline]
-fn add_predicate<T>(node_id: NodeId, nodes: &Slab<Entry<T>>, predicates: &mut Vec<NodeId>) {
-    let entry = &nodes[node_id];
-    if entry.is_leaf() && !predicates.contains(&node_id) {
-        predicates.push(node_id);
+    /// Group the subscriptions whose expressions use similar sets of predicates, to find the
+    /// near-duplicates and the candidates for a set-based rewrite.
+    ///
+    /// Two subscriptions are similar when the Jaccard index of their sets of predicates is at
+    /// least `min_similarity`, between `0.0` and `1.0`; a similarity of `1.0` only groups the
+    /// subscriptions using exactly the same predicates. The clusters are transitive and only
+    /// contain at least two subscriptions. The subscriptions without any predicate are ignored.
+    ///
+    /// This is an offline analysis: its cost grows with the amount of subscriptions sharing the
+    /// same predicates.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [
+    ///     AttributeDefinition::boolean("private"),
+    ///     AttributeDefinition::string("country"),
+    /// ];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, r#"private and country = "CA""#).unwrap();
+    /// atree.insert(&2u64, r#"private and country = "US""#).unwrap();
+    /// atree.insert(&3u64, r#"country = "FR""#).unwrap();
+    ///
+    /// let report = atree.cluster_report(0.3);
+    /// assert_eq!(1, report.clusters().len());
+    /// let cluster = &report.clusters()[0];
+    /// assert_eq!(2, cluster.subscriptions().len());
+    /// assert_eq!("private", cluster.shared_predicates()[0].attribute());
+    /// assert_eq!(3, cluster.predicates());
+    /// ```
+    pub fn cluster_report(&self, min_similarity: f64) -> ClusterReport<'_, T> {
+        let names = self
+            .attributes
+            .names()
+            .map(|(name, id)| (id, name))
+            .collect();
+        let strings = self.strings.to_local();
+        cluster::report(
+            self.subscriptions
+                .keys()
+                .map(|id| (id, self.subscription_predicates(id)))
+                .collect(),
+            min_similarity,
+            |node_id| match &self.nodes[node_id].node {
+                ATreeNode::LNode(LNode { predicate, .. }) => {
+                    PredicateExpression::new(predicate, &names, &strings)
+                }
+                _ => unreachable!("the predicates of the subscriptions are leaf nodes"),
+            },
+        )
     }
-}
 
-#[inline]
-fn process_predicates<'a, T>(
-    predicates: &[NodeId],
-    nodes: &'a Slab<Entry<T>>,
-    event: &Event,
-    matches: &mut Vec<&'a T>,
-    results: &mut EvaluationResult,
-    queues: &mut [Vec<(NodeId, &'a Entry<T>)>],
-) {
-    for predicate_id in predicates {
-        let node = &nodes[*predicate_id];
-        // The evaluation is delayed as much as possible; if the predicate has no
-        // subscribers and no parents, there is no point in evaluating eagerly and
-        // it should only be evaluated if there is a need for it.
-        let delay_evaluation = node.subscription_ids.is_empty() && node.parents().is_empty();
-        if delay_evaluation || results.is_evaluated(*predicate_id) {
-            continue;
+    /// Return the nodes of the predicates of the specified subscription.
+    fn subscription_predicates(&self, subscription_id: &T) -> Vec<NodeId> {
+        let mut predicates = vec![];
+        let mut stack: Vec<_> = self
+            .nodes_by_ids
+            .get(subscription_id)
+            .into_iter()
+            .copied()
+            .collect();
+        while let Some(node_id) = stack.pop() {
+            match &self.nodes[node_id].node {
+                ATreeNode::LNode(_) => predicates.push(node_id),
+                node => stack.extend_from_slice(node.children()),
+            }
         }
+        predicates
+    }
 
-        let result = node.evaluate(event);
-        results.set_result(*predicate_id, result);
-        add_matches(result, node, matches);
+    /// Return the attributes used by the predicates of the specified subscription.
+    fn subscription_attributes(&self, subscription_id: &T) -> HashSet<AttributeId> {
+        let mut attributes = HashSet::new();
+        let mut stack: Vec<_> = self
+            .nodes_by_ids
+            .get(subscription_id)
+            .into_iter()
+            .copied()
+            .collect();
+        while let Some(node_id) = stack.pop() {
+            match &self.nodes[node_id].node {
+                ATreeNode::LNode(LNode { predicate, .. }) => {
+                    attributes.insert(predicate.attribute());
+                }
+                node => stack.extend_from_slice(node.children()),
+            }
+        }
+        attributes
+    }
 
-        node.parents()
+    /// Return the attributes used by at least one of the predicates of the tree.
+    pub(crate) fn referenced_attributes(&self) -> HashSet<AttributeId> {
+        self.nodes
             .iter()
-            .map(|parent_id| (*parent_id, &nodes[*parent_id]))
-            .for_each(|(parent_id, parent)| {
-                if matches!(parent.operator(), Operator::And) && !result.unwrap_or(true) {
-                    results.set_result(parent_id, Some(false));
-                } else {
-                    queues[parent.level() - 2].push((parent_id, parent));
-                }
+            .filter_map(|(_, entry)| match &entry.node {
+                ATreeNode::LNode(LNode { predicate, .. }) => Some(predicate.attribute()),
+                _ => None,
             })
+            .collect()
     }
-}
 
-#[inline]
-fn evaluate_node<'a, T>(
-    node_id: NodeId,
-    event: &Event,
-    node: &'a Entry<T>,
-    nodes: &'a Slab<Entry<T>>,
-    results: &mut EvaluationResult,
-    matches: &mut Vec<&'a T>,
-) -> Option<bool> {
-    let operator = node.operator();
-    let result = match operator {
-        Operator::And => evaluate_and(node.children(), event, nodes, results, matches),
-        Operator::Or => evaluate_or(node.children(), event, nodes, results, matches),
-    };
-    results.set_result(node_id, result);
-    result
-}
+    /// Create a new [`EventBuilder`] to be able to generate an [`Event`] that will be usable for
+    /// finding the matching arbitrary boolean expressions inside the [`ATree`] via the
+    /// [`ATree::search()`] function.
+    #[inline]
+    pub fn make_event(&'_ self) -> EventBuilder<'_> {
+        EventBuilder::with_config(
+            &self.attributes,
+            &self.strings,
+            &self.config,
+            &self.observers,
+        )
+    }
 
-#[inline]
-fn evaluate_and<'a, T>(
-    children: &[NodeId],
-    event: &Event,
-    nodes: &'a Slab<Entry<T>>,
-    results: &mut EvaluationResult,
-    matches: &mut Vec<&'a T>,
-) -> Option<bool> {
-    let mut acc = Some(true);
-    for child_id in children {
-        let result = lazy_evaluate(*child_id, event, nodes, results, matches);
-        match (acc, result) {
-            (Some(false), _) => {
-                acc = Some(false);
-                break;
-            }
-            (_, Some(false)) => {
-                acc = Some(false);
-                break;
-            }
-            (Some(a), Some(b)) => {
-                acc = Some(a && b);
-            }
-            (_, _) => {
-                acc = None;
-            }
-        }
-    }
-    acc
-}
-
-#[inline]
-fn evaluate_or<'a, T>(
-    children: &[NodeId],
-    event: &Event,
-    nodes: &'a Slab<Entry<T>>,
-    results: &mut EvaluationResult,
-    matches: &mut Vec<&'a T>,
-) -> Option<bool> {
-    let mut acc = Some(false);
-    for child_id in children {
-        let result = lazy_evaluate(*child_id, event, nodes, results, matches);
-        match (acc, result) {
-            (Some(true), _) => {
-                acc = Some(true);
-                break;
-            }
-            (_, Some(true)) => {
-                acc = Some(true);
-                break;
-            }
-            (Some(a), Some(b)) => {
-                acc = Some(a || b);
-            }
-            (_, _) => {
-                acc = None;
-            }
-        }
-    }
-
-    acc
-}
-
-#[inline]
-fn lazy_evaluate<'a, T>(
-    node_id: NodeId,
-    event: &Event,
-    nodes: &'a Slab<Entry<T>>,
-    results: &mut EvaluationResult,
-    matches: &mut Vec<&'a T>,
-) -> Option<bool> {
-    if results.is_evaluated(node_id) {
-        return results.get_result(node_id);
-    }
-    let node = &nodes[node_id];
-    let result = if node.is_leaf() {
-        let result = node.evaluate(event);
-        results.set_result(node_id, result);
-        result
-    } else {
-        evaluate_node(node_id, event, node, nodes, results, matches)
-    };
-    add_matches(result, node, matches);
-    result
-}
-
-#[inline]
-fn add_matches<'a, T>(result: Option<bool>, node: &'a Entry<T>, matches: &mut Vec<&'a T>) {
-    if !node.subscription_ids.is_empty() {
-        if let Some(true) = result {
-            for subscription_id in &node.subscription_ids {
-                matches.push(subscription_id);
-            }
-        }
+    /// Intern a string so that it can be passed to
+    /// [`EventBuilder::with_interned_string()`] without being hashed for every event.
+    ///
+    /// Interning the same string multiple times returns the same [`InternedString`], and it stays
+    /// valid when expressions using that string are inserted afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::string("os")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// let ios = atree.intern("iOS");
+    /// atree.insert(&1u64, r#"os = "iOS""#).unwrap();
+    ///
+    /// assert_eq!(Some(ios), atree.interned("iOS"));
+    /// let mut builder = atree.make_event();
+    /// builder.with_interned_string("os", ios).unwrap();
+    /// let event = builder.build().unwrap();
+    /// assert_eq!(vec![&1u64], atree.search(&event).unwrap().matches());
+    /// ```
+    pub fn intern(&mut self, value: &str) -> InternedString {
+        InternedString(self.strings.get_or_update(value))
     }
-}
-
-#[derive(Clone, Debug)]
-struct Entry<T> {
-    id: ExpressionId,
-    subscription_ids: Vec<T>,
-    node: ATreeNode,
-    use_count: usize,
-    cost: u64,
-}
 
-impl<T> Entry<T> {
-    fn new(id: ExpressionId, node: ATreeNode, subscription_id: Option<T>, cost: u64) -> Self {
-        Self {
-            id,
-            node,
-            use_count: 1,
-            subscription_ids: subscription_id
-                .map_or_else(Vec::new, |subscription_id| vec![subscription_id]),
-            cost,
-        }
+    /// Return the [`InternedString`] of a string that was already interned, either explicitly via
+    /// [`ATree::intern()`] or because it is used by an expression.
+    pub fn interned(&self, value: &str) -> Option<InternedString> {
+        self.strings.find(value).map(InternedString)
     }
 
+    /// Build an [`Event`] from a flat JSON object whose keys are the attribute names.
+    ///
+    /// Booleans, strings and arrays are mapped onto the attributes of the same type, numbers onto
+    /// the integer and float attributes and `null` leaves the attribute `undefined`. A number with
+    /// a fractional part cannot be used for an integer attribute. Every attribute that is not in
+    /// the object is left `undefined`.
+    ///
+    /// This is only available when the `json` feature is enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [
+    ///     AttributeDefinition::boolean("private"),
+    ///     AttributeDefinition::integer("exchange_id"),
+    ///     AttributeDefinition::string_list("deals"),
+    /// ];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "private and exchange_id = 1").unwrap();
+    ///
+    /// let event = atree
+    ///     .event_from_json(r#"{"private": true, "exchange_id": 1, "deals": ["deal-1"]}"#)
+    ///     .unwrap();
+    /// assert_eq!(vec![&1u64], atree.search(&event).unwrap().matches());
+    ///
+    /// assert!(atree.event_from_json(r#"{"exchange_id": "1"}"#).is_err());
+    /// ```
+    #[cfg(feature = "json")]
     #[inline]
-    const fn is_leaf(&self) -> bool {
-        matches!(self.node, ATreeNode::LNode(_))
+    pub fn event_from_json(&self, json: &str) -> Result<Event, JsonError> {
+        json::event_from_json(self.make_event(), json)
     }
 
-    #[inline]
-    const fn is_root(&self) -> bool {
-        matches!(self.node, ATreeNode::RNode(_))
+    /// Build one [`Event`] per row of an Apache Arrow [`arrow_array::RecordBatch`].
+    ///
+    /// The columns are mapped onto the attributes with the same name and the null values leave
+    /// the attributes `undefined`. The columns can have the following types:
+    ///
+    /// * boolean attributes: `Boolean`;
+    /// * integer attributes: signed and unsigned integers as long as their values fit in an
+    ///   `i64`;
+    /// * float attributes: `Float32`, `Float64`, `Decimal128` and the integer types;
+    /// * string attributes: `Utf8`, `LargeUtf8` and `Utf8View`;
+    /// * list attributes: `List` and `LargeList` of the types above.
+    ///
+    /// This is only available when the `arrow` feature is enabled.
+    #[cfg(feature = "arrow")]
+    pub fn events_from_record_batch(
+        &self,
+        batch: &arrow_array::RecordBatch,
+    ) -> Result<Vec<Event>, RecordBatchError> {
+        arrow::events_from_record_batch(|| self.make_event(), batch)
     }
 
+    /// Build an [`Event`] from an OpenRTB 2.x bid request by looking up the fields of the
+    /// [`OpenRtbMapping`].
+    ///
+    /// The values are coerced like in [`ATree::event_from_json()`] except for the boolean
+    /// attributes that also accept the `0` and `1` flags used by OpenRTB.
+    ///
+    /// This is only available when the `openrtb` feature is enabled.
+    #[cfg(feature = "openrtb")]
     #[inline]
-    const fn level(&self) -> usize {
-        self.node.level()
+    pub fn event_from_openrtb(
+        &self,
+        bid_request: &str,
+        mapping: &OpenRtbMapping,
+    ) -> Result<Event, JsonError> {
+        mapping.to_event(self.make_event(), bid_request)
     }
 
-    #[inline]
-    fn evaluate(&self, event: &Event) -> Option<bool> {
-        self.node.evaluate(event)
+    /// Search the [`ATree`] for arbitrary boolean expressions that match the [`Event`].
+    ///
+    /// When the `metrics` feature is enabled, the returned [`Report`] also contains the counters
+    /// collected during the search (see [`Report::metrics()`]).
+    ///
+    /// When the `tracing` feature is enabled, the search runs inside a `search` span to which
+    /// these counters are attached once the search is done.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(
+                nodes = self.nodes.len(),
+                matches = tracing::field::Empty,
+                nodes_visited = tracing::field::Empty,
+                predicates_evaluated = tracing::field::Empty,
+                short_circuits = tracing::field::Empty,
+                elapsed_us = tracing::field::Empty,
+            )
+        )
+    )]
+    pub fn search(&'_ self, event: &Event) -> Result<Report<'_, T>, ATreeError<'_>> {
+        Ok(self.search_into(event, Vec::with_capacity(50), false))
     }
 
-    #[inline]
-    fn operator(&self) -> Operator {
-        self.node.operator()
+    /// Search the [`ATree`] for the expressions that match the [`Event`] among the specified
+    /// candidates, e.g. the subscriptions that still have a budget.
+    ///
+    /// The other subscriptions are evaluated as usual, since they can share nodes with the
+    /// candidates, but are left out of the [`Report`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    /// use std::collections::HashSet;
+    ///
+    /// let definitions = [AttributeDefinition::integer("exchange_id")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "exchange_id = 1").unwrap();
+    /// atree.insert(&2u64, "exchange_id < 5").unwrap();
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_integer("exchange_id", 1).unwrap();
+    /// let event = builder.build().unwrap();
+    ///
+    /// let candidates = HashSet::from([2u64, 3u64]);
+    /// let report = atree.search_among(&event, &candidates).unwrap();
+    /// assert_eq!([&2u64], report.matches());
+    /// ```
+    pub fn search_among<'a>(
+        &'a self,
+        event: &Event,
+        candidates: &HashSet<T>,
+    ) -> Result<Report<'a, T>, ATreeError<'a>> {
+        let matches = Among {
+            candidates,
+            matches: Vec::with_capacity(candidates.len().min(50)),
+        };
+        Ok(self.search_into(event, matches, false))
     }
 
-    #[inline]
-    fn children(&self) -> &[NodeId] {
-        self.node.children()
+    /// Search the [`ATree`] for at most `max` expressions that match the [`Event`].
+    ///
+    /// The search stops as soon as `max` matches are found, so which of the matching
+    /// subscriptions are returned is unspecified.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::integer("exchange_id")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "exchange_id = 1").unwrap();
+    /// atree.insert(&2u64, "exchange_id < 5").unwrap();
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_integer("exchange_id", 1).unwrap();
+    /// let event = builder.build().unwrap();
+    ///
+    /// assert_eq!(1, atree.search_limited(&event, 1).unwrap().matches().len());
+    /// ```
+    pub fn search_limited(
+        &'_ self,
+        event: &Event,
+        max: usize,
+    ) -> Result<Report<'_, T>, ATreeError<'_>> {
+        let matches = Limited {
+            max,
+            matches: Vec::with_capacity(max.min(50)),
+        };
+        Ok(self.search_into(event, matches, false))
     }
 
-    #[inline]
-    fn parents(&self) -> &[NodeId] {
-        self.node.parents()
+    /// Search the [`ATree`] like [`ATree::search()`] and also report the subscriptions that
+    /// could not be decided because the [`Event`] lacks some of their attributes.
+    ///
+    /// The expressions that the search leaves aside are evaluated as well to decide whether they
+    /// are false or undefined, so this search is slower and meant for monitoring the quality of
+    /// the events, e.g. to quantify the matches lost to missing fields. The subscriptions that
+    /// are neither in [`Report::matches()`] nor in [`Report::undefined()`] evaluated to false.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [
+    ///     AttributeDefinition::integer("exchange_id"),
+    ///     AttributeDefinition::boolean("private"),
+    /// ];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "exchange_id = 1").unwrap();
+    /// atree.insert(&2u64, "exchange_id = 1 and private").unwrap();
+    /// atree.insert(&3u64, "exchange_id = 2 and private").unwrap();
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_integer("exchange_id", 1).unwrap();
+    /// let event = builder.build().unwrap();
+    ///
+    /// let report = atree.search_with_undefined(&event).unwrap();
+    /// assert_eq!([&1u64], report.matches());
+    /// assert_eq!([&2u64], report.undefined());
+    /// ```
+    pub fn search_with_undefined(&'_ self, event: &Event) -> Result<Report<'_, T>, ATreeError<'_>> {
+        Ok(self.search_into(event, Vec::with_capacity(50), true))
     }
-}
-
-#[derive(Clone, Debug)]
-#[allow(clippy::enum_variant_names)]
-enum ATreeNode {
-    LNode(LNode),
-    INode(INode),
-    RNode(RNode),
-}
 
-impl ATreeNode {
-    #[inline]
-    fn lnode(predicate: &Predicate) -> Self {
-        Self::LNode(LNode {
-            level: 1,
-            parents: vec![],
-            predicate: predicate.clone(),
-        })
+    fn search_into<'a, M>(
+        &'a self,
+        event: &Event,
+        mut matches: M,
+        with_undefined: bool,
+    ) -> Report<'a, T>
+    where
+        M: Matches<'a, T> + Into<Vec<&'a T>>,
+    {
+        let mut recorder = self.recorder();
+        let mut results = self.evaluate(event, &mut matches, &mut recorder);
+        let matches = matches.into();
+        let undefined = if with_undefined {
+            self.undefined_subscriptions(event, &matches, &mut results, &mut recorder)
+        } else {
+            Vec::new()
+        };
+        let report = Report::new(matches, undefined, self.generation, recorder);
+        #[cfg(feature = "metrics")]
+        self.counters
+            .record_search(report.matches.len(), &report.metrics);
+        #[cfg(feature = "tracing")]
+        record_search(&report);
+        self.observers.on_search_complete(&report);
+        report
     }
 
-    #[inline]
-    const fn level(&self) -> usize {
-        match self {
-            Self::RNode(node) => node.level,
-            Self::LNode(node) => node.level,
-            Self::INode(node) => node.level,
+    /// Search the expressions matching the [`Event`] by evaluating each of them on its own, as a
+    /// baseline for [`ATree::search()`].
+    ///
+    /// The nodes shared by the expressions are evaluated again for each of them and the results
+    /// are not propagated; only the `and` and `or` operators stop at the first operand that
+    /// decides their result. The matches are in no particular order.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [
+    ///     AttributeDefinition::boolean("private"),
+    ///     AttributeDefinition::integer("exchange_id"),
+    /// ];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "private and exchange_id = 1").unwrap();
+    /// atree.insert(&2u64, "not private").unwrap();
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_boolean("private", true).unwrap();
+    /// builder.with_integer("exchange_id", 1).unwrap();
+    /// let event = builder.build().unwrap();
+    ///
+    /// assert_eq!(vec![&1u64], atree.baseline_search(&event));
+    /// ```
+    pub fn baseline_search(&self, event: &Event) -> Vec<&T> {
+        let mut matches: Vec<_> = self.always_matching.iter().collect();
+        for (node_id, entry) in &self.nodes {
+            if !entry.subscription_ids.is_empty()
+                && evaluate_linearly(node_id, event, &self.nodes) == Some(true)
+            {
+                matches.extend(
+                    entry
+                        .subscription_ids
+                        .iter()
+                        .filter(|id| !self.tombstones.contains(*id)),
+                );
+            }
         }
+        matches
     }
 
-    #[inline]
-    fn evaluate(&self, event: &Event) -> Option<bool> {
-        match self {
-            Self::LNode(node) => node.predicate.evaluate(event),
-            node => unreachable!("evaluating {node:?} which is not a predicate; this is a bug."),
-        }
-    }
+    /// Search the events with both [`ATree::search()`] and [`ATree::baseline_search()`] to measure
+    /// the speedup of the tree for these events and check that both find the same matches.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::integer("exchange_id")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// for id in 0..100u64 {
+    ///     atree.insert(&id, &format!("exchange_id = {}", id % 10)).unwrap();
+    /// }
+    /// let events: Vec<_> = (0..10)
+    ///     .map(|exchange_id| {
+    ///         let mut builder = atree.make_event();
+    ///         builder.with_integer("exchange_id", exchange_id).unwrap();
+    ///         builder.build().unwrap()
+    ///     })
+    ///     .collect();
+    ///
+    /// let report = atree.compare_with_baseline(&events);
+    /// assert_eq!(10, report.events());
+    /// assert!(report.is_consistent());
+    /// println!("{:.1}x faster than the linear evaluation", report.speedup());
+    /// ```
+    pub fn compare_with_baseline(&self, events: &[Event]) -> BaselineReport {
+        let start = Instant::now();
+        let searched: Vec<_> = events
+            .iter()
+            .map(|event| self.search_into(event, Vec::new(), false).matches)
+            .collect();
+        let tree = start.elapsed();
 
-    #[inline]
-    fn operator(&self) -> Operator {
-        match self {
-            Self::LNode(_) => {
-                unreachable!("trying to get the operator of leaf node; this is a bug");
-            }
-            Self::RNode(RNode { operator, .. }) | Self::INode(INode { operator, .. }) => {
-                operator.clone()
-            }
-        }
+        let start = Instant::now();
+        let evaluated: Vec<_> = events
+            .iter()
+            .map(|event| self.baseline_search(event))
+            .collect();
+        let baseline = start.elapsed();
+
+        let mismatches = searched
+            .iter()
+            .zip(&evaluated)
+            .enumerate()
+            .filter(|(_, (searched, evaluated))| {
+                searched.iter().collect::<HashSet<_>>() != evaluated.iter().collect()
+            })
+            .map(|(index, _)| index)
+            .collect();
+        BaselineReport::new(events.len(), tree, baseline, mismatches)
     }
 
-    #[inline]
-    fn children(&self) -> &[NodeId] {
-        match self {
-            Self::INode(INode { children, .. }) | Self::RNode(RNode { children, .. }) => children,
-            Self::LNode(_) => unreachable!("cannot get children for l-node; this is a bug"),
+    /// Explain why the specified subscription matches the [`Event`] or not, with the outcome of
+    /// each of its predicates.
+    ///
+    /// Returns `None` if there is no such subscription.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [
+    ///     AttributeDefinition::integer("exchange_id"),
+    ///     AttributeDefinition::boolean("private"),
+    /// ];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "exchange_id = 1 and private").unwrap();
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_integer("exchange_id", 1).unwrap();
+    /// builder.with_boolean("private", false).unwrap();
+    /// let event = builder.build().unwrap();
+    ///
+    /// let explanation = atree.explain(&1u64, &event).unwrap();
+    /// assert!(!explanation.matches());
+    /// let results: Vec<_> = explanation.predicates().iter().map(|outcome| outcome.result()).collect();
+    /// assert_eq!(vec![Some(true), Some(false)], results);
+    /// ```
+    pub fn explain(&self, subscription_id: &T, event: &Event) -> Option<Explanation> {
+        let expression = self.expression(subscription_id)?;
+        let mut frozen = FrozenStringTable::new(&self.strings);
+        let evaluated = parser::parse(expression, &self.attributes, &mut frozen).ok()?;
+        let mut strings = LocalStringTable::default();
+        let described = parser::parse(expression, &self.attributes, &mut strings).ok()?;
+        let names = self
+            .attributes
+            .names()
+            .map(|(name, id)| (id, name))
+            .collect();
+        Some(explain::explain(
+            &evaluated, &described, event, &names, &strings,
+        ))
+    }
+
+    /// Count the subscriptions that match the [`Event`] without collecting them.
+    ///
+    /// The count is the length of the matches returned by [`ATree::search()`] for the same
+    /// [`Event`]. The searches made with this method are recorded in the cumulative counters of
+    /// the `metrics` feature but the [`TreeObserver`]s are not notified since there is no
+    /// [`Report`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::integer("exchange_id")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "exchange_id = 1").unwrap();
+    /// atree.insert(&2u64, "exchange_id < 5").unwrap();
+    /// atree.insert(&3u64, "exchange_id > 5").unwrap();
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_integer("exchange_id", 1).unwrap();
+    /// let event = builder.build().unwrap();
+    ///
+    /// assert_eq!(2, atree.match_count(&event).unwrap());
+    /// ```
+    pub fn match_count(&'_ self, event: &Event) -> Result<usize, ATreeError<'_>> {
+        let mut recorder = self.recorder();
+        let mut count = 0;
+        self.evaluate(event, &mut count, &mut recorder);
+        #[cfg(feature = "metrics")]
+        self.counters.record_search(count, &recorder.finish());
+        Ok(count)
+    }
+
+    /// Run representative events through the [`ATree`] before it serves its first searches.
+    ///
+    /// The first searches made after a deploy are slower since the nodes, the predicates and
+    /// their lists are not yet in the CPU caches, or even in memory once the tree was loaded from
+    /// a snapshot. Searching a sample of the production traffic beforehand avoids these latency
+    /// spikes.
+    ///
+    /// The matches are discarded. The warm-up searches are not recorded in the counters of the
+    /// `metrics` feature and the [`TreeObserver`]s are not notified, although the `profiling`
+    /// feature counts their evaluations.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::integer("exchange_id")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "exchange_id = 1").unwrap();
+    ///
+    /// let events: Vec<_> = (0..10)
+    ///     .map(|exchange_id| {
+    ///         let mut builder = atree.make_event();
+    ///         builder.with_integer("exchange_id", exchange_id).unwrap();
+    ///         builder.build().unwrap()
+    ///     })
+    ///     .collect();
+    /// atree.warmup(&events);
+    /// ```
+    pub fn warmup(&self, events: &[Event]) {
+        for event in events {
+            let mut count = 0;
+            self.evaluate(event, &mut count, &mut Recorder::start());
+            std::hint::black_box(count);
         }
     }
 
-    #[inline]
-    fn parents(&self) -> &[NodeId] {
-        match self {
-            Self::INode(INode { parents, .. }) | Self::LNode(LNode { parents, .. }) => parents,
-            Self::RNode(_) => unreachable!("cannot get parents for r-node; this is a bug"),
+    fn recorder(&self) -> Recorder {
+        let recorder = Recorder::start();
+        #[cfg(feature = "profiling")]
+        let recorder = recorder.with_predicate_timing(self.predicate_timing);
+        recorder
+    }
+
+    /// Evaluate the expressions against the [`Event`] and add the matching subscriptions to
+    /// `matches`, once each.
+    fn evaluate<'a>(
+        &'a self,
+        event: &Event,
+        matches: &mut impl Matches<'a, T>,
+        recorder: &mut Recorder,
+    ) -> EvaluationResult {
+        if !self.tombstones.is_empty() {
+            let mut alive = Alive {
+                tombstones: &self.tombstones,
+                matches,
+            };
+            return self.evaluate_unique(event, &mut alive, recorder);
         }
+        self.evaluate_unique(event, matches, recorder)
     }
 
-    #[inline]
-    fn add_parent(&mut self, parent_id: NodeId) {
-        match self {
-            ATreeNode::INode(node) => {
-                node.parents.push(parent_id);
-            }
-            ATreeNode::LNode(node) => {
-                node.parents.push(parent_id);
-            }
-            ATreeNode::RNode(node) => {
-                unreachable!("trying to insert parents to r-node {node:?} which cannot have any parents; this is a bug");
+    fn evaluate_unique<'a>(
+        &'a self,
+        event: &Event,
+        matches: &mut impl Matches<'a, T>,
+        recorder: &mut Recorder,
+    ) -> EvaluationResult {
+        // The IDs attached to several expressions are the only ones that can match twice, so the
+        // other trees do not pay for the deduplication.
+        if self.shared_ids {
+            let mut unique = Unique {
+                seen: HashSet::new(),
+                matches,
+            };
+            self.evaluate_nodes(event, &mut unique, recorder)
+        } else {
+            self.evaluate_nodes(event, matches, recorder)
+        }
+    }
+
+    /// Evaluate the expressions that were left aside by a search and return the subscriptions
+    /// whose result depends on an undefined attribute, except the ones that matched.
+    fn undefined_subscriptions<'a>(
+        &'a self,
+        event: &Event,
+        matches: &[&'a T],
+        results: &mut EvaluationResult,
+        recorder: &mut Recorder,
+    ) -> Vec<&'a T> {
+        // The new results are already known to be false or undefined, so there is nothing to
+        // collect.
+        let mut ignored = 0;
+        let mut seen: HashSet<_> = matches.iter().copied().collect();
+        self.nodes
+            .iter()
+            .filter(|(_, entry)| !entry.subscription_ids.is_empty())
+            .filter(|(node_id, _)| {
+                lazy_evaluate(
+                    *node_id,
+                    event,
+                    &self.nodes,
+                    results,
+                    &mut ignored,
+                    recorder,
+                )
+                .is_none()
+            })
+            .flat_map(|(_, entry)| &entry.subscription_ids)
+            .filter(|subscription_id| !self.tombstones.contains(*subscription_id))
+            .filter(|subscription_id| seen.insert(*subscription_id))
+            .collect()
+    }
+
+    fn evaluate_nodes<'a>(
+        &'a self,
+        event: &Event,
+        matches: &mut impl Matches<'a, T>,
+        recorder: &mut Recorder,
+    ) -> EvaluationResult {
+        let mut results = EvaluationResult::new(self.nodes.len());
+
+        // Since the predicates will already be evaluated and their parents will be put into the
+        // queues, then there is no need to keep a queue for them.
+        let mut queues = vec![Vec::with_capacity(50); self.max_level - 1];
+        process_predicates(
+            &self.predicates,
+            &self.nodes,
+            event,
+            matches,
+            &mut results,
+            &mut queues,
+            recorder,
+        );
+
+        for level in 0..queues.len() {
+            while let Some((node_id, node)) = queues[level].pop() {
+                if matches.is_full() {
+                    return results;
+                }
+                if results.is_evaluated(node_id) {
+                    continue;
+                }
+
+                let result = evaluate_node(
+                    node_id,
+                    event,
+                    node,
+                    &self.nodes,
+                    &mut results,
+                    matches,
+                    recorder,
+                );
+                add_matches(result, node, matches);
+
+                if node.is_root() {
+                    continue;
+                }
+
+                for parent_id in node.parents() {
+                    let entry = &self.nodes[*parent_id];
+                    let is_evaluated = results.is_evaluated(*parent_id);
+                    if !is_evaluated
+                        && matches!(entry.operator(), Operator::And)
+                        && !result.unwrap_or(true)
+                    {
+                        results.set_result(*parent_id, Some(false));
+                        recorder.short_circuit();
+                        continue;
+                    }
+
+                    if !is_evaluated {
+                        queues[entry.level() - 2].push((*parent_id, entry));
+                    }
+                }
             }
         }
+
+        for subscription_id in &self.always_matching {
+            matches.push(subscription_id);
+        }
+        results
     }
-}
 
-#[derive(Clone, Debug)]
-struct LNode {
-    parents: Vec<NodeId>,
-    level: usize,
-    predicate: Predicate,
-}
+    /// Return the generation of the subscriptions, which is incremented by every insertion and
+    /// deletion.
+    ///
+    /// Every [`Report`] is stamped with the generation of the tree it was searched in so that a
+    /// consumer handling the matches asynchronously can tell whether they refer to a superseded
+    /// set of subscriptions.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::boolean("private")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "private").unwrap();
+    /// let event = atree.make_event().build().unwrap();
+    /// let generation = atree.search(&event).unwrap().generation();
+    /// assert_eq!(atree.generation(), generation);
+    ///
+    /// atree.delete(&1);
+    /// assert!(atree.generation() > generation);
+    /// ```
+    #[inline]
+    pub const fn generation(&self) -> u64 {
+        self.generation
+    }
 
-#[derive(Clone, Debug)]
-struct INode {
-    parents: Vec<NodeId>,
-    children: Vec<NodeId>,
-    level: usize,
-    operator: Operator,
-}
+    /// Return the [`ATreeConfig`] used by the [`ATree`].
+    #[inline]
+    pub const fn config(&self) -> &ATreeConfig {
+        &self.config
+    }
 
-#[derive(Clone, Debug)]
-struct RNode {
-    children: Vec<NodeId>,
-    level: usize,
-    operator: Operator,
-}
+    /// Return the definitions of the attributes of the [`ATree`] in the order they were defined.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition, AttributeKind};
+    ///
+    /// let definitions = [
+    ///     AttributeDefinition::boolean("private"),
+    ///     AttributeDefinition::string_list("deals"),
+    /// ];
+    /// let atree = ATree::<u64>::new(&definitions).unwrap();
+    ///
+    /// let attributes = atree.attributes();
+    /// assert_eq!("deals", attributes[1].name());
+    /// assert_eq!(&AttributeKind::StringList, attributes[1].kind());
+    /// ```
+    #[inline]
+    pub fn attributes(&self) -> Vec<AttributeDefinition> {
+        self.attributes.definitions()
+    }
 
-#[derive(Debug)]
-/// Structure that holds the search results from the [`ATree::search()`] function
-pub struct Report<'a, T> {
-    matches: Vec<&'a T>,
-}
+    /// Register a [`TreeObserver`] that will be notified of the insertions, deletions and searches
+    /// made on the [`ATree`].
+    ///
+    /// The observers are called in the order in which they were registered and are shared with
+    /// the clones of the [`ATree`].
+    #[inline]
+    pub fn register_observer(&mut self, observer: Arc<dyn TreeObserver<T>>) {
+        self.observers.register(observer);
+    }
 
-impl<'a, T> Report<'a, T> {
-    const fn new(matches: Vec<&'a T>) -> Self {
-        Self { matches }
+    /// Run the chunks of the batch operations, such as [`ATree::insert_many()`] or
+    /// [`ATree::retain()`], with an [`Executor`] instead of threads spawned for each operation.
+    ///
+    /// The executor is shared with the clones of the [`ATree`].
+    #[inline]
+    pub fn set_executor(&mut self, executor: Arc<dyn Executor>) {
+        self.executor.set(executor);
     }
 
     #[inline]
-    /// Get the search matches
-    pub fn matches(&self) -> &[&'a T] {
-        &self.matches
+    pub(crate) fn executor(&self) -> Option<&dyn Executor> {
+        self.executor.get()
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Search the [`ATree`] for each of the [`Event`]s and return the [`Report`]s in the same
+    /// order.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::integer("exchange_id")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "exchange_id = 1").unwrap();
+    ///
+    /// let events: Vec<_> = [1, 2]
+    ///     .into_iter()
+    ///     .map(|exchange_id| {
+    ///         let mut builder = atree.make_event();
+    ///         builder.with_integer("exchange_id", exchange_id).unwrap();
+    ///         builder.build().unwrap()
+    ///     })
+    ///     .collect();
+    ///
+    /// let reports = atree.search_batch(&events).unwrap();
+    /// assert_eq!(vec![&1u64], reports[0].matches());
+    /// assert!(reports[1].matches().is_empty());
+    /// ```
+    pub fn search_batch(&'_ self, events: &[Event]) -> Result<Vec<Report<'_, T>>, ATreeError<'_>> {
+        events.iter().map(|event| self.search(event)).collect()
+    }
 
-    const AN_INVALID_BOOLEAN_EXPRESSION: &str = "invalid in (1, 2, 3 and";
-    const AN_EXPRESSION: &str = "exchange_id = 1";
-    const A_NOT_EXPRESSION: &str = "not private";
-    const AN_EXPRESSION_WITH_AND_OPERATORS: &str =
-        r#"exchange_id = 1 and deals one of ["deal-1", "deal-2"]"#;
-    const AN_EXPRESSION_WITH_OR_OPERATORS: &str =
-        r#"exchange_id = 1 or deals one of ["deal-1", "deal-2"]"#;
-    const A_COMPLEX_EXPRESSION: &str = r#"exchange_id = 1 and not private and deal_ids one of ["deal-1", "deal-2"] and segment_ids one of [1, 2, 3] and country = 'CA' and city in ['QC'] or country = 'US' and city in ['AZ']"#;
-    const ANOTHER_COMPLEX_EXPRESSION: &str = r#"exchange_id = 1 and not private and deal_ids one of ["deal-1", "deal-2"] and segment_ids one of [1, 2, 3] and country in ['FR', 'GB']"#;
+    /// Return a snapshot of the counters accumulated since the creation of the [`ATree`].
+    ///
+    /// This is only available when the `metrics` feature is enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::boolean("private")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "private").unwrap();
+    /// assert!(atree.insert(&2u64, "private and").is_err());
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_boolean("private", true).unwrap();
+    /// let event = builder.build().unwrap();
+    /// atree.search(&event).unwrap();
+    ///
+    /// let metrics = atree.metrics();
+    /// assert_eq!(1, metrics.inserts());
+    /// assert_eq!(1, metrics.parse_failures());
+    /// assert_eq!(1, metrics.searches());
+    /// assert_eq!(1, metrics.matches());
+    /// ```
+    #[cfg(feature = "metrics")]
+    #[inline]
+    pub fn metrics(&self) -> TreeMetrics {
+        self.counters.snapshot()
+    }
 
-    fn is_sync_and_send<T: Send + Sync>() {}
+    #[inline]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(subscription_id = ?subscription_id, nodes = tracing::field::Empty)
+        )
+    )]
+    /// Delete the specified expression
+    ///
+    /// With [`DeletePolicy::Tombstone`], the subscription is only marked as deleted until the
+    /// next [`ATree::compact()`].
+    pub fn delete(&mut self, subscription_id: &T) {
+        if self.config.deletes() == DeletePolicy::Tombstone {
+            self.tombstone(subscription_id);
+            return;
+        }
+        self.remove_now(subscription_id);
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("nodes", self.nodes.len());
+    }
+
+    /// Delete a subscription along with its nodes, whatever the [`DeletePolicy`].
+    fn remove_now(&mut self, subscription_id: &T) {
+        if let Some(node_id) = self.nodes_by_ids.get(subscription_id) {
+            self.delete_node(subscription_id, *node_id);
+        }
+        self.always_matching.remove(subscription_id);
+        if self.subscriptions.remove(subscription_id).is_some() {
+            self.generation += 1;
+            self.observers.on_delete(subscription_id);
+        }
+    }
+
+    /// Delete many subscriptions at once, looking for the nodes they release on up to `threads`
+    /// threads.
+    ///
+    /// The nodes that are no longer used are found in parallel without modifying the tree, then
+    /// removed in a single pass on the calling thread, which is much faster than deleting a large
+    /// amount of subscriptions one by one with [`ATree::delete()`]. The unknown IDs are ignored.
+    ///
+    /// Returns the amount of deleted subscriptions.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::num::NonZeroUsize;
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::integer("exchange_id")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// for id in 0..10u64 {
+    ///     atree.insert(&id, &format!("exchange_id = {}", id % 3)).unwrap();
+    /// }
+    ///
+    /// let deleted = atree.delete_many(&[1, 2, 3, 42], NonZeroUsize::new(2).unwrap());
+    ///
+    /// assert_eq!(3, deleted);
+    /// assert_eq!(None, atree.expression(&2));
+    /// ```
+    pub fn delete_many(&mut self, subscription_ids: &[T], threads: NonZeroUsize) -> usize
+    where
+        T: Send + Sync,
+    {
+        let mut seen = HashSet::with_capacity(subscription_ids.len());
+        let ids: Vec<_> = subscription_ids
+            .iter()
+            .filter(|id| self.contains(id) && seen.insert(*id))
+            .cloned()
+            .collect();
+        self.remove(&ids, threads);
+        ids.len()
+    }
+
+    /// Keep only the subscriptions for which `keep` returns `true` and return the IDs of the
+    /// deleted ones, in no particular order.
+    ///
+    /// The subscriptions are checked on up to `threads` threads and deleted like with
+    /// [`ATree::delete_many()`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::num::NonZeroUsize;
+    /// use a_tree::{ATree, AttributeDefinition, Metadata};
+    ///
+    /// let definitions = [AttributeDefinition::boolean("private")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// let metadata = Metadata::new().with_owner("ads");
+    /// atree.insert_with_metadata(&1u64, "private", metadata).unwrap();
+    /// atree.insert(&2u64, "not private").unwrap();
+    ///
+    /// let deleted = atree.retain(NonZeroUsize::MIN, |_, metadata| metadata.owner().is_some());
+    ///
+    /// assert_eq!(vec![2], deleted);
+    /// ```
+    pub fn retain<F>(&mut self, threads: NonZeroUsize, keep: F) -> Vec<T>
+    where
+        T: Send + Sync,
+        F: Fn(&T, &Metadata) -> bool + Sync,
+    {
+        let subscriptions: Vec<_> = self.subscriptions.iter().collect();
+        let ids: Vec<_> =
+            loader::parallel_chunks(&subscriptions, threads, self.executor(), |chunk| {
+                chunk
+                    .iter()
+                    .filter(|(id, subscription)| !keep(id, &subscription.metadata))
+                    .map(|(id, _)| (*id).clone())
+                    .collect::<Vec<_>>()
+            })
+            .into_iter()
+            .flatten()
+            .collect();
+        self.remove(&ids, threads);
+        ids
+    }
+
+    /// Delete the subscriptions that are expired at the specified moment, as a Unix timestamp in
+    /// seconds, and return their IDs in no particular order; see [`Metadata::with_expiry()`].
+    ///
+    /// The subscriptions are swept on up to `threads` threads like with [`ATree::retain()`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::num::NonZeroUsize;
+    /// use a_tree::{ATree, AttributeDefinition, Metadata};
+    ///
+    /// let definitions = [AttributeDefinition::boolean("private")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// let metadata = Metadata::new().with_expiry(1_767_225_600);
+    /// atree.insert_with_metadata(&1u64, "private", metadata).unwrap();
+    /// atree.insert(&2u64, "not private").unwrap();
+    ///
+    /// assert!(atree.expire(1_767_225_600, NonZeroUsize::MIN).is_empty());
+    /// assert_eq!(vec![1], atree.expire(1_767_225_601, NonZeroUsize::MIN));
+    /// ```
+    pub fn expire(&mut self, now: u64, threads: NonZeroUsize) -> Vec<T>
+    where
+        T: Send + Sync,
+    {
+        self.retain(threads, |_, metadata| !metadata.is_expired(now))
+    }
+
+    /// Remove the nodes of the deleted subscriptions that were left in the tree with
+    /// [`DeletePolicy::Tombstone`], looking for the nodes they release on up to `threads` threads.
+    ///
+    /// Returns the amount of subscriptions whose nodes were removed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::num::NonZeroUsize;
+    /// use a_tree::{ATree, ATreeConfig, AttributeDefinition, DeletePolicy};
+    ///
+    /// let definitions = [AttributeDefinition::integer("exchange_id")];
+    /// let config = ATreeConfig::new().with_deletes(DeletePolicy::Tombstone);
+    /// let mut atree = ATree::with_config(&definitions, config).unwrap();
+    /// atree.insert(&1u64, "exchange_id = 1").unwrap();
+    /// atree.insert(&2u64, "exchange_id > 0").unwrap();
+    ///
+    /// atree.delete(&1);
+    /// let mut builder = atree.make_event();
+    /// builder.with_integer("exchange_id", 1).unwrap();
+    /// let event = builder.build().unwrap();
+    /// assert_eq!(vec![&2u64], atree.search(&event).unwrap().matches());
+    /// assert_eq!(1, atree.tombstone_count());
+    ///
+    /// assert_eq!(1, atree.compact(NonZeroUsize::MIN));
+    /// assert_eq!(0, atree.tombstone_count());
+    /// ```
+    pub fn compact(&mut self, threads: NonZeroUsize) -> usize
+    where
+        T: Send + Sync,
+    {
+        let ids: Vec<_> = self.tombstones.drain().collect();
+        self.sweep(&ids, threads);
+        ids.len()
+    }
+
+    /// Return the amount of deleted subscriptions whose nodes are waiting for [`ATree::compact()`].
+    #[inline]
+    pub fn tombstone_count(&self) -> usize {
+        self.tombstones.len()
+    }
+
+    /// Whether a subscription was inserted with the ID and was not deleted since.
+    fn contains(&self, subscription_id: &T) -> bool {
+        !self.tombstones.contains(subscription_id)
+            && (self.subscriptions.contains_key(subscription_id)
+                || self.nodes_by_ids.contains_key(subscription_id)
+                || self.always_matching.contains(subscription_id))
+    }
+
+    /// Delete distinct subscriptions according to the [`DeletePolicy`].
+    fn remove(&mut self, subscription_ids: &[T], threads: NonZeroUsize)
+    where
+        T: Send + Sync,
+    {
+        match self.config.deletes() {
+            DeletePolicy::Immediate => self.sweep(subscription_ids, threads),
+            DeletePolicy::Tombstone => {
+                for id in subscription_ids {
+                    self.tombstone(id);
+                }
+            }
+        }
+    }
+
+    /// Mark the subscription as deleted without removing its nodes.
+    fn tombstone(&mut self, subscription_id: &T) {
+        if self.subscriptions.remove(subscription_id).is_none() {
+            return;
+        }
+        self.always_matching.remove(subscription_id);
+        if self.nodes_by_ids.contains_key(subscription_id) {
+            self.tombstones.insert(subscription_id.clone());
+        }
+        self.generation += 1;
+        self.observers.on_delete(subscription_id);
+    }
+
+    /// Delete distinct subscriptions: the nodes they release are found on up to `threads` threads,
+    /// then all of them are removed at once.
+    fn sweep(&mut self, subscription_ids: &[T], threads: NonZeroUsize)
+    where
+        T: Send + Sync,
+    {
+        if subscription_ids.is_empty() {
+            return;
+        }
+        let (released, removed) = plan_sweep(
+            subscription_ids,
+            &self.nodes,
+            &self.nodes_by_ids,
+            threads,
+            self.executor(),
+        );
+
+        let deleted: HashSet<_> = subscription_ids.iter().collect();
+        for (node_id, uses) in &released {
+            let node = &mut self.nodes[*node_id];
+            node.use_count -= uses;
+            node.subscription_ids.retain(|id| !deleted.contains(id));
+        }
+        let nodes = &self.nodes;
+        self.predicates.remove_many(removed.iter().filter_map(
+            |node_id| match &nodes[*node_id].node {
+                ATreeNode::LNode(LNode { predicate, .. }) => Some((*node_id, predicate)),
+                _ => None,
+            },
+        ));
+        let removed: HashSet<_> = removed.into_iter().collect();
+        for node_id in released.keys().filter(|node_id| !removed.contains(node_id)) {
+            self.nodes[*node_id]
+                .node
+                .remove_parents(|parent_id| removed.contains(&parent_id));
+        }
+        self.roots.retain(|node_id| !removed.contains(node_id));
+        for node_id in removed {
+            let node = self.nodes.remove(node_id);
+            self.expression_to_node.remove(&node.id);
+        }
+        self.max_level = get_max_level(&self.roots, &self.nodes);
+
+        // The subscriptions of a compaction were already deleted and do not change the
+        // generation.
+        let mut deleted = false;
+        for id in subscription_ids {
+            self.nodes_by_ids.remove(id);
+            self.always_matching.remove(id);
+            if self.subscriptions.remove(id).is_some() {
+                deleted = true;
+                self.observers.on_delete(id);
+            }
+        }
+        if deleted {
+            self.generation += 1;
+        }
+    }
+
+    #[inline]
+    fn delete_node(&mut self, subscription_id: &T, node_id: NodeId) {
+        let children = decrement_use_count(
+            subscription_id,
+            node_id,
+            &mut self.nodes,
+            &mut self.expression_to_node,
+            &mut self.roots,
+            &mut self.predicates,
+            &mut self.nodes_by_ids,
+            &mut self.max_level,
+        );
+
+        if let Some(children) = children {
+            for child in children {
+                self.delete_node(subscription_id, child);
+            }
+        }
+    }
+
+    /// Export how many times each node was evaluated by the searches in the folded stacks format
+    /// used by tools such as `inferno` or `flamegraph.pl`.
+    ///
+    /// Each line contains the path from an arbitrary boolean expression down to one of its nodes
+    /// followed by the amount of times that node was evaluated. Since the sub-expressions are
+    /// shared, a node appears under every expression that refers to it with the same count.
+    ///
+    /// This is only available when the `profiling` feature is enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [
+    ///     AttributeDefinition::boolean("private"),
+    ///     AttributeDefinition::integer("exchange_id"),
+    /// ];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "private and exchange_id = 1").unwrap();
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_boolean("private", true).unwrap();
+    /// builder.with_integer("exchange_id", 1).unwrap();
+    /// let event = builder.build().unwrap();
+    /// atree.search(&event).unwrap();
+    ///
+    /// let report = atree.profile_report();
+    /// assert_eq!(3, report.lines().count());
+    /// assert!(report.lines().all(|line| line.ends_with(" 1")));
+    /// ```
+    #[cfg(feature = "profiling")]
+    pub fn profile_report(&self) -> String {
+        let mut builder = String::new();
+        let mut stack = Vec::new();
+        for root_id in &self.roots {
+            let root = &self.nodes[*root_id];
+            let frame = format!(
+                "{:?} {}",
+                root.subscription_ids,
+                self.profile_frame(*root_id)
+            );
+            self.write_profile(
+                *root_id,
+                profiling::to_frame(&frame),
+                &mut stack,
+                &mut builder,
+            );
+        }
+        builder
+    }
+
+    /// Time every evaluation of the predicates in the following searches, or stop timing them.
+    ///
+    /// Reading the clock around each predicate slows the searches down, so the timing is disabled
+    /// by default. The timings are reported by [`ATree::predicate_timings()`].
+    ///
+    /// This is only available when the `profiling` feature is enabled.
+    #[cfg(feature = "profiling")]
+    pub fn set_predicate_timing(&mut self, enabled: bool) {
+        self.predicate_timing = enabled;
+    }
+
+    /// Return the wall time spent evaluating each predicate while the predicate timing was
+    /// enabled, from the most expensive predicate to the cheapest one.
+    ///
+    /// The time of a predicate shared by several expressions is only counted once per search
+    /// since its result is reused. The predicates that were never timed are not reported.
+    ///
+    /// This is only available when the `profiling` feature is enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [
+    ///     AttributeDefinition::string("country"),
+    ///     AttributeDefinition::integer("exchange_id"),
+    /// ];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.set_predicate_timing(true);
+    /// atree.insert(&1u64, r#"country in ["FR", "DE", "IT"]"#).unwrap();
+    /// atree.insert(&2u64, "exchange_id = 1").unwrap();
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_string("country", "FR").unwrap();
+    /// let event = builder.build().unwrap();
+    /// atree.search(&event).unwrap();
+    ///
+    /// let timings = atree.predicate_timings();
+    /// assert_eq!(2, timings.len());
+    /// assert!(timings.iter().all(|timing| timing.evaluations() == 1));
+    /// ```
+    #[cfg(feature = "profiling")]
+    pub fn predicate_timings(&self) -> Vec<PredicateTiming> {
+        let names = self
+            .attributes
+            .names()
+            .map(|(name, id)| (id, name))
+            .collect();
+        let strings = self.strings.to_local();
+        let mut timings: Vec<_> = self
+            .nodes
+            .iter()
+            .filter_map(|(_, entry)| match &entry.node {
+                ATreeNode::LNode(LNode { predicate, .. }) if entry.elapsed.samples() > 0 => {
+                    Some(PredicateTiming::new(
+                        PredicateExpression::new(predicate, &names, &strings),
+                        entry.elapsed.samples(),
+                        entry.elapsed.elapsed(),
+                    ))
+                }
+                _ => None,
+            })
+            .collect();
+        timings.sort_by_key(|timing| std::cmp::Reverse(timing.elapsed()));
+        timings
+    }
+
+    #[cfg(feature = "profiling")]
+    fn profile_frame(&self, node_id: NodeId) -> String {
+        match &self.nodes[node_id].node {
+            ATreeNode::LNode(LNode { predicate, .. }) => format!("{predicate}"),
+            ATreeNode::INode(INode { operator, .. }) | ATreeNode::RNode(RNode { operator, .. }) => {
+                format!("{operator:?} #{node_id}")
+            }
+        }
+    }
+
+    #[cfg(feature = "profiling")]
+    fn write_profile(
+        &self,
+        node_id: NodeId,
+        frame: String,
+        stack: &mut Vec<String>,
+        builder: &mut String,
+    ) {
+        use std::fmt::Write;
+
+        stack.push(frame);
+        let entry = &self.nodes[node_id];
+        let evaluations = entry.evaluations.get();
+        if evaluations > 0 {
+            let _ = writeln!(builder, "{} {evaluations}", stack.join(";"));
+        }
+        if !entry.is_leaf() {
+            for child_id in entry.children() {
+                let frame = profiling::to_frame(&self.profile_frame(*child_id));
+                self.write_profile(*child_id, frame, stack, builder);
+            }
+        }
+        stack.pop();
+    }
+
+    /// Export the [`ATree`] to the Graphviz format.
+    pub fn to_graphviz(&self) -> String {
+        const DEFAULT_CAPACITY: usize = 100_000;
+        let mut builder = Vec::with_capacity(DEFAULT_CAPACITY);
+        self.write_graphviz(&mut builder)
+            .expect("writing to a vector cannot fail");
+        String::from_utf8(builder).expect("the Graphviz export only contains UTF-8")
+    }
+
+    /// Write the Graphviz export of [`ATree::to_graphviz()`] to a writer, one node at a time.
+    pub fn write_graphviz(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        writer.write_all(b"digraph {\n")?;
+        writer.write_all(b"rankdir = TB;\n")?;
+        writer.write_all(br#"node [shape = "record"];"#)?;
+        writer.write_all(b"\n")?;
+        let mut relations = Vec::new();
+        let mut levels = vec![vec![]; self.max_level];
+        for (id, entry) in &self.nodes {
+            levels[entry.level() - 1].push(id);
+            match &entry.node {
+                ATreeNode::LNode(LNode { parents, .. }) => {
+                    relations.extend(parents.iter().map(|parent_id| (id, *parent_id)));
+                }
+                ATreeNode::INode(INode {
+                    children, parents, ..
+                }) => {
+                    relations.extend(parents.iter().map(|parent_id| (id, *parent_id)));
+                    relations.extend(children.iter().map(|child_id| (id, *child_id)));
+                }
+                ATreeNode::RNode(RNode { children, .. }) => {
+                    relations.extend(children.iter().map(|child_id| (id, *child_id)));
+                }
+            }
+        }
+
+        writer.write_all(b"\n// nodes\n")?;
+        for ids in levels.into_iter().rev() {
+            for id in &ids {
+                self.write_graphviz_node(*id, writer)?;
+            }
+
+            writer.write_all(b"{rank = same; ")?;
+            for id in ids {
+                write!(writer, "node_{id}; ")?;
+            }
+            writer.write_all(b"};\n")?;
+        }
+
+        writer.write_all(b"\n// edges\n")?;
+        for (from, to) in relations {
+            writeln!(writer, "node_{from} -> node_{to};")?;
+        }
+
+        writer.write_all(b"}")
+    }
+
+    fn write_graphviz_node(&self, id: NodeId, writer: &mut impl io::Write) -> io::Result<()> {
+        let entry = &self.nodes[id];
+        match &entry.node {
+            ATreeNode::LNode(LNode { predicate, .. }) => writeln!(
+                writer,
+                r#"node_{id} [label = "{{{id} | level: {} | {predicate} | subscriptions: {:?} | l-node}}", style = "rounded"];"#,
+                entry.level(),
+                entry.subscription_ids
+            ),
+            ATreeNode::INode(INode { operator, .. }) => writeln!(
+                writer,
+                r#"node_{id} [label = "{{{id} | level: {} | {operator:#?} | subscriptions: {:?} | i-node}}"];"#,
+                entry.level(),
+                entry.subscription_ids
+            ),
+            ATreeNode::RNode(RNode { operator, .. }) => writeln!(
+                writer,
+                r#"node_{id} [label = "{{{id} | level: {} | {operator:#?} | subscriptions: {:?} | r-node}}"];"#,
+                entry.level(),
+                entry.subscription_ids
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn record_search<T>(report: &Report<'_, T>) {
+    let span = tracing::Span::current();
+    let metrics = report.metrics();
+    span.record("matches", report.matches().len());
+    span.record("nodes_visited", metrics.nodes_visited());
+    span.record("predicates_evaluated", metrics.predicates_evaluated());
+    span.record("short_circuits", metrics.short_circuits());
+    span.record("elapsed_us", metrics.elapsed().as_micros() as u64);
+}
+
+#[inline]
+#[allow(clippy::too_many_arguments)]
+fn decrement_use_count<T: Eq + Hash>(
+    subscription_id: &T,
+    node_id: NodeId,
+    nodes: &mut Slab<Entry<T>>,
+    expression_to_node: &mut HashMap<ExpressionId, NodeId>,
+    roots: &mut Vec<NodeId>,
+    predicates: &mut PredicateIndex,
+    nodes_by_ids: &mut HashMap<T, NodeId>,
+    max_level: &mut usize,
+) -> Option<Vec<NodeId>> {
+    let node = &mut nodes[node_id];
+    node.use_count -= 1;
+    let mut children = None;
+    node.subscription_ids.retain(|x| *x != *subscription_id);
+    nodes_by_ids.remove(subscription_id);
+    if node.use_count == 0 {
+        let expression_id = node.id;
+        if !node.is_leaf() {
+            let node_children = node.children().to_vec();
+            for child_id in &node_children {
+                nodes[*child_id]
+                    .node
+                    .remove_parents(|parent_id| parent_id == node_id);
+            }
+            children = Some(node_children);
+        }
+        let node = &nodes[node_id];
+        roots.retain(|x| *x != node_id);
+        if let ATreeNode::LNode(LNode { predicate, .. }) = &node.node {
+            predicates.remove(node_id, predicate);
+        }
+        *max_level = get_max_level(roots, nodes);
+        expression_to_node.remove(&expression_id);
+        nodes.remove(node_id);
+    }
+
+    children
+}
+
+/// Find the nodes released by the deletion of distinct subscriptions without modifying them.
+///
+/// Returns the amount of uses that each node loses along with the nodes that are no longer used.
+/// A node only releases its children once it is no longer used, and the parents are always above
+/// their children, so the levels are processed from the top: the uses lost by the nodes of a
+/// level are all known once the levels above it are done.
+fn plan_sweep<T>(
+    subscription_ids: &[T],
+    nodes: &Slab<Entry<T>>,
+    nodes_by_ids: &HashMap<T, NodeId>,
+    threads: NonZeroUsize,
+    executor: Option<&dyn Executor>,
+) -> (HashMap<NodeId, usize>, Vec<NodeId>)
+where
+    T: Eq + Hash + Sync,
+{
+    let count = |node_ids: &mut dyn Iterator<Item = NodeId>| {
+        let mut counts = HashMap::<NodeId, usize>::new();
+        for node_id in node_ids {
+            *counts.entry(node_id).or_default() += 1;
+        }
+        counts
+    };
+    let mut pending = BTreeMap::<usize, HashMap<NodeId, usize>>::new();
+    let schedule = |pending: &mut BTreeMap<usize, HashMap<NodeId, usize>>, counts| {
+        for (node_id, uses) in counts {
+            *pending
+                .entry(nodes[node_id].level())
+                .or_default()
+                .entry(node_id)
+                .or_default() += uses;
+        }
+    };
+    for counts in loader::parallel_chunks(subscription_ids, threads, executor, |chunk| {
+        count(&mut chunk.iter().filter_map(|id| nodes_by_ids.get(id).copied()))
+    }) {
+        schedule(&mut pending, counts);
+    }
+
+    let mut released = HashMap::new();
+    let mut removed = vec![];
+    while let Some((_, level)) = pending.pop_last() {
+        let level: Vec<_> = level.into_iter().collect();
+        for (freed, children) in loader::parallel_chunks(&level, threads, executor, |chunk| {
+            let freed: Vec<_> = chunk
+                .iter()
+                .filter(|(node_id, uses)| nodes[*node_id].use_count == *uses)
+                .map(|(node_id, _)| *node_id)
+                .collect();
+            let children = count(
+                &mut freed
+                    .iter()
+                    .filter(|node_id| !nodes[**node_id].is_leaf())
+                    .flat_map(|node_id| nodes[*node_id].children().iter().copied()),
+            );
+            (freed, children)
+        }) {
+            removed.extend(freed);
+            schedule(&mut pending, children);
+        }
+        released.extend(level);
+    }
+    (released, removed)
+}
+
+#[inline]
+fn insert_node<T>(
+    expression_to_node: &mut HashMap<ExpressionId, NodeId>,
+    nodes: &mut Slab<Entry<T>>,
+    expression_id: &ExpressionId,
+    node: ATreeNode,
+    subscription_id: Option<T>,
+    cost: u64,
+) -> NodeId {
+    let entry = Entry::new(*expression_id, node, subscription_id, cost);
+    let node_id = nodes.insert(entry);
+    if expression_to_node.insert(*expression_id, node_id).is_some() {
+        unreachable!("{expression_id} is already present; this is a bug");
+    }
+    node_id
+}
+
+#[inline]
+fn add_parent<T>(entry: &mut Entry<T>, node_id: NodeId) {
+    entry.node.add_parent(node_id);
+}
+
+#[inline]
+fn add_subscription_id<T: Eq + Hash + Clone>(
+    subscription_id: &T,
+    node_id: NodeId,
+    nodes: &mut Slab<Entry<T>>,
+    nodes_by_ids: &mut HashMap<T, NodeId>,
+) {
+    nodes[node_id]
+        .subscription_ids
+        .push(subscription_id.clone());
+    nodes_by_ids.insert(subscription_id.clone(), node_id);
+}
+
+#[inline]
+fn increment_use_count<T>(node_id: NodeId, nodes: &mut Slab<Entry<T>>) {
+    nodes[node_id].use_count += 1;
+}
+
+#[inline]
+fn get_max_level<T>(roots: &[NodeId], nodes: &Slab<Entry<T>>) -> usize {
+    roots
+        .iter()
+        .map(|root_id| nodes[*root_id].level())
+        .max()
+        .unwrap_or(1)
+}
+
+#[inline]
+fn change_rnode_to_inode<T>(node_id: NodeId, nodes: &mut Slab<Entry<T>>) {
+    let entry = &mut nodes[node_id];
+    if let ATreeNode::RNode(RNode {
+        children,
+        level,
+        operator,
+    }) = &entry.node
+    {
+        let inode = ATreeNode::INode(INode {
+            parents: vec![],
+            children: children.to_vec(),
+            level: *level,
+            operator: operator.clone(),
+        });
+        entry.node = inode;
+    }
+}
+
+#[inline]
+fn choose_access_child<T>(
+    left_id: NodeId,
+    right_id: NodeId,
+    parent_id: NodeId,
+    order: ConjunctionOrder,
+    nodes: &mut Slab<Entry<T>>,
+    predicates: &mut PredicateIndex,
+) {
+    let left_entry = &nodes[left_id];
+    let right_entry = &nodes[right_id];
+    let accessor_id = match order {
+        ConjunctionOrder::LeftToRight => left_id,
+        ConjunctionOrder::Cost if left_entry.cost < right_entry.cost => left_id,
+        ConjunctionOrder::Cost => right_id,
+    };
+    add_parent(&mut nodes[accessor_id], parent_id);
+    add_predicate(accessor_id, nodes, predicates);
+}
+
+#[inline]
+fn add_predicate<T>(node_id: NodeId, nodes: &Slab<Entry<T>>, predicates: &mut PredicateIndex) {
+    if let ATreeNode::LNode(LNode { predicate, .. }) = &nodes[node_id].node {
+        predicates.insert(node_id, predicate);
+    }
+}
+
+#[inline]
+fn process_predicates<'a, T>(
+    predicates: &PredicateIndex,
+    nodes: &'a Slab<Entry<T>>,
+    event: &Event,
+    matches: &mut impl Matches<'a, T>,
+    results: &mut EvaluationResult,
+    queues: &mut [Vec<(NodeId, &'a Entry<T>)>],
+    recorder: &mut Recorder,
+) {
+    for predicate_id in predicates.linear() {
+        if matches.is_full() {
+            return;
+        }
+        let node = &nodes[*predicate_id];
+        if is_delayed(node) || results.is_evaluated(*predicate_id) {
+            continue;
+        }
+
+        let result = evaluate_predicate(node, event, recorder);
+        propagate_predicate(
+            *predicate_id,
+            node,
+            result,
+            nodes,
+            matches,
+            results,
+            queues,
+            recorder,
+        );
+    }
+
+    // The comparisons and the patterns are decided all at once for each attribute by the indexes.
+    for (predicate_id, result) in predicates.indexed(event) {
+        if matches.is_full() {
+            return;
+        }
+        let node = &nodes[predicate_id];
+        if is_delayed(node) || results.is_evaluated(predicate_id) {
+            continue;
+        }
+
+        recorder.evaluate_predicate();
+        #[cfg(feature = "profiling")]
+        node.evaluations.increment();
+        propagate_predicate(
+            predicate_id,
+            node,
+            result,
+            nodes,
+            matches,
+            results,
+            queues,
+            recorder,
+        );
+    }
+}
+
+/// Whether the evaluation of the predicate is delayed as much as possible; if the predicate has
+/// no subscribers and no parents, there is no point in evaluating eagerly and it should only be
+/// evaluated if there is a need for it.
+#[inline]
+fn is_delayed<T>(node: &Entry<T>) -> bool {
+    node.subscription_ids.is_empty() && node.parents().is_empty()
+}
+
+#[inline]
+#[allow(clippy::too_many_arguments)]
+fn propagate_predicate<'a, T>(
+    predicate_id: NodeId,
+    node: &'a Entry<T>,
+    result: Option<bool>,
+    nodes: &'a Slab<Entry<T>>,
+    matches: &mut impl Matches<'a, T>,
+    results: &mut EvaluationResult,
+    queues: &mut [Vec<(NodeId, &'a Entry<T>)>],
+    recorder: &mut Recorder,
+) {
+    results.set_result(predicate_id, result);
+    add_matches(result, node, matches);
+
+    node.parents()
+        .iter()
+        .map(|parent_id| (*parent_id, &nodes[*parent_id]))
+        .for_each(|(parent_id, parent)| {
+            if matches!(parent.operator(), Operator::And) && !result.unwrap_or(true) {
+                results.set_result(parent_id, Some(false));
+                recorder.short_circuit();
+            } else {
+                queues[parent.level() - 2].push((parent_id, parent));
+            }
+        })
+}
+
+/// Evaluate a predicate against the [`Event`], timing the evaluation when it is enabled.
+#[inline]
+fn evaluate_predicate<T>(node: &Entry<T>, event: &Event, recorder: &mut Recorder) -> Option<bool> {
+    recorder.evaluate_predicate();
+    #[cfg(feature = "profiling")]
+    if recorder.times_predicates() {
+        let start = Instant::now();
+        let result = node.evaluate(event);
+        node.elapsed.record(start.elapsed());
+        return result;
+    }
+    node.evaluate(event)
+}
+
+#[inline]
+fn evaluate_node<'a, T>(
+    node_id: NodeId,
+    event: &Event,
+    node: &'a Entry<T>,
+    nodes: &'a Slab<Entry<T>>,
+    results: &mut EvaluationResult,
+    matches: &mut impl Matches<'a, T>,
+    recorder: &mut Recorder,
+) -> Option<bool> {
+    recorder.visit_node();
+    #[cfg(feature = "profiling")]
+    node.evaluations.increment();
+    let operator = node.operator();
+    let result = match operator {
+        Operator::And => evaluate_and(node.children(), event, nodes, results, matches, recorder),
+        Operator::Or => evaluate_or(node.children(), event, nodes, results, matches, recorder),
+    };
+    results.set_result(node_id, result);
+    result
+}
+
+#[inline]
+fn evaluate_and<'a, T>(
+    children: &[NodeId],
+    event: &Event,
+    nodes: &'a Slab<Entry<T>>,
+    results: &mut EvaluationResult,
+    matches: &mut impl Matches<'a, T>,
+    recorder: &mut Recorder,
+) -> Option<bool> {
+    let mut acc = Some(true);
+    for (index, child_id) in children.iter().enumerate() {
+        let result = lazy_evaluate(*child_id, event, nodes, results, matches, recorder);
+        match (acc, result) {
+            (Some(false), _) | (_, Some(false)) => {
+                acc = Some(false);
+                if index + 1 < children.len() {
+                    recorder.short_circuit();
+                }
+                break;
+            }
+            (Some(a), Some(b)) => {
+                acc = Some(a && b);
+            }
+            (_, _) => {
+                acc = None;
+            }
+        }
+    }
+    acc
+}
+
+#[inline]
+fn evaluate_or<'a, T>(
+    children: &[NodeId],
+    event: &Event,
+    nodes: &'a Slab<Entry<T>>,
+    results: &mut EvaluationResult,
+    matches: &mut impl Matches<'a, T>,
+    recorder: &mut Recorder,
+) -> Option<bool> {
+    let mut acc = Some(false);
+    for (index, child_id) in children.iter().enumerate() {
+        let result = lazy_evaluate(*child_id, event, nodes, results, matches, recorder);
+        match (acc, result) {
+            (Some(true), _) | (_, Some(true)) => {
+                acc = Some(true);
+                if index + 1 < children.len() {
+                    recorder.short_circuit();
+                }
+                break;
+            }
+            (Some(a), Some(b)) => {
+                acc = Some(a || b);
+            }
+            (_, _) => {
+                acc = None;
+            }
+        }
+    }
+
+    acc
+}
+
+#[inline]
+/// Evaluate the expression of a node without the results of the other expressions.
+fn evaluate_linearly<T>(node_id: NodeId, event: &Event, nodes: &Slab<Entry<T>>) -> Option<bool> {
+    let entry = &nodes[node_id];
+    if entry.is_leaf() {
+        return entry.node.evaluate(event);
+    }
+    let (decisive, neutral) = match entry.operator() {
+        Operator::And => (false, true),
+        Operator::Or => (true, false),
+    };
+    let mut result = Some(neutral);
+    for child_id in entry.children() {
+        match evaluate_linearly(*child_id, event, nodes) {
+            Some(value) if value == decisive => return Some(decisive),
+            Some(_) => {}
+            None => result = None,
+        }
+    }
+    result
+}
+
+fn lazy_evaluate<'a, T>(
+    node_id: NodeId,
+    event: &Event,
+    nodes: &'a Slab<Entry<T>>,
+    results: &mut EvaluationResult,
+    matches: &mut impl Matches<'a, T>,
+    recorder: &mut Recorder,
+) -> Option<bool> {
+    if results.is_evaluated(node_id) {
+        return results.get_result(node_id);
+    }
+    let node = &nodes[node_id];
+    let result = if node.is_leaf() {
+        let result = evaluate_predicate(node, event, recorder);
+        results.set_result(node_id, result);
+        result
+    } else {
+        evaluate_node(node_id, event, node, nodes, results, matches, recorder)
+    };
+    add_matches(result, node, matches);
+    result
+}
+
+/// Where a search puts the subscriptions of the matching expressions
+trait Matches<'a, T> {
+    fn push(&mut self, subscription_id: &'a T);
+
+    /// Whether no more matches are accepted, to stop the evaluation early.
+    #[inline]
+    fn is_full(&self) -> bool {
+        false
+    }
+}
+
+impl<'a, T> Matches<'a, T> for Vec<&'a T> {
+    #[inline]
+    fn push(&mut self, subscription_id: &'a T) {
+        Vec::push(self, subscription_id);
+    }
+}
+
+/// Only count the matches, without collecting them.
+impl<'a, T> Matches<'a, T> for usize {
+    #[inline]
+    fn push(&mut self, _: &'a T) {
+        *self += 1;
+    }
+}
+
+/// Only keep the matches that are among the candidates.
+struct Among<'a, 'c, T> {
+    candidates: &'c HashSet<T>,
+    matches: Vec<&'a T>,
+}
+
+impl<'a, T: Eq + Hash> Matches<'a, T> for Among<'a, '_, T> {
+    #[inline]
+    fn push(&mut self, subscription_id: &'a T) {
+        if self.candidates.contains(subscription_id) {
+            self.matches.push(subscription_id);
+        }
+    }
+}
+
+impl<'a, T> From<Among<'a, '_, T>> for Vec<&'a T> {
+    #[inline]
+    fn from(among: Among<'a, '_, T>) -> Self {
+        among.matches
+    }
+}
+
+/// Only keep the first `max` matches.
+struct Limited<'a, T> {
+    max: usize,
+    matches: Vec<&'a T>,
+}
+
+impl<'a, T> Matches<'a, T> for Limited<'a, T> {
+    #[inline]
+    fn push(&mut self, subscription_id: &'a T) {
+        if !self.is_full() {
+            self.matches.push(subscription_id);
+        }
+    }
+
+    #[inline]
+    fn is_full(&self) -> bool {
+        self.matches.len() >= self.max
+    }
+}
+
+impl<'a, T> From<Limited<'a, T>> for Vec<&'a T> {
+    #[inline]
+    fn from(limited: Limited<'a, T>) -> Self {
+        limited.matches
+    }
+}
+
+/// Skip the subscriptions that were deleted but whose nodes are still in the tree.
+struct Alive<'a, 'm, T, M> {
+    tombstones: &'a HashSet<T>,
+    matches: &'m mut M,
+}
+
+impl<'a, T, M> Matches<'a, T> for Alive<'a, '_, T, M>
+where
+    T: Eq + Hash,
+    M: Matches<'a, T>,
+{
+    #[inline]
+    fn push(&mut self, subscription_id: &'a T) {
+        if !self.tombstones.contains(subscription_id) {
+            self.matches.push(subscription_id);
+        }
+    }
+
+    #[inline]
+    fn is_full(&self) -> bool {
+        self.matches.is_full()
+    }
+}
+
+/// Skip the subscriptions that already matched through another of their expressions.
+struct Unique<'a, 'm, T, M> {
+    seen: HashSet<&'a T>,
+    matches: &'m mut M,
+}
+
+impl<'a, T, M> Matches<'a, T> for Unique<'a, '_, T, M>
+where
+    T: Eq + Hash,
+    M: Matches<'a, T>,
+{
+    #[inline]
+    fn push(&mut self, subscription_id: &'a T) {
+        if self.seen.insert(subscription_id) {
+            self.matches.push(subscription_id);
+        }
+    }
+
+    #[inline]
+    fn is_full(&self) -> bool {
+        self.matches.is_full()
+    }
+}
+
+#[inline]
+fn add_matches<'a, T>(result: Option<bool>, node: &'a Entry<T>, matches: &mut impl Matches<'a, T>) {
+    if !node.subscription_ids.is_empty() {
+        if let Some(true) = result {
+            for subscription_id in &node.subscription_ids {
+                matches.push(subscription_id);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Entry<T> {
+    id: ExpressionId,
+    subscription_ids: Vec<T>,
+    node: ATreeNode,
+    use_count: usize,
+    cost: u64,
+    #[cfg(feature = "profiling")]
+    evaluations: EvaluationCounter,
+    #[cfg(feature = "profiling")]
+    elapsed: EvaluationTimer,
+}
+
+impl<T> Entry<T> {
+    fn new(id: ExpressionId, node: ATreeNode, subscription_id: Option<T>, cost: u64) -> Self {
+        Self {
+            id,
+            node,
+            use_count: 1,
+            subscription_ids: subscription_id
+                .map_or_else(Vec::new, |subscription_id| vec![subscription_id]),
+            cost,
+            #[cfg(feature = "profiling")]
+            evaluations: EvaluationCounter::default(),
+            #[cfg(feature = "profiling")]
+            elapsed: EvaluationTimer::default(),
+        }
+    }
+
+    /// The heap bytes used by the entry, without its own size.
+    fn heap_bytes(&self) -> usize {
+        let node = match &self.node {
+            ATreeNode::LNode(LNode {
+                parents, predicate, ..
+            }) => stats::vec_bytes(parents) + predicate.heap_bytes(),
+            ATreeNode::INode(INode {
+                parents, children, ..
+            }) => stats::vec_bytes(parents) + stats::vec_bytes(children),
+            ATreeNode::RNode(RNode { children, .. }) => stats::vec_bytes(children),
+        };
+        stats::vec_bytes(&self.subscription_ids) + node
+    }
+
+    #[inline]
+    const fn is_leaf(&self) -> bool {
+        matches!(self.node, ATreeNode::LNode(_))
+    }
+
+    #[inline]
+    const fn is_root(&self) -> bool {
+        matches!(self.node, ATreeNode::RNode(_))
+    }
+
+    #[inline]
+    const fn level(&self) -> usize {
+        self.node.level()
+    }
+
+    #[inline]
+    fn evaluate(&self, event: &Event) -> Option<bool> {
+        #[cfg(feature = "profiling")]
+        self.evaluations.increment();
+        self.node.evaluate(event)
+    }
+
+    #[inline]
+    fn operator(&self) -> Operator {
+        self.node.operator()
+    }
+
+    #[inline]
+    fn children(&self) -> &[NodeId] {
+        self.node.children()
+    }
+
+    #[inline]
+    fn parents(&self) -> &[NodeId] {
+        self.node.parents()
+    }
+}
+
+#[derive(Clone, Debug)]
+#[allow(clippy::enum_variant_names)]
+enum ATreeNode {
+    LNode(LNode),
+    INode(INode),
+    RNode(RNode),
+}
+
+impl ATreeNode {
+    #[inline]
+    fn lnode(predicate: &Predicate) -> Self {
+        Self::LNode(LNode {
+            level: 1,
+            parents: vec![],
+            predicate: predicate.clone(),
+        })
+    }
+
+    #[inline]
+    const fn level(&self) -> usize {
+        match self {
+            Self::RNode(node) => node.level,
+            Self::LNode(node) => node.level,
+            Self::INode(node) => node.level,
+        }
+    }
+
+    #[inline]
+    fn evaluate(&self, event: &Event) -> Option<bool> {
+        match self {
+            Self::LNode(node) => node.predicate.evaluate(event),
+            node => unreachable!("evaluating {node:?} which is not a predicate; this is a bug."),
+        }
+    }
+
+    #[inline]
+    fn operator(&self) -> Operator {
+        match self {
+            Self::LNode(_) => {
+                unreachable!("trying to get the operator of leaf node; this is a bug");
+            }
+            Self::RNode(RNode { operator, .. }) | Self::INode(INode { operator, .. }) => {
+                operator.clone()
+            }
+        }
+    }
+
+    #[inline]
+    fn children(&self) -> &[NodeId] {
+        match self {
+            Self::INode(INode { children, .. }) | Self::RNode(RNode { children, .. }) => children,
+            Self::LNode(_) => unreachable!("cannot get children for l-node; this is a bug"),
+        }
+    }
+
+    #[inline]
+    fn parents(&self) -> &[NodeId] {
+        match self {
+            Self::INode(INode { parents, .. }) | Self::LNode(LNode { parents, .. }) => parents,
+            Self::RNode(_) => unreachable!("cannot get parents for r-node; this is a bug"),
+        }
+    }
+
+    #[inline]
+    fn add_parent(&mut self, parent_id: NodeId) {
+        match self {
+            ATreeNode::INode(node) => {
+                node.parents.push(parent_id);
+            }
+            ATreeNode::LNode(node) => {
+                node.parents.push(parent_id);
+            }
+            ATreeNode::RNode(node) => {
+                unreachable!("trying to insert parents to r-node {node:?} which cannot have any parents; this is a bug");
+            }
+        }
+    }
+
+    /// Forget the parents that were removed; an r-node has no parents to forget.
+    #[inline]
+    fn remove_parents(&mut self, removed: impl Fn(NodeId) -> bool) {
+        match self {
+            ATreeNode::INode(INode { parents, .. }) | ATreeNode::LNode(LNode { parents, .. }) => {
+                parents.retain(|parent_id| !removed(*parent_id));
+            }
+            ATreeNode::RNode(_) => {}
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct LNode {
+    parents: Vec<NodeId>,
+    level: usize,
+    predicate: Predicate,
+}
+
+#[derive(Clone, Debug)]
+struct INode {
+    parents: Vec<NodeId>,
+    children: Vec<NodeId>,
+    level: usize,
+    operator: Operator,
+}
+
+#[derive(Clone, Debug)]
+struct RNode {
+    children: Vec<NodeId>,
+    level: usize,
+    operator: Operator,
+}
+
+#[derive(Debug)]
+/// Structure that holds the search results from the [`ATree::search()`] function
+pub struct Report<'a, T> {
+    matches: Vec<&'a T>,
+    undefined: Vec<&'a T>,
+    generation: u64,
+    #[cfg(feature = "metrics")]
+    metrics: SearchMetrics,
+}
+
+impl<'a, T> Report<'a, T> {
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    fn new(
+        matches: Vec<&'a T>,
+        undefined: Vec<&'a T>,
+        generation: u64,
+        recorder: Recorder,
+    ) -> Self {
+        Self {
+            matches,
+            undefined,
+            generation,
+            #[cfg(feature = "metrics")]
+            metrics: recorder.finish(),
+        }
+    }
+
+    #[inline]
+    /// Get the search matches
+    ///
+    /// Each matching subscription appears once, even when its ID was inserted with several
+    /// expressions that match, but the order is unspecified; see [`Report::into_sorted()`].
+    pub fn matches(&self) -> &[&'a T] {
+        &self.matches
+    }
+
+    #[inline]
+    /// Get the subscriptions that did not match because their result depends on an attribute
+    /// that is undefined in the event
+    ///
+    /// This is only filled by [`ATree::search_with_undefined()`] and is empty for the other
+    /// searches.
+    pub fn undefined(&self) -> &[&'a T] {
+        &self.undefined
+    }
+
+    #[inline]
+    /// Get the generation of the tree when it was searched; see [`ATree::generation()`]
+    pub const fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Sort the search matches by ID.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::integer("exchange_id")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&3u64, "exchange_id = 1").unwrap();
+    /// atree.insert(&1u64, "exchange_id < 5").unwrap();
+    /// atree.insert(&2u64, "exchange_id > 0").unwrap();
+    /// atree.insert(&1u64, "exchange_id > 0").unwrap();
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_integer("exchange_id", 1).unwrap();
+    /// let event = builder.build().unwrap();
+    ///
+    /// let matches = atree.search(&event).unwrap().into_sorted();
+    /// assert_eq!([&1u64, &2, &3], matches.as_slice());
+    /// assert!(matches.contains(&2));
+    /// ```
+    pub fn into_sorted(self) -> SortedMatches<'a, T>
+    where
+        T: Ord,
+    {
+        let mut matches = self.matches;
+        matches.sort_unstable();
+        SortedMatches(matches)
+    }
+
+    #[cfg(feature = "metrics")]
+    #[inline]
+    /// Get the counters that were collected during the search
+    pub const fn metrics(&self) -> &SearchMetrics {
+        &self.metrics
+    }
+}
+
+/// Search matches sorted by ID, without duplicates; see [`Report::into_sorted()`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SortedMatches<'a, T>(Vec<&'a T>);
+
+impl<'a, T: Ord> SortedMatches<'a, T> {
+    #[inline]
+    /// Get the matches in ascending order
+    pub fn as_slice(&self) -> &[&'a T] {
+        &self.0
+    }
+
+    #[inline]
+    /// Check whether the subscription matched, with a binary search
+    pub fn contains(&self, subscription_id: &T) -> bool {
+        self.0.binary_search(&subscription_id).is_ok()
+    }
+
+    #[inline]
+    /// Get the number of matches
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    /// Check whether nothing matched
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[inline]
+    /// Iterate over the matches in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = &'a T> + '_ {
+        self.0.iter().copied()
+    }
+}
+
+impl<'a, T> IntoIterator for SortedMatches<'a, T> {
+    type Item = &'a T;
+    type IntoIter = std::vec::IntoIter<&'a T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AN_INVALID_BOOLEAN_EXPRESSION: &str = "invalid in (1, 2, 3 and";
+    const AN_EXPRESSION: &str = "exchange_id = 1";
+    const A_NOT_EXPRESSION: &str = "not private";
+    const AN_EXPRESSION_WITH_AND_OPERATORS: &str =
+        r#"exchange_id = 1 and deals one of ["deal-1", "deal-2"]"#;
+    const AN_EXPRESSION_WITH_OR_OPERATORS: &str =
+        r#"exchange_id = 1 or deals one of ["deal-1", "deal-2"]"#;
+    const A_COMPLEX_EXPRESSION: &str = r#"exchange_id = 1 and not private and deal_ids one of ["deal-1", "deal-2"] and segment_ids one of [1, 2, 3] and country = 'CA' and city in ['QC'] or country = 'US' and city in ['AZ']"#;
+    const ANOTHER_COMPLEX_EXPRESSION: &str = r#"exchange_id = 1 and not private and deal_ids one of ["deal-1", "deal-2"] and segment_ids one of [1, 2, 3] and country in ['FR', 'GB']"#;
+
+    fn is_sync_and_send<T: Send + Sync>() {}
+
+    #[test]
+    fn support_sync_and_send_traits() {
+        is_sync_and_send::<ATree<u64>>();
+    }
+
+    #[test]
+    fn fold_the_boolean_literals_without_creating_nodes() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree
+            .insert(&1u64, "false and (private or exchange_id = 1)")
+            .unwrap();
+        atree.insert(&2u64, "true or private").unwrap();
+        atree.insert(&3u64, "true and private").unwrap();
+
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", false).unwrap();
+        let event = builder.build().unwrap();
+        let mut matches = atree.search(&event).unwrap().matches().to_vec();
+        matches.sort();
+
+        assert_eq!(1, atree.nodes.len());
+        assert_eq!(vec![&2u64], matches);
+        assert_eq!(
+            Some("false and (private or exchange_id = 1)"),
+            atree.expression(&1)
+        );
+    }
+
+    #[test]
+    fn insert_the_expressions_of_the_compilation_cache_after_a_restart() {
+        let path = std::env::temp_dir().join("a-tree-compilation-cache-restart.bin");
+        let definitions = [
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::boolean("private"),
+        ];
+        let subscriptions = [
+            (1u64, r#"country in ["US", "CA"] and private"#),
+            (2, r#"deals one of ["deal-1", "deal-3"]"#),
+            (3, "private"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        for (id, expression) in subscriptions {
+            atree.insert(&id, expression).unwrap();
+        }
+        assert_eq!(2, atree.save_compilation_cache(&path).unwrap());
+
+        let mut restarted = ATree::new(&definitions).unwrap();
+        assert_eq!(2, restarted.load_compilation_cache(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        restarted.insert(&1, subscriptions[0].1).unwrap();
+        let results = restarted.insert_many(&subscriptions[1..], NonZeroUsize::MIN);
+        let mut builder = restarted.make_event();
+        builder.with_string("country", "CA").unwrap();
+        builder.with_string_list("deals", &["deal-3"]).unwrap();
+        builder.with_boolean("private", true).unwrap();
+        let event = builder.build().unwrap();
+
+        assert!(results.iter().all(Result::is_ok));
+        let sorted = restarted.search(&event).unwrap().into_sorted();
+        assert_eq!([&1, &2, &3], sorted.as_slice());
+    }
+
+    #[test]
+    fn increment_the_generation_on_every_mutation() {
+        let definitions = [AttributeDefinition::boolean("private")];
+        let mut atree = ATree::new(&definitions).unwrap();
+        assert_eq!(0, atree.generation());
+
+        atree.insert(&1u64, "private").unwrap();
+        atree.insert(&2u64, "not private").unwrap();
+        assert!(atree.insert(&3u64, "private =").is_err());
+        atree.delete(&42);
+        assert_eq!(2, atree.generation());
+        let event = atree.make_event().build().unwrap();
+        let report = atree.search(&event).unwrap();
+        assert_eq!(2, report.generation());
+
+        atree.delete(&1);
+        atree.delete_many(&[2, 3], NonZeroUsize::MIN);
+        atree.delete_many(&[42], NonZeroUsize::MIN);
+        assert_eq!(4, atree.generation());
+        assert_eq!(4, atree.search(&event).unwrap().generation());
+    }
+
+    #[test]
+    fn skip_the_tombstoned_subscriptions_until_the_compaction() {
+        let definitions = [
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::boolean("private"),
+        ];
+        let config = ATreeConfig::new().with_deletes(DeletePolicy::Tombstone);
+        let mut atree = ATree::with_config(&definitions, config).unwrap();
+        atree.insert(&1u64, "exchange_id = 1").unwrap();
+        atree.insert(&2u64, "exchange_id = 1").unwrap();
+        atree.insert(&3u64, "exchange_id > 0 and private").unwrap();
+        atree.insert(&4u64, "true").unwrap();
+        let nodes = atree.nodes.len();
+
+        atree.delete(&1);
+        atree.delete(&4);
+        atree.delete_many(&[3, 42], NonZeroUsize::MIN);
+
+        let mut builder = atree.make_event();
+        builder.with_integer("exchange_id", 1).unwrap();
+        let event = builder.build().unwrap();
+        let report = atree.search_with_undefined(&event).unwrap();
+        assert_eq!([&2], report.matches());
+        assert!(report.undefined().is_empty());
+        assert_eq!(1, atree.search_limited(&event, 1).unwrap().matches().len());
+        assert_eq!(1, atree.match_count(&event).unwrap());
+        assert_eq!(vec![&2], atree.baseline_search(&event));
+        assert_eq!(None, atree.expression(&1));
+        assert_eq!(nodes, atree.nodes.len());
+        assert_eq!(2, atree.tombstone_count());
+
+        let generation = atree.generation();
+        assert_eq!(2, atree.compact(NonZeroUsize::MIN));
+
+        assert_eq!(generation, atree.generation());
+        assert_eq!(0, atree.tombstone_count());
+        assert!(atree.nodes.len() < nodes);
+        assert_eq!(vec![&2], atree.search(&event).unwrap().matches());
+    }
+
+    #[test]
+    fn only_match_the_new_expression_of_a_tombstoned_id_inserted_again() {
+        let definitions = [AttributeDefinition::integer("exchange_id")];
+        let config = ATreeConfig::new().with_deletes(DeletePolicy::Tombstone);
+        let mut atree = ATree::with_config(&definitions, config).unwrap();
+        atree.insert(&1u64, "exchange_id = 1").unwrap();
+        atree.delete(&1);
+
+        atree.insert(&1u64, "exchange_id = 2").unwrap();
+
+        let mut builder = atree.make_event();
+        builder.with_integer("exchange_id", 1).unwrap();
+        let event = builder.build().unwrap();
+        assert!(atree.search(&event).unwrap().matches().is_empty());
+        assert_eq!(0, atree.tombstone_count());
+        assert_eq!(0, atree.compact(NonZeroUsize::MIN));
+        let mut builder = atree.make_event();
+        builder.with_integer("exchange_id", 2).unwrap();
+        let event = builder.build().unwrap();
+        assert_eq!(vec![&1], atree.search(&event).unwrap().matches());
+    }
+
+    #[test]
+    fn replace_the_expression_of_an_id_under_the_tombstone_policy() {
+        let definitions = [AttributeDefinition::integer("x")];
+        let config = ATreeConfig::new()
+            .with_deletes(DeletePolicy::Tombstone)
+            .with_duplicate_ids(DuplicateIdPolicy::Replace);
+        let mut atree = ATree::with_config(&definitions, config).unwrap();
+        atree.insert(&1u64, "x = 1").unwrap();
+        atree.insert(&1u64, "x = 2").unwrap();
+        let search = |atree: &ATree<u64>, x| {
+            let mut builder = atree.make_event();
+            builder.with_integer("x", x).unwrap();
+            let event = builder.build().unwrap();
+            atree.search(&event).unwrap().matches().len()
+        };
+
+        assert_eq!(0, search(&atree, 1));
+        assert_eq!(1, search(&atree, 2));
+        assert_eq!(Some("x = 2"), atree.expression(&1));
+        assert_eq!(0, atree.tombstone_count());
+
+        atree.compact(NonZeroUsize::MIN);
+
+        assert_eq!(0, search(&atree, 1));
+        assert_eq!(1, search(&atree, 2));
+        assert_eq!(Some("x = 2"), atree.expression(&1));
+    }
+
+    #[test]
+    fn can_delete_a_constant_expression() {
+        let definitions = [AttributeDefinition::boolean("private")];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree.insert(&1u64, "not false").unwrap();
+        atree.insert(&2u64, "false").unwrap();
+
+        atree.delete(&1);
+        atree.delete(&2);
+
+        let event = atree.make_event().build().unwrap();
+        assert!(atree.search(&event).unwrap().matches().is_empty());
+        assert_eq!(None, atree.expression(&1));
+        assert_eq!(None, atree.expression(&2));
+    }
+
+    #[test]
+    fn insert_many_expressions_in_parallel() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::string("country"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        let subscriptions: Vec<_> = (0u64..100)
+            .map(|id| match id % 3 {
+                0 => (id, "private"),
+                1 => (id, r#"country = "CA""#),
+                _ => (id, "country ="),
+            })
+            .collect();
+
+        let results = atree.insert_many(&subscriptions, NonZeroUsize::new(4).unwrap());
+
+        assert_eq!(100, results.len());
+        for ((id, _), result) in subscriptions.iter().zip(&results) {
+            assert_eq!(id % 3 != 2, result.is_ok(), "subscription {id}");
+        }
+        let mut builder = atree.make_event();
+        builder.with_string("country", "CA").unwrap();
+        let event = builder.build().unwrap();
+        assert_eq!(33, atree.search(&event).unwrap().matches().len());
+    }
+
+    #[test]
+    fn run_the_chunks_of_the_batch_operations_with_the_executor() {
+        #[derive(Default)]
+        struct Sequential {
+            jobs: std::sync::atomic::AtomicUsize,
+        }
+
+        impl Executor for Sequential {
+            fn execute(&self, jobs: Vec<crate::executor::Job<'_>>) {
+                for job in jobs {
+                    self.jobs.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    job();
+                }
+            }
+        }
+
+        let definitions = [AttributeDefinition::integer("age")];
+        let mut atree = ATree::new(&definitions).unwrap();
+        let executor = Arc::new(Sequential::default());
+        atree.set_executor(executor.clone());
+        let subscriptions: Vec<_> = (0u64..10)
+            .map(|id| (id, if id % 2 == 0 { "age > 18" } else { "age <" }))
+            .collect();
+        let threads = NonZeroUsize::new(4).unwrap();
+
+        let results = atree.insert_many(&subscriptions, threads);
+        let mut deleted = atree.retain(threads, |id, _| *id < 4);
+        deleted.sort_unstable();
+
+        for ((id, _), result) in subscriptions.iter().zip(&results) {
+            assert_eq!(id % 2 == 0, result.is_ok(), "subscription {id}");
+        }
+        assert_eq!(vec![4, 6, 8], deleted);
+        assert!(executor.jobs.load(std::sync::atomic::Ordering::Relaxed) >= 8);
+        let mut builder = atree.make_event();
+        builder.with_integer("age", 20).unwrap();
+        let event = builder.build().unwrap();
+        let report = atree.search(&event).unwrap();
+        let mut matches: Vec<_> = report.matches().iter().map(|id| **id).collect();
+        matches.sort_unstable();
+        assert_eq!(vec![0, 2], matches);
+    }
+
+    #[test]
+    fn report_the_progress_of_each_phase_of_the_batches_of_a_bulk_insertion() {
+        let definitions = [AttributeDefinition::integer("exchange_id")];
+        let mut atree = ATree::new(&definitions).unwrap();
+        let expressions: Vec<_> = (0..=loader::BATCH_SIZE)
+            .map(|id| format!("exchange_id = {id}"))
+            .collect();
+        let subscriptions: Vec<_> = expressions
+            .iter()
+            .enumerate()
+            .map(|(id, expression)| (id as u64, expression.as_str()))
+            .collect();
+        let mut reported = vec![];
+
+        let results = atree.insert_many_with_progress(
+            &subscriptions,
+            NonZeroUsize::new(2).unwrap(),
+            |progress| reported.push((progress.parsed(), progress.merged())),
+        );
+
+        assert!(results.iter().all(Result::is_ok));
+        let total = subscriptions.len();
+        assert_eq!(
+            vec![
+                (loader::BATCH_SIZE, 0),
+                (loader::BATCH_SIZE, loader::BATCH_SIZE),
+                (total, loader::BATCH_SIZE),
+                (total, total)
+            ],
+            reported
+        );
+    }
+
+    #[test]
+    fn count_the_same_matches_as_the_search() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deals"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree.insert(&1u64, "private and exchange_id = 1").unwrap();
+        atree
+            .insert(&2u64, r#"exchange_id = 1 or deals one of ["deal-1"]"#)
+            .unwrap();
+        atree.insert(&3u64, "not private").unwrap();
+        atree.insert(&4u64, "not false").unwrap();
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", true).unwrap();
+        builder.with_integer("exchange_id", 1).unwrap();
+        let event = builder.build().unwrap();
+
+        let count = atree.match_count(&event).unwrap();
+
+        assert_eq!(3, count);
+        assert_eq!(atree.search(&event).unwrap().matches().len(), count);
+    }
+
+    fn an_atree_with_many_matches() -> (ATree<u64>, Event) {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deals"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree.insert(&1u64, "private and exchange_id = 1").unwrap();
+        atree
+            .insert(&2u64, r#"exchange_id = 1 or deals one of ["deal-1"]"#)
+            .unwrap();
+        atree.insert(&3u64, "not private").unwrap();
+        atree.insert(&4u64, "not false").unwrap();
+        atree.insert(&5u64, "exchange_id > 0").unwrap();
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", true).unwrap();
+        builder.with_integer("exchange_id", 1).unwrap();
+        let event = builder.build().unwrap();
+        (atree, event)
+    }
+
+    #[test]
+    fn only_return_the_matching_candidates() {
+        let (atree, event) = an_atree_with_many_matches();
+        let candidates = HashSet::from([2, 3, 4, 6]);
+
+        let mut matches = atree
+            .search_among(&event, &candidates)
+            .unwrap()
+            .matches()
+            .to_vec();
+
+        matches.sort();
+        assert_eq!(vec![&2, &4], matches);
+    }
+
+    #[test]
+    fn return_no_matches_without_candidates() {
+        let (atree, event) = an_atree_with_many_matches();
+
+        let report = atree.search_among(&event, &HashSet::new()).unwrap();
+
+        assert!(report.matches().is_empty());
+    }
+
+    #[test]
+    fn return_at_most_the_limit_of_matches() {
+        let (atree, event) = an_atree_with_many_matches();
+        let all: HashSet<_> = atree
+            .search(&event)
+            .unwrap()
+            .matches()
+            .to_vec()
+            .into_iter()
+            .collect();
+
+        for max in 0..=5 {
+            let matches = atree
+                .search_limited(&event, max)
+                .unwrap()
+                .matches()
+                .to_vec();
+
+            assert_eq!(max.min(all.len()), matches.len());
+            assert!(matches.iter().all(|id| all.contains(id)));
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn stop_evaluating_once_the_limit_is_reached() {
+        let (atree, event) = an_atree_with_many_matches();
+
+        let limited = atree.search_limited(&event, 1).unwrap();
+        let full = atree.search(&event).unwrap();
+
+        assert!(limited.metrics().nodes_visited() <= full.metrics().nodes_visited());
+        assert!(limited.metrics().predicates_evaluated() < full.metrics().predicates_evaluated());
+    }
+
+    #[test]
+    fn can_build_an_atree() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::float("bidfloor"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::integer_list("segment_ids"),
+        ];
+
+        let result = ATree::<u64>::new(&definitions);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn return_an_error_on_duplicate_definitions() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::float("bidfloor"),
+            AttributeDefinition::integer("country"),
+            AttributeDefinition::integer_list("segment_ids"),
+        ];
+
+        let result = ATree::<u64>::new(&definitions);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn reject_the_expressions_with_strings_outside_of_the_dictionary() {
+        let definitions = [AttributeDefinition::string_enum("country", &["CA", "US"])];
+        let mut atree = ATree::<u64>::new(&definitions).unwrap();
+
+        assert!(atree.insert(&1, r#"country in ["CA", "US"]"#).is_ok());
+        assert!(atree.parse_expression(r#"country <> "US""#).is_ok());
+        for expression in [r#"country = "FR""#, r#"country not in ["CA", "FR"]"#] {
+            assert!(atree.insert(&2, expression).is_err());
+            assert!(atree.validate_expression(expression).is_err());
+            assert!(atree.parse_expression(expression).is_err());
+        }
+    }
+
+    #[test]
+    fn return_an_error_on_invalid_boolean_expression() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::integer_list("segment_ids"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+
+        let result = atree.insert(&1u64, AN_INVALID_BOOLEAN_EXPRESSION);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn return_an_error_on_empty_boolean_expression() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::integer_list("segment_ids"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+
+        let result = atree.insert(&1u64, "");
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn can_insert_a_simple_expression() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::integer_list("segment_ids"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+
+        let result = atree.insert(&1u64, AN_EXPRESSION);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn can_insert_an_expression_that_refers_to_a_rnode() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deal_ids"),
+        ];
+        let an_expression = "private or exchange_id = 1";
+        let another_expression =
+            r#"private or exchange_id = 1 or deal_ids one of ["deal-1", "deal-2"]"#;
+        let mut atree = ATree::new(&definitions).unwrap();
+        assert!(atree.insert(&1u64, an_expression).is_ok());
+        assert!(atree.insert(&2u64, another_expression).is_ok());
+    }
+
+    #[test]
+    fn can_insert_the_same_expression_multiple_times() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::integer_list("segment_ids"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+
+        assert!(atree.insert(&1u64, AN_EXPRESSION).is_ok());
+        assert!(atree.insert(&2u64, AN_EXPRESSION).is_ok());
+    }
+
+    #[test]
+    fn can_insert_a_negative_expression() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::integer_list("segment_ids"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+
+        let result = atree.insert(&1u64, A_NOT_EXPRESSION);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn can_insert_an_expression_with_and_operators() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::integer_list("segment_ids"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+
+        let result = atree.insert(&1u64, AN_EXPRESSION_WITH_AND_OPERATORS);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn can_insert_an_expression_with_or_operators() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::integer_list("segment_ids"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+
+        let result = atree.insert(&1u64, AN_EXPRESSION_WITH_OR_OPERATORS);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn can_insert_an_expression_with_mixed_operators() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deal_ids"),
+            AttributeDefinition::integer_list("segment_ids"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string("city"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+
+        let result = atree.insert(&1u64, A_COMPLEX_EXPRESSION);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn can_insert_multiple_expressions_with_mixed_operators() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deal_ids"),
+            AttributeDefinition::integer_list("segment_ids"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string("city"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+
+        assert!(atree.insert(&1u64, A_COMPLEX_EXPRESSION).is_ok());
+        assert!(atree.insert(&2u64, ANOTHER_COMPLEX_EXPRESSION).is_ok());
+    }
+
+    #[test]
+    fn can_search_an_empty_tree() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deal_ids"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::integer_list("segment_ids"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string("city"),
+        ];
+        let atree = ATree::<u64>::new(&definitions).unwrap();
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", false).unwrap();
+        let event = builder.build().unwrap();
+
+        let expected: Vec<&u64> = vec![];
+        let actual = atree.search(&event).unwrap().matches().to_vec();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn can_search_a_single_predicate() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deal_ids"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::integer_list("segment_ids"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string("city"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree.insert(&1u64, "private").unwrap();
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", true).unwrap();
+        let event = builder.build().unwrap();
+
+        let expected = vec![&1u64];
+        let actual = atree.search(&event).unwrap().matches().to_vec();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn ignore_results_that_are_not_matched() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deal_ids"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::integer_list("segment_ids"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string("city"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree.insert(&1u64, "private").unwrap();
+        atree.insert(&2u64, A_COMPLEX_EXPRESSION).unwrap();
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", false).unwrap();
+        let event = builder.build().unwrap();
+
+        let expected: Vec<&u64> = vec![];
+        let actual = atree.search(&event).unwrap().matches().to_vec();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn can_search_simple_expressions() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deal_ids"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::integer_list("segment_ids"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string("city"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree.insert(&1u64, "private").unwrap();
+        atree.insert(&2u64, "not private").unwrap();
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", true).unwrap();
+        let event = builder.build().unwrap();
+
+        let expected = vec![&1u64];
+        let mut actual = atree.search(&event).unwrap().matches().to_vec();
+        actual.sort();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn can_search_complex_expressions() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deal_ids"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::integer_list("segment_ids"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string("city"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+
+        atree.insert(&1, A_COMPLEX_EXPRESSION).unwrap();
+        atree.insert(&2, AN_EXPRESSION_WITH_AND_OPERATORS).unwrap();
+        atree.insert(&3, AN_EXPRESSION_WITH_OR_OPERATORS).unwrap();
+        let mut builder = atree.make_event();
+        builder.with_integer("exchange_id", 1).unwrap();
+        builder.with_boolean("private", true).unwrap();
+        builder
+            .with_string_list("deal_ids", &["deal-1", "deal-2"])
+            .unwrap();
+        builder
+            .with_string_list("deals", &["deal-1", "deal-2"])
+            .unwrap();
+        builder.with_integer_list("segment_ids", &[2, 3]).unwrap();
+        builder.with_string("country", "FR").unwrap();
+        let event = builder.build().unwrap();
 
-    #[test]
-    fn support_sync_and_send_traits() {
-        is_sync_and_send::<ATree<u64>>();
+        let expected = vec![&2, &3];
+        let mut actual = atree.search(&event).unwrap().matches().to_vec();
+        actual.sort();
+        assert_eq!(expected, actual);
     }
 
     #[test]
-    fn can_build_an_atree() {
+    fn can_search_a_tree_with_multiple_shared_sub_expressions() {
         let definitions = [
             AttributeDefinition::boolean("private"),
-            AttributeDefinition::string_list("deals"),
             AttributeDefinition::integer("exchange_id"),
-            AttributeDefinition::float("bidfloor"),
-            AttributeDefinition::string("country"),
+            AttributeDefinition::string_list("deals"),
             AttributeDefinition::integer_list("segment_ids"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string("city"),
         ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        [
+            (
+                1,
+                r#"exchange_id = 1 and not private and deals one of ["deal-1", "deal-2"]"#,
+            ),
+            (
+                2,
+                r#"exchange_id = 1 and not private and deals one of ["deal-2", "deal-3"]"#,
+            ),
+            (
+                3,
+                r#"exchange_id = 1 and not private and deals one of ["deal-2", "deal-3"] and segment_ids one of [1, 2, 3, 4]"#,
+            ),
+            (
+                4,
+                r#"exchange_id = 1 and not private and deals one of ["deal-2", "deal-3"] and segment_ids one of [5, 6, 7, 8] and country in ["CA", "US"]"#,
+            ),
+        ].into_iter().for_each(|(id, expression)| {
+                atree.insert(&id, expression).unwrap()
+        });
 
-        let result = ATree::<u64>::new(&definitions);
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", false).unwrap();
+        builder.with_integer("exchange_id", 1).unwrap();
+        builder
+            .with_string_list("deals", &["deal-1", "deal-3"])
+            .unwrap();
+        builder.with_integer_list("segment_ids", &[2, 3]).unwrap();
+        builder.with_string("country", "CA").unwrap();
+        let event = builder.build().unwrap();
 
-        assert!(result.is_ok());
+        let mut matches = atree.search(&event).unwrap().matches().to_vec();
+        matches.sort();
+        assert_eq!(vec![&1, &2, &3], matches);
     }
 
     #[test]
-    fn return_an_error_on_duplicate_definitions() {
+    fn can_search_a_predicate_already_inserted_as_the_other_child_of_an_and() {
         let definitions = [
-            AttributeDefinition::boolean("private"),
-            AttributeDefinition::string("country"),
-            AttributeDefinition::string_list("deals"),
             AttributeDefinition::integer("exchange_id"),
-            AttributeDefinition::float("bidfloor"),
-            AttributeDefinition::integer("country"),
             AttributeDefinition::integer_list("segment_ids"),
         ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree
+            .insert(&1u64, "exchange_id = 1 and segment_ids is not empty")
+            .unwrap();
+        atree.insert(&2u64, "exchange_id = 1").unwrap();
+        let mut builder = atree.make_event();
+        builder.with_integer("exchange_id", 1).unwrap();
+        builder.with_integer_list("segment_ids", &[]).unwrap();
+        let event = builder.build().unwrap();
 
-        let result = ATree::<u64>::new(&definitions);
+        let report = atree.search(&event).unwrap();
 
-        assert!(result.is_err());
+        assert_eq!(vec![&2], report.matches());
     }
 
     #[test]
-    fn return_an_error_on_invalid_boolean_expression() {
-        let definitions = [
-            AttributeDefinition::boolean("private"),
-            AttributeDefinition::string("country"),
-            AttributeDefinition::string_list("deals"),
-            AttributeDefinition::integer("exchange_id"),
-            AttributeDefinition::integer_list("segment_ids"),
-        ];
+    fn can_delete_a_single_predicate() {
+        let definitions = [AttributeDefinition::boolean("private")];
         let mut atree = ATree::new(&definitions).unwrap();
+        atree.insert(&1u64, "private").unwrap();
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", true).unwrap();
+        let event = builder.build().unwrap();
 
-        let result = atree.insert(&1u64, AN_INVALID_BOOLEAN_EXPRESSION);
+        let results = atree.search(&event).unwrap().matches().to_vec();
+        assert_eq!(vec![&1u64], results);
 
-        assert!(result.is_err());
+        atree.delete(&1u64);
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", true).unwrap();
+        let event = builder.build().unwrap();
+        let results = atree.search(&event).unwrap().matches().to_vec();
+        assert!(results.is_empty());
     }
 
     #[test]
-    fn return_an_error_on_empty_boolean_expression() {
-        let definitions = [
-            AttributeDefinition::boolean("private"),
-            AttributeDefinition::string("country"),
-            AttributeDefinition::string_list("deals"),
-            AttributeDefinition::integer("exchange_id"),
-            AttributeDefinition::integer_list("segment_ids"),
-        ];
+    fn can_delete_the_subscriptions_with_a_tag() {
+        let definitions = [AttributeDefinition::boolean("private")];
         let mut atree = ATree::new(&definitions).unwrap();
+        let campaign = Metadata::new().with_tag("campaign-1");
+        atree
+            .insert_with_metadata(&1u64, "private", campaign.clone())
+            .unwrap();
+        atree
+            .insert_with_metadata(&2u64, "not private", campaign)
+            .unwrap();
+        atree.insert(&3u64, "private").unwrap();
 
-        let result = atree.insert(&1u64, "");
+        let mut deleted = atree.delete_by_tag("campaign-1");
+        deleted.sort_unstable();
 
-        assert!(result.is_err());
+        assert_eq!(vec![1, 2], deleted);
+        assert_eq!(None, atree.ids_with_tag("campaign-1").next());
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", true).unwrap();
+        let event = builder.build().unwrap();
+        let results = atree.search(&event).unwrap().matches().to_vec();
+        assert_eq!(vec![&3u64], results);
     }
 
     #[test]
-    fn can_insert_a_simple_expression() {
+    fn delete_many_subscriptions_like_one_by_one() {
         let definitions = [
             AttributeDefinition::boolean("private"),
-            AttributeDefinition::string("country"),
-            AttributeDefinition::string_list("deals"),
             AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::string_list("deal_ids"),
             AttributeDefinition::integer_list("segment_ids"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string("city"),
+        ];
+        let expressions = [
+            AN_EXPRESSION,
+            A_NOT_EXPRESSION,
+            AN_EXPRESSION_WITH_AND_OPERATORS,
+            AN_EXPRESSION_WITH_OR_OPERATORS,
+            A_COMPLEX_EXPRESSION,
+            ANOTHER_COMPLEX_EXPRESSION,
         ];
         let mut atree = ATree::new(&definitions).unwrap();
+        for id in 0..60u64 {
+            atree
+                .insert(&id, expressions[id as usize % expressions.len()])
+                .unwrap();
+        }
+        let mut expected = atree.clone();
+        let deleted: Vec<_> = (0..60u64).filter(|id| id % 4 != 1).chain([7, 99]).collect();
+        for id in &deleted {
+            expected.delete(id);
+        }
 
-        let result = atree.insert(&1u64, AN_EXPRESSION);
+        let count = atree.delete_many(&deleted, NonZeroUsize::new(3).unwrap());
 
-        assert!(result.is_ok());
+        assert_eq!(45, count);
+        assert_eq!(expected.stats().nodes(), atree.stats().nodes());
+        let mut builder = atree.make_event();
+        builder.with_integer("exchange_id", 1).unwrap();
+        builder.with_boolean("private", false).unwrap();
+        builder.with_string_list("deals", &["deal-1"]).unwrap();
+        let event = builder.build().unwrap();
+        let matches = expected.search(&event).unwrap().matches().to_vec();
+        assert!(!matches.is_empty());
+        assert_eq!(matches, atree.search(&event).unwrap().matches());
+        atree.delete_many(&(0..60u64).collect::<Vec<_>>(), NonZeroUsize::MIN);
+        assert_eq!(0, atree.stats().nodes());
     }
 
     #[test]
-    fn can_insert_an_expression_that_refers_to_a_rnode() {
-        let definitions = [
-            AttributeDefinition::boolean("private"),
-            AttributeDefinition::integer("exchange_id"),
-            AttributeDefinition::string_list("deal_ids"),
-        ];
-        let an_expression = "private or exchange_id = 1";
-        let another_expression =
-            r#"private or exchange_id = 1 or deal_ids one of ["deal-1", "deal-2"]"#;
+    fn delete_the_expired_subscriptions() {
+        let definitions = [AttributeDefinition::boolean("private")];
         let mut atree = ATree::new(&definitions).unwrap();
-        assert!(atree.insert(&1u64, an_expression).is_ok());
-        assert!(atree.insert(&2u64, another_expression).is_ok());
+        atree
+            .insert_with_metadata(&1u64, "private", Metadata::new().with_expiry(100))
+            .unwrap();
+        atree
+            .insert_with_metadata(&2u64, "not private", Metadata::new().with_expiry(200))
+            .unwrap();
+        atree.insert(&3u64, "private").unwrap();
+
+        assert!(atree.expire(100, NonZeroUsize::MIN).is_empty());
+        let mut expired = atree.expire(300, NonZeroUsize::new(2).unwrap());
+        expired.sort_unstable();
+
+        assert_eq!(vec![1, 2], expired);
+        assert_eq!(None, atree.expression(&1));
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", true).unwrap();
+        let event = builder.build().unwrap();
+        let results = atree.search(&event).unwrap().matches().to_vec();
+        assert_eq!(vec![&3u64], results);
     }
 
     #[test]
-    fn can_insert_the_same_expression_multiple_times() {
+    fn replace_the_expression_of_a_duplicate_id_when_configured() {
         let definitions = [
             AttributeDefinition::boolean("private"),
-            AttributeDefinition::string("country"),
-            AttributeDefinition::string_list("deals"),
             AttributeDefinition::integer("exchange_id"),
-            AttributeDefinition::integer_list("segment_ids"),
         ];
-        let mut atree = ATree::new(&definitions).unwrap();
+        let config = ATreeConfig::new().with_duplicate_ids(DuplicateIdPolicy::Replace);
+        let mut atree = ATree::with_config(&definitions, config).unwrap();
+        atree.insert(&1u64, "private").unwrap();
+        atree.insert(&1u64, "exchange_id = 1").unwrap();
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", true).unwrap();
+        let event = builder.build().unwrap();
 
-        assert!(atree.insert(&1u64, AN_EXPRESSION).is_ok());
-        assert!(atree.insert(&2u64, AN_EXPRESSION).is_ok());
+        let results = atree.search(&event).unwrap().matches().to_vec();
+
+        assert!(results.is_empty());
+        assert_eq!(Some("exchange_id = 1"), atree.expression(&1u64));
     }
 
     #[test]
-    fn can_insert_a_negative_expression() {
+    fn reject_a_duplicate_id_when_configured() {
         let definitions = [
             AttributeDefinition::boolean("private"),
-            AttributeDefinition::string("country"),
-            AttributeDefinition::string_list("deals"),
             AttributeDefinition::integer("exchange_id"),
-            AttributeDefinition::integer_list("segment_ids"),
         ];
-        let mut atree = ATree::new(&definitions).unwrap();
+        let config = ATreeConfig::new().with_duplicate_ids(DuplicateIdPolicy::Reject);
+        let mut atree = ATree::with_config(&definitions, config).unwrap();
+        atree.insert(&1u64, "private").unwrap();
 
-        let result = atree.insert(&1u64, A_NOT_EXPRESSION);
+        let result = atree.insert(&1u64, "exchange_id = 1");
 
-        assert!(result.is_ok());
+        assert!(matches!(result, Err(ATreeError::DuplicateId(id)) if id == "1"));
+        assert_eq!(Some("private"), atree.expression(&1u64));
     }
 
     #[test]
-    fn can_insert_an_expression_with_and_operators() {
+    fn return_a_duplicate_id_once_when_several_of_its_expressions_match() {
         let definitions = [
             AttributeDefinition::boolean("private"),
-            AttributeDefinition::string("country"),
-            AttributeDefinition::string_list("deals"),
             AttributeDefinition::integer("exchange_id"),
-            AttributeDefinition::integer_list("segment_ids"),
         ];
         let mut atree = ATree::new(&definitions).unwrap();
+        atree.insert(&1u64, "private").unwrap();
+        atree.insert(&1u64, "exchange_id = 1").unwrap();
+        atree.insert(&1u64, "private or exchange_id = 1").unwrap();
+        atree.insert(&2u64, "true").unwrap();
+        atree.insert(&2u64, "true").unwrap();
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", true).unwrap();
+        builder.with_integer("exchange_id", 1).unwrap();
+        let event = builder.build().unwrap();
 
-        let result = atree.insert(&1u64, AN_EXPRESSION_WITH_AND_OPERATORS);
+        let sorted = atree.search(&event).unwrap().into_sorted();
 
-        assert!(result.is_ok());
+        assert_eq!([&1u64, &2], sorted.as_slice());
+        assert_eq!(2, atree.match_count(&event).unwrap());
+        assert_eq!(
+            vec![&1u64],
+            atree
+                .search_among(&event, &HashSet::from([1u64]))
+                .unwrap()
+                .matches()
+        );
     }
 
     #[test]
-    fn can_insert_an_expression_with_or_operators() {
+    fn separate_the_undefined_subscriptions_from_the_failed_ones() {
         let definitions = [
             AttributeDefinition::boolean("private"),
-            AttributeDefinition::string("country"),
-            AttributeDefinition::string_list("deals"),
             AttributeDefinition::integer("exchange_id"),
-            AttributeDefinition::integer_list("segment_ids"),
+            AttributeDefinition::string("country"),
         ];
         let mut atree = ATree::new(&definitions).unwrap();
+        atree.insert(&1u64, "exchange_id = 1 and private").unwrap();
+        atree.insert(&1u64, "private or exchange_id = 3").unwrap();
+        atree.insert(&2u64, "exchange_id = 2 and private").unwrap();
+        atree.insert(&3u64, r#"country = "CA""#).unwrap();
+        atree.insert(&4u64, "private or exchange_id = 1").unwrap();
+        atree.insert(&4u64, "private").unwrap();
+        atree.insert(&5u64, "not private").unwrap();
+        let mut builder = atree.make_event();
+        builder.with_integer("exchange_id", 1).unwrap();
+        builder.with_string("country", "FR").unwrap();
+        let event = builder.build().unwrap();
 
-        let result = atree.insert(&1u64, AN_EXPRESSION_WITH_OR_OPERATORS);
+        let report = atree.search_with_undefined(&event).unwrap();
 
-        assert!(result.is_ok());
+        assert_eq!([&4u64], report.matches());
+        let mut undefined = report.undefined().to_vec();
+        undefined.sort_unstable();
+        assert_eq!([&1u64, &5], undefined.as_slice());
+        assert!(atree.search(&event).unwrap().undefined().is_empty());
     }
 
     #[test]
-    fn can_insert_an_expression_with_mixed_operators() {
+    fn decide_the_comparisons_on_the_same_attribute_at_once() {
         let definitions = [
             AttributeDefinition::boolean("private"),
             AttributeDefinition::integer("exchange_id"),
-            AttributeDefinition::string_list("deal_ids"),
-            AttributeDefinition::integer_list("segment_ids"),
-            AttributeDefinition::string("country"),
-            AttributeDefinition::string("city"),
+            AttributeDefinition::float("bidfloor"),
         ];
         let mut atree = ATree::new(&definitions).unwrap();
+        for threshold in 0..10u64 {
+            atree
+                .insert(&threshold, &format!("exchange_id > {threshold}"))
+                .unwrap();
+            atree
+                .insert(
+                    &(100 + threshold),
+                    &format!("private and bidfloor <= {threshold}.5"),
+                )
+                .unwrap();
+        }
+        atree.delete(&3);
+        atree.delete(&104);
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", true).unwrap();
+        builder.with_integer("exchange_id", 5).unwrap();
+        builder.with_f64("bidfloor", 2.5).unwrap();
+        let event = builder.build().unwrap();
 
-        let result = atree.insert(&1u64, A_COMPLEX_EXPRESSION);
+        let report = atree.search(&event).unwrap();
 
-        assert!(result.is_ok());
+        let mut matches = report.matches().to_vec();
+        matches.sort_unstable();
+        assert_eq!(
+            vec![&0, &1, &2, &4, &102, &103, &105, &106, &107, &108, &109],
+            matches
+        );
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", true).unwrap();
+        let event = builder.build().unwrap();
+        assert!(atree.search(&event).unwrap().matches().is_empty());
     }
 
     #[test]
-    fn can_insert_multiple_expressions_with_mixed_operators() {
+    fn find_the_ranges_containing_the_value() {
         let definitions = [
-            AttributeDefinition::boolean("private"),
-            AttributeDefinition::integer("exchange_id"),
-            AttributeDefinition::string_list("deal_ids"),
-            AttributeDefinition::integer_list("segment_ids"),
-            AttributeDefinition::string("country"),
-            AttributeDefinition::string("city"),
+            AttributeDefinition::integer("age"),
+            AttributeDefinition::float("price"),
         ];
         let mut atree = ATree::new(&definitions).unwrap();
+        atree.insert(&1, "age between 18 and 25").unwrap();
+        atree.insert(&2, "age between 25 and 34").unwrap();
+        atree.insert(&3, "age not between 18 and 65").unwrap();
+        atree.insert(&4, "not (age between 0 and 17)").unwrap();
+        atree.insert(&5, "age between 30 and 20").unwrap();
+        atree.insert(&6, "age between 20 and 30").unwrap();
+        atree.insert(&7, "price between 1.5 and 2.5").unwrap();
+        atree.delete(&6);
+        let mut builder = atree.make_event();
+        builder.with_integer("age", 25).unwrap();
+        builder.with_f64("price", 2.5).unwrap();
+        let event = builder.build().unwrap();
 
-        assert!(atree.insert(&1u64, A_COMPLEX_EXPRESSION).is_ok());
-        assert!(atree.insert(&2u64, ANOTHER_COMPLEX_EXPRESSION).is_ok());
+        let report = atree.search(&event).unwrap();
+
+        let mut matches = report.matches().to_vec();
+        matches.sort_unstable();
+        assert_eq!(vec![&1, &2, &4, &7], matches);
+        let mut builder = atree.make_event();
+        builder.with_integer("age", 70).unwrap();
+        let event = builder.build().unwrap();
+        let mut matches = atree.search(&event).unwrap().matches().to_vec();
+        matches.sort_unstable();
+        assert_eq!(vec![&3, &4], matches);
+        assert!(atree.insert(&8, "age between 1.5 and 2").is_err());
     }
 
     #[test]
-    fn can_search_an_empty_tree() {
+    fn match_the_recent_times_relative_to_the_event() {
+        const DAY: i64 = 24 * 60 * 60;
         let definitions = [
-            AttributeDefinition::boolean("private"),
-            AttributeDefinition::integer("exchange_id"),
-            AttributeDefinition::string_list("deal_ids"),
-            AttributeDefinition::string_list("deals"),
-            AttributeDefinition::integer_list("segment_ids"),
-            AttributeDefinition::string("country"),
-            AttributeDefinition::string("city"),
+            AttributeDefinition::integer("last_purchase"),
+            AttributeDefinition::float("price"),
         ];
-        let atree = ATree::new(&definitions).unwrap();
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree
+            .insert(&1, "within_last(last_purchase, '7d')")
+            .unwrap();
+        atree
+            .insert(&2, r#"within_last(last_purchase, "1d12h")"#)
+            .unwrap();
+        atree
+            .insert(&3, "not within_last(last_purchase, '1w')")
+            .unwrap();
         let mut builder = atree.make_event();
-        builder.with_boolean("private", false).unwrap();
+        builder.with_integer("last_purchase", 1_000 * DAY).unwrap();
+        builder.with_now(1_002 * DAY);
         let event = builder.build().unwrap();
 
-        let expected: Vec<&u64> = vec![];
-        let actual = atree.search(&event).unwrap().matches().to_vec();
-        assert_eq!(expected, actual);
+        let report = atree.search(&event).unwrap();
+
+        assert_eq!(vec![&1], report.matches());
+        builder = atree.make_event();
+        builder.with_integer("last_purchase", 1_000 * DAY).unwrap();
+        let event = builder.build().unwrap();
+        assert!(atree.search(&event).unwrap().matches().is_empty());
+        assert!(atree.insert(&4, "within_last(last_purchase, '7')").is_err());
+        assert!(atree.insert(&4, "within_last(price, '7d')").is_err());
     }
 
     #[test]
-    fn can_search_a_single_predicate() {
-        let definitions = [
-            AttributeDefinition::boolean("private"),
-            AttributeDefinition::integer("exchange_id"),
-            AttributeDefinition::string_list("deal_ids"),
+    fn return_an_error_on_a_duration_with_a_non_ascii_unit() {
+        let definitions = [AttributeDefinition::integer("last_purchase")];
+        let mut atree = ATree::new(&definitions).unwrap();
+
+        assert!(atree
+            .insert(&1, "within_last(last_purchase, '7é')")
+            .is_err());
+        assert!(atree
+            .insert(&1, "within_last(last_purchase, '7d1日')")
+            .is_err());
+    }
+
+    #[test]
+    fn match_the_lists_with_a_minimum_overlap() {
+        let definitions = [
+            AttributeDefinition::integer_list("segments"),
             AttributeDefinition::string_list("deals"),
-            AttributeDefinition::integer_list("segment_ids"),
-            AttributeDefinition::string("country"),
-            AttributeDefinition::string("city"),
+            AttributeDefinition::integer("count"),
         ];
         let mut atree = ATree::new(&definitions).unwrap();
-        atree.insert(&1u64, "private").unwrap();
+        atree
+            .insert(&1, "segments intersect_at_least [1, 2, 3, 4] count 2")
+            .unwrap();
+        atree
+            .insert(&2, "segments intersect_at_least [1, 2, 3, 4] count 3")
+            .unwrap();
+        atree
+            .insert(
+                &3,
+                r#"not deals intersect_at_least ["a", "b"] count 2 and count > 1"#,
+            )
+            .unwrap();
         let mut builder = atree.make_event();
-        builder.with_boolean("private", true).unwrap();
+        builder.with_integer_list("segments", &[4, 2, 9]).unwrap();
+        builder.with_string_list("deals", &["a", "c"]).unwrap();
+        builder.with_integer("count", 2).unwrap();
         let event = builder.build().unwrap();
 
-        let expected = vec![&1u64];
-        let actual = atree.search(&event).unwrap().matches().to_vec();
-        assert_eq!(expected, actual);
+        let sorted = atree.search(&event).unwrap().into_sorted();
+
+        assert_eq!([&1, &3], sorted.as_slice());
+        assert!(atree
+            .insert(&4, "segments intersect_at_least [1, 2] count 0")
+            .is_err());
+        assert!(atree
+            .insert(&4, "segments intersect_at_least [1, 2] amount 1")
+            .is_err());
+        assert!(atree
+            .insert(&4, "count intersect_at_least [1, 2] count 1")
+            .is_err());
     }
 
     #[test]
-    fn ignore_results_that_are_not_matched() {
+    fn match_the_strings_within_an_edit_distance() {
         let definitions = [
-            AttributeDefinition::boolean("private"),
-            AttributeDefinition::integer("exchange_id"),
-            AttributeDefinition::string_list("deal_ids"),
-            AttributeDefinition::string_list("deals"),
-            AttributeDefinition::integer_list("segment_ids"),
-            AttributeDefinition::string("country"),
-            AttributeDefinition::string("city"),
+            AttributeDefinition::string("brand"),
+            AttributeDefinition::integer("within"),
         ];
         let mut atree = ATree::new(&definitions).unwrap();
-        atree.insert(&1u64, "private").unwrap();
-        atree.insert(&2u64, A_COMPLEX_EXPRESSION).unwrap();
+        atree.insert(&1, "brand ~= 'adidas' within 1").unwrap();
+        atree.insert(&2, "brand ~= 'adidas' within 0").unwrap();
+        atree
+            .insert(&3, "not brand ~= 'nike' within 2 and within > 0")
+            .unwrap();
         let mut builder = atree.make_event();
-        builder.with_boolean("private", false).unwrap();
+        builder.with_string("brand", "adiddas").unwrap();
+        builder.with_integer("within", 1).unwrap();
         let event = builder.build().unwrap();
 
-        let expected: Vec<&u64> = vec![];
-        let actual = atree.search(&event).unwrap().matches().to_vec();
-        assert_eq!(expected, actual);
+        let sorted = atree.search(&event).unwrap().into_sorted();
+
+        assert_eq!([&1, &3], sorted.as_slice());
+        assert!(atree.insert(&4, "brand ~= 'adidas' within 4").is_err());
+        assert!(atree.insert(&4, "brand ~= 'adidas' within -1").is_err());
+        assert!(atree.insert(&4, "brand ~= 'adidas' under 1").is_err());
+        assert!(atree.insert(&4, "within ~= 'adidas' within 1").is_err());
     }
 
     #[test]
-    fn can_search_simple_expressions() {
+    fn look_up_the_values_of_the_high_cardinality_attributes() {
         let definitions = [
+            AttributeDefinition::integer("user_id").high_cardinality(),
+            AttributeDefinition::string("country").high_cardinality(),
             AttributeDefinition::boolean("private"),
-            AttributeDefinition::integer("exchange_id"),
-            AttributeDefinition::string_list("deal_ids"),
-            AttributeDefinition::string_list("deals"),
-            AttributeDefinition::integer_list("segment_ids"),
-            AttributeDefinition::string("country"),
-            AttributeDefinition::string("city"),
         ];
         let mut atree = ATree::new(&definitions).unwrap();
-        atree.insert(&1u64, "private").unwrap();
-        atree.insert(&2u64, "not private").unwrap();
+        atree.insert(&1, "user_id = 42").unwrap();
+        atree.insert(&2, "user_id <> 42").unwrap();
+        atree.insert(&3, "user_id in [1, 42, 100]").unwrap();
+        atree.insert(&4, "user_id not in [1, 100]").unwrap();
+        atree.insert(&5, r#"country = "CA" and private"#).unwrap();
+        atree.insert(&6, r#"country not in ["US", "FR"]"#).unwrap();
+        atree.insert(&7, "user_id > 40").unwrap();
         let mut builder = atree.make_event();
+        builder.with_integer("user_id", 42).unwrap();
+        builder.with_string("country", "CA").unwrap();
         builder.with_boolean("private", true).unwrap();
         let event = builder.build().unwrap();
 
-        let expected = vec![&1u64];
-        let mut actual = atree.search(&event).unwrap().matches().to_vec();
-        actual.sort();
-        assert_eq!(expected, actual);
+        let sorted = atree.search(&event).unwrap().into_sorted();
+
+        assert_eq!([&1, &3, &4, &5, &6, &7], sorted.as_slice());
+
+        atree.delete(&1);
+        atree.delete(&6);
+        let mut builder = atree.make_event();
+        builder.with_integer("user_id", 1).unwrap();
+        let event = builder.build().unwrap();
+
+        let sorted = atree.search(&event).unwrap().into_sorted();
+
+        assert_eq!([&2, &3], sorted.as_slice());
     }
 
     #[test]
-    fn can_search_complex_expressions() {
+    fn match_the_patterns_of_the_strings() {
         let definitions = [
             AttributeDefinition::boolean("private"),
-            AttributeDefinition::integer("exchange_id"),
-            AttributeDefinition::string_list("deal_ids"),
-            AttributeDefinition::string_list("deals"),
-            AttributeDefinition::integer_list("segment_ids"),
-            AttributeDefinition::string("country"),
-            AttributeDefinition::string("city"),
+            AttributeDefinition::string("deal"),
         ];
         let mut atree = ATree::new(&definitions).unwrap();
-
-        atree.insert(&1, A_COMPLEX_EXPRESSION).unwrap();
-        atree.insert(&2, AN_EXPRESSION_WITH_AND_OPERATORS).unwrap();
-        atree.insert(&3, AN_EXPRESSION_WITH_OR_OPERATORS).unwrap();
-        let mut builder = atree.make_event();
-        builder.with_integer("exchange_id", 1).unwrap();
-        builder.with_boolean("private", true).unwrap();
-        builder
-            .with_string_list("deal_ids", &["deal-1", "deal-2"])
+        atree.insert(&1, r#"deal starts with "deal-""#).unwrap();
+        atree.insert(&2, r#"deal starts with "deal-1""#).unwrap();
+        atree
+            .insert(&3, r#"private and deal starts with "deal-2""#)
             .unwrap();
-        builder
-            .with_string_list("deals", &["deal-1", "deal-2"])
+        atree
+            .insert(&4, r#"not (deal starts with "deal-1")"#)
             .unwrap();
-        builder.with_integer_list("segment_ids", &[2, 3]).unwrap();
-        builder.with_string("country", "FR").unwrap();
+        atree.insert(&5, r#"deal starts with """#).unwrap();
+        atree.insert(&6, r#"deal starts with "deal-12""#).unwrap();
+        atree.insert(&7, r#"deal contains "l-1""#).unwrap();
+        atree.insert(&8, r#"not (deal contains "-2")"#).unwrap();
+        atree.delete(&6);
+        let mut builder = atree.make_event();
+        builder.with_string("deal", "deal-12").unwrap();
         let event = builder.build().unwrap();
 
-        let expected = vec![&2, &3];
-        let mut actual = atree.search(&event).unwrap().matches().to_vec();
-        actual.sort();
-        assert_eq!(expected, actual);
+        let report = atree.search(&event).unwrap();
+
+        let mut matches = report.matches().to_vec();
+        matches.sort_unstable();
+        assert_eq!(vec![&1, &2, &5, &7, &8], matches);
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", true).unwrap();
+        builder.with_string("deal", "deal-2").unwrap();
+        let event = builder.build().unwrap();
+        let mut matches = atree.search(&event).unwrap().matches().to_vec();
+        matches.sort_unstable();
+        assert_eq!(vec![&1, &3, &4, &5], matches);
+        let event = atree.make_event().build().unwrap();
+        assert!(atree.search(&event).unwrap().matches().is_empty());
     }
 
     #[test]
-    fn can_search_a_tree_with_multiple_shared_sub_expressions() {
+    fn find_the_same_matches_as_the_linear_evaluation() {
         let definitions = [
             AttributeDefinition::boolean("private"),
             AttributeDefinition::integer("exchange_id"),
-            AttributeDefinition::string_list("deals"),
-            AttributeDefinition::integer_list("segment_ids"),
             AttributeDefinition::string("country"),
-            AttributeDefinition::string("city"),
         ];
         let mut atree = ATree::new(&definitions).unwrap();
-        [
-            (
-                1,
-                r#"exchange_id = 1 and not private and deals one of ["deal-1", "deal-2"]"#,
-            ),
-            (
-                2,
-                r#"exchange_id = 1 and not private and deals one of ["deal-2", "deal-3"]"#,
-            ),
-            (
-                3,
-                r#"exchange_id = 1 and not private and deals one of ["deal-2", "deal-3"] and segment_ids one of [1, 2, 3, 4]"#,
-            ),
-            (
-                4,
-                r#"exchange_id = 1 and not private and deals one of ["deal-2", "deal-3"] and segment_ids one of [5, 6, 7, 8] and country in ["CA", "US"]"#,
-            ),
-        ].into_iter().for_each(|(id, expression)| {
-                atree.insert(&id, expression).unwrap()
-        });
-
-        let mut builder = atree.make_event();
-        builder.with_boolean("private", false).unwrap();
-        builder.with_integer("exchange_id", 1).unwrap();
-        builder
-            .with_string_list("deals", &["deal-1", "deal-3"])
+        atree.insert(&1u64, "exchange_id = 1 and private").unwrap();
+        atree
+            .insert(&2u64, r#"(exchange_id = 1 and private) or country = "CA""#)
             .unwrap();
-        builder.with_integer_list("segment_ids", &[2, 3]).unwrap();
-        builder.with_string("country", "CA").unwrap();
-        let event = builder.build().unwrap();
+        atree
+            .insert(&3u64, "not private or exchange_id = 2")
+            .unwrap();
+        atree.insert(&4u64, "true").unwrap();
+        atree
+            .insert(&5u64, r#"country <> "CA" and private"#)
+            .unwrap();
+        let events: Vec<_> = [(true, None), (false, Some("CA")), (true, Some("US"))]
+            .into_iter()
+            .map(|(private, country)| {
+                let mut builder = atree.make_event();
+                builder.with_boolean("private", private).unwrap();
+                builder.with_integer("exchange_id", 1).unwrap();
+                if let Some(country) = country {
+                    builder.with_string("country", country).unwrap();
+                }
+                builder.build().unwrap()
+            })
+            .collect();
 
-        let mut matches = atree.search(&event).unwrap().matches().to_vec();
-        matches.sort();
-        assert_eq!(vec![&1, &2, &3], matches);
+        let mut matches = atree.baseline_search(&events[2]);
+        matches.sort_unstable();
+        let report = atree.compare_with_baseline(&events);
+
+        assert_eq!(vec![&1u64, &2, &4, &5], matches);
+        assert_eq!(3, report.events());
+        assert!(report.is_consistent());
     }
 
     #[test]
-    fn can_delete_a_single_predicate() {
-        let definitions = [AttributeDefinition::boolean("private")];
+    fn count_a_duplicate_id_once_when_limiting_the_matches() {
+        let definitions = [AttributeDefinition::integer("exchange_id")];
         let mut atree = ATree::new(&definitions).unwrap();
-        atree.insert(&1u64, "private").unwrap();
+        atree.insert(&1u64, "exchange_id = 1").unwrap();
+        atree.insert(&1u64, "exchange_id > 0").unwrap();
+        atree.insert(&2u64, "exchange_id < 5").unwrap();
         let mut builder = atree.make_event();
-        builder.with_boolean("private", true).unwrap();
+        builder.with_integer("exchange_id", 1).unwrap();
         let event = builder.build().unwrap();
 
-        let results = atree.search(&event).unwrap().matches().to_vec();
-        assert_eq!(vec![&1u64], results);
+        let mut matches = atree.search_limited(&event, 2).unwrap().matches().to_vec();
+        matches.sort();
 
-        atree.delete(&1u64);
-        let mut builder = atree.make_event();
-        builder.with_boolean("private", true).unwrap();
-        let event = builder.build().unwrap();
-        let results = atree.search(&event).unwrap().matches().to_vec();
-        assert!(results.is_empty());
+        assert_eq!(vec![&1u64, &2], matches);
     }
 
     #[test]
@@ -1390,4 +4915,243 @@ mod tests {
 
         assert!(!atree.to_graphviz().is_empty());
     }
+
+    #[test]
+    fn notify_the_observers_of_the_changes_and_searches() {
+        #[derive(Default)]
+        struct Recorder {
+            calls: std::sync::Mutex<Vec<String>>,
+        }
+
+        impl TreeObserver<u64> for Recorder {
+            fn on_insert(&self, subscription_id: &u64, expression: &str) {
+                let mut calls = self.calls.lock().unwrap();
+                calls.push(format!("insert {subscription_id} {expression}"));
+            }
+
+            fn on_delete(&self, subscription_id: &u64) {
+                let mut calls = self.calls.lock().unwrap();
+                calls.push(format!("delete {subscription_id}"));
+            }
+
+            fn on_search_complete(&self, report: &Report<'_, u64>) {
+                let mut calls = self.calls.lock().unwrap();
+                calls.push(format!("search {:?}", report.matches()));
+            }
+        }
+
+        let definitions = [AttributeDefinition::boolean("private")];
+        let mut atree = ATree::new(&definitions).unwrap();
+        let recorder = Arc::new(Recorder::default());
+        atree.register_observer(recorder.clone());
+        atree.insert(&1u64, "private").unwrap();
+        assert!(atree.insert(&2u64, AN_INVALID_BOOLEAN_EXPRESSION).is_err());
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", true).unwrap();
+        let event = builder.build().unwrap();
+        atree.search(&event).unwrap();
+        atree.delete(&1u64);
+        atree.delete(&3u64);
+
+        assert_eq!(
+            vec!["insert 1 private", "search [1]", "delete 1"],
+            *recorder.calls.lock().unwrap()
+        );
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn only_report_the_evaluated_nodes_in_the_profile() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deals"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree
+            .insert(&1u64, AN_EXPRESSION_WITH_AND_OPERATORS)
+            .unwrap();
+        let mut builder = atree.make_event();
+        builder.with_integer("exchange_id", 2).unwrap();
+        let event = builder.build().unwrap();
+        atree.search(&event).unwrap();
+        atree.search(&event).unwrap();
+
+        let report = atree.profile_report();
+
+        let lines: Vec<_> = report.lines().collect();
+        assert_eq!(1, lines.len());
+        assert!(lines[0].starts_with("[1] And #"));
+        assert!(lines[0].ends_with(" 2"));
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn only_time_the_predicates_when_the_timing_is_enabled() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deals"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree
+            .insert(&1u64, AN_EXPRESSION_WITH_AND_OPERATORS)
+            .unwrap();
+        let mut builder = atree.make_event();
+        builder.with_integer("exchange_id", 2).unwrap();
+        let event = builder.build().unwrap();
+        atree.search(&event).unwrap();
+        assert!(atree.predicate_timings().is_empty());
+
+        atree.set_predicate_timing(true);
+        atree.search(&event).unwrap();
+        atree.search(&event).unwrap();
+
+        let timings = atree.predicate_timings();
+        assert!(!timings.is_empty());
+        assert!(timings.iter().all(|timing| timing.evaluations() == 2));
+        assert!(timings
+            .windows(2)
+            .all(|pair| pair[0].elapsed() >= pair[1].elapsed()));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn do_not_record_the_warmup_searches() {
+        let definitions = [AttributeDefinition::integer("exchange_id")];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree.insert(&1u64, "exchange_id = 1").unwrap();
+        let mut builder = atree.make_event();
+        builder.with_integer("exchange_id", 1).unwrap();
+        let event = builder.build().unwrap();
+
+        atree.warmup(&[event.clone(), event.clone()]);
+
+        assert_eq!(0, atree.metrics().searches());
+        assert_eq!(vec![&1u64], atree.search(&event).unwrap().matches());
+        assert_eq!(1, atree.metrics().searches());
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn can_collect_metrics_while_searching() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deals"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree
+            .insert(&1u64, AN_EXPRESSION_WITH_AND_OPERATORS)
+            .unwrap();
+        atree.insert(&2u64, "private").unwrap();
+        let mut builder = atree.make_event();
+        builder.with_integer("exchange_id", 1).unwrap();
+        builder.with_boolean("private", true).unwrap();
+        builder.with_string_list("deals", &["deal-1"]).unwrap();
+        let event = builder.build().unwrap();
+
+        let report = atree.search(&event).unwrap();
+
+        let metrics = report.metrics();
+        assert_eq!(4, metrics.nodes_visited());
+        assert_eq!(3, metrics.predicates_evaluated());
+        assert_eq!(0, metrics.short_circuits());
+    }
+
+    #[test]
+    fn only_report_the_missing_attributes_of_the_remaining_expressions() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deals"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree.insert(&1u64, "private").unwrap();
+        atree.insert(&2u64, "exchange_id = 1").unwrap();
+        atree.delete(&2u64);
+        let builder = atree.make_event();
+
+        let report = builder.validate(&atree);
+
+        assert_eq!(["private"], report.missing_attributes());
+    }
+
+    #[test]
+    fn only_share_the_and_operators_written_in_the_same_order_when_evaluated_left_to_right() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer_list("segment_ids"),
+        ];
+        let config = ATreeConfig::new().with_conjunction_order(ConjunctionOrder::LeftToRight);
+        let mut atree = ATree::with_config(&definitions, config).unwrap();
+        atree
+            .insert(&1u64, "segment_ids one of [1, 2] and private")
+            .unwrap();
+        atree
+            .insert(&2u64, "private and segment_ids one of [1, 2]")
+            .unwrap();
+        atree
+            .insert(&3u64, "segment_ids one of [1, 2] and private")
+            .unwrap();
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", true).unwrap();
+        builder.with_integer_list("segment_ids", &[2]).unwrap();
+        let event = builder.build().unwrap();
+
+        let matches = atree.search(&event).unwrap().into_sorted();
+
+        assert_eq!(4, atree.nodes.len());
+        assert_eq!([&1u64, &2, &3], matches.as_slice());
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn evaluate_the_operands_of_an_and_in_the_configured_order() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer_list("segment_ids"),
+        ];
+        let evaluated = |order| {
+            let config = ATreeConfig::new().with_conjunction_order(order);
+            let mut atree = ATree::with_config(&definitions, config).unwrap();
+            atree
+                .insert(&1u64, "segment_ids one of [1, 2] and private")
+                .unwrap();
+            let mut builder = atree.make_event();
+            builder.with_boolean("private", false).unwrap();
+            builder.with_integer_list("segment_ids", &[2]).unwrap();
+            let event = builder.build().unwrap();
+            let report = atree.search(&event).unwrap();
+            assert!(report.matches().is_empty());
+            report.metrics().predicates_evaluated()
+        };
+
+        assert_eq!(1, evaluated(ConjunctionOrder::Cost));
+        assert_eq!(2, evaluated(ConjunctionOrder::LeftToRight));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn count_short_circuits_when_the_access_child_is_false() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deals"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree
+            .insert(&1u64, AN_EXPRESSION_WITH_AND_OPERATORS)
+            .unwrap();
+        let mut builder = atree.make_event();
+        builder.with_integer("exchange_id", 2).unwrap();
+        let event = builder.build().unwrap();
+
+        let report = atree.search(&event).unwrap();
+
+        let metrics = report.metrics();
+        assert_eq!(1, metrics.predicates_evaluated());
+        assert_eq!(1, metrics.short_circuits());
+        assert!(report.matches().is_empty());
+    }
 }
@@ -0,0 +1,187 @@
+use crate::{events::AttributeId, metadata::Metadata};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// A summary of the subscriptions of an [`crate::ATree`] grouped by owner
+///
+/// See [`crate::Metadata::with_owner()`] and [`crate::ATree::audit_report()`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuditReport {
+    owners: Vec<OwnerAudit>,
+}
+
+impl AuditReport {
+    /// The owners sorted by name; the subscriptions without an owner come last.
+    #[inline]
+    pub fn owners(&self) -> &[OwnerAudit] {
+        &self.owners
+    }
+
+    /// The summary of the specified owner; use `None` for the subscriptions without an owner.
+    pub fn owner(&self, owner: Option<&str>) -> Option<&OwnerAudit> {
+        self.owners.iter().find(|audit| audit.owner() == owner)
+    }
+}
+
+/// The subscriptions of a single owner
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OwnerAudit {
+    owner: Option<String>,
+    subscriptions: usize,
+    authors: Vec<String>,
+    oldest: Option<u64>,
+    newest: Option<u64>,
+    attributes: Vec<(String, usize)>,
+}
+
+impl OwnerAudit {
+    /// The owner, or `None` for the subscriptions without an owner.
+    #[inline]
+    pub fn owner(&self) -> Option<&str> {
+        self.owner.as_deref()
+    }
+
+    /// The amount of subscriptions of the owner.
+    #[inline]
+    pub const fn subscriptions(&self) -> usize {
+        self.subscriptions
+    }
+
+    /// The distinct authors of the subscriptions, sorted by name.
+    #[inline]
+    pub fn authors(&self) -> &[String] {
+        &self.authors
+    }
+
+    /// The creation time of the oldest subscription that has one.
+    #[inline]
+    pub const fn oldest(&self) -> Option<u64> {
+        self.oldest
+    }
+
+    /// The creation time of the newest subscription that has one.
+    #[inline]
+    pub const fn newest(&self) -> Option<u64> {
+        self.newest
+    }
+
+    /// The attributes used by the subscriptions along with the amount of subscriptions using
+    /// them, sorted by name.
+    #[inline]
+    pub fn attribute_usage(&self) -> &[(String, usize)] {
+        &self.attributes
+    }
+}
+
+#[derive(Default)]
+struct Accumulator<'a> {
+    subscriptions: usize,
+    authors: BTreeSet<&'a str>,
+    oldest: Option<u64>,
+    newest: Option<u64>,
+    attributes: BTreeMap<&'a str, usize>,
+}
+
+pub(crate) fn report<'a>(
+    subscriptions: impl Iterator<Item = (&'a Metadata, HashSet<AttributeId>)>,
+    names: &HashMap<AttributeId, &'a str>,
+) -> AuditReport {
+    // `None` sorts before `Some` so the subscriptions without an owner are moved at the end.
+    let mut owners: BTreeMap<Option<&str>, Accumulator> = BTreeMap::new();
+    for (metadata, attributes) in subscriptions {
+        let accumulator = owners.entry(metadata.owner()).or_default();
+        accumulator.subscriptions += 1;
+        accumulator.authors.extend(metadata.author());
+        if let Some(created_at) = metadata.created_at() {
+            accumulator.oldest = Some(
+                accumulator
+                    .oldest
+                    .map_or(created_at, |oldest| oldest.min(created_at)),
+            );
+            accumulator.newest = Some(
+                accumulator
+                    .newest
+                    .map_or(created_at, |newest| newest.max(created_at)),
+            );
+        }
+        for attribute in attributes {
+            if let Some(name) = names.get(&attribute) {
+                *accumulator.attributes.entry(name).or_default() += 1;
+            }
+        }
+    }
+
+    let mut owners: Vec<_> = owners
+        .into_iter()
+        .map(|(owner, accumulator)| OwnerAudit {
+            owner: owner.map(str::to_owned),
+            subscriptions: accumulator.subscriptions,
+            authors: accumulator.authors.into_iter().map(str::to_owned).collect(),
+            oldest: accumulator.oldest,
+            newest: accumulator.newest,
+            attributes: accumulator
+                .attributes
+                .into_iter()
+                .map(|(name, count)| (name.to_owned(), count))
+                .collect(),
+        })
+        .collect();
+    if owners.first().is_some_and(|audit| audit.owner.is_none()) {
+        owners.rotate_left(1);
+    }
+    AuditReport { owners }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ATree, AttributeDefinition, Metadata};
+
+    #[test]
+    fn group_the_subscriptions_by_owner() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deals"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        let ads = |author: &str, created_at: u64| {
+            Metadata::new()
+                .with_owner("ads")
+                .with_author(author)
+                .with_created_at(created_at)
+        };
+        atree
+            .insert_with_metadata(&1u64, "private and exchange_id = 1", ads("bob", 20))
+            .unwrap();
+        atree
+            .insert_with_metadata(&2u64, r#"private or deals one of ["a"]"#, ads("alice", 10))
+            .unwrap();
+        atree.insert(&3u64, "exchange_id = 2").unwrap();
+
+        let report = atree.audit_report();
+
+        let owners: Vec<_> = report.owners().iter().map(|audit| audit.owner()).collect();
+        assert_eq!(vec![Some("ads"), None], owners);
+        let ads = report.owner(Some("ads")).unwrap();
+        assert_eq!(2, ads.subscriptions());
+        assert_eq!(["alice", "bob"], ads.authors());
+        assert_eq!((Some(10), Some(20)), (ads.oldest(), ads.newest()));
+        assert_eq!(
+            [
+                ("deals".to_owned(), 1),
+                ("exchange_id".to_owned(), 1),
+                ("private".to_owned(), 2)
+            ],
+            ads.attribute_usage()
+        );
+        let unowned = report.owner(None).unwrap();
+        assert_eq!(1, unowned.subscriptions());
+        assert_eq!([("exchange_id".to_owned(), 1)], unowned.attribute_usage());
+    }
+
+    #[test]
+    fn return_an_empty_report_for_an_empty_tree() {
+        let atree = ATree::<u64>::new(&[AttributeDefinition::boolean("private")]).unwrap();
+
+        assert!(atree.audit_report().owners().is_empty());
+    }
+}
@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+/// The comparison of the searches of an [`crate::ATree`] with the evaluation of each of its
+/// expressions on its own, for a set of events
+///
+/// See [`crate::ATree::compare_with_baseline()`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct BaselineReport {
+    events: usize,
+    tree: Duration,
+    baseline: Duration,
+    mismatches: Vec<usize>,
+}
+
+impl BaselineReport {
+    pub(crate) const fn new(
+        events: usize,
+        tree: Duration,
+        baseline: Duration,
+        mismatches: Vec<usize>,
+    ) -> Self {
+        Self {
+            events,
+            tree,
+            baseline,
+            mismatches,
+        }
+    }
+
+    /// The amount of events that were searched.
+    #[inline]
+    pub const fn events(&self) -> usize {
+        self.events
+    }
+
+    /// The time spent searching the events with [`crate::ATree::search()`].
+    #[inline]
+    pub const fn tree_elapsed(&self) -> Duration {
+        self.tree
+    }
+
+    /// The time spent searching the events with [`crate::ATree::baseline_search()`].
+    #[inline]
+    pub const fn baseline_elapsed(&self) -> Duration {
+        self.baseline
+    }
+
+    /// How many times the searches of the tree were faster than the linear evaluation.
+    pub fn speedup(&self) -> f64 {
+        if self.tree.is_zero() {
+            return f64::INFINITY;
+        }
+        self.baseline.as_secs_f64() / self.tree.as_secs_f64()
+    }
+
+    /// The indexes of the events for which the tree and the linear evaluation disagreed, which
+    /// is a bug of the tree.
+    #[inline]
+    pub fn mismatches(&self) -> &[usize] {
+        &self.mismatches
+    }
+
+    /// Whether the tree and the linear evaluation found the same matches for every event.
+    #[inline]
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compute_the_speedup_of_the_tree() {
+        let report = BaselineReport::new(
+            10,
+            Duration::from_millis(2),
+            Duration::from_millis(10),
+            vec![],
+        );
+
+        assert_eq!(5.0, report.speedup());
+        assert!(report.is_consistent());
+    }
+
+    #[test]
+    fn consider_an_instant_search_infinitely_faster() {
+        let report = BaselineReport::new(0, Duration::ZERO, Duration::ZERO, vec![]);
+
+        assert_eq!(f64::INFINITY, report.speedup());
+    }
+}
@@ -0,0 +1,120 @@
+//! Search matches as [Roaring bitmaps](https://roaringbitmap.org), for the trees whose
+//! subscription IDs are integers.
+
+use crate::atree::Report;
+use roaring::{RoaringBitmap, RoaringTreemap};
+
+/// A subscription ID that can be stored in a Roaring bitmap
+///
+/// This is implemented for the unsigned integer types: the IDs of up to 32 bits are stored in a
+/// [`RoaringBitmap`] and the wider ones in a [`RoaringTreemap`].
+pub trait BitmapId: Copy + Ord {
+    /// Bitmap holding the IDs
+    type Bitmap: Default;
+
+    /// Add the ID to the bitmap.
+    fn insert_into(self, bitmap: &mut Self::Bitmap);
+}
+
+macro_rules! bitmap_id {
+    ($bitmap:ty => $($integer:ty),*) => {
+        $(
+            impl BitmapId for $integer {
+                type Bitmap = $bitmap;
+
+                #[inline]
+                fn insert_into(self, bitmap: &mut Self::Bitmap) {
+                    bitmap.insert(self.into());
+                }
+            }
+        )*
+    };
+}
+
+bitmap_id!(RoaringBitmap => u8, u16, u32);
+bitmap_id!(RoaringTreemap => u64);
+
+impl BitmapId for usize {
+    type Bitmap = RoaringTreemap;
+
+    #[inline]
+    fn insert_into(self, bitmap: &mut Self::Bitmap) {
+        bitmap.insert(self as u64);
+    }
+}
+
+impl<T> Report<'_, T>
+where
+    T: BitmapId,
+{
+    /// Get the search matches as a Roaring bitmap
+    ///
+    /// The bitmap is cheap to intersect with other sets of IDs, such as the subscriptions that
+    /// still have a budget, and is much smaller than the list of matches when they are many. Its
+    /// `serialize_into()` method writes the portable format that the Roaring libraries of the
+    /// other languages read.
+    pub fn matches_bitmap(&self) -> T::Bitmap {
+        let mut matches: Vec<T> = self.matches().iter().map(|id| **id).collect();
+        // The IDs are appended to the last container of the bitmap when they are sorted.
+        matches.sort_unstable();
+        let mut bitmap = T::Bitmap::default();
+        for id in matches {
+            id.insert_into(&mut bitmap);
+        }
+        bitmap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ATree, AttributeDefinition};
+    use roaring::{RoaringBitmap, RoaringTreemap};
+
+    fn search<T>(matching: &[T], others: &[T]) -> T::Bitmap
+    where
+        T: super::BitmapId + std::hash::Hash + std::fmt::Debug,
+    {
+        let definitions = [AttributeDefinition::integer("exchange_id")];
+        let mut atree = ATree::new(&definitions).unwrap();
+        for id in matching {
+            atree.insert(id, "exchange_id = 1").unwrap();
+        }
+        for id in others {
+            atree.insert(id, "exchange_id = 2").unwrap();
+        }
+        let mut builder = atree.make_event();
+        builder.with_integer("exchange_id", 1).unwrap();
+        let event = builder.build().unwrap();
+        atree.search(&event).unwrap().matches_bitmap()
+    }
+
+    #[test]
+    fn can_get_the_matches_of_32_bit_ids_as_a_bitmap() {
+        let bitmap = search(&[7u32, 3, 1_000_000], &[1, 2, 4]);
+
+        assert_eq!(RoaringBitmap::from_iter([7, 3, 1_000_000]), bitmap);
+    }
+
+    #[test]
+    fn can_get_the_matches_of_64_bit_ids_as_a_bitmap() {
+        let bitmap = search(&[u64::MAX, 5], &[1, 2]);
+
+        assert_eq!(RoaringTreemap::from_iter([u64::MAX, 5]), bitmap);
+    }
+
+    #[test]
+    fn return_an_empty_bitmap_when_nothing_matches() {
+        let bitmap = search(&[], &[2u16]);
+
+        assert!(bitmap.is_empty());
+    }
+
+    #[test]
+    fn serialize_the_bitmap_in_the_portable_format() {
+        let bitmap = search(&[1u32, 2, 3], &[]);
+        let mut bytes = vec![];
+        bitmap.serialize_into(&mut bytes).unwrap();
+
+        assert_eq!(bitmap, RoaringBitmap::deserialize_from(&bytes[..]).unwrap());
+    }
+}
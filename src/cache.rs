@@ -0,0 +1,218 @@
+use crate::{ast::Node, stats::map_bytes};
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+/// A bounded cache of the parsed expressions keyed by the hash of the expression.
+///
+/// The cached ASTs refer to the attributes and the strings of the [`crate::ATree`] that parsed
+/// them so a cache must never be shared between trees. When the cache is full, the oldest entry
+/// is evicted.
+#[derive(Clone, Debug)]
+pub(crate) struct ParseCache {
+    capacity: usize,
+    entries: HashMap<u64, CachedAst>,
+    order: VecDeque<u64>,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+#[derive(Clone, Debug)]
+struct CachedAst {
+    expression: Box<str>,
+    ast: Node,
+}
+
+impl ParseCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+        }
+    }
+
+    /// Return a copy of the AST cached for the expression, if any.
+    ///
+    /// The expression is compared with the cached one so that a hash collision is a miss.
+    pub(crate) fn get(&mut self, expression: &str) -> Option<Node> {
+        if self.capacity == 0 {
+            return None;
+        }
+        let cached = self
+            .entries
+            .get(&hash(expression))
+            .filter(|cached| cached.expression.as_ref() == expression);
+        match cached {
+            Some(cached) => {
+                self.hits += 1;
+                Some(cached.ast.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub(crate) fn insert(&mut self, expression: &str, ast: &Node) {
+        if self.capacity == 0 {
+            return;
+        }
+        let key = hash(expression);
+        let cached = CachedAst {
+            expression: expression.into(),
+            ast: ast.clone(),
+        };
+        if self.entries.insert(key, cached).is_some() {
+            return;
+        }
+        self.order.push_back(key);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+                self.evictions += 1;
+            }
+        }
+    }
+
+    /// An estimation of the heap bytes used by the cache, without the cached ASTs.
+    pub(crate) fn heap_bytes(&self) -> usize {
+        map_bytes(&self.entries)
+            + self.order.capacity() * size_of::<u64>()
+            + self
+                .entries
+                .values()
+                .map(|cached| cached.expression.len())
+                .sum::<usize>()
+    }
+
+    pub(crate) fn stats(&self) -> ParseCacheStats {
+        ParseCacheStats {
+            capacity: self.capacity,
+            len: self.entries.len(),
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions,
+        }
+    }
+}
+
+#[inline]
+pub(crate) fn hash(expression: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    expression.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Statistics about the parse cache of an [`crate::ATree`]
+///
+/// See [`crate::ATreeConfig::with_parse_cache()`] and [`crate::ATree::parse_cache_stats()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ParseCacheStats {
+    capacity: usize,
+    len: usize,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+impl ParseCacheStats {
+    /// The maximum amount of expressions kept in the cache; `0` when the cache is disabled.
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The amount of expressions currently in the cache.
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the cache is empty.
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The amount of insertions whose expression was found in the cache.
+    #[inline]
+    pub const fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// The amount of insertions whose expression had to be parsed.
+    #[inline]
+    pub const fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// The amount of expressions removed from the cache to respect its capacity.
+    #[inline]
+    pub const fn evictions(&self) -> u64 {
+        self.evictions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::{AttributeDefinition, AttributeTable},
+        parser,
+        strings::StringTable,
+    };
+
+    #[test]
+    fn can_count_the_hits_and_the_misses() {
+        let (attributes, mut strings) = tables();
+        let mut cache = ParseCache::new(2);
+        let ast = parser::parse("exchange_id = 1", &attributes, &mut strings).unwrap();
+
+        assert_eq!(None, cache.get("exchange_id = 1"));
+        cache.insert("exchange_id = 1", &ast);
+
+        assert_eq!(Some(ast), cache.get("exchange_id = 1"));
+        let stats = cache.stats();
+        assert_eq!((1, 1, 1), (stats.hits(), stats.misses(), stats.len()));
+    }
+
+    #[test]
+    fn can_evict_the_oldest_expression_when_full() {
+        let (attributes, mut strings) = tables();
+        let mut cache = ParseCache::new(2);
+        for expression in ["exchange_id = 1", "exchange_id = 2", "exchange_id = 3"] {
+            let ast = parser::parse(expression, &attributes, &mut strings).unwrap();
+            cache.insert(expression, &ast);
+        }
+
+        assert!(cache.get("exchange_id = 1").is_none());
+        assert!(cache.get("exchange_id = 3").is_some());
+        assert_eq!(1, cache.stats().evictions());
+        assert_eq!(2, cache.stats().len());
+    }
+
+    #[test]
+    fn can_disable_the_cache() {
+        let (attributes, mut strings) = tables();
+        let mut cache = ParseCache::new(0);
+        let ast = parser::parse("exchange_id = 1", &attributes, &mut strings).unwrap();
+
+        cache.insert("exchange_id = 1", &ast);
+
+        assert_eq!(None, cache.get("exchange_id = 1"));
+        assert_eq!(ParseCacheStats::default(), cache.stats());
+    }
+
+    fn tables() -> (AttributeTable, StringTable) {
+        let attributes =
+            AttributeTable::new(&[AttributeDefinition::integer("exchange_id")]).unwrap();
+        (attributes, StringTable::new())
+    }
+}
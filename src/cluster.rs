@@ -0,0 +1,225 @@
+use crate::expression::PredicateExpression;
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+/// The subscriptions of an [`crate::ATree`] grouped by the similarity of their predicates
+///
+/// See [`crate::ATree::cluster_report()`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClusterReport<'a, T> {
+    clusters: Vec<Cluster<'a, T>>,
+}
+
+impl<'a, T> ClusterReport<'a, T> {
+    /// The clusters of at least two subscriptions, from the largest to the smallest.
+    #[inline]
+    pub fn clusters(&self) -> &[Cluster<'a, T>] {
+        &self.clusters
+    }
+}
+
+/// A group of subscriptions whose expressions use similar predicates
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cluster<'a, T> {
+    subscriptions: Vec<&'a T>,
+    shared_predicates: Vec<PredicateExpression>,
+    predicates: usize,
+}
+
+impl<'a, T> Cluster<'a, T> {
+    /// The subscriptions of the cluster, in no particular order.
+    #[inline]
+    pub fn subscriptions(&self) -> &[&'a T] {
+        &self.subscriptions
+    }
+
+    /// The predicates used by every subscription of the cluster, which are the candidates for a
+    /// shared sub-expression or a set-based rewrite.
+    #[inline]
+    pub fn shared_predicates(&self) -> &[PredicateExpression] {
+        &self.shared_predicates
+    }
+
+    /// The amount of distinct predicates used by the subscriptions of the cluster.
+    #[inline]
+    pub const fn predicates(&self) -> usize {
+        self.predicates
+    }
+}
+
+/// Group the subscriptions whose sets of predicates have a Jaccard similarity of at least
+/// `min_similarity`, transitively.
+///
+/// Only the subscriptions sharing at least one predicate are compared, through an inverted index
+/// of the predicates, and `describe` is only called for the shared predicates of the clusters.
+pub(crate) fn report<'a, T, P>(
+    subscriptions: Vec<(&'a T, Vec<P>)>,
+    min_similarity: f64,
+    describe: impl Fn(P) -> PredicateExpression,
+) -> ClusterReport<'a, T>
+where
+    P: Copy + Eq + Hash + Ord,
+{
+    let subscriptions: Vec<_> = subscriptions
+        .into_iter()
+        .filter(|(_, predicates)| !predicates.is_empty())
+        .map(|(id, mut predicates)| {
+            predicates.sort_unstable();
+            predicates.dedup();
+            (id, predicates)
+        })
+        .collect();
+    let mut index: HashMap<P, Vec<usize>> = HashMap::new();
+    for (position, (_, predicates)) in subscriptions.iter().enumerate() {
+        for predicate in predicates {
+            index.entry(*predicate).or_default().push(position);
+        }
+    }
+
+    let mut groups = DisjointSets::new(subscriptions.len());
+    for (position, (_, predicates)) in subscriptions.iter().enumerate() {
+        let mut shared: HashMap<usize, usize> = HashMap::new();
+        for predicate in predicates {
+            // The postings are in ascending order, so each pair is only compared once.
+            for other in index[predicate].iter().filter(|other| **other > position) {
+                *shared.entry(*other).or_default() += 1;
+            }
+        }
+        for (other, shared) in shared {
+            let union = predicates.len() + subscriptions[other].1.len() - shared;
+            if shared as f64 / union as f64 >= min_similarity {
+                groups.union(position, other);
+            }
+        }
+    }
+
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for position in 0..subscriptions.len() {
+        members
+            .entry(groups.find(position))
+            .or_default()
+            .push(position);
+    }
+    let mut clusters: Vec<_> = members
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|members| {
+            let mut shared: Vec<P> = subscriptions[members[0]].1.clone();
+            let mut distinct = HashSet::new();
+            for member in &members {
+                let predicates = &subscriptions[*member].1;
+                shared.retain(|predicate| predicates.binary_search(predicate).is_ok());
+                distinct.extend(predicates.iter().copied());
+            }
+            Cluster {
+                subscriptions: members
+                    .iter()
+                    .map(|member| subscriptions[*member].0)
+                    .collect(),
+                shared_predicates: shared.into_iter().map(&describe).collect(),
+                predicates: distinct.len(),
+            }
+        })
+        .collect();
+    clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.subscriptions.len()));
+    ClusterReport { clusters }
+}
+
+/// A union-find structure with path halving.
+struct DisjointSets {
+    parents: Vec<usize>,
+}
+
+impl DisjointSets {
+    fn new(size: usize) -> Self {
+        Self {
+            parents: (0..size).collect(),
+        }
+    }
+
+    fn find(&mut self, mut element: usize) -> usize {
+        while self.parents[element] != element {
+            self.parents[element] = self.parents[self.parents[element]];
+            element = self.parents[element];
+        }
+        element
+    }
+
+    fn union(&mut self, left: usize, right: usize) {
+        let left = self.find(left);
+        let right = self.find(right);
+        self.parents[left.max(right)] = left.min(right);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ATree, AttributeDefinition, PredicateOperator};
+
+    fn an_atree() -> ATree<u64> {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string_list("deals"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree
+            .insert(&1, r#"private and exchange_id = 1 and country = "CA""#)
+            .unwrap();
+        atree
+            .insert(&2, r#"private and exchange_id = 1 and country = "US""#)
+            .unwrap();
+        atree.insert(&3, "private or exchange_id = 1").unwrap();
+        atree
+            .insert(&4, r#"deals one of ["deal-1"] and country = "FR""#)
+            .unwrap();
+        atree.insert(&5, "true").unwrap();
+        atree
+    }
+
+    #[test]
+    fn group_the_subscriptions_with_similar_predicates() {
+        let atree = an_atree();
+
+        let report = atree.cluster_report(0.5);
+
+        assert_eq!(1, report.clusters().len());
+        let cluster = &report.clusters()[0];
+        let mut subscriptions = cluster.subscriptions().to_vec();
+        subscriptions.sort_unstable();
+        assert_eq!([&1, &2, &3], subscriptions.as_slice());
+        assert_eq!(4, cluster.predicates());
+        let mut shared: Vec<_> = cluster
+            .shared_predicates()
+            .iter()
+            .map(|predicate| (predicate.attribute(), predicate.operator()))
+            .collect();
+        shared.sort_unstable_by_key(|(attribute, _)| *attribute);
+        assert_eq!(
+            [
+                ("exchange_id", PredicateOperator::Equal),
+                ("private", PredicateOperator::Variable)
+            ],
+            shared.as_slice()
+        );
+    }
+
+    #[test]
+    fn only_group_the_subscriptions_with_the_same_predicates_at_the_maximum_similarity() {
+        let mut atree = an_atree();
+        atree
+            .insert(&6, r#"country = "CA" and private and exchange_id = 1"#)
+            .unwrap();
+
+        let report = atree.cluster_report(1.0);
+
+        assert_eq!(1, report.clusters().len());
+        let mut subscriptions = report.clusters()[0].subscriptions().to_vec();
+        subscriptions.sort_unstable();
+        assert_eq!([&1, &6], subscriptions.as_slice());
+        assert_eq!(3, report.clusters()[0].shared_predicates().len());
+    }
+}
@@ -0,0 +1,779 @@
+use crate::{
+    ast::Node,
+    cache::hash,
+    events::AttributeTable,
+    fuzzy::{FuzzyPattern, MAX_EDIT_DISTANCE},
+    parser,
+    predicates::{
+        ComparisonOperator, ComparisonValue, EqualityOperator, FuzzyOperator, ListLiteral,
+        ListOperator, NullOperator, PatternOperator, Predicate, PredicateKind, PrimitiveLiteral,
+        RangeOperator, RecencyOperator, SetOperator,
+    },
+    snapshot::{write_length, write_str, Reader, SnapshotError},
+    stats::map_bytes,
+    strings::{Interner, LocalStringTable, StringTable},
+};
+use itertools::Itertools;
+use rust_decimal::Decimal;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    io,
+};
+
+/// The bytes at the start of every compilation cache.
+const MAGIC: &[u8; 8] = b"ATREECMP";
+
+/// The version of the layout of the compiled expressions.
+///
+/// A cache is only an optimization so a cache written with another version is rejected instead
+/// of being migrated; its expressions are parsed again and the cache can be rewritten.
+const FORMAT_VERSION: u16 = 1;
+
+/// The deepest nesting of the `and`, `or` and `not` operators that is cached.
+///
+/// The nodes are decoded recursively, so the limit keeps a corrupted cache from exhausting the
+/// stack; the expressions nested deeper are parsed every time.
+const MAX_DEPTH: usize = 256;
+
+/// The compiled form of the expressions whose predicates are costly to compile, keyed by the
+/// hash of their content; see [`crate::ATree::save_compilation_cache()`].
+///
+/// The predicates refer to their attributes by name and to their strings by value so that they
+/// can be decoded by another [`crate::ATree`] than the one that compiled them. Decoding them
+/// skips the lexer and the parser; an entry that no longer fits the
+/// attributes of the tree is ignored so that its expression is parsed again.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CompilationCache {
+    entries: HashMap<u64, CompiledExpression>,
+}
+
+#[derive(Clone, Debug)]
+struct CompiledExpression {
+    expression: Box<str>,
+    bytes: Box<[u8]>,
+}
+
+impl CompilationCache {
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Decode the AST compiled for the expression, if any, with the attributes and the strings
+    /// of the tree.
+    ///
+    /// The strings are decoded in a table of their own and only interned in the strings of the
+    /// tree once the whole expression decoded. An entry that cannot be decoded any more is dropped so that it is compiled again from the
+    /// parsed expression the next time the cache is written.
+    pub(crate) fn get(
+        &mut self,
+        expression: &str,
+        attributes: &AttributeTable,
+        strings: &mut StringTable,
+    ) -> Option<Node> {
+        let key = hash(expression);
+        let compiled = self
+            .entries
+            .get(&key)
+            .filter(|compiled| compiled.expression.as_ref() == expression)?;
+        let mut reader = Reader::new(&compiled.bytes);
+        let mut local = LocalStringTable::default();
+        let decoded = decode_node(&mut reader, attributes, &mut local, 0).ok();
+        if decoded.is_none() {
+            self.entries.remove(&key);
+        }
+        decoded.map(|ast| intern_node(ast, &local, strings))
+    }
+
+    /// Write the compiled form of the expressions that have a predicate costly to compile,
+    /// returning how many were written.
+    ///
+    /// The expressions that are not in the cache yet are parsed to be compiled.
+    pub(crate) fn write<'a>(
+        &self,
+        expressions: impl Iterator<Item = &'a str>,
+        attributes: &AttributeTable,
+        buffer: &mut Vec<u8>,
+    ) -> usize {
+        let names = attributes.names_by_id();
+        // The entries are written in the order of their hash so that the same expressions always
+        // produce the same file.
+        let entries: BTreeMap<_, _> = expressions
+            .filter_map(|expression| {
+                let key = hash(expression);
+                let bytes = match self
+                    .entries
+                    .get(&key)
+                    .filter(|compiled| compiled.expression.as_ref() == expression)
+                {
+                    Some(compiled) => Cow::Borrowed(&*compiled.bytes),
+                    None => Cow::Owned(compile(expression, attributes, &names)?),
+                };
+                Some((key, (expression, bytes)))
+            })
+            .collect();
+        buffer.extend_from_slice(MAGIC);
+        buffer.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        write_str(buffer, env!("CARGO_PKG_VERSION"));
+        write_length(buffer, entries.len());
+        for (expression, bytes) in entries.values() {
+            write_str(buffer, expression);
+            write_length(buffer, bytes.len());
+            buffer.extend_from_slice(bytes);
+        }
+        entries.len()
+    }
+
+    /// Add the compiled expressions written by [`CompilationCache::write()`], returning how many
+    /// were read.
+    pub(crate) fn read(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        let mut reader = Reader::new(bytes);
+        if reader.bytes(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+            return Err(invalid_data("the data is not an A-Tree compilation cache"));
+        }
+        let version = reader.u16().map_err(corrupted)?;
+        let written_by = reader.str().map_err(corrupted)?;
+        if version != FORMAT_VERSION {
+            return Err(invalid_data(&format!(
+                "compilation cache format version {version} written by a-tree {written_by} is not supported (supported: {FORMAT_VERSION})"
+            )));
+        }
+        let count = reader.length().map_err(corrupted)?;
+        for _ in 0..count {
+            let expression = reader.str().map_err(corrupted)?;
+            let length = reader.length().map_err(corrupted)?;
+            let bytes = reader.bytes(length).map_err(corrupted)?;
+            self.entries.insert(
+                hash(expression),
+                CompiledExpression {
+                    expression: expression.into(),
+                    bytes: bytes.into(),
+                },
+            );
+        }
+        Ok(count)
+    }
+
+    pub(crate) fn heap_bytes(&self) -> usize {
+        map_bytes(&self.entries)
+            + self
+                .entries
+                .values()
+                .map(|compiled| compiled.expression.len() + compiled.bytes.len())
+                .sum::<usize>()
+    }
+}
+
+/// Parse the expression with strings of its own and encode it when it has a predicate costly to
+/// compile.
+fn compile(expression: &str, attributes: &AttributeTable, names: &[&str]) -> Option<Vec<u8>> {
+    let mut strings = LocalStringTable::default();
+    let ast = parser::parse(expression, attributes, &mut strings).ok()?;
+    if !is_heavyweight(&ast) {
+        return None;
+    }
+    let mut bytes = Vec::new();
+    encode_node(&mut bytes, &ast, names, &strings, 0)?;
+    Some(bytes)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn corrupted(error: SnapshotError) -> io::Error {
+    invalid_data(&error.to_string())
+}
+
+/// Whether the expression has a predicate whose values are sorted, interned or preprocessed
+/// when it is compiled.
+fn is_heavyweight(ast: &Node) -> bool {
+    match ast {
+        Node::And(left, right) | Node::Or(left, right) => {
+            is_heavyweight(left) || is_heavyweight(right)
+        }
+        Node::Not(node) => is_heavyweight(node),
+        Node::Value(predicate) => matches!(
+            predicate.kind(),
+            PredicateKind::Set(_, _)
+                | PredicateKind::List(_, _)
+                | PredicateKind::Pattern(_, _)
+                | PredicateKind::Fuzzy(_, _)
+        ),
+        Node::Constant(_) | Node::Placeholder(_) => false,
+    }
+}
+
+fn encode_node(
+    buffer: &mut Vec<u8>,
+    node: &Node,
+    names: &[&str],
+    strings: &LocalStringTable,
+    depth: usize,
+) -> Option<()> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+    match node {
+        Node::And(left, right) | Node::Or(left, right) => {
+            buffer.push(if matches!(node, Node::And(_, _)) {
+                0
+            } else {
+                1
+            });
+            encode_node(buffer, left, names, strings, depth + 1)?;
+            encode_node(buffer, right, names, strings, depth + 1)?;
+        }
+        Node::Not(node) => {
+            buffer.push(2);
+            encode_node(buffer, node, names, strings, depth + 1)?;
+        }
+        Node::Value(predicate) => {
+            buffer.push(3);
+            write_str(buffer, names.get(predicate.attribute().index())?);
+            encode_kind(buffer, predicate.kind(), strings);
+        }
+        Node::Constant(value) => buffer.push(4 + u8::from(*value)),
+        // The placeholders only appear in the templates, which are not compiled ahead.
+        Node::Placeholder(_) => return None,
+    }
+    Some(())
+}
+
+fn encode_kind(buffer: &mut Vec<u8>, kind: &PredicateKind, strings: &LocalStringTable) {
+    match kind {
+        PredicateKind::Variable => buffer.push(0),
+        PredicateKind::NegatedVariable => buffer.push(1),
+        PredicateKind::Set(operator, values) => {
+            buffer.push(2);
+            buffer.push(u8::from(matches!(operator, SetOperator::NotIn)));
+            encode_list(buffer, values, strings);
+        }
+        PredicateKind::Comparison(operator, value) => {
+            buffer.push(3);
+            buffer.push(match operator {
+                ComparisonOperator::LessThan => 0,
+                ComparisonOperator::LessThanEqual => 1,
+                ComparisonOperator::GreaterThanEqual => 2,
+                ComparisonOperator::GreaterThan => 3,
+            });
+            encode_comparison_value(buffer, value);
+        }
+        PredicateKind::Range(operator, low, high) => {
+            buffer.push(4);
+            buffer.push(u8::from(matches!(operator, RangeOperator::NotBetween)));
+            encode_comparison_value(buffer, low);
+            encode_comparison_value(buffer, high);
+        }
+        PredicateKind::Equality(operator, value) => {
+            buffer.push(5);
+            buffer.push(u8::from(matches!(operator, EqualityOperator::NotEqual)));
+            match value {
+                PrimitiveLiteral::Integer(value) => {
+                    buffer.push(0);
+                    buffer.extend_from_slice(&value.to_le_bytes());
+                }
+                PrimitiveLiteral::Float(value) => {
+                    buffer.push(1);
+                    buffer.extend_from_slice(&value.serialize());
+                }
+                PrimitiveLiteral::String(value) => {
+                    buffer.push(2);
+                    write_str(buffer, strings.resolve(*value));
+                }
+            }
+        }
+        PredicateKind::List(operator, values) => {
+            buffer.push(6);
+            match operator {
+                ListOperator::OneOf => buffer.push(0),
+                ListOperator::NoneOf => buffer.push(1),
+                ListOperator::AllOf => buffer.push(2),
+                ListOperator::NotAllOf => buffer.push(3),
+                ListOperator::IntersectAtLeast(count) => {
+                    buffer.push(4);
+                    write_length(buffer, *count);
+                }
+                ListOperator::IntersectFewerThan(count) => {
+                    buffer.push(5);
+                    write_length(buffer, *count);
+                }
+            }
+            encode_list(buffer, values, strings);
+        }
+        PredicateKind::Null(operator) => {
+            buffer.push(7);
+            buffer.push(match operator {
+                NullOperator::IsNull => 0,
+                NullOperator::IsNotNull => 1,
+                NullOperator::IsEmpty => 2,
+                NullOperator::IsNotEmpty => 3,
+            });
+        }
+        PredicateKind::Pattern(operator, pattern) => {
+            buffer.push(8);
+            buffer.push(match operator {
+                PatternOperator::StartsWith => 0,
+                PatternOperator::NotStartsWith => 1,
+                PatternOperator::Contains => 2,
+                PatternOperator::NotContains => 3,
+            });
+            write_str(buffer, pattern);
+        }
+        PredicateKind::Recency(operator, window) => {
+            buffer.push(9);
+            buffer.push(u8::from(operator.is_negated()));
+            buffer.extend_from_slice(&window.to_le_bytes());
+        }
+        PredicateKind::Fuzzy(operator, pattern) => {
+            buffer.push(10);
+            buffer.push(u8::from(matches!(operator, FuzzyOperator::NotMatches)));
+            write_str(buffer, pattern.pattern());
+            buffer.push(pattern.distance());
+        }
+    }
+}
+
+fn encode_comparison_value(buffer: &mut Vec<u8>, value: &ComparisonValue) {
+    match value {
+        ComparisonValue::Integer(value) => {
+            buffer.push(0);
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+        ComparisonValue::Float(value) => {
+            buffer.push(1);
+            buffer.extend_from_slice(&value.serialize());
+        }
+    }
+}
+
+fn encode_list(buffer: &mut Vec<u8>, values: &ListLiteral, strings: &LocalStringTable) {
+    match values {
+        ListLiteral::IntegerList(values) => {
+            buffer.push(0);
+            write_length(buffer, values.len());
+            for value in values {
+                buffer.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        ListLiteral::StringList(values) => {
+            buffer.push(1);
+            write_length(buffer, values.len());
+            for value in values {
+                write_str(buffer, strings.resolve(*value));
+            }
+        }
+    }
+}
+
+fn decode_node(
+    reader: &mut Reader,
+    attributes: &AttributeTable,
+    strings: &mut LocalStringTable,
+    depth: usize,
+) -> Result<Node, SnapshotError> {
+    if depth > MAX_DEPTH {
+        return Err(SnapshotError::Corrupted(format!(
+            "the expression is nested deeper than {MAX_DEPTH} operators"
+        )));
+    }
+    let node = match reader.u8()? {
+        0 => Node::And(
+            Box::new(decode_node(reader, attributes, strings, depth + 1)?),
+            Box::new(decode_node(reader, attributes, strings, depth + 1)?),
+        ),
+        1 => Node::Or(
+            Box::new(decode_node(reader, attributes, strings, depth + 1)?),
+            Box::new(decode_node(reader, attributes, strings, depth + 1)?),
+        ),
+        2 => Node::Not(Box::new(decode_node(
+            reader,
+            attributes,
+            strings,
+            depth + 1,
+        )?)),
+        3 => {
+            let name = reader.str()?;
+            let kind = decode_kind(reader, strings)?;
+            let predicate = Predicate::new(attributes, name, kind)
+                .and_then(|predicate| predicate.check_dictionary(attributes, &*strings, name))
+                .map_err(SnapshotError::Event)?;
+            Node::Value(predicate)
+        }
+        4 => Node::Constant(false),
+        5 => Node::Constant(true),
+        tag => return Err(unknown_tag("node", tag)),
+    };
+    Ok(node)
+}
+
+fn decode_kind(
+    reader: &mut Reader,
+    strings: &mut LocalStringTable,
+) -> Result<PredicateKind, SnapshotError> {
+    let kind = match reader.u8()? {
+        0 => PredicateKind::Variable,
+        1 => PredicateKind::NegatedVariable,
+        2 => {
+            let operator = match reader.u8()? {
+                0 => SetOperator::In,
+                1 => SetOperator::NotIn,
+                tag => return Err(unknown_tag("set operator", tag)),
+            };
+            PredicateKind::Set(operator, decode_list(reader, strings)?)
+        }
+        3 => {
+            let operator = match reader.u8()? {
+                0 => ComparisonOperator::LessThan,
+                1 => ComparisonOperator::LessThanEqual,
+                2 => ComparisonOperator::GreaterThanEqual,
+                3 => ComparisonOperator::GreaterThan,
+                tag => return Err(unknown_tag("comparison operator", tag)),
+            };
+            PredicateKind::Comparison(operator, decode_comparison_value(reader)?)
+        }
+        4 => {
+            let operator = match reader.u8()? {
+                0 => RangeOperator::Between,
+                1 => RangeOperator::NotBetween,
+                tag => return Err(unknown_tag("range operator", tag)),
+            };
+            let low = decode_comparison_value(reader)?;
+            let high = decode_comparison_value(reader)?;
+            PredicateKind::Range(operator, low, high)
+        }
+        5 => {
+            let operator = match reader.u8()? {
+                0 => EqualityOperator::Equal,
+                1 => EqualityOperator::NotEqual,
+                tag => return Err(unknown_tag("equality operator", tag)),
+            };
+            let value = match reader.u8()? {
+                0 => PrimitiveLiteral::Integer(read_i64(reader)?),
+                1 => PrimitiveLiteral::Float(read_decimal(reader)?),
+                2 => PrimitiveLiteral::String(strings.intern(reader.str()?)),
+                tag => return Err(unknown_tag("literal", tag)),
+            };
+            PredicateKind::Equality(operator, value)
+        }
+        6 => {
+            let operator = match reader.u8()? {
+                0 => ListOperator::OneOf,
+                1 => ListOperator::NoneOf,
+                2 => ListOperator::AllOf,
+                3 => ListOperator::NotAllOf,
+                4 => ListOperator::IntersectAtLeast(read_count(reader)?),
+                5 => ListOperator::IntersectFewerThan(read_count(reader)?),
+                tag => return Err(unknown_tag("list operator", tag)),
+            };
+            PredicateKind::List(operator, decode_list(reader, strings)?)
+        }
+        7 => PredicateKind::Null(match reader.u8()? {
+            0 => NullOperator::IsNull,
+            1 => NullOperator::IsNotNull,
+            2 => NullOperator::IsEmpty,
+            3 => NullOperator::IsNotEmpty,
+            tag => return Err(unknown_tag("null operator", tag)),
+        }),
+        8 => {
+            let operator = match reader.u8()? {
+                0 => PatternOperator::StartsWith,
+                1 => PatternOperator::NotStartsWith,
+                2 => PatternOperator::Contains,
+                3 => PatternOperator::NotContains,
+                tag => return Err(unknown_tag("pattern operator", tag)),
+            };
+            PredicateKind::Pattern(operator, reader.str()?.to_owned())
+        }
+        9 => {
+            let operator = match reader.u8()? {
+                0 => RecencyOperator::WithinLast,
+                1 => RecencyOperator::NotWithinLast,
+                tag => return Err(unknown_tag("recency operator", tag)),
+            };
+            PredicateKind::Recency(operator, read_i64(reader)?)
+        }
+        10 => {
+            let operator = match reader.u8()? {
+                0 => FuzzyOperator::Matches,
+                1 => FuzzyOperator::NotMatches,
+                tag => return Err(unknown_tag("fuzzy operator", tag)),
+            };
+            let pattern = reader.str()?;
+            let distance = reader.u8()?;
+            if distance > MAX_EDIT_DISTANCE {
+                return Err(SnapshotError::Corrupted(format!(
+                    "the edit distance {distance} is above {MAX_EDIT_DISTANCE}"
+                )));
+            }
+            PredicateKind::Fuzzy(operator, FuzzyPattern::new(pattern, distance))
+        }
+        tag => return Err(unknown_tag("predicate", tag)),
+    };
+    Ok(kind)
+}
+
+/// Intern the strings of an expression decoded with strings of its own in the strings of the
+/// tree.
+fn intern_node(node: Node, local: &LocalStringTable, strings: &mut StringTable) -> Node {
+    match node {
+        Node::And(left, right) => Node::And(
+            Box::new(intern_node(*left, local, strings)),
+            Box::new(intern_node(*right, local, strings)),
+        ),
+        Node::Or(left, right) => Node::Or(
+            Box::new(intern_node(*left, local, strings)),
+            Box::new(intern_node(*right, local, strings)),
+        ),
+        Node::Not(node) => Node::Not(Box::new(intern_node(*node, local, strings))),
+        Node::Value(predicate) => {
+            Node::Value(predicate.map_strings(|id| strings.get_or_update(local.resolve(id))))
+        }
+        Node::Constant(_) | Node::Placeholder(_) => node,
+    }
+}
+
+fn decode_comparison_value(reader: &mut Reader) -> Result<ComparisonValue, SnapshotError> {
+    match reader.u8()? {
+        0 => Ok(ComparisonValue::Integer(read_i64(reader)?)),
+        1 => Ok(ComparisonValue::Float(read_decimal(reader)?)),
+        tag => Err(unknown_tag("comparison value", tag)),
+    }
+}
+
+fn decode_list(
+    reader: &mut Reader,
+    strings: &mut LocalStringTable,
+) -> Result<ListLiteral, SnapshotError> {
+    let tag = reader.u8()?;
+    let length = reader.length()?;
+    // The lists are searched by value, so they are sorted again instead of trusting the file;
+    // the string lists are searched by ID, which differ from one tree to the other.
+    match tag {
+        0 => (0..length)
+            .map(|_| read_i64(reader))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|values| ListLiteral::IntegerList(values.into_iter().sorted().unique().collect())),
+        1 => (0..length)
+            .map(|_| reader.str().map(|value| strings.intern(value)))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|values| ListLiteral::StringList(values.into_iter().sorted().unique().collect())),
+        tag => Err(unknown_tag("list", tag)),
+    }
+}
+
+/// Read the amount of values of an `intersect_at_least` or `intersect_fewer_than`, which the
+/// parser only accepts when positive.
+fn read_count(reader: &mut Reader) -> Result<usize, SnapshotError> {
+    match reader.length()? {
+        0 => Err(SnapshotError::Corrupted("the count is 0".to_owned())),
+        count => Ok(count),
+    }
+}
+
+#[inline]
+fn read_i64(reader: &mut Reader) -> Result<i64, SnapshotError> {
+    Ok(reader.u64()? as i64)
+}
+
+fn read_decimal(reader: &mut Reader) -> Result<Decimal, SnapshotError> {
+    let bytes = reader.bytes(16)?;
+    let bytes = bytes.try_into().map_err(|_| SnapshotError::Truncated)?;
+    Ok(Decimal::deserialize(bytes))
+}
+
+fn unknown_tag(what: &str, tag: u8) -> SnapshotError {
+    SnapshotError::Corrupted(format!("unknown {what} tag {tag}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::AttributeDefinition;
+
+    const EXPRESSIONS: [&str; 6] = [
+        r#"deals one of ["deal-2", "deal-1"] and exchange_id in [3, 1, 2]"#,
+        r#"not (deal starts with "deal-") or price between 1.5 and 2.99"#,
+        r#"brand ~= "adidas" within 1 and exchange_id <> 5"#,
+        r#"deals intersect_at_least ["deal-1", "deal-2", "deal-3"] count 2 or private"#,
+        r#"country not in ["US", "CA"] and price = 2.5 and deal is not null"#,
+        r#"segments none of [1, 2] and exchange_id >= 2"#,
+    ];
+
+    #[test]
+    fn decode_the_expressions_like_the_parser() {
+        let attributes = define_attributes();
+        let mut buffer = Vec::new();
+        let written =
+            CompilationCache::default().write(EXPRESSIONS.into_iter(), &attributes, &mut buffer);
+        assert_eq!(6, written);
+
+        let mut cache = CompilationCache::default();
+        assert_eq!(6, cache.read(&buffer).unwrap());
+        let mut strings = StringTable::new();
+        strings.get_or_update("unrelated");
+        for expression in EXPRESSIONS.into_iter().rev() {
+            let decoded = cache.get(expression, &attributes, &mut strings).unwrap();
+            let parsed = parser::parse(expression, &attributes, &mut strings).unwrap();
+
+            assert_eq!(parsed, decoded);
+        }
+    }
+
+    #[test]
+    fn only_write_the_expressions_with_heavyweight_predicates() {
+        let attributes = define_attributes();
+        let expressions = [
+            "exchange_id = 1 and private",
+            "exchange_id in [1, 2]",
+            "private =",
+        ];
+        let mut buffer = Vec::new();
+
+        let written =
+            CompilationCache::default().write(expressions.into_iter(), &attributes, &mut buffer);
+
+        assert_eq!(1, written);
+    }
+
+    #[test]
+    fn drop_the_expressions_that_no_longer_fit_the_attributes() {
+        let attributes = define_attributes();
+        let mut buffer = Vec::new();
+        let expression = "exchange_id in [1, 2]";
+        CompilationCache::default().write(std::iter::once(expression), &attributes, &mut buffer);
+        let mut cache = CompilationCache::default();
+        cache.read(&buffer).unwrap();
+        let other = AttributeTable::new(&[AttributeDefinition::string("exchange_id")]).unwrap();
+        let mut strings = StringTable::new();
+
+        assert_eq!(None, cache.get(expression, &other, &mut strings));
+        assert!(cache.is_empty());
+        cache.read(&buffer).unwrap();
+        assert_eq!(
+            None,
+            cache.get("exchange_id in [1, 3]", &attributes, &mut strings)
+        );
+        assert!(!cache.is_empty());
+    }
+
+    #[test]
+    fn reject_the_data_that_is_not_a_compilation_cache() {
+        let attributes = define_attributes();
+        let mut cache = CompilationCache::default();
+
+        let error = cache.read(b"ATREESNP").unwrap_err();
+
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        let mut buffer = Vec::new();
+        CompilationCache::default().write(std::iter::empty(), &attributes, &mut buffer);
+        buffer[MAGIC.len()] += 1;
+        assert!(cache.read(&buffer).is_err());
+        assert!(cache.read(&buffer[..MAGIC.len() + 1]).is_err());
+    }
+
+    #[test]
+    fn reject_the_expressions_nested_too_deeply() {
+        let attributes = define_attributes();
+        let expression = (0..=MAX_DEPTH)
+            .fold(r#"deal starts with "deal-""#.to_owned(), |nested, _| {
+                format!("private and ({nested})")
+            });
+        let mut buffer = Vec::new();
+        let written = CompilationCache::default().write(
+            std::iter::once(expression.as_str()),
+            &attributes,
+            &mut buffer,
+        );
+        assert_eq!(0, written);
+
+        let mut bytes = vec![2; MAX_DEPTH + 1];
+        bytes.push(5);
+        let mut cache = cache_of(&expression, bytes);
+        let mut strings = StringTable::new();
+
+        assert_eq!(None, cache.get(&expression, &attributes, &mut strings));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn sort_the_lists_read_from_the_cache() {
+        let attributes = define_attributes();
+        let expression = "exchange_id in [1, 3]";
+        let mut bytes = vec![3];
+        write_str(&mut bytes, "exchange_id");
+        bytes.extend([2, 0, 0]);
+        write_length(&mut bytes, 3);
+        for value in [3u64, 1, 3] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut cache = cache_of(expression, bytes);
+        let mut strings = StringTable::new();
+
+        let decoded = cache.get(expression, &attributes, &mut strings).unwrap();
+
+        let parsed = parser::parse(expression, &attributes, &mut strings).unwrap();
+        assert_eq!(parsed, decoded);
+    }
+
+    #[test]
+    fn reject_the_unknown_operator_tags() {
+        let attributes = define_attributes();
+        let expression = "exchange_id not in [1, 3]";
+        let mut bytes = vec![3];
+        write_str(&mut bytes, "exchange_id");
+        bytes.extend([2, 2, 0]);
+        write_length(&mut bytes, 2);
+        for value in [1u64, 3] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut cache = cache_of(expression, bytes);
+        let mut strings = StringTable::new();
+
+        assert_eq!(None, cache.get(expression, &attributes, &mut strings));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn keep_the_strings_of_the_expressions_that_do_not_decode_out_of_the_tree() {
+        let attributes = define_attributes();
+        let expression = r#"deal = "deal-1" and private"#;
+        let mut bytes = vec![0, 3];
+        write_str(&mut bytes, "deal");
+        bytes.extend([5, 0, 2]);
+        write_str(&mut bytes, "deal-1");
+        bytes.push(6);
+        let mut cache = cache_of(expression, bytes);
+        let mut strings = StringTable::new();
+
+        assert_eq!(None, cache.get(expression, &attributes, &mut strings));
+        assert_eq!(0, strings.len());
+    }
+
+    fn cache_of(expression: &str, bytes: Vec<u8>) -> CompilationCache {
+        let mut cache = CompilationCache::default();
+        cache.entries.insert(
+            hash(expression),
+            CompiledExpression {
+                expression: expression.into(),
+                bytes: bytes.into(),
+            },
+        );
+        cache
+    }
+
+    fn define_attributes() -> AttributeTable {
+        AttributeTable::new(&[
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::string("deal"),
+            AttributeDefinition::float("price"),
+            AttributeDefinition::string("brand"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::integer_list("segments"),
+        ])
+        .unwrap()
+    }
+}
@@ -0,0 +1,359 @@
+use rust_decimal::RoundingStrategy;
+
+/// How a float is rounded when it is converted to the decimal representation used by the
+/// [`crate::ATree`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RoundingPolicy {
+    /// Round to the nearest value and the midpoints to the nearest even digit (i.e. `2.5` becomes
+    /// `2` and `3.5` becomes `4`).
+    #[default]
+    HalfEven,
+    /// Round to the nearest value and the midpoints away from zero (i.e. `2.5` becomes `3` and
+    /// `-2.5` becomes `-3`).
+    HalfAwayFromZero,
+    /// Truncate the digits (i.e. `2.9` becomes `2` and `-2.9` becomes `-2`).
+    TowardZero,
+    /// Round away from zero (i.e. `2.1` becomes `3` and `-2.1` becomes `-3`).
+    AwayFromZero,
+    /// Round toward negative infinity (i.e. `2.9` becomes `2` and `-2.1` becomes `-3`).
+    Floor,
+    /// Round toward positive infinity (i.e. `2.1` becomes `3` and `-2.9` becomes `-2`).
+    Ceiling,
+}
+
+impl RoundingPolicy {
+    #[inline]
+    pub(crate) const fn strategy(self) -> RoundingStrategy {
+        match self {
+            Self::HalfEven => RoundingStrategy::MidpointNearestEven,
+            Self::HalfAwayFromZero => RoundingStrategy::MidpointAwayFromZero,
+            Self::TowardZero => RoundingStrategy::ToZero,
+            Self::AwayFromZero => RoundingStrategy::AwayFromZero,
+            Self::Floor => RoundingStrategy::ToNegativeInfinity,
+            Self::Ceiling => RoundingStrategy::ToPositiveInfinity,
+        }
+    }
+}
+
+/// What an [`crate::EventBuilder`] does when it receives an attribute that is not defined in the
+/// [`crate::ATree`]
+///
+/// Ignoring the unknown attributes allows to roll out a new attribute in the services producing
+/// the events before the tree is updated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum UnknownAttributePolicy {
+    /// Return [`crate::EventError::NonExistingAttribute`].
+    #[default]
+    Error,
+    /// Skip the attribute silently.
+    Ignore,
+    /// Skip the attribute and notify the [`crate::TreeObserver::on_unknown_attribute()`] hooks.
+    Warn,
+}
+
+/// What an [`crate::ATree`] does when an expression is inserted for a subscription ID that is
+/// already present
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DuplicateIdPolicy {
+    /// Insert the new expression without removing the previous one: the subscription matches, once,
+    /// when any of them matches but only the last one is returned by
+    /// [`crate::ATree::expression()`] and removed by [`crate::ATree::delete()`].
+    #[default]
+    Allow,
+    /// Delete the previous expression once the new one is parsed.
+    Replace,
+    /// Return [`crate::ATreeError::DuplicateId`] and keep the previous expression.
+    Reject,
+}
+
+/// How an [`crate::ATree`] deletes the subscriptions
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DeletePolicy {
+    /// Remove the nodes that are no longer used by any subscription right away.
+    #[default]
+    Immediate,
+    /// Only mark the subscription as deleted, in constant time, and leave its nodes in the tree
+    /// until [`crate::ATree::compact()`] removes them. The searches skip the deleted
+    /// subscriptions, but their nodes are still evaluated and kept in memory until then.
+    Tombstone,
+}
+
+/// The order in which an [`crate::ATree`] evaluates the operands of the `and` operators
+///
+/// The order does not change the matches, only which predicates are evaluated: the second operand
+/// of an `and` is not evaluated for it once the first one is `false`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ConjunctionOrder {
+    /// Evaluate the operand with the lowest estimated cost first, e.g. a boolean before a list
+    /// operation, whatever the order in which they are written.
+    #[default]
+    Cost,
+    /// Evaluate the operands in the order in which they are written, so that the guards written
+    /// first protect the predicates written after them. The `and` operators whose operands are
+    /// written in different orders are then not shared between the expressions.
+    LeftToRight,
+}
+
+/// The values that an [`crate::EventBuilder`] converts into the type of their attribute instead of
+/// rejecting them with [`crate::EventError::WrongType`]
+///
+/// Every conversion is reported to the [`crate::TreeObserver::on_coercion()`] hooks so that the
+/// producers sending mixed types can be found. No value is converted by default.
+///
+/// # Examples
+///
+/// ```rust
+/// use a_tree::{ATree, ATreeConfig, AttributeDefinition, Coercions};
+///
+/// let config = ATreeConfig::new().with_coercions(Coercions::ALL);
+/// let definitions = [
+///     AttributeDefinition::float("bidfloor"),
+///     AttributeDefinition::integer("exchange_id"),
+/// ];
+/// let mut atree = ATree::with_config(&definitions, config).unwrap();
+/// atree.insert(&1u64, "bidfloor > 0.5 and exchange_id = 42").unwrap();
+///
+/// let mut builder = atree.make_event();
+/// builder.with_integer("bidfloor", 1).unwrap();
+/// builder.with_string("exchange_id", "42").unwrap();
+/// let event = builder.build().unwrap();
+/// assert_eq!(vec![&1u64], atree.search(&event).unwrap().matches());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Coercions {
+    integers_to_floats: bool,
+    strings_to_numbers: bool,
+}
+
+impl Coercions {
+    /// Convert none of the values.
+    pub const NONE: Self = Self {
+        integers_to_floats: false,
+        strings_to_numbers: false,
+    };
+    /// Convert all the values that can be converted.
+    pub const ALL: Self = Self {
+        integers_to_floats: true,
+        strings_to_numbers: true,
+    };
+
+    /// Convert the integers set to a float attribute into floats.
+    pub const fn with_integers_to_floats(mut self) -> Self {
+        self.integers_to_floats = true;
+        self
+    }
+
+    /// Convert the strings set to an integer or a float attribute by parsing them, e.g. `"42"`;
+    /// the strings that are not numbers are still rejected.
+    pub const fn with_strings_to_numbers(mut self) -> Self {
+        self.strings_to_numbers = true;
+        self
+    }
+
+    /// Whether the integers are converted into floats.
+    #[inline]
+    pub const fn integers_to_floats(&self) -> bool {
+        self.integers_to_floats
+    }
+
+    /// Whether the strings are converted into numbers.
+    #[inline]
+    pub const fn strings_to_numbers(&self) -> bool {
+        self.strings_to_numbers
+    }
+}
+
+/// The configuration of an [`crate::ATree`]
+///
+/// # Examples
+///
+/// ```rust
+/// use a_tree::{ATree, ATreeConfig, AttributeDefinition, RoundingPolicy};
+///
+/// let config = ATreeConfig::new()
+///     .with_float_scale(2)
+///     .with_rounding(RoundingPolicy::TowardZero);
+/// let definitions = [AttributeDefinition::float("bidfloor")];
+/// let mut atree = ATree::with_config(&definitions, config).unwrap();
+/// atree.insert(&1u64, "bidfloor = 1.99").unwrap();
+///
+/// let mut builder = atree.make_event();
+/// builder.with_f64("bidfloor", 1.999).unwrap();
+/// let event = builder.build().unwrap();
+/// assert_eq!(vec![&1u64], atree.search(&event).unwrap().matches());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ATreeConfig {
+    float_scale: u32,
+    rounding: RoundingPolicy,
+    unknown_attributes: UnknownAttributePolicy,
+    duplicate_ids: DuplicateIdPolicy,
+    deletes: DeletePolicy,
+    conjunction_order: ConjunctionOrder,
+    coercions: Coercions,
+    parse_cache_capacity: usize,
+}
+
+impl ATreeConfig {
+    /// The amount of decimal digits kept by default when converting a float.
+    pub const DEFAULT_FLOAT_SCALE: u32 = 6;
+    /// The maximum amount of decimal digits that can be kept when converting a float.
+    pub const MAX_FLOAT_SCALE: u32 = 28;
+
+    /// Create the default configuration.
+    pub const fn new() -> Self {
+        Self {
+            float_scale: Self::DEFAULT_FLOAT_SCALE,
+            rounding: RoundingPolicy::HalfEven,
+            unknown_attributes: UnknownAttributePolicy::Error,
+            duplicate_ids: DuplicateIdPolicy::Allow,
+            deletes: DeletePolicy::Immediate,
+            conjunction_order: ConjunctionOrder::Cost,
+            coercions: Coercions::NONE,
+            parse_cache_capacity: 0,
+        }
+    }
+
+    /// Set the amount of decimal digits kept when converting a float with
+    /// [`crate::EventBuilder::with_f64()`].
+    ///
+    /// The scale is capped at [`ATreeConfig::MAX_FLOAT_SCALE`].
+    pub const fn with_float_scale(mut self, scale: u32) -> Self {
+        self.float_scale = if scale > Self::MAX_FLOAT_SCALE {
+            Self::MAX_FLOAT_SCALE
+        } else {
+            scale
+        };
+        self
+    }
+
+    /// Set how the floats are rounded when they are converted with
+    /// [`crate::EventBuilder::with_f64()`].
+    pub const fn with_rounding(mut self, rounding: RoundingPolicy) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Set what the [`crate::EventBuilder`]s do with the attributes that are not defined.
+    pub const fn with_unknown_attributes(mut self, policy: UnknownAttributePolicy) -> Self {
+        self.unknown_attributes = policy;
+        self
+    }
+
+    /// Set what happens when an expression is inserted for a subscription ID that is already
+    /// present.
+    pub const fn with_duplicate_ids(mut self, policy: DuplicateIdPolicy) -> Self {
+        self.duplicate_ids = policy;
+        self
+    }
+
+    /// Set how the subscriptions are deleted; see [`DeletePolicy`].
+    pub const fn with_deletes(mut self, policy: DeletePolicy) -> Self {
+        self.deletes = policy;
+        self
+    }
+
+    /// Set the order in which the operands of the `and` operators are evaluated; see
+    /// [`ConjunctionOrder`].
+    pub const fn with_conjunction_order(mut self, order: ConjunctionOrder) -> Self {
+        self.conjunction_order = order;
+        self
+    }
+
+    /// Set the values that the [`crate::EventBuilder`]s convert into the type of their attribute;
+    /// see [`Coercions`].
+    pub const fn with_coercions(mut self, coercions: Coercions) -> Self {
+        self.coercions = coercions;
+        self
+    }
+
+    /// Keep the parse result of up to `capacity` distinct expressions so that inserting an
+    /// expression string that was already inserted skips the parser.
+    ///
+    /// The cache is disabled by default; once full, the oldest expression is evicted. The hits
+    /// and misses are reported by [`crate::ATree::parse_cache_stats()`].
+    pub const fn with_parse_cache(mut self, capacity: usize) -> Self {
+        self.parse_cache_capacity = capacity;
+        self
+    }
+
+    /// The amount of decimal digits kept when converting a float.
+    #[inline]
+    pub const fn float_scale(&self) -> u32 {
+        self.float_scale
+    }
+
+    /// How the floats are rounded.
+    #[inline]
+    pub const fn rounding(&self) -> RoundingPolicy {
+        self.rounding
+    }
+
+    /// What the [`crate::EventBuilder`]s do with the attributes that are not defined.
+    #[inline]
+    pub const fn unknown_attributes(&self) -> UnknownAttributePolicy {
+        self.unknown_attributes
+    }
+
+    /// What happens when an expression is inserted for a subscription ID that is already present.
+    #[inline]
+    pub const fn duplicate_ids(&self) -> DuplicateIdPolicy {
+        self.duplicate_ids
+    }
+
+    /// How the subscriptions are deleted.
+    #[inline]
+    pub const fn deletes(&self) -> DeletePolicy {
+        self.deletes
+    }
+
+    /// The order in which the operands of the `and` operators are evaluated.
+    #[inline]
+    pub const fn conjunction_order(&self) -> ConjunctionOrder {
+        self.conjunction_order
+    }
+
+    /// The values that the [`crate::EventBuilder`]s convert into the type of their attribute.
+    #[inline]
+    pub const fn coercions(&self) -> Coercions {
+        self.coercions
+    }
+
+    /// The maximum amount of expressions kept in the parse cache; `0` when it is disabled.
+    #[inline]
+    pub const fn parse_cache_capacity(&self) -> usize {
+        self.parse_cache_capacity
+    }
+}
+
+impl Default for ATreeConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cap_the_float_scale() {
+        let config = ATreeConfig::new().with_float_scale(100);
+
+        assert_eq!(ATreeConfig::MAX_FLOAT_SCALE, config.float_scale());
+    }
+
+    #[test]
+    fn use_the_default_values() {
+        let config = ATreeConfig::default();
+
+        assert_eq!(ATreeConfig::DEFAULT_FLOAT_SCALE, config.float_scale());
+        assert_eq!(RoundingPolicy::HalfEven, config.rounding());
+        assert_eq!(UnknownAttributePolicy::Error, config.unknown_attributes());
+        assert_eq!(DuplicateIdPolicy::Allow, config.duplicate_ids());
+        assert_eq!(DeletePolicy::Immediate, config.deletes());
+        assert_eq!(ConjunctionOrder::Cost, config.conjunction_order());
+        assert_eq!(Coercions::NONE, config.coercions());
+        assert_eq!(0, config.parse_cache_capacity());
+    }
+}
@@ -1,4 +1,10 @@
-use crate::{events::EventError, lexer::LexicalError, parser::ATreeParseError};
+use crate::{
+    events::EventError,
+    lexer::{self, LexicalError},
+    parser::ATreeParseError,
+};
+use lalrpop_util::ParseError;
+use std::ops::Range;
 use thiserror::Error;
 
 #[derive(Debug, PartialEq, Error)]
@@ -15,4 +21,117 @@ pub enum ATreeError<'a> {
     ParseError(ATreeParseError<'a>),
     #[error("failed with {0:?}")]
     Event(EventError),
+    #[error("subscription {0} is already present")]
+    DuplicateId(String),
+    #[error("the template expects {expected} parameters, got {actual}")]
+    ParameterCount { expected: usize, actual: usize },
+    #[error("parameter {0} does not fit its placeholder")]
+    InvalidParameter(usize),
+}
+
+impl ATreeError<'_> {
+    /// Return the byte range of the expression that caused the error, if it can be located.
+    ///
+    /// The `expression` must be the one whose parsing returned the error. The errors about an
+    /// attribute point to the first occurrence of that attribute in the expression.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::integer("exchange_id")];
+    /// let atree = ATree::<u64>::new(&definitions).unwrap();
+    /// let expression = "exchange_id = 1 and deals = 2";
+    ///
+    /// let error = atree.validate_expression(expression).unwrap_err();
+    /// assert_eq!(Some(20..25), error.span(expression));
+    /// ```
+    pub fn span(&self, expression: &str) -> Option<Range<usize>> {
+        match self {
+            Self::ParseError(ParseError::InvalidToken { location }) => {
+                let length = expression[*location..]
+                    .chars()
+                    .next()
+                    .map_or(0, char::len_utf8);
+                Some(*location..*location + length)
+            }
+            Self::ParseError(ParseError::UnrecognizedEof { location, .. }) => {
+                Some(*location..*location)
+            }
+            Self::ParseError(
+                ParseError::UnrecognizedToken {
+                    token: (start, _, end),
+                    ..
+                }
+                | ParseError::ExtraToken {
+                    token: (start, _, end),
+                },
+            ) => Some(*start..*end),
+            Self::ParseError(ParseError::User {
+                error: ParserError::Lexical(_),
+            }) => lexer::error_span(expression),
+            Self::ParseError(ParseError::User {
+                error: ParserError::Event(error),
+            })
+            | Self::Event(error) => {
+                attribute_name(error).and_then(|name| lexer::identifier_span(expression, name))
+            }
+            Self::DuplicateId(_) | Self::ParameterCount { .. } | Self::InvalidParameter(_) => None,
+        }
+    }
+}
+
+const fn attribute_name(error: &EventError) -> Option<&String> {
+    match error {
+        EventError::AlreadyPresent(name)
+        | EventError::NonExistingAttribute(name)
+        | EventError::WrongType { name, .. }
+        | EventError::MismatchingTypes { name, .. }
+        | EventError::InvalidFloat { name, .. }
+        | EventError::InvalidScale { name, .. }
+        | EventError::UnsortedList(name)
+        | EventError::UnknownValue(name)
+        | EventError::InvalidEpsilon { name, .. }
+        | EventError::InvalidDuration { name, .. }
+        | EventError::InvalidCount { name, .. }
+        | EventError::InvalidEditDistance { name, .. } => Some(name),
+        EventError::MissingAttributes => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ATree, AttributeDefinition};
+
+    fn span_of(expression: &str) -> Option<std::ops::Range<usize>> {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+        ];
+        let atree = ATree::<u64>::new(&definitions).unwrap();
+        let error = atree.validate_expression(expression).unwrap_err();
+        error.span(expression)
+    }
+
+    #[test]
+    fn locate_an_unexpected_token() {
+        assert_eq!(Some(14..15), span_of("exchange_id = )"));
+    }
+
+    #[test]
+    fn locate_an_unexpected_end() {
+        assert_eq!(Some(11..11), span_of("private and"));
+    }
+
+    #[test]
+    fn locate_a_lexical_error() {
+        assert_eq!(Some(20..24), span_of("exchange_id = 1 and \"abc"));
+        assert_eq!(Some(14..18), span_of("exchange_id = 0xZZ"));
+    }
+
+    #[test]
+    fn locate_the_attribute_of_a_type_error() {
+        assert_eq!(Some(12..23), span_of("private and exchange_id = 'a'"));
+    }
 }
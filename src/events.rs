@@ -1,13 +1,18 @@
 use crate::{
+    atree::ATree,
+    config::{ATreeConfig, UnknownAttributePolicy},
+    observer::EventWarnings,
     predicates::PredicateKind,
-    strings::{StringId, StringTable},
+    strings::{InternedString, StringId, StringTable},
 };
 use itertools::Itertools;
-use rust_decimal::Decimal;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
 use std::{
-    collections::HashMap,
-    fmt::{Display, Formatter},
+    collections::{HashMap, HashSet},
+    fmt::{Debug, Display, Formatter},
+    hash::Hash,
     ops::Index,
+    str::FromStr,
 };
 use thiserror::Error;
 
@@ -31,6 +36,28 @@ pub enum EventError {
         expected: AttributeKind,
         actual: PredicateKind,
     },
+    #[error("{name:?}: {value} cannot be represented as a decimal")]
+    InvalidFloat { name: String, value: f64 },
+    #[error(
+        "{name:?}: the scale {scale} exceeds the maximum of {}",
+        Decimal::MAX_SCALE
+    )]
+    InvalidScale { name: String, scale: u32 },
+    #[error("{0:?}: the list is not sorted in ascending order")]
+    UnsortedList(String),
+    #[error("{0:?}: the value is not one of the values of the attribute")]
+    UnknownValue(String),
+    #[error("{name:?}: the epsilon {value} is not a non-negative decimal")]
+    InvalidEpsilon { name: String, value: f64 },
+    #[error("{name:?}: {duration:?} is not a duration such as \"7d\" or \"1h30m\"")]
+    InvalidDuration { name: String, duration: String },
+    #[error("{name:?}: the count {count} is not a positive amount of values")]
+    InvalidCount { name: String, count: i64 },
+    #[error(
+        "{name:?}: the edit distance {distance} is not between 0 and {}",
+        crate::fuzzy::MAX_EDIT_DISTANCE
+    )]
+    InvalidEditDistance { name: String, distance: i64 },
 }
 
 /// An [`Event`] builder
@@ -40,16 +67,38 @@ pub enum EventError {
 #[derive(Debug)]
 pub struct EventBuilder<'atree> {
     by_ids: Vec<AttributeValue>,
+    texts: Vec<(AttributeId, Box<str>)>,
+    now: Option<i64>,
     attributes: &'atree AttributeTable,
     strings: &'atree StringTable,
+    config: &'atree ATreeConfig,
+    warnings: &'atree dyn EventWarnings,
+    rejections: Rejections,
 }
 
 impl<'atree> EventBuilder<'atree> {
+    #[cfg(test)]
     pub(crate) fn new(attributes: &'atree AttributeTable, strings: &'atree StringTable) -> Self {
+        const DEFAULT_CONFIG: ATreeConfig = ATreeConfig::new();
+        static NO_OBSERVERS: crate::observer::Observers<u64> = crate::observer::Observers::new();
+        Self::with_config(attributes, strings, &DEFAULT_CONFIG, &NO_OBSERVERS)
+    }
+
+    pub(crate) fn with_config(
+        attributes: &'atree AttributeTable,
+        strings: &'atree StringTable,
+        config: &'atree ATreeConfig,
+        warnings: &'atree dyn EventWarnings,
+    ) -> Self {
         Self {
             attributes,
             strings,
+            config,
+            warnings,
             by_ids: vec![AttributeValue::Undefined; attributes.len()],
+            texts: Vec::new(),
+            now: None,
+            rejections: Rejections::default(),
         }
     }
 
@@ -78,7 +127,131 @@ impl<'atree> EventBuilder<'atree> {
     /// let event = builder.build().unwrap();
     /// ```
     pub fn build(self) -> Result<Event, EventError> {
-        Ok(Event(self.by_ids))
+        Ok(Event {
+            values: self.by_ids,
+            texts: self.texts,
+            now: self.now,
+        })
+    }
+
+    /// Build the corresponding [`Event`] without consuming the builder.
+    ///
+    /// This is useful along with [`EventBuilder::reset()`] to reuse the same builder for multiple
+    /// events.
+    pub fn to_event(&self) -> Result<Event, EventError> {
+        Ok(Event {
+            values: self.by_ids.clone(),
+            texts: self.texts.clone(),
+            now: self.now,
+        })
+    }
+
+    /// Set all the attributes back to `undefined` while keeping the allocated capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::integer("exchange_id")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "exchange_id is null").unwrap();
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_integer("exchange_id", 1).unwrap();
+    /// let event = builder.to_event().unwrap();
+    /// assert!(atree.search(&event).unwrap().matches().is_empty());
+    ///
+    /// builder.reset();
+    /// let event = builder.to_event().unwrap();
+    /// assert_eq!(vec![&1u64], atree.search(&event).unwrap().matches());
+    /// ```
+    pub fn reset(&mut self) {
+        self.by_ids.fill(AttributeValue::Undefined);
+        self.texts.clear();
+        self.now = None;
+        self.rejections.clear();
+    }
+
+    /// Check the attributes set so far against the expressions of the [`crate::ATree`] without
+    /// building the [`Event`].
+    ///
+    /// The returned [`ValidationReport`] lists the attributes that are used by the expressions
+    /// but are still `undefined`, as well as the values that were rejected by the builder because
+    /// of an unknown attribute or a wrong type. This is meant as a dry-run during the integration
+    /// of a new event source; the builder can still be used afterwards.
+    ///
+    /// The [`crate::ATree`] must be the one that created this builder.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [
+    ///     AttributeDefinition::boolean("private"),
+    ///     AttributeDefinition::integer("exchange_id"),
+    ///     AttributeDefinition::string("country"),
+    /// ];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, r#"private and country = "CA""#).unwrap();
+    ///
+    /// let mut builder = atree.make_event();
+    /// let _ = builder.with_integer("private", 1);
+    /// let _ = builder.with_string("city", "Montreal");
+    /// let report = builder.validate(&atree);
+    ///
+    /// assert!(!report.is_valid());
+    /// assert_eq!(["country", "private"], report.missing_attributes());
+    /// assert_eq!("private", report.type_mismatches()[0].name());
+    /// assert_eq!(["city"], report.unknown_attributes());
+    /// ```
+    pub fn validate<T>(&self, tree: &ATree<T>) -> ValidationReport
+    where
+        T: Eq + Hash + Clone + Debug,
+    {
+        let referenced = tree.referenced_attributes();
+        let missing_attributes = self
+            .attributes
+            .by_names
+            .iter()
+            .filter(|(_, id)| {
+                referenced.contains(id) && matches!(self.by_ids[id.0], AttributeValue::Undefined)
+            })
+            .map(|(name, _)| name.clone())
+            .sorted()
+            .collect();
+        ValidationReport {
+            missing_attributes,
+            type_mismatches: self.rejections.type_mismatches.clone(),
+            unknown_attributes: self.rejections.unknown_attributes.clone(),
+        }
+    }
+
+    /// Set the time of the event as a Unix timestamp in seconds, i.e. the "now" against which the
+    /// `within_last` predicates are evaluated.
+    ///
+    /// The predicates are `undefined` for the events without a time. The time belongs to the
+    /// event rather than to a search, so the events searched at different times are built with
+    /// their own time.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::integer("last_purchase")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "within_last(last_purchase, '7d')").unwrap();
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_integer("last_purchase", 1_767_225_600).unwrap();
+    /// builder.with_now(1_767_225_600 + 3 * 24 * 60 * 60);
+    /// let event = builder.build().unwrap();
+    /// assert_eq!(vec![&1u64], atree.search(&event).unwrap().matches());
+    /// ```
+    pub fn with_now(&mut self, now: i64) {
+        self.now = Some(now);
     }
 
     /// Set the specified boolean attribute.
@@ -93,7 +266,15 @@ impl<'atree> EventBuilder<'atree> {
     /// Set the specified integer attribute.
     ///
     /// The specified attribute must exist within the [`crate::ATree`] and its type must be integer.
+    ///
+    /// When the tree converts the integers into floats (see [`crate::Coercions`]), the attribute
+    /// can also be a float.
     pub fn with_integer(&mut self, name: &str, value: i64) -> Result<(), EventError> {
+        if self.config.coercions().integers_to_floats()
+            && self.coerce(name, AttributeKind::Integer, AttributeKind::Float)
+        {
+            return self.with_decimal(name, Decimal::from(value));
+        }
         self.add_value(name, AttributeKind::Integer, || {
             AttributeValue::Integer(value)
         })
@@ -102,20 +283,114 @@ impl<'atree> EventBuilder<'atree> {
     /// Set the specified float attribute.
     ///
     /// The specified attribute must exist within the [`crate::ATree`] and its type must be float.
+    /// The scale cannot exceed 28.
     pub fn with_float(&mut self, name: &str, number: i64, scale: u32) -> Result<(), EventError> {
-        self.add_value(name, AttributeKind::Float, || {
-            AttributeValue::Float(Decimal::new(number, scale))
-        })
+        let decimal = Decimal::try_new(number, scale).map_err(|_| EventError::InvalidScale {
+            name: name.to_owned(),
+            scale,
+        })?;
+        self.with_decimal(name, decimal)
+    }
+
+    /// Set the specified float attribute from a `f64`.
+    ///
+    /// The value is rounded to the float scale of the [`crate::ATreeConfig`] of the
+    /// [`crate::ATree`] according to its [`crate::RoundingPolicy`]. `NaN` and the infinities
+    /// cannot be represented and are rejected.
+    ///
+    /// The specified attribute must exist within the [`crate::ATree`] and its type must be float.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::float("bidfloor")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "bidfloor = 0.3").unwrap();
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_f64("bidfloor", 0.1 + 0.2).unwrap();
+    /// let event = builder.build().unwrap();
+    /// assert_eq!(vec![&1u64], atree.search(&event).unwrap().matches());
+    /// ```
+    pub fn with_f64(&mut self, name: &str, value: f64) -> Result<(), EventError> {
+        let decimal = Decimal::from_f64_retain(value).ok_or_else(|| EventError::InvalidFloat {
+            name: name.to_owned(),
+            value,
+        })?;
+        let decimal = decimal
+            .round_dp_with_strategy(self.config.float_scale(), self.config.rounding().strategy());
+        self.with_decimal(name, decimal)
+    }
+
+    #[inline]
+    pub(crate) fn with_decimal(&mut self, name: &str, value: Decimal) -> Result<(), EventError> {
+        self.add_value(name, AttributeKind::Float, || AttributeValue::Float(value))
     }
 
     /// Set the specified string attribute.
     ///
     /// The specified attribute must exist within the [`crate::ATree`] and its type must be string.
+    /// When the attribute was defined with [`AttributeDefinition::string_enum()`], the value must
+    /// be one of its values.
+    ///
+    /// When the tree converts the strings into numbers (see [`crate::Coercions`]), the attribute
+    /// can also be an integer or a float as long as the string is a number.
     pub fn with_string(&mut self, name: &str, value: &str) -> Result<(), EventError> {
+        if self.config.coercions().strings_to_numbers() {
+            if let Some(result) = self.with_number(name, value) {
+                return result;
+            }
+        }
+        let string_index = self.strings.get(value);
+        self.check_dictionary(name, string_index)?;
         self.add_value(name, AttributeKind::String, || {
-            let string_index = self.strings.get(value);
             AttributeValue::String(string_index)
-        })
+        })?;
+        if self.attributes.keeps_texts() {
+            self.keep_text(name, value);
+        }
+        Ok(())
+    }
+
+    /// Set the specified string attribute to a string interned with [`crate::ATree::intern()`].
+    ///
+    /// This avoids hashing the string for every event. The interned string must come from the
+    /// same [`crate::ATree`] as this builder.
+    ///
+    /// The specified attribute must exist within the [`crate::ATree`] and its type must be string.
+    /// The text of the string is not known so the patterns such as `starts with` are `undefined`
+    /// for the attribute.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::string("country")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, r#"country = "CA""#).unwrap();
+    /// let canada = atree.intern("CA");
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_interned_string("country", canada).unwrap();
+    /// let event = builder.build().unwrap();
+    /// assert_eq!(vec![&1u64], atree.search(&event).unwrap().matches());
+    /// ```
+    pub fn with_interned_string(
+        &mut self,
+        name: &str,
+        value: InternedString,
+    ) -> Result<(), EventError> {
+        self.check_dictionary(name, value.0)?;
+        self.add_value(name, AttributeKind::String, || {
+            AttributeValue::String(value.0)
+        })?;
+        if let Some(index) = self.attributes.by_name(name) {
+            self.texts.retain(|(id, _)| *id != index);
+        }
+        Ok(())
     }
 
     /// Set the specified list of integers attribute.
@@ -124,7 +399,54 @@ impl<'atree> EventBuilder<'atree> {
     /// of integers.
     pub fn with_integer_list(&mut self, name: &str, value: &[i64]) -> Result<(), EventError> {
         self.add_value(name, AttributeKind::IntegerList, || {
-            let values = value.iter().sorted().unique().cloned().collect_vec();
+            let mut values = value.to_vec();
+            values.sort_unstable();
+            values.dedup();
+            AttributeValue::IntegerList(values)
+        })
+    }
+
+    /// Set the specified list of integers attribute from values that are already sorted in
+    /// ascending order.
+    ///
+    /// Unlike [`EventBuilder::with_integer_list()`], the values are not sorted again which avoids
+    /// the cost of sorting large lists (e.g. thousands of segment IDs). The order is still
+    /// verified in a single pass and the consecutive duplicates are removed.
+    ///
+    /// The specified attribute must exist within the [`crate::ATree`] and its type must be a list
+    /// of integers.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition, EventError};
+    ///
+    /// let definitions = [AttributeDefinition::integer_list("segment_ids")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "segment_ids one of [2, 5]").unwrap();
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_integer_list_sorted("segment_ids", &[1, 2, 2, 3]).unwrap();
+    /// let event = builder.build().unwrap();
+    /// assert_eq!(vec![&1u64], atree.search(&event).unwrap().matches());
+    ///
+    /// let mut builder = atree.make_event();
+    /// assert_eq!(
+    ///     Err(EventError::UnsortedList("segment_ids".to_string())),
+    ///     builder.with_integer_list_sorted("segment_ids", &[3, 1])
+    /// );
+    /// ```
+    pub fn with_integer_list_sorted(
+        &mut self,
+        name: &str,
+        values: &[i64],
+    ) -> Result<(), EventError> {
+        if values.windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err(EventError::UnsortedList(name.to_owned()));
+        }
+        self.add_value(name, AttributeKind::IntegerList, || {
+            let mut values = values.to_vec();
+            values.dedup();
             AttributeValue::IntegerList(values)
         })
     }
@@ -133,11 +455,11 @@ impl<'atree> EventBuilder<'atree> {
     ///
     /// The specified attribute must exist within the [`crate::ATree`].
     pub fn with_undefined(&mut self, name: &str) -> Result<(), EventError> {
-        let index = self
-            .attributes
-            .by_name(name)
-            .ok_or_else(|| EventError::NonExistingAttribute(name.to_string()))?;
+        let Some(index) = self.attribute_id(name)? else {
+            return Ok(());
+        };
         self.by_ids[index.0] = AttributeValue::Undefined;
+        self.texts.retain(|(id, _)| *id != index);
         Ok(())
     }
 
@@ -157,17 +479,160 @@ impl<'atree> EventBuilder<'atree> {
         })
     }
 
+    /// Set all the attributes of the map.
+    ///
+    /// Each attribute must exist within the [`crate::ATree`] and its type must match the variant
+    /// of its [`AttrValue`]. The values are applied in no particular order and the first error
+    /// encountered is returned, in which case only some of the values might have been set.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttrValue, AttributeDefinition};
+    /// use std::collections::HashMap;
+    ///
+    /// let definitions = [
+    ///     AttributeDefinition::boolean("private"),
+    ///     AttributeDefinition::integer("exchange_id"),
+    ///     AttributeDefinition::string_list("deals"),
+    /// ];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, r#"private and deals one of ["deal-1"]"#).unwrap();
+    ///
+    /// let values = HashMap::from([
+    ///     ("private", AttrValue::Boolean(true)),
+    ///     ("exchange_id", AttrValue::Integer(1)),
+    ///     ("deals", AttrValue::StringList(vec!["deal-1", "deal-2"])),
+    /// ]);
+    /// let mut builder = atree.make_event();
+    /// builder.with_map(values).unwrap();
+    /// let event = builder.build().unwrap();
+    ///
+    /// assert_eq!(vec![&1u64], atree.search(&event).unwrap().matches());
+    /// ```
+    pub fn with_map(&mut self, values: HashMap<&str, AttrValue<'_>>) -> Result<(), EventError> {
+        for (name, value) in values {
+            match value {
+                AttrValue::Boolean(value) => self.with_boolean(name, value),
+                AttrValue::Integer(value) => self.with_integer(name, value),
+                AttrValue::Float { number, scale } => self.with_float(name, number, scale),
+                AttrValue::String(value) => self.with_string(name, value),
+                AttrValue::IntegerList(values) => self.with_integer_list(name, &values),
+                AttrValue::StringList(values) => self.with_string_list(name, &values),
+                AttrValue::Undefined => self.with_undefined(name),
+            }?;
+        }
+        Ok(())
+    }
+
+    /// The values converted into the type of their attribute.
+    #[cfg(feature = "json")]
+    #[inline]
+    pub(crate) fn coercions(&self) -> crate::config::Coercions {
+        self.config.coercions()
+    }
+
+    /// Return the type of the specified attribute.
+    #[cfg(any(feature = "arrow", feature = "json"))]
+    pub(crate) fn kind_of(&self, name: &str) -> Result<AttributeKind, EventError> {
+        self.attributes
+            .by_name(name)
+            .map(|index| self.attributes.by_id(index))
+            .ok_or_else(|| EventError::NonExistingAttribute(name.to_string()))
+    }
+
+    /// Handle an attribute that is not defined according to the [`UnknownAttributePolicy`] of
+    /// the tree.
+    pub(crate) fn unknown_attribute(&mut self, name: &str) -> Result<(), EventError> {
+        self.rejections.unknown_attributes.push(name.to_owned());
+        match self.config.unknown_attributes() {
+            UnknownAttributePolicy::Error => {
+                Err(EventError::NonExistingAttribute(name.to_string()))
+            }
+            UnknownAttributePolicy::Ignore => Ok(()),
+            UnknownAttributePolicy::Warn => {
+                self.warnings.on_unknown_attribute(name);
+                Ok(())
+            }
+        }
+    }
+
+    #[inline]
+    fn attribute_id(&mut self, name: &str) -> Result<Option<AttributeId>, EventError> {
+        match self.attributes.by_name(name) {
+            Some(index) => Ok(Some(index)),
+            None => self.unknown_attribute(name).map(|_| None),
+        }
+    }
+
+    /// Set the integer or float attribute to the number written in the string, if it is one.
+    fn with_number(&mut self, name: &str, value: &str) -> Option<Result<(), EventError>> {
+        let kind = self.attributes.by_id(self.attributes.by_name(name)?);
+        let value = value.trim();
+        let result = match kind {
+            AttributeKind::Integer => self.with_integer(name, value.parse().ok()?),
+            AttributeKind::Float => self.with_decimal(name, Decimal::from_str(value).ok()?),
+            _ => return None,
+        };
+        self.warnings
+            .on_coercion(name, &AttributeKind::String, &kind);
+        Some(result)
+    }
+
+    /// Whether the attribute has the type into which the value is converted, in which case the
+    /// coercion is reported.
+    fn coerce(&self, name: &str, from: AttributeKind, to: AttributeKind) -> bool {
+        let coerced = self
+            .attributes
+            .by_name(name)
+            .is_some_and(|index| self.attributes.by_id(index) == to);
+        if coerced {
+            self.warnings.on_coercion(name, &from, &to);
+        }
+        coerced
+    }
+
+    /// Keep the text of the string when the attribute is used by a pattern.
+    fn keep_text(&mut self, name: &str, value: &str) {
+        let Some(index) = self.attributes.by_name(name) else {
+            return;
+        };
+        if self.attributes.keeps_text(index) {
+            self.texts.retain(|(id, _)| *id != index);
+            self.texts.push((index, value.into()));
+        }
+    }
+
+    /// Check that the string is one of the values of the attribute when it has a dictionary.
+    #[inline]
+    fn check_dictionary(&self, name: &str, value: StringId) -> Result<(), EventError> {
+        let dictionary = self
+            .attributes
+            .by_name(name)
+            .and_then(|id| self.attributes.dictionary(id));
+        match dictionary {
+            Some(dictionary) if !dictionary.contains_id(value) => {
+                Err(EventError::UnknownValue(name.to_owned()))
+            }
+            _ => Ok(()),
+        }
+    }
+
     #[inline]
     fn add_value<F>(&mut self, name: &str, actual: AttributeKind, f: F) -> Result<(), EventError>
     where
         F: FnOnce() -> AttributeValue,
     {
-        let index = self
-            .attributes
-            .by_name(name)
-            .ok_or_else(|| EventError::NonExistingAttribute(name.to_string()))?;
+        let Some(index) = self.attribute_id(name)? else {
+            return Ok(());
+        };
         let expected = self.attributes.by_id(index);
         if expected != actual {
+            self.rejections.type_mismatches.push(TypeMismatch {
+                name: name.to_owned(),
+                expected: expected.clone(),
+                actual: actual.clone(),
+            });
             return Err(EventError::WrongType {
                 name: name.to_owned(),
                 expected,
@@ -179,17 +644,139 @@ impl<'atree> EventBuilder<'atree> {
     }
 }
 
+/// The values rejected by an [`EventBuilder`], kept for [`EventBuilder::validate()`]
+#[derive(Clone, Debug, Default)]
+struct Rejections {
+    type_mismatches: Vec<TypeMismatch>,
+    unknown_attributes: Vec<String>,
+}
+
+impl Rejections {
+    #[inline]
+    fn clear(&mut self) {
+        self.type_mismatches.clear();
+        self.unknown_attributes.clear();
+    }
+}
+
+/// A value that was rejected by an [`EventBuilder`] because its type did not match the type of
+/// the attribute
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeMismatch {
+    name: String,
+    expected: AttributeKind,
+    actual: AttributeKind,
+}
+
+impl TypeMismatch {
+    /// The name of the attribute.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The type of the attribute.
+    #[inline]
+    pub const fn expected(&self) -> &AttributeKind {
+        &self.expected
+    }
+
+    /// The type of the rejected value.
+    #[inline]
+    pub const fn actual(&self) -> &AttributeKind {
+        &self.actual
+    }
+}
+
+/// The result of [`EventBuilder::validate()`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationReport {
+    missing_attributes: Vec<String>,
+    type_mismatches: Vec<TypeMismatch>,
+    unknown_attributes: Vec<String>,
+}
+
+impl ValidationReport {
+    /// Whether the event sets all the attributes used by the expressions and no value was
+    /// rejected.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.missing_attributes.is_empty()
+            && self.type_mismatches.is_empty()
+            && self.unknown_attributes.is_empty()
+    }
+
+    /// The attributes used by at least one expression that are still `undefined`, sorted by name.
+    #[inline]
+    pub fn missing_attributes(&self) -> &[String] {
+        &self.missing_attributes
+    }
+
+    /// The values that were rejected because of their type, in the order they were set.
+    #[inline]
+    pub fn type_mismatches(&self) -> &[TypeMismatch] {
+        &self.type_mismatches
+    }
+
+    /// The attributes that were set but do not exist within the [`crate::ATree`], in the order
+    /// they were set.
+    #[inline]
+    pub fn unknown_attributes(&self) -> &[String] {
+        &self.unknown_attributes
+    }
+}
+
+/// A value that can be assigned to an attribute via [`EventBuilder::with_map()`]
+///
+/// Each variant corresponds to one of the `with_*` functions of the [`EventBuilder`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttrValue<'a> {
+    Boolean(bool),
+    Integer(i64),
+    /// A float represented as `number * 10^-scale`, like [`EventBuilder::with_float()`].
+    Float {
+        number: i64,
+        scale: u32,
+    },
+    String(&'a str),
+    IntegerList(Vec<i64>),
+    StringList(Vec<&'a str>),
+    Undefined,
+}
+
 /// An event that can be used by the [`crate::atree::ATree`] structure to match arbitrary boolean
 /// expressions
 #[derive(Clone, Debug)]
-pub struct Event(Vec<AttributeValue>);
+pub struct Event {
+    values: Vec<AttributeValue>,
+    /// The text of the string attributes used by the patterns, which cannot be interned
+    texts: Vec<(AttributeId, Box<str>)>,
+    now: Option<i64>,
+}
+
+impl Event {
+    /// The time of the event, as a Unix timestamp in seconds.
+    #[inline]
+    pub(crate) const fn now(&self) -> Option<i64> {
+        self.now
+    }
+
+    /// The text of the string attribute when it is used by a pattern.
+    #[inline]
+    pub(crate) fn text(&self, id: AttributeId) -> Option<&str> {
+        self.texts
+            .iter()
+            .find(|(attribute, _)| *attribute == id)
+            .map(|(_, text)| &**text)
+    }
+}
 
 impl Index<AttributeId> for Event {
     type Output = AttributeValue;
 
     #[inline]
     fn index(&self, index: AttributeId) -> &Self::Output {
-        &self.0[index.0]
+        &self.values[index.0]
     }
 }
 
@@ -208,11 +795,46 @@ pub enum AttributeValue {
 pub struct AttributeTable {
     by_names: HashMap<String, AttributeId>,
     by_ids: Vec<AttributeKind>,
+    dictionaries: Vec<Option<Dictionary>>,
+    epsilons: Vec<Option<Decimal>>,
+    high_cardinalities: Vec<bool>,
+    /// Whether the events keep the text of the string attribute for the patterns
+    texts: Vec<bool>,
+    keeps_texts: bool,
+}
+
+/// The closed set of values of an attribute defined with [`AttributeDefinition::string_enum()`]
+#[derive(Clone, Debug)]
+pub struct Dictionary {
+    values: Vec<String>,
+    ids: HashSet<StringId>,
+}
+
+impl Dictionary {
+    /// Whether the string, interned by the [`StringTable`] of the tree, is one of the values.
+    #[inline]
+    pub fn contains_id(&self, id: StringId) -> bool {
+        self.ids.contains(&id)
+    }
+
+    /// Whether the string is one of the values.
+    #[inline]
+    pub fn contains(&self, value: &str) -> bool {
+        self.values.iter().any(|known| known == value)
+    }
 }
 
 #[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd, Debug, Hash)]
 pub struct AttributeId(usize);
 
+impl AttributeId {
+    /// The position of the attribute in the definitions of the tree.
+    #[inline]
+    pub(crate) const fn index(self) -> usize {
+        self.0
+    }
+}
+
 impl Display for AttributeId {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
         write!(formatter, "attribute({})", self.0)
@@ -224,6 +846,9 @@ impl AttributeTable {
         let size = definitions.len();
         let mut by_names = HashMap::with_capacity(size);
         let mut by_ids = Vec::with_capacity(size);
+        let mut dictionaries = Vec::with_capacity(size);
+        let mut epsilons = Vec::with_capacity(size);
+        let mut high_cardinalities = Vec::with_capacity(size);
         for (i, definition) in definitions.iter().enumerate() {
             let name = definition.name.to_owned();
             if by_names.contains_key(&name) {
@@ -232,9 +857,88 @@ impl AttributeTable {
 
             by_names.insert(name, AttributeId(i));
             by_ids.push(definition.kind.clone());
+            dictionaries.push(definition.values.clone().map(|values| Dictionary {
+                values,
+                ids: HashSet::new(),
+            }));
+            let epsilon = definition
+                .epsilon
+                .map(|value| {
+                    Decimal::try_from(value)
+                        .ok()
+                        .filter(|epsilon| !epsilon.is_sign_negative())
+                        .ok_or_else(|| EventError::InvalidEpsilon {
+                            name: definition.name.clone(),
+                            value,
+                        })
+                })
+                .transpose()?;
+            epsilons.push(epsilon);
+            high_cardinalities.push(definition.high_cardinality);
         }
 
-        Ok(Self { by_names, by_ids })
+        Ok(Self {
+            by_names,
+            by_ids,
+            dictionaries,
+            epsilons,
+            high_cardinalities,
+            texts: vec![false; size],
+            keeps_texts: false,
+        })
+    }
+
+    /// Intern the values of the dictionaries so that the strings of the events and of the
+    /// expressions are checked against them by ID.
+    pub fn intern_dictionaries(&mut self, strings: &mut StringTable) {
+        for dictionary in self.dictionaries.iter_mut().flatten() {
+            dictionary.ids = dictionary
+                .values
+                .iter()
+                .map(|value| strings.get_or_update(value))
+                .collect();
+        }
+    }
+
+    /// Return the dictionary of the attribute, if it was defined with one.
+    #[inline]
+    pub fn dictionary(&self, id: AttributeId) -> Option<&Dictionary> {
+        self.dictionaries[id.0].as_ref()
+    }
+
+    /// The tolerance of the float equalities of the attribute; see
+    /// [`AttributeDefinition::float_with_epsilon()`].
+    #[inline]
+    pub fn epsilon(&self, id: AttributeId) -> Option<Decimal> {
+        self.epsilons[id.0]
+    }
+
+    /// The attributes declared with many distinct values; see
+    /// [`AttributeDefinition::high_cardinality()`].
+    pub(crate) fn high_cardinality(&self) -> impl Iterator<Item = AttributeId> + '_ {
+        self.high_cardinalities
+            .iter()
+            .enumerate()
+            .filter(|(_, high)| **high)
+            .map(|(id, _)| AttributeId(id))
+    }
+
+    /// Make the events keep the text of the string attribute since a pattern uses it.
+    #[inline]
+    pub(crate) fn keep_text(&mut self, id: AttributeId) {
+        self.texts[id.0] = true;
+        self.keeps_texts = true;
+    }
+
+    #[inline]
+    pub(crate) fn keeps_text(&self, id: AttributeId) -> bool {
+        self.texts[id.0]
+    }
+
+    /// Whether the events keep the text of any of the string attributes.
+    #[inline]
+    pub(crate) const fn keeps_texts(&self) -> bool {
+        self.keeps_texts
     }
 
     #[inline]
@@ -242,6 +946,21 @@ impl AttributeTable {
         self.by_names.get(name).cloned()
     }
 
+    /// Iterate over the names of the attributes along with their IDs, in no particular order.
+    #[inline]
+    pub(crate) fn names(&self) -> impl Iterator<Item = (&str, AttributeId)> {
+        self.by_names.iter().map(|(name, id)| (name.as_str(), *id))
+    }
+
+    /// The names of the attributes indexed by their ID.
+    pub(crate) fn names_by_id(&self) -> Vec<&str> {
+        let mut names = vec![""; self.len()];
+        for (name, id) in &self.by_names {
+            names[id.0] = name;
+        }
+        names
+    }
+
     #[inline]
     pub fn by_id(&self, id: AttributeId) -> AttributeKind {
         self.by_ids[id.0].clone()
@@ -251,6 +970,23 @@ impl AttributeTable {
     pub fn len(&self) -> usize {
         self.by_ids.len()
     }
+
+    /// Return the definitions of the attributes in the order they were defined.
+    pub fn definitions(&self) -> Vec<AttributeDefinition> {
+        self.by_names
+            .iter()
+            .sorted_by_key(|(_, id)| **id)
+            .map(|(name, id)| AttributeDefinition {
+                name: name.clone(),
+                kind: self.by_id(*id),
+                values: self
+                    .dictionary(*id)
+                    .map(|dictionary| dictionary.values.clone()),
+                epsilon: self.epsilon(*id).and_then(|epsilon| epsilon.to_f64()),
+                high_cardinality: self.high_cardinalities[id.0],
+            })
+            .collect()
+    }
 }
 
 /// The definition of an attribute that is usable by the [`crate::atree::ATree`]
@@ -258,8 +994,12 @@ impl AttributeTable {
 pub struct AttributeDefinition {
     name: String,
     kind: AttributeKind,
+    values: Option<Vec<String>>,
+    epsilon: Option<f64>,
+    high_cardinality: bool,
 }
 
+/// The type of an attribute; see [`AttributeDefinition`].
 #[derive(Clone, PartialEq, Debug)]
 pub enum AttributeKind {
     Boolean,
@@ -277,6 +1017,9 @@ impl AttributeDefinition {
         Self {
             name: name.to_owned(),
             kind,
+            values: None,
+            epsilon: None,
+            high_cardinality: false,
         }
     }
 
@@ -286,6 +1029,9 @@ impl AttributeDefinition {
         Self {
             name: name.to_owned(),
             kind,
+            values: None,
+            epsilon: None,
+            high_cardinality: false,
         }
     }
 
@@ -295,6 +1041,44 @@ impl AttributeDefinition {
         Self {
             name: name.to_owned(),
             kind,
+            values: None,
+            epsilon: None,
+            high_cardinality: false,
+        }
+    }
+
+    /// Create a float attribute definition whose equalities tolerate a difference of up to
+    /// `epsilon`, for the values that carry more noise than the rounding to the float scale of
+    /// the [`crate::ATreeConfig`] absorbs.
+    ///
+    /// `price = 2.99` then matches the events whose price is between `2.99 - epsilon` and
+    /// `2.99 + epsilon`, both included, and `price <> 2.99` matches the others. The comparisons
+    /// are not affected. An epsilon that is negative or that cannot be represented as a decimal
+    /// is rejected with [`EventError::InvalidEpsilon`] when the [`crate::ATree`] is created.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::float_with_epsilon("price", 0.001)];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "price = 2.99").unwrap();
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_f64("price", 2.9905).unwrap();
+    /// let event = builder.build().unwrap();
+    /// assert_eq!(vec![&1u64], atree.search(&event).unwrap().matches());
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_f64("price", 2.992).unwrap();
+    /// let event = builder.build().unwrap();
+    /// assert!(atree.search(&event).unwrap().matches().is_empty());
+    /// ```
+    pub fn float_with_epsilon(name: &str, epsilon: f64) -> Self {
+        Self {
+            epsilon: Some(epsilon),
+            ..Self::float(name)
         }
     }
 
@@ -304,6 +1088,45 @@ impl AttributeDefinition {
         Self {
             name: name.to_owned(),
             kind,
+            values: None,
+            epsilon: None,
+            high_cardinality: false,
+        }
+    }
+
+    /// Create a string attribute definition whose values are restricted to a closed dictionary,
+    /// such as the country codes.
+    ///
+    /// The values are interned once when the [`crate::ATree`] is created. The string literals
+    /// of the expressions and the strings of the events are then rejected with
+    /// [`EventError::UnknownValue`] when they are not in the dictionary.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition, EventError};
+    ///
+    /// let definitions = [AttributeDefinition::string_enum("country", &["CA", "FR", "US"])];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, r#"country in ["CA", "US"]"#).unwrap();
+    /// assert!(atree.insert(&2u64, r#"country = "XX""#).is_err());
+    ///
+    /// let mut builder = atree.make_event();
+    /// assert_eq!(
+    ///     Err(EventError::UnknownValue("country".to_owned())),
+    ///     builder.with_string("country", "XX")
+    /// );
+    /// builder.with_string("country", "CA").unwrap();
+    /// let event = builder.build().unwrap();
+    /// assert_eq!(vec![&1u64], atree.search(&event).unwrap().matches());
+    /// ```
+    pub fn string_enum(name: &str, values: &[&str]) -> Self {
+        Self {
+            name: name.to_owned(),
+            kind: AttributeKind::String,
+            values: Some(values.iter().map(|value| (*value).to_owned()).collect()),
+            epsilon: None,
+            high_cardinality: false,
         }
     }
 
@@ -313,6 +1136,9 @@ impl AttributeDefinition {
         Self {
             name: name.to_owned(),
             kind,
+            values: None,
+            epsilon: None,
+            high_cardinality: false,
         }
     }
 
@@ -322,13 +1148,84 @@ impl AttributeDefinition {
         Self {
             name: name.to_owned(),
             kind,
+            values: None,
+            epsilon: None,
+            high_cardinality: false,
+        }
+    }
+
+    /// Declare that the attribute takes many distinct values across the expressions, such as
+    /// the IDs of the users.
+    ///
+    /// The `=`, `<>`, `in` and `not in` predicates of an integer or a string attribute with a high
+    /// cardinality are kept in a hash map from their values, so that each search looks up the
+    /// value of the event once instead of comparing it with every predicate. The hint is ignored
+    /// for the other types of attributes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::integer("user_id").high_cardinality()];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// for user_id in 0..1_000u64 {
+    ///     atree.insert(&user_id, &format!("user_id = {user_id}")).unwrap();
+    /// }
+    ///
+    /// let mut builder = atree.make_event();
+    /// builder.with_integer("user_id", 42).unwrap();
+    /// let event = builder.build().unwrap();
+    /// assert_eq!(vec![&42u64], atree.search(&event).unwrap().matches());
+    /// ```
+    pub fn high_cardinality(self) -> Self {
+        Self {
+            high_cardinality: true,
+            ..self
         }
     }
+
+    /// The name of the attribute.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The type of the attribute.
+    #[inline]
+    pub const fn kind(&self) -> &AttributeKind {
+        &self.kind
+    }
+
+    /// The values of the dictionary of the attribute; see [`AttributeDefinition::string_enum()`].
+    #[inline]
+    pub fn values(&self) -> Option<&[String]> {
+        self.values.as_deref()
+    }
+
+    /// The tolerance of the float equalities of the attribute; see
+    /// [`AttributeDefinition::float_with_epsilon()`].
+    #[inline]
+    pub const fn epsilon(&self) -> Option<f64> {
+        self.epsilon
+    }
+
+    /// Whether the attribute was declared with many distinct values; see
+    /// [`AttributeDefinition::high_cardinality()`].
+    #[inline]
+    pub const fn is_high_cardinality(&self) -> bool {
+        self.high_cardinality
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        config::Coercions,
+        observer::{Observers, TreeObserver},
+    };
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn can_create_an_attribute_table_with_no_attributes() {
@@ -364,6 +1261,22 @@ mod tests {
         assert!(AttributeTable::new(&definitions).is_err());
     }
 
+    #[test]
+    fn reject_the_strings_that_are_not_in_the_dictionary() {
+        let mut attributes =
+            AttributeTable::new(&[AttributeDefinition::string_enum("country", &["CA", "US"])])
+                .unwrap();
+        let mut strings = StringTable::new();
+        attributes.intern_dictionaries(&mut strings);
+        let mut event_builder = EventBuilder::new(&attributes, &strings);
+
+        assert!(event_builder.with_string("country", "CA").is_ok());
+        assert_eq!(
+            Err(EventError::UnknownValue("country".to_owned())),
+            event_builder.with_string("country", "FR")
+        );
+    }
+
     #[test]
     fn can_add_a_boolean_attribute_value() {
         let attributes = AttributeTable::new(&[AttributeDefinition::boolean("private")]).unwrap();
@@ -398,6 +1311,189 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn can_add_a_f64_attribute_value() {
+        let attributes = AttributeTable::new(&[AttributeDefinition::float("bidfloor")]).unwrap();
+        let strings = StringTable::new();
+        let mut event_builder = EventBuilder::new(&attributes, &strings);
+
+        event_builder.with_f64("bidfloor", 0.1 + 0.2).unwrap();
+
+        let event = event_builder.build().unwrap();
+        assert!(matches!(
+            event.values.as_slice(),
+            [AttributeValue::Float(value)] if *value == Decimal::new(3, 1)
+        ));
+    }
+
+    #[test]
+    fn skip_the_unknown_attributes_when_they_are_ignored() {
+        let attributes = AttributeTable::new(&[AttributeDefinition::string("country")]).unwrap();
+        let strings = StringTable::new();
+        let config = ATreeConfig::new().with_unknown_attributes(UnknownAttributePolicy::Ignore);
+        let observers = Observers::<u64>::new();
+        let mut event_builder =
+            EventBuilder::with_config(&attributes, &strings, &config, &observers);
+
+        assert!(event_builder.with_string("city", "Montreal").is_ok());
+        assert!(event_builder.with_undefined("city").is_ok());
+        assert_eq!(
+            vec!["city", "city"],
+            event_builder.rejections.unknown_attributes
+        );
+    }
+
+    #[test]
+    fn notify_the_observers_of_the_unknown_attributes_when_warning() {
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<String>>);
+
+        impl TreeObserver<u64> for Recorder {
+            fn on_unknown_attribute(&self, name: &str) {
+                self.0.lock().unwrap().push(name.to_owned());
+            }
+        }
+
+        let attributes = AttributeTable::new(&[AttributeDefinition::string("country")]).unwrap();
+        let strings = StringTable::new();
+        let config = ATreeConfig::new().with_unknown_attributes(UnknownAttributePolicy::Warn);
+        let recorder = Arc::new(Recorder::default());
+        let mut observers = Observers::<u64>::new();
+        observers.register(recorder.clone());
+        let mut event_builder =
+            EventBuilder::with_config(&attributes, &strings, &config, &observers);
+
+        assert!(event_builder.with_integer("exchange_id", 1).is_ok());
+        assert!(event_builder.with_string("country", "CA").is_ok());
+        assert_eq!(vec!["exchange_id"], *recorder.0.lock().unwrap());
+    }
+
+    #[test]
+    fn coerce_the_values_into_the_type_of_their_attribute_when_configured() {
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<(String, AttributeKind, AttributeKind)>>);
+
+        impl TreeObserver<u64> for Recorder {
+            fn on_coercion(&self, name: &str, from: &AttributeKind, to: &AttributeKind) {
+                let coercion = (name.to_owned(), from.clone(), to.clone());
+                self.0.lock().unwrap().push(coercion);
+            }
+        }
+
+        let definitions = [
+            AttributeDefinition::float("bidfloor"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string("country"),
+        ];
+        let attributes = AttributeTable::new(&definitions).unwrap();
+        let strings = StringTable::new();
+        let config = ATreeConfig::new().with_coercions(Coercions::ALL);
+        let recorder = Arc::new(Recorder::default());
+        let mut observers = Observers::<u64>::new();
+        observers.register(recorder.clone());
+        let mut event_builder =
+            EventBuilder::with_config(&attributes, &strings, &config, &observers);
+
+        assert!(event_builder.with_integer("bidfloor", 2).is_ok());
+        assert!(event_builder.with_string("exchange_id", "42").is_ok());
+        assert!(event_builder.with_string("country", "42").is_ok());
+        assert!(event_builder.with_string("bidfloor", "cheap").is_err());
+        assert!(event_builder.with_integer("country", 1).is_err());
+        let event = event_builder.build().unwrap();
+        assert!(matches!(
+            event.values.as_slice(),
+            [
+                AttributeValue::Float(value),
+                AttributeValue::Integer(42),
+                AttributeValue::String(_)
+            ] if *value == Decimal::new(2, 0)
+        ));
+        assert_eq!(
+            vec![
+                (
+                    "bidfloor".to_owned(),
+                    AttributeKind::Integer,
+                    AttributeKind::Float
+                ),
+                (
+                    "exchange_id".to_owned(),
+                    AttributeKind::String,
+                    AttributeKind::Integer
+                ),
+            ],
+            *recorder.0.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn reject_the_values_of_another_type_by_default() {
+        let attributes = AttributeTable::new(&[AttributeDefinition::float("bidfloor")]).unwrap();
+        let strings = StringTable::new();
+        let mut event_builder = EventBuilder::new(&attributes, &strings);
+
+        assert!(event_builder.with_integer("bidfloor", 2).is_err());
+        assert!(event_builder.with_string("bidfloor", "2.5").is_err());
+    }
+
+    #[test]
+    fn round_the_f64_attribute_values_with_the_configured_policy() {
+        use crate::config::RoundingPolicy;
+
+        let attributes = AttributeTable::new(&[AttributeDefinition::float("bidfloor")]).unwrap();
+        let strings = StringTable::new();
+        let cases = [
+            (RoundingPolicy::HalfEven, 2.5, 2),
+            (RoundingPolicy::HalfEven, 3.5, 4),
+            (RoundingPolicy::HalfAwayFromZero, 2.5, 3),
+            (RoundingPolicy::TowardZero, -2.9, -2),
+            (RoundingPolicy::AwayFromZero, 2.1, 3),
+            (RoundingPolicy::Floor, -2.1, -3),
+            (RoundingPolicy::Ceiling, 2.1, 3),
+        ];
+
+        for (rounding, value, expected) in cases {
+            let config = ATreeConfig::new()
+                .with_float_scale(0)
+                .with_rounding(rounding);
+            let observers = Observers::<u64>::new();
+            let mut event_builder =
+                EventBuilder::with_config(&attributes, &strings, &config, &observers);
+
+            event_builder.with_f64("bidfloor", value).unwrap();
+
+            let event = event_builder.build().unwrap();
+            assert!(
+                matches!(
+                    event.values.as_slice(),
+                    [AttributeValue::Float(actual)] if *actual == Decimal::from(expected)
+                ),
+                "{rounding:?} of {value} should be {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn return_an_error_when_adding_a_non_finite_f64_attribute_value() {
+        let attributes = AttributeTable::new(&[AttributeDefinition::float("bidfloor")]).unwrap();
+        let strings = StringTable::new();
+        let mut event_builder = EventBuilder::new(&attributes, &strings);
+
+        let result = event_builder.with_f64("bidfloor", f64::NAN);
+
+        assert!(matches!(result, Err(EventError::InvalidFloat { .. })));
+    }
+
+    #[test]
+    fn return_an_error_when_adding_a_float_attribute_value_with_a_scale_too_large() {
+        let attributes = AttributeTable::new(&[AttributeDefinition::float("bidfloor")]).unwrap();
+        let strings = StringTable::new();
+        let mut event_builder = EventBuilder::new(&attributes, &strings);
+
+        let result = event_builder.with_float("bidfloor", 1, 29);
+
+        assert!(matches!(result, Err(EventError::InvalidScale { .. })));
+    }
+
     #[test]
     fn can_add_a_string_attribute_value() {
         let attributes = AttributeTable::new(&[AttributeDefinition::string("country")]).unwrap();
@@ -409,6 +1505,37 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn can_add_an_interned_string_attribute_value() {
+        let attributes = AttributeTable::new(&[AttributeDefinition::string("country")]).unwrap();
+        let mut strings = StringTable::new();
+        let interned = InternedString(strings.get_or_update("US"));
+        let mut event_builder = EventBuilder::new(&attributes, &strings);
+
+        let result = event_builder.with_interned_string("country", interned);
+
+        assert!(result.is_ok());
+        assert!(
+            matches!(&event_builder.by_ids[..], [AttributeValue::String(id)] if *id == strings.get("US"))
+        );
+    }
+
+    #[test]
+    fn forget_the_rejected_values_when_resetting() {
+        let attributes = AttributeTable::new(&[AttributeDefinition::string("country")]).unwrap();
+        let strings = StringTable::new();
+        let mut event_builder = EventBuilder::new(&attributes, &strings);
+        let _ = event_builder.with_integer("country", 1);
+        let _ = event_builder.with_undefined("city");
+        assert_eq!(1, event_builder.rejections.type_mismatches.len());
+        assert_eq!(vec!["city"], event_builder.rejections.unknown_attributes);
+
+        event_builder.reset();
+
+        assert!(event_builder.rejections.type_mismatches.is_empty());
+        assert!(event_builder.rejections.unknown_attributes.is_empty());
+    }
+
     #[test]
     fn can_add_an_integer_list_attribute_value() {
         let attributes =
@@ -421,6 +1548,36 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn can_add_a_sorted_integer_list_attribute_value() {
+        let attributes =
+            AttributeTable::new(&[AttributeDefinition::integer_list("segment_ids")]).unwrap();
+        let strings = StringTable::new();
+        let mut event_builder = EventBuilder::new(&attributes, &strings);
+
+        let result = event_builder.with_integer_list_sorted("segment_ids", &[1, 2, 2, 3]);
+
+        assert!(result.is_ok());
+        assert!(
+            matches!(&event_builder.by_ids[..], [AttributeValue::IntegerList(values)] if values == &[1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn return_an_error_when_the_sorted_integer_list_is_not_sorted() {
+        let attributes =
+            AttributeTable::new(&[AttributeDefinition::integer_list("segment_ids")]).unwrap();
+        let strings = StringTable::new();
+        let mut event_builder = EventBuilder::new(&attributes, &strings);
+
+        let result = event_builder.with_integer_list_sorted("segment_ids", &[1, 3, 2]);
+
+        assert_eq!(
+            Err(EventError::UnsortedList("segment_ids".to_string())),
+            result
+        );
+    }
+
     #[test]
     fn can_add_an_string_list_attribute_value() {
         let attributes =
@@ -433,6 +1590,51 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn can_add_the_attribute_values_of_a_map() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::float("bidfloor"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::integer_list("segment_ids"),
+            AttributeDefinition::string_list("deal_ids"),
+            AttributeDefinition::string("city"),
+        ];
+        let attributes = AttributeTable::new(&definitions).unwrap();
+        let strings = StringTable::new();
+        let mut event_builder = EventBuilder::new(&attributes, &strings);
+
+        let result = event_builder.with_map(HashMap::from([
+            ("private", AttrValue::Boolean(true)),
+            ("exchange_id", AttrValue::Integer(1)),
+            (
+                "bidfloor",
+                AttrValue::Float {
+                    number: 125,
+                    scale: 2,
+                },
+            ),
+            ("country", AttrValue::String("CA")),
+            ("segment_ids", AttrValue::IntegerList(vec![1, 2])),
+            ("deal_ids", AttrValue::StringList(vec!["deal-1"])),
+            ("city", AttrValue::Undefined),
+        ]));
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn return_an_error_when_a_map_value_has_the_wrong_type() {
+        let attributes = AttributeTable::new(&[AttributeDefinition::boolean("private")]).unwrap();
+        let strings = StringTable::new();
+        let mut event_builder = EventBuilder::new(&attributes, &strings);
+
+        let result = event_builder.with_map(HashMap::from([("private", AttrValue::Integer(1))]));
+
+        assert!(matches!(result, Err(EventError::WrongType { .. })));
+    }
+
     #[test]
     fn return_an_error_when_adding_a_non_existing_attribute() {
         let attributes =
@@ -480,6 +1682,22 @@ mod tests {
         assert!(builder.build().is_ok());
     }
 
+    #[test]
+    fn can_reset_the_attributes_to_undefined() {
+        let attributes = AttributeTable::new(&[AttributeDefinition::boolean("private")]).unwrap();
+        let strings = StringTable::new();
+        let mut event_builder = EventBuilder::new(&attributes, &strings);
+        event_builder.with_boolean("private", true).unwrap();
+
+        event_builder.reset();
+
+        let event = event_builder.to_event().unwrap();
+        assert!(matches!(
+            event.values.as_slice(),
+            [AttributeValue::Undefined]
+        ));
+    }
+
     #[test]
     fn can_create_an_event_with_a_missing_attribute() {
         let attributes = AttributeTable::new(&[AttributeDefinition::boolean("private")]).unwrap();
@@ -0,0 +1,69 @@
+use std::{fmt, sync::Arc};
+
+/// A job of a batch operation, which borrows the data of the [`crate::ATree`] for the duration of
+/// [`Executor::execute()`].
+pub type Job<'a> = Box<dyn FnOnce() + Send + 'a>;
+
+/// Runs the jobs of the batch operations of an [`crate::ATree`], such as
+/// [`crate::ATree::insert_many()`], instead of threads spawned for each operation.
+///
+/// By default, the chunks of a batch operation are processed on scoped threads; an executor lets
+/// the application run them on its own thread pool instead.
+///
+/// # Examples
+///
+/// ```rust
+/// use a_tree::{ATree, AttributeDefinition, Executor, Job};
+/// use std::{num::NonZeroUsize, sync::Arc, thread};
+///
+/// struct Scoped;
+///
+/// impl Executor for Scoped {
+///     fn execute(&self, jobs: Vec<Job<'_>>) {
+///         thread::scope(|scope| {
+///             for job in jobs {
+///                 scope.spawn(job);
+///             }
+///         });
+///     }
+/// }
+///
+/// let definitions = [AttributeDefinition::integer("age")];
+/// let mut atree = ATree::new(&definitions).unwrap();
+/// atree.set_executor(Arc::new(Scoped));
+///
+/// let subscriptions = [(1u64, "age > 18"), (2u64, "age < 10")];
+/// let inserted = atree.insert_many(&subscriptions, NonZeroUsize::new(2).unwrap());
+/// assert_eq!(2, inserted.len());
+/// ```
+pub trait Executor: Send + Sync {
+    /// Run all the jobs, possibly concurrently, and return once every one of them is done.
+    ///
+    /// The jobs borrow data that is only valid until this method returns, so it must not return
+    /// before they all ran. A job that panics or that is not run makes the batch operation panic.
+    fn execute(&self, jobs: Vec<Job<'_>>);
+}
+
+/// The [`Executor`] of a tree, if any.
+#[derive(Clone, Default)]
+pub(crate) struct TreeExecutor(Option<Arc<dyn Executor>>);
+
+impl TreeExecutor {
+    #[inline]
+    pub(crate) fn set(&mut self, executor: Arc<dyn Executor>) {
+        self.0 = Some(executor);
+    }
+
+    #[inline]
+    pub(crate) fn get(&self) -> Option<&dyn Executor> {
+        self.0.as_deref()
+    }
+}
+
+impl fmt::Debug for TreeExecutor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TreeExecutor")
+            .field(&self.0.is_some())
+            .finish()
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{
+    ast::Node,
+    events::{AttributeId, Event},
+    expression::PredicateExpression,
+    predicates::Predicate,
+    strings::LocalStringTable,
+};
+use std::collections::HashMap;
+
+/// Why a subscription matches an [`Event`] or not; see [`crate::ATree::explain()`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Explanation {
+    result: Option<bool>,
+    predicates: Vec<PredicateOutcome>,
+}
+
+impl Explanation {
+    /// Whether the subscription matches the event.
+    #[inline]
+    pub const fn matches(&self) -> bool {
+        matches!(self.result, Some(true))
+    }
+
+    /// The result of the whole expression; `None` when it depends on an undefined attribute.
+    #[inline]
+    pub const fn result(&self) -> Option<bool> {
+        self.result
+    }
+
+    /// The outcome of each predicate of the expression, from left to right.
+    #[inline]
+    pub fn predicates(&self) -> &[PredicateOutcome] {
+        &self.predicates
+    }
+}
+
+/// The outcome of a single predicate of an [`Explanation`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PredicateOutcome {
+    predicate: PredicateExpression,
+    result: Option<bool>,
+}
+
+impl PredicateOutcome {
+    /// The predicate.
+    #[inline]
+    pub const fn predicate(&self) -> &PredicateExpression {
+        &self.predicate
+    }
+
+    /// The result of the predicate; `None` when its attribute is undefined in the event.
+    #[inline]
+    pub const fn result(&self) -> Option<bool> {
+        self.result
+    }
+}
+
+/// Evaluate an expression against an event predicate by predicate.
+///
+/// `evaluated` is the expression parsed with the strings of the tree and `described` the same
+/// expression parsed with `strings`, to read back the values of its predicates.
+pub fn explain(
+    evaluated: &Node,
+    described: &Node,
+    event: &Event,
+    names: &HashMap<AttributeId, &str>,
+    strings: &LocalStringTable,
+) -> Explanation {
+    let mut predicates = vec![];
+    let result = evaluate(evaluated, described, event, &mut |predicate, result| {
+        predicates.push(PredicateOutcome {
+            predicate: PredicateExpression::new(predicate, names, strings),
+            result,
+        });
+    });
+    Explanation { result, predicates }
+}
+
+fn evaluate(
+    evaluated: &Node,
+    described: &Node,
+    event: &Event,
+    record: &mut impl FnMut(&Predicate, Option<bool>),
+) -> Option<bool> {
+    match (evaluated, described) {
+        (Node::And(left, right), Node::And(left_described, right_described)) => {
+            let left = evaluate(left, left_described, event, record);
+            let right = evaluate(right, right_described, event, record);
+            match (left, right) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            }
+        }
+        (Node::Or(left, right), Node::Or(left_described, right_described)) => {
+            let left = evaluate(left, left_described, event, record);
+            let right = evaluate(right, right_described, event, record);
+            match (left, right) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            }
+        }
+        (Node::Not(operand), Node::Not(operand_described)) => {
+            evaluate(operand, operand_described, event, record).map(|result| !result)
+        }
+        (Node::Value(predicate), Node::Value(predicate_described)) => {
+            let result = predicate.evaluate(event);
+            record(predicate_described, result);
+            result
+        }
+        (Node::Constant(value), _) => Some(*value),
+        (evaluated, described) => {
+            unreachable!("Mismatched expressions => got: {evaluated:?} and {described:?}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ATree, AttributeDefinition, Literal, PredicateOperator};
+
+    fn an_atree() -> ATree<u64> {
+        ATree::new(&[
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::string("country"),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn explain_each_predicate_of_a_subscription_from_left_to_right() {
+        let mut atree = an_atree();
+        atree
+            .insert(&1, r#"exchange_id = 1 and (country = "CA" or not private)"#)
+            .unwrap();
+        let mut builder = atree.make_event();
+        builder.with_integer("exchange_id", 1).unwrap();
+        builder.with_string("country", "US").unwrap();
+        builder.with_boolean("private", false).unwrap();
+        let event = builder.build().unwrap();
+
+        let explanation = atree.explain(&1, &event).unwrap();
+
+        assert!(explanation.matches());
+        let outcomes: Vec<_> = explanation
+            .predicates()
+            .iter()
+            .map(|outcome| {
+                (
+                    outcome.predicate().attribute(),
+                    outcome.predicate().operator(),
+                    outcome.predicate().value().cloned(),
+                    outcome.result(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "exchange_id",
+                    PredicateOperator::Equal,
+                    Some(Literal::Integer(1)),
+                    Some(true)
+                ),
+                (
+                    "country",
+                    PredicateOperator::Equal,
+                    Some(Literal::String("CA".to_owned())),
+                    Some(false)
+                ),
+                ("private", PredicateOperator::Variable, None, Some(false)),
+            ],
+            outcomes
+        );
+    }
+
+    #[test]
+    fn explain_the_undefined_attributes_as_unknown_outcomes() {
+        let mut atree = an_atree();
+        atree.insert(&1, "exchange_id = 1 or private").unwrap();
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", false).unwrap();
+        let event = builder.build().unwrap();
+
+        let explanation = atree.explain(&1, &event).unwrap();
+
+        assert!(!explanation.matches());
+        assert_eq!(None, explanation.result());
+        let results: Vec<_> = explanation
+            .predicates()
+            .iter()
+            .map(|outcome| outcome.result())
+            .collect();
+        assert_eq!(vec![None, Some(false)], results);
+        assert!(atree.search(&event).unwrap().matches().is_empty());
+    }
+
+    #[test]
+    fn explain_a_constant_expression_without_predicates() {
+        let mut atree = an_atree();
+        atree.insert(&1, "true or private").unwrap();
+        let event = atree.make_event().build().unwrap();
+
+        let explanation = atree.explain(&1, &event).unwrap();
+
+        assert!(explanation.matches());
+        assert!(explanation.predicates().is_empty());
+    }
+
+    #[test]
+    fn return_none_when_explaining_an_unknown_subscription() {
+        let atree = an_atree();
+        let event = atree.make_event().build().unwrap();
+
+        assert_eq!(None, atree.explain(&1, &event));
+    }
+}
@@ -0,0 +1,397 @@
+use crate::{
+    ast::Node,
+    events::AttributeId,
+    predicates::{
+        ComparisonOperator, ComparisonValue, EqualityOperator, FuzzyOperator, ListLiteral,
+        ListOperator, NullOperator, PatternOperator, Predicate, PredicateKind, PrimitiveLiteral,
+        RangeOperator, RecencyOperator, SetOperator,
+    },
+    strings::LocalStringTable,
+};
+use itertools::Itertools;
+use rust_decimal::prelude::ToPrimitive;
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+};
+
+/// The structure of an arbitrary boolean expression as seen by an [`crate::ATree`]; see
+/// [`crate::ATree::parse_expression()`].
+///
+/// The `true` and `false` literals are folded while parsing, so a [`Expression::Constant`] is
+/// only found at the root of an expression.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Predicate(PredicateExpression),
+    Constant(bool),
+}
+
+impl Expression {
+    pub(crate) fn new(
+        node: Node,
+        names: &HashMap<AttributeId, &str>,
+        strings: &LocalStringTable,
+    ) -> Self {
+        let convert = |node: Box<Node>| Box::new(Self::new(*node, names, strings));
+        match node {
+            Node::And(left, right) => Self::And(convert(left), convert(right)),
+            Node::Or(left, right) => Self::Or(convert(left), convert(right)),
+            Node::Not(operand) => Self::Not(convert(operand)),
+            Node::Value(predicate) => {
+                Self::Predicate(PredicateExpression::new(&predicate, names, strings))
+            }
+            Node::Constant(value) => Self::Constant(value),
+            Node::Placeholder(_) => {
+                unreachable!("the placeholders are rejected while parsing the expression")
+            }
+        }
+    }
+
+    /// The operands of an `and`, an `or` or a `not`, from left to right.
+    pub fn children(&self) -> Vec<&Self> {
+        match self {
+            Self::And(left, right) | Self::Or(left, right) => vec![left, right],
+            Self::Not(operand) => vec![operand],
+            Self::Predicate(_) | Self::Constant(_) => vec![],
+        }
+    }
+}
+
+/// A predicate on a single attribute of the events
+#[derive(Clone, Debug, PartialEq)]
+pub struct PredicateExpression {
+    attribute: String,
+    operator: PredicateOperator,
+    value: Option<Literal>,
+    count: Option<usize>,
+    edit_distance: Option<u8>,
+}
+
+impl PredicateExpression {
+    pub(crate) fn new(
+        predicate: &Predicate,
+        names: &HashMap<AttributeId, &str>,
+        strings: &LocalStringTable,
+    ) -> Self {
+        let strings_of = |values: &[_]| {
+            values
+                .iter()
+                .map(|id| strings.resolve(*id).to_owned())
+                .sorted()
+                .collect()
+        };
+        let list = |list: &ListLiteral| match list {
+            ListLiteral::IntegerList(values) => Literal::IntegerList(values.clone()),
+            ListLiteral::StringList(values) => Literal::StringList(strings_of(values)),
+        };
+        let number = |value: &ComparisonValue| match value {
+            ComparisonValue::Integer(value) => Literal::Integer(*value),
+            ComparisonValue::Float(value) => Literal::Float(value.to_f64().unwrap_or(0.0)),
+        };
+        let (operator, value) = match predicate.kind() {
+            PredicateKind::Variable => (PredicateOperator::Variable, None),
+            PredicateKind::NegatedVariable => (PredicateOperator::NegatedVariable, None),
+            PredicateKind::Set(operator, values) => (
+                match operator {
+                    SetOperator::In => PredicateOperator::In,
+                    SetOperator::NotIn => PredicateOperator::NotIn,
+                },
+                Some(list(values)),
+            ),
+            PredicateKind::Comparison(operator, value) => (
+                match operator {
+                    ComparisonOperator::LessThan => PredicateOperator::LessThan,
+                    ComparisonOperator::LessThanEqual => PredicateOperator::LessThanEqual,
+                    ComparisonOperator::GreaterThan => PredicateOperator::GreaterThan,
+                    ComparisonOperator::GreaterThanEqual => PredicateOperator::GreaterThanEqual,
+                },
+                Some(number(value)),
+            ),
+            PredicateKind::Range(operator, low, high) => (
+                match operator {
+                    RangeOperator::Between => PredicateOperator::Between,
+                    RangeOperator::NotBetween => PredicateOperator::NotBetween,
+                },
+                Some(match (number(low), number(high)) {
+                    (Literal::Integer(low), Literal::Integer(high)) => {
+                        Literal::IntegerRange(low, high)
+                    }
+                    (Literal::Float(low), Literal::Float(high)) => Literal::FloatRange(low, high),
+                    (low, high) => unreachable!("mismatching bounds {low:?} and {high:?}"),
+                }),
+            ),
+            PredicateKind::Equality(operator, value) => (
+                match operator {
+                    EqualityOperator::Equal => PredicateOperator::Equal,
+                    EqualityOperator::NotEqual => PredicateOperator::NotEqual,
+                },
+                Some(match value {
+                    PrimitiveLiteral::Integer(value) => Literal::Integer(*value),
+                    PrimitiveLiteral::Float(value) => Literal::Float(value.to_f64().unwrap_or(0.0)),
+                    PrimitiveLiteral::String(id) => {
+                        Literal::String(strings.resolve(*id).to_owned())
+                    }
+                }),
+            ),
+            PredicateKind::List(operator, values) => (
+                match operator {
+                    ListOperator::OneOf => PredicateOperator::OneOf,
+                    ListOperator::NoneOf => PredicateOperator::NoneOf,
+                    ListOperator::AllOf => PredicateOperator::AllOf,
+                    ListOperator::NotAllOf => PredicateOperator::NotAllOf,
+                    ListOperator::IntersectAtLeast(_) => PredicateOperator::IntersectAtLeast,
+                    ListOperator::IntersectFewerThan(_) => PredicateOperator::IntersectFewerThan,
+                },
+                Some(list(values)),
+            ),
+            PredicateKind::Null(operator) => (
+                match operator {
+                    NullOperator::IsNull => PredicateOperator::IsNull,
+                    NullOperator::IsNotNull => PredicateOperator::IsNotNull,
+                    NullOperator::IsEmpty => PredicateOperator::IsEmpty,
+                    NullOperator::IsNotEmpty => PredicateOperator::IsNotEmpty,
+                },
+                None,
+            ),
+            PredicateKind::Pattern(operator, pattern) => (
+                match operator {
+                    PatternOperator::StartsWith => PredicateOperator::StartsWith,
+                    PatternOperator::NotStartsWith => PredicateOperator::NotStartsWith,
+                    PatternOperator::Contains => PredicateOperator::Contains,
+                    PatternOperator::NotContains => PredicateOperator::NotContains,
+                },
+                Some(Literal::String(pattern.clone())),
+            ),
+            PredicateKind::Recency(operator, window) => (
+                match operator {
+                    RecencyOperator::WithinLast => PredicateOperator::WithinLast,
+                    RecencyOperator::NotWithinLast => PredicateOperator::NotWithinLast,
+                },
+                Some(Literal::Integer(*window)),
+            ),
+            PredicateKind::Fuzzy(operator, pattern) => (
+                match operator {
+                    FuzzyOperator::Matches => PredicateOperator::FuzzyEqual,
+                    FuzzyOperator::NotMatches => PredicateOperator::NotFuzzyEqual,
+                },
+                Some(Literal::String(pattern.pattern().to_owned())),
+            ),
+        };
+        let count = match predicate.kind() {
+            PredicateKind::List(
+                ListOperator::IntersectAtLeast(count) | ListOperator::IntersectFewerThan(count),
+                _,
+            ) => Some(*count),
+            _ => None,
+        };
+        let edit_distance = match predicate.kind() {
+            PredicateKind::Fuzzy(_, pattern) => Some(pattern.distance()),
+            _ => None,
+        };
+        Self {
+            attribute: names[&predicate.attribute()].to_owned(),
+            operator,
+            value,
+            count,
+            edit_distance,
+        }
+    }
+
+    /// The name of the attribute.
+    #[inline]
+    pub fn attribute(&self) -> &str {
+        &self.attribute
+    }
+
+    /// The operator applied to the attribute.
+    #[inline]
+    pub const fn operator(&self) -> PredicateOperator {
+        self.operator
+    }
+
+    /// The value compared with the attribute, if the operator takes one.
+    #[inline]
+    pub const fn value(&self) -> Option<&Literal> {
+        self.value.as_ref()
+    }
+
+    /// The amount of values of the list the attribute must contain, for an `intersect_at_least`.
+    #[inline]
+    pub const fn count(&self) -> Option<usize> {
+        self.count
+    }
+
+    /// The amount of edits allowed between the string and the attribute, for a `~=`.
+    #[inline]
+    pub const fn edit_distance(&self) -> Option<u8> {
+        self.edit_distance
+    }
+}
+
+/// The operator of a [`PredicateExpression`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum PredicateOperator {
+    /// A boolean attribute used on its own
+    Variable,
+    /// A negated boolean attribute
+    NegatedVariable,
+    In,
+    NotIn,
+    LessThan,
+    LessThanEqual,
+    GreaterThan,
+    GreaterThanEqual,
+    Equal,
+    NotEqual,
+    OneOf,
+    NoneOf,
+    AllOf,
+    NotAllOf,
+    IsNull,
+    IsNotNull,
+    IsEmpty,
+    IsNotEmpty,
+    StartsWith,
+    NotStartsWith,
+    Contains,
+    NotContains,
+    Between,
+    NotBetween,
+    /// A time at most the amount of seconds of the value before the time of the event
+    WithinLast,
+    NotWithinLast,
+    /// At least [`PredicateExpression::count()`] values of the list in the attribute
+    IntersectAtLeast,
+    IntersectFewerThan,
+    /// A string at most [`PredicateExpression::edit_distance()`] edits away from the value
+    FuzzyEqual,
+    NotFuzzyEqual,
+}
+
+impl Display for PredicateOperator {
+    fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        let operator = match self {
+            Self::Variable => "",
+            Self::NegatedVariable => "not",
+            Self::In => "in",
+            Self::NotIn => "not in",
+            Self::LessThan => "<",
+            Self::LessThanEqual => "<=",
+            Self::GreaterThan => ">",
+            Self::GreaterThanEqual => ">=",
+            Self::Equal => "=",
+            Self::NotEqual => "<>",
+            Self::OneOf => "one of",
+            Self::NoneOf => "none of",
+            Self::AllOf => "all of",
+            Self::NotAllOf => "not all of",
+            Self::IsNull => "is null",
+            Self::IsNotNull => "is not null",
+            Self::IsEmpty => "is empty",
+            Self::IsNotEmpty => "is not empty",
+            Self::StartsWith => "starts with",
+            Self::NotStartsWith => "not starts with",
+            Self::Contains => "contains",
+            Self::NotContains => "not contains",
+            Self::Between => "between",
+            Self::NotBetween => "not between",
+            Self::WithinLast => "within last",
+            Self::NotWithinLast => "not within last",
+            Self::IntersectAtLeast => "intersect at least",
+            Self::IntersectFewerThan => "intersect fewer than",
+            Self::FuzzyEqual => "~=",
+            Self::NotFuzzyEqual => "!~=",
+        };
+        write!(formatter, "{operator}")
+    }
+}
+
+/// The value of a [`PredicateExpression`]
+///
+/// The lists are sorted and deduplicated.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    Integer(i64),
+    Float(f64),
+    String(String),
+    IntegerList(Vec<i64>),
+    StringList(Vec<String>),
+    /// The inclusive bounds of a `between`
+    IntegerRange(i64, i64),
+    /// The inclusive bounds of a `between` on a float
+    FloatRange(f64, f64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ATree, AttributeDefinition};
+
+    #[test]
+    fn can_parse_an_expression_with_the_attribute_names_and_the_strings() {
+        let atree = ATree::<u64>::new(&[
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::integer_list("segments"),
+        ])
+        .unwrap();
+
+        let expression = atree
+            .parse_expression(r#"not private or (country = "CA" and segments one of [3, 1, 3])"#)
+            .unwrap();
+
+        let predicate = |attribute: &str, operator, value| {
+            Box::new(Expression::Predicate(PredicateExpression {
+                attribute: attribute.to_owned(),
+                operator,
+                value,
+                count: None,
+                edit_distance: None,
+            }))
+        };
+        assert_eq!(
+            Expression::Or(
+                Box::new(Expression::Not(predicate(
+                    "private",
+                    PredicateOperator::Variable,
+                    None
+                ))),
+                Box::new(Expression::And(
+                    predicate(
+                        "country",
+                        PredicateOperator::Equal,
+                        Some(Literal::String("CA".to_owned()))
+                    ),
+                    predicate(
+                        "segments",
+                        PredicateOperator::OneOf,
+                        Some(Literal::IntegerList(vec![1, 3]))
+                    ),
+                ))
+            ),
+            expression
+        );
+    }
+
+    #[test]
+    fn fold_the_boolean_literals_when_parsing_an_expression() {
+        let atree = ATree::<u64>::new(&[AttributeDefinition::boolean("private")]).unwrap();
+
+        let expression = atree.parse_expression("private or true").unwrap();
+
+        assert_eq!(Expression::Constant(true), expression);
+        assert!(expression.children().is_empty());
+    }
+
+    #[test]
+    fn does_not_intern_the_strings_of_a_parsed_expression() {
+        let atree = ATree::<u64>::new(&[AttributeDefinition::string("country")]).unwrap();
+
+        atree.parse_expression(r#"country = "CA""#).unwrap();
+
+        assert_eq!(None, atree.interned("CA"));
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt::{Display, Formatter};
+
+/// The largest edit distance of a fuzzy match
+pub(crate) const MAX_EDIT_DISTANCE: u8 = 3;
+
+/// A pattern compiled into a Levenshtein automaton that accepts the texts at most `distance`
+/// edits (insertions, deletions or substitutions of a character) away from it
+///
+/// The automaton is simulated on the band of the 2 × `distance` + 1 characters of the pattern
+/// that can still be aligned with the text read so far, so a text is read once in a time bounded
+/// by the distance instead of the length of the pattern. A text whose length is too far from the
+/// one of the pattern is rejected without being read and the others as soon as no state of the
+/// band is within the distance.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct FuzzyPattern {
+    pattern: String,
+    chars: Box<[char]>,
+    distance: u8,
+}
+
+impl FuzzyPattern {
+    pub(crate) fn new(pattern: &str, distance: u8) -> Self {
+        Self {
+            pattern: pattern.to_owned(),
+            chars: pattern.chars().collect(),
+            distance,
+        }
+    }
+
+    /// The pattern, as written in the expression.
+    #[inline]
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// The amount of edits allowed between the pattern and the text.
+    #[inline]
+    pub const fn distance(&self) -> u8 {
+        self.distance
+    }
+
+    /// Whether the text is at most the distance away from the pattern.
+    pub(crate) fn matches(&self, text: &str) -> bool {
+        let distance = usize::from(self.distance);
+        let length = self.chars.len();
+        if text.chars().count().abs_diff(length) > distance {
+            return false;
+        }
+        // The cost of the edits between the start of the pattern and the text read so far,
+        // capped just above the distance since any higher cost is rejected all the same.
+        let rejected = distance + 1;
+        let mut costs: Vec<_> = (0..=length).map(|end| end.min(rejected)).collect();
+        for (read, c) in text.chars().enumerate() {
+            let read = read + 1;
+            let low = read.saturating_sub(distance);
+            let high = (read + distance).min(length);
+            // The cost before reading the character of the first state of the band.
+            let mut diagonal = if low == 0 {
+                let cost = costs[0];
+                costs[0] = read.min(rejected);
+                cost
+            } else {
+                costs[low - 1]
+            };
+            let mut best = if low == 0 { costs[0] } else { rejected };
+            for end in low.max(1)..=high {
+                let deletion = if end == low {
+                    rejected
+                } else {
+                    costs[end - 1] + 1
+                };
+                let substitution = diagonal + usize::from(self.chars[end - 1] != c);
+                let insertion = costs[end] + 1;
+                diagonal = costs[end];
+                costs[end] = substitution.min(deletion).min(insertion).min(rejected);
+                best = best.min(costs[end]);
+            }
+            if best > distance {
+                return false;
+            }
+        }
+        costs[length] <= distance
+    }
+
+    /// The heap bytes used by the pattern and its automaton.
+    pub(crate) fn heap_bytes(&self) -> usize {
+        self.pattern.capacity() + std::mem::size_of_val(&*self.chars)
+    }
+}
+
+impl Display for FuzzyPattern {
+    fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        write!(formatter, "{:?} within {}", self.pattern, self.distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn levenshtein(a: &str, b: &str) -> usize {
+        let b: Vec<_> = b.chars().collect();
+        let mut costs: Vec<_> = (0..=b.len()).collect();
+        for (i, x) in a.chars().enumerate() {
+            let mut diagonal = costs[0];
+            costs[0] = i + 1;
+            for (j, y) in b.iter().enumerate() {
+                let cost = (diagonal + usize::from(x != *y))
+                    .min(costs[j] + 1)
+                    .min(costs[j + 1] + 1);
+                diagonal = costs[j + 1];
+                costs[j + 1] = cost;
+            }
+        }
+        costs[b.len()]
+    }
+
+    #[test]
+    fn accept_the_texts_within_the_distance() {
+        let pattern = FuzzyPattern::new("adidas", 1);
+
+        assert!(pattern.matches("adidas"));
+        assert!(pattern.matches("adiddas"));
+        assert!(pattern.matches("adida"));
+        assert!(pattern.matches("adibas"));
+        assert!(!pattern.matches("abibas"));
+        assert!(!pattern.matches("adidas originals"));
+        assert!(!pattern.matches(""));
+        assert!(FuzzyPattern::new("", 1).matches("a"));
+        assert!(FuzzyPattern::new("café", 1).matches("cafe"));
+    }
+
+    proptest! {
+        #[test]
+        fn agree_with_the_edit_distance(
+            pattern in "[abc]{0,6}",
+            text in "[abc]{0,8}",
+            distance in 0..=MAX_EDIT_DISTANCE,
+        ) {
+            let expected = levenshtein(&pattern, &text) <= usize::from(distance);
+
+            prop_assert_eq!(expected, FuzzyPattern::new(&pattern, distance).matches(&text));
+        }
+    }
+}
@@ -0,0 +1,501 @@
+use crate::{
+    atree::ATree,
+    error::ATreeError,
+    events::{AttributeDefinition, AttributeKind},
+    json::{self, JsonError},
+    metadata::{Metadata, Subscription},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    fmt::{Debug, Display},
+    hash::Hash,
+    io::Write,
+};
+
+/// The version of the interchange format written by [`write`].
+pub(crate) const VERSION: u64 = 1;
+
+/// Write the interchange document one subscription at a time so that the whole document is
+/// never held in memory.
+pub(crate) fn write<'a, T>(
+    definitions: &[AttributeDefinition],
+    subscriptions: impl Iterator<Item = (&'a T, &'a Subscription)>,
+    writer: &mut impl Write,
+) -> Result<(), JsonError>
+where
+    T: Serialize + 'a,
+{
+    let schema = definitions
+        .iter()
+        .map(|definition| {
+            let mut attribute = Map::new();
+            attribute.insert("name".to_owned(), definition.name().into());
+            attribute.insert("type".to_owned(), kind_name(definition.kind()).into());
+            if let Some(values) = definition.values() {
+                attribute.insert("values".to_owned(), values.into());
+            }
+            if let Some(epsilon) = definition.epsilon() {
+                attribute.insert("epsilon".to_owned(), epsilon.into());
+            }
+            if definition.is_high_cardinality() {
+                attribute.insert("high_cardinality".to_owned(), true.into());
+            }
+            Value::Object(attribute)
+        })
+        .collect::<Vec<_>>();
+
+    // The fields are written in the order of a `serde_json::Map`, as when the document was
+    // built in memory.
+    writer.write_all(b"{\"schema\":").map_err(write_error)?;
+    serde_json::to_writer(&mut *writer, &schema).map_err(write_error)?;
+    writer
+        .write_all(b",\"subscriptions\":[")
+        .map_err(write_error)?;
+    // An ID holding several expressions gets an entry per expression, the oldest first and only
+    // the last one with the metadata, so that they are all inserted again by the import.
+    let no_metadata = Metadata::new();
+    let mut first = true;
+    for (id, subscription) in subscriptions {
+        let id = serde_json::to_value(id).map_err(|error| {
+            JsonError::Format(format!("cannot serialize a subscription ID: {error}"))
+        })?;
+        let last = subscription.shared.len();
+        for (index, expression) in subscription.expressions().enumerate() {
+            if !first {
+                writer.write_all(b",").map_err(write_error)?;
+            }
+            first = false;
+            let metadata = if index == last {
+                &subscription.metadata
+            } else {
+                &no_metadata
+            };
+            let mut entry = Map::new();
+            entry.insert("id".to_owned(), id.clone());
+            entry.insert("expression".to_owned(), expression.into());
+            entry.insert("tags".to_owned(), metadata.tags().into());
+            entry.insert("expiry".to_owned(), metadata.expiry().into());
+            entry.insert("owner".to_owned(), metadata.owner().into());
+            entry.insert("author".to_owned(), metadata.author().into());
+            entry.insert("created_at".to_owned(), metadata.created_at().into());
+            serde_json::to_writer(&mut *writer, &entry).map_err(write_error)?;
+        }
+    }
+    write!(writer, "],\"version\":{VERSION}}}").map_err(write_error)
+}
+
+fn write_error(error: impl Display) -> JsonError {
+    JsonError::Write(error.to_string())
+}
+
+pub(crate) fn import<T>(json: &str) -> Result<ATree<T>, JsonError>
+where
+    T: Eq + Hash + Clone + Debug + DeserializeOwned,
+{
+    let document: Value =
+        serde_json::from_str(json).map_err(|error| JsonError::Syntax(error.to_string()))?;
+    let Value::Object(document) = document else {
+        return Err(JsonError::NotAnObject(json::type_name(&document)));
+    };
+
+    let version = document
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| format_error("missing or invalid \"version\""))?;
+    if version != VERSION {
+        return Err(JsonError::UnsupportedVersion(version));
+    }
+
+    let definitions = array(&document, "schema")?
+        .iter()
+        .map(definition)
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut atree = ATree::new(&definitions).map_err(|error| match error {
+        ATreeError::Event(error) => JsonError::Event(error),
+        error => JsonError::Format(error.to_string()),
+    })?;
+
+    for entry in array(&document, "subscriptions")? {
+        let Value::Object(entry) = entry else {
+            return Err(format_error("a subscription is not an object"));
+        };
+        let id = entry
+            .get("id")
+            .ok_or_else(|| format_error("a subscription is missing its \"id\""))?;
+        let subscription_id: T = serde_json::from_value(id.clone())
+            .map_err(|error| format_error(&format!("invalid subscription ID {id}: {error}")))?;
+        let expression = entry
+            .get("expression")
+            .and_then(Value::as_str)
+            .ok_or_else(|| format_error(&format!("subscription {id} has no \"expression\"")))?;
+        let metadata = metadata(id, entry)?;
+        atree
+            .insert_with_metadata(&subscription_id, expression, metadata)
+            .map_err(|error| JsonError::Expression {
+                id: id.to_string(),
+                message: error.to_string(),
+            })?;
+    }
+
+    Ok(atree)
+}
+
+fn array<'a>(document: &'a Map<String, Value>, key: &str) -> Result<&'a Vec<Value>, JsonError> {
+    document
+        .get(key)
+        .and_then(Value::as_array)
+        .ok_or_else(|| format_error(&format!("missing or invalid {key:?}")))
+}
+
+fn definition(attribute: &Value) -> Result<AttributeDefinition, JsonError> {
+    let name = attribute
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| format_error("an attribute is missing its \"name\""))?;
+    let kind = attribute.get("type").and_then(Value::as_str);
+    let high_cardinality = match attribute.get("high_cardinality") {
+        None => false,
+        Some(value) => value.as_bool().ok_or_else(|| {
+            format_error(&format!(
+                "attribute {name:?} has an invalid \"high_cardinality\""
+            ))
+        })?,
+    };
+    let hinted = |definition: AttributeDefinition| {
+        if high_cardinality {
+            definition.high_cardinality()
+        } else {
+            definition
+        }
+    };
+    if let Some(values) = attribute.get("values") {
+        let values = values
+            .as_array()
+            .filter(|_| kind == Some("string"))
+            .and_then(|values| values.iter().map(Value::as_str).collect::<Option<Vec<_>>>())
+            .ok_or_else(|| format_error(&format!("attribute {name:?} has invalid \"values\"")))?;
+        return Ok(hinted(AttributeDefinition::string_enum(name, &values)));
+    }
+    if let Some(epsilon) = attribute.get("epsilon") {
+        let epsilon = epsilon
+            .as_f64()
+            .filter(|_| kind == Some("float"))
+            .ok_or_else(|| {
+                format_error(&format!("attribute {name:?} has an invalid \"epsilon\""))
+            })?;
+        return Ok(hinted(AttributeDefinition::float_with_epsilon(
+            name, epsilon,
+        )));
+    }
+    let definition = match kind {
+        Some("boolean") => AttributeDefinition::boolean(name),
+        Some("integer") => AttributeDefinition::integer(name),
+        Some("float") => AttributeDefinition::float(name),
+        Some("string") => AttributeDefinition::string(name),
+        Some("integer_list") => AttributeDefinition::integer_list(name),
+        Some("string_list") => AttributeDefinition::string_list(name),
+        _ => {
+            return Err(format_error(&format!(
+                "attribute {name:?} has an invalid \"type\""
+            )))
+        }
+    };
+    Ok(hinted(definition))
+}
+
+/// Read the optional `tags`, `expiry`, `owner`, `author` and `created_at` of a subscription.
+pub(crate) fn metadata(id: &Value, entry: &Map<String, Value>) -> Result<Metadata, JsonError> {
+    let mut metadata = Metadata::new();
+    match entry.get("tags") {
+        None | Some(Value::Null) => {}
+        Some(Value::Array(tags)) => {
+            for tag in tags {
+                let tag = tag.as_str().ok_or_else(|| {
+                    format_error(&format!("subscription {id} has a tag that is not a string"))
+                })?;
+                metadata = metadata.with_tag(tag);
+            }
+        }
+        Some(_) => {
+            return Err(format_error(&format!(
+                "subscription {id} has invalid \"tags\""
+            )))
+        }
+    }
+    match entry.get("expiry") {
+        None | Some(Value::Null) => {}
+        Some(expiry) => {
+            let expiry = expiry.as_u64().ok_or_else(|| {
+                format_error(&format!("subscription {id} has an invalid \"expiry\""))
+            })?;
+            metadata = metadata.with_expiry(expiry);
+        }
+    }
+    if let Some(owner) = optional_str(id, entry, "owner")? {
+        metadata = metadata.with_owner(owner);
+    }
+    if let Some(author) = optional_str(id, entry, "author")? {
+        metadata = metadata.with_author(author);
+    }
+    match entry.get("created_at") {
+        None | Some(Value::Null) => {}
+        Some(created_at) => {
+            let created_at = created_at.as_u64().ok_or_else(|| {
+                format_error(&format!("subscription {id} has an invalid \"created_at\""))
+            })?;
+            metadata = metadata.with_created_at(created_at);
+        }
+    }
+    Ok(metadata)
+}
+
+fn optional_str<'a>(
+    id: &Value,
+    entry: &'a Map<String, Value>,
+    key: &str,
+) -> Result<Option<&'a str>, JsonError> {
+    match entry.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(format_error(&format!(
+            "subscription {id} has an invalid {key:?}"
+        ))),
+    }
+}
+
+const fn kind_name(kind: &AttributeKind) -> &'static str {
+    match kind {
+        AttributeKind::Boolean => "boolean",
+        AttributeKind::Integer => "integer",
+        AttributeKind::Float => "float",
+        AttributeKind::String => "string",
+        AttributeKind::IntegerList => "integer_list",
+        AttributeKind::StringList => "string_list",
+    }
+}
+
+#[inline]
+fn format_error(message: &str) -> JsonError {
+    JsonError::Format(message.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EventError;
+
+    #[test]
+    fn can_round_trip_the_subscriptions() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::string_list("deals"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree
+            .insert_with_metadata(
+                &1u64,
+                r#"private and deals one of ["deal-1"]"#,
+                Metadata::new()
+                    .with_tag("campaign-1")
+                    .with_expiry(10)
+                    .with_owner("ads")
+                    .with_author("bob")
+                    .with_created_at(5),
+            )
+            .unwrap();
+        atree.insert(&2u64, "not private").unwrap();
+
+        let imported = ATree::<u64>::import_json(&atree.export_json().unwrap()).unwrap();
+
+        assert_eq!(
+            Some(r#"private and deals one of ["deal-1"]"#),
+            imported.expression(&1)
+        );
+        assert_eq!(atree.metadata(&1), imported.metadata(&1));
+        assert_eq!(Some(&Metadata::new()), imported.metadata(&2));
+        let mut builder = imported.make_event();
+        builder.with_boolean("private", false).unwrap();
+        let event = builder.build().unwrap();
+        assert_eq!(vec![&2u64], imported.search(&event).unwrap().matches());
+    }
+
+    #[test]
+    fn report_the_failure_of_the_writer() {
+        struct Full;
+        impl Write for Full {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::StorageFull.into())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let atree = ATree::<u64>::new(&[AttributeDefinition::boolean("private")]).unwrap();
+
+        let result = atree.write_json(&mut Full);
+
+        assert!(matches!(result, Err(JsonError::Write(_))));
+    }
+
+    #[test]
+    fn export_the_schema_in_the_order_it_was_defined() {
+        let definitions = [
+            AttributeDefinition::string("country"),
+            AttributeDefinition::integer_list("segments"),
+            AttributeDefinition::float("bidfloor"),
+        ];
+        let atree = ATree::<u64>::new(&definitions).unwrap();
+
+        let document: Value = serde_json::from_str(&atree.export_json().unwrap()).unwrap();
+
+        assert_eq!(
+            serde_json::json!({
+                "version": 1,
+                "schema": [
+                    {"name": "country", "type": "string"},
+                    {"name": "segments", "type": "integer_list"},
+                    {"name": "bidfloor", "type": "float"}
+                ],
+                "subscriptions": []
+            }),
+            document
+        );
+    }
+
+    #[test]
+    fn can_import_the_exported_dictionaries() {
+        let definitions = [AttributeDefinition::string_enum("country", &["CA", "US"])];
+        let atree = ATree::<u64>::new(&definitions).unwrap();
+        let json = atree.export_json().unwrap();
+
+        let copy = ATree::<u64>::import_json(&json).unwrap();
+
+        let document: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            serde_json::json!([{"name": "country", "type": "string", "values": ["CA", "US"]}]),
+            document["schema"]
+        );
+        let values = ["CA".to_owned(), "US".to_owned()];
+        assert_eq!(Some(values.as_slice()), copy.attributes()[0].values());
+    }
+
+    #[test]
+    fn can_import_every_exported_expression_of_a_shared_id() {
+        let definitions = [AttributeDefinition::integer("exchange_id")];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree.insert(&1u64, "exchange_id = 1").unwrap();
+        atree.insert(&1u64, "exchange_id = 2").unwrap();
+
+        let copy = ATree::<u64>::import_json(&atree.export_json().unwrap()).unwrap();
+
+        for exchange_id in [1, 2] {
+            let mut builder = copy.make_event();
+            builder.with_integer("exchange_id", exchange_id).unwrap();
+            let event = builder.build().unwrap();
+            assert_eq!(vec![&1], copy.search(&event).unwrap().matches());
+        }
+        assert_eq!(Some("exchange_id = 2"), copy.expression(&1));
+    }
+
+    #[test]
+    fn can_import_the_exported_epsilons() {
+        let definitions = [AttributeDefinition::float_with_epsilon("price", 0.01)];
+        let atree = ATree::<u64>::new(&definitions).unwrap();
+        let json = atree.export_json().unwrap();
+
+        let copy = ATree::<u64>::import_json(&json).unwrap();
+
+        let document: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            serde_json::json!([{"name": "price", "type": "float", "epsilon": 0.01}]),
+            document["schema"]
+        );
+        assert_eq!(Some(0.01), copy.attributes()[0].epsilon());
+    }
+
+    #[test]
+    fn can_import_the_exported_cardinality_hints() {
+        let definitions = [AttributeDefinition::integer("user_id").high_cardinality()];
+        let atree = ATree::<u64>::new(&definitions).unwrap();
+        let json = atree.export_json().unwrap();
+
+        let copy = ATree::<u64>::import_json(&json).unwrap();
+
+        let document: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            serde_json::json!([{"name": "user_id", "type": "integer", "high_cardinality": true}]),
+            document["schema"]
+        );
+        assert!(copy.attributes()[0].is_high_cardinality());
+    }
+
+    #[test]
+    fn allow_the_metadata_to_be_omitted() {
+        let json = r#"{
+            "version": 1,
+            "schema": [{"name": "private", "type": "boolean"}],
+            "subscriptions": [{"id": "rule-1", "expression": "private"}]
+        }"#;
+
+        let atree = ATree::<String>::import_json(json).unwrap();
+
+        assert_eq!(Some(&Metadata::new()), atree.metadata(&"rule-1".to_owned()));
+    }
+
+    #[test]
+    fn return_an_error_on_an_unsupported_version() {
+        let result = ATree::<u64>::import_json(r#"{"version": 2}"#);
+
+        assert_eq!(Err(JsonError::UnsupportedVersion(2)), result.map(|_| ()));
+    }
+
+    #[test]
+    fn return_an_error_on_an_invalid_attribute_type() {
+        let json = r#"{"version": 1, "schema": [{"name": "private", "type": "bool"}]}"#;
+
+        let result = ATree::<u64>::import_json(json);
+
+        assert_eq!(
+            Err(JsonError::Format(
+                r#"attribute "private" has an invalid "type""#.to_owned()
+            )),
+            result.map(|_| ())
+        );
+    }
+
+    #[test]
+    fn return_an_error_on_duplicate_attributes() {
+        let json = r#"{
+            "version": 1,
+            "schema": [
+                {"name": "private", "type": "boolean"},
+                {"name": "private", "type": "integer"}
+            ],
+            "subscriptions": []
+        }"#;
+
+        let result = ATree::<u64>::import_json(json);
+
+        assert_eq!(
+            Err(JsonError::Event(EventError::AlreadyPresent(
+                "private".to_owned()
+            ))),
+            result.map(|_| ())
+        );
+    }
+
+    #[test]
+    fn return_an_error_with_the_id_of_an_invalid_expression() {
+        let json = r#"{
+            "version": 1,
+            "schema": [{"name": "private", "type": "boolean"}],
+            "subscriptions": [{"id": 7, "expression": "private ="}]
+        }"#;
+
+        let result = ATree::<u64>::import_json(json);
+
+        assert!(matches!(result, Err(JsonError::Expression { id, .. }) if id == "7"));
+    }
+}
@@ -0,0 +1,347 @@
+use crate::events::{AttributeKind, Event, EventBuilder, EventError};
+use rust_decimal::Decimal;
+use serde_json::{Number, Value};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The errors that can happen while building an [`Event`] from a JSON document or while
+/// importing/exporting the subscriptions of an [`crate::ATree`]
+#[derive(Error, PartialEq, Debug)]
+pub enum JsonError {
+    #[error("invalid JSON document: {0}")]
+    Syntax(String),
+    #[error("expected a JSON object, found {0}")]
+    NotAnObject(&'static str),
+    #[error("{name:?}: cannot coerce {found} into {expected:?}")]
+    Coercion {
+        name: String,
+        expected: AttributeKind,
+        found: String,
+    },
+    #[error("failed with {0:?}")]
+    Event(EventError),
+    #[error("invalid interchange document: {0}")]
+    Format(String),
+    #[error("unsupported interchange format version {0}")]
+    UnsupportedVersion(u64),
+    #[error("subscription {id}: {message}")]
+    Expression { id: String, message: String },
+    #[error("cannot write the interchange document: {0}")]
+    Write(String),
+}
+
+pub(crate) fn event_from_json(
+    mut builder: EventBuilder<'_>,
+    json: &str,
+) -> Result<Event, JsonError> {
+    let document: Value =
+        serde_json::from_str(json).map_err(|error| JsonError::Syntax(error.to_string()))?;
+    let Value::Object(fields) = document else {
+        return Err(JsonError::NotAnObject(type_name(&document)));
+    };
+
+    for (name, value) in &fields {
+        let Ok(expected) = builder.kind_of(name) else {
+            builder.unknown_attribute(name).map_err(JsonError::Event)?;
+            continue;
+        };
+        set_value(&mut builder, name, &expected, value)?;
+    }
+
+    builder.build().map_err(JsonError::Event)
+}
+
+/// Set the attribute to the JSON value once coerced into the type of the attribute.
+pub(crate) fn set_value(
+    builder: &mut EventBuilder<'_>,
+    name: &str,
+    expected: &AttributeKind,
+    value: &Value,
+) -> Result<(), JsonError> {
+    let result = match (expected, value) {
+        (_, Value::Null) => builder.with_undefined(name),
+        (AttributeKind::Boolean, Value::Bool(value)) => builder.with_boolean(name, *value),
+        (AttributeKind::Integer, Value::Number(number)) => {
+            let value = to_integer(number).ok_or_else(|| coercion(name, expected, value))?;
+            builder.with_integer(name, value)
+        }
+        (AttributeKind::Float, Value::Number(number)) => {
+            let value = to_decimal(number).ok_or_else(|| coercion(name, expected, value))?;
+            builder.with_decimal(name, value)
+        }
+        (AttributeKind::String, Value::String(value)) => builder.with_string(name, value),
+        (AttributeKind::Integer | AttributeKind::Float, Value::String(text))
+            if builder.coercions().strings_to_numbers() =>
+        {
+            return builder
+                .with_string(name, text)
+                .map_err(|_| coercion(name, expected, value));
+        }
+        (AttributeKind::IntegerList, Value::Array(values)) => {
+            let values = values
+                .iter()
+                .enumerate()
+                .map(|(index, value)| {
+                    value
+                        .as_number()
+                        .and_then(to_integer)
+                        .ok_or_else(|| element_coercion(name, expected, index, value))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            builder.with_integer_list(name, &values)
+        }
+        (AttributeKind::StringList, Value::Array(values)) => {
+            let values = values
+                .iter()
+                .enumerate()
+                .map(|(index, value)| {
+                    value
+                        .as_str()
+                        .ok_or_else(|| element_coercion(name, expected, index, value))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            builder.with_string_list(name, &values)
+        }
+        _ => return Err(coercion(name, expected, value)),
+    };
+    result.map_err(JsonError::Event)
+}
+
+#[inline]
+fn to_integer(number: &Number) -> Option<i64> {
+    number.as_i64()
+}
+
+fn to_decimal(number: &Number) -> Option<Decimal> {
+    if let Some(value) = number.as_i64() {
+        return Some(Decimal::from(value));
+    }
+
+    let value = number.to_string();
+    if value.contains(['e', 'E']) {
+        Decimal::from_scientific(&value).ok()
+    } else {
+        Decimal::from_str(&value).ok()
+    }
+}
+
+fn coercion(name: &str, expected: &AttributeKind, value: &Value) -> JsonError {
+    JsonError::Coercion {
+        name: name.to_owned(),
+        expected: expected.clone(),
+        found: describe(value),
+    }
+}
+
+fn element_coercion(
+    name: &str,
+    expected: &AttributeKind,
+    index: usize,
+    value: &Value,
+) -> JsonError {
+    JsonError::Coercion {
+        name: name.to_owned(),
+        expected: expected.clone(),
+        found: format!("{} at index {index}", describe(value)),
+    }
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Number(number) => format!("number {number}"),
+        value => type_name(value).to_owned(),
+    }
+}
+
+pub(crate) const fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::AttributeDefinition, ATree, ATreeConfig, Coercions, UnknownAttributePolicy,
+    };
+
+    #[test]
+    fn can_build_an_event_with_all_the_attribute_types() {
+        let atree = build_atree();
+
+        let result = atree.event_from_json(
+            r#"{
+                "private": true,
+                "exchange_id": 5,
+                "bidfloor": 1.25,
+                "country": "CA",
+                "segment_ids": [3, 1, 2],
+                "deals": ["deal-1", "deal-2"],
+                "city": null
+            }"#,
+        );
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn can_coerce_integers_and_scientific_notation_into_floats() {
+        let atree = build_atree();
+
+        assert!(atree.event_from_json(r#"{"bidfloor": 2}"#).is_ok());
+        assert!(atree.event_from_json(r#"{"bidfloor": 2.5e-3}"#).is_ok());
+    }
+
+    #[test]
+    fn return_an_error_on_invalid_json() {
+        let atree = build_atree();
+
+        let result = atree.event_from_json(r#"{"private": tru"#);
+
+        assert!(matches!(result, Err(JsonError::Syntax(_))));
+    }
+
+    #[test]
+    fn return_an_error_when_the_document_is_not_an_object() {
+        let atree = build_atree();
+
+        let result = atree.event_from_json("[1, 2]");
+
+        assert_eq!(Err(JsonError::NotAnObject("array")), result.map(|_| ()));
+    }
+
+    #[test]
+    fn return_an_error_on_non_existing_attributes() {
+        let atree = build_atree();
+
+        let result = atree.event_from_json(r#"{"unknown": 1}"#);
+
+        assert_eq!(
+            Err(JsonError::Event(EventError::NonExistingAttribute(
+                "unknown".to_string()
+            ))),
+            result.map(|_| ())
+        );
+    }
+
+    #[test]
+    fn skip_the_non_existing_attributes_when_they_are_ignored() {
+        let definitions = [AttributeDefinition::boolean("private")];
+        let config = ATreeConfig::new().with_unknown_attributes(UnknownAttributePolicy::Ignore);
+        let mut atree = ATree::with_config(&definitions, config).unwrap();
+        atree.insert(&1u64, "private").unwrap();
+
+        let event = atree
+            .event_from_json(r#"{"unknown": 1, "private": true}"#)
+            .unwrap();
+
+        assert_eq!(vec![&1u64], atree.search(&event).unwrap().matches());
+    }
+
+    #[test]
+    fn return_an_error_when_an_integer_has_a_fractional_part() {
+        let atree = build_atree();
+
+        let result = atree.event_from_json(r#"{"exchange_id": 1.5}"#);
+
+        assert_eq!(
+            Err(JsonError::Coercion {
+                name: "exchange_id".to_string(),
+                expected: AttributeKind::Integer,
+                found: "number 1.5".to_string()
+            }),
+            result.map(|_| ())
+        );
+    }
+
+    #[test]
+    fn return_an_error_when_the_value_has_the_wrong_type() {
+        let atree = build_atree();
+
+        let result = atree.event_from_json(r#"{"private": "true"}"#);
+
+        assert_eq!(
+            Err(JsonError::Coercion {
+                name: "private".to_string(),
+                expected: AttributeKind::Boolean,
+                found: "string".to_string()
+            }),
+            result.map(|_| ())
+        );
+    }
+
+    #[test]
+    fn can_parse_the_numbers_written_as_strings_when_configured() {
+        let config = ATreeConfig::new().with_coercions(Coercions::NONE.with_strings_to_numbers());
+        let mut atree = ATree::with_config(&build_atree().attributes(), config).unwrap();
+        atree
+            .insert(&1u64, "exchange_id = 42 and bidfloor < 2.0")
+            .unwrap();
+
+        let event = atree
+            .event_from_json(r#"{"exchange_id": "42", "bidfloor": " 1.5"}"#)
+            .unwrap();
+        let result = atree.event_from_json(r#"{"exchange_id": "forty-two"}"#);
+
+        assert_eq!(vec![&1u64], atree.search(&event).unwrap().matches());
+        assert_eq!(
+            Err(JsonError::Coercion {
+                name: "exchange_id".to_string(),
+                expected: AttributeKind::Integer,
+                found: "string".to_string()
+            }),
+            result.map(|_| ())
+        );
+    }
+
+    #[test]
+    fn return_an_error_with_the_index_of_the_invalid_list_element() {
+        let atree = build_atree();
+
+        let result = atree.event_from_json(r#"{"deals": ["deal-1", 2]}"#);
+
+        assert_eq!(
+            Err(JsonError::Coercion {
+                name: "deals".to_string(),
+                expected: AttributeKind::StringList,
+                found: "number 2 at index 1".to_string()
+            }),
+            result.map(|_| ())
+        );
+    }
+
+    #[test]
+    fn can_search_with_an_event_built_from_json() {
+        let mut atree = build_atree();
+        atree
+            .insert(&1u64, r#"private and deals one of ["deal-2"]"#)
+            .unwrap();
+        atree.insert(&2u64, "bidfloor > 2.0").unwrap();
+
+        let event = atree
+            .event_from_json(r#"{"private": true, "deals": ["deal-2"], "bidfloor": 1.5}"#)
+            .unwrap();
+        let report = atree.search(&event).unwrap();
+
+        assert_eq!(vec![&1u64], report.matches());
+    }
+
+    fn build_atree() -> ATree<u64> {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::float("bidfloor"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string("city"),
+            AttributeDefinition::integer_list("segment_ids"),
+            AttributeDefinition::string_list("deals"),
+        ];
+        ATree::new(&definitions).unwrap()
+    }
+}
@@ -1,7 +1,7 @@
 use crate::error::ParserError;
-use logos::{Logos, SpannedIter};
+use logos::{Lexer as LogosLexer, Logos, SpannedIter};
 use rust_decimal::Decimal;
-use std::{num::ParseIntError, str::FromStr};
+use std::{borrow::Cow, num::ParseIntError, ops::Range, str::FromStr};
 use thiserror::Error;
 
 #[derive(Default, Error, Debug, Clone, PartialEq)]
@@ -13,10 +13,19 @@ pub enum LexicalError {
     Integer(ParseIntError),
     #[error("failed to parse float: {0:?}")]
     Float(rust_decimal::Error),
+    #[error("invalid escape sequence {0:?}")]
+    InvalidEscape(String),
+    #[error("unterminated string literal")]
+    UnterminatedString,
 }
 
 #[derive(Clone, Debug, Logos, PartialEq)]
+/// The whitespace, the `// ...` and `/* ... */` comments as well as the `\` line continuations are
+/// skipped; the spans of the tokens still refer to the original input.
 #[logos(skip r"[\s\t\n\f]+", error = LexicalError)]
+#[logos(skip(r"//[^\n]*", allow_greedy = true))]
+#[logos(skip r"/\*([^*]|\*+[^*/])*\*+/")]
+#[logos(skip r"\\\r?\n")]
 pub enum Token<'source> {
     #[token("<")]
     LessThan,
@@ -32,7 +41,10 @@ pub enum Token<'source> {
     #[token("=")]
     Equal,
     #[token("<>")]
+    #[token("!=")]
     NotEqual,
+    #[token("~=")]
+    FuzzyEqual,
     #[token("in")]
     In,
     #[token("not in")]
@@ -51,6 +63,19 @@ pub enum Token<'source> {
     IsEmpty,
     #[token("is not empty")]
     IsNotEmpty,
+    #[token("starts with")]
+    #[token("starts_with")]
+    StartsWith,
+    #[token("contains")]
+    Contains,
+    #[token("between")]
+    Between,
+    #[token("not between")]
+    NotBetween,
+    #[token("within_last")]
+    WithinLast,
+    #[token("intersect_at_least")]
+    IntersectAtLeast,
     #[token("and")]
     #[token("&&")]
     And,
@@ -67,11 +92,17 @@ pub enum Token<'source> {
     RightSquareBracket,
     #[token(",")]
     Comma,
-    #[regex(r"-?[0-9]+", |lex| lex.slice().parse::<i64>().map_err(LexicalError::Integer))]
+    #[token("?")]
+    Placeholder,
+    #[regex(r"-?[0-9][0-9_]*", integer)]
+    #[regex(r"-?0[xX][0-9a-zA-Z_]*", hexadecimal)]
     IntegerLiteral(i64),
-    #[regex(r#"(\"(\\.|[^"\\])*\"|\'(\\.|[^'\\])*\')"#, |lex| lex.slice().trim_matches(['\'', '"']))]
-    StringLiteral(&'source str),
-    #[regex(r"[0-9]+\.[0-9]*", |lex| Decimal::from_str(lex.slice()).map_err(LexicalError::Float))]
+    #[regex(r#"(\"(\\.|[^"\\])*\"|\'(\\.|[^'\\])*\')"#, string)]
+    #[regex(r#"(\"(\\.|[^"\\])*|\'(\\.|[^'\\])*)"#, unterminated_string)]
+    #[regex(r##"r#*""##, raw_string)]
+    StringLiteral(Cow<'source, str>),
+    #[regex(r"-?[0-9][0-9_]*\.[0-9_]*([eE][+-]?[0-9_]+)?", float)]
+    #[regex(r"-?[0-9][0-9_]*[eE][+-]?[0-9_]+", float)]
     FloatLiteral(Decimal),
     #[token("true", |_| true)]
     #[token("false", |_| false)]
@@ -80,6 +111,122 @@ pub enum Token<'source> {
     Identifier(&'source str),
 }
 
+fn integer<'source>(lex: &LogosLexer<'source, Token<'source>>) -> Result<i64, LexicalError> {
+    lex.slice()
+        .replace('_', "")
+        .parse::<i64>()
+        .map_err(LexicalError::Integer)
+}
+
+fn hexadecimal<'source>(lex: &LogosLexer<'source, Token<'source>>) -> Result<i64, LexicalError> {
+    let slice = lex.slice();
+    let (sign, digits) = match slice.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", slice),
+    };
+    let digits = digits[2..].replace('_', "");
+    i64::from_str_radix(&format!("{sign}{digits}"), 16).map_err(LexicalError::Integer)
+}
+
+fn float<'source>(lex: &LogosLexer<'source, Token<'source>>) -> Result<Decimal, LexicalError> {
+    let slice = lex.slice().replace('_', "");
+    if slice.contains(['e', 'E']) {
+        Decimal::from_scientific(&slice).map_err(LexicalError::Float)
+    } else {
+        Decimal::from_str(&slice).map_err(LexicalError::Float)
+    }
+}
+
+fn string<'source>(
+    lex: &LogosLexer<'source, Token<'source>>,
+) -> Result<Cow<'source, str>, LexicalError> {
+    let slice = lex.slice();
+    unescape(&slice[1..slice.len() - 1])
+}
+
+fn unterminated_string<'source>(
+    _: &LogosLexer<'source, Token<'source>>,
+) -> Result<Cow<'source, str>, LexicalError> {
+    Err(LexicalError::UnterminatedString)
+}
+
+/// Read a raw string such as `r"C:\path"` or `r#"say "hi""#` whose content is kept verbatim.
+fn raw_string<'source>(
+    lex: &mut LogosLexer<'source, Token<'source>>,
+) -> Result<Cow<'source, str>, LexicalError> {
+    let hashes = lex.slice().len() - 2;
+    let remainder = lex.remainder();
+    let mut search = 0;
+    while let Some(position) = remainder[search..].find('"') {
+        let end = search + position;
+        let closing = &remainder[end + 1..];
+        if closing.len() >= hashes && closing.bytes().take(hashes).all(|byte| byte == b'#') {
+            lex.bump(end + 1 + hashes);
+            return Ok(Cow::Borrowed(&remainder[..end]));
+        }
+        search = end + 1;
+    }
+    Err(LexicalError::UnterminatedString)
+}
+
+/// Replace the escape sequences of a string literal.
+///
+/// The `\"`, `\'`, `\\`, `\n`, `\r`, `\t`, `\0` and `\u{...}` sequences are replaced while the
+/// other sequences are kept as they are.
+fn unescape(value: &str) -> Result<Cow<'_, str>, LexicalError> {
+    if !value.contains('\\') {
+        return Ok(Cow::Borrowed(value));
+    }
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('"') => unescaped.push('"'),
+            Some('\'') => unescaped.push('\''),
+            Some('\\') => unescaped.push('\\'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some('0') => unescaped.push('\0'),
+            Some('u') => unescaped.push(unicode_escape(&mut chars)?),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    Ok(Cow::Owned(unescaped))
+}
+
+fn unicode_escape(chars: &mut std::str::Chars<'_>) -> Result<char, LexicalError> {
+    let rest = chars.as_str();
+    let invalid = || {
+        let sequence = rest.find('}').map_or(rest, |end| &rest[..=end]);
+        LexicalError::InvalidEscape(format!("\\u{sequence}"))
+    };
+    let digits = rest
+        .strip_prefix('{')
+        .and_then(|rest| rest.split_once('}'))
+        .map(|(digits, _)| digits)
+        .filter(|digits| {
+            (1..=6).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_hexdigit())
+        })
+        .ok_or_else(invalid)?;
+    let value = u32::from_str_radix(digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(invalid)?;
+    for _ in 0..digits.len() + 2 {
+        chars.next();
+    }
+    Ok(value)
+}
+
 impl std::fmt::Display for Token<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
@@ -116,6 +263,20 @@ impl<'input> Iterator for Lexer<'input> {
     }
 }
 
+/// Return the span of the first token that cannot be lexed.
+pub(crate) fn error_span(input: &str) -> Option<Range<usize>> {
+    Token::lexer(input)
+        .spanned()
+        .find_map(|(token, span)| token.is_err().then_some(span))
+}
+
+/// Return the span of the first occurrence of the identifier.
+pub(crate) fn identifier_span(input: &str, name: &str) -> Option<Range<usize>> {
+    Token::lexer(input).spanned().find_map(|(token, span)| {
+        matches!(token, Ok(Token::Identifier(other)) if other == name).then_some(span)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -129,6 +290,48 @@ mod tests {
             .collect()
     }
 
+    #[test]
+    fn can_skip_the_comments() {
+        let actual = lex_tokens("private // a comment\n/* a\nblock * comment */ and").unwrap();
+        assert_eq!(vec![Token::Identifier("private"), Token::And], actual);
+    }
+
+    #[test]
+    fn skip_the_line_continuations() {
+        let actual = lex_tokens("private \\\n and \\\r\n private").unwrap();
+        assert_eq!(
+            vec![
+                Token::Identifier("private"),
+                Token::And,
+                Token::Identifier("private")
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn keep_the_comment_markers_inside_strings() {
+        let actual = lex_tokens(r#""http://example.com/*""#).unwrap();
+        assert_eq!(
+            vec![Token::StringLiteral("http://example.com/*".into())],
+            actual
+        );
+    }
+
+    #[test]
+    fn keep_the_original_positions_after_a_comment() {
+        let spans = Lexer::new("/* note */ private")
+            .map(|value| value.map(|(start, _, end)| (start, end)))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(vec![(11, 18)], spans);
+    }
+
+    #[test]
+    fn return_an_error_on_an_unterminated_comment() {
+        assert!(lex_tokens("private /* note").is_err());
+    }
+
     #[test]
     fn can_lex_less_than() {
         let actual = lex_tokens("<").unwrap();
@@ -170,7 +373,9 @@ mod tests {
     #[test]
     fn can_lex_not_equal() {
         let actual = lex_tokens("<>").unwrap();
+        let other = lex_tokens("!=").unwrap();
         assert_eq!(vec![Token::NotEqual], actual);
+        assert_eq!(vec![Token::NotEqual], other);
     }
 
     #[test]
@@ -227,6 +432,75 @@ mod tests {
         assert_eq!(vec![Token::IsNotEmpty], actual);
     }
 
+    #[test]
+    fn can_lex_intersect_at_least() {
+        let actual = lex_tokens("segments intersect_at_least [1, 2] count 2").unwrap();
+        assert_eq!(
+            vec![
+                Token::Identifier("segments"),
+                Token::IntersectAtLeast,
+                Token::LeftSquareBracket,
+                Token::IntegerLiteral(1),
+                Token::Comma,
+                Token::IntegerLiteral(2),
+                Token::RightSquareBracket,
+                Token::Identifier("count"),
+                Token::IntegerLiteral(2),
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn can_lex_a_fuzzy_equality() {
+        let actual = lex_tokens("brand ~= 'adidas' within 1").unwrap();
+        assert_eq!(
+            vec![
+                Token::Identifier("brand"),
+                Token::FuzzyEqual,
+                Token::StringLiteral("adidas".into()),
+                Token::Identifier("within"),
+                Token::IntegerLiteral(1),
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn can_lex_within_last() {
+        let actual = lex_tokens("within_last(seen_at, '7d') within_last_seen").unwrap();
+        assert_eq!(
+            vec![
+                Token::WithinLast,
+                Token::LeftParenthesis,
+                Token::Identifier("seen_at"),
+                Token::Comma,
+                Token::StringLiteral("7d".into()),
+                Token::RightParenthesis,
+                Token::Identifier("within_last_seen"),
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn can_lex_between() {
+        let actual = lex_tokens("between not between").unwrap();
+        assert_eq!(vec![Token::Between, Token::NotBetween], actual);
+    }
+
+    #[test]
+    fn can_lex_contains() {
+        let actual = lex_tokens("contains").unwrap();
+        assert_eq!(vec![Token::Contains], actual);
+    }
+
+    #[test]
+    fn can_lex_starts_with() {
+        let actual = lex_tokens("starts with starts_with").unwrap();
+        assert_eq!(vec![Token::StartsWith, Token::StartsWith], actual);
+    }
+
     #[test]
     fn can_lex_and() {
         let actual = lex_tokens("and").unwrap();
@@ -302,33 +576,153 @@ mod tests {
     #[test]
     fn can_lex_empty_string() {
         let actual = lex_tokens("\"\"").unwrap();
-        assert_eq!(vec![Token::StringLiteral("")], actual);
+        assert_eq!(vec![Token::StringLiteral("".into())], actual);
         let actual = lex_tokens("''").unwrap();
-        assert_eq!(vec![Token::StringLiteral("")], actual);
+        assert_eq!(vec![Token::StringLiteral("".into())], actual);
     }
 
     #[test]
     fn can_lex_string() {
         let actual = lex_tokens("\"deal_1\"").unwrap();
-        assert_eq!(vec![Token::StringLiteral("deal_1")], actual);
+        assert_eq!(vec![Token::StringLiteral("deal_1".into())], actual);
         let actual = lex_tokens("'deal_1'").unwrap();
-        assert_eq!(vec![Token::StringLiteral("deal_1")], actual);
+        assert_eq!(vec![Token::StringLiteral("deal_1".into())], actual);
     }
 
     #[test]
     fn can_lex_string_with_escaped_quotes() {
         let actual = lex_tokens(r##""deal\"_1""##).unwrap();
-        assert_eq!(vec![Token::StringLiteral("deal\\\"_1")], actual);
+        assert_eq!(vec![Token::StringLiteral("deal\"_1".into())], actual);
         let actual = lex_tokens("'deal\\'_1'").unwrap();
-        assert_eq!(vec![Token::StringLiteral("deal\\'_1")], actual);
+        assert_eq!(vec![Token::StringLiteral("deal'_1".into())], actual);
     }
 
     #[test]
     fn can_lex_string_with_escaped_chars() {
         let actual = lex_tokens("\"deal_1\n\\dsad\\a\"").unwrap();
-        assert_eq!(vec![Token::StringLiteral("deal_1\n\\dsad\\a")], actual);
+        assert_eq!(
+            vec![Token::StringLiteral("deal_1\n\\dsad\\a".into())],
+            actual
+        );
         let actual = lex_tokens("'deal_1\n\\dsad\\a'").unwrap();
-        assert_eq!(vec![Token::StringLiteral("deal_1\n\\dsad\\a")], actual);
+        assert_eq!(
+            vec![Token::StringLiteral("deal_1\n\\dsad\\a".into())],
+            actual
+        );
+    }
+
+    #[test]
+    fn can_lex_string_with_escape_sequences() {
+        let actual = lex_tokens(r#""a\\b\tc\u{e9}\u{1F600}""#).unwrap();
+        assert_eq!(
+            vec![Token::StringLiteral("a\\b\tc\u{e9}\u{1F600}".into())],
+            actual
+        );
+    }
+
+    #[test]
+    fn return_an_error_on_an_invalid_unicode_escape() {
+        assert_eq!(
+            Err(ParserError::Lexical(LexicalError::InvalidEscape(
+                "\\u{D800}".to_owned()
+            ))),
+            lex_tokens(r#""\u{D800}""#)
+        );
+        assert_eq!(
+            Err(ParserError::Lexical(LexicalError::InvalidEscape(
+                "\\u{zz}".to_owned()
+            ))),
+            lex_tokens(r#""\u{zz}""#)
+        );
+        assert!(lex_tokens(r#""\u{1234567}""#).is_err());
+    }
+
+    #[test]
+    fn can_lex_raw_string() {
+        let actual = lex_tokens(r#"r"C:\deals""#).unwrap();
+        assert_eq!(vec![Token::StringLiteral(r"C:\deals".into())], actual);
+        let actual = lex_tokens(r###"r#"say "hi""# r##"a "# b"##"###).unwrap();
+        assert_eq!(
+            vec![
+                Token::StringLiteral(r#"say "hi""#.into()),
+                Token::StringLiteral(r##"a "# b"##.into())
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn return_an_error_on_an_unterminated_string() {
+        let expected = Err(ParserError::Lexical(LexicalError::UnterminatedString));
+        assert_eq!(expected, lex_tokens(r#"deal = "deal-1"#));
+        assert_eq!(expected, lex_tokens(r##"deal = r#"deal-1""##));
+    }
+
+    #[test]
+    fn can_lex_hexadecimal_integer() {
+        let actual = lex_tokens("0xFF -0x1_0 0Xff").unwrap();
+        assert_eq!(
+            vec![
+                Token::IntegerLiteral(255),
+                Token::IntegerLiteral(-16),
+                Token::IntegerLiteral(255)
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn return_an_error_on_an_invalid_hexadecimal_integer() {
+        assert!(matches!(
+            lex_tokens("0xFG"),
+            Err(ParserError::Lexical(LexicalError::Integer(_)))
+        ));
+        assert!(matches!(
+            lex_tokens("0x"),
+            Err(ParserError::Lexical(LexicalError::Integer(_)))
+        ));
+    }
+
+    #[test]
+    fn can_lex_integer_with_underscores() {
+        let actual = lex_tokens("1_000_000 -1_000").unwrap();
+        assert_eq!(
+            vec![
+                Token::IntegerLiteral(1_000_000),
+                Token::IntegerLiteral(-1_000)
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn return_an_error_on_an_integer_overflow() {
+        assert!(matches!(
+            lex_tokens("9_223_372_036_854_775_808"),
+            Err(ParserError::Lexical(LexicalError::Integer(_)))
+        ));
+    }
+
+    #[test]
+    fn can_lex_float_in_scientific_notation() {
+        let actual = lex_tokens("1.5e6 2E-3 1_000.5 -1.25").unwrap();
+        assert_eq!(
+            vec![
+                Token::FloatLiteral(Decimal::new(1_500_000, 0)),
+                Token::FloatLiteral(Decimal::new(2, 3)),
+                Token::FloatLiteral(Decimal::new(10_005, 1)),
+                Token::FloatLiteral(Decimal::new(-125, 2))
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn return_an_error_on_an_out_of_range_float() {
+        assert!(matches!(
+            lex_tokens("1e99"),
+            Err(ParserError::Lexical(LexicalError::Float(_)))
+        ));
     }
 
     #[test]
@@ -352,11 +746,11 @@ mod tests {
                 Token::Identifier("deal_ids"),
                 Token::OneOf,
                 Token::LeftParenthesis,
-                Token::StringLiteral("deal_1"),
+                Token::StringLiteral("deal_1".into()),
                 Token::Comma,
-                Token::StringLiteral("deal_2"),
+                Token::StringLiteral("deal_2".into()),
                 Token::Comma,
-                Token::StringLiteral("deal_3"),
+                Token::StringLiteral("deal_3".into()),
                 Token::RightParenthesis,
                 Token::RightParenthesis,
             ]),
@@ -386,11 +780,11 @@ mod tests {
                 Token::Identifier("deal_ids"),
                 Token::OneOf,
                 Token::LeftParenthesis,
-                Token::StringLiteral("deal_1"),
+                Token::StringLiteral("deal_1".into()),
                 Token::Comma,
-                Token::StringLiteral("deal_2"),
+                Token::StringLiteral("deal_2".into()),
                 Token::Comma,
-                Token::StringLiteral("deal_3"),
+                Token::StringLiteral("deal_3".into()),
                 Token::RightParenthesis,
                 Token::RightParenthesis,
                 Token::RightParenthesis,
@@ -398,14 +792,14 @@ mod tests {
                 Token::LeftParenthesis,
                 Token::Identifier("continent"),
                 Token::NotEqual,
-                Token::StringLiteral("EU"),
+                Token::StringLiteral("EU".into()),
                 Token::And,
                 Token::Identifier("country"),
                 Token::NotIn,
                 Token::LeftParenthesis,
-                Token::StringLiteral("US"),
+                Token::StringLiteral("US".into()),
                 Token::Comma,
-                Token::StringLiteral("CA"),
+                Token::StringLiteral("CA".into()),
                 Token::RightParenthesis,
                 Token::RightParenthesis,
             ]),
@@ -49,11 +49,31 @@
 //! The following operators are supported:
 //!
 //! * Boolean operators: `and` (`&&`), `or` (`||`), `not` (`!`) and `variable` where `variable` is a defined attribute for the A-Tree;
+//! * Boolean literals: `true` and `false`. They are folded while parsing so `false and (...)` does not create any node;
 //! * Comparison: `<`, `<=`, `>`, `>=`. They work for `integer` and `float`;
-//! * Equality: `=` and `<>`. They work for `integer`, `float` and `string`;
+//! * Equality: `=` and `<>` (`!=`). They work for `integer`, `float` and `string`;
 //! * Null: `is null`, `is not null` (for variables), `is empty` and `is not empty` (for lists);
 //! * Set: `in` and `not in`. They work for list of `integer` or for list of `string`;
-//! * List: `one of`, `none of` and `all of`. They work for list of `integer` and list of `string`.
+//! * List: `one of`, `none of` and `all of`. They work for list of `integer` and list of `string`;
+//!   `intersect_at_least` also requires a minimum amount of values in common, e.g.
+//!   `segment_ids intersect_at_least [1, 2, 3, 4] count 2`;
+//! * Range: `between` and `not between`, with inclusive bounds, e.g. `age between 18 and 25`. They
+//!   work for `integer` and `float`;
+//! * Pattern: `starts with` (`starts_with`) and `contains`. They work for `string`, e.g.
+//!   `deal starts with "deal-"`, and are undefined for the events built before the first pattern on
+//!   the attribute was inserted.
+//! * Fuzzy: `~=` followed by an edit distance of at most 3, e.g. `brand ~= 'adidas' within 1`. It
+//!   works for `string` and is true when the text is at most that amount of character insertions,
+//!   deletions or substitutions away from the string; like the patterns, it is undefined for the
+//!   events built before the first of them on the attribute was inserted.
+//! * Recency: `within_last(attribute, duration)`. It works for `integer` holding a Unix timestamp in
+//!   seconds, e.g. `within_last(last_purchase, '7d')`, and is true when the timestamp is at most
+//!   the duration before the time of the event set with `EventBuilder::with_now()`. The durations
+//!   are made of amounts in seconds (`s`), minutes (`m`), hours (`h`), days (`d`) and weeks (`w`),
+//!   e.g. `'1h30m'`. No attribute is designated as the time of the event: any `integer` attribute
+//!   can be compared, and the "now" is carried by the event instead of being passed to the search.
+//!
+//! The lists can end with a trailing comma, e.g. `segment_ids one of [1, 2, 3,]`.
 //!
 //! As an example, the following would all be valid ABEs:
 //!
@@ -63,6 +83,20 @@
 //! (log_level = 'debug') and (month in [1, 2, 3] and day in [15, 16]) or (month in [4, 5, 6] and day in [10, 11])
 //! ```
 //!
+//! The integers can be written in hexadecimal (`0xFF`) and contain underscores (`1_000_000`); the
+//! floats can use the scientific notation (`1.5e6`). The strings support the `\"`, `\'`, `\\`,
+//! `\n`, `\r`, `\t`, `\0` and `\u{1F600}` escape sequences, while the raw strings such as
+//! `r"C:\deals"` or `r#"say "hi""#` are kept verbatim.
+//!
+//! The expressions can span multiple lines and contain `// ...` and `/* ... */` comments; a `\` at
+//! the end of a line is skipped as well:
+//!
+//! ```text
+//! // Only the private deals of the first exchange
+//! exchange_id = 1 /* the main exchange */ and \
+//!     private
+//! ```
+//!
 //! # Optimizations
 //!
 //! The A-Tree is a data structure that can efficiently search a large amount of arbitrary boolean
@@ -84,21 +118,117 @@
 //! * _Zero suppression filter_: Reduce the amount of nodes to evaluate by applying
 //!   De Morgan's laws and eliminating the NOT nodes;
 //! * _Propagation on demand_: Choose an access child for the AND operators and only
-//!   propagate the result if the access child is true.
+//!   propagate the result if the access child is true. The access child is the cheapest operand
+//!   by default, or the one written first with [`ConjunctionOrder::LeftToRight`].
+//!
+//! # Cargo features
+//!
+//! * `arrow`: Build events from the rows of an Apache Arrow record batch via
+//!   `ATree::events_from_record_batch()`.
+//! * `json`: Build events from flat JSON objects via `ATree::event_from_json()`, move the
+//!   subscriptions between trees with `ATree::export_json()`/`ATree::import_json()` and load
+//!   JSON Lines rules files via `ATree::load_from_path()`.
+//! * `metrics`: Collect per-search counters (nodes visited, predicates evaluated, short-circuits
+//!   and wall time) that are available via `Report::metrics()` as well as cumulative counters
+//!   for the whole tree that are available via `ATree::metrics()`.
+//! * `openrtb`: Build events from OpenRTB 2.x bid requests via `ATree::event_from_openrtb()` with
+//!   a configurable `OpenRtbMapping`. This implies the `json` feature.
+//! * `profiling`: Count how many times each node is evaluated and export these counts as folded
+//!   stacks via `ATree::profile_report()`, and optionally time the predicates to find the most
+//!   expensive ones via `ATree::predicate_timings()`.
+//! * `roaring`: Get the matches of the trees with integer subscription IDs as a
+//!   [Roaring bitmap](https://docs.rs/roaring) via `Report::matches_bitmap()`, to intersect them
+//!   with other sets of IDs cheaply.
+//! * `testutil`: Generate valid expressions and events for a schema with the
+//!   [`proptest`](https://docs.rs/proptest) strategies of the `testutil` module, to property-test
+//!   the integrations of the tree.
+//! * `tracing`: Emit [`tracing`](https://docs.rs/tracing) spans for insertions, deletions and
+//!   searches with the subscription ID, the amount of nodes and the search counters as fields.
+//!   This implies the `metrics` feature.
+#[cfg(feature = "arrow")]
+mod arrow;
 mod ast;
 mod atree;
+mod audit;
+mod baseline;
+#[cfg(feature = "roaring")]
+mod bitmap;
+mod cache;
+mod cluster;
+mod compilation;
+mod config;
 mod error;
 mod evaluation;
 mod events;
+mod executor;
+mod explain;
+mod expression;
+mod fuzzy;
+#[cfg(feature = "json")]
+mod interchange;
+#[cfg(feature = "json")]
+mod json;
 mod lexer;
+mod loader;
+mod metadata;
+mod metrics;
+mod minify;
+mod observer;
+#[cfg(feature = "openrtb")]
+mod openrtb;
 mod parser;
 mod predicates;
+#[cfg(feature = "profiling")]
+mod profiling;
+mod range;
+mod snapshot;
+mod stats;
 mod strings;
+mod template;
 #[cfg(test)]
 mod test_utils;
+#[cfg(feature = "testutil")]
+pub mod testutil;
 
 pub use crate::{
-    atree::{ATree, Report},
-    error::ATreeError,
-    events::{AttributeDefinition, Event, EventBuilder, EventError},
+    atree::{ATree, Report, SortedMatches},
+    audit::{AuditReport, OwnerAudit},
+    baseline::BaselineReport,
+    cache::ParseCacheStats,
+    cluster::{Cluster, ClusterReport},
+    config::{
+        ATreeConfig, Coercions, ConjunctionOrder, DeletePolicy, DuplicateIdPolicy, RoundingPolicy,
+        UnknownAttributePolicy,
+    },
+    error::{ATreeError, ParserError},
+    events::{
+        AttrValue, AttributeDefinition, AttributeKind, Event, EventBuilder, EventError,
+        TypeMismatch, ValidationReport,
+    },
+    executor::{Executor, Job},
+    explain::{Explanation, PredicateOutcome},
+    expression::{Expression, Literal, PredicateExpression, PredicateOperator},
+    loader::{FileFormat, LineError, LoadReport, Progress},
+    metadata::Metadata,
+    minify::minify_expression,
+    observer::TreeObserver,
+    snapshot::{SnapshotError, SnapshotId},
+    stats::TreeStats,
+    strings::InternedString,
+    template::Template,
 };
+
+#[cfg(feature = "arrow")]
+pub use crate::arrow::RecordBatchError;
+#[cfg(feature = "roaring")]
+pub use crate::bitmap::BitmapId;
+#[cfg(feature = "json")]
+pub use crate::json::JsonError;
+#[cfg(feature = "metrics")]
+pub use crate::metrics::{SearchMetrics, TreeMetrics};
+#[cfg(feature = "openrtb")]
+pub use crate::openrtb::OpenRtbMapping;
+#[cfg(feature = "profiling")]
+pub use crate::profiling::PredicateTiming;
+#[cfg(feature = "roaring")]
+pub use roaring;
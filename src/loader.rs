@@ -0,0 +1,686 @@
+use crate::{
+    ast::Node,
+    atree::ATree,
+    error::ATreeError,
+    executor::{Executor, Job},
+    metadata::Metadata,
+    parser::{self, ATreeParseError},
+    strings::{FrozenStringTable, LocalStringTable},
+};
+#[cfg(feature = "json")]
+use crate::{interchange, json};
+#[cfg(feature = "json")]
+use serde_json::Value;
+use std::{
+    fmt::Debug,
+    hash::Hash,
+    io::{self, BufRead},
+    num::NonZeroUsize,
+    str::FromStr,
+    thread,
+};
+use thiserror::Error;
+
+/// The amount of lines that are parsed in parallel before being inserted.
+pub(crate) const BATCH_SIZE: usize = 8192;
+
+/// The format of a rules file loaded with [`crate::ATree::load_from_path()`]
+///
+/// In both formats, the empty lines and the lines starting with `#` are skipped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FileFormat {
+    /// Comma-separated values with the `id,expression,tags,expiry` columns where the last two are
+    /// optional. The tags are separated by `;` and the expiry is a Unix timestamp in seconds (see
+    /// [`crate::Metadata`]). The fields can be quoted with `"`, in which case a `"` inside of them
+    /// is written `""`, and cannot span multiple lines. A first line whose first field is `id` is
+    /// treated as a header.
+    Csv,
+    /// One JSON object per line with the `id`, `expression`, `tags` and `expiry` fields of the
+    /// subscriptions of [`crate::ATree::export_json()`]. The `id` can be a string or a number.
+    #[cfg(feature = "json")]
+    JsonLines,
+}
+
+/// An error on a line of a rules file that was skipped by [`crate::ATree::load_from_path()`]
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum LineError {
+    #[error("line {line}: invalid record: {message}")]
+    Record { line: usize, message: String },
+    #[error("line {line}: invalid subscription ID {id:?}")]
+    Id { line: usize, id: String },
+    #[error("line {line}: {message}")]
+    Expression { line: usize, message: String },
+}
+
+/// The result of [`crate::ATree::load_from_path()`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoadReport {
+    loaded: usize,
+    errors: Vec<LineError>,
+}
+
+impl LoadReport {
+    /// The amount of subscriptions that were inserted.
+    #[inline]
+    pub const fn loaded(&self) -> usize {
+        self.loaded
+    }
+
+    /// The lines that were skipped, in the order they appear in the file.
+    #[inline]
+    pub fn errors(&self) -> &[LineError] {
+        &self.errors
+    }
+}
+
+/// The progress of a bulk insertion, reported after each phase of a batch by
+/// [`crate::ATree::insert_many_with_progress()`] and
+/// [`crate::ATree::load_from_path_with_progress()`]
+///
+/// The expressions of a batch are first parsed in parallel and then merged into the tree one
+/// after the other; the invalid expressions are counted in both phases.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    parsed: usize,
+    merged: usize,
+    total: Option<usize>,
+}
+
+impl Progress {
+    pub(crate) const fn new(total: Option<usize>) -> Self {
+        Self {
+            parsed: 0,
+            merged: 0,
+            total,
+        }
+    }
+
+    /// The amount of expressions that were parsed.
+    #[inline]
+    pub const fn parsed(&self) -> usize {
+        self.parsed
+    }
+
+    /// The amount of expressions that went through the merge into the tree.
+    #[inline]
+    pub const fn merged(&self) -> usize {
+        self.merged
+    }
+
+    /// The amount of expressions to insert, when it is known beforehand.
+    #[inline]
+    pub const fn total(&self) -> Option<usize> {
+        self.total
+    }
+
+    pub(crate) fn record_parsed(&mut self, count: usize) {
+        self.parsed += count;
+    }
+
+    pub(crate) fn record_merged(&mut self, count: usize) {
+        self.merged += count;
+    }
+}
+
+struct Record<T> {
+    line: usize,
+    id: T,
+    expression: String,
+    metadata: Metadata,
+}
+
+#[cfg(test)]
+fn load<T>(atree: &mut ATree<T>, reader: impl BufRead, format: FileFormat) -> io::Result<LoadReport>
+where
+    T: Eq + Hash + Clone + Debug + FromStr,
+{
+    load_with_progress(atree, reader, format, |_| {})
+}
+
+pub(crate) fn load_with_progress<T>(
+    atree: &mut ATree<T>,
+    reader: impl BufRead,
+    format: FileFormat,
+    mut on_progress: impl FnMut(Progress),
+) -> io::Result<LoadReport>
+where
+    T: Eq + Hash + Clone + Debug + FromStr,
+{
+    let mut report = LoadReport::default();
+    let mut progress = Progress::new(None);
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        match decode(format, index + 1, &line) {
+            Ok(Some(record)) => batch.push(record),
+            Ok(None) => {}
+            Err(error) => report.errors.push(error),
+        }
+        if batch.len() == BATCH_SIZE {
+            insert_batch(
+                atree,
+                &mut batch,
+                &mut report,
+                &mut progress,
+                &mut on_progress,
+            );
+        }
+    }
+    if !batch.is_empty() {
+        insert_batch(
+            atree,
+            &mut batch,
+            &mut report,
+            &mut progress,
+            &mut on_progress,
+        );
+    }
+    report.errors.sort_by_key(line_of);
+    Ok(report)
+}
+
+/// Parse the expressions of the batch in parallel and insert them in order.
+fn insert_batch<T>(
+    atree: &mut ATree<T>,
+    batch: &mut Vec<Record<T>>,
+    report: &mut LoadReport,
+    progress: &mut Progress,
+    on_progress: &mut impl FnMut(Progress),
+) where
+    T: Eq + Hash + Clone + Debug,
+{
+    let expressions: Vec<_> = batch
+        .iter()
+        .map(|record| record.expression.as_str())
+        .collect();
+    let threads = thread::available_parallelism().unwrap_or(NonZeroUsize::MIN);
+    let parsed: Vec<_> = parse_all(atree, &expressions, threads)
+        .into_iter()
+        .map(|parsed| parsed.map_err(|error| ATreeError::ParseError(error).to_string()))
+        .collect();
+    let size = batch.len();
+    progress.record_parsed(size);
+    on_progress(*progress);
+
+    for (record, parsed) in batch.drain(..).zip(parsed) {
+        let result = match parsed {
+            Ok(Some(ast)) => atree
+                .insert_ast(&record.id, &record.expression, ast, record.metadata)
+                .map_err(|error| error.to_string()),
+            Ok(None) => atree
+                .insert_with_metadata(&record.id, &record.expression, record.metadata)
+                .map_err(|error| error.to_string()),
+            Err(message) => {
+                atree.record_parse_failure();
+                Err(message)
+            }
+        };
+        match result {
+            Ok(()) => report.loaded += 1,
+            Err(message) => report.errors.push(LineError::Expression {
+                line: record.line,
+                message,
+            }),
+        }
+    }
+    progress.record_merged(size);
+    on_progress(*progress);
+}
+
+/// Parse the expressions on up to `threads` threads.
+///
+/// The expressions found in the compilation cache of the tree are decoded instead. The other
+/// expressions are first parsed with strings of their own and the strings of the ones that
+/// parsed are interned, so that the parsers can then share the strings of the tree without
+/// modifying them; the expressions that do not parse leave the strings of the tree untouched.
+/// `None` is returned for the expressions that still need strings missing from the tree; they
+/// have to be parsed again with the tree itself.
+pub(crate) fn parse_all<'a, T>(
+    atree: &mut ATree<T>,
+    expressions: &[&'a str],
+    threads: NonZeroUsize,
+) -> Vec<Result<Option<Node>, ATreeParseError<'a>>>
+where
+    T: Eq + Hash + Clone + Debug,
+{
+    let compiled: Vec<_> = expressions
+        .iter()
+        .map(|expression| atree.compiled(expression))
+        .collect();
+    if compiled.iter().any(Option::is_some) {
+        let misses: Vec<_> = expressions
+            .iter()
+            .zip(&compiled)
+            .filter(|(_, ast)| ast.is_none())
+            .map(|(expression, _)| *expression)
+            .collect();
+        let mut parsed = parse_uncompiled(atree, &misses, threads).into_iter();
+        return compiled
+            .into_iter()
+            .map(|ast| match ast {
+                Some(ast) => Ok(Some(ast)),
+                None => parsed.next().unwrap_or(Ok(None)),
+            })
+            .collect();
+    }
+    parse_uncompiled(atree, expressions, threads)
+}
+
+fn parse_uncompiled<'a, T>(
+    atree: &mut ATree<T>,
+    expressions: &[&'a str],
+    threads: NonZeroUsize,
+) -> Vec<Result<Option<Node>, ATreeParseError<'a>>>
+where
+    T: Eq + Hash + Clone + Debug,
+{
+    let checked = {
+        let (attributes, strings) = atree.parsing_tables();
+        parallel_map(expressions, threads, atree.executor(), |expression| {
+            let mut local = LocalStringTable::default();
+            parser::parse(expression, attributes, &mut local).map(|_| {
+                local
+                    .values()
+                    .filter(|value| strings.find(value).is_none())
+                    .map(str::to_owned)
+                    .collect::<Vec<_>>()
+            })
+        })
+    };
+    let mut parsable = Vec::with_capacity(expressions.len());
+    for (expression, checked) in expressions.iter().zip(&checked) {
+        if let Ok(values) = checked {
+            for value in values {
+                atree.intern(value);
+            }
+            parsable.push(*expression);
+        }
+    }
+
+    let (attributes, strings) = atree.parsing_tables();
+    let mut parsed = parallel_map(&parsable, threads, atree.executor(), |expression| {
+        let mut strings = FrozenStringTable::new(strings);
+        match parser::parse(expression, attributes, &mut strings) {
+            Ok(_) if strings.take_missing() => Ok(None),
+            Ok(ast) => Ok(Some(ast)),
+            Err(error) => Err(error),
+        }
+    })
+    .into_iter();
+    checked
+        .into_iter()
+        .map(|checked| match checked {
+            Ok(_) => parsed.next().unwrap_or(Ok(None)),
+            Err(error) => Err(error),
+        })
+        .collect()
+}
+
+fn parallel_map<'a, O, F>(
+    expressions: &[&'a str],
+    threads: NonZeroUsize,
+    executor: Option<&dyn Executor>,
+    f: F,
+) -> Vec<O>
+where
+    O: Send,
+    F: Fn(&'a str) -> O + Sync,
+{
+    parallel_chunks(expressions, threads, executor, |chunk| {
+        chunk
+            .iter()
+            .map(|expression| f(expression))
+            .collect::<Vec<_>>()
+    })
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Split the items in up to `threads` chunks and process each chunk on its own thread, or as a
+/// job of the executor of the tree; the results are returned in the order of the chunks.
+pub(crate) fn parallel_chunks<I, O, F>(
+    items: &[I],
+    threads: NonZeroUsize,
+    executor: Option<&dyn Executor>,
+    f: F,
+) -> Vec<O>
+where
+    I: Sync,
+    O: Send,
+    F: Fn(&[I]) -> O + Sync,
+{
+    let chunk_size = items.len().div_ceil(threads.get()).max(1);
+    if items.len() <= chunk_size {
+        return vec![f(items)];
+    }
+    if let Some(executor) = executor {
+        let f = &f;
+        let mut outputs: Vec<Option<O>> = items.chunks(chunk_size).map(|_| None).collect();
+        let jobs = items
+            .chunks(chunk_size)
+            .zip(&mut outputs)
+            .map(|(chunk, output)| Box::new(move || *output = Some(f(chunk))) as Job<'_>)
+            .collect();
+        executor.execute(jobs);
+        return outputs
+            .into_iter()
+            .map(|output| output.expect("a job of the executor panicked or was not run"))
+            .collect();
+    }
+    thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(|| f(chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("a worker thread panicked"))
+            .collect()
+    })
+}
+
+fn decode<T: FromStr>(
+    format: FileFormat,
+    line: usize,
+    content: &str,
+) -> Result<Option<Record<T>>, LineError> {
+    let content = content.trim();
+    if content.is_empty() || content.starts_with('#') {
+        return Ok(None);
+    }
+
+    match format {
+        FileFormat::Csv => decode_csv(line, content),
+        #[cfg(feature = "json")]
+        FileFormat::JsonLines => decode_json(line, content).map(Some),
+    }
+}
+
+fn decode_csv<T: FromStr>(line: usize, content: &str) -> Result<Option<Record<T>>, LineError> {
+    let record_error = |message: &str| LineError::Record {
+        line,
+        message: message.to_owned(),
+    };
+    let fields = split_csv(content).map_err(|message| record_error(&message))?;
+    let (id, expression, tags, expiry) = match fields.as_slice() {
+        [id, ..] if line == 1 && id.trim() == "id" => return Ok(None),
+        [id, expression] => (id, expression, None, None),
+        [id, expression, tags] => (id, expression, Some(tags), None),
+        [id, expression, tags, expiry] => (id, expression, Some(tags), Some(expiry)),
+        _ => return Err(record_error("expected between 2 and 4 fields")),
+    };
+
+    let id = parse_id(line, id.trim())?;
+    let mut metadata = Metadata::new();
+    for tag in tags.iter().flat_map(|tags| tags.split(';')) {
+        let tag = tag.trim();
+        if !tag.is_empty() {
+            metadata = metadata.with_tag(tag);
+        }
+    }
+    if let Some(expiry) = expiry.map(|expiry| expiry.trim()) {
+        if !expiry.is_empty() {
+            let expiry = expiry
+                .parse()
+                .map_err(|_| record_error(&format!("invalid expiry {expiry:?}")))?;
+            metadata = metadata.with_expiry(expiry);
+        }
+    }
+
+    Ok(Some(Record {
+        line,
+        id,
+        expression: expression.clone(),
+        metadata,
+    }))
+}
+
+/// Split a line of comma-separated values, unquoting the quoted fields.
+fn split_csv(content: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut characters = content.chars().peekable();
+    let mut quoted = false;
+    while let Some(character) = characters.next() {
+        match (quoted, character) {
+            (true, '"') if characters.peek() == Some(&'"') => {
+                characters.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (false, '"') if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            (false, ',') => fields.push(std::mem::take(&mut field)),
+            (_, character) => field.push(character),
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted field".to_owned());
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+#[cfg(feature = "json")]
+fn decode_json<T: FromStr>(line: usize, content: &str) -> Result<Record<T>, LineError> {
+    let record_error = |message: String| LineError::Record { line, message };
+    let value: Value =
+        serde_json::from_str(content).map_err(|error| record_error(error.to_string()))?;
+    let Value::Object(entry) = value else {
+        return Err(record_error(format!(
+            "expected a JSON object, found {}",
+            json::type_name(&value)
+        )));
+    };
+
+    let id = match entry.get("id") {
+        Some(Value::String(id)) => parse_id(line, id)?,
+        Some(Value::Number(id)) => parse_id(line, &id.to_string())?,
+        Some(id) => {
+            return Err(LineError::Id {
+                line,
+                id: id.to_string(),
+            })
+        }
+        None => return Err(record_error("missing \"id\"".to_owned())),
+    };
+    let expression = entry
+        .get("expression")
+        .and_then(Value::as_str)
+        .ok_or_else(|| record_error("missing \"expression\"".to_owned()))?;
+    let metadata = interchange::metadata(&entry["id"], &entry)
+        .map_err(|error| record_error(error.to_string()))?;
+
+    Ok(Record {
+        line,
+        id,
+        expression: expression.to_owned(),
+        metadata,
+    })
+}
+
+#[inline]
+fn parse_id<T: FromStr>(line: usize, id: &str) -> Result<T, LineError> {
+    id.parse().map_err(|_| LineError::Id {
+        line,
+        id: id.to_owned(),
+    })
+}
+
+#[inline]
+fn line_of(error: &LineError) -> usize {
+    match error {
+        LineError::Record { line, .. }
+        | LineError::Id { line, .. }
+        | LineError::Expression { line, .. } => *line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::AttributeDefinition;
+    use std::io::Cursor;
+
+    #[test]
+    fn can_load_a_csv_file() {
+        let mut atree = build_atree();
+        let content = concat!(
+            "id,expression,tags,expiry\n",
+            "1,private,campaign-1;campaign-2,10\n",
+            "\n",
+            "# a comment\n",
+            "2,\"country in [\"\"CA\"\", \"\"US\"\"] and not private\"\n",
+        );
+
+        let report = load(&mut atree, Cursor::new(content), FileFormat::Csv).unwrap();
+
+        assert_eq!(2, report.loaded());
+        assert!(report.errors().is_empty());
+        assert_eq!(
+            Some(r#"country in ["CA", "US"] and not private"#),
+            atree.expression(&2)
+        );
+        let metadata = Metadata::new()
+            .with_tag("campaign-1")
+            .with_tag("campaign-2")
+            .with_expiry(10);
+        assert_eq!(Some(&metadata), atree.metadata(&1));
+        let mut builder = atree.make_event();
+        builder.with_boolean("private", false).unwrap();
+        builder.with_string("country", "US").unwrap();
+        let event = builder.build().unwrap();
+        assert_eq!(vec![&2u64], atree.search(&event).unwrap().matches());
+    }
+
+    #[test]
+    fn report_the_progress_of_the_load() {
+        let mut atree = build_atree();
+        let content = "1,private\n2,private =\n3,not private\n";
+        let mut reported = vec![];
+
+        load_with_progress(
+            &mut atree,
+            Cursor::new(content),
+            FileFormat::Csv,
+            |progress| reported.push(progress),
+        )
+        .unwrap();
+
+        let last = reported.last().unwrap();
+        assert_eq!(2, reported.len());
+        assert_eq!((3, 3, None), (last.parsed(), last.merged(), last.total()));
+    }
+
+    #[test]
+    fn report_the_invalid_lines_without_aborting() {
+        let mut atree = build_atree();
+        let content = concat!(
+            "1,private\n",
+            "a,private\n",
+            "3,private =\n",
+            "4\n",
+            "5,\"private\n",
+            "6,not private,,soon\n",
+            "7,country = \"CA\"\n",
+        );
+
+        let report = load(&mut atree, Cursor::new(content), FileFormat::Csv).unwrap();
+
+        assert_eq!(2, report.loaded());
+        let lines: Vec<_> = report.errors().iter().map(line_of).collect();
+        assert_eq!(vec![2, 3, 4, 5, 6], lines);
+        assert_eq!(
+            LineError::Id {
+                line: 2,
+                id: "a".to_owned()
+            },
+            report.errors()[0]
+        );
+        assert!(matches!(
+            report.errors()[1],
+            LineError::Expression { line: 3, .. }
+        ));
+    }
+
+    #[test]
+    fn keep_the_strings_of_the_invalid_lines_out_of_the_tree() {
+        let mut atree = build_atree();
+        let content = "1,country = \"CA\"\n2,country = \"US\" and\n";
+
+        let report = load(&mut atree, Cursor::new(content), FileFormat::Csv).unwrap();
+
+        assert_eq!(1, report.loaded());
+        assert_eq!(1, atree.stats().strings());
+    }
+
+    #[test]
+    fn can_load_more_lines_than_a_batch() {
+        let mut atree = build_atree();
+        let content: String = (0..BATCH_SIZE + 10)
+            .map(|id| format!("{id},country = \"country-{}\"\n", id % 100))
+            .collect();
+
+        let report = load(&mut atree, Cursor::new(content), FileFormat::Csv).unwrap();
+
+        assert_eq!(BATCH_SIZE + 10, report.loaded());
+        let mut builder = atree.make_event();
+        builder.with_string("country", "country-42").unwrap();
+        let event = builder.build().unwrap();
+        assert_eq!(
+            (0..BATCH_SIZE + 10).filter(|id| id % 100 == 42).count(),
+            atree.search(&event).unwrap().matches().len()
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn can_load_a_json_lines_file() {
+        let mut atree = build_atree();
+        let content = concat!(
+            "{\"id\": 1, \"expression\": \"private\", \"tags\": [\"campaign-1\"]}\n",
+            "{\"id\": \"2\", \"expression\": \"not private\", \"expiry\": 10}\n",
+            "{\"id\": true, \"expression\": \"private\"}\n",
+            "[]\n",
+        );
+
+        let report = load(&mut atree, Cursor::new(content), FileFormat::JsonLines).unwrap();
+
+        assert_eq!(2, report.loaded());
+        assert_eq!(
+            Some(&Metadata::new().with_tag("campaign-1")),
+            atree.metadata(&1)
+        );
+        assert_eq!(Some(&Metadata::new().with_expiry(10)), atree.metadata(&2));
+        assert_eq!(
+            LineError::Id {
+                line: 3,
+                id: "true".to_owned()
+            },
+            report.errors()[0]
+        );
+        assert!(matches!(
+            report.errors()[1],
+            LineError::Record { line: 4, .. }
+        ));
+    }
+
+    #[test]
+    fn can_split_the_quoted_fields() {
+        assert_eq!(
+            Ok(vec!["1".to_owned(), "a, \"b\"".to_owned(), String::new()]),
+            split_csv(r#"1,"a, ""b""","#)
+        );
+    }
+
+    fn build_atree() -> ATree<u64> {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::string("country"),
+        ];
+        ATree::new(&definitions).unwrap()
+    }
+}
@@ -0,0 +1,169 @@
+use crate::stats::vec_bytes;
+
+/// Extra information kept by an [`crate::ATree`] along with an arbitrary boolean expression
+///
+/// The tree does not interpret the metadata while searching; it only keeps it so that it can be
+/// queried and exported with the expression.
+///
+/// # Examples
+///
+/// ```rust
+/// use a_tree::{ATree, AttributeDefinition, Metadata};
+///
+/// let definitions = [AttributeDefinition::boolean("private")];
+/// let mut atree = ATree::new(&definitions).unwrap();
+/// let metadata = Metadata::new()
+///     .with_tag("campaign-1")
+///     .with_expiry(1_767_225_600);
+/// atree.insert_with_metadata(&1u64, "private", metadata).unwrap();
+///
+/// let metadata = atree.metadata(&1u64).unwrap();
+/// assert_eq!(["campaign-1"], metadata.tags());
+/// assert_eq!(Some(1_767_225_600), metadata.expiry());
+/// ```
+///
+/// The owner, the author and the creation time of an expression can be recorded for the audits;
+/// see [`crate::ATree::audit_report()`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metadata {
+    tags: Vec<String>,
+    expiry: Option<u64>,
+    owner: Option<String>,
+    author: Option<String>,
+    created_at: Option<u64>,
+}
+
+impl Metadata {
+    /// Create metadata without tags nor expiry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a tag; adding the same tag twice keeps a single copy.
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        if !self.has_tag(tag) {
+            self.tags.push(tag.to_owned());
+        }
+        self
+    }
+
+    /// Set the moment after which the expression should be considered expired, as a Unix
+    /// timestamp in seconds.
+    pub const fn with_expiry(mut self, expiry: u64) -> Self {
+        self.expiry = Some(expiry);
+        self
+    }
+
+    /// Set the team or the service that owns the expression.
+    pub fn with_owner(mut self, owner: &str) -> Self {
+        self.owner = Some(owner.to_owned());
+        self
+    }
+
+    /// Set the person or the tool that wrote the expression.
+    pub fn with_author(mut self, author: &str) -> Self {
+        self.author = Some(author.to_owned());
+        self
+    }
+
+    /// Set the moment the expression was created, as a Unix timestamp in seconds.
+    pub const fn with_created_at(mut self, created_at: u64) -> Self {
+        self.created_at = Some(created_at);
+        self
+    }
+
+    /// The tags in the order they were added.
+    #[inline]
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Whether the specified tag was added.
+    #[inline]
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|other| other == tag)
+    }
+
+    /// The expiry as a Unix timestamp in seconds, if any.
+    #[inline]
+    pub const fn expiry(&self) -> Option<u64> {
+        self.expiry
+    }
+
+    /// Whether the expression is expired at the specified moment, as a Unix timestamp in seconds.
+    #[inline]
+    pub const fn is_expired(&self, now: u64) -> bool {
+        matches!(self.expiry, Some(expiry) if expiry < now)
+    }
+
+    /// The owner of the expression, if any.
+    #[inline]
+    pub fn owner(&self) -> Option<&str> {
+        self.owner.as_deref()
+    }
+
+    /// The author of the expression, if any.
+    #[inline]
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    /// The creation time as a Unix timestamp in seconds, if any.
+    #[inline]
+    pub const fn created_at(&self) -> Option<u64> {
+        self.created_at
+    }
+}
+
+/// An arbitrary boolean expression as it was inserted, along with its metadata.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Subscription {
+    pub expression: Box<str>,
+    pub metadata: Metadata,
+    /// The expressions inserted before `expression` with the same ID under
+    /// [`crate::DuplicateIdPolicy::Allow`], oldest first
+    pub shared: Vec<Box<str>>,
+}
+
+impl Subscription {
+    /// Every expression of the ID, oldest first.
+    pub(crate) fn expressions(&self) -> impl Iterator<Item = &str> {
+        self.shared
+            .iter()
+            .chain([&self.expression])
+            .map(AsRef::as_ref)
+    }
+
+    /// The heap bytes used by the expressions and the metadata.
+    pub(crate) fn heap_bytes(&self) -> usize {
+        let metadata = &self.metadata;
+        self.expression.len()
+            + vec_bytes(&self.shared)
+            + self
+                .shared
+                .iter()
+                .map(|expression| expression.len())
+                .sum::<usize>()
+            + vec_bytes(&metadata.tags)
+            + metadata.tags.iter().map(String::capacity).sum::<usize>()
+            + metadata.owner.as_ref().map_or(0, String::capacity)
+            + metadata.author.as_ref().map_or(0, String::capacity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keep_a_single_copy_of_each_tag() {
+        let metadata = Metadata::new()
+            .with_tag("campaign-1")
+            .with_tag("campaign-2")
+            .with_tag("campaign-1");
+
+        assert_eq!(["campaign-1", "campaign-2"], metadata.tags());
+        assert!(metadata.has_tag("campaign-2"));
+        assert!(!metadata.has_tag("campaign-3"));
+    }
+}
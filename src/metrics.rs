@@ -0,0 +1,350 @@
+#[cfg(feature = "metrics")]
+use std::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Counters collected while running a single [`crate::ATree::search()`]
+///
+/// This is only available when the `metrics` feature is enabled.
+#[cfg(feature = "metrics")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchMetrics {
+    nodes_visited: u64,
+    predicates_evaluated: u64,
+    short_circuits: u64,
+    elapsed: Duration,
+}
+
+#[cfg(feature = "metrics")]
+impl SearchMetrics {
+    /// The amount of nodes (predicates included) whose result had to be computed.
+    #[inline]
+    pub const fn nodes_visited(&self) -> u64 {
+        self.nodes_visited
+    }
+
+    /// The amount of predicates that were evaluated against the event.
+    #[inline]
+    pub const fn predicates_evaluated(&self) -> u64 {
+        self.predicates_evaluated
+    }
+
+    /// The amount of times a node result was decided without evaluating all of its children.
+    #[inline]
+    pub const fn short_circuits(&self) -> u64 {
+        self.short_circuits
+    }
+
+    /// The wall time spent in the search.
+    #[inline]
+    pub const fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+/// Cumulative counters collected since the creation of an [`crate::ATree`]
+///
+/// This is only available when the `metrics` feature is enabled.
+#[cfg(feature = "metrics")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TreeMetrics {
+    searches: u64,
+    matches: u64,
+    inserts: u64,
+    parse_failures: u64,
+    nodes_visited: u64,
+}
+
+#[cfg(feature = "metrics")]
+impl TreeMetrics {
+    /// The amount of searches that were run.
+    #[inline]
+    pub const fn searches(&self) -> u64 {
+        self.searches
+    }
+
+    /// The amount of matches returned by all the searches.
+    #[inline]
+    pub const fn matches(&self) -> u64 {
+        self.matches
+    }
+
+    /// The amount of arbitrary boolean expressions that were successfully inserted.
+    #[inline]
+    pub const fn inserts(&self) -> u64 {
+        self.inserts
+    }
+
+    /// The amount of arbitrary boolean expressions that could not be parsed.
+    #[inline]
+    pub const fn parse_failures(&self) -> u64 {
+        self.parse_failures
+    }
+
+    /// The amount of nodes visited by all the searches.
+    #[inline]
+    pub const fn nodes_visited(&self) -> u64 {
+        self.nodes_visited
+    }
+
+    /// The average amount of nodes visited per search or `0.0` if there were no searches.
+    #[inline]
+    pub fn average_nodes_visited(&self) -> f64 {
+        if self.searches == 0 {
+            0.0
+        } else {
+            self.nodes_visited as f64 / self.searches as f64
+        }
+    }
+
+    /// Format the counters with the Prometheus text exposition format.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use a_tree::{ATree, AttributeDefinition};
+    ///
+    /// let definitions = [AttributeDefinition::boolean("private")];
+    /// let mut atree = ATree::new(&definitions).unwrap();
+    /// atree.insert(&1u64, "private").unwrap();
+    ///
+    /// let text = atree.metrics().to_prometheus();
+    /// assert!(text.contains("atree_inserts_total 1\n"));
+    /// ```
+    pub fn to_prometheus(&self) -> String {
+        let counters = [
+            ("atree_searches_total", "Amount of searches", self.searches),
+            ("atree_matches_total", "Amount of matches", self.matches),
+            (
+                "atree_inserts_total",
+                "Amount of inserted expressions",
+                self.inserts,
+            ),
+            (
+                "atree_parse_failures_total",
+                "Amount of expressions that could not be parsed",
+                self.parse_failures,
+            ),
+            (
+                "atree_nodes_visited_total",
+                "Amount of nodes visited while searching",
+                self.nodes_visited,
+            ),
+        ];
+        let mut output = String::new();
+        for (name, help, value) in counters {
+            let _ = writeln!(output, "# HELP {name} {help}");
+            let _ = writeln!(output, "# TYPE {name} counter");
+            let _ = writeln!(output, "{name} {value}");
+        }
+        output
+    }
+}
+
+/// The atomic counters shared by all the searches of an [`crate::ATree`].
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    searches: AtomicU64,
+    matches: AtomicU64,
+    inserts: AtomicU64,
+    parse_failures: AtomicU64,
+    nodes_visited: AtomicU64,
+}
+
+#[cfg(feature = "metrics")]
+impl Counters {
+    #[inline]
+    pub fn record_search(&self, matches: usize, metrics: &SearchMetrics) {
+        self.searches.fetch_add(1, Ordering::Relaxed);
+        self.matches.fetch_add(matches as u64, Ordering::Relaxed);
+        self.nodes_visited
+            .fetch_add(metrics.nodes_visited, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn record_insert(&self) {
+        self.inserts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn record_parse_failure(&self) {
+        self.parse_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> TreeMetrics {
+        TreeMetrics {
+            searches: self.searches.load(Ordering::Relaxed),
+            matches: self.matches.load(Ordering::Relaxed),
+            inserts: self.inserts.load(Ordering::Relaxed),
+            parse_failures: self.parse_failures.load(Ordering::Relaxed),
+            nodes_visited: self.nodes_visited.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl Clone for Counters {
+    fn clone(&self) -> Self {
+        let snapshot = self.snapshot();
+        Self {
+            searches: AtomicU64::new(snapshot.searches),
+            matches: AtomicU64::new(snapshot.matches),
+            inserts: AtomicU64::new(snapshot.inserts),
+            parse_failures: AtomicU64::new(snapshot.parse_failures),
+            nodes_visited: AtomicU64::new(snapshot.nodes_visited),
+        }
+    }
+}
+
+/// Accumulates the [`SearchMetrics`] during a search.
+///
+/// When the `metrics` feature is disabled, this is a zero-sized type and all of its methods are
+/// no-ops so that the search does not pay for them.
+#[derive(Debug)]
+pub(crate) struct Recorder {
+    #[cfg(feature = "metrics")]
+    metrics: SearchMetrics,
+    #[cfg(feature = "metrics")]
+    start: Instant,
+    #[cfg(feature = "profiling")]
+    time_predicates: bool,
+}
+
+impl Recorder {
+    #[inline]
+    pub fn start() -> Self {
+        Self {
+            #[cfg(feature = "metrics")]
+            metrics: SearchMetrics::default(),
+            #[cfg(feature = "metrics")]
+            start: Instant::now(),
+            #[cfg(feature = "profiling")]
+            time_predicates: false,
+        }
+    }
+
+    /// Time each evaluation of a predicate; see [`crate::ATree::set_predicate_timing()`].
+    #[cfg(feature = "profiling")]
+    #[inline]
+    pub const fn with_predicate_timing(mut self, enabled: bool) -> Self {
+        self.time_predicates = enabled;
+        self
+    }
+
+    #[cfg(feature = "profiling")]
+    #[inline]
+    pub const fn times_predicates(&self) -> bool {
+        self.time_predicates
+    }
+
+    #[inline]
+    pub fn visit_node(&mut self) {
+        #[cfg(feature = "metrics")]
+        {
+            self.metrics.nodes_visited += 1;
+        }
+    }
+
+    #[inline]
+    pub fn evaluate_predicate(&mut self) {
+        #[cfg(feature = "metrics")]
+        {
+            self.metrics.nodes_visited += 1;
+            self.metrics.predicates_evaluated += 1;
+        }
+    }
+
+    #[inline]
+    pub fn short_circuit(&mut self) {
+        #[cfg(feature = "metrics")]
+        {
+            self.metrics.short_circuits += 1;
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    #[inline]
+    pub fn finish(self) -> SearchMetrics {
+        SearchMetrics {
+            elapsed: self.start.elapsed(),
+            ..self.metrics
+        }
+    }
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn start_with_empty_counters() {
+        let metrics = Recorder::start().finish();
+
+        assert_eq!(0, metrics.nodes_visited());
+        assert_eq!(0, metrics.predicates_evaluated());
+        assert_eq!(0, metrics.short_circuits());
+    }
+
+    #[test]
+    fn count_evaluated_predicates_as_visited_nodes() {
+        let mut recorder = Recorder::start();
+
+        recorder.evaluate_predicate();
+        recorder.visit_node();
+        let metrics = recorder.finish();
+
+        assert_eq!(2, metrics.nodes_visited());
+        assert_eq!(1, metrics.predicates_evaluated());
+    }
+
+    #[test]
+    fn can_count_short_circuits() {
+        let mut recorder = Recorder::start();
+
+        recorder.short_circuit();
+        recorder.short_circuit();
+
+        assert_eq!(2, recorder.finish().short_circuits());
+    }
+
+    #[test]
+    fn can_accumulate_the_tree_counters() {
+        let counters = Counters::default();
+        let mut recorder = Recorder::start();
+        recorder.evaluate_predicate();
+        recorder.visit_node();
+        recorder.visit_node();
+        let metrics = recorder.finish();
+
+        counters.record_insert();
+        counters.record_parse_failure();
+        counters.record_search(2, &metrics);
+        counters.record_search(0, &SearchMetrics::default());
+        let snapshot = counters.snapshot();
+
+        assert_eq!(2, snapshot.searches());
+        assert_eq!(2, snapshot.matches());
+        assert_eq!(1, snapshot.inserts());
+        assert_eq!(1, snapshot.parse_failures());
+        assert_eq!(1.5, snapshot.average_nodes_visited());
+    }
+
+    #[test]
+    fn return_a_zero_average_when_there_were_no_searches() {
+        assert_eq!(0.0, TreeMetrics::default().average_nodes_visited());
+    }
+
+    #[test]
+    fn can_format_the_counters_with_the_prometheus_format() {
+        let counters = Counters::default();
+        counters.record_insert();
+
+        let text = counters.snapshot().to_prometheus();
+
+        assert!(text.contains("# TYPE atree_inserts_total counter\natree_inserts_total 1\n"));
+        assert!(text.contains("atree_searches_total 0\n"));
+    }
+}
@@ -0,0 +1,569 @@
+use crate::{
+    error::{ATreeError, ParserError},
+    lexer::{Lexer, Token},
+    parser::ATreeParseError,
+};
+use lalrpop_util::ParseError;
+use std::{borrow::Cow, iter::Peekable};
+
+/// Rewrite an arbitrary boolean expression to the shortest equivalent string.
+///
+/// The comments, the redundant spaces and parentheses are removed, the operators are replaced by
+/// their shortest alias and the lists are sorted and deduplicated. The minified expression parses
+/// to the same tree as the original one, which makes it suitable to persist a large amount of
+/// expressions.
+///
+/// Since the expression is only checked syntactically, the attributes do not need to be defined.
+///
+/// # Examples
+///
+/// ```rust
+/// use a_tree::minify_expression;
+///
+/// let minified = minify_expression(
+///     r#"(exchange_id = 1 and (private)) or not (deals one of ["b", "a", "b"])"#,
+/// )
+/// .unwrap();
+/// assert_eq!(r#"exchange_id=1&&private||!deals one of["a","b"]"#, minified);
+/// ```
+pub fn minify_expression(expression: &str) -> Result<String, ATreeError<'_>> {
+    let mut tokens = Tokens {
+        lexer: Lexer::new(expression).peekable(),
+        end: expression.len(),
+    };
+    let syntax = expression_syntax(&mut tokens).map_err(ATreeError::ParseError)?;
+    if let Some(token) = tokens.lexer.next() {
+        return Err(ATreeError::ParseError(unexpected(token, &[])));
+    }
+    let mut minified = String::with_capacity(expression.len());
+    syntax.write(&mut minified);
+    Ok(minified)
+}
+
+struct Tokens<'a> {
+    lexer: Peekable<Lexer<'a>>,
+    /// The position reported when the expression ends too early.
+    end: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn peek(&mut self) -> Option<&Token<'a>> {
+        match self.lexer.peek() {
+            Some(Ok((_, token, _))) => Some(token),
+            _ => None,
+        }
+    }
+
+    fn skip(&mut self) {
+        self.lexer.next();
+    }
+}
+
+/// The structure of an expression, without any knowledge of its attributes.
+enum Syntax<'a> {
+    And(Box<Syntax<'a>>, Box<Syntax<'a>>),
+    Or(Box<Syntax<'a>>, Box<Syntax<'a>>),
+    Not(Box<Syntax<'a>>),
+    /// A predicate, a variable or a boolean literal in its minified form.
+    Atom(Vec<Cow<'a, str>>),
+}
+
+impl Syntax<'_> {
+    fn write(&self, output: &mut String) {
+        match self {
+            // `and` and `or` share the same precedence and are left associative so only a
+            // binary right operand needs parentheses.
+            Self::And(left, right) => Self::write_binary(output, left, "&&", right),
+            Self::Or(left, right) => Self::write_binary(output, left, "||", right),
+            Self::Not(operand) => {
+                push(output, "!");
+                operand.write_operand(output);
+            }
+            Self::Atom(pieces) => {
+                for piece in pieces {
+                    push(output, piece);
+                }
+            }
+        }
+    }
+
+    fn write_binary(output: &mut String, left: &Self, operator: &str, right: &Self) {
+        left.write(output);
+        push(output, operator);
+        right.write_operand(output);
+    }
+
+    fn write_operand(&self, output: &mut String) {
+        if matches!(self, Self::And(_, _) | Self::Or(_, _)) {
+            push(output, "(");
+            self.write(output);
+            push(output, ")");
+        } else {
+            self.write(output);
+        }
+    }
+}
+
+/// Append a piece of the minified expression, separating it from the previous one by a space
+/// only when both would otherwise be read as a single token.
+fn push(output: &mut String, piece: &str) {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.');
+    let needs_space = output.chars().next_back().is_some_and(is_word)
+        && piece
+            .chars()
+            .next()
+            .is_some_and(|c| is_word(c) || matches!(c, '"' | '\''));
+    if needs_space {
+        output.push(' ');
+    }
+    output.push_str(piece);
+}
+
+fn expression_syntax<'a>(tokens: &mut Tokens<'a>) -> Result<Syntax<'a>, ATreeParseError<'a>> {
+    let mut left = unary_syntax(tokens)?;
+    loop {
+        let is_and = match tokens.peek() {
+            Some(Token::And) => true,
+            Some(Token::Or) => false,
+            _ => return Ok(left),
+        };
+        tokens.skip();
+        let right = Box::new(unary_syntax(tokens)?);
+        left = if is_and {
+            Syntax::And(Box::new(left), right)
+        } else {
+            Syntax::Or(Box::new(left), right)
+        };
+    }
+}
+
+fn unary_syntax<'a>(tokens: &mut Tokens<'a>) -> Result<Syntax<'a>, ATreeParseError<'a>> {
+    let (start, token, end) = next(tokens, &["\"not\"", "\"(\"", "\"identifier\""])?;
+    match token {
+        Token::Not => Ok(Syntax::Not(Box::new(unary_syntax(tokens)?))),
+        Token::LeftParenthesis => {
+            let expression = expression_syntax(tokens)?;
+            expect(tokens, &Token::RightParenthesis, "\")\"")?;
+            Ok(expression)
+        }
+        Token::BooleanLiteral(value) => Ok(Syntax::Atom(vec![value.to_string().into()])),
+        Token::Identifier(name) => identifier_syntax(tokens, name),
+        Token::WithinLast => {
+            expect(tokens, &Token::LeftParenthesis, "\"(\"")?;
+            let (start, token, end) = next(tokens, &["\"identifier\""])?;
+            let Token::Identifier(name) = token else {
+                return Err(unexpected(Ok((start, token, end)), &["\"identifier\""]));
+            };
+            expect(tokens, &Token::Comma, "\",\"")?;
+            let (start, token, end) = next(tokens, &["\"string\""])?;
+            if !matches!(token, Token::StringLiteral(_)) {
+                return Err(unexpected(Ok((start, token, end)), &["\"string\""]));
+            }
+            let duration = literal(&token);
+            expect(tokens, &Token::RightParenthesis, "\")\"")?;
+            Ok(Syntax::Atom(vec![
+                "within_last(".into(),
+                name.into(),
+                ",".into(),
+                duration,
+                ")".into(),
+            ]))
+        }
+        token @ (Token::IntegerLiteral(_) | Token::FloatLiteral(_) | Token::StringLiteral(_)) => {
+            let value = literal(&token);
+            let (start, operator, end) = next(tokens, &["\"=\"", "\"<>\"", "\"<\""])?;
+            let Some(operator) = comparison_operator(&operator) else {
+                return Err(unexpected(Ok((start, operator, end)), &["\"=\""]));
+            };
+            let (start, token, end) = next(tokens, &["\"identifier\""])?;
+            let Token::Identifier(name) = token else {
+                return Err(unexpected(Ok((start, token, end)), &["\"identifier\""]));
+            };
+            Ok(Syntax::Atom(vec![value, operator.into(), name.into()]))
+        }
+        token => Err(unexpected(
+            Ok((start, token, end)),
+            &["\"not\"", "\"(\"", "\"identifier\""],
+        )),
+    }
+}
+
+fn identifier_syntax<'a>(
+    tokens: &mut Tokens<'a>,
+    name: &'a str,
+) -> Result<Syntax<'a>, ATreeParseError<'a>> {
+    let mut atom = vec![Cow::Borrowed(name)];
+    let Some(token) = tokens.peek() else {
+        return Ok(Syntax::Atom(atom));
+    };
+    if let Some(operator) = comparison_operator(token) {
+        tokens.skip();
+        atom.push(operator.into());
+        let (start, token, end) = next(tokens, &["\"integer\"", "\"float\"", "\"string\""])?;
+        if !matches!(
+            token,
+            Token::IntegerLiteral(_) | Token::FloatLiteral(_) | Token::StringLiteral(_)
+        ) {
+            return Err(unexpected(Ok((start, token, end)), &["\"integer\""]));
+        }
+        atom.push(literal(&token));
+    } else if let Some(operator) = null_operator(token) {
+        tokens.skip();
+        atom.push(operator.into());
+    } else if let Some(operator) = list_operator(token) {
+        tokens.skip();
+        atom.push(operator.into());
+        atom.push(list(tokens)?.into());
+    } else if token == &Token::FuzzyEqual {
+        tokens.skip();
+        atom.push("~=".into());
+        let (start, token, end) = next(tokens, &["\"string\""])?;
+        if !matches!(token, Token::StringLiteral(_)) {
+            return Err(unexpected(Ok((start, token, end)), &["\"string\""]));
+        }
+        atom.push(literal(&token));
+        let (start, token, end) = next(tokens, &["\"within\""])?;
+        if token != Token::Identifier("within") {
+            return Err(unexpected(Ok((start, token, end)), &["\"within\""]));
+        }
+        atom.push("within".into());
+        let (start, token, end) = next(tokens, &["\"integer\""])?;
+        if !matches!(token, Token::IntegerLiteral(_)) {
+            return Err(unexpected(Ok((start, token, end)), &["\"integer\""]));
+        }
+        atom.push(literal(&token));
+    } else if token == &Token::IntersectAtLeast {
+        tokens.skip();
+        atom.push("intersect_at_least".into());
+        atom.push(list(tokens)?.into());
+        let (start, token, end) = next(tokens, &["\"count\""])?;
+        if token != Token::Identifier("count") {
+            return Err(unexpected(Ok((start, token, end)), &["\"count\""]));
+        }
+        atom.push("count".into());
+        let (start, token, end) = next(tokens, &["\"integer\""])?;
+        if !matches!(token, Token::IntegerLiteral(_)) {
+            return Err(unexpected(Ok((start, token, end)), &["\"integer\""]));
+        }
+        atom.push(literal(&token));
+    } else if let Some(operator) = pattern_operator(token) {
+        tokens.skip();
+        atom.push(operator.into());
+        let (start, token, end) = next(tokens, &["\"string\""])?;
+        if !matches!(token, Token::StringLiteral(_)) {
+            return Err(unexpected(Ok((start, token, end)), &["\"string\""]));
+        }
+        atom.push(literal(&token));
+    } else if let Some(operator) = range_operator(token) {
+        tokens.skip();
+        atom.push(operator.into());
+        atom.push(number(tokens)?);
+        expect(tokens, &Token::And, "\"and\"")?;
+        atom.push("&&".into());
+        atom.push(number(tokens)?);
+    }
+    Ok(Syntax::Atom(atom))
+}
+
+/// Read a bound of a range.
+fn number<'a>(tokens: &mut Tokens<'a>) -> Result<Cow<'a, str>, ATreeParseError<'a>> {
+    let (start, token, end) = next(tokens, &["\"integer\"", "\"float\""])?;
+    if !matches!(token, Token::IntegerLiteral(_) | Token::FloatLiteral(_)) {
+        return Err(unexpected(
+            Ok((start, token, end)),
+            &["\"integer\"", "\"float\""],
+        ));
+    }
+    Ok(literal(&token))
+}
+
+/// Read a list literal and return it sorted and deduplicated.
+fn list<'a>(tokens: &mut Tokens<'a>) -> Result<String, ATreeParseError<'a>> {
+    let (start, token, end) = next(tokens, &["\"[\"", "\"(\""])?;
+    let closing = match token {
+        Token::LeftSquareBracket => Token::RightSquareBracket,
+        Token::LeftParenthesis => Token::RightParenthesis,
+        token => return Err(unexpected(Ok((start, token, end)), &["\"[\"", "\"(\""])),
+    };
+
+    let mut integers = Vec::new();
+    let mut strings = Vec::new();
+    loop {
+        let (start, token, end) = next(tokens, &["\"integer\"", "\"string\""])?;
+        match token {
+            Token::IntegerLiteral(value) if strings.is_empty() => integers.push(value),
+            Token::StringLiteral(value) if integers.is_empty() => strings.push(value),
+            token if token == closing && !(integers.is_empty() && strings.is_empty()) => break,
+            token => return Err(unexpected(Ok((start, token, end)), &["\"integer\""])),
+        }
+        let (start, token, end) = next(tokens, &["\",\""])?;
+        match token {
+            Token::Comma => {}
+            token if token == closing => break,
+            token => return Err(unexpected(Ok((start, token, end)), &["\",\""])),
+        }
+    }
+
+    integers.sort_unstable();
+    integers.dedup();
+    strings.sort_unstable();
+    strings.dedup();
+    let values: Vec<_> = if strings.is_empty() {
+        integers.iter().map(ToString::to_string).collect()
+    } else {
+        strings.iter().map(|value| quote(value)).collect()
+    };
+    Ok(format!("[{}]", values.join(",")))
+}
+
+fn literal<'a>(token: &Token<'a>) -> Cow<'a, str> {
+    match token {
+        Token::IntegerLiteral(value) => value.to_string().into(),
+        Token::FloatLiteral(value) => {
+            // Keep the decimal point so that the literal is not read back as an integer.
+            let mut value = value.normalize().to_string();
+            if !value.contains('.') {
+                value.push('.');
+            }
+            value.into()
+        }
+        Token::StringLiteral(value) => quote(value).into(),
+        _ => unreachable!("only the literals can be minified"),
+    }
+}
+
+/// Quote a string, preferring the quotes that require the least escape sequences.
+pub(crate) fn quote(value: &str) -> String {
+    let quote = if value.contains('"') && !value.contains('\'') {
+        '\''
+    } else {
+        '"'
+    };
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push(quote);
+    for c in value.chars() {
+        if c == quote || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push(quote);
+    quoted
+}
+
+const fn comparison_operator(token: &Token) -> Option<&'static str> {
+    match token {
+        Token::LessThan => Some("<"),
+        Token::LessThanEqual => Some("<="),
+        Token::GreaterThan => Some(">"),
+        Token::GreaterThanEqual => Some(">="),
+        Token::Equal => Some("="),
+        Token::NotEqual => Some("<>"),
+        _ => None,
+    }
+}
+
+const fn null_operator(token: &Token) -> Option<&'static str> {
+    match token {
+        Token::IsNull => Some("is null"),
+        Token::IsNotNull => Some("is not null"),
+        Token::IsEmpty => Some("is empty"),
+        Token::IsNotEmpty => Some("is not empty"),
+        _ => None,
+    }
+}
+
+const fn pattern_operator(token: &Token) -> Option<&'static str> {
+    match token {
+        Token::StartsWith => Some("starts with"),
+        Token::Contains => Some("contains"),
+        _ => None,
+    }
+}
+
+const fn range_operator(token: &Token) -> Option<&'static str> {
+    match token {
+        Token::Between => Some("between"),
+        Token::NotBetween => Some("not between"),
+        _ => None,
+    }
+}
+
+const fn list_operator(token: &Token) -> Option<&'static str> {
+    match token {
+        Token::In => Some("in"),
+        Token::NotIn => Some("not in"),
+        Token::OneOf => Some("one of"),
+        Token::NoneOf => Some("none of"),
+        Token::AllOf => Some("all of"),
+        _ => None,
+    }
+}
+
+type Spanned<'a> = (usize, Token<'a>, usize);
+
+fn next<'a>(
+    tokens: &mut Tokens<'a>,
+    expected: &[&str],
+) -> Result<Spanned<'a>, ATreeParseError<'a>> {
+    match tokens.lexer.next() {
+        Some(Ok(token)) => Ok(token),
+        Some(Err(error)) => Err(ParseError::User { error }),
+        None => Err(ParseError::UnrecognizedEof {
+            location: tokens.end,
+            expected: expected.iter().map(|token| (*token).to_owned()).collect(),
+        }),
+    }
+}
+
+fn expect<'a>(
+    tokens: &mut Tokens<'a>,
+    expected: &Token,
+    name: &str,
+) -> Result<(), ATreeParseError<'a>> {
+    let (start, token, end) = next(tokens, &[name])?;
+    if &token == expected {
+        Ok(())
+    } else {
+        Err(unexpected(Ok((start, token, end)), &[name]))
+    }
+}
+
+fn unexpected<'a>(
+    token: Result<Spanned<'a>, ParserError>,
+    expected: &[&str],
+) -> ATreeParseError<'a> {
+    match token {
+        Ok(token) => ParseError::UnrecognizedToken {
+            token,
+            expected: expected.iter().map(|token| (*token).to_owned()).collect(),
+        },
+        Err(error) => ParseError::User { error },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::{AttributeDefinition, AttributeTable},
+        parser,
+        strings::StringTable,
+    };
+
+    #[test]
+    fn remove_the_redundant_spaces_and_parentheses() {
+        assert_eq!(
+            "a&&b||c&&!d",
+            minify_expression("((a and (b)) or c) and not (d)").unwrap()
+        );
+        assert_eq!("a&&(b||c)", minify_expression("a and (b or c)").unwrap());
+        assert_eq!("!(a||b)", minify_expression("not (a or b)").unwrap());
+    }
+
+    #[test]
+    fn keep_the_spaces_between_words() {
+        assert_eq!(
+            "segments none of[1,2]&&country is not null&&price>-1",
+            minify_expression("segments none of (2, 1) and country is not null and price > -1")
+                .unwrap()
+        );
+        assert_eq!("-1<price", minify_expression("-1 < price").unwrap());
+        assert_eq!(
+            r#"deal starts with "deal-""#,
+            minify_expression("deal starts_with 'deal-'").unwrap()
+        );
+        assert_eq!(
+            r#"url contains "/sports/""#,
+            minify_expression("url contains '/sports/'").unwrap()
+        );
+        assert_eq!(
+            "age not between 18&&25.5",
+            minify_expression("age not between 18 and 25.50").unwrap()
+        );
+        assert_eq!(
+            r#"!within_last(seen_at,"7d")"#,
+            minify_expression("not within_last( seen_at , '7d' )").unwrap()
+        );
+        assert_eq!(
+            r#"brand~="adidas"within 1"#,
+            minify_expression("brand ~= 'adidas' within 1").unwrap()
+        );
+    }
+
+    #[test]
+    fn sort_and_deduplicate_the_lists() {
+        assert_eq!(
+            r#"deals one of["a","b"]&&ids in[-1,3]"#,
+            minify_expression(r#"deals one of ['b', "a", 'b',] and ids in [3, -1, 3]"#).unwrap()
+        );
+        assert_eq!(
+            "segments intersect_at_least[1,2,4]count 2",
+            minify_expression("segments intersect_at_least [4, 2, 1, 2] count 2").unwrap()
+        );
+    }
+
+    #[test]
+    fn normalize_the_literals() {
+        assert_eq!(
+            r#"id=255&&price>=1.5&&floor<2.&&name='say "hi"'&&path="C:\\d""#,
+            minify_expression(
+                r##"id = 0xFF and price >= 1.50 and floor < 2.0 and name = r#"say "hi""# and path = r"C:\d""##
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn remove_the_comments() {
+        assert_eq!(
+            "private&&a",
+            minify_expression("// private only\nprivate /* and a */ and \\\n a").unwrap()
+        );
+    }
+
+    #[test]
+    fn return_an_error_on_an_invalid_expression() {
+        assert!(minify_expression("").is_err());
+        assert!(minify_expression("a and").is_err());
+        assert!(minify_expression("(a or b").is_err());
+        assert!(minify_expression("a b").is_err());
+        assert!(minify_expression("ids in []").is_err());
+        assert!(minify_expression("ids in [1, 'a']").is_err());
+        assert!(minify_expression("1 = 2").is_err());
+    }
+
+    #[test]
+    fn reparse_to_the_same_tree() {
+        let attributes = AttributeTable::new(&[
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::float("bidfloor"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::integer_list("segments"),
+        ])
+        .unwrap();
+        let mut strings = StringTable::new();
+        let expressions = [
+            r#"exchange_id = 1 and not private or deals one of ["deal-2", "deal-1"]"#,
+            "private or (exchange_id <> 5 and (bidfloor < 1.0 or segments all of [3, 2, 1]))",
+            "not (not private and country is null) and false or 2.5 >= bidfloor",
+            r#"country in ('US', "CA", "US") and !(segments is empty || private)"#,
+            "true and (exchange_id > 1_000 or exchange_id < -0x10)",
+            "private and not within_last(exchange_id, '1h30m')",
+            "not segments intersect_at_least (3, 1, 2) count 2 or private",
+            "country ~= 'Canada' within 2 and private",
+        ];
+
+        for expression in expressions {
+            let minified = minify_expression(expression).unwrap();
+
+            assert_eq!(
+                parser::parse(expression, &attributes, &mut strings).unwrap(),
+                parser::parse(&minified, &attributes, &mut strings).unwrap(),
+                "{expression} minified to {minified}"
+            );
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{atree::Report, events::AttributeKind};
+use std::{fmt, sync::Arc};
+
+/// Hooks that are called by an [`crate::ATree`] when it is modified or searched.
+///
+/// All the methods do nothing by default so that implementors only have to override the ones
+/// they are interested in.
+///
+/// # Examples
+///
+/// ```rust
+/// use a_tree::{ATree, AttributeDefinition, Report, TreeObserver};
+/// use std::sync::{
+///     atomic::{AtomicUsize, Ordering},
+///     Arc,
+/// };
+///
+/// #[derive(Default)]
+/// struct SearchCounter(AtomicUsize);
+///
+/// impl TreeObserver<u64> for SearchCounter {
+///     fn on_search_complete(&self, _report: &Report<'_, u64>) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let definitions = [AttributeDefinition::boolean("private")];
+/// let mut atree = ATree::new(&definitions).unwrap();
+/// let counter = Arc::new(SearchCounter::default());
+/// atree.register_observer(counter.clone());
+///
+/// let event = atree.make_event().build().unwrap();
+/// atree.search(&event).unwrap();
+/// assert_eq!(1, counter.0.load(Ordering::Relaxed));
+/// ```
+pub trait TreeObserver<T>: Send + Sync {
+    /// Called after an arbitrary boolean expression was successfully inserted.
+    fn on_insert(&self, _subscription_id: &T, _expression: &str) {}
+
+    /// Called after an arbitrary boolean expression was deleted.
+    fn on_delete(&self, _subscription_id: &T) {}
+
+    /// Called once a search is done with the [`Report`] that will be returned to the caller.
+    fn on_search_complete(&self, _report: &Report<'_, T>) {}
+
+    /// Called when an [`crate::EventBuilder`] skips an attribute that is not defined because the
+    /// tree is configured with [`crate::UnknownAttributePolicy::Warn`].
+    fn on_unknown_attribute(&self, _name: &str) {}
+
+    /// Called when an [`crate::EventBuilder`] converts a value into the type of its attribute
+    /// because the tree is configured with [`crate::ATreeConfig::with_coercions()`].
+    fn on_coercion(&self, _name: &str, _from: &AttributeKind, _to: &AttributeKind) {}
+}
+
+/// The warnings raised by the [`crate::EventBuilder`]s of a tree.
+///
+/// This is not generic over the subscription IDs so that the builders do not have to be.
+pub(crate) trait EventWarnings: Sync + fmt::Debug {
+    fn on_unknown_attribute(&self, name: &str);
+
+    fn on_coercion(&self, name: &str, from: &AttributeKind, to: &AttributeKind);
+}
+
+/// The observers registered on an [`crate::ATree`].
+pub(crate) struct Observers<T>(Vec<Arc<dyn TreeObserver<T>>>);
+
+impl<T> Observers<T> {
+    #[inline]
+    pub const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    #[inline]
+    pub fn register(&mut self, observer: Arc<dyn TreeObserver<T>>) {
+        self.0.push(observer);
+    }
+
+    #[inline]
+    pub fn on_insert(&self, subscription_id: &T, expression: &str) {
+        for observer in &self.0 {
+            observer.on_insert(subscription_id, expression);
+        }
+    }
+
+    #[inline]
+    pub fn on_delete(&self, subscription_id: &T) {
+        for observer in &self.0 {
+            observer.on_delete(subscription_id);
+        }
+    }
+
+    #[inline]
+    pub fn on_search_complete(&self, report: &Report<'_, T>) {
+        for observer in &self.0 {
+            observer.on_search_complete(report);
+        }
+    }
+}
+
+impl<T> EventWarnings for Observers<T> {
+    fn on_unknown_attribute(&self, name: &str) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(attribute = name, "skipping an unknown event attribute");
+        for observer in &self.0 {
+            observer.on_unknown_attribute(name);
+        }
+    }
+
+    fn on_coercion(&self, name: &str, from: &AttributeKind, to: &AttributeKind) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(attribute = name, ?from, ?to, "coercing an event attribute");
+        for observer in &self.0 {
+            observer.on_coercion(name, from, to);
+        }
+    }
+}
+
+impl<T> Clone for Observers<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> fmt::Debug for Observers<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Observers")
+            .field("count", &self.0.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder {
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl TreeObserver<u64> for Recorder {
+        fn on_insert(&self, subscription_id: &u64, expression: &str) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("insert {subscription_id} {expression}"));
+        }
+
+        fn on_delete(&self, subscription_id: &u64) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("delete {subscription_id}"));
+        }
+    }
+
+    struct Silent;
+
+    impl TreeObserver<u64> for Silent {}
+
+    #[test]
+    fn notify_all_the_registered_observers() {
+        let first = Arc::new(Recorder::default());
+        let second = Arc::new(Recorder::default());
+        let mut observers = Observers::new();
+        observers.register(first.clone());
+        observers.register(second.clone());
+
+        observers.on_insert(&1, "private");
+        observers.on_delete(&1);
+
+        let expected = vec!["insert 1 private".to_string(), "delete 1".to_string()];
+        assert_eq!(expected, *first.calls.lock().unwrap());
+        assert_eq!(expected, *second.calls.lock().unwrap());
+    }
+
+    #[test]
+    fn do_nothing_by_default() {
+        let mut observers = Observers::new();
+        observers.register(Arc::new(Silent));
+
+        observers.on_insert(&1, "private");
+        observers.on_delete(&1);
+        observers.on_unknown_attribute("private");
+    }
+
+    #[test]
+    fn share_the_observers_between_clones() {
+        let recorder = Arc::new(Recorder::default());
+        let mut observers = Observers::new();
+        observers.register(recorder.clone());
+
+        observers.clone().on_delete(&1);
+
+        assert_eq!(
+            vec!["delete 1".to_string()],
+            *recorder.calls.lock().unwrap()
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use crate::{
+    events::{AttributeKind, Event, EventBuilder},
+    json::{self, JsonError},
+};
+use serde_json::Value;
+
+/// A table that maps the fields of an OpenRTB 2.x bid request onto the attributes of an
+/// [`crate::ATree`]
+///
+/// Each attribute is associated to one or more paths inside the bid request. A path is made of
+/// the object keys separated by `.` where each segment can also be an array index (e.g.
+/// `imp.0.bidfloor`) or `*` to go through all the elements of an array (e.g.
+/// `imp.*.pmp.deals.*.id`). A path that goes through an array with `*` produces a list.
+///
+/// When multiple paths are associated to the same attribute, the first one that is present in the
+/// bid request is used. If none are present, the attribute is left `undefined`.
+///
+/// # Examples
+///
+/// ```rust
+/// use a_tree::{ATree, AttributeDefinition, OpenRtbMapping};
+///
+/// let definitions = [
+///     AttributeDefinition::string("country"),
+///     AttributeDefinition::string_list("deal_ids"),
+///     AttributeDefinition::integer("exchange_id"),
+/// ];
+/// let mut atree = ATree::new(&definitions).unwrap();
+/// atree.insert(&1u64, r#"country = "CAN" and deal_ids one of ["deal-1"]"#).unwrap();
+///
+/// let mapping = OpenRtbMapping::standard().with_field("exchange_id", "ext.exchange_id");
+/// let bid_request = r#"{
+///     "id": "1",
+///     "imp": [{"id": "1", "pmp": {"deals": [{"id": "deal-1"}, {"id": "deal-2"}]}}],
+///     "device": {"geo": {"country": "CAN"}},
+///     "ext": {"exchange_id": 5}
+/// }"#;
+/// let event = atree.event_from_openrtb(bid_request, &mapping).unwrap();
+///
+/// assert_eq!(vec![&1u64], atree.search(&event).unwrap().matches());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpenRtbMapping {
+    fields: Vec<(String, Vec<String>)>,
+}
+
+impl OpenRtbMapping {
+    /// Create an empty mapping.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a mapping for the commonly used fields of the bid requests:
+    ///
+    /// | Attribute            | Paths                                      |
+    /// |----------------------|--------------------------------------------|
+    /// | `country`            | `device.geo.country`, `user.geo.country`   |
+    /// | `region`             | `device.geo.region`, `user.geo.region`     |
+    /// | `city`               | `device.geo.city`, `user.geo.city`         |
+    /// | `os`                 | `device.os`                                |
+    /// | `device_type`        | `device.devicetype`                        |
+    /// | `bundle`             | `app.bundle`                               |
+    /// | `domain`             | `site.domain`, `app.domain`                |
+    /// | `publisher_id`       | `site.publisher.id`, `app.publisher.id`    |
+    /// | `tag_ids`            | `imp.*.tagid`                              |
+    /// | `deal_ids`           | `imp.*.pmp.deals.*.id`                     |
+    /// | `private`            | `imp.0.pmp.private_auction`                |
+    /// | `bidfloor`           | `imp.0.bidfloor`                           |
+    /// | `auction_type`       | `at`                                       |
+    /// | `test`               | `test`                                     |
+    /// | `blocked_categories` | `bcat`                                     |
+    /// | `coppa`              | `regs.coppa`                               |
+    ///
+    /// The attributes that are not defined in the [`crate::ATree`] are skipped so this mapping
+    /// can be used with any schema.
+    pub fn standard() -> Self {
+        Self::new()
+            .with_field("country", "device.geo.country")
+            .with_field("country", "user.geo.country")
+            .with_field("region", "device.geo.region")
+            .with_field("region", "user.geo.region")
+            .with_field("city", "device.geo.city")
+            .with_field("city", "user.geo.city")
+            .with_field("os", "device.os")
+            .with_field("device_type", "device.devicetype")
+            .with_field("bundle", "app.bundle")
+            .with_field("domain", "site.domain")
+            .with_field("domain", "app.domain")
+            .with_field("publisher_id", "site.publisher.id")
+            .with_field("publisher_id", "app.publisher.id")
+            .with_field("tag_ids", "imp.*.tagid")
+            .with_field("deal_ids", "imp.*.pmp.deals.*.id")
+            .with_field("private", "imp.0.pmp.private_auction")
+            .with_field("bidfloor", "imp.0.bidfloor")
+            .with_field("auction_type", "at")
+            .with_field("test", "test")
+            .with_field("blocked_categories", "bcat")
+            .with_field("coppa", "regs.coppa")
+    }
+
+    /// Associate the path of a bid request field to an attribute.
+    ///
+    /// If the attribute already has some paths, this one is tried after them.
+    pub fn with_field(mut self, attribute: &str, path: &str) -> Self {
+        let path = path.to_owned();
+        match self.fields.iter_mut().find(|(name, _)| name == attribute) {
+            Some((_, paths)) => paths.push(path),
+            None => self.fields.push((attribute.to_owned(), vec![path])),
+        }
+        self
+    }
+
+    pub(crate) fn to_event(
+        &self,
+        mut builder: EventBuilder<'_>,
+        bid_request: &str,
+    ) -> Result<Event, JsonError> {
+        let document: Value = serde_json::from_str(bid_request)
+            .map_err(|error| JsonError::Syntax(error.to_string()))?;
+        if !document.is_object() {
+            return Err(JsonError::NotAnObject(json::type_name(&document)));
+        }
+
+        for (name, paths) in &self.fields {
+            let Ok(expected) = builder.kind_of(name) else {
+                continue;
+            };
+            let Some(value) = paths.iter().find_map(|path| lookup(&document, path)) else {
+                continue;
+            };
+            let value = coerce_flag(&expected, value);
+            json::set_value(&mut builder, name, &expected, &value)?;
+        }
+
+        builder.build().map_err(JsonError::Event)
+    }
+}
+
+/// OpenRTB represents its flags as `0` or `1` so they are converted for the boolean attributes.
+fn coerce_flag(expected: &AttributeKind, value: Value) -> Value {
+    match (expected, &value) {
+        (AttributeKind::Boolean, Value::Number(number)) => match number.as_u64() {
+            Some(0) => Value::Bool(false),
+            Some(1) => Value::Bool(true),
+            _ => value,
+        },
+        _ => value,
+    }
+}
+
+fn lookup(document: &Value, path: &str) -> Option<Value> {
+    let segments: Vec<_> = path.split('.').collect();
+    let mut values = Vec::new();
+    collect(document, &segments, &mut values);
+    if segments.contains(&"*") {
+        (!values.is_empty()).then(|| Value::Array(values.into_iter().cloned().collect()))
+    } else {
+        values.pop().cloned()
+    }
+}
+
+fn collect<'a>(value: &'a Value, segments: &[&str], values: &mut Vec<&'a Value>) {
+    let Some((segment, rest)) = segments.split_first() else {
+        values.push(value);
+        return;
+    };
+
+    match (value, *segment) {
+        (Value::Array(elements), "*") => {
+            for element in elements {
+                collect(element, rest, values);
+            }
+        }
+        (Value::Array(elements), index) => {
+            if let Some(element) = index.parse().ok().and_then(|i: usize| elements.get(i)) {
+                collect(element, rest, values);
+            }
+        }
+        (Value::Object(fields), key) => {
+            if let Some(field) = fields.get(key) {
+                collect(field, rest, values);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{events::AttributeDefinition, ATree};
+
+    const A_BID_REQUEST: &str = r#"{
+        "id": "request-1",
+        "at": 1,
+        "test": 0,
+        "bcat": ["IAB25", "IAB26"],
+        "imp": [
+            {
+                "id": "1",
+                "tagid": "tag-1",
+                "bidfloor": 1.25,
+                "pmp": {
+                    "private_auction": 1,
+                    "deals": [{"id": "deal-1"}, {"id": "deal-2"}]
+                }
+            },
+            {
+                "id": "2",
+                "tagid": "tag-2",
+                "pmp": {"deals": [{"id": "deal-3"}]}
+            }
+        ],
+        "app": {"bundle": "com.example", "publisher": {"id": "publisher-1"}},
+        "device": {"os": "iOS", "devicetype": 4, "geo": {"country": "CAN", "city": "Montreal"}},
+        "user": {"geo": {"country": "USA", "region": "QC"}}
+    }"#;
+
+    #[test]
+    fn can_map_the_standard_fields() {
+        let mut atree = build_atree();
+        atree
+            .insert(
+                &1u64,
+                r#"country = "CAN" and region = "QC" and private and not test and device_type = 4"#,
+            )
+            .unwrap();
+        atree
+            .insert(
+                &2u64,
+                r#"deal_ids one of ["deal-3"] and tag_ids one of ["tag-2"]"#,
+            )
+            .unwrap();
+        atree
+            .insert(
+                &3u64,
+                r#"bidfloor > 1.0 and auction_type = 1 and blocked_categories one of ["IAB25"]"#,
+            )
+            .unwrap();
+        atree
+            .insert(&4u64, r#"publisher_id = "publisher-1" and domain is null"#)
+            .unwrap();
+
+        let event = atree
+            .event_from_openrtb(A_BID_REQUEST, &OpenRtbMapping::standard())
+            .unwrap();
+        let mut matches = atree.search(&event).unwrap().matches().to_vec();
+        matches.sort();
+
+        assert_eq!(vec![&1u64, &2, &3, &4], matches);
+    }
+
+    #[test]
+    fn use_the_first_path_that_is_present() {
+        let mut atree = build_atree();
+        atree.insert(&1u64, r#"country = "USA""#).unwrap();
+        let mapping = OpenRtbMapping::new()
+            .with_field("country", "site.geo.country")
+            .with_field("country", "user.geo.country");
+
+        let event = atree.event_from_openrtb(A_BID_REQUEST, &mapping).unwrap();
+
+        assert_eq!(vec![&1u64], atree.search(&event).unwrap().matches());
+    }
+
+    #[test]
+    fn return_an_error_when_a_field_cannot_be_coerced() {
+        let atree = build_atree();
+        let mapping = OpenRtbMapping::new().with_field("country", "device.devicetype");
+
+        let result = atree.event_from_openrtb(A_BID_REQUEST, &mapping);
+
+        assert_eq!(
+            Err(JsonError::Coercion {
+                name: "country".to_string(),
+                expected: AttributeKind::String,
+                found: "number 4".to_string()
+            }),
+            result.map(|_| ())
+        );
+    }
+
+    #[test]
+    fn return_an_error_when_the_bid_request_is_not_an_object() {
+        let atree = build_atree();
+
+        let result = atree.event_from_openrtb("[]", &OpenRtbMapping::standard());
+
+        assert_eq!(Err(JsonError::NotAnObject("array")), result.map(|_| ()));
+    }
+
+    #[test]
+    fn can_look_up_array_indexes_and_wildcards() {
+        let document: Value = serde_json::from_str(A_BID_REQUEST).unwrap();
+
+        assert_eq!(Some(Value::from("tag-2")), lookup(&document, "imp.1.tagid"));
+        assert_eq!(
+            Some(Value::from(vec!["deal-1", "deal-2", "deal-3"])),
+            lookup(&document, "imp.*.pmp.deals.*.id")
+        );
+        assert_eq!(None, lookup(&document, "imp.*.missing"));
+        assert_eq!(None, lookup(&document, "imp.5.tagid"));
+    }
+
+    fn build_atree() -> ATree<u64> {
+        let definitions = [
+            AttributeDefinition::string("country"),
+            AttributeDefinition::string("region"),
+            AttributeDefinition::integer("device_type"),
+            AttributeDefinition::string("domain"),
+            AttributeDefinition::string("publisher_id"),
+            AttributeDefinition::string_list("tag_ids"),
+            AttributeDefinition::string_list("deal_ids"),
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::float("bidfloor"),
+            AttributeDefinition::integer("auction_type"),
+            AttributeDefinition::boolean("test"),
+            AttributeDefinition::string_list("blocked_categories"),
+        ];
+        ATree::new(&definitions).unwrap()
+    }
+}
@@ -3,7 +3,7 @@ use crate::{
     error::ParserError,
     events::AttributeTable,
     lexer::{Lexer, Token},
-    strings::StringTable,
+    strings::Interner,
 };
 use lalrpop_util::{lalrpop_mod, ParseError};
 
@@ -14,10 +14,29 @@ use self::grammar::TreeParser;
 pub type ATreeParseError<'a> = ParseError<usize, Token<'a>, ParserError>;
 
 #[inline]
-pub fn parse<'a>(
+pub fn parse<'a, S: Interner>(
     input: &'a str,
     attributes: &AttributeTable,
-    strings: &mut StringTable,
+    strings: &mut S,
+) -> Result<Node, ATreeParseError<'a>> {
+    let ast = parse_template(input, attributes, strings)?;
+    let mut placeholders = vec![];
+    ast.placeholders(&mut placeholders);
+    match placeholders.first() {
+        Some(location) => Err(ParseError::UnrecognizedToken {
+            token: (*location, Token::Placeholder, location + 1),
+            expected: vec![],
+        }),
+        None => Ok(ast),
+    }
+}
+
+/// Parse an expression whose values can be left as `?` placeholders; see [`crate::Template`].
+#[inline]
+pub fn parse_template<'a, S: Interner>(
+    input: &'a str,
+    attributes: &AttributeTable,
+    strings: &mut S,
 ) -> Result<Node, ATreeParseError<'a>> {
     let lexer = Lexer::new(input);
     TreeParser::new().parse(attributes, strings, lexer)
@@ -33,6 +52,7 @@ mod tests {
             ComparisonOperator, ComparisonValue, EqualityOperator, ListLiteral, ListOperator,
             NullOperator, Predicate, PredicateKind, PrimitiveLiteral, SetOperator,
         },
+        strings::StringTable,
         test_utils::{
             ast::{and, not, or, value},
             predicates::{
@@ -64,6 +84,42 @@ mod tests {
         assert!(parsed.is_err());
     }
 
+    #[test]
+    fn can_parse_an_annotated_multi_line_expression() {
+        let mut strings = StringTable::new();
+        let attributes = define_attributes();
+
+        let parsed = parse(
+            "// the private deals\nprivate /* only */ and \\\n    price < 15",
+            &attributes,
+            &mut strings,
+        );
+
+        assert_eq!(
+            Ok(and!(
+                value!(variable!(&attributes, "private")),
+                value!(less_than!(&attributes, "price", comparison_integer!(15)))
+            )),
+            parsed
+        );
+    }
+
+    #[test]
+    fn report_the_position_in_the_original_input_after_a_comment() {
+        let mut strings = StringTable::new();
+        let attributes = define_attributes();
+
+        let parsed = parse("/* note */ price < )", &attributes, &mut strings);
+
+        assert!(matches!(
+            parsed,
+            Err(ParseError::UnrecognizedToken {
+                token: (19, Token::RightParenthesis, 20),
+                ..
+            })
+        ));
+    }
+
     #[test]
     fn can_parse_less_than_expression_with_left_identifier() {
         let mut strings = StringTable::new();
@@ -308,6 +364,61 @@ mod tests {
         assert_eq!(Ok(value!(is_not_empty!(&attributes, "deals"))), parsed);
     }
 
+    #[test]
+    fn can_parse_c_style_operators() {
+        let mut strings = StringTable::new();
+        let attributes = define_attributes();
+
+        let parsed = parse("!private && exchange_id != 1", &attributes, &mut strings);
+
+        assert_eq!(
+            parse(
+                "not private and exchange_id <> 1",
+                &attributes,
+                &mut strings
+            ),
+            parsed
+        );
+    }
+
+    #[test]
+    fn can_parse_the_boolean_literals() {
+        let mut strings = StringTable::new();
+        let attributes = define_attributes();
+
+        assert_eq!(
+            Ok(Node::Constant(false)),
+            parse(
+                "false and (private or price < 15)",
+                &attributes,
+                &mut strings
+            )
+        );
+        assert_eq!(
+            Ok(Node::Constant(true)),
+            parse("not (false or !true)", &attributes, &mut strings)
+        );
+        assert_eq!(
+            Ok(value!(variable!(&attributes, "private"))),
+            parse("true && (private || false)", &attributes, &mut strings)
+        );
+    }
+
+    #[test]
+    fn can_parse_a_list_with_a_trailing_comma() {
+        let mut strings = StringTable::new();
+        let attributes = define_attributes();
+
+        let parsed = parse("segment_ids one of [1, 2, 3,]", &attributes, &mut strings);
+
+        assert_eq!(
+            parse("segment_ids one of [1, 2, 3]", &attributes, &mut strings),
+            parsed
+        );
+        assert!(parse("segment_ids one of [,]", &attributes, &mut strings).is_err());
+        assert!(parse("segment_ids one of [1,,]", &attributes, &mut strings).is_err());
+    }
+
     #[test]
     fn return_an_error_on_an_empty_list() {
         let mut strings = StringTable::new();
@@ -382,6 +493,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn intern_the_strings_without_their_escape_sequences() {
+        let mut strings = StringTable::new();
+        let attributes = define_attributes();
+
+        let parsed = parse(
+            r##"deals one of ["deal \"1\"", r#"deal "1""#, 'deal-\u{32}']"##,
+            &attributes,
+            &mut strings,
+        );
+
+        assert_eq!(
+            Ok(value!(one_of!(
+                &attributes,
+                "deals",
+                string_list!(vec![strings.get("deal \"1\""), strings.get("deal-2")])
+            ))),
+            parsed
+        );
+    }
+
     #[test]
     fn can_parse_one_of_list_expression_with_string_list() {
         let mut strings = StringTable::new();
@@ -1,6 +1,8 @@
 use crate::{
     events::{AttributeId, AttributeKind, AttributeTable, AttributeValue, Event, EventError},
-    strings::StringId,
+    fuzzy::FuzzyPattern,
+    stats::vec_bytes,
+    strings::{Interner, StringId},
 };
 use rust_decimal::Decimal;
 use std::{
@@ -13,6 +15,8 @@ use std::{
 pub struct Predicate {
     attribute: AttributeId,
     kind: PredicateKind,
+    /// The tolerance of a float equality; see [`AttributeTable::epsilon()`].
+    epsilon: Option<Decimal>,
 }
 
 impl Predicate {
@@ -26,13 +30,75 @@ impl Predicate {
             .ok_or_else(|| EventError::NonExistingAttribute(name.to_string()))
             .and_then(|id| {
                 validate_predicate(name, &kind, &attributes.by_id(id))?;
+                let epsilon = match kind {
+                    PredicateKind::Equality(_, PrimitiveLiteral::Float(_)) => {
+                        attributes.epsilon(id)
+                    }
+                    _ => None,
+                };
                 Ok(Predicate {
                     attribute: id,
                     kind,
+                    epsilon,
                 })
             })
     }
 
+    /// Check that the strings of the predicate are values of the dictionary of its attribute,
+    /// when it was defined with one.
+    pub fn check_dictionary<S: Interner>(
+        self,
+        attributes: &AttributeTable,
+        strings: &S,
+        name: &str,
+    ) -> Result<Self, EventError> {
+        let Some(dictionary) = attributes.dictionary(self.attribute) else {
+            return Ok(self);
+        };
+        let values = match &self.kind {
+            PredicateKind::Equality(_, PrimitiveLiteral::String(value)) => {
+                std::slice::from_ref(value)
+            }
+            PredicateKind::Set(_, ListLiteral::StringList(values))
+            | PredicateKind::List(_, ListLiteral::StringList(values)) => values.as_slice(),
+            _ => &[],
+        };
+        if values.iter().all(|value| strings.is_in(*value, dictionary)) {
+            Ok(self)
+        } else {
+            Err(EventError::UnknownValue(name.to_owned()))
+        }
+    }
+
+    /// Map the strings of the predicate to the IDs of another table, such as the strings of an
+    /// expression decoded on their own to the strings of the tree.
+    pub(crate) fn map_strings(mut self, mut f: impl FnMut(StringId) -> StringId) -> Self {
+        match &mut self.kind {
+            PredicateKind::Equality(_, PrimitiveLiteral::String(value)) => *value = f(*value),
+            PredicateKind::Set(_, ListLiteral::StringList(values))
+            | PredicateKind::List(_, ListLiteral::StringList(values)) => {
+                // The string lists are searched by ID, so they are sorted again with the new IDs.
+                for value in values.iter_mut() {
+                    *value = f(*value);
+                }
+                values.sort_unstable();
+                values.dedup();
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// The heap bytes used by the predicate.
+    pub(crate) fn heap_bytes(&self) -> usize {
+        match &self.kind {
+            PredicateKind::Set(_, list) | PredicateKind::List(_, list) => list.heap_bytes(),
+            PredicateKind::Pattern(_, pattern) => pattern.capacity(),
+            PredicateKind::Fuzzy(_, pattern) => pattern.heap_bytes(),
+            _ => 0,
+        }
+    }
+
     #[inline]
     pub fn id(&self) -> u64 {
         use std::hash::DefaultHasher;
@@ -46,9 +112,24 @@ impl Predicate {
         self.kind.cost()
     }
 
+    #[inline]
+    pub fn attribute(&self) -> AttributeId {
+        self.attribute
+    }
+
+    #[inline]
+    pub fn kind(&self) -> &PredicateKind {
+        &self.kind
+    }
+
     pub fn evaluate(&self, event: &Event) -> Option<bool> {
         let value = &event[self.attribute];
         match (&self.kind, value) {
+            // An undefined list is neither empty nor not empty.
+            (
+                PredicateKind::Null(NullOperator::IsEmpty | NullOperator::IsNotEmpty),
+                AttributeValue::Undefined,
+            ) => None,
             (PredicateKind::Null(operator), value) => Some(operator.evaluate(value)),
             (_, AttributeValue::Undefined) => None,
             (PredicateKind::Variable, AttributeValue::Boolean(value)) => Some(*value),
@@ -57,8 +138,26 @@ impl Predicate {
                 Some(operator.evaluate(haystack, needle))
             }
             (PredicateKind::Comparison(operator, a), b) => Some(operator.evaluate(a, b)),
-            (PredicateKind::Equality(operator, a), b) => Some(operator.evaluate(a, b)),
+            (PredicateKind::Range(operator, low, high), value) => {
+                Some(operator.evaluate(low, high, value))
+            }
+            (PredicateKind::Equality(operator, a), b) => match (a, b, self.epsilon) {
+                (PrimitiveLiteral::Float(a), AttributeValue::Float(b), Some(epsilon)) => {
+                    Some(operator.evaluate_within(a, b, epsilon))
+                }
+                _ => Some(operator.evaluate(a, b)),
+            },
             (PredicateKind::List(operator, a), b) => Some(operator.evaluate(a, b)),
+            (PredicateKind::Recency(operator, window), AttributeValue::Integer(time)) => event
+                .now()
+                .map(|now| operator.evaluate(*window, *time, now)),
+            // The text is only kept by the events built once a pattern used the attribute.
+            (PredicateKind::Pattern(operator, pattern), AttributeValue::String(_)) => event
+                .text(self.attribute)
+                .map(|text| operator.evaluate(pattern, text)),
+            (PredicateKind::Fuzzy(operator, pattern), AttributeValue::String(_)) => event
+                .text(self.attribute)
+                .map(|text| operator.evaluate(pattern, text)),
             (kind, value) => {
                 unreachable!("Invalid => got: {kind:?} with {value:?}");
             }
@@ -73,6 +172,7 @@ impl Not for Predicate {
         Self {
             attribute: self.attribute,
             kind: !self.kind,
+            epsilon: self.epsilon,
         }
     }
 }
@@ -96,12 +196,23 @@ fn validate_predicate(
             Ok(())
         }
         (PredicateKind::Comparison(_, ComparisonValue::Float(_)), AttributeKind::Float) => Ok(()),
+        (
+            PredicateKind::Range(_, ComparisonValue::Integer(_), ComparisonValue::Integer(_)),
+            AttributeKind::Integer,
+        ) => Ok(()),
+        (
+            PredicateKind::Range(_, ComparisonValue::Float(_), ComparisonValue::Float(_)),
+            AttributeKind::Float,
+        ) => Ok(()),
 
         (PredicateKind::Equality(_, PrimitiveLiteral::Integer(_)), AttributeKind::Integer) => {
             Ok(())
         }
         (PredicateKind::Equality(_, PrimitiveLiteral::Float(_)), AttributeKind::Float) => Ok(()),
         (PredicateKind::Equality(_, PrimitiveLiteral::String(_)), AttributeKind::String) => Ok(()),
+        (PredicateKind::Pattern(_, _), AttributeKind::String) => Ok(()),
+        (PredicateKind::Fuzzy(_, _), AttributeKind::String) => Ok(()),
+        (PredicateKind::Recency(_, _), AttributeKind::Integer) => Ok(()),
 
         (PredicateKind::List(_, ListLiteral::IntegerList(_)), AttributeKind::IntegerList) => Ok(()),
         (PredicateKind::List(_, ListLiteral::StringList(_)), AttributeKind::StringList) => Ok(()),
@@ -135,9 +246,16 @@ pub enum PredicateKind {
     NegatedVariable,
     Set(SetOperator, ListLiteral),
     Comparison(ComparisonOperator, ComparisonValue),
+    /// The bounds are inclusive; a range whose lower bound is above its upper bound is empty.
+    Range(RangeOperator, ComparisonValue, ComparisonValue),
     Equality(EqualityOperator, PrimitiveLiteral),
     List(ListOperator, ListLiteral),
     Null(NullOperator),
+    Pattern(PatternOperator, String),
+    /// The window is in seconds before the time of the event; see
+    /// [`crate::EventBuilder::with_now()`].
+    Recency(RecencyOperator, i64),
+    Fuzzy(FuzzyOperator, FuzzyPattern),
 }
 
 impl PredicateKind {
@@ -152,6 +270,8 @@ impl PredicateKind {
             | Self::Variable
             | Self::Null(_)
             | Self::Comparison(_, _)
+            | Self::Range(_, _, _)
+            | Self::Recency(_, _)
             | Self::Equality(_, _) => Self::CONSTANT_COST,
             Self::Set(_, ListLiteral::StringList(list)) => {
                 Self::LOGARITHMIC_COST * (list.len() as u64)
@@ -161,6 +281,12 @@ impl PredicateKind {
             }
             Self::List(_, ListLiteral::StringList(list)) => Self::LIST_COST * (list.len() as u64),
             Self::List(_, ListLiteral::IntegerList(list)) => Self::LIST_COST * (list.len() as u64),
+            Self::Pattern(_, pattern) => Self::LIST_COST * (pattern.len() as u64),
+            Self::Fuzzy(_, pattern) => {
+                Self::LIST_COST
+                    * (pattern.pattern().len() as u64)
+                    * (2 * u64::from(pattern.distance()) + 1)
+            }
         }
     }
 }
@@ -184,6 +310,7 @@ impl Not for PredicateKind {
             Self::Comparison(ComparisonOperator::GreaterThanEqual, value) => {
                 Self::Comparison(ComparisonOperator::LessThan, value)
             }
+            Self::Range(operator, low, high) => Self::Range(!operator, low, high),
             Self::Null(NullOperator::IsNull) => Self::Null(NullOperator::IsNotNull),
             Self::Null(NullOperator::IsNotNull) => Self::Null(NullOperator::IsNull),
             Self::Null(NullOperator::IsEmpty) => Self::Null(NullOperator::IsNotEmpty),
@@ -198,8 +325,17 @@ impl Not for PredicateKind {
             Self::List(ListOperator::AllOf, value) => Self::List(ListOperator::NotAllOf, value),
             Self::List(ListOperator::NotAllOf, value) => Self::List(ListOperator::AllOf, value),
             Self::List(ListOperator::NoneOf, value) => Self::List(ListOperator::OneOf, value),
+            Self::List(ListOperator::IntersectAtLeast(count), value) => {
+                Self::List(ListOperator::IntersectFewerThan(count), value)
+            }
+            Self::List(ListOperator::IntersectFewerThan(count), value) => {
+                Self::List(ListOperator::IntersectAtLeast(count), value)
+            }
             Self::Variable => Self::NegatedVariable,
             Self::NegatedVariable => Self::Variable,
+            Self::Pattern(operator, pattern) => Self::Pattern(!operator, pattern),
+            Self::Recency(operator, window) => Self::Recency(!operator, window),
+            Self::Fuzzy(operator, pattern) => Self::Fuzzy(!operator, pattern),
         }
     }
 }
@@ -211,13 +347,175 @@ impl Display for PredicateKind {
             Self::NegatedVariable => write!(formatter, "not, variable"),
             Self::Set(operator, values) => write!(formatter, "{operator}, {values}"),
             Self::Comparison(operator, values) => write!(formatter, "{operator}, {values}"),
+            Self::Range(operator, low, high) => write!(formatter, "{operator}, [{low}, {high}]"),
             Self::List(operator, values) => write!(formatter, "{operator}, {values}"),
             Self::Null(operator) => write!(formatter, "{operator}, variable"),
             Self::Equality(operator, values) => write!(formatter, "{operator}, {values}"),
+            Self::Pattern(operator, pattern) => write!(formatter, "{operator}, {pattern:?}"),
+            Self::Recency(operator, window) => write!(formatter, "{operator}, {window}s"),
+            Self::Fuzzy(operator, pattern) => write!(formatter, "{operator}, {pattern}"),
+        }
+    }
+}
+
+#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+pub enum PatternOperator {
+    StartsWith,
+    NotStartsWith,
+    Contains,
+    NotContains,
+}
+
+impl PatternOperator {
+    fn evaluate(&self, pattern: &str, text: &str) -> bool {
+        match self {
+            Self::StartsWith => text.starts_with(pattern),
+            Self::NotStartsWith => !text.starts_with(pattern),
+            Self::Contains => text.contains(pattern),
+            Self::NotContains => !text.contains(pattern),
+        }
+    }
+
+    /// Whether the operator is the negation of the one checking for the pattern.
+    #[inline]
+    pub(crate) const fn is_negated(&self) -> bool {
+        matches!(self, Self::NotStartsWith | Self::NotContains)
+    }
+
+    /// Whether the pattern must be at the start of the text.
+    #[inline]
+    pub(crate) const fn is_prefix(&self) -> bool {
+        matches!(self, Self::StartsWith | Self::NotStartsWith)
+    }
+}
+
+impl Not for PatternOperator {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        match self {
+            Self::StartsWith => Self::NotStartsWith,
+            Self::NotStartsWith => Self::StartsWith,
+            Self::Contains => Self::NotContains,
+            Self::NotContains => Self::Contains,
+        }
+    }
+}
+
+impl Display for PatternOperator {
+    fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Self::StartsWith => write!(formatter, "starts with"),
+            Self::NotStartsWith => write!(formatter, "not starts with"),
+            Self::Contains => write!(formatter, "contains"),
+            Self::NotContains => write!(formatter, "not contains"),
         }
     }
 }
 
+#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+pub enum FuzzyOperator {
+    Matches,
+    NotMatches,
+}
+
+impl FuzzyOperator {
+    fn evaluate(&self, pattern: &FuzzyPattern, text: &str) -> bool {
+        match self {
+            Self::Matches => pattern.matches(text),
+            Self::NotMatches => !pattern.matches(text),
+        }
+    }
+}
+
+impl Not for FuzzyOperator {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        match self {
+            Self::Matches => Self::NotMatches,
+            Self::NotMatches => Self::Matches,
+        }
+    }
+}
+
+impl Display for FuzzyOperator {
+    fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Self::Matches => write!(formatter, "~="),
+            Self::NotMatches => write!(formatter, "!~="),
+        }
+    }
+}
+
+#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+pub enum RecencyOperator {
+    WithinLast,
+    NotWithinLast,
+}
+
+impl RecencyOperator {
+    /// Whether the time is at most `window` seconds before `now`; a time after `now` is not.
+    fn evaluate(&self, window: i64, time: i64, now: i64) -> bool {
+        let within = time <= now && now.saturating_sub(window) <= time;
+        within != self.is_negated()
+    }
+
+    /// Whether the operator is the negation of the one checking for the window.
+    #[inline]
+    pub(crate) const fn is_negated(&self) -> bool {
+        matches!(self, Self::NotWithinLast)
+    }
+}
+
+impl Not for RecencyOperator {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        match self {
+            Self::WithinLast => Self::NotWithinLast,
+            Self::NotWithinLast => Self::WithinLast,
+        }
+    }
+}
+
+impl Display for RecencyOperator {
+    fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Self::WithinLast => write!(formatter, "within last"),
+            Self::NotWithinLast => write!(formatter, "not within last"),
+        }
+    }
+}
+
+/// Read a duration such as `7d` or `1h30m` and return it in seconds.
+///
+/// A duration is a sequence of amounts followed by their unit: `s` for the seconds, `m` for the
+/// minutes, `h` for the hours, `d` for the days and `w` for the weeks.
+pub(crate) fn parse_duration(duration: &str) -> Option<i64> {
+    let mut seconds = 0i64;
+    let mut rest = duration.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let amount: i64 = rest[..digits].parse().ok()?;
+        let unit = rest[digits..].chars().next()?;
+        let factor = match unit {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return None,
+        };
+        seconds = seconds.checked_add(amount.checked_mul(factor)?)?;
+        rest = &rest[digits + unit.len_utf8()..];
+    }
+    Some(seconds)
+}
+
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 pub enum SetOperator {
     NotIn,
@@ -296,6 +594,64 @@ impl Display for ComparisonOperator {
     }
 }
 
+#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+pub enum RangeOperator {
+    Between,
+    NotBetween,
+}
+
+impl RangeOperator {
+    fn evaluate(
+        &self,
+        low: &ComparisonValue,
+        high: &ComparisonValue,
+        value: &AttributeValue,
+    ) -> bool {
+        let within = match (low, high, value) {
+            (
+                ComparisonValue::Integer(low),
+                ComparisonValue::Integer(high),
+                AttributeValue::Integer(value),
+            ) => low <= value && value <= high,
+            (
+                ComparisonValue::Float(low),
+                ComparisonValue::Float(high),
+                AttributeValue::Float(value),
+            ) => low <= value && value <= high,
+            (low, high, value) => {
+                unreachable!("Range ({self:?}) between {low:?} and {high:?} for {value:?} should never happen. This is a bug.")
+            }
+        };
+        within != self.is_negated()
+    }
+
+    /// Whether the operator is the negation of the one checking for the range.
+    #[inline]
+    pub(crate) const fn is_negated(&self) -> bool {
+        matches!(self, Self::NotBetween)
+    }
+}
+
+impl Not for RangeOperator {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        match self {
+            Self::Between => Self::NotBetween,
+            Self::NotBetween => Self::Between,
+        }
+    }
+}
+
+impl Display for RangeOperator {
+    fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Self::Between => write!(formatter, "between"),
+            Self::NotBetween => write!(formatter, "not between"),
+        }
+    }
+}
+
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 pub enum ComparisonValue {
     Integer(i64),
@@ -335,6 +691,15 @@ impl EqualityOperator {
             Self::NotEqual => *a != *b,
         }
     }
+
+    /// Compare two floats that are considered equal when they differ by at most the epsilon.
+    fn evaluate_within(&self, a: &Decimal, b: &Decimal, epsilon: Decimal) -> bool {
+        let equal = (a - b).abs() <= epsilon;
+        match self {
+            Self::Equal => equal,
+            Self::NotEqual => !equal,
+        }
+    }
 }
 
 impl Display for EqualityOperator {
@@ -355,6 +720,10 @@ pub enum ListOperator {
     // This is an internal operator only This is only to achieve symmetry with
     // the `all_of` operator for the zero suppression filter.
     NotAllOf,
+    /// At least the amount of values of the list are in the attribute
+    IntersectAtLeast(usize),
+    // The negation of `intersect_at_least`, which has no syntax of its own.
+    IntersectFewerThan(usize),
 }
 
 impl ListOperator {
@@ -378,6 +747,8 @@ impl ListOperator {
             Self::NoneOf => none_of(left, right),
             Self::AllOf => all_of(left, right),
             Self::NotAllOf => not_all_of(left, right),
+            Self::IntersectAtLeast(count) => intersect_at_least(left, right, *count),
+            Self::IntersectFewerThan(count) => !intersect_at_least(left, right, *count),
         }
     }
 }
@@ -389,6 +760,10 @@ impl Display for ListOperator {
             Self::OneOf => write!(formatter, "one of"),
             Self::NoneOf => write!(formatter, "none of"),
             Self::NotAllOf => write!(formatter, "not all of"),
+            Self::IntersectAtLeast(count) => write!(formatter, "intersect at least {count} of"),
+            Self::IntersectFewerThan(count) => {
+                write!(formatter, "intersect fewer than {count} of")
+            }
         }
     }
 }
@@ -460,6 +835,32 @@ fn all_of<T: Ord>(left: &[T], right: &[T]) -> bool {
     j >= left.len()
 }
 
+/// Whether the sorted lists have at least `count` values in common, walking both of them at once
+/// and stopping as soon as the count is reached or can no longer be.
+fn intersect_at_least<T: Ord>(left: &[T], right: &[T], count: usize) -> bool {
+    use std::cmp::Ordering;
+
+    let mut found = 0usize;
+    let mut i = 0usize;
+    let mut j = 0usize;
+    while found < count && j < left.len() && i < right.len() {
+        if found + (left.len() - j).min(right.len() - i) < count {
+            return false;
+        }
+        match right[i].cmp(&left[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Equal => {
+                found += 1;
+                i += 1;
+                j += 1;
+            }
+            Ordering::Greater => j += 1,
+        }
+    }
+
+    found >= count
+}
+
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 #[allow(clippy::enum_variant_names)]
 pub enum NullOperator {
@@ -518,6 +919,15 @@ pub enum ListLiteral {
     StringList(Vec<StringId>),
 }
 
+impl ListLiteral {
+    fn heap_bytes(&self) -> usize {
+        match self {
+            Self::IntegerList(values) => vec_bytes(values),
+            Self::StringList(values) => vec_bytes(values),
+        }
+    }
+}
+
 impl Display for ListLiteral {
     fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
         match self {
@@ -708,6 +1118,21 @@ mod tests {
         assert_eq!(Some(true), predicate.evaluate(&event));
     }
 
+    #[test]
+    fn return_undefined_on_empty_checks_for_undefined_list_variable() {
+        let attributes = define_attributes();
+        let strings = StringTable::new();
+        let mut builder = an_event_builder(&attributes, &strings);
+        builder.with_undefined("segment_ids").unwrap();
+        let event = builder.build().unwrap();
+
+        assert_eq!(None, is_empty!(&attributes, "segment_ids").evaluate(&event));
+        assert_eq!(
+            None,
+            is_not_empty!(&attributes, "segment_ids").evaluate(&event)
+        );
+    }
+
     #[test]
     fn return_false_when_searching_for_an_element_in_an_empty_set() {
         let attributes = define_attributes();
@@ -857,6 +1282,53 @@ mod tests {
         assert_eq!(Some(true), predicate.evaluate(&event));
     }
 
+    #[test]
+    fn compare_the_floats_exactly_without_an_epsilon() {
+        let attributes = define_attributes();
+        let strings = StringTable::new();
+        let mut builder = an_event_builder(&attributes, &strings);
+        builder.with_float("bidfloor", 29_901, 4).unwrap();
+        let event = builder.build().unwrap();
+        let predicate = equal!(
+            &attributes,
+            "bidfloor",
+            PrimitiveLiteral::Float(Decimal::new(299, 2))
+        );
+
+        assert_eq!(Some(false), predicate.evaluate(&event));
+    }
+
+    #[test]
+    fn compare_the_floats_within_the_epsilon_of_the_attribute() {
+        let definitions = [AttributeDefinition::float_with_epsilon("bidfloor", 0.001)];
+        let attributes = AttributeTable::new(&definitions).unwrap();
+        let strings = StringTable::new();
+        let value = PrimitiveLiteral::Float(Decimal::new(299, 2));
+        let equal = equal!(&attributes, "bidfloor", value.clone());
+        let not_equal = not_equal!(&attributes, "bidfloor", value);
+
+        for (mantissa, expected) in [(29_901, true), (29_890, true), (29_889, false)] {
+            let mut builder = EventBuilder::new(&attributes, &strings);
+            builder.with_float("bidfloor", mantissa, 4).unwrap();
+            let event = builder.build().unwrap();
+
+            assert_eq!(Some(expected), equal.evaluate(&event));
+            assert_eq!(Some(!expected), not_equal.evaluate(&event));
+        }
+    }
+
+    #[test]
+    fn reject_an_invalid_epsilon() {
+        for epsilon in [-0.1, f64::NAN] {
+            let definitions = [AttributeDefinition::float_with_epsilon("bidfloor", epsilon)];
+
+            assert!(matches!(
+                AttributeTable::new(&definitions),
+                Err(EventError::InvalidEpsilon { .. })
+            ));
+        }
+    }
+
     #[test]
     fn can_check_if_value_lesser_than_another_value_is_less_than_the_other_value() {
         let attributes = define_attributes();
@@ -1404,6 +1876,54 @@ mod tests {
         )
     }
 
+    #[test]
+    fn return_true_when_the_lists_have_at_least_the_count_of_values_in_common() {
+        let attributes = define_attributes();
+        let strings = StringTable::new();
+        let mut builder = an_event_builder(&attributes, &strings);
+        builder
+            .with_integer_list("segment_ids", &[2, 3, 5, 8])
+            .unwrap();
+        let event = builder.build().unwrap();
+        let intersect_at_least = |count| {
+            predicate!(
+                &attributes,
+                "segment_ids",
+                PredicateKind::List(
+                    ListOperator::IntersectAtLeast(count),
+                    integer_list!(vec![1, 2, 3, 4])
+                )
+            )
+        };
+
+        assert_eq!(Some(true), intersect_at_least(1).evaluate(&event));
+        assert_eq!(Some(true), intersect_at_least(2).evaluate(&event));
+        assert_eq!(Some(false), intersect_at_least(3).evaluate(&event));
+        assert_eq!(Some(false), intersect_at_least(5).evaluate(&event));
+    }
+
+    #[test]
+    fn can_negate_an_intersect_at_least_predicate() {
+        let attributes = define_attributes();
+        let strings = StringTable::new();
+        let mut builder = an_event_builder(&attributes, &strings);
+        builder.with_integer_list("segment_ids", &[1, 4]).unwrap();
+        let event = builder.build().unwrap();
+        let predicate = predicate!(
+            &attributes,
+            "segment_ids",
+            PredicateKind::List(
+                ListOperator::IntersectAtLeast(2),
+                integer_list!(vec![1, 2, 3, 4])
+            )
+        );
+
+        assert_eq!(
+            predicate.evaluate(&event).map(std::ops::Not::not),
+            (!predicate).evaluate(&event)
+        )
+    }
+
     #[test]
     fn can_negate_an_all_of_predicate() {
         let attributes = define_attributes();
@@ -1419,6 +1939,51 @@ mod tests {
         )
     }
 
+    #[test]
+    fn can_parse_the_durations() {
+        assert_eq!(Some(7 * 24 * 60 * 60), parse_duration("7d"));
+        assert_eq!(Some(90 * 60), parse_duration("1h30m"));
+        assert_eq!(Some(1_209_645), parse_duration(" 2w45s "));
+        assert_eq!(None, parse_duration(""));
+        assert_eq!(None, parse_duration("7"));
+        assert_eq!(None, parse_duration("d"));
+        assert_eq!(None, parse_duration("7y"));
+        assert_eq!(None, parse_duration("7é"));
+        assert_eq!(None, parse_duration("99999999999999999w"));
+    }
+
+    #[test]
+    fn can_negate_a_within_last_predicate() {
+        let attributes = define_attributes();
+        let strings = StringTable::new();
+        let predicate = Predicate::new(
+            &attributes,
+            "exchange_id",
+            PredicateKind::Recency(RecencyOperator::WithinLast, 60),
+        )
+        .unwrap();
+
+        for (time, now, expected) in [
+            (100, Some(160), Some(true)),
+            (100, Some(161), Some(false)),
+            (100, Some(99), Some(false)),
+            (100, None, None),
+        ] {
+            let mut builder = an_event_builder(&attributes, &strings);
+            builder.with_integer("exchange_id", time).unwrap();
+            if let Some(now) = now {
+                builder.with_now(now);
+            }
+            let event = builder.build().unwrap();
+
+            assert_eq!(expected, predicate.evaluate(&event));
+            assert_eq!(
+                expected.map(std::ops::Not::not),
+                (!predicate.clone()).evaluate(&event)
+            );
+        }
+    }
+
     proptest! {
         #[test]
         #[cfg_attr(miri, ignore)]
@@ -0,0 +1,165 @@
+use crate::expression::PredicateExpression;
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Counts how many times a node was evaluated across all the searches.
+#[derive(Debug, Default)]
+pub(crate) struct EvaluationCounter(AtomicU64);
+
+impl EvaluationCounter {
+    #[inline]
+    pub fn increment(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Clone for EvaluationCounter {
+    fn clone(&self) -> Self {
+        Self(AtomicU64::new(self.get()))
+    }
+}
+
+/// Accumulates the wall time spent evaluating a predicate across all the searches.
+#[derive(Debug, Default)]
+pub(crate) struct EvaluationTimer {
+    nanos: AtomicU64,
+    samples: AtomicU64,
+}
+
+impl EvaluationTimer {
+    #[inline]
+    pub fn record(&self, elapsed: Duration) {
+        let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        self.nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.samples.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn samples(&self) -> u64 {
+        self.samples.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+}
+
+impl Clone for EvaluationTimer {
+    fn clone(&self) -> Self {
+        Self {
+            nanos: AtomicU64::new(self.nanos.load(Ordering::Relaxed)),
+            samples: AtomicU64::new(self.samples()),
+        }
+    }
+}
+
+/// The wall time spent evaluating a predicate; see [`crate::ATree::predicate_timings()`].
+///
+/// This is only available when the `profiling` feature is enabled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PredicateTiming {
+    predicate: PredicateExpression,
+    evaluations: u64,
+    elapsed: Duration,
+}
+
+impl PredicateTiming {
+    pub(crate) const fn new(
+        predicate: PredicateExpression,
+        evaluations: u64,
+        elapsed: Duration,
+    ) -> Self {
+        Self {
+            predicate,
+            evaluations,
+            elapsed,
+        }
+    }
+
+    /// The predicate.
+    #[inline]
+    pub const fn predicate(&self) -> &PredicateExpression {
+        &self.predicate
+    }
+
+    /// The amount of timed evaluations of the predicate.
+    #[inline]
+    pub const fn evaluations(&self) -> u64 {
+        self.evaluations
+    }
+
+    /// The wall time spent in the timed evaluations of the predicate.
+    #[inline]
+    pub const fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The average wall time of an evaluation of the predicate.
+    pub fn average(&self) -> Duration {
+        if self.evaluations == 0 {
+            Duration::ZERO
+        } else {
+            let nanos = self.elapsed.as_nanos() / u128::from(self.evaluations);
+            Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+        }
+    }
+}
+
+/// Make a label usable as a frame of the folded stacks format.
+///
+/// The frames are separated by `;` and the stack is separated from its count by a space, so the
+/// former are replaced and the latter are kept since tools only look at the last space.
+pub(crate) fn to_frame(label: &str) -> String {
+    label.replace(';', ",").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_count_the_evaluations() {
+        let counter = EvaluationCounter::default();
+
+        counter.increment();
+        counter.increment();
+
+        assert_eq!(2, counter.get());
+    }
+
+    #[test]
+    fn keep_the_count_when_cloning() {
+        let counter = EvaluationCounter::default();
+        counter.increment();
+
+        let clone = counter.clone();
+        clone.increment();
+
+        assert_eq!(1, counter.get());
+        assert_eq!(2, clone.get());
+    }
+
+    #[test]
+    fn accumulate_the_recorded_evaluation_times() {
+        let timer = EvaluationTimer::default();
+
+        timer.record(Duration::from_micros(3));
+        timer.record(Duration::from_micros(5));
+
+        assert_eq!(2, timer.samples());
+        assert_eq!(Duration::from_micros(8), timer.elapsed());
+    }
+
+    #[test]
+    fn remove_the_frame_separators_from_the_labels() {
+        assert_eq!("a, b c", to_frame("a; b\nc"));
+    }
+}
@@ -0,0 +1,1101 @@
+use crate::{
+    events::{AttributeId, AttributeValue, Event},
+    predicates::{
+        ComparisonOperator, ComparisonValue, EqualityOperator, ListLiteral, PatternOperator,
+        Predicate, PredicateKind, PrimitiveLiteral, SetOperator,
+    },
+    stats,
+    strings::StringId,
+};
+use aho_corasick::AhoCorasick;
+use rust_decimal::Decimal;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::OnceLock,
+};
+
+type NodeId = usize;
+
+/// The predicates evaluated eagerly by the searches
+///
+/// The comparisons of the integer and float attributes are kept in a range index instead of being
+/// evaluated one by one: the thresholds of each attribute and operator are sorted so that a binary
+/// search on the value of the event decides all of them at once. Likewise, the `between` ranges
+/// of each attribute are kept in an interval tree that finds the ones containing the value of the
+/// event in a single lookup. The `starts with` patterns of each string attribute are kept in a
+/// trie that is walked once along the text of the event and its `contains` patterns in an
+/// Aho-Corasick automaton that finds all of them in a single scan of the text. The equalities and
+/// the sets of the attributes defined with a high cardinality are kept in a hash map from their
+/// values to their predicates, so that the value of the event is looked up once; see
+/// [`crate::AttributeDefinition::high_cardinality()`]. The other predicates are evaluated in the
+/// order they were added.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PredicateIndex {
+    linear: Vec<NodeId>,
+    ranges: BTreeMap<AttributeId, Thresholds>,
+    intervals: BTreeMap<AttributeId, Intervals>,
+    prefixes: BTreeMap<AttributeId, Prefixes>,
+    substrings: BTreeMap<AttributeId, Substrings>,
+    lookups: BTreeMap<AttributeId, Lookups>,
+    /// The attributes whose equalities and sets are looked up by value
+    high_cardinality: HashSet<AttributeId>,
+}
+
+impl PredicateIndex {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Self {
+            linear: Vec::with_capacity(capacity),
+            ranges: BTreeMap::new(),
+            intervals: BTreeMap::new(),
+            prefixes: BTreeMap::new(),
+            substrings: BTreeMap::new(),
+            lookups: BTreeMap::new(),
+            high_cardinality: HashSet::new(),
+        }
+    }
+
+    /// Look up the equalities and the sets of the attributes by value.
+    pub(crate) fn with_high_cardinality(
+        mut self,
+        attributes: impl IntoIterator<Item = AttributeId>,
+    ) -> Self {
+        self.high_cardinality.extend(attributes);
+        self
+    }
+
+    /// Add the predicate of the node unless it was already added.
+    pub(crate) fn insert(&mut self, node_id: NodeId, predicate: &Predicate) {
+        let attribute = predicate.attribute();
+        match predicate.kind() {
+            PredicateKind::Pattern(operator, pattern) if operator.is_prefix() => {
+                return self
+                    .prefixes
+                    .entry(attribute)
+                    .or_default()
+                    .insert(pattern, operator, node_id);
+            }
+            PredicateKind::Pattern(operator, pattern) => {
+                return self
+                    .substrings
+                    .entry(attribute)
+                    .or_default()
+                    .insert(pattern, operator, node_id);
+            }
+            _ => {}
+        }
+        if let Some((keys, negated)) = self.lookup(predicate) {
+            return self
+                .lookups
+                .entry(attribute)
+                .or_default()
+                .insert(&keys, negated, node_id);
+        }
+        if let Some(interval) = interval(node_id, predicate) {
+            return self
+                .intervals
+                .entry(attribute)
+                .or_default()
+                .insert(interval);
+        }
+        match threshold(predicate) {
+            Some((operator, bound)) => self
+                .ranges
+                .entry(attribute)
+                .or_default()
+                .insert(operator, bound, node_id),
+            None if !self.linear.contains(&node_id) => self.linear.push(node_id),
+            None => {}
+        }
+    }
+
+    pub(crate) fn remove(&mut self, node_id: NodeId, predicate: &Predicate) {
+        self.remove_many([(node_id, predicate)]);
+    }
+
+    /// Remove the predicates of many nodes at once, which only goes once through the predicates
+    /// evaluated one by one.
+    pub(crate) fn remove_many<'a>(
+        &mut self,
+        predicates: impl IntoIterator<Item = (NodeId, &'a Predicate)>,
+    ) {
+        let mut linear = HashSet::new();
+        for (node_id, predicate) in predicates {
+            let attribute = predicate.attribute();
+            match predicate.kind() {
+                PredicateKind::Pattern(operator, pattern) if operator.is_prefix() => {
+                    if let Some(prefixes) = self.prefixes.get_mut(&attribute) {
+                        prefixes.remove(pattern, node_id);
+                        if prefixes.is_empty() {
+                            self.prefixes.remove(&attribute);
+                        }
+                    }
+                    continue;
+                }
+                PredicateKind::Pattern(_, pattern) => {
+                    if let Some(substrings) = self.substrings.get_mut(&attribute) {
+                        substrings.remove(pattern, node_id);
+                        if substrings.is_empty() {
+                            self.substrings.remove(&attribute);
+                        }
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            if let Some((keys, _)) = self.lookup(predicate) {
+                if let Some(lookups) = self.lookups.get_mut(&attribute) {
+                    lookups.remove(&keys, node_id);
+                    if lookups.is_empty() {
+                        self.lookups.remove(&attribute);
+                    }
+                }
+                continue;
+            }
+            if let Some(interval) = interval(node_id, predicate) {
+                if let Some(tree) = self.intervals.get_mut(&attribute) {
+                    tree.remove(&interval);
+                    if tree.is_empty() {
+                        self.intervals.remove(&attribute);
+                    }
+                }
+                continue;
+            }
+            match threshold(predicate) {
+                Some((operator, bound)) => {
+                    if let Some(thresholds) = self.ranges.get_mut(&attribute) {
+                        thresholds.remove(operator, bound, node_id);
+                        if thresholds.is_empty() {
+                            self.ranges.remove(&attribute);
+                        }
+                    }
+                }
+                None => {
+                    linear.insert(node_id);
+                }
+            }
+        }
+        if !linear.is_empty() {
+            self.linear.retain(|node_id| !linear.contains(node_id));
+        }
+    }
+
+    /// The values of the predicate along with whether it is negated, when it is looked up by value.
+    fn lookup(&self, predicate: &Predicate) -> Option<(Vec<Key>, bool)> {
+        if !self.high_cardinality.contains(&predicate.attribute()) {
+            return None;
+        }
+        match predicate.kind() {
+            PredicateKind::Equality(operator, value) => Some((
+                vec![key(value)?],
+                matches!(operator, EqualityOperator::NotEqual),
+            )),
+            PredicateKind::Set(operator, values) => Some((
+                match values {
+                    ListLiteral::IntegerList(values) => {
+                        values.iter().copied().map(Key::Integer).collect()
+                    }
+                    ListLiteral::StringList(values) => {
+                        values.iter().copied().map(Key::String).collect()
+                    }
+                },
+                matches!(operator, SetOperator::NotIn),
+            )),
+            _ => None,
+        }
+    }
+
+    /// The predicates that are neither in the range index nor in the trees.
+    #[inline]
+    pub(crate) fn linear(&self) -> &[NodeId] {
+        &self.linear
+    }
+
+    /// Decide the comparisons of the range index, the ranges of the interval trees and the
+    /// patterns of the tries and the automatons for the event.
+    pub(crate) fn indexed<'a>(
+        &'a self,
+        event: &'a Event,
+    ) -> impl Iterator<Item = (NodeId, Option<bool>)> + 'a {
+        let ranges = self
+            .ranges
+            .iter()
+            .flat_map(|(attribute, thresholds)| thresholds.evaluate(&event[*attribute]));
+        let intervals = self
+            .intervals
+            .iter()
+            .flat_map(|(attribute, intervals)| intervals.evaluate(&event[*attribute]));
+        let prefixes = self
+            .prefixes
+            .iter()
+            .flat_map(|(attribute, prefixes)| prefixes.evaluate(text(event, *attribute)));
+        let substrings = self
+            .substrings
+            .iter()
+            .flat_map(|(attribute, substrings)| substrings.evaluate(text(event, *attribute)));
+        let lookups = self
+            .lookups
+            .iter()
+            .flat_map(|(attribute, lookups)| lookups.evaluate(&event[*attribute]));
+        ranges
+            .chain(intervals)
+            .chain(prefixes)
+            .chain(substrings)
+            .chain(lookups)
+    }
+
+    /// The heap bytes used by the index.
+    pub(crate) fn heap_bytes(&self) -> usize {
+        stats::vec_bytes(&self.linear)
+            + self
+                .ranges
+                .values()
+                .map(|thresholds| {
+                    size_of::<(AttributeId, Thresholds)>()
+                        + thresholds
+                            .operators
+                            .iter()
+                            .map(stats::vec_bytes)
+                            .sum::<usize>()
+                })
+                .sum::<usize>()
+            + self
+                .intervals
+                .values()
+                .map(|intervals| size_of::<(AttributeId, Intervals)>() + intervals.heap_bytes())
+                .sum::<usize>()
+            + self
+                .prefixes
+                .values()
+                .map(|prefixes| size_of::<(AttributeId, Prefixes)>() + prefixes.heap_bytes())
+                .sum::<usize>()
+            + self
+                .substrings
+                .values()
+                .map(|substrings| size_of::<(AttributeId, Substrings)>() + substrings.heap_bytes())
+                .sum::<usize>()
+            + self
+                .lookups
+                .values()
+                .map(|lookups| size_of::<(AttributeId, Lookups)>() + lookups.heap_bytes())
+                .sum::<usize>()
+    }
+}
+
+/// The value a comparison is made against; an attribute only has values of one kind
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Bound {
+    Integer(i64),
+    Float(Decimal),
+}
+
+/// The sorted thresholds of the comparisons of an attribute, by operator
+#[derive(Clone, Debug, Default)]
+struct Thresholds {
+    operators: [Vec<(Bound, NodeId)>; 4],
+}
+
+impl Thresholds {
+    fn insert(&mut self, operator: &ComparisonOperator, bound: Bound, node_id: NodeId) {
+        let thresholds = &mut self.operators[slot(operator)];
+        if let Err(position) = thresholds.binary_search(&(bound, node_id)) {
+            thresholds.insert(position, (bound, node_id));
+        }
+    }
+
+    fn remove(&mut self, operator: &ComparisonOperator, bound: Bound, node_id: NodeId) {
+        let thresholds = &mut self.operators[slot(operator)];
+        if let Ok(position) = thresholds.binary_search(&(bound, node_id)) {
+            thresholds.remove(position);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.operators.iter().all(Vec::is_empty)
+    }
+
+    fn evaluate(
+        &self,
+        value: &AttributeValue,
+    ) -> impl Iterator<Item = (NodeId, Option<bool>)> + '_ {
+        let value = bound(value);
+        OPERATORS
+            .iter()
+            .zip(&self.operators)
+            .flat_map(move |(operator, thresholds)| {
+                // The thresholds before the split point are all satisfied or all unsatisfied.
+                let (split, below, above) = match (value, operator) {
+                    (None, _) => (0, None, None),
+                    (Some(value), ComparisonOperator::GreaterThan) => (
+                        thresholds.partition_point(|(bound, _)| *bound < value),
+                        Some(true),
+                        Some(false),
+                    ),
+                    (Some(value), ComparisonOperator::GreaterThanEqual) => (
+                        thresholds.partition_point(|(bound, _)| *bound <= value),
+                        Some(true),
+                        Some(false),
+                    ),
+                    (Some(value), ComparisonOperator::LessThan) => (
+                        thresholds.partition_point(|(bound, _)| *bound <= value),
+                        Some(false),
+                        Some(true),
+                    ),
+                    (Some(value), ComparisonOperator::LessThanEqual) => (
+                        thresholds.partition_point(|(bound, _)| *bound < value),
+                        Some(false),
+                        Some(true),
+                    ),
+                };
+                let (before, after) = thresholds.split_at(split);
+                before
+                    .iter()
+                    .map(move |(_, node_id)| (*node_id, below))
+                    .chain(after.iter().map(move |(_, node_id)| (*node_id, above)))
+            })
+    }
+}
+
+const OPERATORS: [ComparisonOperator; 4] = [
+    ComparisonOperator::LessThan,
+    ComparisonOperator::LessThanEqual,
+    ComparisonOperator::GreaterThanEqual,
+    ComparisonOperator::GreaterThan,
+];
+
+#[inline]
+const fn slot(operator: &ComparisonOperator) -> usize {
+    match operator {
+        ComparisonOperator::LessThan => 0,
+        ComparisonOperator::LessThanEqual => 1,
+        ComparisonOperator::GreaterThanEqual => 2,
+        ComparisonOperator::GreaterThan => 3,
+    }
+}
+
+/// The text of a string attribute of the event, when it was kept.
+fn text(event: &Event, attribute: AttributeId) -> Option<&str> {
+    match event[attribute] {
+        AttributeValue::String(_) => event.text(attribute),
+        _ => None,
+    }
+}
+
+fn bound(value: &AttributeValue) -> Option<Bound> {
+    match value {
+        AttributeValue::Integer(value) => Some(Bound::Integer(*value)),
+        AttributeValue::Float(value) => Some(Bound::Float(*value)),
+        _ => None,
+    }
+}
+
+fn threshold(predicate: &Predicate) -> Option<(&ComparisonOperator, Bound)> {
+    match predicate.kind() {
+        PredicateKind::Comparison(operator, ComparisonValue::Integer(value)) => {
+            Some((operator, Bound::Integer(*value)))
+        }
+        PredicateKind::Comparison(operator, ComparisonValue::Float(value)) => {
+            Some((operator, Bound::Float(*value)))
+        }
+        _ => None,
+    }
+}
+
+/// A `between` range of an attribute with its predicate
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct Interval {
+    low: Bound,
+    high: Bound,
+    node_id: NodeId,
+    negated: bool,
+}
+
+fn interval(node_id: NodeId, predicate: &Predicate) -> Option<Interval> {
+    let PredicateKind::Range(operator, low, high) = predicate.kind() else {
+        return None;
+    };
+    let (low, high) = match (low, high) {
+        (ComparisonValue::Integer(low), ComparisonValue::Integer(high)) => {
+            (Bound::Integer(*low), Bound::Integer(*high))
+        }
+        (ComparisonValue::Float(low), ComparisonValue::Float(high)) => {
+            (Bound::Float(*low), Bound::Float(*high))
+        }
+        _ => return None,
+    };
+    Some(Interval {
+        low,
+        high,
+        node_id,
+        negated: operator.is_negated(),
+    })
+}
+
+/// The `between` ranges of an attribute in an interval tree
+///
+/// The ranges are sorted by their lower bound and the tree is implicit: the range in the middle of
+/// a slice is the root of the subtree made of the slice, and it records the highest upper bound of
+/// that subtree so that the subtrees that cannot contain the value are skipped. The highest upper
+/// bounds are computed again by the first search after the ranges changed, so that adding many
+/// ranges does not go through all of them every time.
+#[derive(Clone, Debug, Default)]
+struct Intervals {
+    intervals: Vec<Interval>,
+    /// The highest upper bound of the subtree rooted at each range
+    highest: OnceLock<Vec<Bound>>,
+}
+
+impl Intervals {
+    fn insert(&mut self, interval: Interval) {
+        if let Err(position) = self.intervals.binary_search(&interval) {
+            self.intervals.insert(position, interval);
+            self.highest = OnceLock::new();
+        }
+    }
+
+    fn remove(&mut self, interval: &Interval) {
+        if let Ok(position) = self.intervals.binary_search(interval) {
+            self.intervals.remove(position);
+            self.highest = OnceLock::new();
+        }
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    fn highest(&self) -> &[Bound] {
+        self.highest.get_or_init(|| {
+            let mut highest: Vec<_> = self
+                .intervals
+                .iter()
+                .map(|interval| interval.high)
+                .collect();
+            index_subtree(&mut highest, 0, self.intervals.len());
+            highest
+        })
+    }
+
+    /// Flag the ranges of the subtree that contain the value.
+    fn stab(&self, highest: &[Bound], value: Bound, start: usize, end: usize, within: &mut [bool]) {
+        if start >= end {
+            return;
+        }
+        let middle = start + (end - start) / 2;
+        if highest[middle] < value {
+            return;
+        }
+        self.stab(highest, value, start, middle, within);
+        let interval = &self.intervals[middle];
+        // The ranges on the right start at or after this one.
+        if interval.low <= value {
+            within[middle] = value <= interval.high;
+            self.stab(highest, value, middle + 1, end, within);
+        }
+    }
+
+    fn evaluate(
+        &self,
+        value: &AttributeValue,
+    ) -> impl Iterator<Item = (NodeId, Option<bool>)> + '_ {
+        let value = bound(value);
+        let mut within = vec![false; self.intervals.len()];
+        if let Some(value) = value {
+            self.stab(self.highest(), value, 0, self.intervals.len(), &mut within);
+        }
+        self.intervals
+            .iter()
+            .zip(within)
+            .map(move |(interval, within)| {
+                let result = value.map(|_| within != interval.negated);
+                (interval.node_id, result)
+            })
+    }
+
+    fn heap_bytes(&self) -> usize {
+        stats::vec_bytes(&self.intervals) + self.highest.get().map_or(0, stats::vec_bytes)
+    }
+}
+
+/// Record in each range of the slice the highest upper bound of the subtree it is the root of.
+fn index_subtree(highest: &mut [Bound], start: usize, end: usize) -> Option<Bound> {
+    if start >= end {
+        return None;
+    }
+    let middle = start + (end - start) / 2;
+    let left = index_subtree(highest, start, middle);
+    let right = index_subtree(highest, middle + 1, end);
+    let subtree = [left, right]
+        .into_iter()
+        .flatten()
+        .fold(highest[middle], Bound::max);
+    highest[middle] = subtree;
+    Some(subtree)
+}
+
+/// The `starts with` patterns of an attribute in a trie of the bytes of their prefixes
+#[derive(Clone, Debug)]
+struct Prefixes {
+    /// The nodes of the trie, starting with the empty prefix
+    nodes: Vec<TrieNode>,
+    /// The predicates of the trie along with whether they are negated, sorted by node
+    predicates: Vec<(NodeId, bool)>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct TrieNode {
+    /// The next byte of the prefixes along with the trie node it leads to, sorted by byte
+    children: Vec<(u8, usize)>,
+    /// The predicates whose prefix ends here
+    predicates: Vec<NodeId>,
+}
+
+impl Default for Prefixes {
+    fn default() -> Self {
+        Self {
+            nodes: vec![TrieNode::default()],
+            predicates: Vec::new(),
+        }
+    }
+}
+
+impl Prefixes {
+    fn insert(&mut self, pattern: &str, operator: &PatternOperator, node_id: NodeId) {
+        let Err(position) = self
+            .predicates
+            .binary_search_by_key(&node_id, |(id, _)| *id)
+        else {
+            return;
+        };
+        self.predicates
+            .insert(position, (node_id, operator.is_negated()));
+        let mut current = 0;
+        for byte in pattern.bytes() {
+            current = match self.nodes[current]
+                .children
+                .binary_search_by_key(&byte, |(child, _)| *child)
+            {
+                Ok(index) => self.nodes[current].children[index].1,
+                Err(index) => {
+                    let next = self.nodes.len();
+                    self.nodes.push(TrieNode::default());
+                    self.nodes[current].children.insert(index, (byte, next));
+                    next
+                }
+            };
+        }
+        self.nodes[current].predicates.push(node_id);
+    }
+
+    /// Forget the predicate; the nodes of the trie are kept for the prefixes inserted again.
+    fn remove(&mut self, pattern: &str, node_id: NodeId) {
+        if let Ok(position) = self
+            .predicates
+            .binary_search_by_key(&node_id, |(id, _)| *id)
+        {
+            self.predicates.remove(position);
+        }
+        if let Some(current) = self.find(pattern) {
+            self.nodes[current].predicates.retain(|id| *id != node_id);
+        }
+    }
+
+    fn find(&self, pattern: &str) -> Option<usize> {
+        pattern.bytes().try_fold(0, |current, byte| {
+            let children = &self.nodes[current].children;
+            children
+                .binary_search_by_key(&byte, |(child, _)| *child)
+                .ok()
+                .map(|index| children[index].1)
+        })
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.predicates.is_empty()
+    }
+
+    fn evaluate(&self, text: Option<&str>) -> impl Iterator<Item = (NodeId, Option<bool>)> + '_ {
+        let mut found = Vec::new();
+        let defined = text.is_some();
+        if let Some(text) = text {
+            let mut current = 0;
+            found.extend(&self.nodes[current].predicates);
+            for byte in text.bytes() {
+                let children = &self.nodes[current].children;
+                let Ok(index) = children.binary_search_by_key(&byte, |(child, _)| *child) else {
+                    break;
+                };
+                current = children[index].1;
+                found.extend(&self.nodes[current].predicates);
+            }
+            found.sort_unstable();
+        }
+        self.predicates.iter().map(move |(node_id, negated)| {
+            let result = defined.then(|| found.binary_search(node_id).is_ok() != *negated);
+            (*node_id, result)
+        })
+    }
+
+    fn heap_bytes(&self) -> usize {
+        stats::vec_bytes(&self.nodes)
+            + stats::vec_bytes(&self.predicates)
+            + self
+                .nodes
+                .iter()
+                .map(|node| stats::vec_bytes(&node.children) + stats::vec_bytes(&node.predicates))
+                .sum::<usize>()
+    }
+}
+
+/// The `contains` patterns of an attribute, compiled into an Aho-Corasick automaton
+///
+/// The automaton cannot be changed once built, so it is built again by the first search after the
+/// patterns changed. When the patterns are too large for an automaton, each pattern is searched
+/// on its own instead.
+#[derive(Clone, Debug, Default)]
+struct Substrings {
+    /// The distinct patterns along with the predicates looking for them, sorted by pattern
+    patterns: Vec<(String, Vec<NodeId>)>,
+    /// The predicates along with whether they are negated, sorted by node
+    predicates: Vec<(NodeId, bool)>,
+    automaton: OnceLock<Option<AhoCorasick>>,
+}
+
+impl Substrings {
+    fn insert(&mut self, pattern: &str, operator: &PatternOperator, node_id: NodeId) {
+        let Err(position) = self
+            .predicates
+            .binary_search_by_key(&node_id, |(id, _)| *id)
+        else {
+            return;
+        };
+        self.predicates
+            .insert(position, (node_id, operator.is_negated()));
+        match self
+            .patterns
+            .binary_search_by(|(other, _)| other.as_str().cmp(pattern))
+        {
+            Ok(index) => self.patterns[index].1.push(node_id),
+            Err(index) => {
+                self.patterns
+                    .insert(index, (pattern.to_owned(), vec![node_id]));
+                self.automaton = OnceLock::new();
+            }
+        }
+    }
+
+    fn remove(&mut self, pattern: &str, node_id: NodeId) {
+        if let Ok(position) = self
+            .predicates
+            .binary_search_by_key(&node_id, |(id, _)| *id)
+        {
+            self.predicates.remove(position);
+        }
+        if let Ok(index) = self
+            .patterns
+            .binary_search_by(|(other, _)| other.as_str().cmp(pattern))
+        {
+            let predicates = &mut self.patterns[index].1;
+            predicates.retain(|id| *id != node_id);
+            if predicates.is_empty() {
+                self.patterns.remove(index);
+                self.automaton = OnceLock::new();
+            }
+        }
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.predicates.is_empty()
+    }
+
+    fn automaton(&self) -> Option<&AhoCorasick> {
+        self.automaton
+            .get_or_init(|| AhoCorasick::new(self.patterns.iter().map(|(pattern, _)| pattern)).ok())
+            .as_ref()
+    }
+
+    fn evaluate(&self, text: Option<&str>) -> impl Iterator<Item = (NodeId, Option<bool>)> + '_ {
+        let mut found = Vec::new();
+        let defined = text.is_some();
+        if let Some(text) = text {
+            if let Some(automaton) = self.automaton() {
+                let mut seen = vec![false; self.patterns.len()];
+                for pattern in automaton.find_overlapping_iter(text) {
+                    let index = pattern.pattern().as_usize();
+                    if !seen[index] {
+                        seen[index] = true;
+                        found.extend(&self.patterns[index].1);
+                    }
+                }
+            } else {
+                for (pattern, predicates) in &self.patterns {
+                    if text.contains(pattern.as_str()) {
+                        found.extend(predicates);
+                    }
+                }
+            }
+            found.sort_unstable();
+        }
+        self.predicates.iter().map(move |(node_id, negated)| {
+            let result = defined.then(|| found.binary_search(node_id).is_ok() != *negated);
+            (*node_id, result)
+        })
+    }
+
+    fn heap_bytes(&self) -> usize {
+        stats::vec_bytes(&self.patterns)
+            + stats::vec_bytes(&self.predicates)
+            + self
+                .patterns
+                .iter()
+                .map(|(pattern, predicates)| pattern.capacity() + stats::vec_bytes(predicates))
+                .sum::<usize>()
+            + self
+                .automaton
+                .get()
+                .and_then(Option::as_ref)
+                .map_or(0, AhoCorasick::memory_usage)
+    }
+}
+
+/// A value of an equality or a set that is looked up; the floats are always compared one by one
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Key {
+    Integer(i64),
+    String(StringId),
+}
+
+fn key(value: &PrimitiveLiteral) -> Option<Key> {
+    match value {
+        PrimitiveLiteral::Integer(value) => Some(Key::Integer(*value)),
+        PrimitiveLiteral::String(value) => Some(Key::String(*value)),
+        PrimitiveLiteral::Float(_) => None,
+    }
+}
+
+/// The equalities and the sets of a high cardinality attribute in a hash map by value
+#[derive(Clone, Debug, Default)]
+struct Lookups {
+    /// The predicates that are true for each value, before their negation, sorted by node
+    by_values: HashMap<Key, Vec<NodeId>>,
+    /// The predicates along with whether they are negated, sorted by node
+    predicates: Vec<(NodeId, bool)>,
+}
+
+impl Lookups {
+    fn insert(&mut self, keys: &[Key], negated: bool, node_id: NodeId) {
+        let Err(position) = self
+            .predicates
+            .binary_search_by_key(&node_id, |(id, _)| *id)
+        else {
+            return;
+        };
+        self.predicates.insert(position, (node_id, negated));
+        for key in keys {
+            let predicates = self.by_values.entry(*key).or_default();
+            if let Err(position) = predicates.binary_search(&node_id) {
+                predicates.insert(position, node_id);
+            }
+        }
+    }
+
+    fn remove(&mut self, keys: &[Key], node_id: NodeId) {
+        if let Ok(position) = self
+            .predicates
+            .binary_search_by_key(&node_id, |(id, _)| *id)
+        {
+            self.predicates.remove(position);
+        }
+        for key in keys {
+            if let Some(predicates) = self.by_values.get_mut(key) {
+                predicates.retain(|id| *id != node_id);
+                if predicates.is_empty() {
+                    self.by_values.remove(key);
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.predicates.is_empty()
+    }
+
+    fn evaluate(
+        &self,
+        value: &AttributeValue,
+    ) -> impl Iterator<Item = (NodeId, Option<bool>)> + '_ {
+        let (defined, found) = match value {
+            AttributeValue::Integer(value) => (true, self.by_values.get(&Key::Integer(*value))),
+            AttributeValue::String(value) => (true, self.by_values.get(&Key::String(*value))),
+            _ => (false, None),
+        };
+        let found = found.map_or(&[][..], Vec::as_slice);
+        self.predicates.iter().map(move |(node_id, negated)| {
+            let result = defined.then(|| found.binary_search(node_id).is_ok() != *negated);
+            (*node_id, result)
+        })
+    }
+
+    fn heap_bytes(&self) -> usize {
+        stats::vec_bytes(&self.predicates)
+            + stats::map_bytes(&self.by_values)
+            + self.by_values.values().map(stats::vec_bytes).sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::{AttributeDefinition, AttributeTable, EventBuilder},
+        predicates::RangeOperator,
+        strings::StringTable,
+        test_utils::predicates::{
+            comparison_integer, greater_than, greater_than_equal, less_than, less_than_equal,
+            predicate,
+        },
+    };
+    use proptest::prelude::*;
+
+    fn define_attributes() -> AttributeTable {
+        AttributeTable::new(&[AttributeDefinition::integer("exchange_id")]).unwrap()
+    }
+
+    proptest! {
+        #[test]
+        fn decide_the_comparisons_like_their_predicates(
+            thresholds in prop::collection::vec((0..4usize, -5..5i64), 1..50),
+            value in prop::option::of(-6..6i64),
+        ) {
+            let attributes = define_attributes();
+            let strings = StringTable::new();
+            let predicates: Vec<_> = thresholds
+                .into_iter()
+                .map(|(operator, threshold)| {
+                    let value = comparison_integer!(threshold);
+                    match operator {
+                        0 => less_than!(&attributes, "exchange_id", value),
+                        1 => less_than_equal!(&attributes, "exchange_id", value),
+                        2 => greater_than_equal!(&attributes, "exchange_id", value),
+                        _ => greater_than!(&attributes, "exchange_id", value),
+                    }
+                })
+                .collect();
+            let mut index = PredicateIndex::default();
+            for (node_id, predicate) in predicates.iter().enumerate() {
+                index.insert(node_id, predicate);
+            }
+            let mut builder = EventBuilder::new(&attributes, &strings);
+            if let Some(value) = value {
+                builder.with_integer("exchange_id", value).unwrap();
+            }
+            let event = builder.build().unwrap();
+
+            let mut results: Vec<_> = index.indexed(&event).collect();
+            results.sort_unstable();
+
+            let expected: Vec<_> = predicates
+                .iter()
+                .enumerate()
+                .map(|(node_id, predicate)| (node_id, predicate.evaluate(&event)))
+                .collect();
+            prop_assert_eq!(expected, results);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn decide_the_patterns_like_their_predicates(
+            patterns in prop::collection::vec((0..4usize, "[ab]{0,3}"), 1..30),
+            value in prop::option::of("[ab]{0,6}"),
+        ) {
+            let mut attributes =
+                AttributeTable::new(&[AttributeDefinition::string("deal")]).unwrap();
+            attributes.keep_text(attributes.by_name("deal").unwrap());
+            let strings = StringTable::new();
+            let predicates: Vec<_> = patterns
+                .into_iter()
+                .map(|(operator, pattern)| {
+                    let operator = match operator {
+                        0 => PatternOperator::StartsWith,
+                        1 => PatternOperator::NotStartsWith,
+                        2 => PatternOperator::Contains,
+                        _ => PatternOperator::NotContains,
+                    };
+                    let kind = PredicateKind::Pattern(operator, pattern);
+                    Predicate::new(&attributes, "deal", kind).unwrap()
+                })
+                .collect();
+            let mut index = PredicateIndex::default();
+            for (node_id, predicate) in predicates.iter().enumerate() {
+                index.insert(node_id, predicate);
+            }
+            let mut builder = EventBuilder::new(&attributes, &strings);
+            if let Some(value) = &value {
+                builder.with_string("deal", value).unwrap();
+            }
+            let event = builder.build().unwrap();
+
+            let mut results: Vec<_> = index.indexed(&event).collect();
+            results.sort_unstable();
+
+            let expected: Vec<_> = predicates
+                .iter()
+                .enumerate()
+                .map(|(node_id, predicate)| (node_id, predicate.evaluate(&event)))
+                .collect();
+            prop_assert_eq!(expected, results);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn decide_the_ranges_like_their_predicates(
+            ranges in prop::collection::vec((any::<bool>(), -5..5i64, -5..5i64), 1..50),
+            value in prop::option::of(-6..6i64),
+        ) {
+            let attributes = define_attributes();
+            let strings = StringTable::new();
+            let predicates: Vec<_> = ranges
+                .into_iter()
+                .map(|(negated, low, high)| {
+                    let operator = if negated {
+                        RangeOperator::NotBetween
+                    } else {
+                        RangeOperator::Between
+                    };
+                    let kind = PredicateKind::Range(
+                        operator,
+                        ComparisonValue::Integer(low),
+                        ComparisonValue::Integer(high),
+                    );
+                    Predicate::new(&attributes, "exchange_id", kind).unwrap()
+                })
+                .collect();
+            let mut index = PredicateIndex::default();
+            for (node_id, predicate) in predicates.iter().enumerate() {
+                index.insert(node_id, predicate);
+            }
+            let mut builder = EventBuilder::new(&attributes, &strings);
+            if let Some(value) = value {
+                builder.with_integer("exchange_id", value).unwrap();
+            }
+            let event = builder.build().unwrap();
+
+            let mut results: Vec<_> = index.indexed(&event).collect();
+            results.sort_unstable();
+
+            let expected: Vec<_> = predicates
+                .iter()
+                .enumerate()
+                .map(|(node_id, predicate)| (node_id, predicate.evaluate(&event)))
+                .collect();
+            prop_assert_eq!(expected, results);
+        }
+    }
+
+    #[test]
+    fn forget_the_removed_comparisons() {
+        let attributes = define_attributes();
+        let predicate = greater_than!(&attributes, "exchange_id", comparison_integer!(1));
+        let mut index = PredicateIndex::default();
+        index.insert(1, &predicate);
+        index.insert(1, &predicate);
+
+        index.remove(1, &predicate);
+
+        assert!(index.ranges.is_empty());
+    }
+
+    #[test]
+    fn build_the_automaton_again_once_the_patterns_changed() {
+        let mut attributes = AttributeTable::new(&[AttributeDefinition::string("url")]).unwrap();
+        attributes.keep_text(attributes.by_name("url").unwrap());
+        let strings = StringTable::new();
+        let contains = |pattern: &str| {
+            let kind = PredicateKind::Pattern(PatternOperator::Contains, pattern.to_owned());
+            Predicate::new(&attributes, "url", kind).unwrap()
+        };
+        let mut builder = EventBuilder::new(&attributes, &strings);
+        builder
+            .with_string("url", "https://example.com/sports/hockey")
+            .unwrap();
+        let event = builder.build().unwrap();
+        let mut index = PredicateIndex::default();
+        index.insert(1, &contains("/sports/"));
+        assert_eq!(
+            vec![(1, Some(true))],
+            index.indexed(&event).collect::<Vec<_>>()
+        );
+
+        index.insert(2, &contains("/news/"));
+        index.insert(3, &contains("hockey"));
+        index.remove(1, &contains("/sports/"));
+
+        assert_eq!(
+            vec![(2, Some(false)), (3, Some(true))],
+            index.indexed(&event).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn search_each_pattern_when_the_automaton_cannot_be_built() {
+        let mut substrings = Substrings::default();
+        substrings.insert("/sports/", &PatternOperator::Contains, 1);
+        substrings.insert("/news/", &PatternOperator::NotContains, 2);
+        substrings.insert("hockey", &PatternOperator::Contains, 3);
+        substrings.automaton.set(None).unwrap();
+
+        let results: Vec<_> = substrings
+            .evaluate(Some("https://example.com/sports/hockey"))
+            .collect();
+
+        assert_eq!(
+            vec![(1, Some(true)), (2, Some(true)), (3, Some(true))],
+            results
+        );
+    }
+
+    #[test]
+    fn forget_the_removed_ranges() {
+        let attributes = define_attributes();
+        let kind = PredicateKind::Range(
+            RangeOperator::Between,
+            ComparisonValue::Integer(1),
+            ComparisonValue::Integer(3),
+        );
+        let predicate = Predicate::new(&attributes, "exchange_id", kind).unwrap();
+        let mut index = PredicateIndex::default();
+        index.insert(1, &predicate);
+        index.insert(1, &predicate);
+
+        index.remove(1, &predicate);
+
+        assert!(index.intervals.is_empty());
+    }
+
+    #[test]
+    fn index_the_ranges_on_the_first_search_after_they_changed() {
+        let mut intervals = Intervals::default();
+        for (node_id, (low, high)) in [(1, 5), (2, 3), (4, 8)].into_iter().enumerate() {
+            intervals.insert(Interval {
+                low: Bound::Integer(low),
+                high: Bound::Integer(high),
+                node_id,
+                negated: false,
+            });
+        }
+        assert!(intervals.highest.get().is_none());
+
+        let within: Vec<_> = intervals
+            .evaluate(&AttributeValue::Integer(4))
+            .filter_map(|(node_id, result)| result.unwrap().then_some(node_id))
+            .collect();
+
+        assert_eq!(vec![0, 2], within);
+        assert!(intervals.highest.get().is_some());
+    }
+}
@@ -0,0 +1,1207 @@
+use crate::{
+    atree::ATree,
+    config::{
+        ATreeConfig, Coercions, ConjunctionOrder, DeletePolicy, DuplicateIdPolicy, RoundingPolicy,
+        UnknownAttributePolicy,
+    },
+    error::ATreeError,
+    events::{AttributeDefinition, AttributeKind, EventError},
+    metadata::{Metadata, Subscription},
+};
+use std::{borrow::Cow, fmt::Debug, hash::Hash};
+use thiserror::Error;
+
+/// The bytes at the start of every snapshot.
+const MAGIC: &[u8; 8] = b"ATREESNP";
+
+/// The version of the snapshot format written by [`write`].
+///
+/// It must be incremented whenever the layout of the payload changes, in which case [`migrate`]
+/// has to convert the payload of the previous version so that the snapshots written by the
+/// previous crate version can still be loaded.
+pub(crate) const FORMAT_VERSION: u16 = 9;
+
+/// The errors that can happen while loading a snapshot with [`crate::ATree::from_snapshot()`]
+#[derive(Error, Debug, PartialEq)]
+pub enum SnapshotError {
+    #[error("the data is not an A-Tree snapshot")]
+    NotASnapshot,
+    #[error(
+        "snapshot format version {found} written by a-tree {written_by} is not supported (supported: 1 to {supported})"
+    )]
+    UnsupportedVersion {
+        found: u16,
+        supported: u16,
+        written_by: String,
+    },
+    #[error("the snapshot is truncated")]
+    Truncated,
+    #[error("the snapshot is corrupted: {0}")]
+    Corrupted(String),
+    #[error("subscription {id}: {message}")]
+    Expression { id: String, message: String },
+    #[error("failed with {0:?}")]
+    Event(EventError),
+}
+
+/// A subscription ID that can be written to a snapshot
+///
+/// This is implemented for the integer types and [`String`].
+pub trait SnapshotId: Sized {
+    /// Append the binary representation of the ID.
+    fn encode(&self, buffer: &mut Vec<u8>);
+
+    /// Read an ID written by [`SnapshotId::encode()`] at the start of the bytes and return it
+    /// along with the amount of bytes that were read.
+    fn decode(bytes: &[u8]) -> Option<(Self, usize)>;
+}
+
+macro_rules! integer_snapshot_id {
+    ($($integer:ty),*) => {
+        $(
+            impl SnapshotId for $integer {
+                #[inline]
+                fn encode(&self, buffer: &mut Vec<u8>) {
+                    buffer.extend_from_slice(&self.to_le_bytes());
+                }
+
+                #[inline]
+                fn decode(bytes: &[u8]) -> Option<(Self, usize)> {
+                    const SIZE: usize = std::mem::size_of::<$integer>();
+                    let bytes = bytes.get(..SIZE)?.try_into().ok()?;
+                    Some((<$integer>::from_le_bytes(bytes), SIZE))
+                }
+            }
+        )*
+    };
+}
+
+integer_snapshot_id!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl SnapshotId for usize {
+    #[inline]
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        (*self as u64).encode(buffer);
+    }
+
+    #[inline]
+    fn decode(bytes: &[u8]) -> Option<(Self, usize)> {
+        let (value, size) = u64::decode(bytes)?;
+        Some((usize::try_from(value).ok()?, size))
+    }
+}
+
+impl SnapshotId for String {
+    #[inline]
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        write_str(buffer, self);
+    }
+
+    #[inline]
+    fn decode(bytes: &[u8]) -> Option<(Self, usize)> {
+        let mut reader = Reader::new(bytes);
+        let value = reader.str().ok()?.to_owned();
+        Some((value, reader.position))
+    }
+}
+
+pub(crate) fn write<'a, T>(
+    config: &ATreeConfig,
+    definitions: &[AttributeDefinition],
+    subscriptions: impl ExactSizeIterator<Item = (&'a T, &'a Subscription)>,
+) -> Vec<u8>
+where
+    T: SnapshotId + 'a,
+{
+    let mut buffer = Vec::new();
+    buffer.extend_from_slice(MAGIC);
+    buffer.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    write_str(&mut buffer, env!("CARGO_PKG_VERSION"));
+    write_payload(&mut buffer, config, definitions, subscriptions);
+    buffer
+}
+
+/// Compute the fingerprint of the snapshot that [`write`] would produce.
+pub(crate) fn fingerprint<'a, T>(
+    config: &ATreeConfig,
+    definitions: &[AttributeDefinition],
+    subscriptions: impl ExactSizeIterator<Item = (&'a T, &'a Subscription)>,
+) -> u64
+where
+    T: SnapshotId + 'a,
+{
+    let mut payload = Vec::new();
+    write_payload(&mut payload, config, definitions, subscriptions);
+    hash_payload(&payload)
+}
+
+/// Compute the fingerprint of a snapshot without loading it.
+pub(crate) fn fingerprint_of<T: SnapshotId>(bytes: &[u8]) -> Result<u64, SnapshotError> {
+    let mut reader = Reader::new(bytes);
+    if reader.bytes(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+        return Err(SnapshotError::NotASnapshot);
+    }
+    let version = reader.u16()?;
+    let written_by = reader.str()?.to_owned();
+    let payload = migrate::<T>(version, &written_by, reader.rest())?;
+    Ok(hash_payload(&payload))
+}
+
+/// Hash the format version and the payload with the 64-bit FNV-1a function, whose result does
+/// not depend on the platform nor on the Rust version.
+///
+/// The version of the crate that wrote the snapshot is left out so that the fingerprint only
+/// changes with the content of the tree or with the layout of the payload.
+fn hash_payload(payload: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    FORMAT_VERSION
+        .to_le_bytes()
+        .iter()
+        .chain(payload)
+        .fold(OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+        })
+}
+
+/// Write the configuration, the schema and the subscriptions.
+///
+/// The subscriptions are sorted by their encoded ID so that the trees holding the same
+/// subscriptions produce the same bytes whatever the order in which they were inserted. An ID
+/// holding several expressions is written once per expression, the oldest first and only the last
+/// one with the metadata, so that they are all inserted again when the snapshot is read.
+fn write_payload<'a, T>(
+    buffer: &mut Vec<u8>,
+    config: &ATreeConfig,
+    definitions: &[AttributeDefinition],
+    subscriptions: impl ExactSizeIterator<Item = (&'a T, &'a Subscription)>,
+) where
+    T: SnapshotId + 'a,
+{
+    buffer.extend_from_slice(&config.float_scale().to_le_bytes());
+    buffer.push(rounding_tag(config.rounding()));
+    buffer.push(unknown_attributes_tag(config.unknown_attributes()));
+    buffer.push(duplicate_ids_tag(config.duplicate_ids()));
+    buffer.push(conjunction_order_tag(config.conjunction_order()));
+    buffer.push(coercions_tag(config.coercions()));
+    buffer.push(deletes_tag(config.deletes()));
+    write_length(buffer, config.parse_cache_capacity());
+
+    write_length(buffer, definitions.len());
+    for definition in definitions {
+        write_str(buffer, definition.name());
+        buffer.push(kind_tag(definition.kind()));
+        match definition.values() {
+            Some(values) => {
+                buffer.push(1);
+                write_length(buffer, values.len());
+                for value in values {
+                    write_str(buffer, value);
+                }
+            }
+            None => buffer.push(0),
+        }
+        write_optional_u64(buffer, definition.epsilon().map(f64::to_bits));
+        buffer.push(u8::from(definition.is_high_cardinality()));
+    }
+
+    let mut subscriptions: Vec<_> = subscriptions
+        .map(|(id, subscription)| {
+            let mut encoded = Vec::new();
+            id.encode(&mut encoded);
+            (encoded, subscription)
+        })
+        .collect();
+    subscriptions.sort_unstable_by(|(left, _), (right, _)| left.cmp(right));
+    write_length(
+        buffer,
+        subscriptions
+            .iter()
+            .map(|(_, subscription)| subscription.shared.len() + 1)
+            .sum(),
+    );
+    let no_metadata = Metadata::new();
+    for (id, subscription) in subscriptions {
+        let last = subscription.shared.len();
+        for (index, expression) in subscription.expressions().enumerate() {
+            buffer.extend_from_slice(&id);
+            write_str(buffer, expression);
+            write_metadata(
+                buffer,
+                if index == last {
+                    &subscription.metadata
+                } else {
+                    &no_metadata
+                },
+            );
+        }
+    }
+}
+
+fn write_metadata(buffer: &mut Vec<u8>, metadata: &Metadata) {
+    write_length(buffer, metadata.tags().len());
+    for tag in metadata.tags() {
+        write_str(buffer, tag);
+    }
+    write_optional_u64(buffer, metadata.expiry());
+    write_optional_str(buffer, metadata.owner());
+    write_optional_str(buffer, metadata.author());
+    write_optional_u64(buffer, metadata.created_at());
+}
+
+pub(crate) fn read<T>(bytes: &[u8]) -> Result<ATree<T>, SnapshotError>
+where
+    T: Eq + Hash + Clone + Debug + SnapshotId,
+{
+    let mut reader = Reader::new(bytes);
+    if reader.bytes(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+        return Err(SnapshotError::NotASnapshot);
+    }
+    let version = reader.u16()?;
+    let written_by = reader.str()?.to_owned();
+    let payload = migrate::<T>(version, &written_by, reader.rest())?;
+
+    let mut reader = Reader::new(&payload);
+    let config = ATreeConfig::new()
+        .with_float_scale(reader.u32()?)
+        .with_rounding(rounding(reader.u8()?)?)
+        .with_unknown_attributes(unknown_attributes(reader.u8()?)?)
+        .with_duplicate_ids(duplicate_ids(reader.u8()?)?)
+        .with_conjunction_order(conjunction_order(reader.u8()?)?)
+        .with_coercions(coercions(reader.u8()?)?)
+        .with_deletes(deletes(reader.u8()?)?)
+        .with_parse_cache(reader.length()?);
+
+    let definitions = (0..reader.length()?)
+        .map(|_| {
+            let name = reader.str()?;
+            let tag = reader.u8()?;
+            let values = match reader.u8()? {
+                0 => None,
+                _ => Some(
+                    (0..reader.length()?)
+                        .map(|_| reader.str())
+                        .collect::<Result<Vec<_>, SnapshotError>>()?,
+                ),
+            };
+            let epsilon = reader.optional_u64()?.map(f64::from_bits);
+            let definition = definition(name, tag, values.as_deref(), epsilon)?;
+            Ok(match reader.u8()? {
+                0 => definition,
+                _ => definition.high_cardinality(),
+            })
+        })
+        .collect::<Result<Vec<_>, SnapshotError>>()?;
+    let mut atree = ATree::with_config(&definitions, config).map_err(|error| match error {
+        ATreeError::Event(error) => SnapshotError::Event(error),
+        error => SnapshotError::Corrupted(error.to_string()),
+    })?;
+
+    for _ in 0..reader.length()? {
+        let id = reader.id::<T>()?;
+        let expression = reader.str()?;
+        let mut metadata = Metadata::new();
+        for _ in 0..reader.length()? {
+            metadata = metadata.with_tag(reader.str()?);
+        }
+        if let Some(expiry) = reader.optional_u64()? {
+            metadata = metadata.with_expiry(expiry);
+        }
+        if let Some(owner) = reader.optional_str()? {
+            metadata = metadata.with_owner(owner);
+        }
+        if let Some(author) = reader.optional_str()? {
+            metadata = metadata.with_author(author);
+        }
+        if let Some(created_at) = reader.optional_u64()? {
+            metadata = metadata.with_created_at(created_at);
+        }
+        atree
+            .insert_with_metadata(&id, expression, metadata)
+            .map_err(|error| SnapshotError::Expression {
+                id: format!("{id:?}"),
+                message: error.to_string(),
+            })?;
+    }
+
+    if !reader.rest().is_empty() {
+        return Err(SnapshotError::Corrupted(
+            "unexpected bytes after the subscriptions".to_owned(),
+        ));
+    }
+    Ok(atree)
+}
+
+/// Convert the payload of a snapshot to the layout of the current [`FORMAT_VERSION`].
+///
+/// Each former version is converted to the next one until the current version is reached.
+fn migrate<'a, T: SnapshotId>(
+    version: u16,
+    written_by: &str,
+    payload: &'a [u8],
+) -> Result<Cow<'a, [u8]>, SnapshotError> {
+    if version == 0 || version > FORMAT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion {
+            found: version,
+            supported: FORMAT_VERSION,
+            written_by: written_by.to_owned(),
+        });
+    }
+
+    let mut payload = Cow::Borrowed(payload);
+    if version < 2 {
+        payload = Cow::Owned(migrate_v1::<T>(&payload)?);
+    }
+    if version < 3 {
+        payload = Cow::Owned(migrate_v2(&payload)?);
+    }
+    if version < 4 {
+        payload = Cow::Owned(migrate_v3(&payload)?);
+    }
+    if version < 5 {
+        payload = Cow::Owned(migrate_v4(&payload)?);
+    }
+    if version < 6 {
+        payload = Cow::Owned(migrate_v5(&payload)?);
+    }
+    if version < 7 {
+        payload = Cow::Owned(migrate_v6(&payload)?);
+    }
+    if version < 8 {
+        payload = Cow::Owned(migrate_v7(&payload)?);
+    }
+    if version < 9 {
+        payload = Cow::Owned(migrate_v8(&payload)?);
+    }
+    Ok(payload)
+}
+
+/// Add the owner, the author and the creation time introduced by the version 2 to the
+/// subscriptions of a version 1 payload.
+fn migrate_v1<T: SnapshotId>(payload: &[u8]) -> Result<Vec<u8>, SnapshotError> {
+    let mut reader = Reader::new(payload);
+    // The configuration and the attributes did not change.
+    reader.bytes(6)?;
+    for _ in 0..reader.length()? {
+        reader.str()?;
+        reader.u8()?;
+    }
+    let mut migrated = payload[..reader.position].to_vec();
+
+    let subscriptions = reader.length()?;
+    write_length(&mut migrated, subscriptions);
+    for _ in 0..subscriptions {
+        let start = reader.position;
+        reader.id::<T>()?;
+        reader.str()?;
+        for _ in 0..reader.length()? {
+            reader.str()?;
+        }
+        reader.optional_u64()?;
+        migrated.extend_from_slice(&payload[start..reader.position]);
+        migrated.extend_from_slice(&[0, 0, 0]);
+    }
+    migrated.extend_from_slice(reader.rest());
+    Ok(migrated)
+}
+
+/// Add the duplicate ID policy introduced by the version 3 to the configuration of a version 2
+/// payload.
+fn migrate_v2(payload: &[u8]) -> Result<Vec<u8>, SnapshotError> {
+    let mut reader = Reader::new(payload);
+    let config = reader.bytes(6)?;
+    let mut migrated = Vec::with_capacity(payload.len() + 1);
+    migrated.extend_from_slice(config);
+    migrated.push(duplicate_ids_tag(DuplicateIdPolicy::default()));
+    migrated.extend_from_slice(reader.rest());
+    Ok(migrated)
+}
+
+/// Add the conjunction order introduced by the version 4 to the configuration of a version 3
+/// payload.
+fn migrate_v3(payload: &[u8]) -> Result<Vec<u8>, SnapshotError> {
+    let mut reader = Reader::new(payload);
+    let config = reader.bytes(7)?;
+    let mut migrated = Vec::with_capacity(payload.len() + 1);
+    migrated.extend_from_slice(config);
+    migrated.push(conjunction_order_tag(ConjunctionOrder::default()));
+    migrated.extend_from_slice(reader.rest());
+    Ok(migrated)
+}
+
+/// Add the dictionaries introduced by the version 5, none of them, to the attributes of a
+/// version 4 payload.
+fn migrate_v4(payload: &[u8]) -> Result<Vec<u8>, SnapshotError> {
+    let mut reader = Reader::new(payload);
+    let config = reader.bytes(8)?;
+    let mut migrated = Vec::with_capacity(payload.len());
+    migrated.extend_from_slice(config);
+    let attributes = reader.length()?;
+    write_length(&mut migrated, attributes);
+    for _ in 0..attributes {
+        let start = reader.position;
+        reader.str()?;
+        reader.u8()?;
+        migrated.extend_from_slice(&payload[start..reader.position]);
+        migrated.push(0);
+    }
+    migrated.extend_from_slice(reader.rest());
+    Ok(migrated)
+}
+
+/// Add the float epsilons introduced by the version 6, none of them, to the attributes of a
+/// version 5 payload.
+fn migrate_v5(payload: &[u8]) -> Result<Vec<u8>, SnapshotError> {
+    let mut reader = Reader::new(payload);
+    let config = reader.bytes(8)?;
+    let mut migrated = Vec::with_capacity(payload.len());
+    migrated.extend_from_slice(config);
+    let attributes = reader.length()?;
+    write_length(&mut migrated, attributes);
+    for _ in 0..attributes {
+        let start = reader.position;
+        reader.str()?;
+        reader.u8()?;
+        if reader.u8()? != 0 {
+            for _ in 0..reader.length()? {
+                reader.str()?;
+            }
+        }
+        migrated.extend_from_slice(&payload[start..reader.position]);
+        migrated.push(0);
+    }
+    migrated.extend_from_slice(reader.rest());
+    Ok(migrated)
+}
+
+/// Add the coercions introduced by the version 7, none of them, to the configuration of a
+/// version 6 payload.
+fn migrate_v6(payload: &[u8]) -> Result<Vec<u8>, SnapshotError> {
+    let mut reader = Reader::new(payload);
+    let config = reader.bytes(8)?;
+    let mut migrated = Vec::with_capacity(payload.len() + 1);
+    migrated.extend_from_slice(config);
+    migrated.push(coercions_tag(Coercions::NONE));
+    migrated.extend_from_slice(reader.rest());
+    Ok(migrated)
+}
+
+/// Add the cardinality hints introduced by the version 8, none of them, to the attributes of a
+/// version 7 payload.
+fn migrate_v7(payload: &[u8]) -> Result<Vec<u8>, SnapshotError> {
+    let mut reader = Reader::new(payload);
+    let config = reader.bytes(9)?;
+    let mut migrated = Vec::with_capacity(payload.len());
+    migrated.extend_from_slice(config);
+    let attributes = reader.length()?;
+    write_length(&mut migrated, attributes);
+    for _ in 0..attributes {
+        let start = reader.position;
+        reader.str()?;
+        reader.u8()?;
+        if reader.u8()? != 0 {
+            for _ in 0..reader.length()? {
+                reader.str()?;
+            }
+        }
+        reader.optional_u64()?;
+        migrated.extend_from_slice(&payload[start..reader.position]);
+        migrated.push(0);
+    }
+    migrated.extend_from_slice(reader.rest());
+    Ok(migrated)
+}
+
+/// Add the delete policy and the capacity of the parse cache introduced by the version 9,
+/// `DeletePolicy::Immediate` and no cache, to the configuration of a version 8 payload.
+fn migrate_v8(payload: &[u8]) -> Result<Vec<u8>, SnapshotError> {
+    let mut reader = Reader::new(payload);
+    let config = reader.bytes(9)?;
+    let mut migrated = Vec::with_capacity(payload.len() + 9);
+    migrated.extend_from_slice(config);
+    migrated.push(deletes_tag(DeletePolicy::Immediate));
+    write_length(&mut migrated, 0);
+    migrated.extend_from_slice(reader.rest());
+    Ok(migrated)
+}
+
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    pub(crate) fn bytes(&mut self, size: usize) -> Result<&'a [u8], SnapshotError> {
+        let end = self
+            .position
+            .checked_add(size)
+            .ok_or(SnapshotError::Truncated)?;
+        let bytes = self
+            .bytes
+            .get(self.position..end)
+            .ok_or(SnapshotError::Truncated)?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SnapshotError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, SnapshotError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, SnapshotError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn length(&mut self) -> Result<usize, SnapshotError> {
+        usize::try_from(self.u64()?)
+            .map_err(|_| SnapshotError::Corrupted("length overflow".to_owned()))
+    }
+
+    pub(crate) fn str(&mut self) -> Result<&'a str, SnapshotError> {
+        let length = self.length()?;
+        std::str::from_utf8(self.bytes(length)?)
+            .map_err(|error| SnapshotError::Corrupted(error.to_string()))
+    }
+
+    fn optional_u64(&mut self) -> Result<Option<u64>, SnapshotError> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => Ok(Some(self.u64()?)),
+        }
+    }
+
+    fn optional_str(&mut self) -> Result<Option<&'a str>, SnapshotError> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => Ok(Some(self.str()?)),
+        }
+    }
+
+    fn id<T: SnapshotId>(&mut self) -> Result<T, SnapshotError> {
+        let (id, size) = T::decode(self.rest()).ok_or(SnapshotError::Truncated)?;
+        self.position += size;
+        Ok(id)
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.bytes[self.position..]
+    }
+}
+
+#[inline]
+pub(crate) fn write_length(buffer: &mut Vec<u8>, length: usize) {
+    buffer.extend_from_slice(&(length as u64).to_le_bytes());
+}
+
+#[inline]
+pub(crate) fn write_str(buffer: &mut Vec<u8>, value: &str) {
+    write_length(buffer, value.len());
+    buffer.extend_from_slice(value.as_bytes());
+}
+
+#[inline]
+fn write_optional_u64(buffer: &mut Vec<u8>, value: Option<u64>) {
+    match value {
+        Some(value) => {
+            buffer.push(1);
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+        None => buffer.push(0),
+    }
+}
+
+#[inline]
+fn write_optional_str(buffer: &mut Vec<u8>, value: Option<&str>) {
+    match value {
+        Some(value) => {
+            buffer.push(1);
+            write_str(buffer, value);
+        }
+        None => buffer.push(0),
+    }
+}
+
+const fn kind_tag(kind: &AttributeKind) -> u8 {
+    match kind {
+        AttributeKind::Boolean => 0,
+        AttributeKind::Integer => 1,
+        AttributeKind::Float => 2,
+        AttributeKind::String => 3,
+        AttributeKind::IntegerList => 4,
+        AttributeKind::StringList => 5,
+    }
+}
+
+fn definition(
+    name: &str,
+    tag: u8,
+    values: Option<&[&str]>,
+    epsilon: Option<f64>,
+) -> Result<AttributeDefinition, SnapshotError> {
+    match (tag, epsilon) {
+        (2, Some(epsilon)) => return Ok(AttributeDefinition::float_with_epsilon(name, epsilon)),
+        (_, Some(_)) => {
+            return Err(SnapshotError::Corrupted(format!(
+                "attribute {name:?} has an epsilon but is not a float"
+            )))
+        }
+        (_, None) => {}
+    }
+    match (tag, values) {
+        (3, Some(values)) => Ok(AttributeDefinition::string_enum(name, values)),
+        (_, Some(_)) => Err(SnapshotError::Corrupted(format!(
+            "attribute {name:?} has a dictionary but is not a string"
+        ))),
+        (0, None) => Ok(AttributeDefinition::boolean(name)),
+        (1, None) => Ok(AttributeDefinition::integer(name)),
+        (2, None) => Ok(AttributeDefinition::float(name)),
+        (3, None) => Ok(AttributeDefinition::string(name)),
+        (4, None) => Ok(AttributeDefinition::integer_list(name)),
+        (5, None) => Ok(AttributeDefinition::string_list(name)),
+        _ => Err(invalid_tag("attribute type", tag)),
+    }
+}
+
+const fn rounding_tag(rounding: RoundingPolicy) -> u8 {
+    match rounding {
+        RoundingPolicy::HalfEven => 0,
+        RoundingPolicy::HalfAwayFromZero => 1,
+        RoundingPolicy::TowardZero => 2,
+        RoundingPolicy::AwayFromZero => 3,
+        RoundingPolicy::Floor => 4,
+        RoundingPolicy::Ceiling => 5,
+    }
+}
+
+fn rounding(tag: u8) -> Result<RoundingPolicy, SnapshotError> {
+    match tag {
+        0 => Ok(RoundingPolicy::HalfEven),
+        1 => Ok(RoundingPolicy::HalfAwayFromZero),
+        2 => Ok(RoundingPolicy::TowardZero),
+        3 => Ok(RoundingPolicy::AwayFromZero),
+        4 => Ok(RoundingPolicy::Floor),
+        5 => Ok(RoundingPolicy::Ceiling),
+        _ => Err(invalid_tag("rounding policy", tag)),
+    }
+}
+
+const fn unknown_attributes_tag(policy: UnknownAttributePolicy) -> u8 {
+    match policy {
+        UnknownAttributePolicy::Error => 0,
+        UnknownAttributePolicy::Ignore => 1,
+        UnknownAttributePolicy::Warn => 2,
+    }
+}
+
+fn unknown_attributes(tag: u8) -> Result<UnknownAttributePolicy, SnapshotError> {
+    match tag {
+        0 => Ok(UnknownAttributePolicy::Error),
+        1 => Ok(UnknownAttributePolicy::Ignore),
+        2 => Ok(UnknownAttributePolicy::Warn),
+        _ => Err(invalid_tag("unknown attribute policy", tag)),
+    }
+}
+
+const fn duplicate_ids_tag(policy: DuplicateIdPolicy) -> u8 {
+    match policy {
+        DuplicateIdPolicy::Allow => 0,
+        DuplicateIdPolicy::Replace => 1,
+        DuplicateIdPolicy::Reject => 2,
+    }
+}
+
+fn duplicate_ids(tag: u8) -> Result<DuplicateIdPolicy, SnapshotError> {
+    match tag {
+        0 => Ok(DuplicateIdPolicy::Allow),
+        1 => Ok(DuplicateIdPolicy::Replace),
+        2 => Ok(DuplicateIdPolicy::Reject),
+        _ => Err(invalid_tag("duplicate ID policy", tag)),
+    }
+}
+
+const fn conjunction_order_tag(order: ConjunctionOrder) -> u8 {
+    match order {
+        ConjunctionOrder::Cost => 0,
+        ConjunctionOrder::LeftToRight => 1,
+    }
+}
+
+fn conjunction_order(tag: u8) -> Result<ConjunctionOrder, SnapshotError> {
+    match tag {
+        0 => Ok(ConjunctionOrder::Cost),
+        1 => Ok(ConjunctionOrder::LeftToRight),
+        _ => Err(invalid_tag("conjunction order", tag)),
+    }
+}
+
+const fn coercions_tag(coercions: Coercions) -> u8 {
+    coercions.integers_to_floats() as u8 | (coercions.strings_to_numbers() as u8) << 1
+}
+
+const fn deletes_tag(policy: DeletePolicy) -> u8 {
+    match policy {
+        DeletePolicy::Immediate => 0,
+        DeletePolicy::Tombstone => 1,
+    }
+}
+
+fn deletes(tag: u8) -> Result<DeletePolicy, SnapshotError> {
+    match tag {
+        0 => Ok(DeletePolicy::Immediate),
+        1 => Ok(DeletePolicy::Tombstone),
+        _ => Err(invalid_tag("delete policy", tag)),
+    }
+}
+
+fn coercions(tag: u8) -> Result<Coercions, SnapshotError> {
+    if tag > 0b11 {
+        return Err(invalid_tag("coercions", tag));
+    }
+    let mut coercions = Coercions::NONE;
+    if tag & 0b01 != 0 {
+        coercions = coercions.with_integers_to_floats();
+    }
+    if tag & 0b10 != 0 {
+        coercions = coercions.with_strings_to_numbers();
+    }
+    Ok(coercions)
+}
+
+#[inline]
+fn invalid_tag(name: &str, tag: u8) -> SnapshotError {
+    SnapshotError::Corrupted(format!("invalid {name} {tag}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_round_trip_a_tree() {
+        let config = ATreeConfig::new()
+            .with_float_scale(2)
+            .with_rounding(RoundingPolicy::Floor)
+            .with_unknown_attributes(UnknownAttributePolicy::Ignore)
+            .with_duplicate_ids(DuplicateIdPolicy::Reject)
+            .with_conjunction_order(ConjunctionOrder::LeftToRight)
+            .with_coercions(Coercions::NONE.with_strings_to_numbers())
+            .with_deletes(DeletePolicy::Tombstone)
+            .with_parse_cache(64);
+        let mut atree = ATree::with_config(&an_attribute_list(), config).unwrap();
+        atree
+            .insert_with_metadata(
+                &"rule-1".to_owned(),
+                r#"private and deals one of ["deal-1"]"#,
+                Metadata::new()
+                    .with_tag("campaign-1")
+                    .with_expiry(10)
+                    .with_owner("ads")
+                    .with_author("bob")
+                    .with_created_at(5),
+            )
+            .unwrap();
+        atree
+            .insert(&"rule-2".to_owned(), "bidfloor > 1.5")
+            .unwrap();
+
+        let copy = ATree::<String>::from_snapshot(&atree.to_snapshot()).unwrap();
+
+        assert_eq!(atree.config(), copy.config());
+        for id in ["rule-1".to_owned(), "rule-2".to_owned()] {
+            assert_eq!(atree.expression(&id), copy.expression(&id));
+            assert_eq!(atree.metadata(&id), copy.metadata(&id));
+        }
+        let mut builder = copy.make_event();
+        builder.with_f64("bidfloor", 1.519).unwrap();
+        let event = builder.build().unwrap();
+        assert_eq!(vec!["rule-2"], copy.search(&event).unwrap().matches());
+    }
+
+    #[test]
+    fn can_round_trip_every_expression_of_a_shared_id() {
+        let definitions = [AttributeDefinition::integer("exchange_id")];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree.insert(&1u64, "exchange_id = 1").unwrap();
+        atree
+            .insert_with_metadata(&1u64, "exchange_id = 2", Metadata::new().with_owner("ads"))
+            .unwrap();
+
+        let copy = ATree::<u64>::from_snapshot(&atree.to_snapshot()).unwrap();
+
+        for exchange_id in [1, 2] {
+            let mut builder = copy.make_event();
+            builder.with_integer("exchange_id", exchange_id).unwrap();
+            let event = builder.build().unwrap();
+            assert_eq!(vec![&1], copy.search(&event).unwrap().matches());
+        }
+        assert_eq!(Some("exchange_id = 2"), copy.expression(&1));
+        assert_eq!(atree.metadata(&1), copy.metadata(&1));
+        assert_eq!(atree.to_snapshot(), copy.to_snapshot());
+    }
+
+    #[test]
+    fn can_migrate_a_version_1_snapshot() {
+        let mut snapshot = MAGIC.to_vec();
+        snapshot.extend_from_slice(&1u16.to_le_bytes());
+        write_str(&mut snapshot, "0.5.1");
+        snapshot.extend_from_slice(&6u32.to_le_bytes());
+        snapshot.extend_from_slice(&[0, 0]);
+        write_length(&mut snapshot, 1);
+        write_str(&mut snapshot, "private");
+        snapshot.push(0);
+        write_length(&mut snapshot, 2);
+        for (id, expiry) in [(1u64, Some(10)), (2, None)] {
+            id.encode(&mut snapshot);
+            write_str(&mut snapshot, "private");
+            write_length(&mut snapshot, 1);
+            write_str(&mut snapshot, "campaign-1");
+            write_optional_u64(&mut snapshot, expiry);
+        }
+
+        let atree = ATree::<u64>::from_snapshot(&snapshot).unwrap();
+
+        assert_eq!(
+            Some(&Metadata::new().with_tag("campaign-1").with_expiry(10)),
+            atree.metadata(&1)
+        );
+        assert_eq!(
+            Some(&Metadata::new().with_tag("campaign-1")),
+            atree.metadata(&2)
+        );
+    }
+
+    #[test]
+    fn can_migrate_a_version_2_snapshot() {
+        let mut snapshot = MAGIC.to_vec();
+        snapshot.extend_from_slice(&2u16.to_le_bytes());
+        write_str(&mut snapshot, "0.6.0");
+        snapshot.extend_from_slice(&6u32.to_le_bytes());
+        snapshot.extend_from_slice(&[0, 1]);
+        write_length(&mut snapshot, 1);
+        write_str(&mut snapshot, "private");
+        snapshot.push(0);
+        write_length(&mut snapshot, 1);
+        1u64.encode(&mut snapshot);
+        write_str(&mut snapshot, "private");
+        write_length(&mut snapshot, 0);
+        snapshot.extend_from_slice(&[0, 0, 0, 0]);
+
+        let atree = ATree::<u64>::from_snapshot(&snapshot).unwrap();
+
+        assert_eq!(DuplicateIdPolicy::Allow, atree.config().duplicate_ids());
+        assert_eq!(
+            UnknownAttributePolicy::Ignore,
+            atree.config().unknown_attributes()
+        );
+        assert_eq!(Some("private"), atree.expression(&1));
+    }
+
+    #[test]
+    fn can_migrate_a_version_3_snapshot() {
+        let mut snapshot = MAGIC.to_vec();
+        snapshot.extend_from_slice(&3u16.to_le_bytes());
+        write_str(&mut snapshot, "0.6.0");
+        snapshot.extend_from_slice(&6u32.to_le_bytes());
+        snapshot.extend_from_slice(&[0, 0, 2]);
+        write_length(&mut snapshot, 1);
+        write_str(&mut snapshot, "private");
+        snapshot.push(0);
+        write_length(&mut snapshot, 1);
+        1u64.encode(&mut snapshot);
+        write_str(&mut snapshot, "private");
+        write_length(&mut snapshot, 0);
+        snapshot.extend_from_slice(&[0, 0, 0, 0]);
+
+        let atree = ATree::<u64>::from_snapshot(&snapshot).unwrap();
+
+        assert_eq!(ConjunctionOrder::Cost, atree.config().conjunction_order());
+        assert_eq!(DuplicateIdPolicy::Reject, atree.config().duplicate_ids());
+        assert_eq!(Some("private"), atree.expression(&1));
+    }
+
+    #[test]
+    fn can_migrate_a_version_4_snapshot() {
+        let mut snapshot = MAGIC.to_vec();
+        snapshot.extend_from_slice(&4u16.to_le_bytes());
+        write_str(&mut snapshot, "0.7.0");
+        snapshot.extend_from_slice(&6u32.to_le_bytes());
+        snapshot.extend_from_slice(&[0, 0, 0, 1]);
+        write_length(&mut snapshot, 1);
+        write_str(&mut snapshot, "country");
+        snapshot.push(3);
+        write_length(&mut snapshot, 1);
+        1u64.encode(&mut snapshot);
+        write_str(&mut snapshot, r#"country = "CA""#);
+        write_length(&mut snapshot, 0);
+        snapshot.extend_from_slice(&[0, 0, 0, 0]);
+
+        let atree = ATree::<u64>::from_snapshot(&snapshot).unwrap();
+
+        assert_eq!(None, atree.attributes()[0].values());
+        assert_eq!(
+            ConjunctionOrder::LeftToRight,
+            atree.config().conjunction_order()
+        );
+        assert_eq!(Some(r#"country = "CA""#), atree.expression(&1));
+    }
+
+    #[test]
+    fn can_migrate_a_version_5_snapshot() {
+        let mut snapshot = MAGIC.to_vec();
+        snapshot.extend_from_slice(&5u16.to_le_bytes());
+        write_str(&mut snapshot, "0.8.0");
+        snapshot.extend_from_slice(&6u32.to_le_bytes());
+        snapshot.extend_from_slice(&[0, 0, 0, 0]);
+        write_length(&mut snapshot, 2);
+        write_str(&mut snapshot, "country");
+        snapshot.push(3);
+        snapshot.push(1);
+        write_length(&mut snapshot, 1);
+        write_str(&mut snapshot, "CA");
+        write_str(&mut snapshot, "price");
+        snapshot.push(2);
+        snapshot.push(0);
+        write_length(&mut snapshot, 1);
+        1u64.encode(&mut snapshot);
+        write_str(&mut snapshot, r#"country = "CA" and price = 2.99"#);
+        write_length(&mut snapshot, 0);
+        snapshot.extend_from_slice(&[0, 0, 0, 0]);
+
+        let atree = ATree::<u64>::from_snapshot(&snapshot).unwrap();
+
+        let values = ["CA".to_owned()];
+        assert_eq!(Some(values.as_slice()), atree.attributes()[0].values());
+        assert_eq!(None, atree.attributes()[1].epsilon());
+        assert_eq!(
+            Some(r#"country = "CA" and price = 2.99"#),
+            atree.expression(&1)
+        );
+    }
+
+    #[test]
+    fn can_migrate_a_version_6_snapshot() {
+        let mut snapshot = MAGIC.to_vec();
+        snapshot.extend_from_slice(&6u16.to_le_bytes());
+        write_str(&mut snapshot, "0.9.0");
+        snapshot.extend_from_slice(&6u32.to_le_bytes());
+        snapshot.extend_from_slice(&[0, 1, 0, 0]);
+        write_length(&mut snapshot, 1);
+        write_str(&mut snapshot, "price");
+        snapshot.extend_from_slice(&[2, 0, 0]);
+        write_length(&mut snapshot, 1);
+        1u64.encode(&mut snapshot);
+        write_str(&mut snapshot, "price = 2.99");
+        write_length(&mut snapshot, 0);
+        snapshot.extend_from_slice(&[0, 0, 0, 0]);
+
+        let atree = ATree::<u64>::from_snapshot(&snapshot).unwrap();
+
+        assert_eq!(Coercions::NONE, atree.config().coercions());
+        assert_eq!(
+            UnknownAttributePolicy::Ignore,
+            atree.config().unknown_attributes()
+        );
+        assert_eq!(Some("price = 2.99"), atree.expression(&1));
+    }
+
+    #[test]
+    fn can_migrate_a_version_7_snapshot() {
+        let mut snapshot = MAGIC.to_vec();
+        snapshot.extend_from_slice(&7u16.to_le_bytes());
+        write_str(&mut snapshot, "0.9.1");
+        snapshot.extend_from_slice(&6u32.to_le_bytes());
+        snapshot.extend_from_slice(&[0, 1, 0, 0, 0]);
+        write_length(&mut snapshot, 1);
+        write_str(&mut snapshot, "user_id");
+        snapshot.extend_from_slice(&[1, 0, 0]);
+        write_length(&mut snapshot, 1);
+        1u64.encode(&mut snapshot);
+        write_str(&mut snapshot, "user_id = 42");
+        write_length(&mut snapshot, 0);
+        snapshot.extend_from_slice(&[0, 0, 0, 0]);
+
+        let atree = ATree::<u64>::from_snapshot(&snapshot).unwrap();
+
+        assert!(!atree.attributes()[0].is_high_cardinality());
+        assert_eq!(Some("user_id = 42"), atree.expression(&1));
+    }
+
+    #[test]
+    fn can_migrate_a_version_8_snapshot() {
+        let mut snapshot = MAGIC.to_vec();
+        snapshot.extend_from_slice(&8u16.to_le_bytes());
+        write_str(&mut snapshot, "0.5.1");
+        snapshot.extend_from_slice(&6u32.to_le_bytes());
+        snapshot.extend_from_slice(&[0, 1, 0, 0, 0]);
+        write_length(&mut snapshot, 1);
+        write_str(&mut snapshot, "user_id");
+        snapshot.extend_from_slice(&[1, 0, 0, 0]);
+        write_length(&mut snapshot, 1);
+        1u64.encode(&mut snapshot);
+        write_str(&mut snapshot, "user_id = 42");
+        write_length(&mut snapshot, 0);
+        snapshot.extend_from_slice(&[0, 0, 0, 0]);
+
+        let atree = ATree::<u64>::from_snapshot(&snapshot).unwrap();
+
+        assert_eq!(DeletePolicy::Immediate, atree.config().deletes());
+        assert_eq!(0, atree.config().parse_cache_capacity());
+        assert_eq!(Some("user_id = 42"), atree.expression(&1));
+    }
+
+    #[test]
+    fn keep_the_cardinality_hints_of_the_attributes() {
+        let definitions = [
+            AttributeDefinition::integer("user_id").high_cardinality(),
+            AttributeDefinition::string("country"),
+        ];
+        let mut atree = ATree::<u64>::new(&definitions).unwrap();
+        atree.insert(&1, "user_id in [1, 42]").unwrap();
+
+        let copy = ATree::<u64>::from_snapshot(&atree.to_snapshot()).unwrap();
+
+        assert!(copy.attributes()[0].is_high_cardinality());
+        assert!(!copy.attributes()[1].is_high_cardinality());
+        let mut builder = copy.make_event();
+        builder.with_integer("user_id", 42).unwrap();
+        let event = builder.build().unwrap();
+        assert_eq!(vec![&1], copy.search(&event).unwrap().matches());
+    }
+
+    #[test]
+    fn keep_the_epsilons_of_the_attributes() {
+        let definitions = [AttributeDefinition::float_with_epsilon("price", 0.01)];
+        let mut atree = ATree::<u64>::new(&definitions).unwrap();
+        atree.insert(&1, "price = 2.99").unwrap();
+
+        let copy = ATree::<u64>::from_snapshot(&atree.to_snapshot()).unwrap();
+
+        assert_eq!(Some(0.01), copy.attributes()[0].epsilon());
+        let mut builder = copy.make_event();
+        builder.with_f64("price", 2.995).unwrap();
+        let event = builder.build().unwrap();
+        assert_eq!(vec![&1], copy.search(&event).unwrap().matches());
+    }
+
+    #[test]
+    fn keep_the_dictionaries_of_the_attributes() {
+        let definitions = [AttributeDefinition::string_enum("country", &["CA", "US"])];
+        let atree = ATree::<u64>::new(&definitions).unwrap();
+
+        let copy = ATree::<u64>::from_snapshot(&atree.to_snapshot()).unwrap();
+
+        let values = ["CA".to_owned(), "US".to_owned()];
+        assert_eq!(Some(values.as_slice()), copy.attributes()[0].values());
+        let mut builder = copy.make_event();
+        assert!(builder.with_string("country", "FR").is_err());
+    }
+
+    #[test]
+    fn return_an_error_when_the_magic_is_missing() {
+        let result = ATree::<u64>::from_snapshot(b"{\"version\": 1}");
+
+        assert_eq!(Err(SnapshotError::NotASnapshot), result.map(|_| ()));
+    }
+
+    #[test]
+    fn return_an_error_with_the_writer_on_an_unsupported_version() {
+        let mut snapshot = ATree::<u64>::new(&an_attribute_list())
+            .unwrap()
+            .to_snapshot();
+        snapshot[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&99u16.to_le_bytes());
+
+        let result = ATree::<u64>::from_snapshot(&snapshot);
+
+        assert_eq!(
+            Err(SnapshotError::UnsupportedVersion {
+                found: 99,
+                supported: FORMAT_VERSION,
+                written_by: env!("CARGO_PKG_VERSION").to_owned()
+            }),
+            result.map(|_| ())
+        );
+    }
+
+    #[test]
+    fn return_an_error_on_a_truncated_snapshot() {
+        let mut atree = ATree::new(&an_attribute_list()).unwrap();
+        atree.insert(&1u64, "private").unwrap();
+        let snapshot = atree.to_snapshot();
+
+        for length in MAGIC.len()..snapshot.len() {
+            let result = ATree::<u64>::from_snapshot(&snapshot[..length]);
+
+            assert!(result.is_err(), "{length} bytes should not be loaded");
+        }
+    }
+
+    #[test]
+    fn write_the_same_snapshot_whatever_the_insertion_order() {
+        let ids: Vec<_> = (0..50u64).collect();
+        let snapshot = |ids: &[u64]| {
+            let mut atree = ATree::new(&an_attribute_list()).unwrap();
+            for id in ids {
+                let expression = format!("bidfloor > {id}.5");
+                atree.insert(id, &expression).unwrap();
+            }
+            (atree.to_snapshot(), atree.fingerprint())
+        };
+
+        let (expected, fingerprint) = snapshot(&ids);
+        let reversed: Vec<_> = ids.iter().rev().copied().collect();
+
+        assert_eq!((expected.clone(), fingerprint), snapshot(&reversed));
+        assert_eq!(
+            Ok(fingerprint),
+            ATree::<u64>::snapshot_fingerprint(&expected)
+        );
+    }
+
+    #[test]
+    fn change_the_fingerprint_with_the_content_of_the_tree() {
+        let mut atree = ATree::new(&an_attribute_list()).unwrap();
+        atree.insert(&1u64, "private").unwrap();
+        let fingerprint = atree.fingerprint();
+
+        atree.insert(&2u64, "not private").unwrap();
+
+        assert_ne!(fingerprint, atree.fingerprint());
+    }
+
+    #[test]
+    fn can_encode_the_ids() {
+        let mut buffer = Vec::new();
+        (-5i32).encode(&mut buffer);
+        "id".to_owned().encode(&mut buffer);
+
+        assert_eq!(Some((-5, 4)), i32::decode(&buffer));
+        assert_eq!(Some(("id".to_owned(), 10)), String::decode(&buffer[4..]));
+        assert_eq!(None, u64::decode(&buffer[..3]));
+    }
+
+    fn an_attribute_list() -> [AttributeDefinition; 3] {
+        [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::string_list("deals"),
+            AttributeDefinition::float("bidfloor"),
+        ]
+    }
+}
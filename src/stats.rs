@@ -0,0 +1,122 @@
+use std::{
+    collections::{HashMap, HashSet},
+    mem::size_of,
+};
+
+/// The size of an [`crate::ATree`]
+///
+/// See [`crate::ATree::stats()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TreeStats {
+    pub(crate) subscriptions: usize,
+    pub(crate) nodes: usize,
+    pub(crate) predicates: usize,
+    pub(crate) roots: usize,
+    pub(crate) max_level: usize,
+    pub(crate) strings: usize,
+    pub(crate) memory_usage: usize,
+}
+
+impl TreeStats {
+    /// The amount of subscriptions.
+    #[inline]
+    pub const fn subscriptions(&self) -> usize {
+        self.subscriptions
+    }
+
+    /// The amount of nodes, shared or not.
+    #[inline]
+    pub const fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    /// The amount of distinct predicates, i.e. of leaf nodes.
+    #[inline]
+    pub const fn predicates(&self) -> usize {
+        self.predicates
+    }
+
+    /// The amount of root nodes.
+    #[inline]
+    pub const fn roots(&self) -> usize {
+        self.roots
+    }
+
+    /// The height of the tallest expression.
+    #[inline]
+    pub const fn max_level(&self) -> usize {
+        self.max_level
+    }
+
+    /// The amount of distinct strings used by the expressions and the interned values.
+    #[inline]
+    pub const fn strings(&self) -> usize {
+        self.strings
+    }
+
+    /// An estimation of the bytes used by the tree; see [`crate::ATree::memory_usage()`].
+    #[inline]
+    pub const fn memory_usage(&self) -> usize {
+        self.memory_usage
+    }
+}
+
+/// The heap bytes reserved by a vector, without the heap bytes of its elements.
+#[inline]
+pub(crate) const fn vec_bytes<V>(values: &Vec<V>) -> usize {
+    values.capacity() * size_of::<V>()
+}
+
+/// An estimation of the heap bytes reserved by a map, without the heap bytes of its entries.
+///
+/// Every bucket of the table also has a control byte.
+#[inline]
+pub(crate) fn map_bytes<K, V, S>(map: &HashMap<K, V, S>) -> usize {
+    map.capacity() * (size_of::<(K, V)>() + 1)
+}
+
+/// An estimation of the heap bytes reserved by a set, without the heap bytes of its values.
+#[inline]
+pub(crate) fn set_bytes<V, S>(set: &HashSet<V, S>) -> usize {
+    set.capacity() * (size_of::<V>() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ATree, AttributeDefinition};
+
+    #[test]
+    fn count_the_shared_nodes_once() {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+        ];
+        let mut atree = ATree::new(&definitions).unwrap();
+        atree.insert(&1u64, "private and exchange_id = 1").unwrap();
+        atree.insert(&2u64, "private and exchange_id = 2").unwrap();
+        atree.insert(&3u64, "private").unwrap();
+
+        let stats = atree.stats();
+
+        assert_eq!(3, stats.subscriptions());
+        assert_eq!(3, stats.predicates());
+        assert_eq!(5, stats.nodes());
+        assert_eq!(2, stats.roots());
+        assert_eq!(stats.memory_usage(), atree.memory_usage());
+    }
+
+    #[test]
+    fn grow_the_memory_usage_with_the_subscriptions() {
+        let definitions = [AttributeDefinition::string_list("deals")];
+        let mut atree = ATree::new(&definitions).unwrap();
+        let empty = atree.memory_usage();
+
+        for id in 0u64..100 {
+            let expression = format!(r#"deals one of ["deal-{id}", "deal-{}"]"#, id + 1);
+            atree.insert(&id, &expression).unwrap();
+        }
+
+        assert!(atree.memory_usage() > empty);
+        assert_eq!(101, atree.stats().strings());
+    }
+}
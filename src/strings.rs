@@ -1,3 +1,4 @@
+use crate::{events::Dictionary, stats::map_bytes};
 use std::collections::HashMap;
 
 #[derive(Clone, Debug)]
@@ -25,6 +26,20 @@ impl StringTable {
         StringId(index)
     }
 
+    pub fn find(&self, value: &str) -> Option<StringId> {
+        self.by_values.get(value).cloned().map(StringId)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.by_values.len()
+    }
+
+    /// An estimation of the heap bytes used by the table.
+    pub fn heap_bytes(&self) -> usize {
+        map_bytes(&self.by_values) + self.by_values.keys().map(String::capacity).sum::<usize>()
+    }
+
     pub fn get_or_update(&mut self, value: &str) -> StringId {
         let counter = self.by_values.entry(value.to_string()).or_insert_with(|| {
             let counter = self.counter;
@@ -34,11 +49,150 @@ impl StringTable {
 
         StringId(*counter)
     }
+
+    /// Copy the strings to a [`LocalStringTable`] to read back the values of the predicates.
+    pub fn to_local(&self) -> LocalStringTable {
+        let mut values = vec![String::new(); self.counter - 1];
+        for (value, id) in &self.by_values {
+            values[id - 1].clone_from(value);
+        }
+        LocalStringTable {
+            values,
+            by_values: self.by_values.clone(),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd, Debug, Hash)]
 pub struct StringId(usize);
 
+/// Resolves the strings of the expressions to their IDs while parsing.
+pub trait Interner {
+    fn intern(&mut self, value: &str) -> StringId;
+
+    /// Whether a string returned by [`Interner::intern()`] is one of the values of the
+    /// dictionary.
+    fn is_in(&self, id: StringId, dictionary: &Dictionary) -> bool;
+}
+
+impl Interner for StringTable {
+    #[inline]
+    fn intern(&mut self, value: &str) -> StringId {
+        self.get_or_update(value)
+    }
+
+    #[inline]
+    fn is_in(&self, id: StringId, dictionary: &Dictionary) -> bool {
+        dictionary.contains_id(id)
+    }
+}
+
+/// Resolves the strings with an existing [`StringTable`] without modifying it so that the
+/// expressions can be parsed from multiple threads.
+///
+/// The strings that are not in the table resolve to the sentinel ID and are flagged as missing;
+/// an expression parsed with missing strings must be parsed again with the [`StringTable`].
+pub struct FrozenStringTable<'a> {
+    table: &'a StringTable,
+    missing: bool,
+}
+
+impl<'a> FrozenStringTable<'a> {
+    pub const fn new(table: &'a StringTable) -> Self {
+        Self {
+            table,
+            missing: false,
+        }
+    }
+
+    /// Whether a string was missing since the last call, resetting the flag.
+    #[inline]
+    pub fn take_missing(&mut self) -> bool {
+        std::mem::take(&mut self.missing)
+    }
+}
+
+impl Interner for FrozenStringTable<'_> {
+    #[inline]
+    fn intern(&mut self, value: &str) -> StringId {
+        self.table.find(value).unwrap_or_else(|| {
+            self.missing = true;
+            StringId(StringTable::SENTINEL_ID)
+        })
+    }
+
+    #[inline]
+    fn is_in(&self, id: StringId, dictionary: &Dictionary) -> bool {
+        dictionary.contains_id(id)
+    }
+}
+
+/// Interns the strings of a single expression in a table of its own so that they can be read
+/// back without modifying the strings of the tree.
+#[derive(Default)]
+pub struct LocalStringTable {
+    values: Vec<String>,
+    by_values: HashMap<String, usize>,
+}
+
+impl LocalStringTable {
+    /// Return the string of an ID returned by this table.
+    #[inline]
+    pub fn resolve(&self, id: StringId) -> &str {
+        &self.values[id.0 - 1]
+    }
+
+    /// Return the strings interned by this table in the order they were interned.
+    #[inline]
+    pub(crate) fn values(&self) -> impl Iterator<Item = &str> {
+        self.values.iter().map(String::as_str)
+    }
+}
+
+impl Interner for LocalStringTable {
+    fn intern(&mut self, value: &str) -> StringId {
+        if let Some(&id) = self.by_values.get(value) {
+            return StringId(id);
+        }
+        self.values.push(value.to_owned());
+        let id = self.values.len();
+        self.by_values.insert(value.to_owned(), id);
+        StringId(id)
+    }
+
+    /// The IDs of this table are not the ones of the tree, so the strings are compared instead.
+    fn is_in(&self, id: StringId, dictionary: &Dictionary) -> bool {
+        dictionary.contains(self.resolve(id))
+    }
+}
+
+/// A string interned by an [`crate::ATree`] via [`crate::ATree::intern()`]
+///
+/// Setting a string attribute with [`crate::EventBuilder::with_interned_string()`] skips the
+/// hashing of the string, which helps when the same few values (e.g. country codes, OS names) are
+/// used by most of the events. An interned string is only meaningful for the tree that created
+/// it.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub struct InternedString(pub(crate) StringId);
+
+impl InternedString {
+    /// Rebuild an interned string from its raw representation as returned by
+    /// [`InternedString::to_raw()`].
+    ///
+    /// A value that was not returned by the same tree is not an error but will not match any of
+    /// the strings of that tree.
+    #[inline]
+    pub const fn from_raw(raw: u64) -> Self {
+        Self(StringId(raw as usize))
+    }
+
+    /// Return the raw representation of the interned string.
+    #[inline]
+    pub const fn to_raw(self) -> u64 {
+        self.0 .0 as u64
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -73,6 +227,49 @@ mod tests {
         assert_eq!(id, table.get_or_update(A_KEY));
     }
 
+    #[test]
+    fn can_find_a_string_without_updating_the_table() {
+        let mut table = StringTable::new();
+        let id = table.get_or_update(A_KEY);
+
+        assert_eq!(Some(id), table.find(A_KEY));
+        assert_eq!(None, table.find(ANOTHER_KEY));
+        assert_eq!(1, table.len());
+    }
+
+    #[test]
+    fn flag_the_strings_missing_from_a_frozen_table() {
+        let mut table = StringTable::new();
+        let id = table.get_or_update(A_KEY);
+        let mut frozen = FrozenStringTable::new(&table);
+
+        assert_eq!(id, frozen.intern(A_KEY));
+        assert!(!frozen.take_missing());
+        assert_eq!(table.get(ANOTHER_KEY), frozen.intern(ANOTHER_KEY));
+        assert!(frozen.take_missing());
+        assert!(!frozen.take_missing());
+    }
+
+    #[test]
+    fn can_convert_an_interned_string_to_its_raw_representation() {
+        let mut table = StringTable::new();
+        let interned = InternedString(table.get_or_update(A_KEY));
+
+        assert_eq!(interned, InternedString::from_raw(interned.to_raw()));
+    }
+
+    #[test]
+    fn can_resolve_the_strings_of_the_table_from_its_local_copy() {
+        let mut table = StringTable::new();
+        let id = table.get_or_update(A_KEY);
+        let another_id = table.get_or_update(ANOTHER_KEY);
+
+        let local = table.to_local();
+
+        assert_eq!(A_KEY, local.resolve(id));
+        assert_eq!(ANOTHER_KEY, local.resolve(another_id));
+    }
+
     #[test]
     fn can_add_multiple_strings() {
         let mut table = StringTable::new();
@@ -0,0 +1,367 @@
+use crate::{
+    ast::Node,
+    error::ATreeError,
+    events::{AttributeTable, EventError},
+    expression::Literal,
+    lexer::{Lexer, Token},
+    minify::quote,
+    parser::ATreeParseError,
+    predicates::{
+        ComparisonOperator, ComparisonValue, EqualityOperator, ListLiteral, ListOperator,
+        Predicate, PredicateKind, PrimitiveLiteral, SetOperator,
+    },
+    strings::Interner,
+};
+use itertools::Itertools;
+use lalrpop_util::ParseError;
+use rust_decimal::Decimal;
+
+/// An arbitrary boolean expression whose values are left as `?` placeholders, parsed once and
+/// bound to different values for each subscription
+///
+/// The placeholders can only replace the value on the right of a comparison, an equality, a list
+/// or a set operator. The parameters are bound in the order of the placeholders in the
+/// expression. A template refers to the attributes and the strings of the [`crate::ATree`] that
+/// parsed it so it must only be used with that tree; see [`crate::ATree::template()`].
+#[derive(Clone, Debug)]
+pub struct Template {
+    expression: String,
+    ast: Node,
+    locations: Vec<usize>,
+}
+
+impl Template {
+    pub(crate) fn new(expression: &str, ast: Node) -> Result<Self, ATreeParseError<'_>> {
+        let locations: Vec<_> = Lexer::new(expression)
+            .filter_map(|token| match token {
+                Ok((start, Token::Placeholder, _)) => Some(start),
+                _ => None,
+            })
+            .collect();
+        let mut bound = vec![];
+        ast.placeholders(&mut bound);
+        // A placeholder in a branch folded with a boolean literal would never be checked.
+        if let Some(location) = locations.iter().find(|location| !bound.contains(location)) {
+            return Err(ParseError::UnrecognizedToken {
+                token: (*location, Token::Placeholder, location + 1),
+                expected: vec![],
+            });
+        }
+        Ok(Self {
+            expression: expression.to_owned(),
+            ast,
+            locations,
+        })
+    }
+
+    /// The expression of the template, with its placeholders.
+    #[inline]
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// The amount of parameters to bind.
+    #[inline]
+    pub fn parameters(&self) -> usize {
+        self.locations.len()
+    }
+
+    /// Bind the parameters to the placeholders and return the resulting expression along with its
+    /// AST, without parsing it again.
+    pub(crate) fn bind<S: Interner>(
+        &self,
+        parameters: &[Literal],
+        attributes: &AttributeTable,
+        strings: &mut S,
+    ) -> Result<(String, Node), ATreeError<'static>> {
+        if parameters.len() != self.locations.len() {
+            return Err(ATreeError::ParameterCount {
+                expected: self.locations.len(),
+                actual: parameters.len(),
+            });
+        }
+        let ast = self.ast.clone().bind(&mut |placeholder: Placeholder| {
+            let position = self
+                .locations
+                .binary_search(&placeholder.location)
+                .unwrap_or_else(|_| unreachable!("the placeholders are located while parsing"));
+            placeholder.bind(&parameters[position], position, attributes, strings)
+        })?;
+        let mut expression = String::with_capacity(self.expression.len());
+        let mut start = 0;
+        for (location, parameter) in self.locations.iter().zip(parameters) {
+            expression.push_str(&self.expression[start..*location]);
+            expression.push_str(&render(parameter));
+            start = location + 1;
+        }
+        expression.push_str(&self.expression[start..]);
+        Ok((expression, ast))
+    }
+}
+
+/// A `?` placeholder of a [`Template`] with the attribute and the operator it applies to
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placeholder {
+    attribute: String,
+    operator: PlaceholderOperator,
+    location: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlaceholderOperator {
+    Comparison(ComparisonOperator),
+    Equality(EqualityOperator),
+    List(ListOperator),
+    Set(SetOperator),
+}
+
+impl Placeholder {
+    pub fn new(
+        attributes: &AttributeTable,
+        name: &str,
+        operator: PlaceholderOperator,
+        location: usize,
+    ) -> Result<Self, EventError> {
+        attributes
+            .by_name(name)
+            .ok_or_else(|| EventError::NonExistingAttribute(name.to_owned()))?;
+        Ok(Self {
+            attribute: name.to_owned(),
+            operator,
+            location,
+        })
+    }
+
+    fn bind<S: Interner>(
+        self,
+        parameter: &Literal,
+        position: usize,
+        attributes: &AttributeTable,
+        strings: &mut S,
+    ) -> Result<Predicate, ATreeError<'static>> {
+        let invalid = || ATreeError::InvalidParameter(position);
+        let kind = match (self.operator, parameter) {
+            (PlaceholderOperator::Comparison(operator), Literal::Integer(value)) => {
+                PredicateKind::Comparison(operator, ComparisonValue::Integer(*value))
+            }
+            (PlaceholderOperator::Comparison(operator), Literal::Float(value)) => {
+                let value = decimal(*value).ok_or_else(invalid)?;
+                PredicateKind::Comparison(operator, ComparisonValue::Float(value))
+            }
+            (PlaceholderOperator::Equality(operator), Literal::Integer(value)) => {
+                PredicateKind::Equality(operator, PrimitiveLiteral::Integer(*value))
+            }
+            (PlaceholderOperator::Equality(operator), Literal::Float(value)) => {
+                let value = decimal(*value).ok_or_else(invalid)?;
+                PredicateKind::Equality(operator, PrimitiveLiteral::Float(value))
+            }
+            (PlaceholderOperator::Equality(operator), Literal::String(value)) => {
+                PredicateKind::Equality(operator, PrimitiveLiteral::String(strings.intern(value)))
+            }
+            (PlaceholderOperator::List(operator), parameter) => {
+                PredicateKind::List(operator, list(parameter, strings).ok_or_else(invalid)?)
+            }
+            (PlaceholderOperator::Set(operator), parameter) => {
+                PredicateKind::Set(operator, list(parameter, strings).ok_or_else(invalid)?)
+            }
+            _ => return Err(invalid()),
+        };
+        Predicate::new(attributes, &self.attribute, kind)
+            .and_then(|predicate| {
+                predicate.check_dictionary(attributes, &*strings, &self.attribute)
+            })
+            .map_err(ATreeError::Event)
+    }
+}
+
+impl Node {
+    /// Collect the locations of the placeholders of the expression, from left to right.
+    pub(crate) fn placeholders(&self, locations: &mut Vec<usize>) {
+        match self {
+            Self::And(left, right) | Self::Or(left, right) => {
+                left.placeholders(locations);
+                right.placeholders(locations);
+            }
+            Self::Not(operand) => operand.placeholders(locations),
+            Self::Placeholder(placeholder) => locations.push(placeholder.location),
+            Self::Value(_) | Self::Constant(_) => {}
+        }
+    }
+
+    fn bind<F>(self, bind: &mut F) -> Result<Self, ATreeError<'static>>
+    where
+        F: FnMut(Placeholder) -> Result<Predicate, ATreeError<'static>>,
+    {
+        Ok(match self {
+            Self::And(left, right) => {
+                Self::And(Box::new(left.bind(bind)?), Box::new(right.bind(bind)?))
+            }
+            Self::Or(left, right) => {
+                Self::Or(Box::new(left.bind(bind)?), Box::new(right.bind(bind)?))
+            }
+            Self::Not(operand) => Self::Not(Box::new(operand.bind(bind)?)),
+            Self::Placeholder(placeholder) => Self::Value(bind(placeholder)?),
+            node => node,
+        })
+    }
+}
+
+/// The value of a float parameter as it would be read from an expression.
+fn decimal(value: f64) -> Option<Decimal> {
+    Decimal::try_from(value).ok()
+}
+
+fn list<S: Interner>(parameter: &Literal, strings: &mut S) -> Option<ListLiteral> {
+    match parameter {
+        Literal::IntegerList(values) => Some(ListLiteral::IntegerList(
+            values.iter().copied().sorted().unique().collect(),
+        )),
+        Literal::StringList(values) => Some(ListLiteral::StringList(
+            values
+                .iter()
+                .map(|value| strings.intern(value))
+                .sorted()
+                .unique()
+                .collect(),
+        )),
+        _ => None,
+    }
+}
+
+/// Write a parameter with the syntax of the expressions, so that the bound expression parses back
+/// to the same predicates.
+fn render(parameter: &Literal) -> String {
+    match parameter {
+        Literal::Integer(value) => value.to_string(),
+        Literal::Float(value) => {
+            let mut value = decimal(*value).unwrap_or_default().to_string();
+            if !value.contains('.') {
+                value.push('.');
+            }
+            value
+        }
+        Literal::String(value) => quote(value),
+        Literal::IntegerList(values) => format!("[{}]", values.iter().join(", ")),
+        Literal::StringList(values) => {
+            format!("[{}]", values.iter().map(|value| quote(value)).join(", "))
+        }
+        Literal::IntegerRange(low, high) => format!("{low} and {high}"),
+        Literal::FloatRange(low, high) => format!(
+            "{} and {}",
+            render(&Literal::Float(*low)),
+            render(&Literal::Float(*high))
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ATree, ATreeError, AttributeDefinition, Literal};
+
+    fn an_atree() -> ATree<u64> {
+        let definitions = [
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::float("price"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::integer_list("segments"),
+        ];
+        ATree::new(&definitions).unwrap()
+    }
+
+    #[test]
+    fn insert_the_subscriptions_bound_to_a_template() {
+        let mut atree = an_atree();
+        let template = atree
+            .template("price > ? and country in ? and segments one of ?")
+            .unwrap();
+        assert_eq!(3, template.parameters());
+
+        atree
+            .insert_template(
+                &1,
+                &template,
+                &[
+                    Literal::Float(1.5),
+                    Literal::StringList(vec!["US".to_owned(), "CA".to_owned()]),
+                    Literal::IntegerList(vec![3, 1]),
+                ],
+            )
+            .unwrap();
+        atree
+            .insert_template(
+                &2,
+                &template,
+                &[
+                    Literal::Float(3.0),
+                    Literal::StringList(vec!["say \"hi\"".to_owned()]),
+                    Literal::IntegerList(vec![2]),
+                ],
+            )
+            .unwrap();
+
+        assert_eq!(
+            Some(r#"price > 1.5 and country in ["US", "CA"] and segments one of [3, 1]"#),
+            atree.expression(&1)
+        );
+        assert_eq!(
+            Some(r#"price > 3. and country in ['say "hi"'] and segments one of [2]"#),
+            atree.expression(&2)
+        );
+        let mut builder = atree.make_event();
+        builder.with_f64("price", 2.0).unwrap();
+        builder.with_string("country", "CA").unwrap();
+        builder.with_integer_list("segments", &[1, 2]).unwrap();
+        let event = builder.build().unwrap();
+        assert_eq!(vec![&1], atree.search(&event).unwrap().matches());
+    }
+
+    #[test]
+    fn share_the_nodes_with_the_same_expression_inserted_directly() {
+        let mut atree = an_atree();
+        let template = atree.template("private and price > ?").unwrap();
+        atree.insert(&1, "private and price > 2.5").unwrap();
+        let nodes = atree.stats().nodes();
+
+        atree
+            .insert_template(&2, &template, &[Literal::Float(2.5)])
+            .unwrap();
+
+        assert_eq!(nodes, atree.stats().nodes());
+    }
+
+    #[test]
+    fn return_an_error_when_the_parameters_do_not_fit_the_template() {
+        let mut atree = an_atree();
+        let template = atree.template("price > ? and country = ?").unwrap();
+
+        assert!(matches!(
+            atree.insert_template(&1, &template, &[Literal::Float(1.0)]),
+            Err(ATreeError::ParameterCount {
+                expected: 2,
+                actual: 1
+            })
+        ));
+        assert!(matches!(
+            atree.insert_template(
+                &1,
+                &template,
+                &[Literal::Float(1.0), Literal::IntegerList(vec![1])]
+            ),
+            Err(ATreeError::InvalidParameter(1))
+        ));
+        assert!(matches!(
+            atree.insert_template(&1, &template, &[Literal::Float(1.0), Literal::Integer(2)]),
+            Err(ATreeError::Event(_))
+        ));
+        assert_eq!(None, atree.expression(&1));
+    }
+
+    #[test]
+    fn reject_the_placeholders_outside_of_a_template() {
+        let mut atree = an_atree();
+
+        assert!(atree.insert(&1, "price > ?").is_err());
+        assert!(atree.template("false and price > ?").is_err());
+        assert!(atree.template("unknown > ?").is_err());
+    }
+}
@@ -0,0 +1,644 @@
+//! [`proptest`] strategies generating valid expressions and events for a schema, so that the
+//! integrations of the [`ATree`] can be property-tested against inputs it accepts.
+//!
+//! The expressions and the events draw their values from the same small domains so that the
+//! generated events match a fair share of the generated expressions. The generated expressions
+//! can also be evaluated on their own with [`RandomExpression::evaluate()`], to check the matches
+//! of a tree against a straightforward interpreter.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use a_tree::{testutil, ATree, AttributeDefinition};
+//! use proptest::prelude::*;
+//!
+//! let definitions = [
+//!     AttributeDefinition::boolean("private"),
+//!     AttributeDefinition::string_list("deals"),
+//! ];
+//!
+//! proptest!(|(expression in testutil::expression(&definitions), event in testutil::event(&definitions))| {
+//!     let mut atree = ATree::new(&definitions).unwrap();
+//!     atree.insert(&1u64, &expression).unwrap();
+//!     let event = event.build(&atree).unwrap();
+//!     atree.search(&event).unwrap();
+//! });
+//! ```
+
+use crate::{ATree, AttributeDefinition, AttributeKind, Event, EventError, PredicateOperator};
+use proptest::{
+    collection,
+    prelude::{any, prop_oneof, BoxedStrategy, Just, Strategy},
+    sample,
+};
+use rust_decimal::Decimal;
+use std::{
+    cmp::Ordering,
+    fmt::{Debug, Display, Formatter},
+    hash::Hash,
+    ops::RangeInclusive,
+};
+
+/// Strings of the expressions and the events
+const STRINGS: [&str; 6] = ["a", "b", "c", "deal-1", "deal-2", "Québec"];
+/// Integers of the expressions and the events
+const INTEGERS: RangeInclusive<i64> = -5..=5;
+/// Floats of the expressions and the events, in hundredths
+const HUNDREDTHS: RangeInclusive<i64> = -500..=500;
+/// Sizes of the lists of the expressions and the events
+const LIST_SIZES: RangeInclusive<usize> = 1..=4;
+/// Maximum depth of the `and`, `or` and `not` operators of an expression
+const DEPTH: u32 = 4;
+/// Number of attributes of the generated schemas
+const ATTRIBUTES: RangeInclusive<usize> = 1..=8;
+
+const COMPARISONS: [PredicateOperator; 6] = [
+    PredicateOperator::LessThan,
+    PredicateOperator::LessThanEqual,
+    PredicateOperator::GreaterThan,
+    PredicateOperator::GreaterThanEqual,
+    PredicateOperator::Equal,
+    PredicateOperator::NotEqual,
+];
+const EQUALITIES: [PredicateOperator; 2] = [PredicateOperator::Equal, PredicateOperator::NotEqual];
+const SETS: [PredicateOperator; 2] = [PredicateOperator::In, PredicateOperator::NotIn];
+const LISTS: [PredicateOperator; 3] = [
+    PredicateOperator::OneOf,
+    PredicateOperator::NoneOf,
+    PredicateOperator::AllOf,
+];
+const PATTERNS: [PredicateOperator; 2] =
+    [PredicateOperator::StartsWith, PredicateOperator::Contains];
+/// Prefixes and substrings of the strings of the expressions and the events
+const FRAGMENTS: [&str; 7] = ["a", "d", "deal-", "deal-1", "Qu", "al-", "bec"];
+const NULLS: [PredicateOperator; 2] = [PredicateOperator::IsNull, PredicateOperator::IsNotNull];
+const EMPTINESS: [PredicateOperator; 2] =
+    [PredicateOperator::IsEmpty, PredicateOperator::IsNotEmpty];
+const VARIABLES: [PredicateOperator; 4] = [
+    PredicateOperator::Variable,
+    PredicateOperator::NegatedVariable,
+    PredicateOperator::IsNull,
+    PredicateOperator::IsNotNull,
+];
+
+/// Generate schemas of 1 to 8 attributes of any kind, named `attribute_0`, `attribute_1`, etc.
+pub fn schema() -> BoxedStrategy<Vec<AttributeDefinition>> {
+    let kinds = vec![
+        AttributeKind::Boolean,
+        AttributeKind::Integer,
+        AttributeKind::Float,
+        AttributeKind::String,
+        AttributeKind::IntegerList,
+        AttributeKind::StringList,
+    ];
+    collection::vec(sample::select(kinds), ATTRIBUTES)
+        .prop_map(|kinds| {
+            kinds
+                .iter()
+                .enumerate()
+                .map(|(index, kind)| {
+                    let name = format!("attribute_{index}");
+                    match kind {
+                        AttributeKind::Boolean => AttributeDefinition::boolean(&name),
+                        AttributeKind::Integer => AttributeDefinition::integer(&name),
+                        AttributeKind::Float => AttributeDefinition::float(&name),
+                        AttributeKind::String => AttributeDefinition::string(&name),
+                        AttributeKind::IntegerList => AttributeDefinition::integer_list(&name),
+                        AttributeKind::StringList => AttributeDefinition::string_list(&name),
+                    }
+                })
+                .collect()
+        })
+        .boxed()
+}
+
+/// Generate expressions on the attributes of a schema, with every operator that applies to the
+/// kind of each attribute.
+///
+/// The expressions are accepted by an [`ATree`] created with the same attribute definitions. An
+/// empty schema only yields `true`.
+pub fn expression(definitions: &[AttributeDefinition]) -> BoxedStrategy<String> {
+    expression_tree(definitions)
+        .prop_map(|expression| expression.to_string())
+        .boxed()
+}
+
+/// Generate expressions like [`expression()`], as trees that can be evaluated with
+/// [`RandomExpression::evaluate()`].
+pub fn expression_tree(definitions: &[AttributeDefinition]) -> BoxedStrategy<RandomExpression> {
+    if definitions.is_empty() {
+        return Just(RandomExpression::Constant(true)).boxed();
+    }
+
+    let predicates: Vec<_> = definitions.iter().map(predicate).collect();
+    let leaf = prop_oneof![
+        19 => sample::select(predicates).prop_flat_map(|predicate| predicate),
+        1 => any::<bool>().prop_map(RandomExpression::Constant),
+    ];
+    leaf.prop_recursive(DEPTH, 32, 2, |inner| {
+        prop_oneof![
+            (inner.clone(), inner.clone())
+                .prop_map(|(left, right)| RandomExpression::And(left.into(), right.into())),
+            (inner.clone(), inner.clone())
+                .prop_map(|(left, right)| RandomExpression::Or(left.into(), right.into())),
+            inner.prop_map(|operand| RandomExpression::Not(operand.into())),
+        ]
+    })
+    .boxed()
+}
+
+/// Generate events for the attributes of a schema; each attribute is either set to a value of
+/// its kind, explicitly undefined or left out.
+pub fn event(definitions: &[AttributeDefinition]) -> BoxedStrategy<RandomEvent> {
+    let values: Vec<_> = definitions
+        .iter()
+        .map(|definition| {
+            let name = definition.name().to_owned();
+            collection::vec(value(definition.kind()), 0..=1)
+                .prop_map(move |value| value.into_iter().map(|value| (name.clone(), value)).next())
+        })
+        .collect();
+    values
+        .prop_map(|values| RandomEvent {
+            values: values.into_iter().flatten().collect(),
+        })
+        .boxed()
+}
+
+/// Expression generated by [`expression_tree()`]; its [`Display`] is the expression in the DSL
+#[derive(Clone, Debug, PartialEq)]
+pub enum RandomExpression {
+    And(Box<RandomExpression>, Box<RandomExpression>),
+    Or(Box<RandomExpression>, Box<RandomExpression>),
+    Not(Box<RandomExpression>),
+    Predicate {
+        attribute: String,
+        operator: PredicateOperator,
+        value: Option<RandomValue>,
+    },
+    Constant(bool),
+}
+
+impl RandomExpression {
+    /// Evaluate the expression against an event one predicate at a time, without the sharing and
+    /// the rewriting of the tree, as a reference for the matches of [`ATree::search()`].
+    ///
+    /// `None` stands for undefined: the predicates on an undefined attribute are undefined,
+    /// except for `is null` and `is not null`, and an `and` (resp. an `or`) is only undefined
+    /// when none of its operands is false (resp. true). An expression matches an event when it
+    /// evaluates to `Some(true)`.
+    pub fn evaluate(&self, event: &RandomEvent) -> Option<bool> {
+        match self {
+            Self::And(left, right) => match (left.evaluate(event), right.evaluate(event)) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            },
+            Self::Or(left, right) => match (left.evaluate(event), right.evaluate(event)) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            },
+            Self::Not(operand) => operand.evaluate(event).map(|result| !result),
+            Self::Predicate {
+                attribute,
+                operator,
+                value,
+            } => evaluate_predicate(*operator, value.as_ref(), event.value(attribute)),
+            Self::Constant(value) => Some(*value),
+        }
+    }
+}
+
+impl Display for RandomExpression {
+    fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Self::And(left, right) => write!(formatter, "({left} and {right})"),
+            Self::Or(left, right) => write!(formatter, "({left} or {right})"),
+            Self::Not(operand) => write!(formatter, "not ({operand})"),
+            Self::Predicate {
+                attribute,
+                operator: PredicateOperator::Variable,
+                ..
+            } => write!(formatter, "{attribute}"),
+            Self::Predicate {
+                attribute,
+                operator: PredicateOperator::NegatedVariable,
+                ..
+            } => write!(formatter, "not {attribute}"),
+            Self::Predicate {
+                attribute,
+                operator,
+                value: None,
+            } => write!(formatter, "{attribute} {operator}"),
+            Self::Predicate {
+                attribute,
+                operator,
+                value: Some(value),
+            } => write!(formatter, "{attribute} {operator} {value}"),
+            Self::Constant(value) => write!(formatter, "{value}"),
+        }
+    }
+}
+
+/// Event generated by [`event()`], built into an [`Event`] by the tree to search
+#[derive(Clone, Debug, PartialEq)]
+pub struct RandomEvent {
+    values: Vec<(String, RandomValue)>,
+}
+
+impl RandomEvent {
+    /// The values of the event by attribute name; the missing attributes are undefined.
+    pub fn values(&self) -> &[(String, RandomValue)] {
+        &self.values
+    }
+
+    /// The value of an attribute, [`RandomValue::Undefined`] when it is missing.
+    pub fn value(&self, name: &str) -> &RandomValue {
+        self.values
+            .iter()
+            .find_map(|(attribute, value)| (attribute == name).then_some(value))
+            .unwrap_or(&RandomValue::Undefined)
+    }
+
+    /// Build the event with the strings of a tree.
+    pub fn build<T>(&self, atree: &ATree<T>) -> Result<Event, EventError>
+    where
+        T: Eq + Hash + Clone + Debug,
+    {
+        let mut builder = atree.make_event();
+        for (name, value) in &self.values {
+            match value {
+                RandomValue::Boolean(value) => builder.with_boolean(name, *value),
+                RandomValue::Integer(value) => builder.with_integer(name, *value),
+                RandomValue::Float { number, scale } => builder.with_float(name, *number, *scale),
+                RandomValue::String(value) => builder.with_string(name, value),
+                RandomValue::IntegerList(values) => builder.with_integer_list(name, values),
+                RandomValue::StringList(values) => {
+                    let values: Vec<_> = values.iter().map(String::as_str).collect();
+                    builder.with_string_list(name, &values)
+                }
+                RandomValue::Undefined => builder.with_undefined(name),
+            }?;
+        }
+        builder.build()
+    }
+}
+
+/// Value of an attribute of a [`RandomEvent`] or of a predicate of a [`RandomExpression`], like
+/// [`crate::AttrValue`] but owned; its [`Display`] is the literal in the DSL
+#[derive(Clone, Debug, PartialEq)]
+pub enum RandomValue {
+    Boolean(bool),
+    Integer(i64),
+    /// A float represented as `number * 10^-scale`
+    Float {
+        number: i64,
+        scale: u32,
+    },
+    String(String),
+    IntegerList(Vec<i64>),
+    StringList(Vec<String>),
+    Undefined,
+}
+
+impl RandomValue {
+    fn decimal(&self) -> Option<Decimal> {
+        match self {
+            Self::Float { number, scale } => Decimal::try_new(*number, *scale).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl Display for RandomValue {
+    fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Self::Boolean(value) => write!(formatter, "{value}"),
+            Self::Integer(value) => write!(formatter, "{value}"),
+            Self::Float { number, scale } => {
+                // The literal needs a decimal point to be read as a float.
+                let digits = format!(
+                    "{:0>width$}",
+                    number.unsigned_abs(),
+                    width = *scale as usize + 1
+                );
+                let (integer, fraction) = digits.split_at(digits.len() - *scale as usize);
+                let sign = if *number < 0 { "-" } else { "" };
+                let fraction = if fraction.is_empty() { "0" } else { fraction };
+                write!(formatter, "{sign}{integer}.{fraction}")
+            }
+            Self::String(value) => write!(formatter, "{value:?}"),
+            Self::IntegerList(values) => {
+                let values: Vec<_> = values.iter().map(i64::to_string).collect();
+                write!(formatter, "[{}]", values.join(", "))
+            }
+            Self::StringList(values) => {
+                let values: Vec<_> = values.iter().map(|value| format!("{value:?}")).collect();
+                write!(formatter, "[{}]", values.join(", "))
+            }
+            Self::Undefined => write!(formatter, "undefined"),
+        }
+    }
+}
+
+fn evaluate_predicate(
+    operator: PredicateOperator,
+    literal: Option<&RandomValue>,
+    value: &RandomValue,
+) -> Option<bool> {
+    use PredicateOperator as Operator;
+
+    match (operator, value) {
+        (Operator::IsNull, value) => Some(*value == RandomValue::Undefined),
+        (Operator::IsNotNull, value) => Some(*value != RandomValue::Undefined),
+        (_, RandomValue::Undefined) => None,
+        (Operator::IsEmpty, RandomValue::IntegerList(values)) => Some(values.is_empty()),
+        (Operator::IsEmpty, RandomValue::StringList(values)) => Some(values.is_empty()),
+        (Operator::IsNotEmpty, RandomValue::IntegerList(values)) => Some(!values.is_empty()),
+        (Operator::IsNotEmpty, RandomValue::StringList(values)) => Some(!values.is_empty()),
+        (Operator::Variable, RandomValue::Boolean(value)) => Some(*value),
+        (Operator::NegatedVariable, RandomValue::Boolean(value)) => Some(!*value),
+        (Operator::In, value) => contains(literal?, value),
+        (Operator::NotIn, value) => contains(literal?, value).map(|result| !result),
+        (Operator::Equal, value) => compare(value, literal?).map(Ordering::is_eq),
+        (Operator::NotEqual, value) => compare(value, literal?).map(Ordering::is_ne),
+        (Operator::LessThan, value) => compare(value, literal?).map(Ordering::is_lt),
+        (Operator::LessThanEqual, value) => compare(value, literal?).map(Ordering::is_le),
+        (Operator::GreaterThan, value) => compare(value, literal?).map(Ordering::is_gt),
+        (Operator::GreaterThanEqual, value) => compare(value, literal?).map(Ordering::is_ge),
+        (Operator::OneOf, values) => one_of(values, literal?),
+        (Operator::NoneOf, values) => one_of(values, literal?).map(|result| !result),
+        (Operator::AllOf, values) => all_of(values, literal?),
+        (Operator::NotAllOf, values) => all_of(values, literal?).map(|result| !result),
+        (Operator::StartsWith, RandomValue::String(value)) => match literal? {
+            RandomValue::String(prefix) => Some(value.starts_with(prefix.as_str())),
+            _ => None,
+        },
+        (Operator::NotStartsWith, RandomValue::String(value)) => match literal? {
+            RandomValue::String(prefix) => Some(!value.starts_with(prefix.as_str())),
+            _ => None,
+        },
+        (Operator::Contains, RandomValue::String(value)) => match literal? {
+            RandomValue::String(substring) => Some(value.contains(substring.as_str())),
+            _ => None,
+        },
+        (Operator::NotContains, RandomValue::String(value)) => match literal? {
+            RandomValue::String(substring) => Some(!value.contains(substring.as_str())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn compare(value: &RandomValue, literal: &RandomValue) -> Option<Ordering> {
+    match (value, literal) {
+        (RandomValue::Integer(value), RandomValue::Integer(literal)) => Some(value.cmp(literal)),
+        (RandomValue::Float { .. }, RandomValue::Float { .. }) => {
+            Some(value.decimal()?.cmp(&literal.decimal()?))
+        }
+        (RandomValue::String(value), RandomValue::String(literal)) => Some(value.cmp(literal)),
+        _ => None,
+    }
+}
+
+fn contains(literal: &RandomValue, value: &RandomValue) -> Option<bool> {
+    match (literal, value) {
+        (RandomValue::IntegerList(literal), RandomValue::Integer(value)) => {
+            Some(literal.contains(value))
+        }
+        (RandomValue::StringList(literal), RandomValue::String(value)) => {
+            Some(literal.contains(value))
+        }
+        _ => None,
+    }
+}
+
+/// Whether any value of the event is in the list of the predicate.
+fn one_of(values: &RandomValue, literal: &RandomValue) -> Option<bool> {
+    match (values, literal) {
+        (RandomValue::IntegerList(values), RandomValue::IntegerList(literal)) => {
+            Some(values.iter().any(|value| literal.contains(value)))
+        }
+        (RandomValue::StringList(values), RandomValue::StringList(literal)) => {
+            Some(values.iter().any(|value| literal.contains(value)))
+        }
+        _ => None,
+    }
+}
+
+/// Whether every value of the event is in the list of the predicate.
+fn all_of(values: &RandomValue, literal: &RandomValue) -> Option<bool> {
+    match (values, literal) {
+        (RandomValue::IntegerList(values), RandomValue::IntegerList(literal)) => {
+            Some(values.iter().all(|value| literal.contains(value)))
+        }
+        (RandomValue::StringList(values), RandomValue::StringList(literal)) => {
+            Some(values.iter().all(|value| literal.contains(value)))
+        }
+        _ => None,
+    }
+}
+
+fn value(kind: &AttributeKind) -> BoxedStrategy<RandomValue> {
+    let value = match kind {
+        AttributeKind::Boolean => any::<bool>().prop_map(RandomValue::Boolean).boxed(),
+        AttributeKind::Integer => integer(),
+        AttributeKind::Float => float(),
+        AttributeKind::String => string(),
+        AttributeKind::IntegerList => integer_list(0..=*LIST_SIZES.end()),
+        AttributeKind::StringList => string_list(0..=*LIST_SIZES.end()),
+    };
+    prop_oneof![9 => value, 1 => Just(RandomValue::Undefined)].boxed()
+}
+
+fn predicate(definition: &AttributeDefinition) -> BoxedStrategy<RandomExpression> {
+    let name = definition.name();
+    match definition.kind() {
+        AttributeKind::Boolean => predicates(name, &VARIABLES, None),
+        AttributeKind::Integer => prop_oneof![
+            predicates(name, &COMPARISONS, Some(integer())),
+            predicates(name, &SETS, Some(integer_list(LIST_SIZES))),
+            predicates(name, &NULLS, None),
+        ]
+        .boxed(),
+        AttributeKind::Float => prop_oneof![
+            predicates(name, &COMPARISONS, Some(float())),
+            predicates(name, &NULLS, None),
+        ]
+        .boxed(),
+        AttributeKind::String => prop_oneof![
+            predicates(name, &EQUALITIES, Some(string())),
+            predicates(name, &SETS, Some(string_list(LIST_SIZES))),
+            predicates(name, &PATTERNS, Some(fragment())),
+            predicates(name, &NULLS, None),
+        ]
+        .boxed(),
+        AttributeKind::IntegerList => prop_oneof![
+            predicates(name, &LISTS, Some(integer_list(LIST_SIZES))),
+            predicates(name, &EMPTINESS, None),
+        ]
+        .boxed(),
+        AttributeKind::StringList => prop_oneof![
+            predicates(name, &LISTS, Some(string_list(LIST_SIZES))),
+            predicates(name, &EMPTINESS, None),
+        ]
+        .boxed(),
+    }
+}
+
+fn predicates(
+    name: &str,
+    operators: &'static [PredicateOperator],
+    values: Option<BoxedStrategy<RandomValue>>,
+) -> BoxedStrategy<RandomExpression> {
+    let name = name.to_owned();
+    let values = match values {
+        Some(values) => values.prop_map(Some).boxed(),
+        None => Just(None).boxed(),
+    };
+    (sample::select(operators), values)
+        .prop_map(move |(operator, value)| RandomExpression::Predicate {
+            attribute: name.clone(),
+            operator,
+            value,
+        })
+        .boxed()
+}
+
+fn integer() -> BoxedStrategy<RandomValue> {
+    INTEGERS.prop_map(RandomValue::Integer).boxed()
+}
+
+fn float() -> BoxedStrategy<RandomValue> {
+    HUNDREDTHS
+        .prop_map(|number| RandomValue::Float { number, scale: 2 })
+        .boxed()
+}
+
+fn string() -> BoxedStrategy<RandomValue> {
+    sample::select(&STRINGS[..])
+        .prop_map(|value| RandomValue::String(value.to_owned()))
+        .boxed()
+}
+
+fn fragment() -> BoxedStrategy<RandomValue> {
+    sample::select(&FRAGMENTS[..])
+        .prop_map(|value| RandomValue::String(value.to_owned()))
+        .boxed()
+}
+
+fn integer_list(sizes: RangeInclusive<usize>) -> BoxedStrategy<RandomValue> {
+    collection::vec(INTEGERS, sizes)
+        .prop_map(RandomValue::IntegerList)
+        .boxed()
+}
+
+fn string_list(sizes: RangeInclusive<usize>) -> BoxedStrategy<RandomValue> {
+    collection::vec(sample::select(&STRINGS[..]), sizes)
+        .prop_map(|values| RandomValue::StringList(values.into_iter().map(str::to_owned).collect()))
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ATreeConfig, ConjunctionOrder};
+    use proptest::prelude::*;
+
+    fn definitions() -> Vec<AttributeDefinition> {
+        vec![
+            AttributeDefinition::boolean("private"),
+            AttributeDefinition::integer("exchange_id"),
+            AttributeDefinition::float("bidfloor"),
+            AttributeDefinition::string("country"),
+            AttributeDefinition::integer_list("segment_ids"),
+            AttributeDefinition::string_list("deal_ids"),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn generates_expressions_accepted_by_the_tree(expression in expression(&definitions())) {
+            let atree = ATree::<u64>::new(&definitions()).unwrap();
+
+            prop_assert!(atree.validate_expression(&expression).is_ok(), "{expression}");
+        }
+
+        #[test]
+        fn generates_events_accepted_by_the_tree(
+            expression in expression(&definitions()),
+            event in event(&definitions())
+        ) {
+            let mut atree = ATree::new(&definitions()).unwrap();
+            atree.insert(&1u64, &expression).unwrap();
+
+            let event = event.build(&atree).unwrap();
+
+            prop_assert!(atree.search(&event).is_ok());
+        }
+    }
+
+    fn scenario(
+    ) -> impl Strategy<Value = (Vec<AttributeDefinition>, Vec<RandomExpression>, RandomEvent)> {
+        schema().prop_flat_map(|definitions| {
+            (
+                Just(definitions.clone()),
+                collection::vec(expression_tree(&definitions), 1..20),
+                event(&definitions),
+            )
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn search_matches_the_reference_evaluation(
+            (definitions, expressions, event) in scenario(),
+            order in sample::select(&[ConjunctionOrder::Cost, ConjunctionOrder::LeftToRight][..])
+        ) {
+            let config = ATreeConfig::new().with_conjunction_order(order);
+            let mut atree = ATree::with_config(&definitions, config).unwrap();
+            for (id, expression) in (0u64..).zip(&expressions) {
+                atree.insert(&id, &expression.to_string()).unwrap();
+            }
+
+            let built = event.build(&atree).unwrap();
+            let report = atree.search_with_undefined(&built).unwrap();
+            let baseline = atree.baseline_search(&built);
+
+            for (id, expression) in (0u64..).zip(&expressions) {
+                prop_assert_eq!(
+                    report.matches().contains(&&id),
+                    baseline.contains(&&id),
+                    "baseline of {} on {:?}",
+                    expression,
+                    event
+                );
+                let expected = expression.evaluate(&event);
+                prop_assert_eq!(
+                    expected == Some(true),
+                    report.matches().contains(&&id),
+                    "{} on {:?}",
+                    expression,
+                    event
+                );
+                prop_assert_eq!(
+                    expected.is_none(),
+                    report.undefined().contains(&&id),
+                    "{} on {:?}",
+                    expression,
+                    event
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn only_generates_true_for_an_empty_schema() {
+        let mut runner = proptest::test_runner::TestRunner::deterministic();
+
+        let expression = expression(&[]).new_tree(&mut runner).unwrap().current();
+
+        assert_eq!("true", expression);
+    }
+}